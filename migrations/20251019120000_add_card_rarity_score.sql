@@ -0,0 +1,2 @@
+-- rarity score computed periodically from ownership statistics
+ALTER TABLE card ADD COLUMN rarity_score REAL;
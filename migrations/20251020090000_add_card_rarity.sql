@@ -0,0 +1,2 @@
+-- rarity tier assigned by administrators
+ALTER TABLE card ADD COLUMN rarity VARCHAR(16) NOT NULL DEFAULT 'common';
@@ -0,0 +1,9 @@
+-- track how many copies of a card a user owns
+ALTER TABLE ownership ADD COLUMN quantity INTEGER NOT NULL DEFAULT 0;
+
+UPDATE
+    ownership
+SET
+    quantity = (CASE WHEN owned THEN 1 ELSE 0 END);
+
+ALTER TABLE ownership DROP COLUMN owned;
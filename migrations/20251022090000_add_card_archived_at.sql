@@ -0,0 +1,2 @@
+-- archived cards are retired from circulation without being deleted
+ALTER TABLE card ADD COLUMN archived_at TIMESTAMP;
@@ -0,0 +1,9 @@
+-- per-guild card content lint rules
+CREATE TABLE guild_lint_rules (
+    guild_id BIGINT PRIMARY KEY,
+    max_length INTEGER,
+    -- JSON arrays of strings
+    banned_words TEXT NOT NULL DEFAULT '[]',
+    required_sections TEXT NOT NULL DEFAULT '[]',
+    updated_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,3 @@
+-- who created and last edited a card
+ALTER TABLE card ADD COLUMN created_by INTEGER REFERENCES user(id);
+ALTER TABLE card ADD COLUMN last_edited_by INTEGER REFERENCES user(id);
@@ -0,0 +1,11 @@
+-- restricts who may grant a card; cards without entries may be granted by
+-- anyone allowed to grant cards
+CREATE TABLE card_grant_policy (
+    card_id INTEGER NOT NULL REFERENCES card(id),
+    -- either 'user' or 'role'
+    kind VARCHAR(8) NOT NULL,
+    -- discord id of the user or role
+    subject_id BIGINT NOT NULL,
+
+    UNIQUE (card_id, kind, subject_id)
+);
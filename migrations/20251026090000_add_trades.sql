@@ -0,0 +1,23 @@
+-- trades between two users; cards change hands only once the recipient
+-- accepts the trade
+CREATE TABLE trade (
+    id INTEGER PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    initiator_id INTEGER NOT NULL REFERENCES user(id),
+    recipient_id INTEGER NOT NULL REFERENCES user(id),
+    -- one of 'open', 'accepted' or 'cancelled'
+    status VARCHAR(16) NOT NULL DEFAULT 'open',
+    inserted_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+-- the cards each side of a trade gives up
+CREATE TABLE trade_item (
+    trade_id INTEGER NOT NULL REFERENCES trade(id),
+    -- the user giving up the card
+    owner_id INTEGER NOT NULL REFERENCES user(id),
+    card_id INTEGER NOT NULL REFERENCES card(id),
+    quantity INTEGER NOT NULL DEFAULT 1,
+
+    UNIQUE (trade_id, owner_id, card_id)
+);
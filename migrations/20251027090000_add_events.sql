@@ -0,0 +1,24 @@
+-- time-limited events; once an event ends, the worker archives its cards and
+-- fills in the summary columns
+CREATE TABLE event (
+    id INTEGER PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    starts_at TIMESTAMP NOT NULL,
+    ends_at TIMESTAMP NOT NULL,
+    ended_at TIMESTAMP,
+    participants INTEGER,
+    cards_granted INTEGER,
+    inserted_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+
+    UNIQUE (guild_id, name)
+);
+
+-- the cards that are only active during an event
+CREATE TABLE event_card (
+    event_id INTEGER NOT NULL REFERENCES event(id),
+    card_id INTEGER NOT NULL REFERENCES card(id),
+
+    UNIQUE (event_id, card_id)
+);
@@ -0,0 +1,8 @@
+-- cards a user has pinned to their showcase, whether or not they own them
+CREATE TABLE favorite (
+    user_id INTEGER NOT NULL REFERENCES user(id),
+    card_id INTEGER NOT NULL REFERENCES card(id),
+    inserted_at TIMESTAMP NOT NULL,
+
+    UNIQUE (user_id, card_id)
+);
@@ -0,0 +1,5 @@
+-- scratch space for the startup self-test; rows never outlive the test
+CREATE TABLE self_test (
+    id INTEGER PRIMARY KEY,
+    token CHAR(32) NOT NULL
+);
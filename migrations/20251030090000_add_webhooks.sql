@@ -0,0 +1,29 @@
+-- urls a guild's card events are delivered to
+CREATE TABLE webhook (
+    id INTEGER PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    url TEXT NOT NULL,
+    -- key payloads are signed with
+    secret TEXT NOT NULL,
+    inserted_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+
+    UNIQUE (guild_id, url)
+);
+
+-- outbox of event payloads waiting to be delivered to a webhook
+CREATE TABLE webhook_delivery (
+    id INTEGER PRIMARY KEY,
+    webhook_id INTEGER NOT NULL REFERENCES webhook(id) ON DELETE CASCADE,
+    event VARCHAR(32) NOT NULL,
+    payload TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL,
+    -- null until the webhook accepts the payload
+    delivered_at TIMESTAMP,
+    last_error TEXT,
+    inserted_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX webhook_delivery_pending ON webhook_delivery (next_attempt_at)
+    WHERE delivered_at IS NULL;
@@ -0,0 +1,11 @@
+-- recently dispatched events, kept so webhook consumers can catch up
+CREATE TABLE event_log (
+    -- never reused, even after old events are removed
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    guild_id BIGINT NOT NULL,
+    event VARCHAR(32) NOT NULL,
+    payload TEXT NOT NULL,
+    inserted_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX event_log_guild_id ON event_log (guild_id, seq);
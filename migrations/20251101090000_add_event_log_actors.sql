@@ -0,0 +1,5 @@
+-- who caused each logged event, and which card it was about
+ALTER TABLE event_log ADD COLUMN actor_id INTEGER REFERENCES user(id) ON DELETE SET NULL;
+ALTER TABLE event_log ADD COLUMN card_id INTEGER;
+
+CREATE INDEX event_log_card_id ON event_log (card_id, seq);
@@ -0,0 +1,10 @@
+-- how many times each card was shown, in total and by day
+ALTER TABLE card ADD COLUMN view_count INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE card_view (
+    card_id INTEGER NOT NULL REFERENCES card(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    views INTEGER NOT NULL,
+
+    PRIMARY KEY (card_id, day)
+);
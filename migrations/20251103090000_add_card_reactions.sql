@@ -0,0 +1,12 @@
+-- how owners rated the cards they own, from 1 to 5
+CREATE TABLE card_reaction (
+    user_id INTEGER NOT NULL REFERENCES user(id),
+    card_id INTEGER NOT NULL REFERENCES card(id),
+    rating INTEGER NOT NULL CHECK (rating BETWEEN 1 AND 5),
+    inserted_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+
+    PRIMARY KEY (user_id, card_id)
+);
+
+CREATE INDEX card_reaction_card_id ON card_reaction (card_id);
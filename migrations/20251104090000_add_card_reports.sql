@@ -0,0 +1,19 @@
+-- cards flagged as inappropriate by users, waiting to be reviewed
+CREATE TABLE card_report (
+    id INTEGER PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    card_id INTEGER NOT NULL REFERENCES card(id),
+    reporter_id INTEGER NOT NULL REFERENCES user(id),
+    reason TEXT NOT NULL,
+    -- one of 'open', 'resolved' or 'dismissed'
+    status VARCHAR(16) NOT NULL DEFAULT 'open',
+    resolved_by INTEGER REFERENCES user(id),
+    resolved_at TIMESTAMP,
+    inserted_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX card_report_guild_id_status ON card_report (guild_id, status);
+
+-- a user has at most one open report on a card
+CREATE UNIQUE INDEX card_report_open ON card_report (card_id, reporter_id)
+WHERE status = 'open';
@@ -0,0 +1,12 @@
+-- cards and categories a user must have collected before a card may be
+-- granted to them
+CREATE TABLE card_prerequisite (
+    card_id INTEGER NOT NULL REFERENCES card(id),
+    -- exactly one of these is set
+    required_card_id INTEGER REFERENCES card(id),
+    required_category VARCHAR(255),
+
+    CHECK ((required_card_id IS NULL) != (required_category IS NULL))
+);
+
+CREATE INDEX card_prerequisite_card_id ON card_prerequisite (card_id);
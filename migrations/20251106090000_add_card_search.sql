@@ -0,0 +1,23 @@
+-- full-text index over card content, kept in sync with the card table
+CREATE VIRTUAL TABLE card_search USING fts5(
+    content,
+    content = 'card',
+    content_rowid = 'id'
+);
+
+INSERT INTO card_search (card_search) VALUES ('rebuild');
+
+CREATE TRIGGER card_search_insert AFTER INSERT ON card BEGIN
+    INSERT INTO card_search (rowid, content) VALUES (new.id, new.content);
+END;
+
+CREATE TRIGGER card_search_delete AFTER DELETE ON card BEGIN
+    INSERT INTO card_search (card_search, rowid, content)
+    VALUES ('delete', old.id, old.content);
+END;
+
+CREATE TRIGGER card_search_update AFTER UPDATE OF content ON card BEGIN
+    INSERT INTO card_search (card_search, rowid, content)
+    VALUES ('delete', old.id, old.content);
+    INSERT INTO card_search (rowid, content) VALUES (new.id, new.content);
+END;
@@ -0,0 +1,29 @@
+-- reward rules; once a user owns every card of a rule, the worker grants
+-- them the rule's reward card
+CREATE TABLE card_rule (
+    id INTEGER PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    reward_id INTEGER NOT NULL REFERENCES card(id),
+    inserted_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+
+    UNIQUE (guild_id, name)
+);
+
+-- the set of cards a rule asks for
+CREATE TABLE card_rule_card (
+    rule_id INTEGER NOT NULL REFERENCES card_rule(id),
+    card_id INTEGER NOT NULL REFERENCES card(id),
+
+    UNIQUE (rule_id, card_id)
+);
+
+-- the users a rule has rewarded, so nobody is rewarded twice
+CREATE TABLE card_rule_reward (
+    rule_id INTEGER NOT NULL REFERENCES card_rule(id),
+    user_id INTEGER NOT NULL REFERENCES user(id),
+    inserted_at TIMESTAMP NOT NULL,
+
+    UNIQUE (rule_id, user_id)
+);
@@ -0,0 +1,20 @@
+-- seasons; a snapshot of every card owned in a guild at some point in time
+CREATE TABLE season (
+    id INTEGER PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    reset BOOLEAN NOT NULL DEFAULT FALSE,
+    inserted_at TIMESTAMP NOT NULL,
+
+    UNIQUE (guild_id, name)
+);
+
+-- what every user owned when the season was recorded
+CREATE TABLE season_ownership (
+    season_id INTEGER NOT NULL REFERENCES season(id),
+    card_id INTEGER NOT NULL REFERENCES card(id),
+    owner_id INTEGER NOT NULL REFERENCES user(id),
+    quantity INTEGER NOT NULL,
+
+    UNIQUE (season_id, owner_id, card_id)
+);
@@ -0,0 +1,2 @@
+-- card listings always filter by guild and usually rank by name
+CREATE INDEX card_guild_id_name ON card (guild_id, name);
@@ -0,0 +1,16 @@
+-- what each guild rewards duplicate trade-ins with; trade-ins are disabled
+-- without a reward
+CREATE TABLE guild_trade_in_rules (
+    guild_id BIGINT PRIMARY KEY,
+    reward TEXT,
+    updated_at TIMESTAMP NOT NULL
+);
+
+-- currency earned from trade-ins, per guild
+CREATE TABLE wallet (
+    user_id INTEGER NOT NULL REFERENCES user(id),
+    guild_id BIGINT NOT NULL,
+    balance INTEGER NOT NULL DEFAULT 0,
+
+    UNIQUE (user_id, guild_id)
+);
@@ -0,0 +1,17 @@
+-- when a user was first granted a card; backfilled from whatever is left of
+-- the event log, and unknown otherwise
+ALTER TABLE ownership ADD COLUMN granted_at TIMESTAMP;
+
+UPDATE ownership
+SET granted_at = (
+    SELECT MIN(e.inserted_at)
+    FROM event_log e
+    WHERE
+        e.card_id = ownership.card_id
+        AND (
+            (e.event IN ('card.granted', 'reward.granted')
+                AND json_extract(e.payload, '$.data.user_id') = ownership.owner_id)
+            OR (e.event = 'card.transferred'
+                AND json_extract(e.payload, '$.data.to_id') = ownership.owner_id)
+        )
+);
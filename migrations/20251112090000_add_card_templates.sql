@@ -0,0 +1,20 @@
+-- operator-level starter decks that guilds can copy their cards from
+CREATE TABLE card_template (
+    id INTEGER PRIMARY KEY,
+    name VARCHAR(255) NOT NULL UNIQUE,
+    description TEXT,
+    inserted_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+-- the cards of a template, kept as they would be imported
+CREATE TABLE card_template_card (
+    template_id INTEGER NOT NULL REFERENCES card_template(id),
+    position INTEGER NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    category_name VARCHAR(255),
+    content TEXT NOT NULL,
+    previous VARCHAR(255),
+
+    UNIQUE (template_id, name)
+);
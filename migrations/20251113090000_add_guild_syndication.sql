@@ -0,0 +1,10 @@
+-- a guild's read-only subscription to a category of another guild's cards
+CREATE TABLE guild_syndication (
+    id INTEGER PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    source_guild_id BIGINT NOT NULL,
+    category_name VARCHAR(255) NOT NULL,
+    inserted_at TIMESTAMP NOT NULL,
+
+    UNIQUE (guild_id, source_guild_id, category_name)
+);
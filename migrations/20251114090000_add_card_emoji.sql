@@ -0,0 +1,5 @@
+-- an emoji that identifies a card, as an alternative to its name
+ALTER TABLE card ADD COLUMN emoji VARCHAR(255);
+
+-- looks cards up by emoji, which are unique within a guild
+CREATE UNIQUE INDEX card_guild_id_emoji ON card (guild_id, emoji) WHERE emoji IS NOT NULL;
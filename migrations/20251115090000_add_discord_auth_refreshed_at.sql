@@ -0,0 +1,5 @@
+-- when a user's display name was last confirmed with discord; unknown for
+-- users that have not been seen since
+ALTER TABLE discord_auth ADD COLUMN refreshed_at TIMESTAMP;
+
+CREATE INDEX discord_auth_refreshed_at ON discord_auth (refreshed_at);
@@ -0,0 +1,25 @@
+-- where a card's content is kept; `inline` content is in the card row, and
+-- anything else leaves it empty
+ALTER TABLE card ADD COLUMN content_location VARCHAR(16) NOT NULL DEFAULT 'inline';
+
+-- long card content moved out of the card table
+CREATE TABLE card_content (
+    card_id INTEGER PRIMARY KEY REFERENCES card(id) ON DELETE CASCADE,
+    content TEXT NOT NULL
+);
+
+-- the search index can no longer read content from the card table, so it
+-- keeps a copy of its own, written alongside the content
+DROP TRIGGER card_search_insert;
+DROP TRIGGER card_search_delete;
+DROP TRIGGER card_search_update;
+DROP TABLE card_search;
+
+CREATE VIRTUAL TABLE card_search USING fts5(content);
+
+INSERT INTO card_search (rowid, content)
+SELECT id, content FROM card;
+
+CREATE TRIGGER card_search_delete AFTER DELETE ON card BEGIN
+    DELETE FROM card_search WHERE rowid = old.id;
+END;
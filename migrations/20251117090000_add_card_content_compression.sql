@@ -0,0 +1,2 @@
+-- zstd compressed content; when set, `content` is left empty
+ALTER TABLE card_content ADD COLUMN compressed BLOB;
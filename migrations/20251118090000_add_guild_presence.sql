@@ -0,0 +1,10 @@
+-- guilds the bot reports serving, so data of departed guilds can be found
+CREATE TABLE guild_presence (
+    guild_id BIGINT PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    member_count INTEGER,
+    first_seen_at TIMESTAMP NOT NULL,
+    last_seen_at TIMESTAMP NOT NULL,
+    -- set when the guild is first missing from a report
+    departed_at TIMESTAMP
+);
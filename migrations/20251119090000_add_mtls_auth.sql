@@ -0,0 +1,7 @@
+-- managed users that authenticate with a client certificate instead of an
+-- API key, by the certificate's common name
+CREATE TABLE mtls_auth (
+    user_id INTEGER NOT NULL UNIQUE REFERENCES user(id),
+    common_name VARCHAR(255) NOT NULL UNIQUE,
+    inserted_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,7 @@
+-- what each guild announces in public; completions are not announced
+-- without a channel
+CREATE TABLE guild_announcements (
+    guild_id BIGINT PRIMARY KEY,
+    completions_channel_id BIGINT,
+    updated_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,13 @@
+-- every request an operator made as another user, for auditing
+CREATE TABLE impersonation_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    actor_id INTEGER NOT NULL REFERENCES user(id),
+    user_id INTEGER NOT NULL REFERENCES user(id),
+    reason TEXT NOT NULL,
+    method VARCHAR(16) NOT NULL,
+    path TEXT NOT NULL,
+    request_id VARCHAR(64),
+    inserted_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX impersonation_log_user_id ON impersonation_log (user_id);
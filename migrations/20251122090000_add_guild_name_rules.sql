@@ -0,0 +1,8 @@
+-- the card name rules of each guild; guilds without rules allow any name
+CREATE TABLE guild_name_rules (
+    guild_id BIGINT PRIMARY KEY,
+    max_length INTEGER,
+    ascii_only BOOLEAN NOT NULL DEFAULT FALSE,
+    symbols TEXT,
+    updated_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,10 @@
+-- UI state stored for multi-step bot flows, readable until it expires
+CREATE TABLE view_state (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL REFERENCES user(id) ON DELETE CASCADE,
+    data TEXT NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX view_state_expires_at ON view_state (expires_at);
@@ -0,0 +1,9 @@
+-- weighted cards packs are pulled from, per guild
+CREATE TABLE drop_table_entry (
+    guild_id BIGINT NOT NULL,
+    card_id INTEGER NOT NULL REFERENCES card(id) ON DELETE CASCADE,
+    weight INTEGER NOT NULL,
+    max_copies INTEGER,
+    updated_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (guild_id, card_id)
+);
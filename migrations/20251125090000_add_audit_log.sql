@@ -0,0 +1,18 @@
+-- events kept for auditing, apart from the event log so they outlive its
+-- replay window; entries share their event's sequence number
+CREATE TABLE audit_log (
+    seq INTEGER PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    event VARCHAR(32) NOT NULL,
+    payload TEXT NOT NULL,
+    actor_id INTEGER REFERENCES user(id) ON DELETE SET NULL,
+    card_id INTEGER,
+    inserted_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX audit_log_guild_id ON audit_log (guild_id, seq);
+CREATE INDEX audit_log_card_id ON audit_log (card_id, seq);
+
+INSERT INTO audit_log (seq, guild_id, event, payload, actor_id, card_id, inserted_at)
+SELECT seq, guild_id, event, payload, actor_id, card_id, inserted_at
+FROM event_log;
@@ -0,0 +1,3 @@
+-- where the summaries of ended events are posted; they are not posted
+-- without a channel
+ALTER TABLE guild_announcements ADD COLUMN events_channel_id BIGINT;
@@ -0,0 +1,3 @@
+-- when a user's display name last failed to be looked up on discord, so
+-- deleted accounts do not stay at the head of the stale queue
+ALTER TABLE discord_auth ADD COLUMN refresh_failed_at TIMESTAMP;
@@ -0,0 +1,6 @@
+-- the newest event removed from each guild's event log, so replays know
+-- whether events they ask for are gone
+CREATE TABLE event_log_pruned (
+    guild_id BIGINT PRIMARY KEY,
+    seq INTEGER NOT NULL
+);
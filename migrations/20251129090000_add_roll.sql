@@ -0,0 +1,36 @@
+-- rolls, keyed by their seed, so the pool a roll picked from is logged once
+-- instead of with every card it granted
+CREATE TABLE roll (
+    seed TEXT PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    pool TEXT NOT NULL,
+    inserted_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX roll_guild_id ON roll (guild_id);
+
+INSERT OR IGNORE INTO roll (seed, guild_id, pool, inserted_at)
+SELECT
+    json_extract(payload, '$.data.roll.seed'),
+    guild_id,
+    json_extract(payload, '$.data.roll.pool'),
+    MIN(inserted_at)
+FROM (
+    SELECT guild_id, payload, inserted_at FROM audit_log
+    UNION ALL
+    SELECT guild_id, payload, inserted_at FROM event_log
+)
+WHERE json_extract(payload, '$.data.roll.pool') IS NOT NULL
+GROUP BY json_extract(payload, '$.data.roll.seed');
+
+UPDATE audit_log
+SET payload = json_remove(payload, '$.data.roll.pool')
+WHERE json_extract(payload, '$.data.roll.pool') IS NOT NULL;
+
+UPDATE event_log
+SET payload = json_remove(payload, '$.data.roll.pool')
+WHERE json_extract(payload, '$.data.roll.pool') IS NOT NULL;
+
+UPDATE webhook_delivery
+SET payload = json_remove(payload, '$.data.roll.pool')
+WHERE json_extract(payload, '$.data.roll.pool') IS NOT NULL;
@@ -0,0 +1,104 @@
+//! Display name backfill.
+//!
+//! The server only learns a user's display name when the bot proxies for
+//! them, so users that stop using the bot keep their old names in
+//! leaderboards and audit entries. The bot periodically asks the server for
+//! recently active users with stale names and looks them up on Discord. Users
+//! that cannot be looked up are reported back, so they do not stay at the head
+//! of the queue.
+
+use std::num::NonZeroU64;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time::{MissedTickBehavior, interval};
+
+use twilight_http::Client;
+use twilight_model::id::{Id, marker::UserMarker};
+
+use crate::http::Client as DbClient;
+
+/// How many users are refreshed at once.
+const BATCH_COUNT: u32 = 100;
+
+/// Refreshes stale display names from Discord.
+///
+/// Cheaply cloneable.
+#[derive(Clone, Debug)]
+pub struct Backfill {
+    client: Arc<Client>,
+    db_client: DbClient,
+    /// How old a display name may get, in hours.
+    max_age: u32,
+}
+
+impl Backfill {
+    /// Creates a new `Backfill`.
+    pub fn new(client: Arc<Client>, db_client: DbClient, max_age: u32) -> Backfill {
+        Backfill {
+            client,
+            db_client,
+            max_age,
+        }
+    }
+
+    /// Refreshes a batch of stale users each `period`, forever.
+    pub async fn run(self, period: Duration) {
+        let mut interval = interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            match self.refresh().await {
+                Ok(0) => (),
+                Ok(count) => tracing::info!(count, "refreshed stale display names"),
+                Err(err) => tracing::warn!(?err, "failed to refresh stale display names"),
+            }
+        }
+    }
+
+    /// Refreshes a single batch of stale users, returning how many were
+    /// refreshed.
+    async fn refresh(&self) -> anyhow::Result<usize> {
+        let stale = self
+            .db_client
+            .stale_discord_users()
+            .count(BATCH_COUNT)
+            .max_age(self.max_age)
+            .execute()
+            .await?;
+
+        let mut users = Vec::with_capacity(stale.len());
+        let mut failed = Vec::new();
+
+        for stale in stale {
+            let user_id = Id::<UserMarker>::from(NonZeroU64::from(stale.discord_id));
+
+            match self.client.user(user_id).await {
+                Ok(user) => {
+                    let user = user.model().await?;
+                    users.push((user.id, user.name));
+                }
+                // deleted accounts cannot be looked up anymore
+                Err(err) => {
+                    tracing::debug!(%user_id, ?err, "failed to fetch user");
+                    failed.push(user_id);
+                }
+            }
+        }
+
+        if users.is_empty() && failed.is_empty() {
+            return Ok(0);
+        }
+
+        let count = users.len();
+        self.db_client
+            .update_discord_users(users)
+            .failed(failed)
+            .execute()
+            .await?;
+
+        Ok(count)
+    }
+}
@@ -0,0 +1,86 @@
+//! Bulk card archival.
+//!
+//! See [`command_archive`].
+
+use anyhow::Error;
+
+use chrono::NaiveDate;
+
+use twilight_model::{
+    application::interaction::application_command::{CommandData, CommandOptionValue},
+    channel::message::MessageFlags,
+    http::interaction::{InteractionResponse, InteractionResponseType},
+};
+
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+use crate::commands::InteractionContext;
+
+/// `/archive`, archives cards in bulk by category or creation date.
+pub async fn command_archive(cx: InteractionContext, data: CommandData) -> anyhow::Result<()> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+
+    let option = |name: &str| {
+        data.options
+            .iter()
+            .find(|option| option.name == name)
+            .and_then(|option| match option.value {
+                CommandOptionValue::String(ref value) => Some(value.clone()),
+                _ => None,
+            })
+    };
+
+    let category = option("category");
+    let before = option("before")
+        .map(|before| NaiveDate::parse_from_str(before.trim(), "%Y-%m-%d"))
+        .transpose();
+
+    let message = match (category, before) {
+        (_, Err(_)) => String::from("Dates must be formatted as `YYYY-MM-DD`."),
+        (None, Ok(None)) => {
+            String::from("Give a `category` or a `before` date to choose which cards to archive.")
+        }
+        (category, Ok(before)) => {
+            let mut request = cx.db_client.archive_cards(guild_id);
+
+            if let Some(category) = category.as_ref() {
+                request = request.category(category);
+            }
+
+            if let Some(before) = before {
+                request = request.created_before(before.and_time(Default::default()));
+            }
+
+            let res = request.execute().await?;
+
+            tracing::debug!(?category, ?before, "/archive: archived {} cards", res.archived);
+
+            match res.archived {
+                0 => String::from("No cards matched; nothing was archived."),
+                1 => String::from("Archived 1 card."),
+                n => format!("Archived {} cards.", n),
+            }
+        }
+    };
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .flags(MessageFlags::EPHEMERAL)
+                        .content(message)
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
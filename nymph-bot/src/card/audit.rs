@@ -0,0 +1,347 @@
+//! Guild audit log.
+//!
+//! See [`command_audit`].
+
+use std::iter;
+
+use anyhow::{Context as _, Error};
+
+use nymph_model::{
+    card::normalize_name,
+    dispatch::Event,
+    response::audit::{AuditEntry, AuditLogResponse},
+};
+
+use twilight_model::{
+    application::interaction::application_command::{CommandData, CommandOptionValue},
+    channel::message::{
+        AllowedMentions, Component, MessageFlags,
+        component::{ActionRow, ButtonStyle, Container},
+    },
+    http::interaction::{InteractionResponse, InteractionResponseType},
+    id::{Id, marker::GuildMarker},
+};
+
+use twilight_util::builder::{
+    InteractionResponseDataBuilder,
+    message::{ButtonBuilder, ContainerBuilder, TextDisplayBuilder},
+};
+
+use super::show_not_found;
+
+use crate::commands::InteractionContext;
+
+/// How many entries are shown on each page.
+const PAGE_SIZE: usize = 10;
+
+/// How many entries are listed if no limit is given.
+const DEFAULT_LIMIT: u32 = 25;
+
+/// The most entries that can be listed.
+const MAX_LIMIT: u32 = 100;
+
+/// `/audit`, lists recent card grants, revokes, edits and reports in the
+/// guild.
+pub async fn command_audit(cx: InteractionContext, data: CommandData) -> anyhow::Result<()> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+
+    let user = data
+        .options
+        .iter()
+        .find(|option| option.name == "user")
+        .and_then(|option| match option.value {
+            CommandOptionValue::User(id) => Some(id),
+            _ => None,
+        })
+        .and_then(|id| data.resolved.as_ref().and_then(|r| r.users.get(&id)));
+    let name = data
+        .options
+        .iter()
+        .find(|option| option.name == "card")
+        .and_then(|option| match option.value {
+            CommandOptionValue::String(ref value) => Some(normalize_name(value)),
+            _ => None,
+        });
+    let limit = data
+        .options
+        .iter()
+        .find(|option| option.name == "limit")
+        .and_then(|option| match option.value {
+            CommandOptionValue::Integer(value) => Some(value.clamp(1, MAX_LIMIT as i64) as u32),
+            _ => None,
+        })
+        .unwrap_or(DEFAULT_LIMIT);
+
+    let user_id = match user {
+        Some(user) => Some(cx.db_client.get_discord_user(user).await?.id),
+        None => None,
+    };
+
+    let card_id = match name {
+        Some(name) => {
+            let card = cx
+                .db_client
+                .list_cards(guild_id)
+                .find(&name)
+                .execute()
+                .await
+                .context("failed to fetch card")?
+                .into_iter()
+                // only find exact matches
+                .find(|card| card.name == name);
+
+            let Some(card) = card else {
+                tracing::debug!("/audit: failed to find card w/ name `{}`", name);
+                show_not_found(&cx, &name).await?;
+
+                return Ok(());
+            };
+
+            Some(card.id)
+        }
+        None => None,
+    };
+
+    let filter = AuditFilter {
+        user_id,
+        card_id,
+        limit,
+    };
+    let container = display_audit_log(&cx, guild_id, filter, 0).await?;
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .components(iter::once(Component::Container(container)))
+                        .flags(MessageFlags::EPHEMERAL | MessageFlags::IS_COMPONENTS_V2)
+                        .allowed_mentions(AllowedMentions::default())
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// The page buttons of `/audit`, turns the page of the audit log.
+pub async fn component_audit_page(cx: InteractionContext, id: &str) -> anyhow::Result<()> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+
+    let (page, filter) = AuditFilter::parse(id).context("malformed audit page")?;
+    let container = display_audit_log(&cx, guild_id, filter, page).await?;
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::UpdateMessage,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .components(iter::once(Component::Container(container)))
+                        .flags(MessageFlags::IS_COMPONENTS_V2)
+                        .allowed_mentions(AllowedMentions::default())
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// What an audit log listing is filtered by.
+///
+/// Carried between pages in the custom ids of the page buttons.
+#[derive(Clone, Copy, Debug)]
+struct AuditFilter {
+    user_id: Option<i32>,
+    card_id: Option<i32>,
+    limit: u32,
+}
+
+impl AuditFilter {
+    /// The custom id of a button that turns to `page`.
+    fn custom_id(&self, page: usize) -> String {
+        let id = |id: Option<i32>| id.map(|id| id.to_string()).unwrap_or_default();
+
+        format!(
+            "audit:{}:{}:{}:{}",
+            page,
+            self.limit,
+            id(self.user_id),
+            id(self.card_id)
+        )
+    }
+
+    /// Parses a custom id, without its `audit:` prefix.
+    fn parse(s: &str) -> Option<(usize, AuditFilter)> {
+        let mut parts = s.split(':');
+
+        let page = parts.next()?.parse().ok()?;
+        let limit = parts.next()?.parse().ok()?;
+        let mut id = || match parts.next()? {
+            "" => Some(None),
+            id => id.parse().ok().map(Some),
+        };
+        let user_id = id()?;
+        let card_id = id()?;
+
+        Some((
+            page,
+            AuditFilter {
+                user_id,
+                card_id,
+                limit,
+            },
+        ))
+    }
+}
+
+/// Creates a container showing a page of the audit log.
+async fn display_audit_log(
+    cx: &InteractionContext,
+    guild_id: Id<GuildMarker>,
+    filter: AuditFilter,
+    page: usize,
+) -> anyhow::Result<Container> {
+    let mut request = cx.db_client.get_audit_log(guild_id).count(filter.limit);
+
+    if let Some(user_id) = filter.user_id {
+        request = request.user(user_id);
+    }
+
+    if let Some(card_id) = filter.card_id {
+        request = request.card(card_id);
+    }
+
+    let log = request
+        .execute()
+        .await
+        .context("failed to fetch audit log")?;
+
+    let pages = log.entries.len().div_ceil(PAGE_SIZE).max(1);
+    let page = page.min(pages - 1);
+
+    let mut body = String::from("## Audit log");
+
+    if log.entries.is_empty() {
+        body.push_str("\nNothing has happened yet.");
+    }
+
+    for entry in log.entries.iter().skip(page * PAGE_SIZE).take(PAGE_SIZE) {
+        body.push('\n');
+        body.push_str(&format_entry(&log, entry));
+    }
+
+    if pages > 1 {
+        body.push_str(&format!("\n-# Page {} of {}", page + 1, pages));
+    }
+
+    let mut container = ContainerBuilder::new()
+        .accent_color(Some(cx.config.general.embed_color))
+        .spoiler(false)
+        .component(TextDisplayBuilder::new(body).build())
+        .build();
+
+    if pages > 1 {
+        let action_row = ActionRow {
+            id: None,
+            components: vec![
+                ButtonBuilder::new(ButtonStyle::Secondary)
+                    .custom_id(filter.custom_id(page.saturating_sub(1)))
+                    .label("Newer")
+                    .disabled(page == 0)
+                    .build()
+                    .into(),
+                ButtonBuilder::new(ButtonStyle::Secondary)
+                    .custom_id(filter.custom_id(page + 1))
+                    .label("Older")
+                    .disabled(page + 1 >= pages)
+                    .build()
+                    .into(),
+            ],
+        };
+
+        container.components.push(Component::ActionRow(action_row));
+    }
+
+    Ok(container)
+}
+
+/// Formats an audit log entry as a list item.
+fn format_entry(log: &AuditLogResponse, entry: &AuditEntry) -> String {
+    let user = |id: i32| format_user(log, id);
+
+    let action = match &entry.event {
+        Event::CardCreated(card) => format!("created `{}`", card.name),
+        Event::CardUpdated(card) => format!("edited `{}`", card.name),
+        Event::CardGranted(ownership) => format!(
+            "granted `{}` to {}",
+            ownership.card.name,
+            user(ownership.user_id)
+        ),
+        Event::CardRevoked(ownership) => format!(
+            "revoked `{}` from {}",
+            ownership.card.name,
+            user(ownership.user_id)
+        ),
+        Event::CardTransferred(transfer) => format!(
+            "moved `{}` from {} to {}",
+            transfer.card.name,
+            user(transfer.from_id),
+            user(transfer.to_id)
+        ),
+        Event::CardReported(report) => format!("reported `{}`", report.card.name),
+        Event::ReportResolved(report) => format!(
+            "{} a report on `{}` by {}",
+            report.report.status.to_str(),
+            report.card.name,
+            user(report.report.reporter.id)
+        ),
+        Event::RewardGranted(reward) => format!(
+            "earned `{}` by completing `{}`",
+            reward.card.name, reward.rule.name
+        ),
+        Event::SetCompleted(completion) => {
+            format!("was celebrated for completing `{}`", completion.rule.name)
+        }
+        Event::EventEnded(end) => format!("ended the event `{}`", end.event.name),
+        Event::SeasonReset(season) => format!("reset every card for season `{}`", season.name),
+    };
+
+    let actor = entry
+        .actor_id
+        .map(user)
+        .unwrap_or_else(|| String::from("Someone"));
+
+    format!(
+        "- <t:{}:f> {} {}",
+        entry.timestamp.and_utc().timestamp(),
+        actor,
+        action
+    )
+}
+
+/// Formats a user mentioned in the audit log, preferring a Discord mention.
+fn format_user(log: &AuditLogResponse, id: i32) -> String {
+    match log.users.iter().find(|user| user.user.id == id) {
+        Some(user) => match user.discord_id {
+            Some(discord_id) => format!("<@{}>", discord_id.get()),
+            None => user.user.display_name.clone(),
+        },
+        None => format!("user {}", id),
+    }
+}
@@ -2,4 +2,178 @@
 //!
 //! See [`command_admin_card`].
 
-//pub async fn command_admin_card()
+use std::iter;
+
+use anyhow::{Context as _, Error};
+
+use nymph_model::{
+    ApiError, ErrorCode,
+    card::{Visibility, normalize_name},
+    request::card::Expand,
+};
+
+use twilight_model::{
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        message_component::MessageComponentInteractionData,
+    },
+    channel::message::{Component, MessageFlags},
+    http::interaction::{InteractionResponse, InteractionResponseType},
+};
+
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+use crate::commands::InteractionContext;
+
+use super::{display_card_admin, prerequisite_candidates, show_card_editor, show_not_found};
+
+/// `/sl`, shows a card and its administrator information to an admin.
+pub async fn command_admin_card(cx: InteractionContext, data: CommandData) -> anyhow::Result<()> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+
+    let name = data
+        .options
+        .iter()
+        .find(|option| option.name == "name")
+        .and_then(|option| match option.value {
+            CommandOptionValue::String(ref value) => Some(value),
+            _ => None,
+        })
+        .ok_or_else(|| Error::msg("invalid command payload"))?;
+    let name = normalize_name(name);
+
+    // admins edit cards of every visibility
+    let card = cx
+        .db_client
+        .list_cards(guild_id)
+        .find(&name)
+        .visibility([Visibility::Public, Visibility::Hidden, Visibility::Private])
+        .execute()
+        .await?
+        .into_iter()
+        // only find exact matches
+        .find(|card| card.name == name);
+
+    let Some(card) = card else {
+        tracing::debug!("/sl: failed to find card w/ name `{}`", name);
+        show_not_found(&cx, &name).await?;
+
+        return Ok(());
+    };
+
+    // fetch the card as the bot to get privileged information
+    let card = cx
+        .db_client
+        .get_card(guild_id, card.id)
+        .expand([Expand::Upgrades, Expand::Downgrade])
+        .execute()
+        .await?;
+
+    tracing::debug!(?card, "/sl: got card");
+
+    show_card_editor(&cx, &card).await
+}
+
+/// The prerequisite menus of the card editor, replaces what a card asks for
+/// before it can be granted.
+///
+/// `kind` is either `cards` or `categories`, naming the half of the
+/// prerequisites the menu sets.
+pub async fn component_set_prerequisites(
+    cx: InteractionContext,
+    kind: &str,
+    card_id: i32,
+    data: MessageComponentInteractionData,
+) -> anyhow::Result<()> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+
+    let card = cx
+        .db_client
+        .get_card(guild_id, card_id)
+        .expand([Expand::Upgrades, Expand::Downgrade])
+        .execute()
+        .await?;
+    let mut prerequisites = cx
+        .db_client
+        .get_prerequisites(guild_id, card_id)
+        .execute()
+        .await?;
+
+    match kind {
+        "cards" => {
+            // the menu only offers some cards; keep the ones it left out
+            let candidates = prerequisite_candidates(&cx, guild_id, &card).await?;
+
+            prerequisites
+                .cards
+                .retain(|id| !candidates.iter().any(|candidate| candidate.id == *id));
+
+            for value in data.values.iter() {
+                prerequisites
+                    .cards
+                    .push(value.parse().context("malformed card id")?);
+            }
+        }
+        "categories" => prerequisites.categories = data.values.clone(),
+        _ => return Err(Error::msg("unknown prerequisite kind")),
+    }
+
+    if let Err(err) = cx
+        .db_client
+        .update_prerequisites(guild_id, card_id, prerequisites)
+        .execute()
+        .await
+    {
+        let err = match err.downcast::<ApiError>() {
+            Ok(err) if matches!(err.code, ErrorCode::NotFound | ErrorCode::InvalidData) => err,
+            Ok(err) => return Err(err.into()),
+            Err(err) => return Err(err),
+        };
+
+        cx.client
+            .interaction(cx.application_id)
+            .create_response(
+                cx.id,
+                &cx.token,
+                &InteractionResponse {
+                    kind: InteractionResponseType::ChannelMessageWithSource,
+                    data: Some(
+                        InteractionResponseDataBuilder::new()
+                            .flags(MessageFlags::EPHEMERAL)
+                            .content(err.message)
+                            .build(),
+                    ),
+                },
+            )
+            .await?;
+
+        return Ok(());
+    }
+
+    tracing::debug!(card_id, kind, "updated card prerequisites");
+
+    let card_container = display_card_admin(&cx, &card).await?;
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::UpdateMessage,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .components(iter::once(Component::Container(card_container)))
+                        .flags(MessageFlags::IS_COMPONENTS_V2)
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
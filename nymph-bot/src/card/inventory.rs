@@ -1,21 +1,40 @@
 //! Inventory interactions.
 
+use std::iter;
+use std::time::Duration;
+
 use anyhow::{Context as _, Error};
 
-use nymph_model::{ApiError, ErrorCode};
+use nymph_model::{
+    ApiError, ErrorCode, card::normalize_name, response::card::TradeInResponse,
+    trade_in::TradeInReward,
+};
 
 use twilight_model::{
-    application::interaction::application_command::{CommandData, CommandOptionValue},
-    channel::message::{AllowedMentions, MessageFlags},
-    http::interaction::{InteractionResponse, InteractionResponseType},
+    application::interaction::{
+        application_command::{CommandData, CommandOptionValue},
+        message_component::MessageComponentInteractionData,
+    },
+    channel::message::{
+        AllowedMentions, Component, MessageFlags,
+        component::{ActionRow, ButtonStyle, Container},
+    },
+    http::{
+        attachment::Attachment,
+        interaction::{InteractionResponse, InteractionResponseType},
+    },
+    id::{Id, marker::GuildMarker},
     user::User,
 };
 
-use twilight_util::builder::InteractionResponseDataBuilder;
+use twilight_util::builder::{
+    InteractionResponseDataBuilder,
+    message::{ButtonBuilder, ContainerBuilder, TextDisplayBuilder},
+};
 
-use crate::commands::InteractionContext;
+use crate::{commands::InteractionContext, followup::FollowupAction};
 
-use super::show_not_found;
+use super::{page_buttons, passes_grant_policy, show_not_found};
 
 use derive_more::{Display, Error};
 
@@ -25,12 +44,6 @@ pub async fn command_transfer_card(cx: InteractionContext, data: CommandData) ->
     let guild_id = cx
         .guild_id
         .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
-    let caller = cx
-        .member
-        .as_ref()
-        .and_then(|m| m.user.as_ref())
-        .ok_or_else(|| Error::msg("missing user in interaction"))?;
-
     let options = InventoryTransferOptions::try_from(&data)?;
 
     let is_current_user = cx
@@ -67,19 +80,51 @@ pub async fn command_transfer_card(cx: InteractionContext, data: CommandData) ->
         let user = cx.db_client.get_discord_user(&options.target_user).await?;
 
         if options.kind == InventoryTransferType::Grant {
+            // the card's grant policy excludes the caller
+            if !passes_grant_policy(&cx, card.id).await? {
+                let message = format!("You are not allowed to grant card `{}`.", card.name);
+
+                cx.client
+                    .interaction(cx.application_id)
+                    .create_response(
+                        cx.id,
+                        &cx.token,
+                        &InteractionResponse {
+                            kind: InteractionResponseType::ChannelMessageWithSource,
+                            data: Some(
+                                InteractionResponseDataBuilder::new()
+                                    .flags(MessageFlags::EPHEMERAL)
+                                    .content(message)
+                                    .allowed_mentions(AllowedMentions::default())
+                                    .build(),
+                            ),
+                        },
+                    )
+                    .await?;
+
+                return Ok(());
+            }
+
+            // only managed clients may grant, so the grant is made as the
+            // bot once the policy passes
             match cx
                 .db_client
-                .proxy_for(&caller)
                 .grant_card_to_user(user.id, card.id)
                 .execute()
                 .await
             {
                 Ok(card) => {
                     // the operation was successful
-                    let message = format!(
-                        "Granted card `{}` to user <@{}>!",
-                        card.name, options.target_user.id,
-                    );
+                    let message = match card.quantity {
+                        Some(quantity) if quantity > 1 => format!(
+                            "Granted card `{}` to user <@{}>! They now own {} copies.",
+                            card.name, options.target_user.id, quantity,
+                        ),
+                        _ => format!(
+                            "Granted card `{}` to user <@{}>!",
+                            card.name, options.target_user.id,
+                        ),
+                    };
 
                     cx.client
                         .interaction(cx.application_id)
@@ -103,12 +148,18 @@ pub async fn command_transfer_card(cx: InteractionContext, data: CommandData) ->
                 }
                 Err(err) if err.is::<ApiError>() => {
                     match err.downcast_ref::<ApiError>().unwrap().code {
-                        ErrorCode::InvalidTransfer => {
-                            // user already owns the card!
-                            let message = format!(
-                                "User <@{}> already owns card `{}`!",
-                                options.target_user.id, card.name,
-                            );
+                        ErrorCode::UnmetPrerequisites | ErrorCode::InvalidTransfer => {
+                            // the card cannot be given out; the server names
+                            // what the user is missing
+                            let message = match err.downcast_ref::<ApiError>().unwrap() {
+                                err if err.code == ErrorCode::UnmetPrerequisites => {
+                                    err.message.clone()
+                                }
+                                _ => format!(
+                                    "Card `{}` cannot be granted to user <@{}>!",
+                                    card.name, options.target_user.id,
+                                ),
+                            };
 
                             cx.client
                                 .interaction(cx.application_id)
@@ -136,19 +187,26 @@ pub async fn command_transfer_card(cx: InteractionContext, data: CommandData) ->
                 Err(err) => Err(err),
             }
         } else {
+            // only managed clients may revoke; members are checked by the
+            // command's default permissions instead
             match cx
                 .db_client
-                .proxy_for(&caller)
                 .revoke_card_from_user(user.id, card.id)
                 .execute()
                 .await
             {
                 Ok(card) => {
                     // the operation was successful
-                    let message = format!(
-                        "Revoked card `{}` from user <@{}>!",
-                        card.name, options.target_user.id,
-                    );
+                    let message = match card.quantity {
+                        Some(quantity) if quantity > 0 => format!(
+                            "Revoked card `{}` from user <@{}>! They have {} copies left.",
+                            card.name, options.target_user.id, quantity,
+                        ),
+                        _ => format!(
+                            "Revoked card `{}` from user <@{}>!",
+                            card.name, options.target_user.id,
+                        ),
+                    };
 
                     cx.client
                         .interaction(cx.application_id)
@@ -173,7 +231,7 @@ pub async fn command_transfer_card(cx: InteractionContext, data: CommandData) ->
                 Err(err) if err.is::<ApiError>() => {
                     match err.downcast_ref::<ApiError>().unwrap().code {
                         ErrorCode::InvalidTransfer => {
-                            // user already owns the card!
+                            // user does not own the card!
                             let message = format!(
                                 "User <@{}> does not own card `{}`!",
                                 options.target_user.id, card.name,
@@ -237,6 +295,717 @@ pub async fn command_transfer_card(cx: InteractionContext, data: CommandData) ->
     }
 }
 
+/// `/gift`, gives one of the caller's cards to another member.
+pub async fn command_gift(cx: InteractionContext, data: CommandData) -> Result<(), Error> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+    let caller = cx
+        .member
+        .as_ref()
+        .and_then(|m| m.user.as_ref())
+        .ok_or_else(|| Error::msg("missing user in interaction"))?;
+
+    let options = InventoryTransferOptions::try_from(&data)?;
+
+    let (message, flags) = if options.target_user.bot {
+        (
+            format!(
+                "User <@{}> is a bot. Unfortunately, automatons do not have the higher thought required to appreciate game design.",
+                options.target_user.id
+            ),
+            MessageFlags::EPHEMERAL,
+        )
+    } else if options.target_user.id == caller.id {
+        (
+            format!("-# {}", cx.config.accent.self_grant),
+            MessageFlags::EPHEMERAL,
+        )
+    } else {
+        // only cards the caller can see may be gifted
+        let card = cx
+            .db_client
+            .proxy_for(caller)
+            .list_cards(guild_id)
+            .find(&options.name)
+            .execute()
+            .await
+            .context("failed to fetch card")?
+            .into_iter()
+            // only find exact matches
+            .find(|card| card.name == options.name);
+
+        let Some(card) = card else {
+            tracing::debug!("/gift: failed to find card w/ name `{}`", options.name);
+            show_not_found(&cx, &options.name).await?;
+
+            return Ok(());
+        };
+
+        let from = cx.db_client.get_discord_user(caller).await?;
+        let to = cx.db_client.get_discord_user(&options.target_user).await?;
+
+        match cx
+            .db_client
+            .proxy_for(caller)
+            .transfer_card(from.id, card.id, to.id)
+            .execute()
+            .await
+        {
+            Ok(res) => (
+                format!(
+                    "<@{}> gave card `{}` to user <@{}>!",
+                    caller.id, res.card.name, options.target_user.id,
+                ),
+                MessageFlags::empty(),
+            ),
+            Err(err) if err.is::<ApiError>() => {
+                let err = err.downcast::<ApiError>().unwrap();
+
+                match err.code {
+                    ErrorCode::InvalidTransfer => (
+                        format!("You cannot gift card `{}`!", card.name),
+                        MessageFlags::EPHEMERAL,
+                    ),
+                    _ => return Err(err.into()),
+                }
+            }
+            Err(err) => return Err(err),
+        }
+    };
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .flags(flags)
+                        .content(message)
+                        .allowed_mentions(AllowedMentions::default())
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// `/whohas`, lists the members that own a card.
+pub async fn command_who_has(cx: InteractionContext, data: CommandData) -> Result<(), Error> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+
+    let name = data
+        .options
+        .iter()
+        .find(|option| option.name == "name")
+        .and_then(|option| match option.value {
+            CommandOptionValue::String(ref value) => Some(value),
+            _ => None,
+        })
+        .ok_or(InvalidCommandPayload)?;
+    let name = normalize_name(name);
+
+    // fetch requested card
+    let card = cx
+        .db_client
+        .list_cards(guild_id)
+        .find(&name)
+        .execute()
+        .await
+        .context("failed to fetch card")?
+        .into_iter()
+        // only find exact matches
+        .find(|card| card.name == name);
+
+    let Some(card) = card else {
+        tracing::debug!("/whohas: failed to find card w/ name `{}`", name);
+        show_not_found(&cx, &name).await?;
+
+        return Ok(());
+    };
+
+    let owners = cx
+        .db_client
+        .list_card_owners(guild_id, card.id)
+        .execute()
+        .await
+        .context("failed to fetch card owners")?;
+
+    let message = if owners.items.is_empty() {
+        format!("Nobody owns card `{}`.", card.name)
+    } else {
+        let mut list = owners
+            .items
+            .iter()
+            .map(|owner| {
+                let name = match owner.discord_id {
+                    Some(discord_id) => format!("<@{}>", discord_id.get()),
+                    None => owner.user.display_name.clone(),
+                };
+
+                if owner.quantity > 1 {
+                    format!("- {} ×{}", name, owner.quantity)
+                } else {
+                    format!("- {}", name)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let more = owners.total_items - owners.items.len() as u64;
+
+        if more > 0 {
+            list.push_str(&format!("\n-# …and {} more", more));
+        }
+
+        format!("Card `{}` is owned by:\n{}", card.name, list)
+    };
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .flags(MessageFlags::EPHEMERAL)
+                        .content(message)
+                        .allowed_mentions(AllowedMentions::default())
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// The "Grant to…" menu of the card editor, grants a card to the selected
+/// user.
+pub async fn component_grant_card(
+    cx: InteractionContext,
+    card_id: i32,
+    data: MessageComponentInteractionData,
+) -> Result<(), Error> {
+    let target_user = data
+        .resolved
+        .as_ref()
+        .and_then(|resolved| resolved.users.values().next())
+        .ok_or(InvalidCommandPayload)?;
+
+    let (message, flags) = if target_user.bot {
+        (
+            format!(
+                "User <@{}> is a bot. Unfortunately, automatons do not have the higher thought required to appreciate game design.",
+                target_user.id
+            ),
+            MessageFlags::EPHEMERAL,
+        )
+    } else if !passes_grant_policy(&cx, card_id).await? {
+        (
+            String::from("You are not allowed to grant this card."),
+            MessageFlags::EPHEMERAL,
+        )
+    } else {
+        let user = cx.db_client.get_discord_user(target_user).await?;
+
+        match cx
+            .db_client
+            .grant_card_to_user(user.id, card_id)
+            .execute()
+            .await
+        {
+            Ok(card) => (
+                match card.quantity {
+                    Some(quantity) if quantity > 1 => format!(
+                        "Granted card `{}` to user <@{}>! They now own {} copies.",
+                        card.name, target_user.id, quantity,
+                    ),
+                    _ => format!(
+                        "Granted card `{}` to user <@{}>!",
+                        card.name, target_user.id,
+                    ),
+                },
+                MessageFlags::empty(),
+            ),
+            Err(err) if err.is::<ApiError>() => {
+                let err = err.downcast::<ApiError>().unwrap();
+
+                match err.code {
+                    ErrorCode::InvalidTransfer | ErrorCode::UnmetPrerequisites => {
+                        (err.message, MessageFlags::EPHEMERAL)
+                    }
+                    _ => return Err(err.into()),
+                }
+            }
+            Err(err) => return Err(err),
+        }
+    };
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .flags(flags)
+                        .content(message)
+                        .allowed_mentions(AllowedMentions::default())
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// How many cards `/inv` shows at once.
+const INVENTORY_PAGE_SIZE: u32 = 25;
+
+/// The most buttons an action row can hold.
+const MAX_ROW_BUTTONS: usize = 5;
+
+/// How long the trade-in offers of `/inv` stand before their buttons are
+/// taken away.
+///
+/// Interaction tokens expire after 15 minutes, so this must be shorter.
+const TRADE_IN_OFFER_LIFETIME: Duration = Duration::from_secs(10 * 60);
+
+/// `/inv`, lists the cards a user owns, with buttons to trade in their
+/// duplicates.
+pub async fn command_inventory(cx: InteractionContext, _data: CommandData) -> Result<(), Error> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+
+    let container = display_inventory(&cx, guild_id, 1, None).await?;
+    let offers_trade_ins = has_trade_in_buttons(&container);
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .components(iter::once(Component::Container(container)))
+                        .flags(MessageFlags::EPHEMERAL | MessageFlags::IS_COMPONENTS_V2)
+                        .allowed_mentions(AllowedMentions::default())
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    // offers go stale as the inventory changes, so they only stand a while
+    if offers_trade_ins {
+        let expired = FollowupAction::Replace {
+            content: "Your trade-in offers expired. Use `/inv` again to trade in duplicates."
+                .to_owned(),
+        };
+
+        if let Err(err) = cx
+            .followups
+            .schedule(&cx, TRADE_IN_OFFER_LIFETIME, expired)
+            .await
+        {
+            tracing::warn!(?err, "failed to schedule trade-in offer expiry");
+        }
+    }
+
+    Ok(())
+}
+
+/// The page buttons of `/inv`, turns the page of the inventory.
+pub async fn component_inventory_page(cx: InteractionContext, page: &str) -> Result<(), Error> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+
+    let page = page.parse::<u32>().context("malformed inventory page")?;
+    let container = display_inventory(&cx, guild_id, page, None).await?;
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::UpdateMessage,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .components(iter::once(Component::Container(container)))
+                        .flags(MessageFlags::IS_COMPONENTS_V2)
+                        .allowed_mentions(AllowedMentions::default())
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// The "Trade in" buttons of `/inv`, trades in a card's duplicates.
+pub async fn component_trade_in(cx: InteractionContext, id: &str) -> Result<(), Error> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+    let caller = cx
+        .member
+        .as_ref()
+        .and_then(|m| m.user.as_ref())
+        .ok_or_else(|| Error::msg("missing user in interaction"))?;
+
+    // buttons sent before the inventory had pages have no page
+    let mut parts = id.split(':');
+    let (card_id, count, page) = (|| {
+        let card_id = parts.next()?.parse::<i32>().ok()?;
+        let count = parts.next()?.parse().ok()?;
+        let page = match parts.next() {
+            Some(page) => page.parse().ok()?,
+            None => 1,
+        };
+
+        Some((card_id, count, page))
+    })()
+    .context("malformed trade-in id")?;
+
+    let user = cx.db_client.get_discord_user(caller).await?;
+
+    let note = match cx
+        .db_client
+        .proxy_for(caller)
+        .trade_in(user.id, card_id, count)
+        .execute()
+        .await
+    {
+        Ok(res) => describe_trade_in(&res, count),
+        // the inventory was stale, or the guild's rules changed
+        Err(err)
+            if err.downcast_ref::<ApiError>().is_some_and(|err| {
+                matches!(
+                    err.code,
+                    ErrorCode::InvalidTransfer | ErrorCode::InvalidData | ErrorCode::Forbidden
+                )
+            }) =>
+        {
+            err.downcast::<ApiError>().unwrap().message
+        }
+        Err(err) => return Err(err),
+    };
+
+    let container = display_inventory(&cx, guild_id, page, Some(note)).await?;
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::UpdateMessage,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .components(iter::once(Component::Container(container)))
+                        .flags(MessageFlags::IS_COMPONENTS_V2)
+                        .allowed_mentions(AllowedMentions::default())
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// The "Export as CSV" button of `/inv`, sends the caller their cards in a
+/// direct message, for keeping track of them in a spreadsheet.
+pub async fn component_inventory_export(cx: InteractionContext, format: &str) -> Result<(), Error> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+    let caller = cx
+        .member
+        .as_ref()
+        .and_then(|m| m.user.as_ref())
+        .ok_or_else(|| Error::msg("missing user in interaction"))?;
+
+    if format != "csv" {
+        return Err(Error::msg(format!("unknown export format `{}`", format)));
+    }
+
+    let user = cx.db_client.get_discord_user(caller).await?;
+
+    let file = cx
+        .db_client
+        .proxy_for(caller)
+        .export_inventory(user.id)
+        .guild(guild_id)
+        .execute()
+        .await
+        .context("failed to export inventory")?;
+
+    let sent = async {
+        let channel = cx
+            .client
+            .create_private_channel(caller.id)
+            .await?
+            .model()
+            .await?;
+
+        cx.client
+            .create_message(channel.id)
+            .content("Here are the cards you own.")
+            .attachments(&[Attachment::from_bytes(
+                String::from("inventory.csv"),
+                file,
+                0,
+            )])
+            .await?;
+
+        Ok::<_, Error>(())
+    }
+    .await;
+
+    let message = match sent {
+        Ok(()) => "Sent your cards to your direct messages.",
+        // users may refuse direct messages from server members
+        Err(err) => {
+            tracing::debug!(?err, "failed to send inventory export");
+            "Your cards could not be sent to you. Do you accept direct messages from server members?"
+        }
+    };
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .content(message)
+                        .flags(MessageFlags::EPHEMERAL)
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Creates a container listing a page of the caller's cards, with a button for
+/// each card that has duplicates to trade in.
+async fn display_inventory(
+    cx: &InteractionContext,
+    guild_id: Id<GuildMarker>,
+    page: u32,
+    note: Option<String>,
+) -> Result<Container, Error> {
+    let caller = cx
+        .member
+        .as_ref()
+        .and_then(|m| m.user.as_ref())
+        .ok_or_else(|| Error::msg("missing user in interaction"))?;
+
+    let user = cx.db_client.get_discord_user(caller).await?;
+    let client = cx.db_client.proxy_for(caller);
+
+    let list = |page: u32| {
+        client
+            .list_inventory(user.id)
+            .guild(guild_id)
+            .page(page)
+            .count(INVENTORY_PAGE_SIZE)
+            .execute()
+    };
+
+    let cards = match list(page.max(1)).await {
+        Ok(cards) => cards,
+        // cards traded in since may have emptied the page, so the last page
+        // is shown instead
+        Err(err)
+            if err
+                .downcast_ref::<ApiError>()
+                .is_some_and(|err| err.code == ErrorCode::InvalidData) =>
+        {
+            let cards = list(1).await.context("failed to fetch inventory")?;
+
+            if cards.total_pages > 1 {
+                list(cards.total_pages)
+                    .await
+                    .context("failed to fetch inventory")?
+            } else {
+                cards
+            }
+        }
+        Err(err) => return Err(err.context("failed to fetch inventory")),
+    };
+
+    let rules = client
+        .get_trade_in_rules(guild_id)
+        .execute()
+        .await
+        .context("failed to fetch trade-in rules")?;
+
+    let mut body = String::from("## Inventory");
+
+    if let Some(note) = note {
+        body.push_str(&format!("\n{}", note));
+    }
+
+    if cards.items.is_empty() {
+        body.push_str(&format!(
+            "\n-# {}\nYou do not have any cards.",
+            cx.config.accent.no_cards_owned
+        ));
+    }
+
+    let mut buttons: Vec<Component> = Vec::new();
+
+    for card in cards.items.iter() {
+        let quantity = card.quantity.unwrap_or(1);
+
+        let name = match card.emoji.as_ref() {
+            Some(emoji) => format!("{} `{}`", emoji, card.name),
+            None => format!("`{}`", card.name),
+        };
+
+        if quantity > 1 {
+            body.push_str(&format!("\n- {} ×{}", name, quantity));
+        } else {
+            body.push_str(&format!("\n- {}", name));
+        }
+
+        // the last copy is always kept
+        let count = match rules.reward {
+            Some(TradeInReward::Currency { .. }) => quantity - 1,
+            Some(TradeInReward::Roll { copies }) if copies > 0 => (quantity - 1) / copies * copies,
+            _ => 0,
+        };
+
+        if count > 0 {
+            // button labels are capped at 80 characters
+            let name = card.name.chars().take(60).collect::<String>();
+
+            buttons.push(
+                ButtonBuilder::new(ButtonStyle::Secondary)
+                    .custom_id(format!("trade_in:{}:{}:{}", card.id, count, cards.page))
+                    .label(format!("Trade in {} ×{}", name, count))
+                    .build()
+                    .into(),
+            );
+        }
+    }
+
+    if cards.total_pages > 1 {
+        body.push_str(&format!(
+            "\n-# Page {} of {}",
+            cards.page, cards.total_pages
+        ));
+    }
+
+    let mut container = ContainerBuilder::new()
+        .accent_color(Some(cx.config.general.embed_color))
+        .spoiler(false)
+        .component(TextDisplayBuilder::new(body).build())
+        .build();
+
+    for row in buttons.chunks(MAX_ROW_BUTTONS) {
+        container.components.push(Component::ActionRow(ActionRow {
+            id: None,
+            components: row.to_vec(),
+        }));
+    }
+
+    if cards.total_pages > 1 {
+        container
+            .components
+            .push(page_buttons(cards.page, cards.total_pages, |page| {
+                format!("inv:{}", page)
+            }));
+    }
+
+    if !cards.items.is_empty() {
+        container.components.push(Component::ActionRow(ActionRow {
+            id: None,
+            components: vec![
+                ButtonBuilder::new(ButtonStyle::Secondary)
+                    .custom_id("inv_export:csv")
+                    .label("Export as CSV")
+                    .build()
+                    .into(),
+            ],
+        }));
+    }
+
+    Ok(container)
+}
+
+/// Checks if an inventory offers to trade in any duplicates.
+fn has_trade_in_buttons(container: &Container) -> bool {
+    container
+        .components
+        .iter()
+        .any(|component| match component {
+            Component::ActionRow(row) => row.components.iter().any(|component| {
+                matches!(
+                    component,
+                    Component::Button(button)
+                        if button
+                            .custom_id
+                            .as_deref()
+                            .is_some_and(|id| id.starts_with("trade_in:"))
+                )
+            }),
+            _ => false,
+        })
+}
+
+/// Describes what duplicates were traded in for.
+fn describe_trade_in(res: &TradeInResponse, count: u32) -> String {
+    let traded = format!("Traded in {} duplicate(s) of `{}`", count, res.card.name);
+
+    if let Some(balance) = res.balance {
+        format!(
+            "{} for currency. Your balance is now **{}**.",
+            traded, balance
+        )
+    } else if !res.rolled.is_empty() {
+        let rolled = res
+            .rolled
+            .iter()
+            .map(|card| format!("`{}`", card.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        match res.seed.as_ref() {
+            // the seed lets moderators replay the roll if it is disputed
+            Some(seed) => format!("{} and rolled {}!\n-# Roll seed `{}`", traded, rolled, seed),
+            None => format!("{} and rolled {}!", traded, rolled),
+        }
+    } else {
+        format!("{}.", traded)
+    }
+}
+
 #[derive(Debug, Display, Error)]
 #[display("invalid command payload")]
 struct InvalidCommandPayload;
@@ -252,6 +1021,7 @@ struct InventoryTransferOptions {
 enum InventoryTransferType {
     Grant,
     Revoke,
+    Gift,
 }
 
 impl TryFrom<&CommandData> for InventoryTransferOptions {
@@ -268,8 +1038,8 @@ impl TryFrom<&CommandData> for InventoryTransferOptions {
                 CommandOptionValue::String(ref value) => Some(value),
                 _ => None,
             })
-            .ok_or(InvalidCommandPayload)?
-            .to_ascii_uppercase();
+            .ok_or(InvalidCommandPayload)?;
+        let name = normalize_name(name);
 
         let target_user = value
             .options
@@ -286,6 +1056,7 @@ impl TryFrom<&CommandData> for InventoryTransferOptions {
         let kind = match value.name.as_str() {
             "grant" => InventoryTransferType::Grant,
             "revoke" => InventoryTransferType::Revoke,
+            "gift" => InventoryTransferType::Gift,
             _ => return Err(InvalidCommandPayload),
         };
 
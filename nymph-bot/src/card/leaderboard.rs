@@ -0,0 +1,90 @@
+//! Most viewed cards.
+//!
+//! See [`command_leaderboard`].
+
+use std::iter;
+
+use anyhow::{Context as _, Error};
+
+use nymph_model::card::Visibility;
+
+use twilight_model::{
+    application::interaction::application_command::CommandData,
+    channel::message::{Component, MessageFlags},
+    http::interaction::{InteractionResponse, InteractionResponseType},
+};
+
+use twilight_util::builder::{
+    InteractionResponseDataBuilder,
+    message::{ContainerBuilder, TextDisplayBuilder},
+};
+
+use super::format_title;
+
+use crate::commands::InteractionContext;
+
+/// How many cards are listed.
+const COUNT: u32 = 10;
+
+/// `/leaderboard`, shows the guild's most viewed cards of the week.
+pub async fn command_leaderboard(cx: InteractionContext, _data: CommandData) -> anyhow::Result<()> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+
+    let cards = cx
+        .db_client
+        .popular_cards(guild_id)
+        .days(7)
+        .count(COUNT)
+        .execute()
+        .await
+        .context("failed to fetch popular cards")?;
+
+    let mut body = String::from("## Most viewed this week");
+
+    if cards.is_empty() {
+        body.push_str("\nNo cards have been viewed this week.");
+    }
+
+    for (i, popular) in cards.iter().enumerate() {
+        // don't give away cards members may not know about
+        let title = match popular.card.visibility {
+            Visibility::Public => format_title(&cx.config, &popular.card),
+            _ => String::from("*A secret card*"),
+        };
+
+        body.push_str(&format!(
+            "\n{}. {} — {} view{}",
+            i + 1,
+            title,
+            popular.views,
+            if popular.views == 1 { "" } else { "s" }
+        ));
+    }
+
+    let container = ContainerBuilder::new()
+        .accent_color(Some(cx.config.general.embed_color))
+        .spoiler(false)
+        .component(TextDisplayBuilder::new(body).build())
+        .build();
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .components(iter::once(Component::Container(container)))
+                        .flags(MessageFlags::EPHEMERAL | MessageFlags::IS_COMPONENTS_V2)
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
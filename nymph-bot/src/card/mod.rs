@@ -1,18 +1,43 @@
 //! Card functions and instrumentation.
 
+mod archive;
+mod audit;
 mod editor;
 mod inventory;
+mod leaderboard;
+mod progress;
+mod recent;
+mod report;
+mod setup;
 mod show;
 
-pub use inventory::command_transfer_card;
+pub use archive::command_archive;
+pub use audit::{command_audit, component_audit_page};
+pub use editor::{command_admin_card, component_set_prerequisites};
+pub use inventory::{
+    command_gift, command_inventory, command_transfer_card, command_who_has, component_grant_card,
+    component_inventory_export, component_inventory_page, component_trade_in,
+};
+pub use leaderboard::command_leaderboard;
+pub use progress::command_progress;
+pub use recent::RecentCards;
+pub use report::{
+    command_report, command_reports, component_report_page, component_resolve_report,
+};
+pub use setup::{autocomplete_setup, command_setup};
 pub use show::command_show;
 
 use std::fmt::Debug;
 use std::iter;
+use std::num::NonZeroU64;
+use std::time::Duration;
 
 use anyhow::Error;
 
-use nymph_model::card::{Card, Visibility};
+use nymph_model::{
+    card::{Author, Card, Visibility, normalize_name},
+    request::card::CardSort,
+};
 
 use tracing::instrument;
 
@@ -26,6 +51,7 @@ use twilight_model::{
         component::{ActionRow, Button, ButtonStyle, Container, SelectMenuType},
     },
     http::interaction::{InteractionResponse, InteractionResponseType},
+    id::{Id, marker::GuildMarker},
 };
 
 use twilight_util::builder::{
@@ -36,9 +62,12 @@ use twilight_util::builder::{
     },
 };
 
-use crate::commands::InteractionContext;
+use crate::{commands::InteractionContext, config::Config, dispatch::AUTOCOMPLETE_ENTRY_LEN};
+
+/// The most options a select menu can hold.
+const MAX_SELECT_OPTIONS: usize = 25;
 
-/// Autocompletes a `/s` command.
+/// Autocompletes a card name option, like the one of `/s`.
 pub async fn autocomplete(cx: &InteractionContext, data: CommandData) -> anyhow::Result<()> {
     let guild_id = cx
         .guild_id
@@ -49,34 +78,82 @@ pub async fn autocomplete(cx: &InteractionContext, data: CommandData) -> anyhow:
         .and_then(|m| m.user.as_ref())
         .ok_or_else(|| Error::msg("missing user in interaction"))?;
 
+    // commands may name their card option differently
     let name = data
         .options
         .iter()
-        .find(|option| option.name == "name")
-        .and_then(|option| match option.value {
+        .find_map(|option| match option.value {
             CommandOptionValue::Focused(ref value, CommandOptionType::String) => Some(value),
             _ => None,
         })
         .ok_or_else(|| Error::msg("invalid command payload"))?;
 
-    // make search query uppercase
-    let name = name.to_ascii_uppercase();
+    // normalize search query like card names are
+    let name = normalize_name(name);
+
+    // search card; the search keeps running past the budget, so its results
+    // can still be suggested next time
+    let search = tokio::spawn({
+        let db_client = cx.db_client.proxy_for(caller);
+        let recent_cards = cx.recent_cards.clone();
+        let name = name.clone();
+        let user_id = caller.id;
+
+        async move {
+            let names = db_client
+                .list_cards(guild_id)
+                .search(name)
+                // leave out card contents, which are never shown here
+                .fields([
+                    "id",
+                    "guild_id",
+                    "name",
+                    "visibility",
+                    "rarity",
+                    "hidden",
+                    "created_at",
+                    "updated_at",
+                ])
+                .execute()
+                .await?
+                .into_iter()
+                .filter(|card| !card.hidden.unwrap_or(false))
+                .map(|card| card.name)
+                .collect::<Vec<_>>();
+
+            recent_cards.remember(guild_id, user_id, &names).await;
+
+            Ok::<_, Error>(names)
+        }
+    });
 
-    // search card
-    let choices = cx
-        .db_client
-        .proxy_for(&caller)
-        .list_cards(guild_id)
-        .search(name)
-        .execute()
-        .await?
-        .into_iter()
-        .filter(|card| !card.hidden.unwrap_or(false))
-        .map(|card| CommandOptionChoice {
-            name_localizations: None,
-            value: CommandOptionChoiceValue::String(card.name.clone()),
-            name: card.name,
-        });
+    let budget = Duration::from_millis(cx.config.autocomplete.budget);
+
+    let names = match tokio::time::timeout(budget, search).await {
+        Ok(Ok(Ok(names))) => names,
+        Ok(Ok(Err(err))) => {
+            tracing::warn!(?err, "autocomplete search failed, suggesting recent cards");
+            cx.recent_cards
+                .matching(guild_id, caller.id, &name, AUTOCOMPLETE_ENTRY_LEN)
+                .await
+        }
+        Ok(Err(err)) => return Err(err.into()),
+        Err(_) => {
+            tracing::warn!(
+                ?budget,
+                "autocomplete search too slow, suggesting recent cards"
+            );
+            cx.recent_cards
+                .matching(guild_id, caller.id, &name, AUTOCOMPLETE_ENTRY_LEN)
+                .await
+        }
+    };
+
+    let choices = names.into_iter().map(|name| CommandOptionChoice {
+        name_localizations: None,
+        value: CommandOptionChoiceValue::String(name.clone()),
+        name,
+    });
 
     cx.client
         .interaction(cx.application_id)
@@ -131,15 +208,7 @@ where
     // Each card becomes a section of a message component
     let components = cards.into_iter().map(|card| {
         // Build card detail
-        let category = card
-            .category_name
-            .as_ref()
-            .and_then(|n| cx.config.category.get(n));
-        let formatted_title = category
-            .map(|c| c.format_title(&card.name))
-            .unwrap_or_else(|| format!("`{}`", card.name));
-
-        let body = format!("## {}", formatted_title);
+        let body = format!("## {}", format_title(&cx.config, card));
 
         // Create button to show card
         let button = ButtonBuilder::new(ButtonStyle::Secondary)
@@ -217,20 +286,162 @@ async fn display_card_admin(cx: &InteractionContext, card: &Card) -> anyhow::Res
 
     action_row.components.push(visibility_selector.into());
 
+    // only offer granting the card if the caller passes its grant policy
+    let grant_row = passes_grant_policy(cx, card.id).await?.then(|| ActionRow {
+        id: None,
+        components: vec![
+            SelectMenuBuilder::new(format!("grant_card:{}", card.id), SelectMenuType::User)
+                .placeholder("Grant to…")
+                .build()
+                .into(),
+        ],
+    });
+
+    // offer the cards and categories the card may build on
+    let prerequisites = cx
+        .db_client
+        .get_prerequisites(guild_id, card.id)
+        .execute()
+        .await?;
+    let candidates = prerequisite_candidates(cx, guild_id, card).await?;
+
+    let card_prerequisites = (!candidates.is_empty()).then(|| {
+        let selector = candidates.iter().fold(
+            SelectMenuBuilder::new(
+                format!("prerequisite_cards:{}", card.id),
+                SelectMenuType::Text,
+            ),
+            |selector, candidate| {
+                selector.option(
+                    SelectMenuOptionBuilder::new(candidate.name.as_str(), candidate.id.to_string())
+                        .default(prerequisites.cards.contains(&candidate.id)),
+                )
+            },
+        );
+
+        ActionRow {
+            id: None,
+            components: vec![
+                selector
+                    .placeholder("Requires owning…")
+                    .min_values(0)
+                    .max_values(candidates.len() as u8)
+                    .build()
+                    .into(),
+            ],
+        }
+    });
+
+    let mut categories = cx
+        .config
+        .category
+        .keys()
+        .chain(prerequisites.categories.iter())
+        .collect::<Vec<_>>();
+    categories.sort();
+    categories.dedup();
+    categories.truncate(MAX_SELECT_OPTIONS);
+
+    let category_prerequisites = (!categories.is_empty()).then(|| {
+        let selector = categories.iter().fold(
+            SelectMenuBuilder::new(
+                format!("prerequisite_categories:{}", card.id),
+                SelectMenuType::Text,
+            ),
+            |selector, category| {
+                selector.option(
+                    SelectMenuOptionBuilder::new(category.as_str(), category.as_str())
+                        .default(prerequisites.categories.contains(*category)),
+                )
+            },
+        );
+
+        ActionRow {
+            id: None,
+            components: vec![
+                selector
+                    .placeholder("Requires collecting…")
+                    .min_values(0)
+                    .max_values(categories.len() as u8)
+                    .build()
+                    .into(),
+            ],
+        }
+    });
+
+    // show administrator statistics
+    let mut stats = match card.rarity_score {
+        Some(score) => format!("-# Rarity score: {:.2}", score),
+        None => String::from("-# Rarity score: unowned"),
+    };
+
+    if let Some(author) = card.created_by.as_ref() {
+        stats.push_str(&format!("\n-# Created by {}", format_author(author)));
+    }
+
+    if let Some(author) = card.last_edited_by.as_ref() {
+        stats.push_str(&format!("\n-# Last edited by {}", format_author(author)));
+    }
+
+    if let Some(ratings) = card.ratings {
+        stats.push_str(&format!(
+            "\n-# Rated {:.1} / 5 by {} owner{}",
+            ratings.average,
+            ratings.count,
+            if ratings.count == 1 { "" } else { "s" }
+        ));
+    }
+
+    card_container.components.push(Component::TextDisplay(
+        TextDisplayBuilder::new(stats).build(),
+    ));
+
     // finalize
     card_container.components.push(action_row.into());
 
+    card_container.components.extend(
+        card_prerequisites
+            .into_iter()
+            .chain(category_prerequisites)
+            .map(Component::ActionRow),
+    );
+
+    if let Some(grant_row) = grant_row {
+        card_container.components.push(grant_row.into());
+    }
+
     Ok(card_container)
 }
 
+/// Lists the cards offered as prerequisites of a card in the editor.
+///
+/// These are the other cards of the card's category, as a select menu cannot
+/// hold the whole guild.
+async fn prerequisite_candidates(
+    cx: &InteractionContext,
+    guild_id: Id<GuildMarker>,
+    card: &Card,
+) -> anyhow::Result<Vec<Card>> {
+    let mut request = cx
+        .db_client
+        .list_cards(guild_id)
+        .sort(CardSort::Name)
+        .count(MAX_SELECT_OPTIONS as u32);
+
+    if let Some(category) = card.category_name.as_ref() {
+        request = request.category(category);
+    }
+
+    Ok(request
+        .execute()
+        .await?
+        .into_iter()
+        .filter(|candidate| candidate.id != card.id)
+        .collect())
+}
+
 /// Creates a card container populated with the information of the card.
 fn display_card(cx: &InteractionContext, card: &Card) -> anyhow::Result<Container> {
-    let category = card
-        .category_name
-        .as_ref()
-        .and_then(|n| cx.config.category.get(n));
-    let color = category.and_then(|c| c.color);
-
     // create the card action row
     let mut action_row = ActionRow {
         id: None,
@@ -265,31 +476,97 @@ fn display_card(cx: &InteractionContext, card: &Card) -> anyhow::Result<Containe
         }));
     }
 
-    // append any category prefixes/suffixes to title
-    let formatted_title = category
-        .map(|c| c.format_title(&card.name))
-        .unwrap_or_else(|| format!("`{}`", card.name));
+    let mut card_container = render_card(&cx.config, card);
+    // add action row only if there are buttons to add
+    if action_row.components.len() > 0 {
+        card_container
+            .components
+            .push(Component::ActionRow(action_row));
+    }
+
+    // create response
+    Ok(card_container)
+}
+
+/// Renders a card as a container, without any buttons.
+pub fn render_card(config: &Config, card: &Card) -> Container {
+    let category = card
+        .category_name
+        .as_ref()
+        .and_then(|n| config.category.get(n));
+    let rarity = config.rarity.get(card.rarity.to_str());
+    let color = category
+        .and_then(|c| c.color)
+        .or_else(|| rarity.and_then(|r| r.color));
 
     // build card body
-    let body = format!("# {}\n{}", formatted_title, card.content);
+    let body = format!("# {}\n{}", format_title(config, card), card.content);
 
     //let timestamp =
     //    Timestamp::from_micros(card.updated_at().and_utc().timestamp_micros()).expect("valid time");
 
-    let mut card_container = ContainerBuilder::new()
+    ContainerBuilder::new()
         .accent_color(color)
         .spoiler(false)
         .component(TextDisplayBuilder::new(body).build())
-        .build();
-    // add action row only if there are buttons to add
-    if action_row.components.len() > 0 {
-        card_container
-            .components
-            .push(Component::ActionRow(action_row));
+        .build()
+}
+
+/// Formats the title of a card, appending any category and rarity
+/// decorations.
+fn format_title(config: &Config, card: &Card) -> String {
+    let category = card
+        .category_name
+        .as_ref()
+        .and_then(|n| config.category.get(n));
+
+    // append any category prefixes/suffixes to title
+    let title = category
+        .map(|c| c.format_title(&card.name))
+        .unwrap_or_else(|| format!("`{}`", card.name));
+
+    let title = match card.emoji.as_ref() {
+        Some(emoji) => format!("{} {}", emoji, title),
+        None => title,
+    };
+
+    match config.rarity.get(card.rarity.to_str()) {
+        Some(rarity) => rarity.format_title(title),
+        None => title,
+    }
+}
+
+/// Formats a card author, mentioning them if they are a discord user.
+fn format_author(author: &Author) -> String {
+    match author.discord_id {
+        Some(discord_id) => format!("<@{}>", discord_id.get()),
+        None => author.user.display_name.clone(),
     }
+}
 
-    // create response
-    Ok(card_container)
+/// Creates the buttons that turn the pages of a listing, starting at `1`.
+///
+/// Everything the listing needs is carried in the custom id `custom_id`
+/// makes for the page a button turns to, never in the bot's memory, so the
+/// buttons keep working after the bot restarts.
+fn page_buttons(page: u32, total_pages: u32, custom_id: impl Fn(u32) -> String) -> Component {
+    Component::ActionRow(ActionRow {
+        id: None,
+        components: vec![
+            ButtonBuilder::new(ButtonStyle::Secondary)
+                .custom_id(custom_id(page.saturating_sub(1).max(1)))
+                .label("Previous")
+                .disabled(page <= 1)
+                .build()
+                .into(),
+            ButtonBuilder::new(ButtonStyle::Secondary)
+                .custom_id(custom_id(page + 1))
+                .label("Next")
+                .disabled(page >= total_pages)
+                .build()
+                .into(),
+        ],
+    })
 }
 
 /// Responds to an interaction with a not found error message.
@@ -350,3 +627,33 @@ async fn show_unauthorized(cx: &InteractionContext, name: impl AsRef<str>) -> an
 
     Ok(())
 }
+
+/// Checks if the member that made an interaction passes a card's grant
+/// policy.
+///
+/// The server only takes grants from managed clients, so the bot checks the
+/// policy itself before granting a card for a member.
+async fn passes_grant_policy(cx: &InteractionContext, card_id: i32) -> anyhow::Result<bool> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+    let policy = cx
+        .db_client
+        .get_grant_policy(guild_id, card_id)
+        .execute()
+        .await?;
+
+    let caller_id = cx
+        .member
+        .as_ref()
+        .and_then(|m| m.user.as_ref())
+        .map(|u| NonZeroU64::from(u.id).into());
+    let caller_roles = cx
+        .member
+        .iter()
+        .flat_map(|m| m.roles.iter())
+        .map(|role| NonZeroU64::from(*role).into())
+        .collect::<Vec<_>>();
+
+    Ok(policy.allows(caller_id, &caller_roles))
+}
@@ -0,0 +1,88 @@
+//! Collection progress.
+//!
+//! See [`command_progress`].
+
+use std::iter;
+
+use anyhow::Error;
+
+use twilight_model::{
+    application::interaction::application_command::CommandData,
+    channel::message::{Component, MessageFlags},
+    http::interaction::{InteractionResponse, InteractionResponseType},
+};
+
+use twilight_util::builder::{
+    InteractionResponseDataBuilder,
+    message::{ContainerBuilder, TextDisplayBuilder},
+};
+
+use crate::commands::InteractionContext;
+
+/// `/progress`, shows a user how much of the guild's card set they have
+/// collected.
+pub async fn command_progress(cx: InteractionContext, _data: CommandData) -> anyhow::Result<()> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+    let caller = cx
+        .member
+        .as_ref()
+        .and_then(|m| m.user.as_ref())
+        .ok_or_else(|| Error::msg("missing user in interaction"))?;
+
+    let user = cx.db_client.get_discord_user(caller).await?;
+    let progress = cx
+        .db_client
+        .proxy_for(caller)
+        .get_progress(user.id, guild_id)
+        .execute()
+        .await?;
+
+    let mut body = format!(
+        "## Collection\n**{}** / **{}** cards collected",
+        progress.owned, progress.total
+    );
+
+    for category in progress.categories.iter() {
+        let name = match category.category_name.as_ref() {
+            Some(name) => cx
+                .config
+                .category
+                .get(name)
+                .map(|c| c.format_title(name))
+                .unwrap_or_else(|| format!("`{}`", name)),
+            None => String::from("Uncategorized"),
+        };
+
+        body.push_str(&format!(
+            "\n- {}: {} / {}",
+            name, category.owned, category.total
+        ));
+    }
+
+    let container = ContainerBuilder::new()
+        .accent_color(Some(cx.config.general.embed_color))
+        .spoiler(false)
+        .component(TextDisplayBuilder::new(body).build())
+        .build();
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .components(iter::once(Component::Container(container)))
+                        .flags(MessageFlags::EPHEMERAL | MessageFlags::IS_COMPONENTS_V2)
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+//! Recently suggested card names.
+//!
+//! Autocomplete remembers the card names each user was recently suggested,
+//! so it still has something to suggest when the API answers too slowly.
+//! Names are only ever suggested back to the user that was shown them, and
+//! are forgotten after a while, in case a card is hidden from them since.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use moka::future::Cache;
+
+use twilight_model::id::{
+    Id,
+    marker::{GuildMarker, UserMarker},
+};
+
+/// How long names are remembered for.
+const TIME_TO_LIVE: Duration = Duration::from_secs(10 * 60);
+
+/// The most names remembered for each user.
+const MAX_NAMES: usize = 250;
+
+/// A user in a guild.
+type Key = (Id<GuildMarker>, Id<UserMarker>);
+
+/// The card names users were recently suggested.
+///
+/// Cheaply cloneable.
+#[derive(Clone, Debug)]
+pub struct RecentCards(Cache<Key, Arc<[String]>>);
+
+impl RecentCards {
+    /// Creates a new, empty `RecentCards`.
+    pub fn new() -> RecentCards {
+        RecentCards(
+            Cache::builder()
+                .max_capacity(10_000)
+                .time_to_live(TIME_TO_LIVE)
+                .build(),
+        )
+    }
+
+    /// Remembers names suggested to a user, newest first.
+    pub async fn remember(
+        &self,
+        guild_id: Id<GuildMarker>,
+        user_id: Id<UserMarker>,
+        names: &[String],
+    ) {
+        let key = (guild_id, user_id);
+        let old = self.0.get(&key).await.unwrap_or_default();
+
+        let mut merged = names.to_vec();
+        merged.extend(old.iter().filter(|name| !names.contains(name)).cloned());
+        merged.truncate(MAX_NAMES);
+
+        self.0.insert(key, merged.into()).await;
+    }
+
+    /// Finds remembered names that contain `query`.
+    pub async fn matching(
+        &self,
+        guild_id: Id<GuildMarker>,
+        user_id: Id<UserMarker>,
+        query: &str,
+        count: usize,
+    ) -> Vec<String> {
+        let Some(names) = self.0.get(&(guild_id, user_id)).await else {
+            return Vec::new();
+        };
+
+        names
+            .iter()
+            .filter(|name| name.contains(query))
+            .take(count)
+            .cloned()
+            .collect()
+    }
+}
+
+impl Default for RecentCards {
+    fn default() -> Self {
+        RecentCards::new()
+    }
+}
@@ -0,0 +1,332 @@
+//! Content reports.
+//!
+//! See [`command_report`] and [`command_reports`].
+
+use std::iter;
+
+use anyhow::{Context as _, Error};
+
+use nymph_model::{ApiError, ErrorCode, card::normalize_name, report::ReportStatus};
+
+use twilight_model::{
+    application::interaction::application_command::{CommandData, CommandOptionValue},
+    channel::message::{
+        AllowedMentions, Component, MessageFlags,
+        component::{ActionRow, ButtonStyle, Container},
+    },
+    http::interaction::{InteractionResponse, InteractionResponseType},
+    id::{Id, marker::GuildMarker},
+};
+
+use twilight_util::builder::{
+    InteractionResponseDataBuilder,
+    message::{ButtonBuilder, ContainerBuilder, TextDisplayBuilder},
+};
+
+use super::{page_buttons, show_not_found};
+
+use crate::commands::InteractionContext;
+
+/// How many reports are shown at once.
+const PAGE_SIZE: u32 = 5;
+
+/// `/report`, flags a card as inappropriate for the moderators to review.
+pub async fn command_report(cx: InteractionContext, data: CommandData) -> anyhow::Result<()> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+    let caller = cx
+        .member
+        .as_ref()
+        .and_then(|m| m.user.as_ref())
+        .ok_or_else(|| Error::msg("missing user in interaction"))?;
+
+    let string = |name: &str| {
+        data.options
+            .iter()
+            .find(|option| option.name == name)
+            .and_then(|option| match option.value {
+                CommandOptionValue::String(ref value) => Some(value.as_str()),
+                _ => None,
+            })
+            .ok_or_else(|| Error::msg("invalid command payload"))
+    };
+
+    let name = normalize_name(string("name")?);
+    let reason = string("reason")?;
+
+    // only cards the caller can see may be reported
+    let card = cx
+        .db_client
+        .proxy_for(caller)
+        .list_cards(guild_id)
+        .find(&name)
+        .execute()
+        .await
+        .context("failed to fetch card")?
+        .into_iter()
+        // only find exact matches
+        .find(|card| card.name == name);
+
+    let Some(card) = card else {
+        tracing::debug!("/report: failed to find card w/ name `{}`", name);
+        show_not_found(&cx, &name).await?;
+
+        return Ok(());
+    };
+
+    let report = cx
+        .db_client
+        .proxy_for(caller)
+        .create_report(guild_id, card.id, reason)
+        .execute()
+        .await
+        .context("failed to report card")?;
+
+    let message = format!(
+        "Thanks, card `{}` was reported to the moderators.",
+        report.card_name
+    );
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .flags(MessageFlags::EPHEMERAL)
+                        .content(message)
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// `/reports`, lists the open reports waiting for review.
+pub async fn command_reports(cx: InteractionContext, _data: CommandData) -> anyhow::Result<()> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+
+    let container = display_reports(&cx, guild_id, 1).await?;
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .components(iter::once(Component::Container(container)))
+                        .flags(MessageFlags::EPHEMERAL | MessageFlags::IS_COMPONENTS_V2)
+                        .allowed_mentions(AllowedMentions::default())
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// The page buttons of `/reports`, turns the page of the reports.
+pub async fn component_report_page(cx: InteractionContext, page: &str) -> anyhow::Result<()> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+
+    let page = page.parse::<u32>().context("malformed report page")?;
+    let container = display_reports(&cx, guild_id, page).await?;
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::UpdateMessage,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .components(iter::once(Component::Container(container)))
+                        .flags(MessageFlags::IS_COMPONENTS_V2)
+                        .allowed_mentions(AllowedMentions::default())
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// The resolve and dismiss buttons of `/reports`, closes a report.
+pub async fn component_resolve_report(cx: InteractionContext, id: &str) -> anyhow::Result<()> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+
+    let mut parts = id.split(':');
+
+    let (id, status) = parts
+        .next()
+        .zip(parts.next())
+        .and_then(|(id, status)| Some((id.parse::<i32>().ok()?, status.parse().ok()?)))
+        .context("malformed report id")?;
+    // buttons sent before the reports had pages have no page
+    let page = match parts.next() {
+        Some(page) => page.parse::<u32>().context("malformed report id")?,
+        None => 1,
+    };
+
+    match cx
+        .db_client
+        .resolve_report(guild_id, id, status)
+        .execute()
+        .await
+    {
+        Ok(_) => (),
+        // someone else got to the report first
+        Err(err)
+            if err
+                .downcast_ref::<ApiError>()
+                .is_some_and(|err| matches!(err.code, ErrorCode::ReportClosed)) => {}
+        Err(err) => return Err(err),
+    }
+
+    let container = display_reports(&cx, guild_id, page).await?;
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::UpdateMessage,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .components(iter::once(Component::Container(container)))
+                        .flags(MessageFlags::IS_COMPONENTS_V2)
+                        .allowed_mentions(AllowedMentions::default())
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Creates a container showing a page of the open reports, oldest first, with
+/// buttons to close each of them.
+async fn display_reports(
+    cx: &InteractionContext,
+    guild_id: Id<GuildMarker>,
+    page: u32,
+) -> anyhow::Result<Container> {
+    let list = |page: u32| {
+        cx.db_client
+            .list_reports(guild_id)
+            .status(ReportStatus::Open)
+            .page(page)
+            .count(PAGE_SIZE)
+            .execute()
+    };
+
+    let reports = match list(page.max(1)).await {
+        Ok(reports) => reports,
+        // reports closed since may have emptied the page, so the last page is
+        // shown instead
+        Err(err)
+            if err
+                .downcast_ref::<ApiError>()
+                .is_some_and(|err| err.code == ErrorCode::InvalidData) =>
+        {
+            let reports = list(1).await.context("failed to fetch reports")?;
+
+            if reports.total_pages > 1 {
+                list(reports.total_pages)
+                    .await
+                    .context("failed to fetch reports")?
+            } else {
+                reports
+            }
+        }
+        Err(err) => return Err(err.context("failed to fetch reports")),
+    };
+
+    let mut header = String::from("## Open reports");
+
+    if reports.items.is_empty() {
+        header.push_str("\nThere is nothing to review.");
+    }
+
+    let mut container = ContainerBuilder::new()
+        .accent_color(Some(cx.config.general.embed_color))
+        .spoiler(false)
+        .component(TextDisplayBuilder::new(header).build())
+        .build();
+
+    let (page, total_pages) = (reports.page, reports.total_pages);
+
+    for report in reports {
+        let body = format!(
+            "**`{}`** reported by {} <t:{}:R>\n> {}",
+            report.card_name,
+            report.reporter.display_name,
+            report.created_at.and_utc().timestamp(),
+            report.reason.replace('\n', "\n> "),
+        );
+
+        let action_row = ActionRow {
+            id: None,
+            components: vec![
+                ButtonBuilder::new(ButtonStyle::Danger)
+                    .custom_id(format!(
+                        "report:{}:{}:{}",
+                        report.id,
+                        ReportStatus::Resolved.to_str(),
+                        page
+                    ))
+                    .label("Resolve")
+                    .build()
+                    .into(),
+                ButtonBuilder::new(ButtonStyle::Secondary)
+                    .custom_id(format!(
+                        "report:{}:{}:{}",
+                        report.id,
+                        ReportStatus::Dismissed.to_str(),
+                        page
+                    ))
+                    .label("Dismiss")
+                    .build()
+                    .into(),
+            ],
+        };
+
+        container.components.push(Component::TextDisplay(
+            TextDisplayBuilder::new(body).build(),
+        ));
+        container.components.push(Component::ActionRow(action_row));
+    }
+
+    if total_pages > 1 {
+        container.components.push(Component::TextDisplay(
+            TextDisplayBuilder::new(format!("-# Page {} of {}", page, total_pages)).build(),
+        ));
+        container
+            .components
+            .push(page_buttons(page, total_pages, |page| {
+                format!("reports:{}", page)
+            }));
+    }
+
+    Ok(container)
+}
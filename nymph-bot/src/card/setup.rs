@@ -0,0 +1,179 @@
+//! Guild setup from card templates.
+//!
+//! See [`command_setup`].
+
+use anyhow::Error;
+
+use nymph_model::{response::card::ImportStatus, template::Template};
+
+use twilight_model::{
+    application::{
+        command::{CommandOptionChoice, CommandOptionChoiceValue, CommandOptionType},
+        interaction::application_command::{CommandData, CommandOptionValue},
+    },
+    channel::message::MessageFlags,
+    http::interaction::{InteractionResponse, InteractionResponseType},
+};
+
+use twilight_util::builder::InteractionResponseDataBuilder;
+
+use crate::commands::InteractionContext;
+
+/// The most choices an autocomplete response can hold.
+const MAX_CHOICES: usize = 25;
+
+/// `/setup`, copies a card template into the guild as a starter deck.
+///
+/// Without a template, lists the templates to choose from.
+pub async fn command_setup(cx: InteractionContext, data: CommandData) -> anyhow::Result<()> {
+    let guild_id = cx
+        .guild_id
+        .ok_or_else(|| Error::msg("missing guild id in interaction"))?;
+
+    let name = data
+        .options
+        .iter()
+        .find(|option| option.name == "template")
+        .and_then(|option| match option.value {
+            CommandOptionValue::String(ref value) => Some(value.trim()),
+            _ => None,
+        });
+
+    let templates = cx.db_client.list_templates().execute().await?;
+
+    let template = name.and_then(|name| {
+        templates
+            .iter()
+            .find(|template| template.name.eq_ignore_ascii_case(name))
+    });
+
+    let message = match (name, template) {
+        (_, Some(template)) => {
+            let res = cx
+                .db_client
+                .instantiate_template(guild_id, template.id)
+                .execute()
+                .await?;
+
+            let count = |status| res.rows.iter().filter(|row| row.status == status).count();
+
+            tracing::debug!(
+                template = template.name,
+                "/setup: copied {} cards",
+                res.imported
+            );
+
+            let mut message = match res.imported {
+                1 => format!("Copied 1 card from **{}**.", template.name),
+                n => format!("Copied {} cards from **{}**.", n, template.name),
+            };
+
+            match count(ImportStatus::Skipped) {
+                0 => (),
+                1 => message.push_str("\n1 card was skipped, since it already exists."),
+                n => message.push_str(&format!(
+                    "\n{} cards were skipped, since they already exist.",
+                    n
+                )),
+            }
+
+            match count(ImportStatus::Failed) {
+                0 => (),
+                1 => message.push_str("\n1 card could not be copied."),
+                n => message.push_str(&format!("\n{} cards could not be copied.", n)),
+            }
+
+            message
+        }
+        (_, None) if templates.is_empty() => String::from("No card templates are available."),
+        (name, None) => {
+            let mut message = match name {
+                Some(name) => format!("There is no template named `{}`. ", name),
+                None => String::new(),
+            };
+
+            message.push_str("Pick one of these templates:");
+
+            for template in templates.iter() {
+                message.push_str(&format!("\n- {}", describe(template)));
+            }
+
+            message
+        }
+    };
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::ChannelMessageWithSource,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .flags(MessageFlags::EPHEMERAL)
+                        .content(message)
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Autocompletes the template option of `/setup`.
+pub async fn autocomplete_setup(cx: &InteractionContext, data: CommandData) -> anyhow::Result<()> {
+    let name = data
+        .options
+        .iter()
+        .find_map(|option| match option.value {
+            CommandOptionValue::Focused(ref value, CommandOptionType::String) => Some(value),
+            _ => None,
+        })
+        .ok_or_else(|| Error::msg("invalid command payload"))?
+        .to_lowercase();
+
+    let choices = cx
+        .db_client
+        .list_templates()
+        .execute()
+        .await?
+        .into_iter()
+        .filter(|template| template.name.to_lowercase().contains(&name))
+        .take(MAX_CHOICES)
+        .map(|template| CommandOptionChoice {
+            name_localizations: None,
+            value: CommandOptionChoiceValue::String(template.name.clone()),
+            name: template.name,
+        });
+
+    cx.client
+        .interaction(cx.application_id)
+        .create_response(
+            cx.id,
+            &cx.token,
+            &InteractionResponse {
+                kind: InteractionResponseType::ApplicationCommandAutocompleteResult,
+                data: Some(
+                    InteractionResponseDataBuilder::new()
+                        .choices(choices)
+                        .build(),
+                ),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+fn describe(template: &Template) -> String {
+    let mut line = format!("**{}** ({} cards)", template.name, template.cards);
+
+    if let Some(description) = template.description.as_ref() {
+        line.push_str(": ");
+        line.push_str(description);
+    }
+
+    line
+}
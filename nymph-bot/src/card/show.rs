@@ -4,7 +4,11 @@
 
 use std::iter;
 
-use nymph_model::{ApiError, ErrorCode, card::Card};
+use nymph_model::{
+    ApiError, ErrorCode,
+    card::{Card, normalize_name},
+    request::card::Expand,
+};
 
 use twilight_util::builder::InteractionResponseDataBuilder;
 
@@ -35,17 +39,33 @@ pub async fn command_show(cx: InteractionContext, data: CommandData) -> anyhow::
             _ => None,
         })
         .ok_or_else(|| Error::msg("invalid command payload"))?;
-    let name = name.to_ascii_uppercase();
-
-    let card = cx
-        .db_client
-        .list_cards(guild_id)
-        .find(&name)
-        .execute()
-        .await?
-        .into_iter()
-        // only find exact matches
-        .find(|card| card.name == name);
+    let card = if is_emoji(name) {
+        let name = name.trim();
+
+        match cx.db_client.lookup_card(guild_id, name).execute().await {
+            Ok(card) => Some(card),
+            Err(err)
+                if err
+                    .downcast_ref::<ApiError>()
+                    .is_some_and(|err| err.code == ErrorCode::NotFound) =>
+            {
+                None
+            }
+            Err(err) => return Err(err),
+        }
+    } else {
+        let name = normalize_name(name);
+
+        cx.db_client
+            .list_cards(guild_id)
+            .find(&name)
+            .execute()
+            .await?
+            .into_iter()
+            // only find exact matches
+            .find(|card| card.name == name)
+    };
+    let name = normalize_name(name);
 
     let Some(Card { id, .. }) = card else {
         // confidently say no card exists
@@ -85,6 +105,17 @@ pub async fn command_show(cx: InteractionContext, data: CommandData) -> anyhow::
     }
 }
 
+/// Checks if a card name given to `/s` is actually an emoji.
+///
+/// Custom emojis are sent as their markup, and card names always have a
+/// letter or digit in them, which Unicode emojis never do.
+fn is_emoji(name: &str) -> bool {
+    let name = name.trim();
+
+    (name.starts_with('<') && name.ends_with('>'))
+        || (!name.is_empty() && !name.chars().any(|c| c.is_ascii_alphanumeric()))
+}
+
 /// Creates an [`InteractionResponse`] for showing a card
 ///
 /// By default, `kind` is
@@ -104,6 +135,7 @@ pub async fn show_card(cx: &InteractionContext, id: i32) -> anyhow::Result<Inter
         .db_client
         .proxy_for(&caller)
         .get_card(guild_id, id)
+        .expand([Expand::Upgrades, Expand::Downgrade])
         .execute()
         .await?;
 
@@ -16,9 +16,9 @@ use twilight_model::{
     oauth::ApplicationIntegrationType,
 };
 
-use twilight_util::builder::command::{CommandBuilder, StringBuilder, UserBuilder};
+use twilight_util::builder::command::{CommandBuilder, IntegerBuilder, StringBuilder, UserBuilder};
 
-use crate::{config::Config, http::Client as DbClient};
+use crate::{card::RecentCards, config::Config, followup::Followups, http::Client as DbClient};
 
 use derive_more::Deref;
 
@@ -36,11 +36,15 @@ pub struct InteractionContext {
     pub db_client: DbClient,
     pub cache: Arc<InMemoryCache>,
     pub config: Arc<Config>,
+    /// Card names recently suggested to users, for slow autocompletes.
+    pub recent_cards: RecentCards,
+    /// Follow-ups scheduled to be sent later.
+    pub followups: Followups,
     pub application_id: Id<ApplicationMarker>,
 }
 
 /// Returns a list of commands the bot offers.
-pub fn commands() -> [Command; 5] {
+pub fn commands() -> [Command; 13] {
     [
         CommandBuilder::new(
             "s",
@@ -77,6 +81,22 @@ pub fn commands() -> [Command; 5] {
         .integration_types([ApplicationIntegrationType::GuildInstall])
         .contexts([InteractionContextType::Guild])
         .build(),
+        CommandBuilder::new(
+            "progress",
+            "Displays how much of the card set you have collected",
+            CommandType::ChatInput,
+        )
+        .integration_types([ApplicationIntegrationType::GuildInstall])
+        .contexts([InteractionContextType::Guild])
+        .build(),
+        CommandBuilder::new(
+            "leaderboard",
+            "Displays the most viewed cards this week",
+            CommandType::ChatInput,
+        )
+        .integration_types([ApplicationIntegrationType::GuildInstall])
+        .contexts([InteractionContextType::Guild])
+        .build(),
         CommandBuilder::new(
             "grant",
             "Grants a card to a member, allowing them to view it with /s",
@@ -107,5 +127,109 @@ pub fn commands() -> [Command; 5] {
                 .required(true),
         )
         .build(),
+        CommandBuilder::new(
+            "gift",
+            "Gives one of your cards to another member",
+            CommandType::ChatInput,
+        )
+        .integration_types([ApplicationIntegrationType::GuildInstall])
+        .contexts([InteractionContextType::Guild])
+        .option(UserBuilder::new("user", "The member to give the card to").required(true))
+        .option(
+            StringBuilder::new("name", "The name of the card")
+                .autocomplete(true)
+                .required(true),
+        )
+        .build(),
+        CommandBuilder::new(
+            "whohas",
+            "Lists the members that own a card",
+            CommandType::ChatInput,
+        )
+        .integration_types([ApplicationIntegrationType::GuildInstall])
+        .contexts([InteractionContextType::Guild])
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .option(
+            StringBuilder::new("name", "The name of the card")
+                .autocomplete(true)
+                .required(true),
+        )
+        .build(),
+        CommandBuilder::new(
+            "archive",
+            "Archives cards in bulk, retiring them from circulation",
+            CommandType::ChatInput,
+        )
+        .integration_types([ApplicationIntegrationType::GuildInstall])
+        .contexts([InteractionContextType::Guild])
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .option(StringBuilder::new(
+            "category",
+            "Archive cards in this category",
+        ))
+        .option(StringBuilder::new(
+            "before",
+            "Archive cards created before this date (YYYY-MM-DD)",
+        ))
+        .build(),
+        CommandBuilder::new(
+            "audit",
+            "Lists recent card grants, revokes and edits",
+            CommandType::ChatInput,
+        )
+        .integration_types([ApplicationIntegrationType::GuildInstall])
+        .contexts([InteractionContextType::Guild])
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .option(UserBuilder::new(
+            "user",
+            "Only list entries involving this member",
+        ))
+        .option(StringBuilder::new("card", "Only list entries about this card").autocomplete(true))
+        .option(
+            IntegerBuilder::new("limit", "How many entries to list")
+                .min_value(1)
+                .max_value(100),
+        )
+        .build(),
+        CommandBuilder::new(
+            "report",
+            "Reports a card as inappropriate to the moderators",
+            CommandType::ChatInput,
+        )
+        .integration_types([ApplicationIntegrationType::GuildInstall])
+        .contexts([InteractionContextType::Guild])
+        .option(
+            StringBuilder::new("name", "The name of the card")
+                .autocomplete(true)
+                .required(true),
+        )
+        .option(
+            StringBuilder::new("reason", "Why the card is inappropriate")
+                .max_length(1000)
+                .required(true),
+        )
+        .build(),
+        CommandBuilder::new(
+            "reports",
+            "Lists the reported cards waiting for review",
+            CommandType::ChatInput,
+        )
+        .integration_types([ApplicationIntegrationType::GuildInstall])
+        .contexts([InteractionContextType::Guild])
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .build(),
+        CommandBuilder::new(
+            "setup",
+            "Sets up the server's cards from a starter deck",
+            CommandType::ChatInput,
+        )
+        .integration_types([ApplicationIntegrationType::GuildInstall])
+        .contexts([InteractionContextType::Guild])
+        .default_member_permissions(Permissions::MANAGE_GUILD)
+        .option(
+            StringBuilder::new("template", "The starter deck to copy cards from")
+                .autocomplete(true),
+        )
+        .build(),
     ]
 }
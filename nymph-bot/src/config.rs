@@ -1,6 +1,10 @@
 //! Bot configuration.
 
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
 
 use figment::{
     Figment,
@@ -21,6 +25,33 @@ pub struct Config {
     /// Contains set information.
     #[serde(default)]
     pub category: HashMap<String, CategoryConfig>,
+    /// Contains rarity tier information.
+    #[serde(default)]
+    pub rarity: HashMap<String, RarityConfig>,
+    /// Reward notification configuration.
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    /// Display name backfill configuration.
+    #[serde(default)]
+    pub backfill: BackfillConfig,
+    /// Guild telemetry configuration.
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    /// Metrics endpoint configuration.
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    /// Autocomplete configuration.
+    #[serde(default)]
+    pub autocomplete: AutocompleteConfig,
+    /// Follow-up scheduler configuration.
+    #[serde(default)]
+    pub followup: FollowupConfig,
+    /// Tracing filter directives, like `info,nymph_bot=debug`.
+    ///
+    /// Overrides `RUST_LOG` when set. Re-read when the bot receives
+    /// `SIGUSR1`.
+    #[serde(default)]
+    pub log: Option<String>,
 }
 
 impl Config {
@@ -62,12 +93,199 @@ pub struct ApiConfig {
     /// How many times the bot should refresh.
     #[serde(default = "token_refresh_retries_default")]
     pub token_refresh_retries: u32,
+    /// The secret proxy assertions are signed with.
+    ///
+    /// If set, the bot proxies for users with signed assertions instead of
+    /// their access tokens. This must match the server's `proxy_secret`.
+    #[serde(default)]
+    pub proxy_secret: Option<String>,
+    /// A PEM file with a client certificate and its private key.
+    ///
+    /// If set, the certificate is presented to servers that accept client
+    /// certificates, and authenticates the bot in place of its API key.
+    #[serde(default)]
+    pub identity: Option<PathBuf>,
+    /// When to warn about a route failing often.
+    #[serde(default)]
+    pub error_rate: ErrorRateConfig,
 }
 
 fn token_refresh_retries_default() -> u32 {
     5
 }
 
+/// Route error rate alert config.
+///
+/// See [`crate::http::metrics`].
+#[derive(Deserialize, Debug, Clone)]
+pub struct ErrorRateConfig {
+    /// The error rate, from `0` to `1`, a route is warned about at.
+    #[serde(default = "error_rate_threshold_default")]
+    pub threshold: f64,
+    /// How many of a route's latest requests the error rate is taken over.
+    #[serde(default = "error_rate_window_default")]
+    pub window: usize,
+    /// How many requests a route must have made before it is warned about.
+    #[serde(default = "error_rate_min_requests_default")]
+    pub min_requests: usize,
+}
+
+impl Default for ErrorRateConfig {
+    fn default() -> Self {
+        ErrorRateConfig {
+            threshold: error_rate_threshold_default(),
+            window: error_rate_window_default(),
+            min_requests: error_rate_min_requests_default(),
+        }
+    }
+}
+
+fn error_rate_threshold_default() -> f64 {
+    0.25
+}
+
+fn error_rate_window_default() -> usize {
+    100
+}
+
+fn error_rate_min_requests_default() -> usize {
+    20
+}
+
+/// Reward notification config.
+#[derive(Deserialize, Debug, Clone)]
+pub struct NotifyConfig {
+    /// How often each guild's events are checked for rewards, in seconds.
+    #[serde(default = "notify_interval_default")]
+    pub interval: u64,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        NotifyConfig {
+            interval: notify_interval_default(),
+        }
+    }
+}
+
+fn notify_interval_default() -> u64 {
+    30
+}
+
+/// Display name backfill config.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BackfillConfig {
+    /// How often stale display names are refreshed, in seconds.
+    #[serde(default = "backfill_interval_default")]
+    pub interval: u64,
+    /// How old a display name may get before it is refreshed, in hours.
+    #[serde(default = "backfill_max_age_default")]
+    pub max_age: u32,
+}
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        BackfillConfig {
+            interval: backfill_interval_default(),
+            max_age: backfill_max_age_default(),
+        }
+    }
+}
+
+fn backfill_interval_default() -> u64 {
+    60 * 60
+}
+
+fn backfill_max_age_default() -> u32 {
+    24
+}
+
+/// Guild telemetry config.
+///
+/// See [`crate::telemetry`].
+#[derive(Deserialize, Debug, Clone)]
+pub struct TelemetryConfig {
+    /// Whether the guilds the bot serves are reported to the server.
+    #[serde(default)]
+    pub enabled: bool,
+    /// How often the guilds are reported, in seconds.
+    #[serde(default = "telemetry_interval_default")]
+    pub interval: u64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        TelemetryConfig {
+            enabled: false,
+            interval: telemetry_interval_default(),
+        }
+    }
+}
+
+fn telemetry_interval_default() -> u64 {
+    60 * 60
+}
+
+/// Metrics endpoint config.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct MetricsConfig {
+    /// The address metrics are served on at `/metrics`, like
+    /// `127.0.0.1:9100`.
+    ///
+    /// Metrics are not served if this is not set.
+    #[serde(default)]
+    pub bind: Option<SocketAddr>,
+}
+
+/// Autocomplete config.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AutocompleteConfig {
+    /// How long the API is waited on for suggestions, in milliseconds.
+    ///
+    /// Discord drops autocomplete responses after 3 seconds, so recently
+    /// suggested cards are used instead once this runs out.
+    #[serde(default = "autocomplete_budget_default")]
+    pub budget: u64,
+}
+
+impl Default for AutocompleteConfig {
+    fn default() -> Self {
+        AutocompleteConfig {
+            budget: autocomplete_budget_default(),
+        }
+    }
+}
+
+fn autocomplete_budget_default() -> u64 {
+    2_000
+}
+
+/// Follow-up scheduler config.
+///
+/// See [`crate::followup`].
+#[derive(Deserialize, Debug, Clone)]
+pub struct FollowupConfig {
+    /// The file pending follow-ups are kept in while the bot is down.
+    ///
+    /// The file holds interaction tokens, which let anyone answer those
+    /// interactions as the bot for a few minutes. It is only made readable
+    /// by the user the bot runs as, but keep it out of shared directories.
+    #[serde(default = "followup_path_default")]
+    pub path: PathBuf,
+}
+
+impl Default for FollowupConfig {
+    fn default() -> Self {
+        FollowupConfig {
+            path: followup_path_default(),
+        }
+    }
+}
+
+fn followup_path_default() -> PathBuf {
+    PathBuf::from("nymph-bot-followups.json")
+}
+
 /// Configuration for accent text that appears in certain states or actions.
 #[derive(Deserialize, Debug, Clone)]
 pub struct AccentTextConfig {
@@ -131,6 +349,29 @@ impl CategoryConfig {
     }
 }
 
+/// Describes a rarity tier.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RarityConfig {
+    /// Added to the beginning of the card's title, before any category
+    /// decorations.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Overrides the embed color, if the card's category does not.
+    #[serde(deserialize_with = "deser_hex_color_optional")]
+    #[serde(default)]
+    pub color: Option<u32>,
+}
+
+impl RarityConfig {
+    /// Formats an already formatted title of a card with this rarity.
+    pub fn format_title(&self, title: impl AsRef<str>) -> String {
+        match self.prefix.as_ref() {
+            Some(prefix) => format!("{} {}", prefix, title.as_ref()),
+            None => title.as_ref().to_owned(),
+        }
+    }
+}
+
 fn deser_hex_color<'de, D>(deser: D) -> Result<u32, D::Error>
 where
     D: Deserializer<'de>,
@@ -2,8 +2,12 @@
 
 use tracing::instrument;
 
+use anyhow::Context as _;
+
+use nymph_model::card::normalize_name;
+
 use twilight_model::application::interaction::{
-    Interaction, InteractionData, InteractionType, application_command::CommandData,
+    InteractionData, InteractionType, application_command::CommandData,
     message_component::MessageComponentInteractionData,
 };
 
@@ -42,19 +46,19 @@ pub async fn interaction(mut cx: InteractionContext) {
                 }
             }
         }
-        /*
         InteractionType::MessageComponent => {
-            let data = interaction.0.data.take();
+            let data = cx.interaction.data.take();
             let Some(InteractionData::MessageComponent(data)) = data else {
                 tracing::error!("failed to get interaction payload");
                 return;
             };
 
-            if let Err(err) = message_component(cx, &interaction, data).await {
-                tracing::error!(?interaction, "{:?}", err);
+            if let Err(err) = message_component(cx, *data).await {
+                for err in err.chain() {
+                    tracing::error!("{:?}", err);
+                }
             }
         }
-        */
         // ignore other payloads
         _ => (),
     }
@@ -63,7 +67,18 @@ pub async fn interaction(mut cx: InteractionContext) {
 async fn slash_command(cx: InteractionContext, data: CommandData) -> anyhow::Result<()> {
     match data.name.as_str() {
         "s" => crate::card::command_show(cx, data).await?,
+        "sl" => crate::card::command_admin_card(cx, data).await?,
+        "inv" => crate::card::command_inventory(cx, data).await?,
+        "progress" => crate::card::command_progress(cx, data).await?,
+        "leaderboard" => crate::card::command_leaderboard(cx, data).await?,
         "grant" | "revoke" => crate::card::command_transfer_card(cx, data).await?,
+        "gift" => crate::card::command_gift(cx, data).await?,
+        "whohas" => crate::card::command_who_has(cx, data).await?,
+        "archive" => crate::card::command_archive(cx, data).await?,
+        "audit" => crate::card::command_audit(cx, data).await?,
+        "report" => crate::card::command_report(cx, data).await?,
+        "reports" => crate::card::command_reports(cx, data).await?,
+        "setup" => crate::card::command_setup(cx, data).await?,
         /*
                 "sl" => {
                     let name = data
@@ -75,7 +90,7 @@ async fn slash_command(cx: InteractionContext, data: CommandData) -> anyhow::Res
                             _ => None,
                         })
                         .ok_or_else(|| Error::msg("invalid command payload"))?;
-                    let name = name.to_uppercase();
+                    let name = normalize_name(name);
 
                     // fetch card from client
                     let card = cx
@@ -135,7 +150,7 @@ async fn slash_command(cx: InteractionContext, data: CommandData) -> anyhow::Res
                     _ => None,
                 })
                 .ok_or_else(|| Error::msg("invalid command payload"))?;
-            let name = name.to_uppercase();
+            let name = normalize_name(name);
 
             let target_user_id = data
                 .options
@@ -242,7 +257,7 @@ async fn slash_command(cx: InteractionContext, data: CommandData) -> anyhow::Res
                     _ => None,
                 })
                 .ok_or_else(|| Error::msg("invalid command payload"))?;
-            let name = name.to_uppercase();
+            let name = normalize_name(name);
 
             let target_user_id = data
                 .options
@@ -353,7 +368,10 @@ async fn slash_command(cx: InteractionContext, data: CommandData) -> anyhow::Res
 
 async fn autocomplete(cx: InteractionContext, data: CommandData) -> anyhow::Result<()> {
     match data.name.as_str() {
-        "s" => crate::card::autocomplete(&cx, data).await?,
+        "s" | "sl" | "gift" | "whohas" | "audit" | "report" => {
+            crate::card::autocomplete(&cx, data).await?
+        }
+        "setup" => crate::card::autocomplete_setup(&cx, data).await?,
         _ => tracing::warn!(?cx.interaction, "unknown interaction"),
     }
 
@@ -362,11 +380,31 @@ async fn autocomplete(cx: InteractionContext, data: CommandData) -> anyhow::Resu
 
 async fn message_component(
     cx: InteractionContext,
-    interaction: &Interaction,
-    data: Box<MessageComponentInteractionData>,
+    data: MessageComponentInteractionData,
 ) -> anyhow::Result<()> {
-    // Currently, the only interactable components this bot attaches are simple
-    // buttons that show cards.
+    match data.custom_id.split_once(':') {
+        Some(("grant_card", card_id)) => {
+            let card_id = card_id.parse::<i32>().context("malformed card id")?;
+            crate::card::component_grant_card(cx, card_id, data).await?
+        }
+        Some(("prerequisite_cards", card_id)) => {
+            let card_id = card_id.parse::<i32>().context("malformed card id")?;
+            crate::card::component_set_prerequisites(cx, "cards", card_id, data).await?
+        }
+        Some(("prerequisite_categories", card_id)) => {
+            let card_id = card_id.parse::<i32>().context("malformed card id")?;
+            crate::card::component_set_prerequisites(cx, "categories", card_id, data).await?
+        }
+        Some(("audit", page)) => crate::card::component_audit_page(cx, page).await?,
+        Some(("inv", page)) => crate::card::component_inventory_page(cx, page).await?,
+        Some(("inv_export", format)) => crate::card::component_inventory_export(cx, format).await?,
+        Some(("report", id)) => crate::card::component_resolve_report(cx, id).await?,
+        Some(("reports", page)) => crate::card::component_report_page(cx, page).await?,
+        Some(("trade_in", id)) => crate::card::component_trade_in(cx, id).await?,
+        _ => tracing::debug!(custom_id = %data.custom_id, "unhandled message component"),
+    }
+
+    // TODO: port the remaining components to the API client
     /*
     let custom_id = data.custom_id.as_str();
 
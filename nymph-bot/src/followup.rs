@@ -0,0 +1,296 @@
+//! Delayed interaction follow-ups.
+//!
+//! Some flows answer an interaction again later, like warning that an offer
+//! is about to expire. [`Followups`] keeps the interaction's token until the
+//! follow-up is due, then sends it. Discord only accepts a token for 15
+//! minutes after its interaction, so follow-ups must be due before then.
+//! Pending follow-ups are kept in a file, so they are still sent if the bot
+//! restarts in between, as long as their token has not expired by then.
+//!
+//! Anyone holding a pending follow-up's token may answer its interaction as
+//! the bot until the token expires, so the file is only readable by the user
+//! the bot runs as.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context as _, Error};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use serde::{Deserialize, Serialize};
+
+use tokio::io::AsyncWriteExt as _;
+use tokio::sync::Mutex;
+use tokio::time::{MissedTickBehavior, interval};
+
+use twilight_http::Client;
+use twilight_model::{
+    application::interaction::Interaction,
+    channel::message::{Component, MessageFlags},
+    id::{
+        Id,
+        marker::{ApplicationMarker, InteractionMarker},
+    },
+};
+use twilight_util::builder::message::TextDisplayBuilder;
+
+/// How long an interaction token is accepted for.
+const TOKEN_LIFETIME: TimeDelta = TimeDelta::minutes(15);
+
+/// How long before its token expires a follow-up must be due, so it still has
+/// time to be sent.
+const TOKEN_MARGIN: TimeDelta = TimeDelta::seconds(30);
+
+/// How often due follow-ups are checked for.
+const TICK: Duration = Duration::from_secs(1);
+
+/// The first second of 2015, which Discord snowflakes count from, in
+/// milliseconds.
+const DISCORD_EPOCH: i64 = 1_420_070_400_000;
+
+/// A follow-up waiting to be sent.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Followup {
+    /// The token of the interaction followed up on.
+    pub token: String,
+    /// When the interaction was created.
+    pub created_at: DateTime<Utc>,
+    /// When the follow-up is sent.
+    pub due_at: DateTime<Utc>,
+    /// What is sent.
+    pub action: FollowupAction,
+}
+
+impl Followup {
+    /// Checks if the interaction's token has expired.
+    pub fn expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.created_at + TOKEN_LIFETIME
+    }
+}
+
+/// What a follow-up sends.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum FollowupAction {
+    /// Sends a new message.
+    Send { content: String, ephemeral: bool },
+    /// Edits the interaction's original response.
+    Edit { content: String },
+    /// Replaces the components of the interaction's original response with
+    /// a note, for responses sent as components.
+    Replace { content: String },
+}
+
+/// Schedules follow-ups of interactions.
+///
+/// Cheaply cloneable.
+#[derive(Clone, Debug)]
+pub struct Followups {
+    client: Arc<Client>,
+    application_id: Id<ApplicationMarker>,
+    path: Arc<PathBuf>,
+    pending: Arc<Mutex<Vec<Followup>>>,
+}
+
+impl Followups {
+    /// Creates a new `Followups`, keeping pending follow-ups in the file at
+    /// `path`.
+    pub fn new(
+        client: Arc<Client>,
+        application_id: Id<ApplicationMarker>,
+        path: PathBuf,
+    ) -> Followups {
+        Followups {
+            client,
+            application_id,
+            path: Arc::new(path),
+            pending: Arc::default(),
+        }
+    }
+
+    /// Picks back up the follow-ups pending when the bot last stopped.
+    ///
+    /// Follow-ups whose token expired in the meantime are dropped.
+    pub async fn load(&self) -> anyhow::Result<()> {
+        let bytes = match tokio::fs::read(&*self.path).await {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(Error::from(err).context("failed to read follow-ups")),
+        };
+
+        let now = Utc::now();
+        let followups =
+            serde_json::from_slice::<Vec<Followup>>(&bytes).context("failed to read follow-ups")?;
+        let total = followups.len();
+
+        let mut pending = self.pending.lock().await;
+        pending.extend(followups.into_iter().filter(|f| !f.expired(now)));
+
+        if pending.len() < total {
+            tracing::warn!(
+                dropped = total - pending.len(),
+                "dropped follow-ups whose token expired while the bot was down"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Schedules a follow-up of an interaction, sent after `delay`.
+    ///
+    /// Fails if the follow-up would be due too close to when the
+    /// interaction's token expires.
+    pub async fn schedule(
+        &self,
+        interaction: &Interaction,
+        delay: Duration,
+        action: FollowupAction,
+    ) -> anyhow::Result<()> {
+        let created_at = created_at(interaction.id);
+        let due_at = Utc::now() + TimeDelta::from_std(delay)?;
+
+        if due_at > created_at + TOKEN_LIFETIME - TOKEN_MARGIN {
+            return Err(Error::msg(
+                "follow-up would be due after the interaction token expires",
+            ));
+        }
+
+        let mut pending = self.pending.lock().await;
+
+        pending.push(Followup {
+            token: interaction.token.clone(),
+            created_at,
+            due_at,
+            action,
+        });
+
+        // a follow-up that was not saved is not scheduled either
+        if let Err(err) = self.save(&pending).await {
+            pending.pop();
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Sends follow-ups as they come due, forever.
+    pub async fn run(self) {
+        let mut interval = interval(TICK);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            let now = Utc::now();
+            let due = {
+                let mut pending = self.pending.lock().await;
+
+                let (due, rest) = pending
+                    .drain(..)
+                    .partition::<Vec<_>, _>(|followup| followup.due_at <= now);
+                *pending = rest;
+
+                if !due.is_empty()
+                    && let Err(err) = self.save(&pending).await
+                {
+                    tracing::error!(?err, "failed to save follow-ups");
+                }
+
+                due
+            };
+
+            for followup in due {
+                if followup.expired(now) {
+                    tracing::warn!(due_at = %followup.due_at, "follow-up token expired");
+                    continue;
+                }
+
+                if let Err(err) = self.send(&followup.token, followup.action).await {
+                    tracing::warn!(?err, "failed to send follow-up");
+                }
+            }
+        }
+    }
+
+    /// Sends a follow-up.
+    async fn send(&self, token: &str, action: FollowupAction) -> anyhow::Result<()> {
+        let interaction = self.client.interaction(self.application_id);
+
+        match action {
+            FollowupAction::Send { content, ephemeral } => {
+                let flags = if ephemeral {
+                    MessageFlags::EPHEMERAL
+                } else {
+                    MessageFlags::empty()
+                };
+
+                interaction
+                    .create_followup(token)
+                    .content(&content)
+                    .flags(flags)
+                    .await?;
+            }
+            FollowupAction::Edit { content } => {
+                interaction
+                    .update_response(token)
+                    .content(Some(&content))
+                    .await?;
+            }
+            FollowupAction::Replace { content } => {
+                let components = [Component::TextDisplay(
+                    TextDisplayBuilder::new(content).build(),
+                )];
+
+                interaction
+                    .update_response(token)
+                    .components(Some(&components))
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes pending follow-ups to their file.
+    async fn save(&self, pending: &[Followup]) -> anyhow::Result<()> {
+        let bytes = serde_json::to_vec(pending)?;
+
+        // replacing the file whole keeps a crash from leaving it half written
+        let tmp = self.path.with_extension("tmp");
+        let mut file = tokio::fs::File::create(&tmp)
+            .await
+            .context("failed to write follow-ups")?;
+
+        // set before anything is written, in case the file was left behind
+        // with wider permissions
+        #[cfg(unix)]
+        {
+            use std::{fs::Permissions, os::unix::fs::PermissionsExt as _};
+
+            file.set_permissions(Permissions::from_mode(0o600))
+                .await
+                .context("failed to write follow-ups")?;
+        }
+
+        file.write_all(&bytes)
+            .await
+            .context("failed to write follow-ups")?;
+        file.flush().await.context("failed to write follow-ups")?;
+        drop(file);
+
+        tokio::fs::rename(&tmp, &*self.path)
+            .await
+            .context("failed to write follow-ups")?;
+
+        Ok(())
+    }
+}
+
+/// Finds when an interaction was created from its snowflake.
+fn created_at(id: Id<InteractionMarker>) -> DateTime<Utc> {
+    let millis = (id.get() >> 22) as i64 + DISCORD_EPOCH;
+
+    DateTime::from_timestamp_millis(millis).unwrap_or_else(Utc::now)
+}
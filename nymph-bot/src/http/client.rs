@@ -1,34 +1,76 @@
 //! Nymph API client.
 
-use super::request::user::UpdateDiscordUser;
+use super::request::user::{
+    BatchUpdateDiscordUsers, GetProgress, ListStaleDiscordUsers, UpdateDiscordUser,
+};
 
 use anyhow::Error;
 
+use base16::encode_lower;
+
+use chrono::Utc;
+
+use hmac::{Hmac, Mac as _};
+
 use std::num::NonZeroU64;
 use std::sync::Arc;
+use std::time::Instant;
 
 use derive_more::{Deref, Display, Error};
 
 use crate::config::ApiConfig;
 
-use crate::http::request::card::inventory::{GrantCard, RevokeCard};
-use crate::http::request::card::{GetCard, ListCards};
+use crate::http::metrics::{Metrics, Route};
+
+use crate::http::request::audit::GetAuditLog;
+use crate::http::request::card::inventory::{
+    ExportInventory, GetTradeInRules, GrantCard, ListCardOwners, ListInventory, RevokeCard,
+    TradeIn, TransferCard,
+};
+use crate::http::request::card::{
+    ArchiveCards, GetCard, GetGrantPolicy, GetPrerequisites, ListCards, LookupCard, PopularCards,
+    UpdatePrerequisites,
+};
+use crate::http::request::report::{CreateReport, ListReports, ResolveReport};
+use crate::http::request::telemetry::{DepartGuild, ReportGuilds};
+use crate::http::request::template::{InstantiateTemplate, ListTemplates};
+use crate::http::request::view::{CreateView, GetView};
+use crate::http::request::webhook::ReplayEvents;
 
 use moka::future::Cache;
 
 use http::{HeaderName, HeaderValue, Method, header};
 
 use nymph_model::{
-    ApiError, ErrorCode, response::user::UpdateDiscordUserResponse, user::User as DbUser,
+    ApiError, ErrorCode,
+    card::Prerequisites,
+    error::REQUEST_ID_HEADER,
+    proxy::{PROXY_FOR_HEADER, ProxyAssertion},
+    report::ReportStatus,
+    request::telemetry::ReportedGuild,
+    response::{DEPRECATION_HEADER, SUNSET_HEADER, Warning, user::UpdateDiscordUserResponse},
+    user::User as DbUser,
 };
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use sha2::Sha256;
+
 use twilight_model::id::marker::GuildMarker;
 use twilight_model::{
     id::{Id, marker::UserMarker},
     user::User,
 };
 
+/// The media type of MessagePack.
+const MSGPACK: &str = "application/msgpack";
+
+/// The path prefix of the API version the bot was built against.
+///
+/// Servers keep older versions around, so the bot keeps working when the
+/// server is upgraded ahead of it.
+const API_VERSION: &str = "/v1";
+
 /// A client used to access the HTTP API.
 ///
 /// Cheaply cloneable, as it uses an `Arc` to track internal state and manage
@@ -46,6 +88,8 @@ struct ClientState {
     endpoint: String,
     api_key: String,
     token_refresh_retries: u32,
+    proxy_secret: Option<String>,
+    metrics: Metrics,
 }
 
 /// A cached user.
@@ -61,15 +105,20 @@ pub struct CachedUser {
 impl Client {
     /// Creates a new client.
     pub fn new(config: &ApiConfig) -> Result<Client, Error> {
-        let http = reqwest::Client::builder()
-            .use_rustls_tls()
-            .deflate(true)
-            .build()?;
+        let mut http = reqwest::Client::builder().use_rustls_tls().deflate(true);
+
+        if let Some(identity) = config.identity.as_ref() {
+            http = http.identity(reqwest::Identity::from_pem(&std::fs::read(identity)?)?);
+        }
+
+        let http = http.build()?;
 
         let state = ClientState {
             endpoint: config.endpoint.to_owned(),
             api_key: config.key.to_owned(),
             token_refresh_retries: config.token_refresh_retries,
+            proxy_secret: config.proxy_secret.to_owned(),
+            metrics: Metrics::new(config.error_rate.clone()),
         };
 
         Ok(Client {
@@ -94,6 +143,48 @@ impl Client {
         }
     }
 
+    /// Gets many users at once, like [`Client::get_discord_user`].
+    ///
+    /// Users missing from the cache are fetched in batches, rather than one
+    /// request each. Users are returned in the order they were given.
+    pub async fn get_discord_users(&self, users: &[User]) -> Result<Vec<DbUser>, Error> {
+        let mut found = Vec::with_capacity(users.len());
+        let mut missing = Vec::new();
+
+        for user in users {
+            let cached = self.user_cache.get(&user.id).await;
+
+            if cached.is_none() {
+                missing.push((user.id, user.name.clone()));
+            }
+
+            found.push(cached.map(|cached| cached.user));
+        }
+
+        // the server updates at most this many users at once
+        for chunk in missing.chunks(100) {
+            for res in self.update_discord_users(chunk.to_vec()).execute().await? {
+                let discord_id = Id::<UserMarker>::from(NonZeroU64::from(res.discord_id));
+
+                for (user, found) in users.iter().zip(found.iter_mut()) {
+                    if user.id == discord_id && found.is_none() {
+                        *found = Some(res.user.clone());
+                    }
+                }
+            }
+        }
+
+        found
+            .into_iter()
+            .map(|user| user.ok_or_else(|| Error::msg("user missing from batch response")))
+            .collect()
+    }
+
+    /// The metrics of the requests the client made.
+    pub fn metrics(&self) -> &Metrics {
+        &self.state.metrics
+    }
+
     /// Proxies as a user.
     ///
     /// Creates a copy of the client that can be used to proxy for a user.
@@ -109,11 +200,51 @@ impl Client {
         GetCard::new(self.clone(), guild_id, id)
     }
 
+    /// Gets a card by its emoji.
+    pub fn lookup_card(&self, guild_id: Id<GuildMarker>, emoji: impl Into<String>) -> LookupCard {
+        LookupCard::new(self.clone(), guild_id, emoji)
+    }
+
     /// Lists all avaialble cards in a guild.
     pub fn list_cards(&self, guild_id: Id<GuildMarker>) -> ListCards {
         ListCards::new(self.clone(), guild_id)
     }
 
+    /// Lists the most viewed cards in a guild.
+    pub fn popular_cards(&self, guild_id: Id<GuildMarker>) -> PopularCards {
+        PopularCards::new(self.clone(), guild_id)
+    }
+
+    /// Archives cards in a guild in bulk.
+    pub fn archive_cards(&self, guild_id: Id<GuildMarker>) -> ArchiveCards {
+        ArchiveCards::new(self.clone(), guild_id)
+    }
+
+    /// Gets the grant policy of a card.
+    pub fn get_grant_policy(&self, guild_id: Id<GuildMarker>, card_id: i32) -> GetGrantPolicy {
+        GetGrantPolicy::new(self.clone(), guild_id, card_id)
+    }
+
+    /// Gets the prerequisites of a card.
+    pub fn get_prerequisites(&self, guild_id: Id<GuildMarker>, card_id: i32) -> GetPrerequisites {
+        GetPrerequisites::new(self.clone(), guild_id, card_id)
+    }
+
+    /// Replaces the prerequisites of a card.
+    pub fn update_prerequisites(
+        &self,
+        guild_id: Id<GuildMarker>,
+        card_id: i32,
+        prerequisites: Prerequisites,
+    ) -> UpdatePrerequisites {
+        UpdatePrerequisites::new(self.clone(), guild_id, card_id, prerequisites)
+    }
+
+    /// Lists all users that own a card.
+    pub fn list_card_owners(&self, guild_id: Id<GuildMarker>, card_id: i32) -> ListCardOwners {
+        ListCardOwners::new(self.clone(), guild_id, card_id)
+    }
+
     /// Grants a card to a user.
     pub fn grant_card_to_user(&self, user_id: i32, card_id: i32) -> GrantCard {
         GrantCard::new(self.clone(), user_id, card_id)
@@ -124,6 +255,92 @@ impl Client {
         RevokeCard::new(self.clone(), user_id, card_id)
     }
 
+    /// Transfers a card from one user to another.
+    pub fn transfer_card(&self, from_id: i32, card_id: i32, to_id: i32) -> TransferCard {
+        TransferCard::new(self.clone(), from_id, card_id, to_id)
+    }
+
+    /// Lists all cards a user owns.
+    pub fn list_inventory(&self, user_id: i32) -> ListInventory {
+        ListInventory::new(self.clone(), user_id)
+    }
+
+    /// Exports the cards a user owns as CSV.
+    pub fn export_inventory(&self, user_id: i32) -> ExportInventory {
+        ExportInventory::new(self.clone(), user_id)
+    }
+
+    /// Trades in duplicates of a card a user owns.
+    pub fn trade_in(&self, user_id: i32, card_id: i32, count: u32) -> TradeIn {
+        TradeIn::new(self.clone(), user_id, card_id, count)
+    }
+
+    /// Gets the duplicate trade-in rules of a guild.
+    pub fn get_trade_in_rules(&self, guild_id: Id<GuildMarker>) -> GetTradeInRules {
+        GetTradeInRules::new(self.clone(), guild_id)
+    }
+
+    /// Lists a guild's audit log.
+    pub fn get_audit_log(&self, guild_id: Id<GuildMarker>) -> GetAuditLog {
+        GetAuditLog::new(self.clone(), guild_id)
+    }
+
+    /// Replays a guild's logged events.
+    pub fn replay_events(&self, guild_id: Id<GuildMarker>) -> ReplayEvents {
+        ReplayEvents::new(self.clone(), guild_id)
+    }
+
+    /// Reports a card.
+    pub fn create_report(
+        &self,
+        guild_id: Id<GuildMarker>,
+        card_id: i32,
+        reason: impl Into<String>,
+    ) -> CreateReport {
+        CreateReport::new(self.clone(), guild_id, card_id, reason.into())
+    }
+
+    /// Lists a guild's reports.
+    pub fn list_reports(&self, guild_id: Id<GuildMarker>) -> ListReports {
+        ListReports::new(self.clone(), guild_id)
+    }
+
+    /// Resolves or dismisses a report.
+    pub fn resolve_report(
+        &self,
+        guild_id: Id<GuildMarker>,
+        id: i32,
+        status: ReportStatus,
+    ) -> ResolveReport {
+        ResolveReport::new(self.clone(), guild_id, id, status)
+    }
+
+    /// Lists all card templates.
+    pub fn list_templates(&self) -> ListTemplates {
+        ListTemplates::new(self.clone())
+    }
+
+    /// Copies a card template's cards into a guild.
+    pub fn instantiate_template(&self, guild_id: Id<GuildMarker>, id: i32) -> InstantiateTemplate {
+        InstantiateTemplate::new(self.clone(), guild_id, id)
+    }
+
+    /// Stores UI state as a view, so a component can carry only the view's
+    /// id.
+    pub fn create_view(&self, data: serde_json::Value) -> CreateView {
+        CreateView::new(self.clone(), data)
+    }
+
+    /// Gets a view.
+    pub fn get_view(&self, id: i32) -> GetView {
+        GetView::new(self.clone(), id)
+    }
+
+    /// Gets a user's collection progress in a guild.
+    pub fn get_progress(&self, user_id: i32, guild_id: Id<GuildMarker>) -> GetProgress {
+        GetProgress::new(self.clone(), user_id, guild_id)
+    }
+
     /// Updates a Discord user's information.
     pub fn update_discord_user(
         &self,
@@ -133,11 +350,51 @@ impl Client {
         UpdateDiscordUser::new(self.clone(), discord_id, display_name.into())
     }
 
+    /// Updates many Discord users' information at once.
+    pub fn update_discord_users(
+        &self,
+        users: impl IntoIterator<Item = (Id<UserMarker>, String)>,
+    ) -> BatchUpdateDiscordUsers {
+        BatchUpdateDiscordUsers::new(self.clone(), users.into_iter().collect())
+    }
+
+    /// Lists recently active users whose display names may be out of date.
+    pub fn stale_discord_users(&self) -> ListStaleDiscordUsers {
+        ListStaleDiscordUsers::new(self.clone())
+    }
+
+    /// Reports every guild the bot serves.
+    pub fn report_guilds(&self, guilds: impl IntoIterator<Item = ReportedGuild>) -> ReportGuilds {
+        ReportGuilds::new(self.clone(), guilds.into_iter().collect())
+    }
+
+    /// Reports that the bot left a guild.
+    pub fn depart_guild(&self, guild_id: Id<GuildMarker>) -> DepartGuild {
+        DepartGuild::new(self.clone(), guild_id)
+    }
+
     /// Makes a generic request to the server.
     pub(super) fn request(&self, method: Method, url: impl AsRef<str>) -> Request {
         Request::new(self.clone(), method, url)
     }
 
+    /// Executes a built request, recording it in the client's metrics.
+    async fn execute(
+        &self,
+        route: &Route,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, Error> {
+        let start = Instant::now();
+        let res = self.http.execute(request).await;
+
+        let failed = res
+            .as_ref()
+            .map_or(true, |res| res.status().is_server_error());
+        self.state.metrics.record(route, start.elapsed(), failed);
+
+        Ok(res?)
+    }
+
     /// Updates the user cache with a result from the `/users/discord`
     /// endpoint.
     pub(super) async fn update_cache(&self, res: &UpdateDiscordUserResponse) {
@@ -161,24 +418,134 @@ impl Client {
     }
 }
 
+/// A HTTP client response.
+#[derive(Debug)]
+pub struct Response(reqwest::Response);
+
+impl Response {
+    /// Deserializes the body of the response.
+    ///
+    /// Every request asks for MessagePack, but JSON is read as well, in case
+    /// the server answers with it anyway.
+    ///
+    /// If the server says the request used a deprecated part of the API, the
+    /// warnings are logged.
+    pub async fn body<T>(self) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let headers = self.0.headers();
+
+        let msgpack = headers
+            .get(header::CONTENT_TYPE)
+            .is_some_and(|mime| mime == MSGPACK);
+        let deprecated = headers.contains_key(DEPRECATION_HEADER);
+        let sunset = headers
+            .get(SUNSET_HEADER)
+            .and_then(|sunset| sunset.to_str().ok())
+            .map(String::from);
+        let path = self.0.url().path().to_owned();
+
+        let body = self.0.bytes().await?;
+
+        if deprecated {
+            let warnings = decode::<Warnings>(msgpack, &body)
+                .map(|body| body.warnings)
+                .unwrap_or_default();
+
+            if warnings.is_empty() {
+                tracing::warn!(path, sunset, "request used a deprecated api");
+            }
+
+            for warning in warnings {
+                tracing::warn!(
+                    path,
+                    message = warning.message,
+                    sunset = ?warning.sunset,
+                    "request used a deprecated api"
+                );
+            }
+        }
+
+        decode(msgpack, &body)
+    }
+
+    /// Reads the body of the response as is, like a file the server sent.
+    pub async fn bytes(self) -> Result<Vec<u8>, Error> {
+        Ok(self.0.bytes().await?.to_vec())
+    }
+
+    /// Reads the error of a failed response.
+    ///
+    /// The server's ID for the request is logged, so the failure can be found
+    /// in the server's logs.
+    async fn error(self) -> Result<ApiError, Error> {
+        let status = self.0.status();
+        let request_id = self
+            .0
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|id| id.to_str().ok())
+            .map(String::from);
+
+        let error = self.body::<ApiError>().await;
+
+        match error.as_ref() {
+            Ok(error) if status.is_server_error() => {
+                tracing::warn!(%status, request_id, ?error.code, "request failed")
+            }
+            Ok(error) => tracing::debug!(%status, request_id, ?error.code, "request failed"),
+            Err(err) => tracing::warn!(%status, request_id, ?err, "request failed"),
+        }
+
+        error
+    }
+}
+
+/// The warnings of a response; see [`Warning`].
+#[derive(Deserialize)]
+struct Warnings {
+    #[serde(default)]
+    warnings: Vec<Warning>,
+}
+
+fn decode<T>(msgpack: bool, body: &[u8]) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    if msgpack {
+        Ok(rmp_serde::from_slice(body)?)
+    } else {
+        Ok(serde_json::from_slice(body)?)
+    }
+}
+
 /// A HTTP client request.
 #[derive(Debug)]
 pub struct Request {
     client: Client,
     request: reqwest::RequestBuilder,
+    route: Route,
 }
 
 impl Request {
     /// Creates a new `Request`.
     ///
-    /// The url is appended to the API endpoint, and headers are set before
-    /// sending the request.
+    /// The url is appended to the API endpoint under [`API_VERSION`], and
+    /// headers are set before sending the request.
     pub fn new(client: Client, method: Method, url: impl AsRef<str>) -> Request {
-        let url = format!("{}{}", client.state.endpoint, url.as_ref());
+        let route = Route::new(&method, url.as_ref());
+        let url = format!("{}{}{}", client.state.endpoint, API_VERSION, url.as_ref());
 
+        // the server encodes responses as JSON otherwise, which is larger and
+        // slower to read for big card listings
         Request {
-            request: client.http.request(method, url),
+            request: client
+                .http
+                .request(method, url)
+                .header(header::ACCEPT, MSGPACK),
             client,
+            route,
         }
     }
 
@@ -207,7 +574,7 @@ impl Request {
     /// Makes a general request to the API as the bot.
     ///
     /// This bypasses any possible proxying.
-    pub async fn send_privileged(self) -> Result<reqwest::Response, Error> {
+    pub async fn send_privileged(self) -> Result<Response, Error> {
         let mut request = self.request.build()?;
 
         request.headers_mut().insert(
@@ -215,24 +582,57 @@ impl Request {
             HeaderValue::from_str(&self.client.state.api_key).expect("valid api key"),
         );
 
-        let res = self.client.http.execute(request).await?;
+        let res = self.client.execute(&self.route, request).await?;
 
         if res.status().is_success() {
-            Ok(res)
+            Ok(Response(res))
         } else {
-            Err(res.json::<ApiError>().await?.into())
+            Err(Response(res).error().await?.into())
         }
     }
 
+    /// Makes a request to the API as the bot, for a user.
+    ///
+    /// Instead of the user's access token, a proxy assertion signed with
+    /// `secret` is sent alongside the bot's API key.
+    async fn send_asserted(mut self, user: &User, secret: &str) -> Result<Response, Error> {
+        // the server only accepts assertions for users it knows about
+        self.client.get_discord_user(user).await?;
+
+        let discord_id = nymph_model::Id::from(NonZeroU64::from(user.id));
+        let timestamp = Utc::now().timestamp();
+
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any size");
+        mac.update(ProxyAssertion::message(discord_id, timestamp).as_bytes());
+
+        let assertion = ProxyAssertion {
+            discord_id,
+            timestamp,
+            signature: encode_lower(&mac.finalize().into_bytes()),
+        };
+
+        self.request = self.request.header(PROXY_FOR_HEADER, assertion.to_string());
+        self.send_privileged().await
+    }
+
     /// Makes a general request to the API.
-    pub async fn send(mut self) -> Result<reqwest::Response, Error> {
+    pub async fn send(mut self) -> Result<Response, Error> {
         let token_refresh_retries = self.client.state.token_refresh_retries;
 
-        if self.client.proxy_for.is_some() {
+        if let Some(user) = self.client.proxy_for.clone()
+            && let Some(secret) = self.client.state.proxy_secret.clone()
+        {
+            self.send_asserted(&user, &secret).await
+        } else if self.client.proxy_for.is_some() {
             let mut request = self.request.build()?;
             let user = self.client.proxy_for.take().unwrap();
 
-            for _ in 0..token_refresh_retries {
+            for attempt in 0..token_refresh_retries {
+                if attempt > 0 {
+                    self.client.state.metrics.retry(&self.route);
+                }
+
                 // try to get bearer token
                 let token = if let Some(token) = self
                     .client
@@ -243,6 +643,8 @@ impl Request {
                 {
                     token
                 } else {
+                    self.client.state.metrics.token_refresh(&self.route);
+
                     // fetch bearer token from internet
                     self.client
                         .update_discord_user(user.id, user.name.clone())
@@ -261,15 +663,14 @@ impl Request {
                 // request with token
                 let res = self
                     .client
-                    .http
-                    .execute(request.try_clone().expect("cloneable request"))
+                    .execute(&self.route, request.try_clone().expect("cloneable request"))
                     .await?;
 
                 if res.status().is_success() {
                     // short circuit with success value
-                    return Ok(res);
+                    return Ok(Response(res));
                 } else {
-                    let error = res.json::<ApiError>().await?;
+                    let error = Response(res).error().await?;
 
                     if error.code == ErrorCode::BadCredentials {
                         // retry request after getting new credentials
@@ -0,0 +1,280 @@
+//! Request metrics.
+//!
+//! The client tracks how each route of the API behaves: how many requests
+//! were made, how long they took, how many failed, and how often requests
+//! were retried or needed a fresh access token. Routes are told apart by
+//! their method and path, with ids replaced by `{id}`.
+//!
+//! Only transport failures and server errors count as failures; a missing
+//! card is a normal answer. A warning is logged when a route's error rate
+//! over its latest requests crosses [`ErrorRateConfig::threshold`], and again
+//! once it recovers.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use http::Method;
+
+use crate::config::ErrorRateConfig;
+
+/// The upper bounds of the latency histogram buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 9] = [0.025, 0.05, 0.1, 0.25, 0.5, 1., 2.5, 5., 10.];
+
+/// A counter's name, help text, and how it is read from a route's metrics.
+type Counter = (&'static str, &'static str, fn(&RouteMetrics) -> u64);
+
+/// The metrics of every route the client requested.
+///
+/// Cheaply cloneable.
+#[derive(Clone, Debug, Default)]
+pub struct Metrics(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    config: ErrorRateConfig,
+    routes: Mutex<HashMap<Route, RouteMetrics>>,
+}
+
+/// A route of the API.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Route {
+    /// The HTTP method of the route.
+    pub method: String,
+    /// The path of the route, with ids replaced by `{id}`.
+    pub path: String,
+}
+
+impl Route {
+    /// Gets the route a request is made to.
+    pub fn new(method: &Method, path: &str) -> Route {
+        let path = path
+            .split('/')
+            .map(|segment| {
+                if !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit()) {
+                    "{id}"
+                } else {
+                    segment
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        Route {
+            method: method.as_str().to_owned(),
+            path,
+        }
+    }
+}
+
+/// The metrics of a single route.
+#[derive(Clone, Debug, Default)]
+pub struct RouteMetrics {
+    /// How many requests were made.
+    pub requests: u64,
+    /// How many requests failed.
+    pub errors: u64,
+    /// How many requests were retried.
+    pub retries: u64,
+    /// How many access tokens were fetched to make requests.
+    pub token_refreshes: u64,
+    /// How many requests finished within each of [`LATENCY_BUCKETS`].
+    latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    /// How long all requests took together.
+    latency_sum: Duration,
+    /// Whether each of the latest requests failed, oldest first.
+    recent: VecDeque<bool>,
+    /// If the error rate is over the threshold.
+    alerting: bool,
+}
+
+impl RouteMetrics {
+    /// The error rate over the latest requests.
+    pub fn error_rate(&self) -> f64 {
+        if self.recent.is_empty() {
+            return 0.;
+        }
+
+        let errors = self.recent.iter().filter(|failed| **failed).count();
+        errors as f64 / self.recent.len() as f64
+    }
+}
+
+impl Metrics {
+    /// Creates a new `Metrics`.
+    pub fn new(config: ErrorRateConfig) -> Metrics {
+        Metrics(Arc::new(Inner {
+            config,
+            routes: Mutex::default(),
+        }))
+    }
+
+    /// Records a finished request.
+    pub fn record(&self, route: &Route, latency: Duration, failed: bool) {
+        let config = &self.0.config;
+
+        self.update(route, |metrics| {
+            metrics.requests += 1;
+            metrics.latency_sum += latency;
+
+            let secs = latency.as_secs_f64();
+            for (bucket, le) in metrics.latency_buckets.iter_mut().zip(LATENCY_BUCKETS) {
+                if secs <= le {
+                    *bucket += 1;
+                }
+            }
+
+            if failed {
+                metrics.errors += 1;
+            }
+
+            if metrics.recent.len() >= config.window.max(1) {
+                metrics.recent.pop_front();
+            }
+            metrics.recent.push_back(failed);
+
+            if metrics.recent.len() < config.min_requests {
+                return;
+            }
+
+            let error_rate = metrics.error_rate();
+
+            if !metrics.alerting && error_rate >= config.threshold {
+                metrics.alerting = true;
+                tracing::warn!(
+                    method = route.method,
+                    path = route.path,
+                    error_rate,
+                    "route error rate crossed threshold"
+                );
+            } else if metrics.alerting && error_rate < config.threshold {
+                metrics.alerting = false;
+                tracing::info!(
+                    method = route.method,
+                    path = route.path,
+                    error_rate,
+                    "route error rate recovered"
+                );
+            }
+        });
+    }
+
+    /// Records that a request was retried.
+    pub fn retry(&self, route: &Route) {
+        self.update(route, |metrics| metrics.retries += 1);
+    }
+
+    /// Records that an access token was fetched to make a request.
+    pub fn token_refresh(&self, route: &Route) {
+        self.update(route, |metrics| metrics.token_refreshes += 1);
+    }
+
+    /// Gets the metrics of every route requested so far.
+    pub fn routes(&self) -> Vec<(Route, RouteMetrics)> {
+        let routes = self.0.routes.lock().expect("metrics poisoned");
+
+        let mut routes = routes
+            .iter()
+            .map(|(route, metrics)| (route.clone(), metrics.clone()))
+            .collect::<Vec<_>>();
+        routes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        routes
+    }
+
+    /// Renders the metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let routes = self.routes();
+        let mut out = String::new();
+
+        let counters: [Counter; 4] = [
+            (
+                "nymph_api_requests_total",
+                "Requests made to the API.",
+                |m| m.requests,
+            ),
+            (
+                "nymph_api_errors_total",
+                "Requests that failed in transport or with a server error.",
+                |m| m.errors,
+            ),
+            (
+                "nymph_api_retries_total",
+                "Requests that were retried.",
+                |m| m.retries,
+            ),
+            (
+                "nymph_api_token_refreshes_total",
+                "Access tokens fetched to make requests.",
+                |m| m.token_refreshes,
+            ),
+        ];
+
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+
+            for (route, metrics) in routes.iter() {
+                let _ = writeln!(out, "{}{{{}}} {}", name, labels(route), value(metrics));
+            }
+        }
+
+        let name = "nymph_api_error_rate";
+        let _ = writeln!(
+            out,
+            "# HELP {} The error rate over the latest requests.",
+            name
+        );
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+
+        for (route, metrics) in routes.iter() {
+            let _ = writeln!(
+                out,
+                "{}{{{}}} {}",
+                name,
+                labels(route),
+                metrics.error_rate()
+            );
+        }
+
+        let name = "nymph_api_request_duration_seconds";
+        let _ = writeln!(out, "# HELP {} How long requests took.", name);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+
+        for (route, metrics) in routes.iter() {
+            let labels = labels(route);
+
+            for (count, le) in metrics.latency_buckets.iter().zip(LATENCY_BUCKETS) {
+                let _ = writeln!(out, "{}_bucket{{{},le=\"{}\"}} {}", name, labels, le, count);
+            }
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"+Inf\"}} {}",
+                name, labels, metrics.requests
+            );
+            let _ = writeln!(
+                out,
+                "{}_sum{{{}}} {}",
+                name,
+                labels,
+                metrics.latency_sum.as_secs_f64()
+            );
+            let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, metrics.requests);
+        }
+
+        out
+    }
+
+    fn update(&self, route: &Route, f: impl FnOnce(&mut RouteMetrics)) {
+        let mut routes = self.0.routes.lock().expect("metrics poisoned");
+
+        match routes.get_mut(route) {
+            Some(metrics) => f(metrics),
+            None => f(routes.entry(route.clone()).or_default()),
+        }
+    }
+}
+
+fn labels(route: &Route) -> String {
+    format!("method=\"{}\",route=\"{}\"", route.method, route.path)
+}
@@ -1,6 +1,7 @@
 //! Nymph HTTP client.
 
 pub mod client;
+pub mod metrics;
 pub mod request;
 
 pub use client::Client;
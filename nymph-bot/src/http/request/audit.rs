@@ -0,0 +1,65 @@
+//! Audit log queries.
+
+use http::Method;
+
+use nymph_model::{request::audit::AuditLogQuery, response::audit::AuditLogResponse};
+
+use twilight_model::id::{Id, marker::GuildMarker};
+
+use crate::http::Client;
+
+use anyhow::Error;
+
+/// Lists a guild's audit log.
+#[derive(Debug)]
+pub struct GetAuditLog {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+    query: AuditLogQuery,
+}
+
+impl GetAuditLog {
+    /// Creates a new `GetAuditLog`.
+    pub fn new(client: Client, guild_id: Id<GuildMarker>) -> GetAuditLog {
+        GetAuditLog {
+            client,
+            guild_id,
+            query: AuditLogQuery::default(),
+        }
+    }
+
+    /// Only lists entries caused by or about a user.
+    pub fn user(mut self, user_id: i32) -> GetAuditLog {
+        self.query.user_id = Some(user_id);
+        self
+    }
+
+    /// Only lists entries about a card.
+    pub fn card(mut self, card_id: i32) -> GetAuditLog {
+        self.query.card_id = Some(card_id);
+        self
+    }
+
+    /// Sets the count of entries to return.
+    pub fn count(mut self, count: u32) -> GetAuditLog {
+        self.query.count = Some(count);
+        self
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<AuditLogResponse, Error> {
+        let GetAuditLog {
+            client,
+            guild_id,
+            query,
+        } = self;
+
+        let request = client
+            .request(Method::GET, format!("/guilds/{}/audit", guild_id))
+            .query(&query)
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
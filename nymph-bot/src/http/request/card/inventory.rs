@@ -1,9 +1,24 @@
 //! Card inventory transfers and manipulation.
 
+use std::num::NonZeroU64;
+
 use anyhow::Error;
 
 use http::Method;
-use nymph_model::{card::Card, request::card::inventory::GrantRequest};
+use nymph_model::{
+    card::Card,
+    request::card::inventory::{
+        ExportFormat, ExportInventoryQuery, GrantRequest, ListInventoryQuery, ListOwnersQuery,
+        TradeInRequest, TransferRequest,
+    },
+    response::{
+        Paginated,
+        card::{CardOwner, TradeInResponse, TransferResponse},
+    },
+    trade_in::TradeInRules,
+};
+
+use twilight_model::id::{Id, marker::GuildMarker};
 
 use crate::http::Client;
 
@@ -39,7 +54,7 @@ impl GrantCard {
             .send()
             .await?;
 
-        Ok(request.json().await?)
+        Ok(request.body().await?)
     }
 }
 
@@ -77,6 +92,295 @@ impl RevokeCard {
             .send()
             .await?;
 
-        Ok(request.json().await?)
+        Ok(request.body().await?)
+    }
+}
+
+/// Transfers a card from one user to another.
+#[derive(Debug)]
+pub struct TransferCard {
+    client: Client,
+    from_id: i32,
+    card_id: i32,
+    to_id: i32,
+}
+
+impl TransferCard {
+    /// Creates a new `TransferCard`.
+    pub fn new(client: Client, from_id: i32, card_id: i32, to_id: i32) -> TransferCard {
+        TransferCard {
+            client,
+            from_id,
+            card_id,
+            to_id,
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<TransferResponse, Error> {
+        let TransferCard {
+            client,
+            from_id,
+            card_id,
+            to_id,
+        } = self;
+
+        let request = client
+            .request(
+                Method::POST,
+                format!("/users/{}/cards/{}/transfer", from_id, card_id),
+            )
+            .json(&TransferRequest { to_id })
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Lists all users that own a card.
+#[derive(Debug)]
+pub struct ListCardOwners {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+    card_id: i32,
+    page: Option<u32>,
+    count: Option<u32>,
+}
+
+impl ListCardOwners {
+    /// Creates a new `ListCardOwners`.
+    pub fn new(client: Client, guild_id: Id<GuildMarker>, card_id: i32) -> ListCardOwners {
+        ListCardOwners {
+            client,
+            guild_id,
+            card_id,
+            page: None,
+            count: None,
+        }
+    }
+
+    /// Sets the page to explore.
+    pub fn page(self, page: u32) -> ListCardOwners {
+        ListCardOwners {
+            page: Some(page),
+            ..self
+        }
+    }
+
+    /// Sets the count of entries to return.
+    pub fn count(self, count: u32) -> ListCardOwners {
+        ListCardOwners {
+            count: Some(count),
+            ..self
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<Paginated<CardOwner>, Error> {
+        let ListCardOwners {
+            client,
+            guild_id,
+            card_id,
+            page,
+            count,
+        } = self;
+
+        let request = client
+            .request(
+                Method::GET,
+                format!("/guilds/{}/cards/{}/owners", guild_id, card_id),
+            )
+            .query(&ListOwnersQuery { page, count })
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Lists all cards a user owns.
+#[derive(Debug)]
+pub struct ListInventory {
+    client: Client,
+    user_id: i32,
+    guild_id: Option<Id<GuildMarker>>,
+    page: Option<u32>,
+    count: Option<u32>,
+}
+
+impl ListInventory {
+    /// Creates a new `ListInventory`.
+    pub fn new(client: Client, user_id: i32) -> ListInventory {
+        ListInventory {
+            client,
+            user_id,
+            guild_id: None,
+            page: None,
+            count: None,
+        }
+    }
+
+    /// Only lists cards of a guild.
+    pub fn guild(self, guild_id: Id<GuildMarker>) -> ListInventory {
+        ListInventory {
+            guild_id: Some(guild_id),
+            ..self
+        }
+    }
+
+    /// Sets the page to explore.
+    pub fn page(self, page: u32) -> ListInventory {
+        ListInventory {
+            page: Some(page),
+            ..self
+        }
+    }
+
+    /// Sets the count of entries to return.
+    pub fn count(self, count: u32) -> ListInventory {
+        ListInventory {
+            count: Some(count),
+            ..self
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<Paginated<Card>, Error> {
+        let ListInventory {
+            client,
+            user_id,
+            guild_id,
+            page,
+            count,
+        } = self;
+
+        let request = client
+            .request(Method::GET, format!("/users/{}/cards", user_id))
+            .query(&ListInventoryQuery {
+                guild_id: guild_id.map(|guild_id| NonZeroU64::from(guild_id).into()),
+                favorites: None,
+                page,
+                count,
+            })
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Exports the cards a user owns as CSV.
+#[derive(Debug)]
+pub struct ExportInventory {
+    client: Client,
+    user_id: i32,
+    guild_id: Option<Id<GuildMarker>>,
+}
+
+impl ExportInventory {
+    /// Creates a new `ExportInventory`.
+    pub fn new(client: Client, user_id: i32) -> ExportInventory {
+        ExportInventory {
+            client,
+            user_id,
+            guild_id: None,
+        }
+    }
+
+    /// Only exports cards of a guild.
+    pub fn guild(self, guild_id: Id<GuildMarker>) -> ExportInventory {
+        ExportInventory {
+            guild_id: Some(guild_id),
+            ..self
+        }
+    }
+
+    /// Sends the request, returning the CSV file.
+    pub async fn execute(self) -> Result<Vec<u8>, Error> {
+        let ExportInventory {
+            client,
+            user_id,
+            guild_id,
+        } = self;
+
+        let request = client
+            .request(Method::GET, format!("/users/{}/cards/export", user_id))
+            .query(&ExportInventoryQuery {
+                guild_id: guild_id.map(|guild_id| NonZeroU64::from(guild_id).into()),
+                format: Some(ExportFormat::Csv),
+            })
+            .send()
+            .await?;
+
+        request.bytes().await
+    }
+}
+
+/// Trades in duplicates of a card.
+#[derive(Debug)]
+pub struct TradeIn {
+    client: Client,
+    user_id: i32,
+    card_id: i32,
+    count: u32,
+}
+
+impl TradeIn {
+    /// Creates a new `TradeIn`.
+    pub fn new(client: Client, user_id: i32, card_id: i32, count: u32) -> TradeIn {
+        TradeIn {
+            client,
+            user_id,
+            card_id,
+            count,
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<TradeInResponse, Error> {
+        let TradeIn {
+            client,
+            user_id,
+            card_id,
+            count,
+        } = self;
+
+        let request = client
+            .request(
+                Method::POST,
+                format!("/users/{}/cards/{}/trade-in", user_id, card_id),
+            )
+            .json(&TradeInRequest { count })
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Gets the duplicate trade-in rules of a guild.
+#[derive(Debug)]
+pub struct GetTradeInRules {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+}
+
+impl GetTradeInRules {
+    /// Creates a new `GetTradeInRules`.
+    pub fn new(client: Client, guild_id: Id<GuildMarker>) -> GetTradeInRules {
+        GetTradeInRules { client, guild_id }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<TradeInRules, Error> {
+        let GetTradeInRules { client, guild_id } = self;
+
+        let request = client
+            .request(Method::GET, format!("/guilds/{}/trade-in", guild_id))
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
     }
 }
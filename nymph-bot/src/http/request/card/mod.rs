@@ -4,7 +4,19 @@ pub mod inventory;
 
 use http::Method;
 
-use nymph_model::{card::Card, request::card::ListCardsQuery};
+use chrono::NaiveDateTime;
+
+use nymph_model::{
+    card::{Card, GrantPolicy, Prerequisites, Rarity, Visibility},
+    request::card::{
+        ArchiveCardsRequest, CardSort, Expand, ExpandSet, FieldSet, ListCardsQuery,
+        LookupCardQuery, PopularCardsQuery, ShowCardQuery, SortOrder, VisibilityFilter,
+    },
+    response::{
+        Paginated,
+        card::{ArchiveCardsResponse, PopularCard},
+    },
+};
 
 use twilight_model::id::{Id, marker::GuildMarker};
 
@@ -18,6 +30,15 @@ pub struct ListCards {
     client: Client,
     guild_id: Id<GuildMarker>,
     query: Option<String>,
+    q_content: Option<String>,
+    rarity: Option<Rarity>,
+    category: Option<String>,
+    owned: Option<bool>,
+    visibility: Option<VisibilityFilter>,
+    fields: Option<FieldSet>,
+    expand: Option<ExpandSet>,
+    sort: Option<CardSort>,
+    order: Option<SortOrder>,
     page: Option<u32>,
     count: Option<u32>,
 }
@@ -29,6 +50,15 @@ impl ListCards {
             client,
             guild_id,
             query: None,
+            q_content: None,
+            rarity: None,
+            category: None,
+            owned: None,
+            visibility: None,
+            fields: None,
+            expand: None,
+            sort: None,
+            order: None,
             page: None,
             count: None,
         }
@@ -42,6 +72,86 @@ impl ListCards {
         }
     }
 
+    /// Searches the content of the guild's cards for a phrase.
+    pub fn search_content(self, phrase: impl Into<String>) -> ListCards {
+        ListCards {
+            q_content: Some(phrase.into()),
+            ..self
+        }
+    }
+
+    /// Filters the cards by rarity.
+    pub fn rarity(self, rarity: Rarity) -> ListCards {
+        ListCards {
+            rarity: Some(rarity),
+            ..self
+        }
+    }
+
+    /// Filters the cards by category.
+    pub fn category(self, category: impl Into<String>) -> ListCards {
+        ListCards {
+            category: Some(category.into()),
+            ..self
+        }
+    }
+
+    /// Filters the cards by whether the user owns them.
+    pub fn owned(self, owned: bool) -> ListCards {
+        ListCards {
+            owned: Some(owned),
+            ..self
+        }
+    }
+
+    /// Filters the cards by visibility.
+    ///
+    /// Listing cards that are not public takes privileged access.
+    pub fn visibility(self, visibility: impl IntoIterator<Item = Visibility>) -> ListCards {
+        ListCards {
+            visibility: Some(VisibilityFilter(visibility.into_iter().collect())),
+            ..self
+        }
+    }
+
+    /// Only fetches some fields of each card.
+    ///
+    /// The fields a [`Card`] cannot go without must still be asked for.
+    pub fn fields<I>(self, fields: I) -> ListCards
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        ListCards {
+            fields: Some(FieldSet(fields.into_iter().map(Into::into).collect())),
+            ..self
+        }
+    }
+
+    /// Loads the related cards of each card.
+    pub fn expand(self, expand: impl IntoIterator<Item = Expand>) -> ListCards {
+        ListCards {
+            expand: Some(ExpandSet(expand.into_iter().collect())),
+            ..self
+        }
+    }
+
+    /// Sets how the cards are ordered.
+    pub fn sort(self, sort: CardSort) -> ListCards {
+        ListCards {
+            sort: Some(sort),
+            ..self
+        }
+    }
+
+    /// Sets which way the cards are ordered.
+    pub fn order(self, order: SortOrder) -> ListCards {
+        ListCards {
+            order: Some(order),
+            ..self
+        }
+    }
+
     /// Sets the page to explore.
     pub fn page(self, page: u32) -> ListCards {
         ListCards {
@@ -68,22 +178,99 @@ impl ListCards {
     }
 
     /// Sends the request.
-    pub async fn execute(self) -> Result<Vec<Card>, Error> {
+    pub async fn execute(self) -> Result<Paginated<Card>, Error> {
         let ListCards {
             client,
             guild_id,
             query,
+            q_content,
+            rarity,
+            category,
+            owned,
+            visibility,
+            fields,
+            expand,
+            sort,
+            order,
             page,
             count,
         } = self;
 
         let request = client
             .request(Method::GET, format!("/guilds/{}/cards", guild_id))
-            .query(&ListCardsQuery { query, page, count })
+            .query(&ListCardsQuery {
+                query,
+                q_content,
+                rarity,
+                category,
+                owned,
+                visibility,
+                fields,
+                expand,
+                sort,
+                order,
+                page,
+                count,
+            })
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Lists the most viewed cards in a guild.
+#[derive(Debug)]
+pub struct PopularCards {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+    days: Option<u32>,
+    count: Option<u32>,
+}
+
+impl PopularCards {
+    /// Creates a new `PopularCards`.
+    pub fn new(client: Client, guild_id: Id<GuildMarker>) -> PopularCards {
+        PopularCards {
+            client,
+            guild_id,
+            days: None,
+            count: None,
+        }
+    }
+
+    /// Sets how many days back views are counted.
+    pub fn days(self, days: u32) -> PopularCards {
+        PopularCards {
+            days: Some(days),
+            ..self
+        }
+    }
+
+    /// Sets the count of entries to return.
+    pub fn count(self, count: u32) -> PopularCards {
+        PopularCards {
+            count: Some(count),
+            ..self
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<Vec<PopularCard>, Error> {
+        let PopularCards {
+            client,
+            guild_id,
+            days,
+            count,
+        } = self;
+
+        let request = client
+            .request(Method::GET, format!("/guilds/{}/cards/popular", guild_id))
+            .query(&PopularCardsQuery { days, count })
             .send()
             .await?;
 
-        Ok(request.json().await?)
+        Ok(request.body().await?)
     }
 }
 
@@ -92,6 +279,7 @@ pub struct GetCard {
     client: Client,
     guild_id: Id<GuildMarker>,
     id: i32,
+    expand: Option<ExpandSet>,
 }
 
 impl GetCard {
@@ -101,6 +289,15 @@ impl GetCard {
             client,
             guild_id,
             id,
+            expand: None,
+        }
+    }
+
+    /// Loads the related cards of the card.
+    pub fn expand(self, expand: impl IntoIterator<Item = Expand>) -> GetCard {
+        GetCard {
+            expand: Some(ExpandSet(expand.into_iter().collect())),
+            ..self
         }
     }
 
@@ -110,13 +307,234 @@ impl GetCard {
             client,
             guild_id,
             id,
+            expand,
         } = self;
 
         let request = client
             .request(Method::GET, format!("/guilds/{}/cards/{}", guild_id, id))
+            .query(&ShowCardQuery {
+                fields: None,
+                expand,
+            })
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Gets a card by its emoji.
+pub struct LookupCard {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+    emoji: String,
+}
+
+impl LookupCard {
+    /// Create a new `LookupCard`.
+    pub fn new(client: Client, guild_id: Id<GuildMarker>, emoji: impl Into<String>) -> LookupCard {
+        LookupCard {
+            client,
+            guild_id,
+            emoji: emoji.into(),
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<Card, Error> {
+        let LookupCard {
+            client,
+            guild_id,
+            emoji,
+        } = self;
+
+        let request = client
+            .request(Method::GET, format!("/guilds/{}/cards/lookup", guild_id))
+            .query(&LookupCardQuery { emoji })
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Gets the grant policy of a card.
+#[derive(Debug)]
+pub struct GetGrantPolicy {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+    card_id: i32,
+}
+
+impl GetGrantPolicy {
+    /// Creates a new `GetGrantPolicy`.
+    pub fn new(client: Client, guild_id: Id<GuildMarker>, card_id: i32) -> GetGrantPolicy {
+        GetGrantPolicy {
+            client,
+            guild_id,
+            card_id,
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<GrantPolicy, Error> {
+        let GetGrantPolicy {
+            client,
+            guild_id,
+            card_id,
+        } = self;
+
+        let request = client
+            .request(
+                Method::GET,
+                format!("/guilds/{}/cards/{}/grant-policy", guild_id, card_id),
+            )
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Gets the prerequisites of a card.
+#[derive(Debug)]
+pub struct GetPrerequisites {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+    card_id: i32,
+}
+
+impl GetPrerequisites {
+    /// Creates a new `GetPrerequisites`.
+    pub fn new(client: Client, guild_id: Id<GuildMarker>, card_id: i32) -> GetPrerequisites {
+        GetPrerequisites {
+            client,
+            guild_id,
+            card_id,
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<Prerequisites, Error> {
+        let GetPrerequisites {
+            client,
+            guild_id,
+            card_id,
+        } = self;
+
+        let request = client
+            .request(
+                Method::GET,
+                format!("/guilds/{}/cards/{}/prerequisites", guild_id, card_id),
+            )
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Replaces the prerequisites of a card.
+#[derive(Debug)]
+pub struct UpdatePrerequisites {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+    card_id: i32,
+    prerequisites: Prerequisites,
+}
+
+impl UpdatePrerequisites {
+    /// Creates a new `UpdatePrerequisites`.
+    pub fn new(
+        client: Client,
+        guild_id: Id<GuildMarker>,
+        card_id: i32,
+        prerequisites: Prerequisites,
+    ) -> UpdatePrerequisites {
+        UpdatePrerequisites {
+            client,
+            guild_id,
+            card_id,
+            prerequisites,
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<Prerequisites, Error> {
+        let UpdatePrerequisites {
+            client,
+            guild_id,
+            card_id,
+            prerequisites,
+        } = self;
+
+        let request = client
+            .request(
+                Method::PUT,
+                format!("/guilds/{}/cards/{}/prerequisites", guild_id, card_id),
+            )
+            .json(&prerequisites)
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Archives cards in a guild in bulk.
+#[derive(Debug)]
+pub struct ArchiveCards {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+    category_name: Option<String>,
+    created_before: Option<NaiveDateTime>,
+}
+
+impl ArchiveCards {
+    /// Creates a new `ArchiveCards`.
+    pub fn new(client: Client, guild_id: Id<GuildMarker>) -> ArchiveCards {
+        ArchiveCards {
+            client,
+            guild_id,
+            category_name: None,
+            created_before: None,
+        }
+    }
+
+    /// Only archives cards in a category.
+    pub fn category(self, category_name: impl Into<String>) -> ArchiveCards {
+        ArchiveCards {
+            category_name: Some(category_name.into()),
+            ..self
+        }
+    }
+
+    /// Only archives cards created before a certain time.
+    pub fn created_before(self, created_before: NaiveDateTime) -> ArchiveCards {
+        ArchiveCards {
+            created_before: Some(created_before),
+            ..self
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<ArchiveCardsResponse, Error> {
+        let ArchiveCards {
+            client,
+            guild_id,
+            category_name,
+            created_before,
+        } = self;
+
+        let request = client
+            .request(Method::POST, format!("/guilds/{}/cards/archive", guild_id))
+            .json(&ArchiveCardsRequest {
+                category_name,
+                created_before,
+            })
             .send()
             .await?;
 
-        Ok(request.json().await?)
+        Ok(request.body().await?)
     }
 }
@@ -1,2 +1,8 @@
+pub mod audit;
 pub mod card;
+pub mod report;
+pub mod telemetry;
+pub mod template;
 pub mod user;
+pub mod view;
+pub mod webhook;
@@ -0,0 +1,163 @@
+//! Content reports.
+
+use http::Method;
+
+use nymph_model::{
+    report::{Report, ReportStatus},
+    request::report::{CreateReportRequest, ListReportsQuery, ResolveReportRequest},
+    response::Paginated,
+};
+
+use twilight_model::id::{Id, marker::GuildMarker};
+
+use crate::http::Client;
+
+use anyhow::Error;
+
+/// Reports a card.
+#[derive(Debug)]
+pub struct CreateReport {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+    card_id: i32,
+    reason: String,
+}
+
+impl CreateReport {
+    /// Creates a new `CreateReport`.
+    pub fn new(
+        client: Client,
+        guild_id: Id<GuildMarker>,
+        card_id: i32,
+        reason: String,
+    ) -> CreateReport {
+        CreateReport {
+            client,
+            guild_id,
+            card_id,
+            reason,
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<Report, Error> {
+        let CreateReport {
+            client,
+            guild_id,
+            card_id,
+            reason,
+        } = self;
+
+        let request = client
+            .request(
+                Method::POST,
+                format!("/guilds/{}/cards/{}/reports", guild_id, card_id),
+            )
+            .json(&CreateReportRequest { reason })
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Lists a guild's reports.
+#[derive(Debug)]
+pub struct ListReports {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+    query: ListReportsQuery,
+}
+
+impl ListReports {
+    /// Creates a new `ListReports`.
+    pub fn new(client: Client, guild_id: Id<GuildMarker>) -> ListReports {
+        ListReports {
+            client,
+            guild_id,
+            query: ListReportsQuery::default(),
+        }
+    }
+
+    /// Only lists reports with a status.
+    pub fn status(mut self, status: ReportStatus) -> ListReports {
+        self.query.status = Some(status);
+        self
+    }
+
+    /// Sets the page to return.
+    pub fn page(mut self, page: u32) -> ListReports {
+        self.query.page = Some(page);
+        self
+    }
+
+    /// Sets the count of reports to return.
+    pub fn count(mut self, count: u32) -> ListReports {
+        self.query.count = Some(count);
+        self
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<Paginated<Report>, Error> {
+        let ListReports {
+            client,
+            guild_id,
+            query,
+        } = self;
+
+        let request = client
+            .request(Method::GET, format!("/guilds/{}/reports", guild_id))
+            .query(&query)
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Resolves or dismisses a report.
+#[derive(Debug)]
+pub struct ResolveReport {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+    id: i32,
+    status: ReportStatus,
+}
+
+impl ResolveReport {
+    /// Creates a new `ResolveReport`.
+    pub fn new(
+        client: Client,
+        guild_id: Id<GuildMarker>,
+        id: i32,
+        status: ReportStatus,
+    ) -> ResolveReport {
+        ResolveReport {
+            client,
+            guild_id,
+            id,
+            status,
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<Report, Error> {
+        let ResolveReport {
+            client,
+            guild_id,
+            id,
+            status,
+        } = self;
+
+        let request = client
+            .request(
+                Method::POST,
+                format!("/guilds/{}/reports/{}/resolve", guild_id, id),
+            )
+            .json(&ResolveReportRequest { status })
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
@@ -0,0 +1,67 @@
+//! Telemetry reports.
+
+use http::Method;
+
+use nymph_model::{
+    request::telemetry::{ReportGuildsRequest, ReportedGuild},
+    response::telemetry::{GuildPresence, ReportGuildsResponse},
+};
+
+use twilight_model::id::{Id, marker::GuildMarker};
+
+use crate::http::Client;
+
+use anyhow::Error;
+
+/// Reports every guild the bot serves.
+#[derive(Debug)]
+pub struct ReportGuilds {
+    client: Client,
+    guilds: Vec<ReportedGuild>,
+}
+
+impl ReportGuilds {
+    /// Creates a new `ReportGuilds`.
+    pub fn new(client: Client, guilds: Vec<ReportedGuild>) -> ReportGuilds {
+        ReportGuilds { client, guilds }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<ReportGuildsResponse, Error> {
+        let ReportGuilds { client, guilds } = self;
+
+        let request = client
+            .request(Method::POST, "/telemetry/guilds")
+            .json(&ReportGuildsRequest { guilds })
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Reports that the bot left a guild.
+#[derive(Debug)]
+pub struct DepartGuild {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+}
+
+impl DepartGuild {
+    /// Creates a new `DepartGuild`.
+    pub fn new(client: Client, guild_id: Id<GuildMarker>) -> DepartGuild {
+        DepartGuild { client, guild_id }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<GuildPresence, Error> {
+        let DepartGuild { client, guild_id } = self;
+
+        let request = client
+            .request(Method::DELETE, format!("/telemetry/guilds/{}", guild_id))
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
@@ -0,0 +1,85 @@
+//! Card templates.
+
+use http::Method;
+
+use nymph_model::{
+    request::card::ImportCardsQuery, response::card::ImportCardsResponse, template::Template,
+};
+
+use twilight_model::id::{Id, marker::GuildMarker};
+
+use crate::http::Client;
+
+use anyhow::Error;
+
+/// Lists all card templates.
+#[derive(Debug)]
+pub struct ListTemplates {
+    client: Client,
+}
+
+impl ListTemplates {
+    /// Creates a new `ListTemplates`.
+    pub fn new(client: Client) -> ListTemplates {
+        ListTemplates { client }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<Vec<Template>, Error> {
+        let ListTemplates { client } = self;
+
+        let request = client
+            .request(Method::GET, "/admin/templates")
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Copies a card template's cards into a guild.
+#[derive(Debug)]
+pub struct InstantiateTemplate {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+    id: i32,
+    dry_run: bool,
+}
+
+impl InstantiateTemplate {
+    /// Creates a new `InstantiateTemplate`.
+    pub fn new(client: Client, guild_id: Id<GuildMarker>, id: i32) -> InstantiateTemplate {
+        InstantiateTemplate {
+            client,
+            guild_id,
+            id,
+            dry_run: false,
+        }
+    }
+
+    /// Only reports what would be copied.
+    pub fn dry_run(self, dry_run: bool) -> InstantiateTemplate {
+        InstantiateTemplate { dry_run, ..self }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<ImportCardsResponse, Error> {
+        let InstantiateTemplate {
+            client,
+            guild_id,
+            id,
+            dry_run,
+        } = self;
+
+        let request = client
+            .request(
+                Method::POST,
+                format!("/guilds/{}/templates/{}/instantiate", guild_id, id),
+            )
+            .query(&ImportCardsQuery { dry_run })
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
@@ -5,10 +5,17 @@ use std::num::NonZeroU64;
 use http::Method;
 
 use nymph_model::{
-    request::user::UpdateDiscordUserRequest, response::user::UpdateDiscordUserResponse,
+    request::user::{
+        BatchUpdateDiscordUsersRequest, DiscordUser, ProgressQuery, StaleDiscordUsersQuery,
+        UpdateDiscordUserRequest,
+    },
+    response::user::{ProgressResponse, StaleDiscordUser, UpdateDiscordUserResponse},
 };
 
-use twilight_model::id::{Id, marker::UserMarker};
+use twilight_model::id::{
+    Id,
+    marker::{GuildMarker, UserMarker},
+};
 
 use crate::http::Client;
 
@@ -62,9 +69,167 @@ impl UpdateDiscordUser {
             .await?;
 
         // get response and cache
-        let res = request.json::<UpdateDiscordUserResponse>().await?;
+        let res = request.body::<UpdateDiscordUserResponse>().await?;
         client.update_cache(&res).await;
 
         Ok(res)
     }
 }
+
+/// Proxies for many Discord users at once using the bot.
+#[derive(Debug)]
+pub struct BatchUpdateDiscordUsers {
+    client: Client,
+    users: Vec<(Id<UserMarker>, String)>,
+    failed: Vec<Id<UserMarker>>,
+    generate_tokens: bool,
+}
+
+impl BatchUpdateDiscordUsers {
+    /// Creates a new `BatchUpdateDiscordUsers`.
+    pub fn new(client: Client, users: Vec<(Id<UserMarker>, String)>) -> Self {
+        BatchUpdateDiscordUsers {
+            client,
+            users,
+            failed: Vec::new(),
+            generate_tokens: false,
+        }
+    }
+
+    /// Marks users that could not be looked up on Discord.
+    pub fn failed(self, failed: impl IntoIterator<Item = Id<UserMarker>>) -> Self {
+        BatchUpdateDiscordUsers {
+            failed: failed.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// Generates tokens.
+    pub fn generate_tokens(self, generate_tokens: bool) -> Self {
+        BatchUpdateDiscordUsers {
+            generate_tokens,
+            ..self
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<Vec<UpdateDiscordUserResponse>, Error> {
+        let BatchUpdateDiscordUsers {
+            client,
+            users,
+            failed,
+            generate_tokens,
+        } = self;
+
+        let users = users
+            .into_iter()
+            .map(|(discord_id, display_name)| DiscordUser {
+                discord_id: NonZeroU64::from(discord_id).into(),
+                display_name,
+            })
+            .collect();
+
+        let request = client
+            .request(Method::POST, "/users/discord/batch")
+            .json(&BatchUpdateDiscordUsersRequest {
+                users,
+                failed: failed
+                    .into_iter()
+                    .map(|discord_id| NonZeroU64::from(discord_id).into())
+                    .collect(),
+                generate_tokens,
+            })
+            .send_privileged()
+            .await?;
+
+        // get responses and cache
+        let res = request.body::<Vec<UpdateDiscordUserResponse>>().await?;
+
+        for user in res.iter() {
+            client.update_cache(user).await;
+        }
+
+        Ok(res)
+    }
+}
+
+/// Lists recently active users whose display names may be out of date.
+#[derive(Debug)]
+pub struct ListStaleDiscordUsers {
+    client: Client,
+    query: StaleDiscordUsersQuery,
+}
+
+impl ListStaleDiscordUsers {
+    /// Creates a new `ListStaleDiscordUsers`.
+    pub fn new(client: Client) -> Self {
+        ListStaleDiscordUsers {
+            client,
+            query: StaleDiscordUsersQuery::default(),
+        }
+    }
+
+    /// How many users to list at most.
+    pub fn count(mut self, count: u32) -> Self {
+        self.query.count = Some(count);
+        self
+    }
+
+    /// How long ago a display name must have been refreshed to be stale, in
+    /// hours.
+    pub fn max_age(mut self, max_age: u32) -> Self {
+        self.query.max_age = Some(max_age);
+        self
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<Vec<StaleDiscordUser>, Error> {
+        let ListStaleDiscordUsers { client, query } = self;
+
+        let request = client
+            .request(Method::GET, "/users/discord/stale")
+            .query(&query)
+            .send_privileged()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Gets a user's collection progress in a guild.
+#[derive(Debug)]
+pub struct GetProgress {
+    client: Client,
+    user_id: i32,
+    guild_id: Id<GuildMarker>,
+}
+
+impl GetProgress {
+    /// Creates a new `GetProgress`.
+    pub fn new(client: Client, user_id: i32, guild_id: Id<GuildMarker>) -> Self {
+        GetProgress {
+            client,
+            user_id,
+            guild_id,
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<ProgressResponse, Error> {
+        let GetProgress {
+            client,
+            user_id,
+            guild_id,
+        } = self;
+
+        let request = client
+            .request(Method::GET, format!("/users/{}/progress", user_id))
+            .query(&ProgressQuery {
+                guild_id: NonZeroU64::from(guild_id).into(),
+            })
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
@@ -0,0 +1,73 @@
+//! Shared view state.
+
+use http::Method;
+
+use nymph_model::{request::view::CreateViewRequest, view::View};
+
+use serde_json::Value;
+
+use crate::http::Client;
+
+use anyhow::Error;
+
+/// Stores UI state as a view.
+#[derive(Debug)]
+pub struct CreateView {
+    client: Client,
+    request: CreateViewRequest,
+}
+
+impl CreateView {
+    /// Creates a new `CreateView`.
+    pub fn new(client: Client, data: Value) -> CreateView {
+        CreateView {
+            client,
+            request: CreateViewRequest { data, ttl: None },
+        }
+    }
+
+    /// Sets how long the view lives, in seconds.
+    pub fn ttl(mut self, ttl: u32) -> CreateView {
+        self.request.ttl = Some(ttl);
+        self
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<View, Error> {
+        let CreateView { client, request } = self;
+
+        let request = client
+            .request(Method::POST, "/views")
+            .json(&request)
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
+
+/// Gets a view that has not expired.
+#[derive(Debug)]
+pub struct GetView {
+    client: Client,
+    id: i32,
+}
+
+impl GetView {
+    /// Creates a new `GetView`.
+    pub fn new(client: Client, id: i32) -> GetView {
+        GetView { client, id }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<View, Error> {
+        let GetView { client, id } = self;
+
+        let request = client
+            .request(Method::GET, format!("/views/{}", id))
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
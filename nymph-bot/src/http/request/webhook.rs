@@ -0,0 +1,66 @@
+//! Event log queries.
+
+use http::Method;
+
+use nymph_model::{request::webhook::ReplayQuery, response::webhook::ReplayResponse};
+
+use twilight_model::id::{Id, marker::GuildMarker};
+
+use crate::http::Client;
+
+use anyhow::Error;
+
+/// Replays a guild's logged events.
+#[derive(Debug)]
+pub struct ReplayEvents {
+    client: Client,
+    guild_id: Id<GuildMarker>,
+    after: Option<u64>,
+    count: Option<u32>,
+}
+
+impl ReplayEvents {
+    /// Creates a new `ReplayEvents`.
+    pub fn new(client: Client, guild_id: Id<GuildMarker>) -> ReplayEvents {
+        ReplayEvents {
+            client,
+            guild_id,
+            after: None,
+            count: None,
+        }
+    }
+
+    /// Only replays events after a sequence number.
+    pub fn after(self, seq: u64) -> ReplayEvents {
+        ReplayEvents {
+            after: Some(seq),
+            ..self
+        }
+    }
+
+    /// Sets the count of events to return.
+    pub fn count(self, count: u32) -> ReplayEvents {
+        ReplayEvents {
+            count: Some(count),
+            ..self
+        }
+    }
+
+    /// Sends the request.
+    pub async fn execute(self) -> Result<ReplayResponse, Error> {
+        let ReplayEvents {
+            client,
+            guild_id,
+            after,
+            count,
+        } = self;
+
+        let request = client
+            .request(Method::GET, format!("/guilds/{}/events/replay", guild_id))
+            .query(&ReplayQuery { after, count })
+            .send()
+            .await?;
+
+        Ok(request.body().await?)
+    }
+}
@@ -1,7 +1,13 @@
 //! `nymph` bot frontend.
 
+pub mod backfill;
 pub mod card;
 pub mod commands;
 pub mod config;
 pub mod dispatch;
+pub mod followup;
 pub mod http;
+pub mod log;
+pub mod metrics;
+pub mod notify;
+pub mod telemetry;
@@ -0,0 +1,70 @@
+//! Runtime-adjustable logging.
+//!
+//! The bot's tracing filter is re-read from its config file whenever it
+//! receives `SIGUSR1`, so noisy targets can be turned on to diagnose an issue
+//! without reconnecting to the gateway.
+
+use tracing_subscriber::{
+    EnvFilter, Registry, fmt, layer::SubscriberExt as _, reload, util::SubscriberInitExt as _,
+};
+
+use crate::config::Config;
+
+/// The filter used when neither the config file nor `RUST_LOG` set one.
+pub const DEFAULT_FILTER: &str = "info";
+
+/// A handle to the bot's tracing filter.
+pub type LogFilter = reload::Handle<EnvFilter, Registry>;
+
+/// Installs the global tracing subscriber, returning a handle to its filter.
+///
+/// The initial filter is read from `RUST_LOG`, falling back to
+/// [`DEFAULT_FILTER`].
+pub fn init() -> LogFilter {
+    let (filter, handle) = reload::Layer::new(default_filter());
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .init();
+
+    handle
+}
+
+/// Re-reads the log filter from the config file whenever the bot receives
+/// `SIGUSR1`.
+#[cfg(unix)]
+pub async fn reload_on_signal(config_path: std::path::PathBuf, log_filter: LogFilter) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut signal = signal(SignalKind::user_defined1()).expect("failed to install signal handler");
+
+    while signal.recv().await.is_some() {
+        let filter = match Config::load(&config_path) {
+            Ok(config) => match config.log {
+                Some(directives) => EnvFilter::try_new(directives),
+                None => Ok(default_filter()),
+            },
+            Err(err) => {
+                tracing::error!(?err, "failed to read log filter");
+                continue;
+            }
+        };
+
+        match filter {
+            Ok(filter) => {
+                let directives = filter.to_string();
+
+                match log_filter.reload(filter) {
+                    Ok(()) => tracing::info!(filter = directives, "reloaded log filter"),
+                    Err(err) => tracing::error!(?err, "failed to reload log filter"),
+                }
+            }
+            Err(err) => tracing::error!(?err, "invalid log filter"),
+        }
+    }
+}
+
+fn default_filter() -> EnvFilter {
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER))
+}
@@ -1,6 +1,10 @@
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
-use nymph_bot::{commands::InteractionContext, config::Config, dispatch, http::Client as DbClient};
+use nymph_bot::{
+    backfill::Backfill, card::RecentCards, commands::InteractionContext, config::Config, dispatch,
+    followup::Followups, http::Client as DbClient, log, metrics, notify::Notifier,
+    telemetry::Telemetry,
+};
 
 use twilight_cache_inmemory::{InMemoryCacheBuilder, ResourceType};
 use twilight_gateway::{
@@ -9,13 +13,23 @@ use twilight_gateway::{
 use twilight_http::Client;
 use twilight_model::gateway::payload::incoming::GuildCreate;
 
+use tracing_subscriber::EnvFilter;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenv::dotenv().ok();
-    tracing_subscriber::fmt::init();
+    let log_filter = log::init();
 
     // load config
-    let config = Arc::new(Config::load("nymph-bot.toml")?);
+    let config_path = PathBuf::from("nymph-bot.toml");
+    let config = Arc::new(Config::load(&config_path)?);
+
+    if let Some(directives) = config.log.as_ref() {
+        log_filter.reload(EnvFilter::try_new(directives)?)?;
+    }
+
+    #[cfg(unix)]
+    tokio::spawn(log::reload_on_signal(config_path, log_filter));
 
     tracing::info!("connecting to api...");
 
@@ -46,6 +60,52 @@ async fn main() -> anyhow::Result<()> {
 
     let interaction = client.interaction(application.id);
 
+    // announce rule rewards and completed sets as they are granted
+    let notifier = Notifier::new(client.clone(), db_client.clone(), config.clone());
+    tokio::spawn(
+        notifier
+            .clone()
+            .run(Duration::from_secs(config.notify.interval)),
+    );
+
+    // keep display names of users that stopped using the bot current
+    let backfill = Backfill::new(client.clone(), db_client.clone(), config.backfill.max_age);
+    tokio::spawn(backfill.run(Duration::from_secs(config.backfill.interval)));
+
+    // report the guilds the bot serves, if opted in
+    let telemetry = config
+        .telemetry
+        .enabled
+        .then(|| Telemetry::new(db_client.clone()));
+
+    if let Some(telemetry) = telemetry.clone() {
+        tokio::spawn(telemetry.run(Duration::from_secs(config.telemetry.interval)));
+    }
+
+    // serve the api client's metrics, if configured
+    if let Some(addr) = config.metrics.bind {
+        let db_client = db_client.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = metrics::serve(addr, db_client).await {
+                tracing::error!(?err, "failed to serve metrics");
+            }
+        });
+    }
+
+    // send follow-ups as they come due, including ones left from before a
+    // restart
+    let followups = Followups::new(client.clone(), application.id, config.followup.path.clone());
+
+    if let Err(err) = followups.load().await {
+        tracing::error!(?err, "failed to load pending follow-ups");
+    }
+
+    tokio::spawn(followups.clone().run());
+
+    // suggest recently seen cards when autocomplete searches are slow
+    let recent_cards = RecentCards::new();
+
     let mut shard = Shard::with_config(ShardId::ONE, shard_config);
 
     while let Some(item) = shard.next_event(EventTypeFlags::all()).await {
@@ -74,9 +134,27 @@ async fn main() -> anyhow::Result<()> {
                     .await?;
             }
             Event::GuildCreate(guild) => match guild.as_ref() {
-                GuildCreate::Available(guild) => tracing::info!("guild: {}", guild.name),
+                GuildCreate::Available(guild) => {
+                    tracing::info!("guild: {}", guild.name);
+                    notifier.watch(guild.id);
+
+                    if let Some(telemetry) = telemetry.as_ref() {
+                        telemetry.serve(guild.id, &guild.name, guild.member_count);
+                    }
+                }
                 _ => (),
             },
+            Event::GuildUpdate(guild) => {
+                if let Some(telemetry) = telemetry.as_ref() {
+                    telemetry.serve(guild.id, &guild.name, guild.member_count);
+                }
+            }
+            // unavailable guilds are in an outage, not left
+            Event::GuildDelete(guild) if guild.unavailable != Some(true) => {
+                if let Some(telemetry) = telemetry.as_ref() {
+                    telemetry.depart(guild.id);
+                }
+            }
             Event::InteractionCreate(interaction) => {
                 let interaction = interaction.0;
 
@@ -87,6 +165,8 @@ async fn main() -> anyhow::Result<()> {
                     client: client.clone(),
                     cache: cache.clone(),
                     db_client: db_client.clone(),
+                    recent_cards: recent_cards.clone(),
+                    followups: followups.clone(),
                     application_id: application.id,
                 };
 
@@ -0,0 +1,36 @@
+//! Metrics endpoint.
+//!
+//! Serves the metrics of the bot's API client at `/metrics`, in the
+//! Prometheus text format. See [`crate::http::metrics`].
+
+use std::net::SocketAddr;
+
+use axum::{Router, extract::State, http::header, response::IntoResponse, routing::get};
+
+use tokio::net::TcpListener;
+
+use crate::http::Client as DbClient;
+
+/// The media type of the Prometheus text format.
+const PROMETHEUS_TEXT: &str = "text/plain; version=0.0.4";
+
+/// Serves metrics on `addr` until the bot shuts down.
+pub async fn serve(addr: SocketAddr, db_client: DbClient) -> anyhow::Result<()> {
+    let router = Router::new()
+        .route("/metrics", get(metrics))
+        .with_state(db_client);
+
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!("serving metrics on {}", addr);
+
+    axum::serve(listener, router).await?;
+
+    Ok(())
+}
+
+async fn metrics(State(db_client): State<DbClient>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, PROMETHEUS_TEXT)],
+        db_client.metrics().render(),
+    )
+}
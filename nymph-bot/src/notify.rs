@@ -0,0 +1,267 @@
+//! Reward notifications.
+//!
+//! The server grants the rewards of reward rules in the background, so the
+//! bot polls each guild's event log for them and lets the rewarded users know
+//! in their DMs. Guilds that opt in also have completed sets celebrated in a
+//! public channel, with the reward card shown inline if it is public, and the
+//! summaries of ended events posted.
+
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use nymph_model::{
+    card::Visibility,
+    dispatch::{Event, EventEnd, RuleReward, SetCompletion},
+};
+
+use tokio::time::{MissedTickBehavior, interval};
+
+use twilight_http::Client;
+use twilight_model::{
+    channel::message::{Component, MessageFlags},
+    id::{
+        Id,
+        marker::{ChannelMarker, GuildMarker, UserMarker},
+    },
+};
+use twilight_util::builder::message::TextDisplayBuilder;
+
+use crate::{card::render_card, config::Config, http::Client as DbClient};
+
+/// How many events are fetched at once.
+const REPLAY_COUNT: u32 = 100;
+
+/// Announces rewards granted by reward rules.
+///
+/// Cheaply cloneable.
+#[derive(Clone, Debug)]
+pub struct Notifier {
+    client: Arc<Client>,
+    db_client: DbClient,
+    config: Arc<Config>,
+    /// The last sequence number seen in each watched guild, or `None` if the
+    /// guild has not been polled yet.
+    cursors: Arc<Mutex<HashMap<Id<GuildMarker>, Option<u64>>>>,
+}
+
+impl Notifier {
+    /// Creates a new `Notifier`.
+    pub fn new(client: Arc<Client>, db_client: DbClient, config: Arc<Config>) -> Notifier {
+        Notifier {
+            client,
+            db_client,
+            config,
+            cursors: Arc::default(),
+        }
+    }
+
+    /// Starts watching a guild's events.
+    ///
+    /// Rewards granted before the guild is first polled are not announced.
+    pub fn watch(&self, guild_id: Id<GuildMarker>) {
+        self.cursors.lock().unwrap().entry(guild_id).or_insert(None);
+    }
+
+    /// Polls every watched guild each `period`, forever.
+    pub async fn run(self, period: Duration) {
+        let mut interval = interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            let guilds = self
+                .cursors
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(guild_id, seq)| (*guild_id, *seq))
+                .collect::<Vec<_>>();
+
+            for (guild_id, seq) in guilds {
+                match self.poll(guild_id, seq).await {
+                    Ok(seq) => {
+                        self.cursors.lock().unwrap().insert(guild_id, Some(seq));
+                    }
+                    Err(err) => tracing::warn!(%guild_id, ?err, "failed to poll guild events"),
+                }
+            }
+        }
+    }
+
+    /// Announces the rewards granted in a guild after `seq`.
+    ///
+    /// If `seq` is `None`, skips to the latest event without announcing
+    /// anything. Returns the last sequence number seen.
+    async fn poll(&self, guild_id: Id<GuildMarker>, seq: Option<u64>) -> anyhow::Result<u64> {
+        let catching_up = seq.is_none();
+        let mut seq = seq.unwrap_or(0);
+
+        loop {
+            let replay = self
+                .db_client
+                .replay_events(guild_id)
+                .after(seq)
+                .count(REPLAY_COUNT)
+                .execute()
+                .await?;
+            let len = replay.events.len();
+
+            for envelope in replay.events {
+                seq = envelope.seq.map_or(seq, |event_seq| event_seq.max(seq));
+
+                if catching_up {
+                    continue;
+                }
+
+                match envelope.event {
+                    Event::RewardGranted(reward) => {
+                        if let Err(err) = self.announce(&reward).await {
+                            // users may have their DMs closed
+                            tracing::debug!(
+                                user_id = reward.user_id,
+                                ?err,
+                                "failed to announce reward"
+                            );
+                        }
+                    }
+                    Event::SetCompleted(completion) => {
+                        if let Err(err) = self.celebrate(&completion).await {
+                            // the channel may be gone, or closed to the bot
+                            tracing::warn!(
+                                %guild_id,
+                                channel_id = completion.channel_id.get(),
+                                ?err,
+                                "failed to celebrate completed set"
+                            );
+                        }
+                    }
+                    Event::EventEnded(end) => {
+                        if let Err(err) = self.summarize(&end).await {
+                            tracing::warn!(
+                                %guild_id,
+                                event_id = end.event.id,
+                                ?err,
+                                "failed to post event summary"
+                            );
+                        }
+                    }
+                    _ => (),
+                }
+            }
+
+            if len < REPLAY_COUNT as usize {
+                return Ok(seq);
+            }
+        }
+    }
+
+    /// Lets a user know they were rewarded.
+    async fn announce(&self, reward: &RuleReward) -> anyhow::Result<()> {
+        let Some(discord_id) = reward.discord_id else {
+            return Ok(());
+        };
+        let user_id = Id::<UserMarker>::from(NonZeroU64::from(discord_id));
+
+        let message = format!(
+            "You collected every card of **{}** and were rewarded card `{}`!",
+            reward.rule.name, reward.card.name
+        );
+
+        let channel = self
+            .client
+            .create_private_channel(user_id)
+            .await?
+            .model()
+            .await?;
+
+        self.client
+            .create_message(channel.id)
+            .content(&message)
+            .await?;
+
+        tracing::debug!(
+            user_id = reward.user_id,
+            rule_id = reward.rule.id,
+            "announced reward"
+        );
+
+        Ok(())
+    }
+
+    /// Celebrates a completed set in the guild's announcement channel.
+    ///
+    /// Only public reward cards are shown; others are only named, so the
+    /// channel does not learn what they look like.
+    async fn celebrate(&self, completion: &SetCompletion) -> anyhow::Result<()> {
+        let channel_id = Id::<ChannelMarker>::from(NonZeroU64::from(completion.channel_id));
+
+        let who = match completion.discord_id {
+            Some(discord_id) => format!("<@{}>", discord_id.get()),
+            None => String::from("Someone"),
+        };
+        let what = match completion.category_name.as_ref() {
+            Some(category_name) => format!("every **{}** card", category_name),
+            None => format!("the **{}** set", completion.rule.name),
+        };
+
+        let components = if completion.card.visibility == Visibility::Public {
+            vec![
+                Component::TextDisplay(
+                    TextDisplayBuilder::new(format!(
+                        "🎉 {} collected {} and earned a reward!",
+                        who, what
+                    ))
+                    .build(),
+                ),
+                Component::Container(render_card(&self.config, &completion.card)),
+            ]
+        } else {
+            vec![Component::TextDisplay(
+                TextDisplayBuilder::new(format!(
+                    "🎉 {} collected {} and earned `{}`!",
+                    who, what, completion.card.name
+                ))
+                .build(),
+            )]
+        };
+
+        self.client
+            .create_message(channel_id)
+            .flags(MessageFlags::IS_COMPONENTS_V2)
+            .components(&components)
+            .await?;
+
+        tracing::debug!(
+            user_id = completion.user_id,
+            rule_id = completion.rule.id,
+            "celebrated completed set"
+        );
+
+        Ok(())
+    }
+
+    /// Posts the summary of an ended event in the guild's events channel.
+    async fn summarize(&self, end: &EventEnd) -> anyhow::Result<()> {
+        let (Some(channel_id), Some(summary)) = (end.channel_id, end.event.summary.as_ref()) else {
+            return Ok(());
+        };
+        let channel_id = Id::<ChannelMarker>::from(NonZeroU64::from(channel_id));
+
+        let message = format!(
+            "The **{}** event has ended! {} players took part, and {} cards were granted.",
+            end.event.name, summary.participants, summary.cards_granted
+        );
+
+        self.client
+            .create_message(channel_id)
+            .content(&message)
+            .await?;
+
+        tracing::debug!(event_id = end.event.id, "posted event summary");
+
+        Ok(())
+    }
+}
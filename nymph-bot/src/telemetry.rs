@@ -0,0 +1,103 @@
+//! Guild telemetry.
+//!
+//! If enabled, the bot periodically reports every guild it serves to the
+//! server, so the server can tell which guilds it left and how many members
+//! it reaches. Nothing is reported unless the config opts in.
+
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use nymph_model::request::telemetry::ReportedGuild;
+
+use tokio::time::{MissedTickBehavior, interval};
+
+use twilight_model::id::{Id, marker::GuildMarker};
+
+use crate::http::Client as DbClient;
+
+/// Reports the guilds the bot serves.
+///
+/// Cheaply cloneable.
+#[derive(Clone, Debug)]
+pub struct Telemetry {
+    db_client: DbClient,
+    guilds: Arc<Mutex<HashMap<Id<GuildMarker>, ReportedGuild>>>,
+}
+
+impl Telemetry {
+    /// Creates a new `Telemetry`.
+    pub fn new(db_client: DbClient) -> Telemetry {
+        Telemetry {
+            db_client,
+            guilds: Arc::default(),
+        }
+    }
+
+    /// Marks a guild as served, or updates what is known about it.
+    ///
+    /// If `member_count` is `None`, the last known count is kept.
+    pub fn serve(&self, guild_id: Id<GuildMarker>, name: &str, member_count: Option<u64>) {
+        let mut guilds = self.guilds.lock().unwrap();
+        let guild = guilds.entry(guild_id).or_insert_with(|| ReportedGuild {
+            id: nymph_model::Id::from(NonZeroU64::from(guild_id)),
+            name: String::new(),
+            member_count: None,
+        });
+
+        guild.name = name.to_owned();
+        guild.member_count = member_count.or(guild.member_count);
+    }
+
+    /// Marks a guild as no longer served, and tells the server right away so
+    /// the guild's grace period starts now rather than at the next report.
+    pub fn depart(&self, guild_id: Id<GuildMarker>) {
+        self.guilds.lock().unwrap().remove(&guild_id);
+
+        let db_client = self.db_client.clone();
+
+        tokio::spawn(async move {
+            match db_client.depart_guild(guild_id).execute().await {
+                Ok(_) => tracing::debug!(%guild_id, "reported departed guild"),
+                Err(err) => tracing::warn!(?err, %guild_id, "failed to report departed guild"),
+            }
+        });
+    }
+
+    /// Reports every served guild each `period`, forever.
+    ///
+    /// The first report is sent after a full period, so the guilds of the
+    /// gateway's ready event have arrived by then.
+    pub async fn run(self, period: Duration) {
+        let mut interval = interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            let guilds = self
+                .guilds
+                .lock()
+                .unwrap()
+                .values()
+                .cloned()
+                .collect::<Vec<_>>();
+
+            // an empty report would mark every guild as departed
+            if guilds.is_empty() {
+                continue;
+            }
+
+            match self.db_client.report_guilds(guilds).execute().await {
+                Ok(res) => tracing::debug!(
+                    joined = res.joined.len(),
+                    departed = res.departed.len(),
+                    "reported guilds"
+                ),
+                Err(err) => tracing::warn!(?err, "failed to report guilds"),
+            }
+        }
+    }
+}
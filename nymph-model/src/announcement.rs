@@ -0,0 +1,20 @@
+//! Public announcement data models.
+
+use serde::{Deserialize, Serialize};
+
+use super::Id;
+
+/// What a guild announces in public, and where.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct AnnouncementSettings {
+    /// The channel users completing a reward rule's set are celebrated in.
+    ///
+    /// Completions are not announced if left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completions_channel_id: Option<Id>,
+    /// The channel the summaries of ended events are posted in.
+    ///
+    /// Summaries are not posted if left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events_channel_id: Option<Id>,
+}
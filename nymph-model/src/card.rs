@@ -8,7 +8,7 @@ use derive_more::{Display, Error};
 
 use serde::{Deserialize, Serialize};
 
-use super::Id;
+use super::{Id, user::User};
 
 /// A single card.
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -19,12 +19,23 @@ pub struct Card {
     pub guild_id: Id,
     /// The card's name.
     pub name: String,
+    /// The emoji identifying the card, if it has one.
+    ///
+    /// Either Discord custom emoji markup, like `<:name:id>`, or a Unicode
+    /// emoji. Emojis are unique within a guild.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
     /// The card's category, if it belongs to a category.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub category_name: Option<String>,
     /// The card's visibility status.
     pub visibility: Visibility,
+    /// The card's rarity tier.
+    pub rarity: Rarity,
     /// The card's content in Markdown.
+    ///
+    /// Empty if left out of a sparse response.
+    #[serde(default)]
     pub content: String,
     /// Whether or not the card is usually hidden from the user.
     ///
@@ -37,10 +48,183 @@ pub struct Card {
     /// The card's downgrade.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub downgrade: Option<Box<Card>>,
+    /// How many copies of the card the user owns.
+    ///
+    /// Only appears in inventory responses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<u32>,
+    /// The card's rarity score, computed from how few players own the card.
+    ///
+    /// Only appears to privileged callers, and only if the card is owned by
+    /// at least one player.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rarity_score: Option<f64>,
+    /// When the card was archived.
+    ///
+    /// Archived cards are retired from circulation; they remain visible to
+    /// their owners, but cannot be searched for or granted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<NaiveDateTime>,
+    /// The user that created the card.
+    ///
+    /// Only appears to privileged callers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_by: Option<Author>,
+    /// The user that last edited the card.
+    ///
+    /// Only appears to privileged callers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_edited_by: Option<Author>,
+    /// How owners rated the card.
+    ///
+    /// Only appears to privileged callers, and only if the card was rated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ratings: Option<Ratings>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
 
+impl Card {
+    /// The names of every field a card serializes with.
+    ///
+    /// These are the fields that may be asked for in a sparse response.
+    pub const FIELDS: &[&str] = &[
+        "id",
+        "guild_id",
+        "name",
+        "emoji",
+        "category_name",
+        "visibility",
+        "rarity",
+        "content",
+        "hidden",
+        "upgrades",
+        "downgrade",
+        "quantity",
+        "rarity_score",
+        "archived_at",
+        "created_by",
+        "last_edited_by",
+        "ratings",
+        "created_at",
+        "updated_at",
+    ];
+}
+
+/// Normalizes a card name, for storing cards and for looking them up.
+///
+/// Names are uppercased by Unicode's rules rather than only ASCII's, so
+/// `émile` and `ÉMILE` are the same name, and runs of whitespace become a
+/// single space. The bot and the server must agree on this, or names typed
+/// into one would never match names stored by the other.
+pub fn normalize_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase()
+}
+
+/// A user that authored a card.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct Author {
+    /// The user.
+    pub user: User,
+    /// The discord ID of the user, if they are a discord user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discord_id: Option<Id>,
+}
+
+/// How owners rated a card.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Ratings {
+    /// How many owners rated the card.
+    pub count: u32,
+    /// The average rating, from `1` to `5`.
+    pub average: f64,
+}
+
+/// Describes who may grant a card.
+///
+/// A card with an empty policy may be granted by anyone allowed to grant
+/// cards.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct GrantPolicy {
+    /// Discord users that may grant the card.
+    #[serde(default)]
+    pub users: Vec<Id>,
+    /// Discord roles whose members may grant the card.
+    #[serde(default)]
+    pub roles: Vec<Id>,
+}
+
+impl GrantPolicy {
+    /// Checks if the policy places no restrictions on the card.
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty() && self.roles.is_empty()
+    }
+
+    /// Checks if a discord user with a set of roles may grant the card.
+    pub fn allows(&self, user: Option<Id>, roles: &[Id]) -> bool {
+        self.is_empty()
+            || user.is_some_and(|user| self.users.contains(&user))
+            || roles.iter().any(|role| self.roles.contains(role))
+    }
+}
+
+/// Describes what a user must have collected before a card can be granted to
+/// them.
+///
+/// A card with no prerequisites may be granted to anyone.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct Prerequisites {
+    /// Cards the user must own.
+    #[serde(default)]
+    pub cards: Vec<i32>,
+    /// Categories the user must have collected every card of.
+    #[serde(default)]
+    pub categories: Vec<String>,
+}
+
+impl Prerequisites {
+    /// Checks if the card has no prerequisites.
+    pub fn is_empty(&self) -> bool {
+        self.cards.is_empty() && self.categories.is_empty()
+    }
+}
+
+/// A guild's card name rules.
+///
+/// Rules are checked against names after they are normalized, whenever a
+/// card is created, renamed or imported. The default rules allow any name.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct NameRules {
+    /// The maximum length of a card's name, in characters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u32>,
+    /// If names may only use ASCII characters.
+    #[serde(default)]
+    pub ascii_only: bool,
+    /// The characters names may use besides letters, digits and spaces.
+    ///
+    /// If unset, names may use any character.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbols: Option<String>,
+}
+
+impl NameRules {
+    /// Checks if a name may use a character.
+    pub fn allows(&self, c: char) -> bool {
+        if self.ascii_only && !c.is_ascii() {
+            return false;
+        }
+
+        match self.symbols.as_deref() {
+            Some(symbols) => c.is_alphanumeric() || c == ' ' || symbols.contains(c),
+            None => true,
+        }
+    }
+}
+
 /// Card visibility.
 ///
 /// This determines how the card appears to users that do not own the card.
@@ -108,3 +292,67 @@ impl FromStr for Visibility {
 #[derive(Clone, Debug, Display, Error)]
 #[display("no such visibility \"{_0}\" exists")]
 pub struct NoSuchVisibility(#[error(not(source))] String);
+
+/// Card rarity.
+///
+/// A tier assigned to a card by administrators, ordered from most to least
+/// common.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+impl Rarity {
+    /// Creates a string representation of the rarity that can be used to get
+    /// back the rarity with [`FromStr`].
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Rarity::Common => "common",
+            Rarity::Uncommon => "uncommon",
+            Rarity::Rare => "rare",
+            Rarity::Epic => "epic",
+            Rarity::Legendary => "legendary",
+        }
+    }
+}
+
+impl TryFrom<String> for Rarity {
+    type Error = NoSuchRarity;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl TryFrom<&str> for Rarity {
+    type Error = NoSuchRarity;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl FromStr for Rarity {
+    type Err = NoSuchRarity;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "common" => Ok(Rarity::Common),
+            "uncommon" => Ok(Rarity::Uncommon),
+            "rare" => Ok(Rarity::Rare),
+            "epic" => Ok(Rarity::Epic),
+            "legendary" => Ok(Rarity::Legendary),
+            _ => Err(NoSuchRarity(s.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Display, Error)]
+#[display("no such rarity \"{_0}\" exists")]
+pub struct NoSuchRarity(#[error(not(source))] String);
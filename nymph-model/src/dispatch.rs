@@ -0,0 +1,345 @@
+//! Dispatched event models.
+//!
+//! Whenever something happens to a card or a guild, the server dispatches an
+//! [`Event`]
+//! to the guild's webhooks and to gateway clients. Both receive the event
+//! wrapped in the same [`Envelope`], so one schema serves every consumer.
+
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+
+use derive_more::{Display, Error};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Id, card::Card, event, report::Report, rule::Rule, season::Season};
+
+/// The version of the event schema.
+///
+/// Bumped whenever an existing event changes shape. New events may be added
+/// without bumping it, so consumers should ignore events they do not know.
+pub const VERSION: u32 = 1;
+
+/// A dispatched event, along with where and when it happened.
+///
+/// Serializes as the envelope's fields alongside the event's `event` tag and
+/// `data`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Envelope {
+    /// The version of the event schema, see [`VERSION`].
+    pub version: u32,
+    /// The guild the event happened in.
+    pub guild_id: Id,
+    /// When the event happened.
+    pub timestamp: NaiveDateTime,
+    /// The position of the event in the server's event log.
+    ///
+    /// Sequence numbers are shared by every guild, so a guild's events may
+    /// skip some. Pass the last one seen to the replay endpoint to catch up
+    /// on missed events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// Something that happened to a card or a guild.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "event", content = "data")]
+pub enum Event {
+    /// A card was created.
+    #[serde(rename = "card.created")]
+    CardCreated(Card),
+    /// A card was updated.
+    #[serde(rename = "card.updated")]
+    CardUpdated(Card),
+    /// A copy of a card was granted to a user.
+    #[serde(rename = "card.granted")]
+    CardGranted(CardOwnership),
+    /// A copy of a card was revoked from a user.
+    #[serde(rename = "card.revoked")]
+    CardRevoked(CardOwnership),
+    /// A copy of a card was moved from one user to another.
+    #[serde(rename = "card.transferred")]
+    CardTransferred(CardTransfer),
+    /// A card was reported by a user.
+    #[serde(rename = "card.reported")]
+    CardReported(CardReport),
+    /// A report was resolved or dismissed.
+    #[serde(rename = "report.resolved")]
+    ReportResolved(CardReport),
+    /// A user collected the set of a reward rule and was granted its reward.
+    #[serde(rename = "reward.granted")]
+    RewardGranted(RuleReward),
+    /// A user collected the set of a reward rule, in a guild that announces
+    /// completions in public.
+    ///
+    /// Dispatched right after the rule's [`Event::RewardGranted`].
+    #[serde(rename = "set.completed")]
+    SetCompleted(SetCompletion),
+    /// An event ended, and its cards were archived.
+    #[serde(rename = "event.ended")]
+    EventEnded(EventEnd),
+    /// A season was recorded, and every user's cards in the guild were reset.
+    #[serde(rename = "season.reset")]
+    SeasonReset(Season),
+}
+
+impl Event {
+    /// The kind of the event.
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::CardCreated(_) => EventKind::CardCreated,
+            Event::CardUpdated(_) => EventKind::CardUpdated,
+            Event::CardGranted(_) => EventKind::CardGranted,
+            Event::CardRevoked(_) => EventKind::CardRevoked,
+            Event::CardTransferred(_) => EventKind::CardTransferred,
+            Event::CardReported(_) => EventKind::CardReported,
+            Event::ReportResolved(_) => EventKind::ReportResolved,
+            Event::RewardGranted(_) => EventKind::RewardGranted,
+            Event::SetCompleted(_) => EventKind::SetCompleted,
+            Event::EventEnded(_) => EventKind::EventEnded,
+            Event::SeasonReset(_) => EventKind::SeasonReset,
+        }
+    }
+
+    /// The guild the event happened in.
+    pub fn guild_id(&self) -> Id {
+        match self {
+            Event::EventEnded(end) => end.event.guild_id,
+            Event::SeasonReset(season) => season.guild_id,
+            event => event.card().expect("card event").guild_id,
+        }
+    }
+
+    /// The card the event is about, if it is about one.
+    pub fn card(&self) -> Option<&Card> {
+        match self {
+            Event::CardCreated(card) | Event::CardUpdated(card) => Some(card),
+            Event::CardGranted(ownership) | Event::CardRevoked(ownership) => Some(&ownership.card),
+            Event::CardTransferred(transfer) => Some(&transfer.card),
+            Event::CardReported(report) | Event::ReportResolved(report) => Some(&report.card),
+            Event::RewardGranted(reward) => Some(&reward.card),
+            Event::SetCompleted(completion) => Some(&completion.card),
+            Event::EventEnded(_) | Event::SeasonReset(_) => None,
+        }
+    }
+
+    /// The card the event is about, if it is about one.
+    pub fn card_mut(&mut self) -> Option<&mut Card> {
+        match self {
+            Event::CardCreated(card) | Event::CardUpdated(card) => Some(card),
+            Event::CardGranted(ownership) | Event::CardRevoked(ownership) => {
+                Some(&mut ownership.card)
+            }
+            Event::CardTransferred(transfer) => Some(&mut transfer.card),
+            Event::CardReported(report) | Event::ReportResolved(report) => Some(&mut report.card),
+            Event::RewardGranted(reward) => Some(&mut reward.card),
+            Event::SetCompleted(completion) => Some(&mut completion.card),
+            Event::EventEnded(_) | Event::SeasonReset(_) => None,
+        }
+    }
+
+    /// The ids of the users the event is about, if any.
+    pub fn user_ids(&self) -> Vec<i32> {
+        match self {
+            Event::CardCreated(_) | Event::CardUpdated(_) => Vec::new(),
+            Event::CardGranted(ownership) | Event::CardRevoked(ownership) => {
+                vec![ownership.user_id]
+            }
+            Event::CardTransferred(transfer) => vec![transfer.from_id, transfer.to_id],
+            Event::CardReported(report) | Event::ReportResolved(report) => {
+                vec![report.report.reporter.id]
+            }
+            Event::RewardGranted(reward) => vec![reward.user_id],
+            Event::SetCompleted(completion) => vec![completion.user_id],
+            Event::EventEnded(_) | Event::SeasonReset(_) => Vec::new(),
+        }
+    }
+
+    /// `true` if the event may only be seen by privileged users.
+    pub fn privileged(&self) -> bool {
+        matches!(self, Event::CardReported(_) | Event::ReportResolved(_))
+    }
+}
+
+/// The data of an event that changed how many copies of a card a user owns.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CardOwnership {
+    /// The user whose copies changed.
+    pub user_id: i32,
+    /// The card, with the user's new quantity.
+    pub card: Card,
+    /// How the card was rolled, if it was granted by a roll.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roll: Option<Roll>,
+}
+
+/// How a rolled card was picked.
+///
+/// Rolls are deterministic, so the same seed and pool always pick the same
+/// cards, and a roll can be replayed to check it was fair. The pool is logged
+/// once per roll by the server, under the roll's seed.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Roll {
+    /// The seed of the roll, in hex.
+    pub seed: String,
+    /// Which of the roll's picks the card was, from `0`.
+    pub index: u32,
+}
+
+/// The data of a [`Event::CardTransferred`] event.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CardTransfer {
+    /// The user the card was moved from.
+    pub from_id: i32,
+    /// The user the card was moved to.
+    pub to_id: i32,
+    /// The card.
+    pub card: Card,
+    /// How many copies the sender owns afterwards.
+    pub from_quantity: u32,
+    /// How many copies the recipient owns afterwards.
+    pub to_quantity: u32,
+}
+
+/// The data of an event about a report.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CardReport {
+    /// The report.
+    pub report: Report,
+    /// The reported card.
+    pub card: Card,
+}
+
+/// The data of a [`Event::RewardGranted`] event.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RuleReward {
+    /// The rule whose set was collected.
+    pub rule: Rule,
+    /// The user that was rewarded.
+    pub user_id: i32,
+    /// The discord ID of the user, if they are a discord user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discord_id: Option<Id>,
+    /// The reward card, with the user's new quantity.
+    pub card: Card,
+}
+
+/// The data of a [`Event::SetCompleted`] event.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SetCompletion {
+    /// The rule whose set was collected.
+    pub rule: Rule,
+    /// The category every card of the set belongs to, if they share one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category_name: Option<String>,
+    /// The user that completed the set.
+    pub user_id: i32,
+    /// The discord ID of the user, if they are a discord user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discord_id: Option<Id>,
+    /// The channel the completion is announced in.
+    pub channel_id: Id,
+    /// The reward card, with the user's new quantity.
+    pub card: Card,
+}
+
+/// The data of a [`Event::EventEnded`] event.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EventEnd {
+    /// The event, with its summary.
+    pub event: event::Event,
+    /// The channel the summary is posted in, if the guild posts them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<Id>,
+}
+
+/// The kind of an [`Event`].
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub enum EventKind {
+    #[serde(rename = "card.created")]
+    CardCreated,
+    #[serde(rename = "card.updated")]
+    CardUpdated,
+    #[serde(rename = "card.granted")]
+    CardGranted,
+    #[serde(rename = "card.revoked")]
+    CardRevoked,
+    #[serde(rename = "card.transferred")]
+    CardTransferred,
+    #[serde(rename = "card.reported")]
+    CardReported,
+    #[serde(rename = "report.resolved")]
+    ReportResolved,
+    #[serde(rename = "reward.granted")]
+    RewardGranted,
+    #[serde(rename = "set.completed")]
+    SetCompleted,
+    #[serde(rename = "event.ended")]
+    EventEnded,
+    #[serde(rename = "season.reset")]
+    SeasonReset,
+}
+
+impl EventKind {
+    /// Creates a string representation of the kind that can be used to get
+    /// back the kind with [`FromStr`].
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            EventKind::CardCreated => "card.created",
+            EventKind::CardUpdated => "card.updated",
+            EventKind::CardGranted => "card.granted",
+            EventKind::CardRevoked => "card.revoked",
+            EventKind::CardTransferred => "card.transferred",
+            EventKind::CardReported => "card.reported",
+            EventKind::ReportResolved => "report.resolved",
+            EventKind::RewardGranted => "reward.granted",
+            EventKind::SetCompleted => "set.completed",
+            EventKind::EventEnded => "event.ended",
+            EventKind::SeasonReset => "season.reset",
+        }
+    }
+}
+
+impl TryFrom<String> for EventKind {
+    type Error = NoSuchEventKind;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl TryFrom<&str> for EventKind {
+    type Error = NoSuchEventKind;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl FromStr for EventKind {
+    type Err = NoSuchEventKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "card.created" => Ok(EventKind::CardCreated),
+            "card.updated" => Ok(EventKind::CardUpdated),
+            "card.granted" => Ok(EventKind::CardGranted),
+            "card.revoked" => Ok(EventKind::CardRevoked),
+            "card.transferred" => Ok(EventKind::CardTransferred),
+            "card.reported" => Ok(EventKind::CardReported),
+            "report.resolved" => Ok(EventKind::ReportResolved),
+            "reward.granted" => Ok(EventKind::RewardGranted),
+            "set.completed" => Ok(EventKind::SetCompleted),
+            "event.ended" => Ok(EventKind::EventEnded),
+            "season.reset" => Ok(EventKind::SeasonReset),
+            _ => Err(NoSuchEventKind(s.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Display, Error)]
+#[display("no such event kind \"{_0}\" exists")]
+pub struct NoSuchEventKind(#[error(not(source))] String);
@@ -0,0 +1,46 @@
+//! Drop table data models.
+//!
+//! A guild's drop table lists the cards a pack may pull, each with a weight:
+//! a card weighing twice as much as another is pulled twice as often. A card
+//! may also be capped, so it is no longer pulled for a user that owns enough
+//! copies of it.
+
+use chrono::NaiveDateTime;
+
+use serde::{Deserialize, Serialize};
+
+/// The most a single card may weigh.
+pub const MAX_WEIGHT: u32 = 1_000_000;
+
+/// The most cards a drop table may list.
+pub const MAX_ENTRIES: usize = 500;
+
+/// How many pulls a preview simulates if not told otherwise.
+pub const DEFAULT_PREVIEW_PULLS: u32 = 1000;
+
+/// The most pulls a preview may simulate.
+pub const MAX_PREVIEW_PULLS: u32 = 100_000;
+
+/// A guild's drop table.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DropTable {
+    /// The cards that may be pulled, by card id.
+    pub entries: Vec<DropTableEntry>,
+    /// The weight of every entry added up.
+    pub total_weight: u64,
+}
+
+/// A card that may be pulled.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DropTableEntry {
+    /// The card pulled.
+    pub card_id: i32,
+    /// How likely the card is pulled, relative to the other entries.
+    pub weight: u32,
+    /// How many copies of the card a user may own before it is no longer
+    /// pulled for them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_copies: Option<u32>,
+    /// When the entry was last changed.
+    pub updated_at: NaiveDateTime,
+}
@@ -6,6 +6,11 @@ use serde::{Deserialize, Serialize};
 
 use derive_more::Error;
 
+use crate::lint::LintViolation;
+
+/// The header a request's ID is sent and returned in.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
 /// API error.
 #[derive(Clone, Debug, Deserialize, Serialize, Error)]
 pub struct ApiError {
@@ -13,6 +18,9 @@ pub struct ApiError {
     pub code: ErrorCode,
     /// A user-friendly message of the error.
     pub message: String,
+    /// The ID of the request that failed, to find it in the server's logs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl Display for ApiError {
@@ -21,6 +29,18 @@ impl Display for ApiError {
     }
 }
 
+/// An API error caused by card content breaking a guild's lint rules.
+///
+/// This is a superset of [`ApiError`], so it can be read as one.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LintError {
+    /// The API error.
+    #[serde(flatten)]
+    pub error: ApiError,
+    /// Every lint rule the content broke.
+    pub violations: Vec<LintViolation>,
+}
+
 /// An API error code.
 #[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
 #[serde(from = "u32", into = "u32")]
@@ -40,16 +60,30 @@ pub enum ErrorCode {
     Hidden,
     /// The card is already owned by the user.
     InvalidTransfer,
+    /// The card's content breaks the guild's lint rules.
+    LintViolation,
     /// The user is unauthorized.
     Unauthenticated,
     /// The user's credentials have expired or are otherwise bad.
     BadCredentials,
     /// The user does not have the right permissions to access a resource.
     InsufficientPermissions,
+    /// The client made too many requests.
+    RateLimited,
+    /// The resource changed since the client last fetched it.
+    PreconditionFailed,
+    /// The report has already been resolved or dismissed.
+    ReportClosed,
+    /// The card cannot be granted until the user collects what it requires.
+    UnmetPrerequisites,
     /// An internal server error occured.
     ///
     /// This is a bug, usually.
     InternalServerError,
+    /// The request took too long and was aborted.
+    Timeout,
+    /// The request could change data while the server is in maintenance.
+    Maintenance,
     /// Any other error code.
     Other(u32),
 }
@@ -66,8 +100,15 @@ impl From<u32> for ErrorCode {
             4006 => ErrorCode::Hidden,
             4007 => ErrorCode::InsufficientPermissions,
             4008 => ErrorCode::InvalidTransfer,
+            4009 => ErrorCode::LintViolation,
             4010 => ErrorCode::BadCredentials,
+            4011 => ErrorCode::RateLimited,
+            4012 => ErrorCode::PreconditionFailed,
+            4013 => ErrorCode::ReportClosed,
+            4014 => ErrorCode::UnmetPrerequisites,
             5000 => ErrorCode::InternalServerError,
+            5001 => ErrorCode::Timeout,
+            5002 => ErrorCode::Maintenance,
             other => ErrorCode::Other(other),
         }
     }
@@ -85,8 +126,15 @@ impl From<ErrorCode> for u32 {
             ErrorCode::Hidden => 4006,
             ErrorCode::InsufficientPermissions => 4007,
             ErrorCode::InvalidTransfer => 4008,
+            ErrorCode::LintViolation => 4009,
             ErrorCode::BadCredentials => 4010,
+            ErrorCode::RateLimited => 4011,
+            ErrorCode::PreconditionFailed => 4012,
+            ErrorCode::ReportClosed => 4013,
+            ErrorCode::UnmetPrerequisites => 4014,
             ErrorCode::InternalServerError => 5000,
+            ErrorCode::Timeout => 5001,
+            ErrorCode::Maintenance => 5002,
             ErrorCode::Other(other) => other,
         }
     }
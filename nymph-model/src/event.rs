@@ -0,0 +1,44 @@
+//! Event data models.
+
+use chrono::NaiveDateTime;
+
+use serde::{Deserialize, Serialize};
+
+use super::Id;
+
+/// A time-limited event.
+///
+/// An event's cards may only be granted while the event is running, and are
+/// archived once it ends.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Event {
+    /// The unique identifier of the event.
+    pub id: i32,
+    /// The guild the event belongs to.
+    pub guild_id: Id,
+    /// The event's name.
+    pub name: String,
+    /// When the event starts.
+    pub starts_at: NaiveDateTime,
+    /// When the event ends.
+    pub ends_at: NaiveDateTime,
+    /// The IDs of the cards that are only active during the event.
+    pub cards: Vec<i32>,
+    /// The event's summary.
+    ///
+    /// Only appears once the event has ended and its cards were archived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<EventSummary>,
+}
+
+/// A summary of an event, written when it ends.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct EventSummary {
+    /// When the event's cards were archived.
+    pub ended_at: NaiveDateTime,
+    /// How many users own at least one of the event's cards.
+    pub participants: u32,
+    /// How many copies of the event's cards were granted while it ran,
+    /// including ones revoked since.
+    pub cards_granted: u32,
+}
@@ -0,0 +1,73 @@
+//! Gateway protocol models.
+//!
+//! The gateway is a WebSocket that pushes card events to clients as they
+//! happen. Every message is a JSON text frame tagged by its `op`.
+//!
+//! 1. The server greets a new connection with [`GatewayMessage::Hello`].
+//! 2. The client sends [`GatewayCommand::Subscribe`] to start receiving
+//!    [`GatewayMessage::Dispatch`] messages. To resume a dropped connection,
+//!    it passes the `seq` of the last dispatch it received.
+//! 3. The client sends [`GatewayCommand::Heartbeat`] at least every
+//!    `heartbeat_interval` milliseconds, or the server closes the connection.
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    Id,
+    dispatch::{Envelope, EventKind},
+};
+
+/// A message sent by the server.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum GatewayMessage {
+    /// Sent when the client connects.
+    Hello {
+        /// How often the client must send heartbeats, in milliseconds.
+        heartbeat_interval: u64,
+    },
+    /// An event happened.
+    Dispatch {
+        /// The sequence number of the dispatch, used to resume.
+        seq: u64,
+        #[serde(flatten)]
+        envelope: Box<Envelope>,
+    },
+    /// Acknowledges a heartbeat.
+    HeartbeatAck,
+    /// A subscription resumed after replaying missed dispatches.
+    Resumed {
+        /// How many missed dispatches were replayed.
+        replayed: u64,
+    },
+    /// Dispatches were lost and cannot be replayed.
+    ///
+    /// The client should refetch anything it caches over the REST API, then
+    /// subscribe again without a `seq`.
+    InvalidSession,
+}
+
+/// A command sent by the client.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum GatewayCommand {
+    /// Keeps the connection alive.
+    Heartbeat,
+    /// Starts, or replaces, the client's subscription.
+    Subscribe {
+        /// The guilds to receive events from.
+        ///
+        /// If empty, events from every guild are received.
+        #[serde(default)]
+        guilds: Vec<Id>,
+        /// The events to receive.
+        ///
+        /// If empty, every event is received.
+        #[serde(default)]
+        events: Vec<EventKind>,
+        /// The sequence number of the last dispatch received, to resume a
+        /// dropped connection.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        seq: Option<u64>,
+    },
+}
@@ -0,0 +1,44 @@
+//! Impersonation.
+//!
+//! An operator can make a request as another user by sending the user's id
+//! in the [`ACT_AS_HEADER`], along with why in the [`ACT_AS_REASON_HEADER`].
+//! The request then sees exactly what the user would, which helps when
+//! reproducing issues only a single user runs into. Only reads may be made
+//! as another user, and every one is kept in an audit trail.
+
+use chrono::NaiveDateTime;
+
+use serde::{Deserialize, Serialize};
+
+use super::user::User;
+
+/// The header the id of the user a request is made as is sent in.
+pub const ACT_AS_HEADER: &str = "x-act-as";
+
+/// The header the reason for a request made as another user is sent in.
+pub const ACT_AS_REASON_HEADER: &str = "x-act-as-reason";
+
+/// The longest reason that is accepted.
+pub const MAX_REASON_LEN: usize = 512;
+
+/// A request an operator made as another user.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Impersonation {
+    /// The unique ID of the entry.
+    pub id: i32,
+    /// The operator that made the request.
+    pub actor: User,
+    /// The user the request was made as.
+    pub user: User,
+    /// Why the request was made.
+    pub reason: String,
+    /// The HTTP method of the request.
+    pub method: String,
+    /// The path of the request, without its query.
+    pub path: String,
+    /// The ID of the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// When the request was made.
+    pub inserted_at: NaiveDateTime,
+}
@@ -1,12 +1,30 @@
 //! Nymph data representations.
 
+pub mod announcement;
 pub mod card;
+pub mod dispatch;
+pub mod drop_table;
 pub mod error;
+pub mod event;
+pub mod gateway;
+pub mod impersonation;
+pub mod lint;
+pub mod policy;
+pub mod proxy;
+pub mod report;
 pub mod request;
 pub mod response;
+pub mod rule;
+pub mod season;
+pub mod syndication;
+pub mod template;
+pub mod trade;
+pub mod trade_in;
 pub mod user;
+pub mod view;
+pub mod webhook;
 
-pub use error::{ApiError, ErrorCode};
+pub use error::{ApiError, ErrorCode, LintError};
 
 use std::num::NonZeroU64;
 
@@ -51,3 +69,15 @@ impl Serialize for Id {
         self.0.get().to_string().serialize(serializer)
     }
 }
+
+/// Deserializes an optional field that distinguishes between being missing
+/// (`None`) and being `null` (`Some(None)`).
+///
+/// Use with `#[serde(default)]`.
+pub fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,66 @@
+//! Card content linting.
+
+use std::fmt::{self, Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+/// A guild's card content lint rules.
+///
+/// Rules are evaluated whenever a card is created, updated or imported.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct LintRules {
+    /// The maximum length of a card's content, in characters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u32>,
+    /// Words or phrases that may not appear in a card's content.
+    ///
+    /// These are matched case-insensitively, on word boundaries.
+    #[serde(default)]
+    pub banned_words: Vec<String>,
+    /// Markdown headings every card's content must have.
+    ///
+    /// These are matched case-insensitively, ignoring the heading level.
+    #[serde(default)]
+    pub required_sections: Vec<String>,
+}
+
+/// A single broken lint rule.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(tag = "rule", rename_all = "kebab-case")]
+pub enum LintViolation {
+    /// The content is longer than the maximum length.
+    MaxLength {
+        /// The maximum length.
+        max_length: u32,
+        /// The length of the content.
+        length: u32,
+    },
+    /// The content contains a banned word.
+    BannedWord {
+        /// The banned word.
+        word: String,
+    },
+    /// The content is missing a required section.
+    RequiredSection {
+        /// The missing section.
+        section: String,
+    },
+}
+
+impl Display for LintViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LintViolation::MaxLength { max_length, length } => write!(
+                f,
+                "Content is {} characters long, but can be at most {}.",
+                length, max_length
+            ),
+            LintViolation::BannedWord { word } => {
+                write!(f, "Content contains banned word `{}`.", word)
+            }
+            LintViolation::RequiredSection { section } => {
+                write!(f, "Content is missing required section `{}`.", section)
+            }
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! Route authorization policies.
+
+use serde::{Deserialize, Serialize};
+
+/// Who may call a route.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Access {
+    /// Any authenticated user.
+    Authenticated,
+    /// The user named by the route's `{user_id}`, or a managed user.
+    Owner,
+    /// Only managed users, like API keys and the bot.
+    Managed,
+}
+
+/// The policy of a single route.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RoutePolicy {
+    /// The HTTP method of the route.
+    pub method: String,
+    /// The route's path, with parameters in braces.
+    pub path: String,
+    /// Who may call the route.
+    pub access: Access,
+    /// Any checks the route makes on top of its access.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
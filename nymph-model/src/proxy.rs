@@ -0,0 +1,87 @@
+//! Signed proxy assertions.
+//!
+//! A managed client can act for a Discord user by sending its API key along
+//! with a [`ProxyAssertion`] in the [`PROXY_FOR_HEADER`]. The assertion is
+//! signed with a secret shared between the client and the server, so the
+//! client never has to hold the user's access token.
+//!
+//! The signature is the hex-encoded HMAC-SHA256 of
+//! [`ProxyAssertion::message`].
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use derive_more::{Display, Error};
+
+use super::Id;
+
+/// The header a proxy assertion is sent in.
+pub const PROXY_FOR_HEADER: &str = "x-proxy-for";
+
+/// How far the timestamp of an assertion may be from the server's clock, in
+/// seconds.
+pub const MAX_SKEW: i64 = 60;
+
+/// A signed claim that a request is made for a Discord user.
+///
+/// Formats as `{discord_id}.{timestamp}.{signature}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProxyAssertion {
+    /// The Discord id of the user the request is made for.
+    pub discord_id: Id,
+    /// When the assertion was made, as a Unix timestamp in seconds.
+    pub timestamp: i64,
+    /// The hex-encoded signature of the assertion.
+    pub signature: String,
+}
+
+impl ProxyAssertion {
+    /// The message that is signed.
+    pub fn message(discord_id: Id, timestamp: i64) -> String {
+        format!("{}.{}", discord_id.get(), timestamp)
+    }
+}
+
+impl Display for ProxyAssertion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}",
+            self.discord_id.get(),
+            self.timestamp,
+            self.signature
+        )
+    }
+}
+
+impl FromStr for ProxyAssertion {
+    type Err = InvalidProxyAssertion;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().splitn(3, '.');
+
+        let discord_id = parts
+            .next()
+            .and_then(|s| s.parse::<u64>().ok())
+            .and_then(Id::new)
+            .ok_or(InvalidProxyAssertion)?;
+        let timestamp = parts
+            .next()
+            .and_then(|s| s.parse::<i64>().ok())
+            .ok_or(InvalidProxyAssertion)?;
+        let signature = parts
+            .next()
+            .filter(|s| !s.is_empty())
+            .ok_or(InvalidProxyAssertion)?;
+
+        Ok(ProxyAssertion {
+            discord_id,
+            timestamp,
+            signature: signature.to_owned(),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Display, Error)]
+#[display("malformed proxy assertion")]
+pub struct InvalidProxyAssertion;
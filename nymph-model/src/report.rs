@@ -0,0 +1,94 @@
+//! Content report data models.
+
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+
+use derive_more::{Display, Error};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Id, user::User};
+
+/// A report flagging a card as inappropriate.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Report {
+    /// The unique identifier of the report.
+    pub id: i32,
+    /// The guild the reported card belongs to.
+    pub guild_id: Id,
+    /// The ID of the reported card.
+    pub card_id: i32,
+    /// The reported card's name.
+    pub card_name: String,
+    /// The user that made the report.
+    pub reporter: User,
+    /// Why the card was reported.
+    pub reason: String,
+    /// The report's status.
+    pub status: ReportStatus,
+    /// The user that resolved or dismissed the report.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_by: Option<User>,
+    /// When the report was resolved or dismissed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+/// Report status.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReportStatus {
+    /// The report is waiting to be reviewed.
+    Open,
+    /// The report was reviewed, and something was done about it.
+    Resolved,
+    /// The report was reviewed, and nothing needed to be done.
+    Dismissed,
+}
+
+impl ReportStatus {
+    /// Creates a string representation of the status that can be used to get
+    /// back the status with [`FromStr`].
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            ReportStatus::Open => "open",
+            ReportStatus::Resolved => "resolved",
+            ReportStatus::Dismissed => "dismissed",
+        }
+    }
+}
+
+impl TryFrom<String> for ReportStatus {
+    type Error = NoSuchReportStatus;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl TryFrom<&str> for ReportStatus {
+    type Error = NoSuchReportStatus;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl FromStr for ReportStatus {
+    type Err = NoSuchReportStatus;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(ReportStatus::Open),
+            "resolved" => Ok(ReportStatus::Resolved),
+            "dismissed" => Ok(ReportStatus::Dismissed),
+            _ => Err(NoSuchReportStatus(s.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Display, Error)]
+#[display("no such report status \"{_0}\" exists")]
+pub struct NoSuchReportStatus(#[error(not(source))] String);
@@ -0,0 +1,58 @@
+//! API operator request models.
+
+use serde::{Deserialize, Serialize};
+
+/// Request body for replacing the server's tracing filter.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UpdateLogFilterRequest {
+    /// The new filter directives, like `info,sqlx=debug`.
+    pub filter: String,
+}
+
+/// Request body for entering or leaving maintenance.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UpdateMaintenanceRequest {
+    /// If the server should be in maintenance.
+    pub enabled: bool,
+}
+
+/// Query parameters for creating a card template.
+///
+/// The template's cards are sent as the body, in any format the bulk import
+/// endpoint accepts.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CreateTemplateQuery {
+    /// The template's name.
+    pub name: String,
+    /// What the template is about.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Query parameters for listing recorded requests.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RecordedRequestsQuery {
+    /// Only list the request with this ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// How many requests to list at most, newest first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
+
+/// Query parameters for listing requests made as other users.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ImpersonationsQuery {
+    /// Only list requests made by this operator.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor_id: Option<i32>,
+    /// Only list requests made as this user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i32>,
+    /// Only list requests older than the entry of this ID.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<i32>,
+    /// How many requests to list at most, newest first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
@@ -0,0 +1,20 @@
+//! API audit log request models.
+
+use serde::{Deserialize, Serialize};
+
+/// Query for listing a guild's audit log.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AuditLogQuery {
+    /// Only list entries caused by or about this user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i32>,
+    /// Only list entries about this card.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub card_id: Option<i32>,
+    /// Only list entries with a lesser sequence number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<u64>,
+    /// How many entries should be returned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
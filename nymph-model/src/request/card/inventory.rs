@@ -10,6 +10,9 @@ pub struct ListInventoryQuery {
     /// Filter by guild.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub guild_id: Option<Id>,
+    /// Only list cards the user has favorited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favorites: Option<bool>,
     /// The query's page.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub page: Option<u32>,
@@ -18,9 +21,88 @@ pub struct ListInventoryQuery {
     pub count: Option<u32>,
 }
 
+/// Export cards owned by user endpoint.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ExportInventoryQuery {
+    /// Filter by guild.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guild_id: Option<Id>,
+    /// The format of the export.
+    ///
+    /// Defaults to [`ExportFormat::Csv`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<ExportFormat>,
+}
+
+/// Query for the `GET /guilds/{guild_id}/ownership/export` endpoint.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ExportOwnershipQuery {
+    /// The format of the export.
+    ///
+    /// Defaults to [`MatrixFormat::Csv`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<MatrixFormat>,
+}
+
+/// A format an ownership matrix may be exported in.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MatrixFormat {
+    /// Comma-separated values, with a row for each card and a column for
+    /// each user.
+    ///
+    /// Guilds with too many cards and users for a dense matrix can only be
+    /// exported as JSON.
+    #[default]
+    Csv,
+    /// An [`OwnershipMatrix`](crate::response::card::OwnershipMatrix).
+    Json,
+}
+
+/// A format an inventory may be exported in.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportFormat {
+    /// Comma-separated values, with a header row.
+    #[default]
+    Csv,
+}
+
 /// A request for granting a card.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct GrantRequest {
     /// The ID of the card to grant.
     pub card_id: i32,
 }
+
+/// A request for trading in duplicates of a card.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TradeInRequest {
+    /// How many duplicates to trade in.
+    pub count: u32,
+}
+
+/// A request for rating a card.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReactionRequest {
+    /// The rating, from `1` to `5`.
+    pub rating: u8,
+}
+
+/// A request for transferring a card to another user.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TransferRequest {
+    /// The ID of the user receiving the card.
+    pub to_id: i32,
+}
+
+/// List owners of a card endpoint.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ListOwnersQuery {
+    /// The query's page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    /// How many results should be returned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
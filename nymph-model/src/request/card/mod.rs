@@ -2,14 +2,66 @@
 
 pub mod inventory;
 
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+
+use derive_more::{Display, Error};
+
 use serde::{Deserialize, Serialize};
 
+use crate::card::{NoSuchVisibility, Rarity, Visibility};
+
 /// List cards endpoint.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ListCardsQuery {
     /// Search query.
+    ///
+    /// Matches card names containing the query. If the query has any `*`, it
+    /// must match the whole name instead, with each `*` matching any run of
+    /// characters; `DRAGON*` finds names starting with `DRAGON`. `%` and `_`
+    /// are matched literally.
+    ///
+    /// The query may also hold `field:value` filters, like
+    /// `category:forest owned:false dragon`. The `category`, `owned`,
+    /// `rarity`, `visibility` and `content` fields are understood, and work
+    /// like their params, which take precedence. Values with spaces may be
+    /// put in double quotes.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub query: Option<String>,
+    /// Search for a phrase in the card's content.
+    ///
+    /// Matches are ranked by relevance unless another sort is asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub q_content: Option<String>,
+    /// Filter by rarity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rarity: Option<Rarity>,
+    /// Filter by category.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Filter by whether the caller owns the card.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owned: Option<bool>,
+    /// Filter by visibility.
+    ///
+    /// Only privileged callers may list cards that are not public.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<VisibilityFilter>,
+    /// Only include these fields of each card.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<FieldSet>,
+    /// Load these related cards of each card.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expand: Option<ExpandSet>,
+    /// How to order the cards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<CardSort>,
+    /// Which way to order the cards.
+    ///
+    /// Only applies to the `name`, `created_at` and `updated_at` sorts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order: Option<SortOrder>,
     /// The query's page.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub page: Option<u32>,
@@ -17,3 +69,285 @@ pub struct ListCardsQuery {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub count: Option<u32>,
 }
+
+/// Query for looking up a card by its emoji.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LookupCardQuery {
+    /// The emoji of the card.
+    pub emoji: String,
+}
+
+/// Show card endpoint.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ShowCardQuery {
+    /// Only include these fields of the card.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fields: Option<FieldSet>,
+    /// Load these related cards of the card.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expand: Option<ExpandSet>,
+}
+
+/// A set of card fields to include in a sparse response.
+///
+/// Appears in queries as a comma-separated list, like `id,name`. See
+/// [`Card::FIELDS`](crate::card::Card::FIELDS) for the fields that may be
+/// named.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(from = "String", into = "String")]
+pub struct FieldSet(pub Vec<String>);
+
+impl From<String> for FieldSet {
+    fn from(value: String) -> Self {
+        FieldSet(
+            value
+                .split(',')
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_owned())
+                .collect(),
+        )
+    }
+}
+
+impl From<FieldSet> for String {
+    fn from(value: FieldSet) -> Self {
+        value.0.join(",")
+    }
+}
+
+/// A set of related cards to load alongside a card.
+///
+/// Appears in queries as a comma-separated list, like `upgrades,downgrade`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ExpandSet(pub Vec<Expand>);
+
+impl TryFrom<String> for ExpandSet {
+    type Error = NoSuchExpand;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse())
+            .collect::<Result<Vec<_>, _>>()
+            .map(ExpandSet)
+    }
+}
+
+impl From<ExpandSet> for String {
+    fn from(value: ExpandSet) -> Self {
+        value
+            .0
+            .iter()
+            .map(|expand| expand.to_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// A related card that can be loaded alongside a card.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Expand {
+    /// The cards that upgrade from the card.
+    Upgrades,
+    /// The card the card upgrades from.
+    Downgrade,
+}
+
+impl Expand {
+    /// Creates a string representation of the relation that can be used to
+    /// get back the relation with [`FromStr`].
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Expand::Upgrades => "upgrades",
+            Expand::Downgrade => "downgrade",
+        }
+    }
+}
+
+impl FromStr for Expand {
+    type Err = NoSuchExpand;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "upgrades" => Ok(Expand::Upgrades),
+            "downgrade" => Ok(Expand::Downgrade),
+            _ => Err(NoSuchExpand(s.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Display, Error)]
+#[display("no such relation \"{_0}\" exists")]
+pub struct NoSuchExpand(#[error(not(source))] String);
+
+/// How listed cards are ordered.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CardSort {
+    /// Closest matches to the search first, then oldest first.
+    #[default]
+    Relevance,
+    /// Highest rated first, then by relevance.
+    TopRated,
+    /// By name.
+    Name,
+    /// By when the card was created.
+    CreatedAt,
+    /// By when the card was last updated.
+    UpdatedAt,
+}
+
+impl CardSort {
+    /// Creates a string representation of the sort, as it appears in queries.
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            CardSort::Relevance => "relevance",
+            CardSort::TopRated => "top_rated",
+            CardSort::Name => "name",
+            CardSort::CreatedAt => "created_at",
+            CardSort::UpdatedAt => "updated_at",
+        }
+    }
+}
+
+/// Which way listed cards are ordered.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    /// Smallest, earliest or alphabetically first to last.
+    #[default]
+    Asc,
+    /// Largest, latest or alphabetically last to first.
+    Desc,
+}
+
+/// A set of visibilities to filter cards by.
+///
+/// Appears in queries as a comma-separated list, like `public,hidden`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct VisibilityFilter(pub Vec<Visibility>);
+
+impl VisibilityFilter {
+    /// Checks if the filter only lets public cards through.
+    pub fn is_public(&self) -> bool {
+        self.0.iter().all(|visibility| visibility.is_public())
+    }
+}
+
+impl TryFrom<String> for VisibilityFilter {
+    type Error = NoSuchVisibility;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value
+            .split(',')
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse())
+            .collect::<Result<Vec<_>, _>>()
+            .map(VisibilityFilter)
+    }
+}
+
+impl From<VisibilityFilter> for String {
+    fn from(value: VisibilityFilter) -> Self {
+        value
+            .0
+            .iter()
+            .map(|visibility| visibility.to_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Most viewed cards endpoint.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PopularCardsQuery {
+    /// How many days back views are counted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub days: Option<u32>,
+    /// How many cards should be returned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
+
+/// Request body for creating a card.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CreateCardRequest {
+    /// The card's name.
+    pub name: String,
+    /// The emoji identifying the card.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    /// The card's category.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category_name: Option<String>,
+    /// The card's content in Markdown.
+    pub content: String,
+    /// The card's visibility. Cards are private by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<Visibility>,
+    /// The card's rarity tier. Cards are common by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rarity: Option<Rarity>,
+}
+
+/// Request body for updating a card.
+///
+/// Only the given fields are updated.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct UpdateCardRequest {
+    /// The card's new name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The card's new emoji, or `null` to remove the card's emoji.
+    #[serde(
+        default,
+        deserialize_with = "crate::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub emoji: Option<Option<String>>,
+    /// The card's new category, or `null` to remove the card's category.
+    #[serde(
+        default,
+        deserialize_with = "crate::double_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub category_name: Option<Option<String>>,
+    /// The card's new content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// The card's new visibility.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub visibility: Option<Visibility>,
+    /// The card's new rarity tier.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rarity: Option<Rarity>,
+}
+
+/// Request body for the bulk archive endpoint.
+///
+/// Cards matching *all* of the given filters are archived. At least one
+/// filter must be given.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ArchiveCardsRequest {
+    /// Archive cards belonging to this category.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category_name: Option<String>,
+    /// Archive cards created before this time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_before: Option<NaiveDateTime>,
+}
+
+/// Query for the bulk import endpoint.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ImportCardsQuery {
+    /// Validate the file and return the report without importing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
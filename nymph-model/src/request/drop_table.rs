@@ -0,0 +1,30 @@
+//! API drop table request models.
+
+use serde::{Deserialize, Serialize};
+
+/// Request body for adding or changing a card of a drop table.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UpdateDropTableEntryRequest {
+    /// How likely the card is pulled, relative to the other entries.
+    pub weight: u32,
+    /// How many copies of the card a user may own before it is no longer
+    /// pulled for them.
+    ///
+    /// Left out, the card is never capped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_copies: Option<u32>,
+}
+
+/// Query for the `GET /guilds/{guild_id}/drop-table/preview` endpoint.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DropTablePreviewQuery {
+    /// How many pulls are simulated.
+    ///
+    /// Defaults to
+    /// [`DEFAULT_PREVIEW_PULLS`](crate::drop_table::DEFAULT_PREVIEW_PULLS).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pulls: Option<u32>,
+    /// The seed of a previous preview, to simulate the same pulls again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<String>,
+}
@@ -0,0 +1,19 @@
+//! API event request models.
+
+use chrono::NaiveDateTime;
+
+use serde::{Deserialize, Serialize};
+
+/// Request body for creating an event.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CreateEventRequest {
+    /// The event's name.
+    pub name: String,
+    /// When the event starts.
+    pub starts_at: NaiveDateTime,
+    /// When the event ends.
+    pub ends_at: NaiveDateTime,
+    /// The IDs of the cards that are only active during the event.
+    #[serde(default)]
+    pub cards: Vec<i32>,
+}
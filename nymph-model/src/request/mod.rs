@@ -1,4 +1,16 @@
 //! API request models.
 
+pub mod admin;
+pub mod audit;
 pub mod card;
+pub mod drop_table;
+pub mod event;
+pub mod report;
+pub mod rule;
+pub mod season;
+pub mod syndication;
+pub mod telemetry;
+pub mod trade;
 pub mod user;
+pub mod view;
+pub mod webhook;
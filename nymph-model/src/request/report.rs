@@ -0,0 +1,36 @@
+//! API report request models.
+
+use serde::{Deserialize, Serialize};
+
+use crate::report::ReportStatus;
+
+/// A request for reporting a card.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CreateReportRequest {
+    /// Why the card is reported.
+    pub reason: String,
+}
+
+/// Query for listing a guild's reports.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ListReportsQuery {
+    /// Only list reports with this status.
+    ///
+    /// Defaults to [`ReportStatus::Open`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ReportStatus>,
+    /// The query's page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    /// How many results should be returned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
+
+/// A request for closing a report.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ResolveReportRequest {
+    /// What the report is closed as, either
+    /// [`ReportStatus::Resolved`] or [`ReportStatus::Dismissed`].
+    pub status: ReportStatus,
+}
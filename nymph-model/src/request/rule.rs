@@ -0,0 +1,30 @@
+//! API reward rule request models.
+
+use serde::{Deserialize, Serialize};
+
+/// Request body for creating a reward rule.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CreateRuleRequest {
+    /// The rule's name.
+    pub name: String,
+    /// The IDs of the cards a user must collect.
+    pub cards: Vec<i32>,
+    /// The ID of the card granted once the set is collected.
+    pub reward_id: i32,
+}
+
+/// Request body for updating a reward rule.
+///
+/// Fields that are left out are not changed.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct UpdateRuleRequest {
+    /// The rule's new name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The new cards a user must collect.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cards: Option<Vec<i32>>,
+    /// The new card granted once the set is collected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reward_id: Option<i32>,
+}
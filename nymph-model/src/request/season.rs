@@ -0,0 +1,25 @@
+//! API season request models.
+
+use serde::{Deserialize, Serialize};
+
+/// Request body for recording a season.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CreateSeasonRequest {
+    /// The season's name.
+    pub name: String,
+    /// Whether or not to reset every user's cards once the season is
+    /// recorded.
+    #[serde(default)]
+    pub reset: bool,
+}
+
+/// Query for the `GET /users/{user_id}/seasons/{season_id}/cards` endpoint.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ListSeasonCardsQuery {
+    /// The query's page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+    /// How many results should be returned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
@@ -0,0 +1,14 @@
+//! API card syndication request models.
+
+use serde::{Deserialize, Serialize};
+
+use crate::Id;
+
+/// Request body for subscribing to another guild's category.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CreateSyndicationRequest {
+    /// The guild the cards belong to.
+    pub source_guild_id: Id,
+    /// The category to subscribe to.
+    pub category_name: String,
+}
@@ -0,0 +1,26 @@
+//! API telemetry request models.
+
+use serde::{Deserialize, Serialize};
+
+use crate::Id;
+
+/// Request body for reporting every guild a bot serves.
+///
+/// Guilds missing from the report are marked as departed.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReportGuildsRequest {
+    /// The guilds.
+    pub guilds: Vec<ReportedGuild>,
+}
+
+/// A guild a bot serves.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReportedGuild {
+    /// The guild's ID.
+    pub id: Id,
+    /// The guild's name.
+    pub name: String,
+    /// How many members the guild has, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member_count: Option<u64>,
+}
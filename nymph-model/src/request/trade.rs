@@ -0,0 +1,32 @@
+//! API trade request models.
+
+use serde::{Deserialize, Serialize};
+
+use crate::Id;
+
+/// A request for opening a trade.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OpenTradeRequest {
+    /// The guild the traded cards belong to.
+    pub guild_id: Id,
+    /// The ID of the user the trade is offered to.
+    pub recipient_id: i32,
+    /// The cards the initiator gives to the recipient.
+    #[serde(default)]
+    pub offered: Vec<TradeItemRequest>,
+    /// The cards the recipient gives to the initiator.
+    #[serde(default)]
+    pub requested: Vec<TradeItemRequest>,
+}
+
+/// A card given up by one side of a trade.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TradeItemRequest {
+    /// The ID of the card.
+    pub card_id: i32,
+    /// How many copies of the card are given up.
+    ///
+    /// Defaults to `1`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<u32>,
+}
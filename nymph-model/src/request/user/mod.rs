@@ -18,3 +18,48 @@ pub struct UpdateDiscordUserRequest {
     /// Whether or not to generate a token for use in proxy.
     pub generate_token: bool,
 }
+
+/// Request body for the `POST /users/discord/batch` endpoint.
+///
+/// Like [`UpdateDiscordUserRequest`], but for many users at once.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BatchUpdateDiscordUsersRequest {
+    /// The users to update.
+    pub users: Vec<DiscordUser>,
+    /// The discord IDs of users that could not be looked up.
+    ///
+    /// They are not listed as stale again until their display names are as
+    /// old as the `max_age` asked for.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed: Vec<Id>,
+    /// Whether or not to generate tokens for use in proxy.
+    pub generate_tokens: bool,
+}
+
+/// A single user in a [`BatchUpdateDiscordUsersRequest`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DiscordUser {
+    /// The discord ID of the user.
+    pub discord_id: Id,
+    /// The user's current username.
+    pub display_name: String,
+}
+
+/// Query for the `GET /users/discord/stale` endpoint.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct StaleDiscordUsersQuery {
+    /// How many users to list at most.
+    pub count: Option<u32>,
+    /// How long ago a display name must have been refreshed to be stale, in
+    /// hours.
+    pub max_age: Option<u32>,
+    /// How recently a user must have been active to be listed, in days.
+    pub active_within: Option<u32>,
+}
+
+/// Query for the `GET /users/{user_id}/progress` endpoint.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProgressQuery {
+    /// The guild to count cards in.
+    pub guild_id: Id,
+}
@@ -0,0 +1,17 @@
+//! API view state request models.
+
+use serde::{Deserialize, Serialize};
+
+use serde_json::Value;
+
+/// A request for storing a view.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CreateViewRequest {
+    /// The state to store.
+    pub data: Value,
+    /// How long the view lives, in seconds.
+    ///
+    /// Defaults to [`DEFAULT_VIEW_TTL`](crate::view::DEFAULT_VIEW_TTL).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u32>,
+}
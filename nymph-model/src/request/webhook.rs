@@ -0,0 +1,21 @@
+//! API webhook request models.
+
+use serde::{Deserialize, Serialize};
+
+/// Request body for registering a webhook.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CreateWebhookRequest {
+    /// The URL payloads are posted to.
+    pub url: String,
+}
+
+/// Query for replaying a guild's events.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReplayQuery {
+    /// Only events with a greater sequence number are returned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<u64>,
+    /// How many events should be returned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<u32>,
+}
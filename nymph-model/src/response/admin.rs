@@ -0,0 +1,78 @@
+//! API operator responses.
+
+use chrono::NaiveDateTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::dispatch::Roll;
+
+/// The server's current tracing filter.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LogFilterResponse {
+    /// The filter directives.
+    pub filter: String,
+}
+
+/// Whether the server is in maintenance.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MaintenanceResponse {
+    /// If requests that could change data are refused.
+    pub enabled: bool,
+}
+
+/// A replayed roll.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RollReplayResponse {
+    /// The sequence number of the logged grant.
+    pub seq: u64,
+    /// The user the card was granted to.
+    pub user_id: i32,
+    /// The ID of the card the log says was granted.
+    pub card_id: i32,
+    /// The ID of the card the replayed roll picks.
+    pub replayed_card_id: i32,
+    /// If the replay picked the same card.
+    pub verified: bool,
+    /// The roll, as it was logged.
+    pub roll: Roll,
+    /// The IDs of the cards the roll picked from, in order.
+    pub pool: Vec<i32>,
+}
+
+/// A finished database backup.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BackupResponse {
+    /// Where the backup was written on the server.
+    pub path: String,
+    /// The size of the backup, in bytes.
+    pub size: u64,
+    /// Old backups removed to make room for this one.
+    pub removed: Vec<String>,
+}
+
+/// A request the server recorded, with its response.
+///
+/// Credentials are redacted from the query and bodies. Bodies are only
+/// recorded if they are small JSON documents.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RecordedRequest {
+    /// The ID of the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// When the request was received.
+    pub received_at: NaiveDateTime,
+    /// The HTTP method of the request.
+    pub method: String,
+    /// The path of the request, with its query.
+    pub path: String,
+    /// The body of the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<serde_json::Value>,
+    /// The status code of the response.
+    pub status: u16,
+    /// The body of the response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_body: Option<serde_json::Value>,
+    /// How long the request took, in milliseconds.
+    pub duration_ms: u64,
+}
@@ -0,0 +1,41 @@
+//! API audit log response models.
+
+use chrono::NaiveDateTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Id, dispatch::Event, user::User};
+
+/// Response for listing a guild's audit log.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AuditLogResponse {
+    /// The entries, newest first.
+    pub entries: Vec<AuditEntry>,
+    /// Every user the entries mention.
+    pub users: Vec<AuditUser>,
+}
+
+/// Something that happened in a guild, and who did it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AuditEntry {
+    /// The entry's sequence number, shared with its event in the event log.
+    pub seq: u64,
+    /// When it happened.
+    pub timestamp: NaiveDateTime,
+    /// The id of the user that caused it.
+    ///
+    /// Missing for entries logged before actors were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor_id: Option<i32>,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// A user mentioned in an audit log.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AuditUser {
+    pub user: User,
+    /// The user's Discord id, if they have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discord_id: Option<Id>,
+}
@@ -0,0 +1,156 @@
+//! Card API responses.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Id, card::Card, lint::LintViolation, user::User};
+
+/// A response from the bulk archive endpoint.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ArchiveCardsResponse {
+    /// How many cards were archived.
+    pub archived: u32,
+}
+
+/// A single owner of a card, from `GET /guilds/{guild_id}/cards/{id}/owners`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CardOwner {
+    /// The user that owns the card.
+    pub user: User,
+    /// The discord ID of the user, if they are a discord user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discord_id: Option<Id>,
+    /// How many copies of the card the user owns.
+    pub quantity: u32,
+}
+
+/// Who owns what in a guild, from `GET /guilds/{guild_id}/ownership/export`.
+///
+/// Lists every card of the guild, and every user that owns at least one of
+/// them.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct OwnershipMatrix {
+    /// The cards, ordered by id.
+    pub cards: Vec<OwnershipMatrixCard>,
+    /// The users, ordered by id.
+    pub users: Vec<OwnershipMatrixUser>,
+    /// How many copies of each card each user owns.
+    ///
+    /// Only cards a user owns at least one copy of are listed, ordered by
+    /// card, then by user.
+    pub ownership: Vec<OwnershipMatrixEntry>,
+}
+
+/// A card of an [`OwnershipMatrix`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OwnershipMatrixCard {
+    /// The unique ID of the card.
+    pub id: i32,
+    /// The card's name.
+    pub name: String,
+}
+
+/// How many copies of a card a user of an [`OwnershipMatrix`] owns.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OwnershipMatrixEntry {
+    /// The unique ID of the card.
+    pub card_id: i32,
+    /// The unique ID of the user.
+    pub user_id: i32,
+    /// How many copies the user owns.
+    pub quantity: u32,
+}
+
+/// A user of an [`OwnershipMatrix`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OwnershipMatrixUser {
+    /// The user.
+    pub user: User,
+    /// The discord ID of the user, if they are a discord user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discord_id: Option<Id>,
+}
+
+/// A card and its views, from `GET /guilds/{guild_id}/cards/popular`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PopularCard {
+    pub card: Card,
+    /// How many times the card was shown in the requested days.
+    pub views: u64,
+    /// How many times the card was shown in total.
+    pub total_views: u64,
+}
+
+/// A response from the transfer endpoint.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TransferResponse {
+    /// The card that was transferred.
+    pub card: Card,
+    /// How many copies the sender owns afterwards.
+    pub from_quantity: u32,
+    /// How many copies the recipient owns afterwards.
+    pub to_quantity: u32,
+}
+
+/// A response from the trade-in endpoint.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct TradeInResponse {
+    /// The card that was traded in, with how many copies the user has left.
+    pub card: Card,
+    /// The user's currency afterwards, if duplicates were traded in for
+    /// currency.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<u64>,
+    /// The cards rolled, if duplicates were traded in for rolls.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rolled: Vec<Card>,
+    /// The seed the cards were rolled with, in hex, if any were rolled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<String>,
+}
+
+/// A response from the bulk import endpoint.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ImportCardsResponse {
+    /// How many cards were imported.
+    ///
+    /// For dry runs, this is how many cards would have been imported.
+    pub imported: u32,
+    /// Whether this was a dry run, in which case nothing was written.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// A report for each row of the imported file, in file order.
+    pub rows: Vec<ImportRowReport>,
+}
+
+/// The outcome of importing a single row.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ImportRowReport {
+    /// The row of the file, starting at `1` for the first card.
+    pub row: u32,
+    /// The name of the card in this row, if one could be read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// What happened to the row.
+    pub status: ImportStatus,
+    /// Why the row was skipped or failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Problems with the row that did not stop it from being imported.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// The lint rules the row's content broke, if it failed linting.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub violations: Vec<LintViolation>,
+}
+
+/// The outcome of importing a single row.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportStatus {
+    /// The row was imported as a new card.
+    Imported,
+    /// The row was valid, but a card with the same name already exists.
+    Skipped,
+    /// The row could not be mapped into a card.
+    Failed,
+}
@@ -0,0 +1,36 @@
+//! API drop table response models.
+
+use serde::{Deserialize, Serialize};
+
+/// Response body for the `GET /guilds/{guild_id}/drop-table/preview`
+/// endpoint.
+///
+/// The pulls are simulated for a single user that starts out owning nothing,
+/// so capped cards stop being pulled once the user would own enough copies.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DropTablePreview {
+    /// How many pulls were simulated.
+    pub pulls: u32,
+    /// How many pulls came up empty because every card was capped.
+    pub exhausted: u32,
+    /// The seed the pulls were simulated from, in hex.
+    pub seed: String,
+    /// The weight of every entry added up.
+    pub total_weight: u64,
+    /// How often every card of the table was pulled, in the table's order.
+    pub entries: Vec<DropTablePreviewEntry>,
+}
+
+/// How often a card was pulled in a preview.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DropTablePreviewEntry {
+    /// The card pulled.
+    pub card_id: i32,
+    /// How likely the card is pulled according to its weight alone, from 0
+    /// to 1.
+    pub chance: f64,
+    /// How many times the card was pulled.
+    pub count: u32,
+    /// The share of the pulls the card made up, from 0 to 1.
+    pub share: f64,
+}
@@ -1,3 +1,67 @@
 //! API responses.
 
+pub mod admin;
+pub mod audit;
+pub mod card;
+pub mod drop_table;
+pub mod telemetry;
 pub mod user;
+pub mod webhook;
+
+use std::vec;
+
+use chrono::NaiveDate;
+
+use serde::{Deserialize, Serialize};
+
+/// The header marking a response as using a deprecated part of the API.
+pub const DEPRECATION_HEADER: &str = "deprecation";
+
+/// The header with the date a deprecated part of the API is removed.
+pub const SUNSET_HEADER: &str = "sunset";
+
+/// A notice that a request used a deprecated part of the API.
+///
+/// Responses that carry warnings also have a [`DEPRECATION_HEADER`], and a
+/// [`SUNSET_HEADER`] if any warning has a sunset date. JSON object bodies list
+/// them in a `warnings` array.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Warning {
+    /// What is deprecated, and what to use instead.
+    pub message: String,
+    /// The date the deprecated part of the API stops working, if set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sunset: Option<NaiveDate>,
+}
+
+/// A page of results from a list endpoint.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Paginated<T> {
+    /// The results on this page.
+    pub items: Vec<T>,
+    /// The page, starting at `1`.
+    pub page: u32,
+    /// How many results there are across all pages.
+    pub total_items: u64,
+    /// How many pages there are.
+    ///
+    /// This is at least `1`, even if there are no results.
+    pub total_pages: u32,
+    /// The page after this one, if this is not the last page.
+    ///
+    /// Pass it as the `page` of the next request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<u32>,
+    /// Deprecated parts of the API the request used.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
+}
+
+impl<T> IntoIterator for Paginated<T> {
+    type Item = T;
+    type IntoIter = vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
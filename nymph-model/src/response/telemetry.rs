@@ -0,0 +1,46 @@
+//! API telemetry responses.
+
+use chrono::NaiveDateTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Id;
+
+/// A response from `POST /telemetry/guilds`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReportGuildsResponse {
+    /// Guilds that were not served before this report.
+    pub joined: Vec<Id>,
+    /// Guilds that were served before this report, but are not anymore.
+    pub departed: Vec<Id>,
+}
+
+/// A guild a bot has reported serving.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GuildPresence {
+    /// The guild's ID.
+    pub id: Id,
+    /// The guild's name, as last reported.
+    pub name: String,
+    /// How many members the guild has, as last reported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member_count: Option<u64>,
+    /// When the guild was first reported.
+    pub first_seen_at: NaiveDateTime,
+    /// When the guild was last reported.
+    pub last_seen_at: NaiveDateTime,
+    /// When the guild was first missing from a report, if it still is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub departed_at: Option<NaiveDateTime>,
+}
+
+/// A response from `GET /admin/guilds`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GuildPresenceResponse {
+    /// Every reported guild, served guilds first.
+    pub guilds: Vec<GuildPresence>,
+    /// How many guilds are served.
+    pub served: u32,
+    /// How many members the served guilds have in total.
+    pub members: u64,
+}
@@ -1,5 +1,7 @@
 //! User API responses.
 
+use chrono::NaiveDateTime;
+
 use serde::{Deserialize, Serialize};
 
 use crate::{Id, user::User};
@@ -19,3 +21,43 @@ pub struct UpdateDiscordUserResponse {
     /// typically have very short lifetimes (15 mins).
     pub access_token: Option<String>,
 }
+
+/// A user listed by `GET /users/discord/stale`, whose display name may be out
+/// of date.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StaleDiscordUser {
+    /// The user.
+    pub user: User,
+    /// The discord ID of the user.
+    pub discord_id: Id,
+    /// When the user's display name was last refreshed from Discord, or
+    /// `None` if it never was.
+    pub refreshed_at: Option<NaiveDateTime>,
+}
+
+/// A response from `GET /users/{user_id}/progress`, describing how much of a
+/// guild's card set a user has collected.
+///
+/// Only cards the user knows exist are counted; private cards count towards
+/// the totals only once they are owned.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProgressResponse {
+    /// How many cards the user owns.
+    pub owned: u32,
+    /// How many cards there are to collect.
+    pub total: u32,
+    /// Progress broken down by category.
+    pub categories: Vec<CategoryProgress>,
+}
+
+/// Collection progress of a single category.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CategoryProgress {
+    /// The name of the category, or `None` for uncategorized cards.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category_name: Option<String>,
+    /// How many cards in the category the user owns.
+    pub owned: u32,
+    /// How many cards there are to collect in the category.
+    pub total: u32,
+}
@@ -0,0 +1,15 @@
+//! API webhook response models.
+
+use serde::{Deserialize, Serialize};
+
+use crate::dispatch::Envelope;
+
+/// Response for replaying a guild's events.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReplayResponse {
+    /// The events, oldest first.
+    pub events: Vec<Envelope>,
+    /// `true` if some events after the requested sequence number have already
+    /// been removed, so the consumer should re-sync instead.
+    pub truncated: bool,
+}
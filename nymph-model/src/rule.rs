@@ -0,0 +1,29 @@
+//! Reward rule data models.
+
+use chrono::NaiveDateTime;
+
+use serde::{Deserialize, Serialize};
+
+use super::Id;
+
+/// A reward rule.
+///
+/// Once a user owns every card of a rule's set, the server grants them the
+/// rule's reward card. Each user is only rewarded once per rule.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Rule {
+    /// The unique identifier of the rule.
+    pub id: i32,
+    /// The guild the rule belongs to.
+    pub guild_id: Id,
+    /// The rule's name.
+    pub name: String,
+    /// The IDs of the cards a user must collect.
+    pub cards: Vec<i32>,
+    /// The ID of the card granted once the set is collected.
+    pub reward_id: i32,
+    /// When the rule was created.
+    pub created_at: NaiveDateTime,
+    /// When the rule was last updated.
+    pub updated_at: NaiveDateTime,
+}
@@ -0,0 +1,29 @@
+//! Season data models.
+
+use chrono::NaiveDateTime;
+
+use serde::{Deserialize, Serialize};
+
+use super::Id;
+
+/// A season.
+///
+/// Seasons are snapshots of everything owned in a guild, so collections can
+/// be reset periodically without losing track of what users once owned.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Season {
+    /// The unique identifier of the season.
+    pub id: i32,
+    /// The guild the season belongs to.
+    pub guild_id: Id,
+    /// The season's name.
+    pub name: String,
+    /// Whether or not ownership was reset when the season was recorded.
+    pub reset: bool,
+    /// How many users owned a card when the season was recorded.
+    pub owners: u32,
+    /// How many copies of cards were owned when the season was recorded.
+    pub copies: u32,
+    /// When the season was recorded.
+    pub created_at: NaiveDateTime,
+}
@@ -0,0 +1,28 @@
+//! Card syndication data models.
+
+use chrono::NaiveDateTime;
+
+use serde::{Deserialize, Serialize};
+
+use super::Id;
+
+/// A guild's subscription to a category of another guild's cards.
+///
+/// Public cards of the category are listed and shown in the subscribing
+/// guild as if they were its own, but stay owned and edited by the source
+/// guild.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Syndication {
+    /// The unique identifier of the syndication.
+    pub id: i32,
+    /// The subscribing guild.
+    pub guild_id: Id,
+    /// The guild the cards belong to.
+    pub source_guild_id: Id,
+    /// The category subscribed to.
+    pub category_name: String,
+    /// How many cards are currently syndicated.
+    pub cards: u32,
+    /// When the guild subscribed.
+    pub created_at: NaiveDateTime,
+}
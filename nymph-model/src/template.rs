@@ -0,0 +1,26 @@
+//! Card template data models.
+
+use chrono::NaiveDateTime;
+
+use serde::{Deserialize, Serialize};
+
+/// A card template.
+///
+/// Templates are starter decks kept by the server's operators. A guild can
+/// copy a template's cards, categories and upgrades into itself when it is
+/// first set up.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Template {
+    /// The unique identifier of the template.
+    pub id: i32,
+    /// The template's name.
+    pub name: String,
+    /// What the template is about.
+    pub description: Option<String>,
+    /// How many cards the template has.
+    pub cards: u32,
+    /// When the template was created.
+    pub created_at: NaiveDateTime,
+    /// When the template's cards were last replaced.
+    pub updated_at: NaiveDateTime,
+}
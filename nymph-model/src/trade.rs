@@ -0,0 +1,100 @@
+//! Trade data models.
+
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+
+use derive_more::{Display, Error};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Id, user::User};
+
+/// A trade of cards between two users.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Trade {
+    /// The unique identifier of the trade.
+    pub id: i32,
+    /// The guild the traded cards belong to.
+    pub guild_id: Id,
+    /// The user that opened the trade.
+    pub initiator: User,
+    /// The user the trade was offered to.
+    pub recipient: User,
+    /// The trade's status.
+    pub status: TradeStatus,
+    /// The cards the initiator gives to the recipient.
+    pub offered: Vec<TradeItem>,
+    /// The cards the recipient gives to the initiator.
+    pub requested: Vec<TradeItem>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// A card given up by one side of a trade.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub struct TradeItem {
+    /// The ID of the card.
+    pub card_id: i32,
+    /// The card's name.
+    pub name: String,
+    /// How many copies of the card are given up.
+    pub quantity: u32,
+}
+
+/// Trade status.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TradeStatus {
+    /// The trade is waiting for the recipient to accept it.
+    Open,
+    /// The trade was accepted, and the cards have changed hands.
+    Accepted,
+    /// The trade was cancelled by either side.
+    Cancelled,
+}
+
+impl TradeStatus {
+    /// Creates a string representation of the status that can be used to get
+    /// back the status with [`FromStr`].
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            TradeStatus::Open => "open",
+            TradeStatus::Accepted => "accepted",
+            TradeStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl TryFrom<String> for TradeStatus {
+    type Error = NoSuchTradeStatus;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl TryFrom<&str> for TradeStatus {
+    type Error = NoSuchTradeStatus;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl FromStr for TradeStatus {
+    type Err = NoSuchTradeStatus;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(TradeStatus::Open),
+            "accepted" => Ok(TradeStatus::Accepted),
+            "cancelled" => Ok(TradeStatus::Cancelled),
+            _ => Err(NoSuchTradeStatus(s.to_string())),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Display, Error)]
+#[display("no such trade status \"{_0}\" exists")]
+pub struct NoSuchTradeStatus(#[error(not(source))] String);
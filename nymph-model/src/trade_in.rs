@@ -0,0 +1,32 @@
+//! Duplicate trade-in data models.
+
+use serde::{Deserialize, Serialize};
+
+/// A guild's duplicate trade-in rules.
+///
+/// Users may trade in spare copies of a card, keeping at least one, for
+/// whatever the guild rewards them with.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq, Serialize)]
+pub struct TradeInRules {
+    /// What duplicates are traded in for.
+    ///
+    /// Trade-ins are disabled if left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reward: Option<TradeInReward>,
+}
+
+/// What duplicates are traded in for.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum TradeInReward {
+    /// Each duplicate is worth some of the guild's currency.
+    Currency {
+        /// How much currency a single duplicate is worth.
+        per_copy: u32,
+    },
+    /// Every few duplicates roll a random card.
+    Roll {
+        /// How many duplicates are traded in for a single roll.
+        copies: u32,
+    },
+}
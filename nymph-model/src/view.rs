@@ -0,0 +1,33 @@
+//! Shared view state.
+//!
+//! Discord caps component custom ids at 100 characters, too few to carry the
+//! state of a multi-step flow like building a trade or setting up a guild.
+//! The bot instead stores the state as a [`View`] and carries only the view's
+//! id, until the view expires.
+
+use chrono::NaiveDateTime;
+
+use serde::{Deserialize, Serialize};
+
+use serde_json::Value;
+
+/// How long a view lives if no TTL is given, in seconds.
+pub const DEFAULT_VIEW_TTL: u32 = 15 * 60;
+
+/// The longest a view may live, in seconds.
+pub const MAX_VIEW_TTL: u32 = 24 * 60 * 60;
+
+/// The largest state a view may hold, in bytes of JSON.
+pub const MAX_VIEW_LEN: usize = 16 * 1024;
+
+/// Stored UI state.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct View {
+    /// The unique ID of the view.
+    pub id: i32,
+    /// The state, in whatever shape the UI needs.
+    pub data: Value,
+    /// When the view expires, after which it can no longer be read.
+    pub expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
@@ -0,0 +1,126 @@
+//! Webhook data models.
+//!
+//! Every payload delivered to a webhook is a JSON-encoded
+//! [`Envelope`](crate::dispatch::Envelope), signed with the webhook's secret.
+//! The signature is sent in the [`SIGNATURE_HEADER`] as `sha256=` followed by
+//! the hex-encoded HMAC-SHA256 of the [`TIMESTAMP_HEADER`], a `.`, and the
+//! request body. Receivers can check deliveries with [`verify`].
+
+use base16::{decode, encode_lower};
+
+use chrono::{NaiveDateTime, Utc};
+
+use derive_more::{Display, Error};
+
+use hmac::{Hmac, Mac as _};
+
+use serde::{Deserialize, Serialize};
+
+use sha2::Sha256;
+
+use super::Id;
+
+/// The header the payload signature is sent in.
+pub const SIGNATURE_HEADER: &str = "x-nymph-signature";
+
+/// The header the time the payload was signed is sent in, as a Unix timestamp
+/// in seconds.
+pub const TIMESTAMP_HEADER: &str = "x-nymph-timestamp";
+
+/// The header the payload's event is sent in.
+pub const EVENT_HEADER: &str = "x-nymph-event";
+
+/// The header the unique identifier of a delivery is sent in.
+///
+/// A delivery that is retried keeps its identifier, so receivers can use it to
+/// drop duplicates.
+pub const DELIVERY_HEADER: &str = "x-nymph-delivery";
+
+/// A URL a guild's card events are delivered to.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Webhook {
+    /// The unique identifier of the webhook.
+    pub id: i32,
+    /// The guild the webhook belongs to.
+    pub guild_id: Id,
+    /// The URL payloads are posted to.
+    pub url: String,
+    /// The secret payloads are signed with.
+    ///
+    /// The secret's text is used as the HMAC key as-is.
+    ///
+    /// Only appears when the webhook is created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// How old a delivery may be before [`verify`] refuses it, in seconds.
+///
+/// Retried deliveries are signed again, so this only needs to cover the time
+/// a single request takes.
+pub const DEFAULT_TOLERANCE: i64 = 5 * 60;
+
+/// Signs a payload, giving the value of the [`SIGNATURE_HEADER`].
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    format!(
+        "sha256={}",
+        encode_lower(&mac(secret, timestamp, body).finalize().into_bytes())
+    )
+}
+
+/// Verifies a delivery from the values of its [`SIGNATURE_HEADER`] and
+/// [`TIMESTAMP_HEADER`].
+///
+/// Deliveries signed more than `tolerance` seconds away from now are refused,
+/// so a captured delivery cannot be replayed later.
+pub fn verify(
+    secret: &str,
+    signature: &str,
+    timestamp: &str,
+    body: &[u8],
+    tolerance: i64,
+) -> Result<(), SignatureError> {
+    let timestamp = timestamp
+        .trim()
+        .parse::<i64>()
+        .map_err(|_| SignatureError::Malformed)?;
+    let signature = signature
+        .trim()
+        .strip_prefix("sha256=")
+        .and_then(|s| decode(s).ok())
+        .ok_or(SignatureError::Malformed)?;
+
+    if (Utc::now().timestamp() - timestamp).abs() > tolerance {
+        return Err(SignatureError::Expired);
+    }
+
+    // compares in constant time
+    mac(secret, timestamp, body)
+        .verify_slice(&signature)
+        .map_err(|_| SignatureError::Mismatch)
+}
+
+fn mac(secret: &str, timestamp: i64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// A delivery that failed [`verify`].
+#[derive(Clone, Copy, Debug, Display, Error, PartialEq, Eq)]
+pub enum SignatureError {
+    /// The signature or timestamp could not be read.
+    #[display("malformed signature or timestamp")]
+    Malformed,
+    /// The delivery was signed too long ago.
+    #[display("signature has expired")]
+    Expired,
+    /// The signature does not match the payload.
+    #[display("signature does not match")]
+    Mismatch,
+}
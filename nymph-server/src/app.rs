@@ -1,13 +1,18 @@
 //! Nymph general application items.
 
+use std::convert::Infallible;
 use std::fmt::{self, Debug, Display, Formatter};
+use std::net::IpAddr;
+use std::str::FromStr as _;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Error;
 
 use axum::RequestExt as _;
-use axum::extract::rejection::{FormRejection, JsonRejection, QueryRejection};
-use axum::extract::{FromRequestParts, Request};
+use axum::body::{Body, Bytes};
+use axum::extract::rejection::{BytesRejection, FormRejection, JsonRejection, QueryRejection};
+use axum::extract::{FromRequestParts, OriginalUri, Request};
 use axum::middleware::Next;
 use axum::{
     Form, Json,
@@ -15,12 +20,18 @@ use axum::{
     response::{IntoResponse, Response},
 };
 
-use http::{HeaderValue, StatusCode, header};
+use http::{HeaderMap, HeaderValue, StatusCode, header, request::Parts};
 
-use nymph_model::{ApiError, ErrorCode};
+use ipnet::IpNet;
 
-use serde::de::DeserializeOwned;
-use sqlx::{SqlitePool, pool::PoolOptions};
+use nymph_model::{ApiError, ErrorCode, LintError, lint::LintViolation};
+
+use serde::{Serialize, de::DeserializeOwned};
+use sqlx::{
+    SqlitePool,
+    pool::PoolOptions,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous},
+};
 
 use derive_more::{Deref, Display, From};
 
@@ -33,22 +44,85 @@ use rand::{Rng as _, SeedableRng as _, rngs::StdRng};
 
 use base16::encode_lower;
 
-use crate::config::ServerConfig;
+use chrono::NaiveDateTime;
+
+use sha2::{Digest as _, Sha256};
+
+use tracing_subscriber::reload;
+
+use crate::{
+    backup::BackupError,
+    config::{
+        BackupConfig, CacheConfig, FaultConfig, JournalMode, RecordConfig, ServerConfig,
+        StorageConfig, Synchronous,
+    },
+    deprecation,
+    fault::FaultInjector,
+    gateway::Gateway,
+    log::LogFilter,
+    maintenance::Maintenance,
+    ratelimit::RateLimiter,
+    request::{self, record::Recorder},
+    storage::{ContentStore, StorageError},
+    store::{CardStore, OwnershipStore, SqlStore, StoreError, UserStore},
+    views::ViewCounter,
+};
+
+/// The media type of MessagePack.
+pub const MSGPACK: &str = "application/msgpack";
 
 /// Shared server state.
 ///
 /// Cheaply cloneable.
 #[derive(Clone)]
 pub struct AppState {
+    /// The address the server is binded to.
+    pub host: IpAddr,
     /// The port the server is binded to.
     pub port: u16,
     /// A database connection pool.
     pub db: SqlitePool,
+    /// Where cards are read from.
+    pub cards: Arc<dyn CardStore>,
+    /// Where users are read from.
+    pub users: Arc<dyn UserStore>,
+    /// Where inventories are changed.
+    pub ownership: Arc<dyn OwnershipStore>,
     /// The secret signing keys for tokens.
     ///
     /// This is randomly generated on app startup. This means that when the
     /// daemon restarts, old JWTs will be rejected.
     pub keys: Arc<SigningKeys>,
+    /// The secret proxy assertions are signed with, if they are accepted.
+    pub proxy_secret: Option<Arc<str>>,
+    /// A handle to the tracing filter, to adjust logging at runtime.
+    pub log_filter: LogFilter,
+    /// Where and how many database backups are kept.
+    pub backup: Arc<BackupConfig>,
+    /// The bus events are published to gateway clients on.
+    pub gateway: Gateway,
+    /// The request budgets of every client.
+    pub rate_limiter: RateLimiter,
+    /// Card views that have yet to be written.
+    pub views: ViewCounter,
+    /// Where card content is kept.
+    pub content: ContentStore,
+    /// How long responses may be cached.
+    pub cache: Arc<CacheConfig>,
+    /// Which requests faults are injected into.
+    pub faults: FaultInjector,
+    /// Whether requests that change data are refused.
+    pub maintenance: Maintenance,
+    /// How long a request may take before it is answered with an error.
+    pub timeout: Option<Duration>,
+    /// The latest requests, if they are recorded.
+    pub recorder: Recorder,
+    /// Routes that refuse requests without a client certificate.
+    pub tls_client_routes: Arc<[String]>,
+    /// Reverse proxies whose forwarding headers are believed.
+    pub trusted_proxies: Arc<[IpNet]>,
+    /// Networks privileged requests may come from, if restricted.
+    pub privileged_networks: Arc<[IpNet]>,
 }
 
 impl AppState {
@@ -56,15 +130,33 @@ impl AppState {
     ///
     /// See [`Config`] to learn more on what the options do.
     pub async fn new(config: ServerConfig) -> Result<AppState, Error> {
-        let ServerConfig { port, .. } = config;
+        let ServerConfig { host, port, .. } = config;
 
         // get url
         let Some(database_url) = config.database_url.as_ref() else {
             return Err(Error::msg("`DATABASE_URL` not present"));
         };
 
-        // establish database connection
-        let pool = PoolOptions::new().connect(database_url).await?;
+        // queries are written for SQLite, down to its date functions, full
+        // text search and `VACUUM INTO` backups
+        if !database_url.starts_with("sqlite:") {
+            return Err(Error::msg(
+                "`DATABASE_URL` must be a `sqlite:` url; other databases are not supported",
+            ));
+        }
+
+        // establish database connection, creating the database on first run
+        let options = SqliteConnectOptions::from_str(database_url)?
+            .create_if_missing(true)
+            .journal_mode(journal_mode(config.pool.journal_mode))
+            .busy_timeout(Duration::from_millis(config.pool.busy_timeout))
+            .synchronous(synchronous(config.pool.synchronous));
+        let pool = PoolOptions::new()
+            .max_connections(config.pool.max_connections)
+            .min_connections(config.pool.min_connections)
+            .acquire_timeout(Duration::from_secs(config.pool.acquire_timeout))
+            .connect_with(options)
+            .await?;
 
         // randomly generate JWT secret
         let keys = match config.signing_key.as_ref() {
@@ -72,10 +164,91 @@ impl AppState {
             None => Arc::from(SigningKeys::new_random()),
         };
 
+        let store = SqlStore::new(pool.clone());
+
         Ok(AppState {
+            host,
             port,
             db: pool,
+            cards: Arc::new(store.clone()),
+            users: Arc::new(store.clone()),
+            ownership: Arc::new(store),
             keys,
+            proxy_secret: config.proxy_secret.as_deref().map(Arc::from),
+            log_filter: LogFilter::default(),
+            backup: Arc::default(),
+            gateway: Gateway::new(),
+            rate_limiter: RateLimiter::new(config.rate_limit.clone()),
+            views: ViewCounter::new(),
+            content: ContentStore::default(),
+            cache: Arc::default(),
+            faults: FaultInjector::default(),
+            maintenance: Maintenance::new(config.maintenance),
+            timeout: config.timeout.map(Duration::from_secs),
+            recorder: Recorder::default(),
+            tls_client_routes: Arc::from(config.tls_client_routes),
+            trusted_proxies: Arc::from(config.trusted_proxies),
+            privileged_networks: Arc::from(config.privileged_networks),
+        })
+    }
+
+    /// Replaces the stores handlers read and write through.
+    pub fn with_stores(
+        self,
+        cards: Arc<dyn CardStore>,
+        users: Arc<dyn UserStore>,
+        ownership: Arc<dyn OwnershipStore>,
+    ) -> AppState {
+        AppState {
+            cards,
+            users,
+            ownership,
+            ..self
+        }
+    }
+
+    /// Attaches a handle to the tracing filter.
+    pub fn with_log_filter(self, log_filter: LogFilter) -> AppState {
+        AppState { log_filter, ..self }
+    }
+
+    /// Sets where database backups are written.
+    pub fn with_backup(self, backup: BackupConfig) -> AppState {
+        AppState {
+            backup: Arc::new(backup),
+            ..self
+        }
+    }
+
+    /// Sets how long responses may be cached.
+    pub fn with_cache(self, cache: CacheConfig) -> AppState {
+        AppState {
+            cache: Arc::new(cache),
+            ..self
+        }
+    }
+
+    /// Sets where card content is kept.
+    pub fn with_storage(self, storage: StorageConfig) -> Result<AppState, Error> {
+        Ok(AppState {
+            content: ContentStore::new(storage)?,
+            ..self
+        })
+    }
+
+    /// Sets how many requests are recorded.
+    pub fn with_recorder(self, record: RecordConfig) -> AppState {
+        AppState {
+            recorder: Recorder::new(record),
+            ..self
+        }
+    }
+
+    /// Sets which requests faults are injected into.
+    pub fn with_faults(self, faults: FaultConfig) -> Result<AppState, Error> {
+        Ok(AppState {
+            faults: FaultInjector::new(faults)?,
+            ..self
         })
     }
 }
@@ -83,6 +256,7 @@ impl AppState {
 impl Debug for AppState {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("ServerState")
+            .field("host", &self.host)
             .field("port", &self.port)
             .finish_non_exhaustive()
     }
@@ -94,9 +268,13 @@ pub async fn app_rest_headers(request: Request, next: Next) -> Response {
 
     //let hsts_time = 60 * 60 * 24;
 
+    // responses are never cached, unless they say otherwise
+    res.headers_mut()
+        .entry(header::CACHE_CONTROL)
+        .or_insert(HeaderValue::from_static("no-store"));
+
     // apply additional headers for REST safety
     res.headers_mut().extend([
-        (header::CACHE_CONTROL, HeaderValue::from_static("no-store")),
         (
             header::CONTENT_SECURITY_POLICY,
             HeaderValue::from_static("frame-ancestors 'none'"),
@@ -115,6 +293,198 @@ pub async fn app_rest_headers(request: Request, next: Next) -> Response {
     res
 }
 
+/// Finds how much an `Accept` header prefers a media type.
+///
+/// Returns how specific the most specific matching media range is, from `3`
+/// for the media type itself down to `1` for `*/*`, along with its quality.
+/// Media types that nothing matches have a quality of `0`.
+fn quality(accept: &str, media: &str) -> (u8, f32) {
+    let kind = media.split_once('/').map_or(media, |(kind, _)| kind);
+    let mut best = (0, 0.);
+
+    for range in accept.split(',') {
+        let mut params = range.split(';');
+        let range = params.next().unwrap_or_default().trim();
+        let quality = params
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|quality| quality.trim().parse::<f32>().ok())
+            .unwrap_or(1.);
+
+        let specificity = if range.eq_ignore_ascii_case(media) {
+            3
+        } else if range
+            .strip_suffix("/*")
+            .is_some_and(|range| range.eq_ignore_ascii_case(kind))
+        {
+            2
+        } else if range == "*/*" {
+            1
+        } else {
+            continue;
+        };
+
+        if specificity > best.0 {
+            best = (specificity, quality);
+        }
+    }
+
+    best
+}
+
+/// Encodes JSON responses as MessagePack for clients that accept it.
+///
+/// Handlers only ever respond with JSON, so bodies are transcoded here
+/// instead. Everything else, like streamed listings, is left alone.
+///
+/// MessagePack must be asked for by name, and preferred at least as much as
+/// JSON.
+pub async fn msgpack_responses(request: Request, next: Next) -> Response {
+    let accepts_msgpack = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| {
+            let (specificity, msgpack) = quality(accept, MSGPACK);
+            let (_, json) = quality(accept, "application/json");
+
+            specificity == 3 && msgpack > 0. && msgpack >= json
+        });
+
+    let mut res = next.run(request).await;
+
+    res.headers_mut()
+        .append(header::VARY, HeaderValue::from_static("accept"));
+
+    let is_json = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|mime| mime.as_bytes().starts_with(b"application/json"));
+
+    if !accepts_msgpack || !is_json {
+        return res;
+    }
+
+    let (mut parts, body) = res.into_parts();
+
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(err) => {
+            tracing::error!(?err, "failed to read response body");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let encoded = serde_json::from_slice::<serde_json::Value>(&body)
+        .map_err(Error::from)
+        .and_then(|value| Ok(rmp_serde::to_vec_named(&value)?));
+
+    match encoded {
+        Ok(encoded) => {
+            parts
+                .headers
+                .insert(header::CONTENT_TYPE, HeaderValue::from_static(MSGPACK));
+            parts.headers.remove(header::CONTENT_LENGTH);
+
+            Response::from_parts(parts, Body::from(encoded))
+        }
+        // fall back to the JSON as it was
+        Err(err) => {
+            tracing::warn!(?err, "failed to encode response as msgpack");
+            Response::from_parts(parts, Body::from(body))
+        }
+    }
+}
+
+/// The version of the API a request was made to.
+///
+/// Every route is mounted under the prefix of each version, and once more
+/// without any prefix. Unversioned paths are deprecated aliases of
+/// [`ApiVersion::V1`], kept so older clients keep working.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ApiVersion {
+    /// A path without a version prefix.
+    Unversioned,
+    /// Paths under `/v1`.
+    V1,
+}
+
+impl ApiVersion {
+    /// The newest version of the API.
+    pub const LATEST: ApiVersion = ApiVersion::V1;
+
+    /// The path prefix of the version.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            ApiVersion::Unversioned => "",
+            ApiVersion::V1 => "/v1",
+        }
+    }
+
+    /// Splits the version prefix off a path.
+    pub fn split(path: &str) -> (ApiVersion, &str) {
+        match path.strip_prefix(ApiVersion::V1.prefix()) {
+            Some(rest) if rest.is_empty() || rest.starts_with('/') => (ApiVersion::V1, rest),
+            _ => (ApiVersion::Unversioned, path),
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for ApiVersion
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // nested routers strip their prefix from the request's uri
+        let path = match parts.extensions.get::<OriginalUri>() {
+            Some(OriginalUri(uri)) => uri.path(),
+            None => parts.uri.path(),
+        };
+
+        Ok(ApiVersion::split(path).0)
+    }
+}
+
+/// Marks responses to unversioned paths as deprecated.
+///
+/// The `Link` header points clients to the same path under the latest
+/// version.
+pub async fn deprecate_unversioned(version: ApiVersion, request: Request, next: Next) -> Response {
+    let successor = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|OriginalUri(uri)| uri.path().to_owned());
+
+    if version == ApiVersion::Unversioned {
+        deprecation::warn(format!(
+            "Unversioned paths are deprecated; use `{}` paths instead.",
+            ApiVersion::LATEST.prefix()
+        ));
+    }
+
+    let mut res = next.run(request).await;
+
+    if version != ApiVersion::Unversioned {
+        return res;
+    }
+
+    let link = successor.and_then(|path| {
+        HeaderValue::try_from(format!(
+            "<{}{}>; rel=\"successor-version\"",
+            ApiVersion::LATEST.prefix(),
+            path
+        ))
+        .ok()
+    });
+
+    if let Some(link) = link {
+        res.headers_mut().insert(header::LINK, link);
+    }
+
+    res
+}
+
 /// Selective body extractor.
 #[derive(Deref)]
 pub struct Payload<T>(pub T);
@@ -143,6 +513,10 @@ where
                 let AppJson(json) = req.extract_with_state::<AppJson<T>, _, _>(state).await?;
                 Ok(Payload(json))
             }
+            MSGPACK => {
+                let body = req.extract_with_state::<Bytes, _, _>(state).await?;
+                Ok(Payload(rmp_serde::from_slice(&body)?))
+            }
             mime => Err(AppErrorKind::UnsupportedContentType(mime.to_owned()).into()),
         }
     }
@@ -181,6 +555,256 @@ where
     }
 }
 
+/// App JSON responder that clients can revalidate with `If-None-Match`.
+///
+/// The weak ETag is a digest of the body, since bodies also carry ownership
+/// and related cards that change independently, and differ between callers.
+/// Resources that can be updated with `If-Match` prefix it with their
+/// [revision](Conditional::revision), which is the same for every caller and
+/// every representation; see [`revision_matches`].
+pub struct Conditional<T> {
+    body: T,
+    if_none_match: Option<HeaderValue>,
+    revision: Option<String>,
+}
+
+impl<T> Conditional<T> {
+    /// Creates a new `Conditional` for a request with the given headers.
+    pub fn new(headers: &HeaderMap, body: T) -> Conditional<T> {
+        Conditional {
+            body,
+            if_none_match: headers.get(header::IF_NONE_MATCH).cloned(),
+            revision: None,
+        }
+    }
+
+    /// Prefixes the ETag with a revision from [`revision_of`].
+    pub fn revision(self, revision: String) -> Conditional<T> {
+        Conditional {
+            revision: Some(revision),
+            ..self
+        }
+    }
+}
+
+impl<T> IntoResponse for Conditional<T>
+where
+    T: Serialize,
+{
+    fn into_response(self) -> Response {
+        let body = serde_json::to_vec(&self.body).expect("valid json");
+        let etag = match self.revision {
+            Some(revision) => format!("W/\"{}-{}\"", revision, digest_of(&body)),
+            None => etag_of(&body),
+        };
+
+        let matches = self
+            .if_none_match
+            .as_ref()
+            .is_some_and(|tags| etag_matches(tags, &etag));
+
+        let headers = [
+            (header::ETAG, etag),
+            // bodies differ between callers
+            (header::CACHE_CONTROL, String::from("private, no-cache")),
+        ];
+
+        if matches {
+            (StatusCode::NOT_MODIFIED, headers).into_response()
+        } else {
+            (
+                headers,
+                [(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("application/json"),
+                )],
+                body,
+            )
+                .into_response()
+        }
+    }
+}
+
+/// A response that shared caches may reuse for a while.
+///
+/// Only for responses that are the same for every caller allowed to cache
+/// them; see [`CacheConfig`]. Responses are left as they are if `max_age` is
+/// not set, or if the wrapped response is an error.
+pub struct Cached<T> {
+    body: T,
+    max_age: Option<u32>,
+    last_modified: Option<NaiveDateTime>,
+    if_modified_since: Option<NaiveDateTime>,
+}
+
+impl<T> Cached<T> {
+    /// Creates a new `Cached` for a request with the given headers.
+    ///
+    /// `If-Modified-Since` is ignored if the request also has an
+    /// `If-None-Match`, which takes precedence.
+    pub fn new(headers: &HeaderMap, body: T, max_age: Option<u32>) -> Cached<T> {
+        let if_modified_since = headers
+            .get(header::IF_MODIFIED_SINCE)
+            .filter(|_| !headers.contains_key(header::IF_NONE_MATCH))
+            .and_then(|date| date.to_str().ok())
+            .and_then(|date| NaiveDateTime::parse_from_str(date, HTTP_DATE).ok());
+
+        Cached {
+            body,
+            max_age,
+            last_modified: None,
+            if_modified_since,
+        }
+    }
+
+    /// Sets when the response last changed.
+    pub fn last_modified(self, last_modified: NaiveDateTime) -> Cached<T> {
+        Cached {
+            last_modified: Some(last_modified),
+            ..self
+        }
+    }
+}
+
+impl<T> IntoResponse for Cached<T>
+where
+    T: IntoResponse,
+{
+    fn into_response(self) -> Response {
+        let Some(max_age) = self.max_age else {
+            return self.body.into_response();
+        };
+
+        // dates only have second precision
+        let not_modified =
+            self.last_modified
+                .zip(self.if_modified_since)
+                .is_some_and(|(last_modified, since)| {
+                    last_modified.and_utc().timestamp() <= since.and_utc().timestamp()
+                });
+
+        let mut res = if not_modified {
+            StatusCode::NOT_MODIFIED.into_response()
+        } else {
+            self.body.into_response()
+        };
+
+        if !res.status().is_success() && res.status() != StatusCode::NOT_MODIFIED {
+            return res;
+        }
+
+        let headers = res.headers_mut();
+
+        headers.insert(
+            header::CACHE_CONTROL,
+            HeaderValue::try_from(format!("public, max-age={}", max_age))
+                .expect("valid cache control"),
+        );
+
+        if let Some(last_modified) = self.last_modified {
+            headers.insert(
+                header::LAST_MODIFIED,
+                HeaderValue::try_from(last_modified.format(HTTP_DATE).to_string())
+                    .expect("valid http date"),
+            );
+        }
+
+        res
+    }
+}
+
+/// The format of dates in HTTP headers.
+pub const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// A body that only serializes some of its fields.
+///
+/// Serializes as the full body if no fields are given.
+pub struct Sparse<T> {
+    body: T,
+    fields: Option<Arc<[String]>>,
+}
+
+impl<T> Sparse<T> {
+    /// Creates a new `Sparse` keeping only `fields` of `body`.
+    pub fn new(body: T, fields: Option<Arc<[String]>>) -> Sparse<T> {
+        Sparse { body, fields }
+    }
+}
+
+impl<T> Serialize for Sparse<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let Some(fields) = self.fields.as_ref() else {
+            return self.body.serialize(serializer);
+        };
+
+        let mut value = serde_json::to_value(&self.body).map_err(serde::ser::Error::custom)?;
+
+        if let serde_json::Value::Object(object) = &mut value {
+            object.retain(|key, _| fields.contains(key));
+        }
+
+        value.serialize(serializer)
+    }
+}
+
+/// Computes the weak ETag of a serialized body.
+pub fn etag_of(body: &[u8]) -> String {
+    format!("W/\"{}\"", digest_of(body))
+}
+
+/// Computes the revision of a resource from its id and `updated_at`.
+pub fn revision_of(id: impl Display, updated_at: NaiveDateTime) -> String {
+    let digest = Sha256::digest(format!(
+        "{}:{}",
+        id,
+        updated_at.and_utc().timestamp_micros()
+    ));
+
+    encode_lower(&digest[..8])
+}
+
+fn digest_of(body: &[u8]) -> String {
+    encode_lower(&Sha256::digest(body)[..16])
+}
+
+/// Checks if an `If-Match` or `If-None-Match` header matches an ETag.
+///
+/// Tags are compared weakly, so `W/` prefixes are ignored.
+pub fn etag_matches(tags: &HeaderValue, etag: &str) -> bool {
+    let Ok(tags) = tags.to_str() else {
+        return false;
+    };
+
+    tags.split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag.trim_start_matches("W/"))
+}
+
+/// Checks if an `If-Match` header matches a resource's revision.
+///
+/// Only the revision prefix of each tag is compared, so tags of any
+/// representation of the resource match, for any caller.
+pub fn revision_matches(tags: &HeaderValue, revision: &str) -> bool {
+    let Ok(tags) = tags.to_str() else {
+        return false;
+    };
+
+    tags.split(',').map(|tag| tag.trim()).any(|tag| {
+        tag == "*"
+            || tag
+                .trim_start_matches("W/")
+                .trim_matches('"')
+                .split_once('-')
+                .is_some_and(|(prefix, _)| prefix == revision)
+    })
+}
+
 /// An app error.
 #[derive(Debug)]
 pub struct AppError {
@@ -224,6 +848,10 @@ impl std::error::Error for AppError {
             AppErrorKind::Json(err) => Some(err),
             AppErrorKind::InvalidJwt(err) => Some(err),
             AppErrorKind::Database(err) => Some(err),
+            AppErrorKind::LogReload(err) => Some(err),
+            AppErrorKind::Backup(err) => Some(err),
+            AppErrorKind::Storage(err) => Some(err),
+            AppErrorKind::Store(err) => Some(err),
             _ => None,
         }
     }
@@ -252,15 +880,49 @@ pub enum AppErrorKind {
     /// The request's JSON body was malformed or unexpected.
     #[display("{_0}")]
     Json(JsonRejection),
+    /// The request's body could not be read.
+    #[display("{_0}")]
+    Bytes(BytesRejection),
+    /// The request's MessagePack body was malformed or unexpected.
+    #[display("{_0}")]
+    Msgpack(rmp_serde::decode::Error),
     /// A data field's value is out of range.
     #[from(ignore)]
     FieldOutOfRange(String),
+    /// A data field is required, but was not given.
+    #[from(ignore)]
+    MissingField(String),
     /// The card cannot be added to the user's inventory because they already
     /// own the card, or the card cannot be removed from the user's inventory
     /// because they do not have the card.
     #[display("Card `{_0}` cannot be transferred.`")]
     #[from(ignore)]
     InvalidTransfer(String),
+    /// The card cannot be granted to the user until they collect what it
+    /// requires.
+    #[display("Card `{_0}` has unmet prerequisites")]
+    #[from(ignore)]
+    UnmetPrerequisites(String),
+    /// The trade has already been accepted or cancelled.
+    #[from(ignore)]
+    #[display("Trade {_0} is no longer open")]
+    TradeClosed(i32),
+    /// The report has already been resolved or dismissed.
+    #[from(ignore)]
+    #[display("Report {_0} is no longer open")]
+    ReportClosed(i32),
+    /// An uploaded file could not be read at all.
+    #[from(ignore)]
+    #[display("The uploaded file could not be read")]
+    InvalidFile,
+    /// A card with the same name already exists in the guild.
+    #[from(ignore)]
+    #[display("Card `{_0}` already exists")]
+    AlreadyExists(String),
+    /// The card's content breaks the guild's lint rules.
+    #[from(ignore)]
+    #[display("Card content breaks {} lint rule(s)", _0.len())]
+    Lint(Vec<LintViolation>),
     /// A request sent a payload without a MIME type.
     MissingContentType,
     /// A request sent a payload with a MIME type the server refused to serve.
@@ -287,9 +949,21 @@ pub enum AppErrorKind {
     /// API key used to authenticate is invalid.
     #[display("API key is invalid")]
     InvalidApiKey,
+    /// Proxy assertion used to authenticate is malformed, expired or badly
+    /// signed.
+    #[display("Proxy assertion is invalid")]
+    InvalidProxyAssertion,
     /// Authentication is missing.
     #[display("Request unauthenticated")]
     Unauthenticated,
+    /// The client made too many requests, and may try again after the
+    /// duration.
+    #[from(ignore)]
+    #[display("Rate limited for {_0:?}")]
+    RateLimited(Duration),
+    /// The resource changed since the client last fetched it.
+    #[display("Precondition failed")]
+    PreconditionFailed,
     /// Missing mTLS certificate for secured route.
     #[display("Missing mTLS certificate for secured route")]
     MissingCertificate,
@@ -299,6 +973,28 @@ pub enum AppErrorKind {
     /// An internal database error happened that was unhandled.
     #[display("{_0}")]
     Database(sqlx::Error),
+    /// The tracing filter could not be replaced.
+    #[display("{_0}")]
+    LogReload(reload::Error),
+    /// A database backup could not be taken.
+    #[display("{_0}")]
+    Backup(BackupError),
+    /// Card content could not be read or written.
+    #[display("{_0}")]
+    Storage(StorageError),
+    /// A store failed.
+    #[display("{_0}")]
+    Store(StoreError),
+    /// The request took longer than the configured timeout.
+    #[from(ignore)]
+    #[display("Timed out after {_0:?}")]
+    Timeout(Duration),
+    /// The request failed on purpose; see [`crate::fault`].
+    #[display("Injected fault")]
+    InjectedFault,
+    /// The request could change data while the server is in maintenance.
+    #[display("Server in maintenance")]
+    Maintenance,
 }
 
 impl AppErrorKind {
@@ -307,6 +1003,10 @@ impl AppErrorKind {
         matches!(
             self,
             AppErrorKind::Database(_)
+                | AppErrorKind::LogReload(_)
+                | AppErrorKind::Backup(_)
+                | AppErrorKind::Storage(_)
+                | AppErrorKind::Store(_)
                 | AppErrorKind::Json(JsonRejection::BytesRejection(_))
                 | AppErrorKind::Form(FormRejection::BytesRejection(_))
         )
@@ -315,6 +1015,44 @@ impl AppErrorKind {
 
 impl IntoResponse for AppError {
     fn into_response(mut self) -> Response {
+        // lint errors carry their violations in the body
+        if let AppErrorKind::Lint(violations) = &mut self.kind {
+            let error = LintError {
+                error: ApiError {
+                    code: ErrorCode::LintViolation,
+                    message: self
+                        .message
+                        .take()
+                        .unwrap_or_else(|| "Card content breaks the guild's lint rules.".into()),
+                    request_id: request::id::current(),
+                },
+                violations: std::mem::take(violations),
+            };
+
+            return (StatusCode::BAD_REQUEST, AppJson(error)).into_response();
+        }
+
+        // rate limited clients are told when to come back
+        if let AppErrorKind::RateLimited(retry_after) = &self.kind {
+            let error = ApiError {
+                code: ErrorCode::RateLimited,
+                message: self
+                    .message
+                    .take()
+                    .unwrap_or_else(|| "Too many requests.".into()),
+                request_id: request::id::current(),
+            };
+            // round up so clients never come back too early
+            let retry_after = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                AppJson(error),
+            )
+                .into_response();
+        }
+
         let (status, mut error, internal_error) = match self.kind {
             // QUERY errors
             AppErrorKind::Query(QueryRejection::FailedToDeserializeQueryString(error)) => (
@@ -322,6 +1060,7 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::InvalidData,
                     message: error.to_string(),
+                    request_id: None,
                 },
                 None,
             ),
@@ -331,6 +1070,7 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::InvalidData,
                     message: error.to_string(),
+                    request_id: None,
                 },
                 None,
             ),
@@ -339,6 +1079,7 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::InvalidData,
                     message: error.to_string(),
+                    request_id: None,
                 },
                 None,
             ),
@@ -347,6 +1088,7 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::UnsupportedContentType,
                     message: "No supported content type.".into(),
+                    request_id: None,
                 },
                 None,
             ),
@@ -356,6 +1098,7 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::InvalidData,
                     message: error.to_string(),
+                    request_id: None,
                 },
                 None,
             ),
@@ -364,6 +1107,7 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::MalformedJson,
                     message: error.to_string(),
+                    request_id: None,
                 },
                 None,
             ),
@@ -372,6 +1116,26 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::UnsupportedContentType,
                     message: "No supported content type.".into(),
+                    request_id: None,
+                },
+                None,
+            ),
+            // MessagePack errors
+            AppErrorKind::Bytes(rejection) => (
+                rejection.status(),
+                ApiError {
+                    code: ErrorCode::InvalidData,
+                    message: rejection.body_text(),
+                    request_id: None,
+                },
+                None,
+            ),
+            AppErrorKind::Msgpack(error) => (
+                StatusCode::BAD_REQUEST,
+                ApiError {
+                    code: ErrorCode::InvalidData,
+                    message: error.to_string(),
+                    request_id: None,
                 },
                 None,
             ),
@@ -381,6 +1145,37 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::InvalidTransfer,
                     message: format!("Ownership of card `{}` cannot be transferred.", name),
+                    request_id: None,
+                },
+                None,
+            ),
+            AppErrorKind::UnmetPrerequisites(name) => (
+                StatusCode::BAD_REQUEST,
+                ApiError {
+                    code: ErrorCode::UnmetPrerequisites,
+                    message: format!(
+                        "Card `{}` cannot be granted until its prerequisites are met.",
+                        name
+                    ),
+                    request_id: None,
+                },
+                None,
+            ),
+            AppErrorKind::PreconditionFailed => (
+                StatusCode::PRECONDITION_FAILED,
+                ApiError {
+                    code: ErrorCode::PreconditionFailed,
+                    message: "The resource was changed by someone else.".into(),
+                    request_id: None,
+                },
+                None,
+            ),
+            AppErrorKind::AlreadyExists(name) => (
+                StatusCode::CONFLICT,
+                ApiError {
+                    code: ErrorCode::InvalidData,
+                    message: format!("A card named `{}` already exists.", name),
+                    request_id: None,
                 },
                 None,
             ),
@@ -390,6 +1185,43 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::InvalidData,
                     message: format!("Field `{}`'s value is out of range.", name),
+                    request_id: None,
+                },
+                None,
+            ),
+            AppErrorKind::MissingField(name) => (
+                StatusCode::BAD_REQUEST,
+                ApiError {
+                    code: ErrorCode::InvalidData,
+                    message: format!("Field `{}` is required.", name),
+                    request_id: None,
+                },
+                None,
+            ),
+            AppErrorKind::TradeClosed(id) => (
+                StatusCode::CONFLICT,
+                ApiError {
+                    code: ErrorCode::InvalidTransfer,
+                    message: format!("Trade {} is no longer open.", id),
+                    request_id: None,
+                },
+                None,
+            ),
+            AppErrorKind::ReportClosed(id) => (
+                StatusCode::CONFLICT,
+                ApiError {
+                    code: ErrorCode::ReportClosed,
+                    message: format!("Report {} is no longer open.", id),
+                    request_id: None,
+                },
+                None,
+            ),
+            AppErrorKind::InvalidFile => (
+                StatusCode::BAD_REQUEST,
+                ApiError {
+                    code: ErrorCode::InvalidData,
+                    message: "The uploaded file could not be read.".into(),
+                    request_id: None,
                 },
                 None,
             ),
@@ -398,6 +1230,7 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::NotFound,
                     message: format!("Unrecognized MIME type: {}.", mime),
+                    request_id: None,
                 },
                 None,
             ),
@@ -406,6 +1239,7 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::NotFound,
                     message: "Missing request content type.".into(),
+                    request_id: None,
                 },
                 None,
             ),
@@ -414,6 +1248,7 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::NotFound,
                     message: "The resource was not found.".into(),
+                    request_id: None,
                 },
                 None,
             ),
@@ -422,6 +1257,7 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::Forbidden,
                     message: "This resource is forbidden.".into(),
+                    request_id: None,
                 },
                 None,
             ),
@@ -430,6 +1266,7 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::Hidden,
                     message: format!("The card `{}` is hidden to you.", card_name),
+                    request_id: None,
                 },
                 None,
             ),
@@ -438,6 +1275,7 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::InsufficientPermissions,
                     message: "You don't have the permissions to do this.".into(),
+                    request_id: None,
                 },
                 None,
             ),
@@ -453,6 +1291,7 @@ impl IntoResponse for AppError {
                     } else {
                         "Access token verification failed.".into()
                     },
+                    request_id: None,
                 },
                 None,
             ),
@@ -461,6 +1300,16 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::BadCredentials,
                     message: "Invalid API key.".into(),
+                    request_id: None,
+                },
+                None,
+            ),
+            AppErrorKind::InvalidProxyAssertion => (
+                StatusCode::UNAUTHORIZED,
+                ApiError {
+                    code: ErrorCode::BadCredentials,
+                    message: "Proxy assertion verification failed.".into(),
+                    request_id: None,
                 },
                 None,
             ),
@@ -471,6 +1320,35 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::Unauthenticated,
                     message: "Request is unauthenticated.".into(),
+                    request_id: None,
+                },
+                None,
+            ),
+            AppErrorKind::Timeout(_) => (
+                StatusCode::GATEWAY_TIMEOUT,
+                ApiError {
+                    code: ErrorCode::Timeout,
+                    message: "The request took too long.".into(),
+                    request_id: None,
+                },
+                None,
+            ),
+            AppErrorKind::Maintenance => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                ApiError {
+                    code: ErrorCode::Maintenance,
+                    message: "The server is in maintenance; only reads are served.".into(),
+                    request_id: None,
+                },
+                None,
+            ),
+            // indistinguishable from a real failure, but not logged as one
+            AppErrorKind::InjectedFault => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError {
+                    code: ErrorCode::InternalServerError,
+                    message: "An internal server error occured.".into(),
+                    request_id: None,
                 },
                 None,
             ),
@@ -480,6 +1358,7 @@ impl IntoResponse for AppError {
                 ApiError {
                     code: ErrorCode::InternalServerError,
                     message: "An internal server error occured.".into(),
+                    request_id: None,
                 },
                 Some(AppError {
                     kind: error_kind,
@@ -492,6 +1371,8 @@ impl IntoResponse for AppError {
             error.message = message;
         }
 
+        error.request_id = request::id::current();
+
         let mut response = (status, AppJson(error)).into_response();
         if let Some(error) = internal_error {
             response.extensions_mut().insert(Arc::new(error));
@@ -545,6 +1426,28 @@ impl Debug for SigningKeys {
     }
 }
 
+/// Converts a configured journal mode to the one sqlx sets.
+fn journal_mode(mode: JournalMode) -> SqliteJournalMode {
+    match mode {
+        JournalMode::Delete => SqliteJournalMode::Delete,
+        JournalMode::Truncate => SqliteJournalMode::Truncate,
+        JournalMode::Persist => SqliteJournalMode::Persist,
+        JournalMode::Memory => SqliteJournalMode::Memory,
+        JournalMode::Wal => SqliteJournalMode::Wal,
+        JournalMode::Off => SqliteJournalMode::Off,
+    }
+}
+
+/// Converts a configured sync level to the one sqlx sets.
+fn synchronous(level: Synchronous) -> SqliteSynchronous {
+    match level {
+        Synchronous::Off => SqliteSynchronous::Off,
+        Synchronous::Normal => SqliteSynchronous::Normal,
+        Synchronous::Full => SqliteSynchronous::Full,
+        Synchronous::Extra => SqliteSynchronous::Extra,
+    }
+}
+
 /// Creates a random HMAC signing key and returns it as a [`String`]
 pub fn random_signing_key() -> String {
     let mut rng = StdRng::from_os_rng();
@@ -0,0 +1,81 @@
+//! Privileged network allowlist.
+//!
+//! A self-hoster exposing the API publicly may keep its privileged half
+//! reachable only from their own networks. If any
+//! [`privileged_networks`](crate::config::ServerConfig::privileged_networks)
+//! are configured, requests from anywhere else are refused if they present an
+//! API key or a client certificate, if they authenticate as a managed user in
+//! any other way, or if they are made to a route only managed users may call,
+//! like the user proxy endpoints. This holds even if the caller's credentials
+//! would have been accepted.
+
+use axum::{
+    RequestPartsExt as _,
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::{IntoResponse as _, Response},
+};
+
+use nymph_model::policy::Access;
+
+use crate::{
+    app::{AppError, AppErrorKind, AppState},
+    request::forwarded::ClientAddr,
+    tls::ClientCertificate,
+};
+
+use super::{Authentication, api_key::X_API_KEY, policy};
+
+/// Middleware that refuses privileged requests from outside the configured
+/// networks.
+pub async fn restrict(
+    State(state): State<AppState>,
+    path: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if state.privileged_networks.is_empty() {
+        return next.run(request).await;
+    }
+
+    let privileged = request.headers().contains_key(X_API_KEY)
+        || request.extensions().get::<ClientCertificate>().is_some()
+        || path
+            .and_then(|path| policy::find(request.method(), path.as_str()))
+            .is_some_and(|policy| matches!(policy.access, Access::Managed));
+
+    // callers that fail to authenticate are refused later on anyway; the
+    // result is cached, so the handler does not authenticate them again
+    let (mut parts, body) = request.into_parts();
+    let privileged = privileged
+        || parts
+            .extract_with_state::<Authentication, _>(&state)
+            .await
+            .is_ok_and(|auth| auth.managed);
+    let request = Request::from_parts(parts, body);
+
+    if !privileged {
+        return next.run(request).await;
+    }
+
+    // if the address is not known, it is not allowed
+    let addr = request.extensions().get::<ClientAddr>().copied();
+    let allowed = addr.is_some_and(|addr| {
+        state
+            .privileged_networks
+            .iter()
+            .any(|net| net.contains(&*addr))
+    });
+
+    if !allowed {
+        tracing::debug!(
+            client = addr.map(tracing::field::display),
+            "privileged request refused"
+        );
+        return AppError::from(AppErrorKind::Forbidden)
+            .with_message("Privileged requests cannot be made from this address.")
+            .into_response();
+    }
+
+    next.run(request).await
+}
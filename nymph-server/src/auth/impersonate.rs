@@ -0,0 +1,169 @@
+//! Impersonation.
+//!
+//! Lets a managed user read the API as another user, for reproducing issues
+//! only that user runs into. See [`nymph_model::impersonation`] for the
+//! headers. Every request made this way is written to the impersonation log
+//! before it is served, and refused if it cannot be.
+
+use axum::{
+    RequestPartsExt as _,
+    extract::{FromRef, FromRequestParts, OriginalUri},
+};
+
+use chrono::Utc;
+
+use http::{HeaderName, Method, request::Parts};
+
+use nymph_model::impersonation::{ACT_AS_HEADER, ACT_AS_REASON_HEADER, MAX_REASON_LEN};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppState},
+    request,
+};
+
+use super::{AuthenticatedUser, TokenAuthentication, authenticate_client, proxy::X_PROXY_FOR};
+
+pub const X_ACT_AS: HeaderName = HeaderName::from_static(ACT_AS_HEADER);
+pub const X_ACT_AS_REASON: HeaderName = HeaderName::from_static(ACT_AS_REASON_HEADER);
+
+/// Impersonation authentication.
+#[derive(Clone, Debug)]
+pub struct ImpersonationAuthentication {
+    /// The user the request is made as.
+    pub user: AuthenticatedUser,
+    /// The managed user that made the request.
+    pub impersonated_by: AuthenticatedUser,
+    /// Why the request was made.
+    pub reason: String,
+}
+
+impl<S> FromRequestParts<S> for ImpersonationAuthentication
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // if the result was cached, simply return the cached value; this also
+        // keeps a request from being logged more than once
+        if let Some(auth) = parts.extensions.get::<ImpersonationAuthentication>() {
+            return Ok(auth.clone());
+        }
+
+        let Some(user_id) = parts.headers.get(X_ACT_AS) else {
+            return Err(AppErrorKind::Unauthenticated.into());
+        };
+        let user_id = user_id
+            .to_str()
+            .ok()
+            .and_then(|id| id.trim().parse::<i32>().ok())
+            .ok_or_else(|| AppErrorKind::FieldOutOfRange(ACT_AS_HEADER.into()))?;
+
+        if parts.headers.contains_key(X_PROXY_FOR) {
+            return Err(AppError::from(AppErrorKind::Forbidden)
+                .with_message("A request cannot be proxied and made as another user at once."));
+        }
+
+        // the caller authenticates as themselves, never through a proxy
+        let impersonated_by = match parts
+            .extract_with_state::<TokenAuthentication, S>(state)
+            .await
+        {
+            Ok(token) => token.user,
+            Err(err) if matches!(err.kind(), AppErrorKind::Unauthenticated) => {
+                authenticate_client(parts, state).await?
+            }
+            Err(err) => return Err(err),
+        };
+
+        if !impersonated_by.managed {
+            return Err(AppErrorKind::Forbidden.into());
+        }
+
+        if !matches!(parts.method, Method::GET | Method::HEAD) {
+            return Err(AppError::from(AppErrorKind::Forbidden)
+                .with_message("Only reads may be made as another user."));
+        }
+
+        let reason = parts
+            .headers
+            .get(X_ACT_AS_REASON)
+            .and_then(|reason| reason.to_str().ok())
+            .map(|reason| reason.trim())
+            .filter(|reason| !reason.is_empty())
+            .ok_or_else(|| AppErrorKind::MissingField(ACT_AS_REASON_HEADER.into()))?;
+
+        if reason.len() > MAX_REASON_LEN {
+            return Err(AppErrorKind::FieldOutOfRange(ACT_AS_REASON_HEADER.into()).into());
+        }
+
+        let reason = reason.to_owned();
+        let state = AppState::from_ref(state);
+
+        // nested routers strip the version prefix from the path
+        let path = match parts.extensions.get::<OriginalUri>() {
+            Some(OriginalUri(uri)) => uri.path().to_owned(),
+            None => parts.uri.path().to_owned(),
+        };
+
+        let user = sqlx::query_as::<_, AuthenticatedUser>(
+            r#"
+            SELECT
+                u.id, u.display_name, u.managed
+            FROM
+                user u
+            WHERE
+                u.id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| {
+            AppError::from(AppErrorKind::NotFound)
+                .with_message(format!("The user of id {} does not exist.", user_id))
+        })?;
+
+        // managed users can already see everything
+        if user.managed {
+            return Err(AppError::from(AppErrorKind::Forbidden)
+                .with_message("Requests cannot be made as managed users."));
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO impersonation_log
+                (actor_id, user_id, reason, method, path, request_id, inserted_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(impersonated_by.id)
+        .bind(user.id)
+        .bind(&reason)
+        .bind(parts.method.as_str())
+        .bind(path)
+        .bind(request::id::current())
+        .bind(Utc::now())
+        .execute(&state.db)
+        .await?;
+
+        tracing::info!(
+            actor_id = impersonated_by.id,
+            user_id = user.id,
+            reason,
+            "request made as another user"
+        );
+
+        let auth = ImpersonationAuthentication {
+            user,
+            impersonated_by,
+            reason,
+        };
+
+        // cache to extensions
+        parts.extensions.insert(auth.clone());
+
+        Ok(auth)
+    }
+}
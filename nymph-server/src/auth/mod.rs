@@ -1,9 +1,17 @@
 //! Service authentication.
 
+pub mod allowlist;
 pub mod api_key;
+pub mod impersonate;
+pub mod mtls;
+pub mod policy;
+pub mod proxy;
 pub mod token;
 
 pub use api_key::ApiKeyAuthentication;
+pub use impersonate::ImpersonationAuthentication;
+pub use mtls::MtlsAuthentication;
+pub use proxy::ProxyAuthentication;
 pub use token::{Claims, ClaimsBuilder, Sub, TokenAuthentication};
 
 use axum::{
@@ -11,7 +19,7 @@ use axum::{
     extract::{FromRef, FromRequestParts},
 };
 
-use derive_more::Deref;
+use derive_more::{Deref, From};
 
 use http::request::Parts;
 
@@ -33,8 +41,10 @@ pub struct AuthenticatedUser {
 /// Authentication guard.
 ///
 /// This doesn't care how a user gets authenticated, just that they eventually
-/// will be authenticated.
-#[derive(Clone, Debug, Deref)]
+/// will be authenticated. Tokens are tried first, then proxy assertions, then
+/// client certificates, then API keys. A request made as another user is
+/// authenticated as that user.
+#[derive(Clone, Debug, Deref, From)]
 pub struct Authentication(AuthenticatedUser);
 
 impl<S> FromRequestParts<S> for Authentication
@@ -45,18 +55,63 @@ where
     type Rejection = AppError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if parts.headers.contains_key(impersonate::X_ACT_AS) {
+            return parts
+                .extract_with_state::<ImpersonationAuthentication, S>(state)
+                .await
+                .map(|impersonation| Authentication(impersonation.user.clone()));
+        }
+
         let token = parts
             .extract_with_state::<TokenAuthentication, S>(state)
             .await
             .map(|token| Authentication(token.user.clone()));
 
-        match token {
-            Ok(token) => Ok(token),
+        let proxy = match token {
+            Ok(token) => return Ok(token),
             Err(err) if matches!(err.kind(), AppErrorKind::Unauthenticated) => parts
-                .extract_with_state::<ApiKeyAuthentication, S>(state)
+                .extract_with_state::<ProxyAuthentication, S>(state)
                 .await
-                .map(|api_key| Authentication(api_key.user.clone())),
+                .map(|proxy| Authentication(proxy.user.clone())),
+            Err(err) => return Err(err),
+        };
+
+        // a request without a proxy assertion falls back to the client's own
+        // credentials
+        match proxy {
+            Ok(proxy) => Ok(proxy),
+            Err(err)
+                if matches!(err.kind(), AppErrorKind::Unauthenticated)
+                    && !parts.headers.contains_key(proxy::X_PROXY_FOR) =>
+            {
+                authenticate_client(parts, state).await.map(Authentication)
+            }
             Err(err) => Err(err),
         }
     }
 }
+
+/// Authenticates the client making a request, rather than a user it acts
+/// for.
+///
+/// A client certificate is preferred over an API key.
+pub async fn authenticate_client<S>(
+    parts: &mut Parts,
+    state: &S,
+) -> Result<AuthenticatedUser, AppError>
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    match parts
+        .extract_with_state::<MtlsAuthentication, S>(state)
+        .await
+    {
+        Ok(mtls) => Ok(mtls.user),
+        Err(err) if matches!(err.kind(), AppErrorKind::MissingCertificate) => parts
+            .extract_with_state::<ApiKeyAuthentication, S>(state)
+            .await
+            .map(|api_key| api_key.user),
+        Err(err) => Err(err),
+    }
+}
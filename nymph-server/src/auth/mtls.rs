@@ -0,0 +1,176 @@
+//! Client certificate authentication.
+//!
+//! A managed client may present a certificate signed by the configured
+//! client CA instead of an API key. The certificate's common name picks the
+//! user, which is created the first time the name is seen.
+
+use axum::{
+    extract::{FromRef, FromRequestParts, MatchedPath, Request, State},
+    middleware::Next,
+    response::{IntoResponse as _, Response},
+};
+
+use chrono::Utc;
+
+use http::request::Parts;
+
+use sqlx::SqlitePool;
+
+use crate::{
+    app::{ApiVersion, AppError, AppErrorKind, AppState},
+    tls::ClientCertificate,
+};
+
+use super::AuthenticatedUser;
+
+/// The longest common name a user may be created for.
+const MAX_COMMON_NAME_LEN: usize = 255;
+
+/// Client certificate authentication.
+#[derive(Clone, Debug)]
+pub struct MtlsAuthentication {
+    pub user: AuthenticatedUser,
+}
+
+impl<S> FromRequestParts<S> for MtlsAuthentication
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // if the result was cached, simply return the cached value
+        if let Some(auth) = parts.extensions.get::<MtlsAuthentication>() {
+            return Ok(auth.clone());
+        }
+
+        let Some(certificate) = parts.extensions.get::<ClientCertificate>() else {
+            return Err(AppErrorKind::MissingCertificate.into());
+        };
+
+        let Some(common_name) = certificate
+            .common_name
+            .as_deref()
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty() && name.len() <= MAX_COMMON_NAME_LEN)
+        else {
+            return Err(AppErrorKind::InvalidCommonName.into());
+        };
+
+        let state = AppState::from_ref(state);
+        let user = get_or_create_bot_user(&state.db, common_name).await?;
+
+        let auth = MtlsAuthentication { user };
+        parts.extensions.insert(auth.clone());
+
+        Ok(auth)
+    }
+}
+
+/// Gets the managed user of a certificate's common name, creating it if the
+/// name was never seen.
+pub async fn get_or_create_bot_user(
+    db: &SqlitePool,
+    common_name: &str,
+) -> Result<AuthenticatedUser, AppError> {
+    if let Some(user) = get_bot_user(db, common_name).await? {
+        return Ok(user);
+    }
+
+    let now = Utc::now();
+    let mut tx = db.begin().await?;
+
+    let user = sqlx::query_as::<_, AuthenticatedUser>(
+        r#"
+        INSERT INTO user (display_name, managed, inserted_at, updated_at)
+        VALUES ($1, TRUE, $2, $2)
+        RETURNING id, display_name, managed
+        "#,
+    )
+    .bind(common_name)
+    .bind(now)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let res = sqlx::query(
+        r#"
+        INSERT INTO mtls_auth (user_id, common_name, inserted_at)
+        VALUES ($1, $2, $3)
+        "#,
+    )
+    .bind(user.id)
+    .bind(common_name)
+    .bind(now)
+    .execute(&mut *tx)
+    .await;
+
+    match res {
+        Ok(_) => (),
+        // a concurrent request created the user first; dropping the
+        // transaction takes back the user created here
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+            drop(tx);
+
+            return match get_bot_user(db, common_name).await? {
+                Some(user) => Ok(user),
+                None => Err(sqlx::Error::Database(err).into()),
+            };
+        }
+        Err(err) => return Err(err.into()),
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(user_id = user.id, common_name, "created certificate user");
+
+    Ok(user)
+}
+
+/// Gets the managed user of a certificate's common name.
+async fn get_bot_user(
+    db: &SqlitePool,
+    common_name: &str,
+) -> Result<Option<AuthenticatedUser>, sqlx::Error> {
+    sqlx::query_as::<_, AuthenticatedUser>(
+        r#"
+        SELECT
+            u.id, u.display_name, u.managed
+        FROM
+            user u, mtls_auth ma
+        WHERE
+            u.id = ma.user_id
+            AND ma.common_name = $1
+        "#,
+    )
+    .bind(common_name)
+    .fetch_optional(db)
+    .await
+}
+
+/// Middleware that refuses requests to the configured routes unless the
+/// client presented a certificate.
+pub async fn require(
+    State(state): State<AppState>,
+    path: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let required = path.is_some_and(|path| {
+        let (_, path) = ApiVersion::split(path.as_str());
+
+        // nested index routes are matched with a trailing slash
+        let path = match path.strip_suffix('/') {
+            Some(path) if !path.is_empty() => path,
+            _ => path,
+        };
+
+        state.tls_client_routes.iter().any(|route| route == path)
+    });
+
+    if required && request.extensions().get::<ClientCertificate>().is_none() {
+        return AppError::from(AppErrorKind::MissingCertificate).into_response();
+    }
+
+    next.run(request).await
+}
@@ -0,0 +1,329 @@
+//! Route authorization policies.
+//!
+//! Every route is listed in [`POLICIES`] with who may call it, and
+//! [`enforce`] checks callers against it before the route's handler runs.
+//! Routes missing from the table are refused outright, so a new route cannot
+//! be served without deciding who may call it. Paths are listed without a
+//! version prefix, as every version shares the same policies.
+//!
+//! Handlers still make their own finer checks, like whether the caller is
+//! part of a trade; those are described in each policy's note.
+
+use axum::{
+    extract::{MatchedPath, RawPathParams, Request},
+    middleware::Next,
+    response::Response,
+};
+
+use http::Method;
+
+use nymph_model::policy::Access;
+
+use crate::{
+    app::{ApiVersion, AppError, AppErrorKind},
+    auth::Authentication,
+};
+
+/// The policy of a single route.
+#[derive(Clone, Copy, Debug)]
+pub struct Policy {
+    /// The HTTP method of the route.
+    pub method: &'static str,
+    /// The route's path, as matched by the router.
+    pub path: &'static str,
+    /// Who may call the route.
+    pub access: Access,
+    /// Any checks the route makes on top of its access.
+    pub note: Option<&'static str>,
+}
+
+impl Policy {
+    const fn new(method: &'static str, path: &'static str, access: Access) -> Policy {
+        Policy {
+            method,
+            path,
+            access,
+            note: None,
+        }
+    }
+
+    const fn note(self, note: &'static str) -> Policy {
+        Policy {
+            note: Some(note),
+            ..self
+        }
+    }
+}
+
+/// The policy of every route the server serves.
+pub static POLICIES: &[Policy] = &[
+    // cards
+    Policy::new("GET", "/guilds/{guild_id}/cards", Access::Authenticated)
+        .note("private cards are only listed to their owners and managed users; only managed users may filter by non-public visibility"),
+    Policy::new("POST", "/guilds/{guild_id}/cards", Access::Managed),
+    Policy::new("POST", "/guilds/{guild_id}/cards/archive", Access::Managed),
+    Policy::new("POST", "/guilds/{guild_id}/cards/import", Access::Managed),
+    Policy::new("GET", "/guilds/{guild_id}/cards/popular", Access::Managed),
+    Policy::new("GET", "/guilds/{guild_id}/cards/lookup", Access::Authenticated)
+        .note("hidden and private cards are only shown to their owners and managed users"),
+    Policy::new(
+        "GET",
+        "/guilds/{guild_id}/cards/{id}",
+        Access::Authenticated,
+    )
+    .note("private cards are only shown to their owners and managed users"),
+    Policy::new("PATCH", "/guilds/{guild_id}/cards/{id}", Access::Managed),
+    Policy::new(
+        "GET",
+        "/guilds/{guild_id}/cards/{id}/owners",
+        Access::Managed,
+    ),
+    Policy::new(
+        "GET",
+        "/guilds/{guild_id}/cards/{id}/grant-policy",
+        Access::Managed,
+    ),
+    Policy::new(
+        "PUT",
+        "/guilds/{guild_id}/cards/{id}/grant-policy",
+        Access::Managed,
+    ),
+    Policy::new(
+        "GET",
+        "/guilds/{guild_id}/cards/{id}/prerequisites",
+        Access::Managed,
+    ),
+    Policy::new(
+        "PUT",
+        "/guilds/{guild_id}/cards/{id}/prerequisites",
+        Access::Managed,
+    ),
+    Policy::new(
+        "POST",
+        "/guilds/{guild_id}/cards/{id}/reports",
+        Access::Authenticated,
+    )
+    .note("non-managed users must be able to see the card"),
+    // events
+    Policy::new("GET", "/guilds/{guild_id}/events", Access::Authenticated),
+    Policy::new("POST", "/guilds/{guild_id}/events", Access::Managed),
+    Policy::new(
+        "GET",
+        "/guilds/{guild_id}/events/{id}",
+        Access::Authenticated,
+    ),
+    Policy::new("GET", "/guilds/{guild_id}/events/replay", Access::Managed),
+    // webhooks
+    Policy::new("GET", "/guilds/{guild_id}/webhooks", Access::Managed),
+    Policy::new("POST", "/guilds/{guild_id}/webhooks", Access::Managed),
+    Policy::new(
+        "DELETE",
+        "/guilds/{guild_id}/webhooks/{id}",
+        Access::Managed,
+    ),
+    // reports
+    Policy::new("GET", "/guilds/{guild_id}/reports", Access::Managed),
+    Policy::new(
+        "POST",
+        "/guilds/{guild_id}/reports/{id}/resolve",
+        Access::Managed,
+    ),
+    // reward rules
+    Policy::new("GET", "/guilds/{guild_id}/rules", Access::Managed),
+    Policy::new("POST", "/guilds/{guild_id}/rules", Access::Managed),
+    Policy::new("GET", "/guilds/{guild_id}/rules/{id}", Access::Managed),
+    Policy::new("PATCH", "/guilds/{guild_id}/rules/{id}", Access::Managed),
+    Policy::new("DELETE", "/guilds/{guild_id}/rules/{id}", Access::Managed),
+    // syndication
+    Policy::new("GET", "/guilds/{guild_id}/syndications", Access::Managed),
+    Policy::new("POST", "/guilds/{guild_id}/syndications", Access::Managed),
+    Policy::new(
+        "DELETE",
+        "/guilds/{guild_id}/syndications/{id}",
+        Access::Managed,
+    ),
+    // drop tables
+    Policy::new("GET", "/guilds/{guild_id}/drop-table", Access::Managed),
+    Policy::new(
+        "GET",
+        "/guilds/{guild_id}/drop-table/preview",
+        Access::Managed,
+    ),
+    Policy::new(
+        "PUT",
+        "/guilds/{guild_id}/drop-table/{card_id}",
+        Access::Managed,
+    ),
+    Policy::new(
+        "DELETE",
+        "/guilds/{guild_id}/drop-table/{card_id}",
+        Access::Managed,
+    ),
+    // seasons
+    Policy::new("GET", "/guilds/{guild_id}/seasons", Access::Authenticated),
+    Policy::new("POST", "/guilds/{guild_id}/seasons", Access::Managed),
+    // guilds
+    Policy::new("GET", "/guilds/{guild_id}/audit", Access::Managed),
+    Policy::new("GET", "/guilds/{guild_id}/lint", Access::Managed),
+    Policy::new("PUT", "/guilds/{guild_id}/lint", Access::Managed),
+    Policy::new("GET", "/guilds/{guild_id}/name-rules", Access::Managed),
+    Policy::new("PUT", "/guilds/{guild_id}/name-rules", Access::Managed),
+    Policy::new(
+        "GET",
+        "/guilds/{guild_id}/trade-in",
+        Access::Authenticated,
+    ),
+    Policy::new("PUT", "/guilds/{guild_id}/trade-in", Access::Managed),
+    Policy::new(
+        "GET",
+        "/guilds/{guild_id}/ownership/export",
+        Access::Managed,
+    ),
+    Policy::new(
+        "GET",
+        "/guilds/{guild_id}/announcements",
+        Access::Managed,
+    ),
+    Policy::new(
+        "PUT",
+        "/guilds/{guild_id}/announcements",
+        Access::Managed,
+    ),
+    Policy::new(
+        "POST",
+        "/guilds/{guild_id}/templates/{id}/instantiate",
+        Access::Managed,
+    ),
+    // users
+    Policy::new("POST", "/users/discord", Access::Managed),
+    Policy::new("POST", "/users/discord/batch", Access::Managed),
+    Policy::new("GET", "/users/discord/stale", Access::Managed),
+    Policy::new("GET", "/users/{user_id}/cards", Access::Owner),
+    Policy::new("GET", "/users/{user_id}/cards/export", Access::Owner),
+    Policy::new("GET", "/users/{user_id}/cards/export.csv", Access::Owner),
+    Policy::new("POST", "/users/{user_id}/cards", Access::Managed)
+        .note("the managed client checks the card's grant policy"),
+    Policy::new(
+        "DELETE",
+        "/users/{user_id}/cards/{card_id}",
+        Access::Managed,
+    ),
+    Policy::new(
+        "POST",
+        "/users/{user_id}/cards/{card_id}/transfer",
+        Access::Owner,
+    ),
+    Policy::new(
+        "POST",
+        "/users/{user_id}/cards/{card_id}/trade-in",
+        Access::Owner,
+    ),
+    Policy::new(
+        "PUT",
+        "/users/{user_id}/cards/{card_id}/reaction",
+        Access::Owner,
+    )
+    .note("only owners of the card may rate it"),
+    Policy::new(
+        "DELETE",
+        "/users/{user_id}/cards/{card_id}/reaction",
+        Access::Owner,
+    ),
+    Policy::new("PUT", "/users/{user_id}/favorites/{card_id}", Access::Owner),
+    Policy::new(
+        "DELETE",
+        "/users/{user_id}/favorites/{card_id}",
+        Access::Owner,
+    ),
+    Policy::new("GET", "/users/{user_id}/progress", Access::Owner),
+    Policy::new(
+        "GET",
+        "/users/{user_id}/seasons/{season_id}/cards",
+        Access::Owner,
+    ),
+    // trades
+    Policy::new("POST", "/trades", Access::Authenticated),
+    Policy::new("GET", "/trades/{id}", Access::Authenticated)
+        .note("non-managed users must be either side of the trade"),
+    Policy::new("POST", "/trades/{id}/accept", Access::Authenticated)
+        .note("non-managed users must be the recipient of the trade"),
+    Policy::new("POST", "/trades/{id}/cancel", Access::Authenticated)
+        .note("non-managed users must be either side of the trade"),
+    // views
+    Policy::new("POST", "/views", Access::Authenticated),
+    Policy::new("GET", "/views/{id}", Access::Authenticated)
+        .note("non-managed users may only read views they stored"),
+    // gateway
+    Policy::new("GET", "/gateway", Access::Authenticated)
+        .note("non-managed users only receive events about public cards, without reports"),
+    // telemetry
+    Policy::new("POST", "/telemetry/guilds", Access::Managed),
+    Policy::new("DELETE", "/telemetry/guilds/{guild_id}", Access::Managed),
+    // operators
+    Policy::new("GET", "/admin/log-filter", Access::Managed),
+    Policy::new("PUT", "/admin/log-filter", Access::Managed),
+    Policy::new("POST", "/admin/backup", Access::Managed),
+    Policy::new("GET", "/admin/maintenance", Access::Managed),
+    Policy::new("PUT", "/admin/maintenance", Access::Managed),
+    Policy::new("GET", "/admin/policies", Access::Managed),
+    Policy::new("GET", "/admin/requests", Access::Managed),
+    Policy::new("GET", "/admin/impersonations", Access::Managed)
+        .note("requests made as other users cannot list it, as they are never managed"),
+    Policy::new("GET", "/admin/rolls/{seq}", Access::Managed),
+    Policy::new("GET", "/admin/guilds", Access::Managed),
+    Policy::new("GET", "/admin/templates", Access::Managed),
+    Policy::new("POST", "/admin/templates", Access::Managed),
+    Policy::new("DELETE", "/admin/templates/{id}", Access::Managed),
+];
+
+/// Finds the policy of a route.
+///
+/// Policies are shared by every version of the API, so `path` may have a
+/// version prefix.
+pub fn find(method: &Method, path: &str) -> Option<&'static Policy> {
+    let (_, path) = ApiVersion::split(path);
+
+    // nested index routes are matched with a trailing slash
+    let path = match path.strip_suffix('/') {
+        Some(path) if !path.is_empty() => path,
+        _ => path,
+    };
+
+    POLICIES
+        .iter()
+        .find(|policy| policy.method == method.as_str() && policy.path == path)
+}
+
+/// Middleware that checks the caller against the route's policy.
+pub async fn enforce(
+    method: Method,
+    path: MatchedPath,
+    params: RawPathParams,
+    auth: Authentication,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let Some(policy) = find(&method, path.as_str()) else {
+        tracing::error!(%method, path = path.as_str(), "route has no policy");
+        return Err(AppErrorKind::Forbidden.into());
+    };
+
+    match policy.access {
+        Access::Authenticated => (),
+        Access::Owner if auth.managed => (),
+        Access::Owner => {
+            let user_id = params
+                .iter()
+                .find(|(name, _)| *name == "user_id")
+                .and_then(|(_, value)| value.parse::<i32>().ok());
+
+            if user_id != Some(auth.id) {
+                return Err(AppErrorKind::InsufficientPermissions.into());
+            }
+        }
+        Access::Managed if auth.managed => (),
+        Access::Managed => return Err(AppErrorKind::Forbidden.into()),
+    }
+
+    Ok(next.run(request).await)
+}
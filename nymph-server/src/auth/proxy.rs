@@ -0,0 +1,125 @@
+//! Signed proxy assertion authentication.
+//!
+//! Lets a managed client act for a Discord user without holding an access
+//! token for them. See [`nymph_model::proxy`] for the assertion format.
+
+use axum::extract::{FromRef, FromRequestParts};
+
+use base16::decode;
+
+use chrono::Utc;
+
+use hmac::{Hmac, Mac as _};
+
+use http::{HeaderName, request::Parts};
+
+use nymph_model::proxy::{MAX_SKEW, PROXY_FOR_HEADER, ProxyAssertion};
+
+use sha2::Sha256;
+
+use crate::app::{AppError, AppErrorKind, AppState};
+
+use super::{AuthenticatedUser, authenticate_client};
+
+pub const X_PROXY_FOR: HeaderName = HeaderName::from_static(PROXY_FOR_HEADER);
+
+/// Proxy assertion authentication.
+#[derive(Clone, Debug)]
+pub struct ProxyAuthentication {
+    /// The user the request is made for.
+    pub user: AuthenticatedUser,
+    /// The managed user that made the request.
+    pub proxied_by: AuthenticatedUser,
+}
+
+impl<S> FromRequestParts<S> for ProxyAuthentication
+where
+    AppState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // if the result was cached, simply return the cached value
+        if let Some(auth) = parts.extensions.get::<ProxyAuthentication>() {
+            return Ok(auth.clone());
+        }
+
+        let Some(assertion) = parts
+            .headers
+            .get(X_PROXY_FOR)
+            .and_then(|s| s.to_str().ok())
+            .map(|s| s.to_owned())
+        else {
+            return Err(AppErrorKind::Unauthenticated.into());
+        };
+
+        // only managed users may proxy
+        let proxied_by = authenticate_client(parts, state).await?;
+
+        if !proxied_by.managed {
+            return Err(AppErrorKind::Forbidden.into());
+        }
+
+        let state = AppState::from_ref(state);
+
+        let Some(secret) = state.proxy_secret.as_ref() else {
+            return Err(AppError::from(AppErrorKind::InvalidProxyAssertion)
+                .with_message("Proxy assertions are not accepted by this server."));
+        };
+
+        let assertion = assertion
+            .parse::<ProxyAssertion>()
+            .map_err(|_| AppErrorKind::InvalidProxyAssertion)?;
+
+        if !verify(secret, &assertion) {
+            return Err(AppErrorKind::InvalidProxyAssertion.into());
+        }
+
+        let user = sqlx::query_as::<_, AuthenticatedUser>(
+            r#"
+            SELECT
+                u.id, u.display_name, u.managed
+            FROM
+                user u, discord_auth da
+            WHERE
+                u.id = da.user_id
+                AND da.discord_id = $1
+            "#,
+        )
+        .bind(assertion.discord_id.get() as i64)
+        .fetch_optional(&state.db)
+        .await?;
+
+        match user {
+            Some(user) => {
+                let auth = ProxyAuthentication { user, proxied_by };
+
+                // cache to extensions
+                parts.extensions.insert(auth.clone());
+
+                Ok(auth)
+            }
+            // the user has to be registered through `/users/discord` first
+            None => Err(AppErrorKind::Unauthenticated.into()),
+        }
+    }
+}
+
+/// Checks the signature and age of an assertion.
+fn verify(secret: &str, assertion: &ProxyAssertion) -> bool {
+    if (Utc::now().timestamp() - assertion.timestamp).abs() > MAX_SKEW {
+        return false;
+    }
+
+    let Ok(signature) = decode(&assertion.signature) else {
+        return false;
+    };
+
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any size");
+    mac.update(ProxyAssertion::message(assertion.discord_id, assertion.timestamp).as_bytes());
+
+    // compares in constant time
+    mac.verify_slice(&signature).is_ok()
+}
@@ -0,0 +1,114 @@
+//! Online database backups.
+//!
+//! Backups are taken with `VACUUM INTO`, which writes a consistent snapshot of
+//! the database while the server keeps serving requests.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+
+use derive_more::{Display, Error, From};
+
+use sqlx::SqlitePool;
+
+use tokio::fs;
+
+use crate::config::BackupConfig;
+
+/// The prefix of every backup file name.
+const PREFIX: &str = "nymph-";
+
+/// The extension of every backup file name.
+const EXTENSION: &str = ".sqlite";
+
+/// A finished backup.
+#[derive(Clone, Debug)]
+pub struct Backup {
+    /// Where the backup was written.
+    pub path: PathBuf,
+    /// The size of the backup, in bytes.
+    pub size: u64,
+    /// Old backups removed to make room for this one.
+    pub removed: Vec<PathBuf>,
+}
+
+/// An error taking a backup.
+#[derive(Debug, Display, Error, From)]
+pub enum BackupError {
+    /// The backup directory could not be read or written.
+    #[display("{_0}")]
+    Io(io::Error),
+    /// The database could not be copied.
+    #[display("{_0}")]
+    Database(sqlx::Error),
+}
+
+/// Writes a backup of the database to `directory`, then removes the oldest
+/// backups so only `config.keep` remain.
+pub async fn run(
+    db: &SqlitePool,
+    directory: &Path,
+    config: &BackupConfig,
+) -> Result<Backup, BackupError> {
+    fs::create_dir_all(directory).await?;
+
+    // timestamps sort the same way as the names do
+    let name = format!(
+        "{}{}{}",
+        PREFIX,
+        Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+        EXTENSION
+    );
+    let path = directory.join(name);
+
+    sqlx::query("VACUUM INTO $1")
+        .bind(path.to_string_lossy())
+        .execute(db)
+        .await?;
+
+    let size = fs::metadata(&path).await?.len();
+    let removed = rotate(directory, config.keep).await?;
+
+    tracing::info!(path = %path.display(), size, removed = removed.len(), "backed up database");
+
+    Ok(Backup {
+        path,
+        size,
+        removed,
+    })
+}
+
+/// Removes all but the newest `keep` backups in a directory.
+///
+/// Does nothing if `keep` is `0`.
+async fn rotate(directory: &Path, keep: usize) -> Result<Vec<PathBuf>, io::Error> {
+    if keep == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    let mut entries = fs::read_dir(directory).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+
+        if name.starts_with(PREFIX) && name.ends_with(EXTENSION) {
+            backups.push(entry.path());
+        }
+    }
+
+    backups.sort();
+
+    let excess = backups.len().saturating_sub(keep);
+    let removed = backups.drain(..excess).collect::<Vec<_>>();
+
+    for path in removed.iter() {
+        fs::remove_file(path).await?;
+    }
+
+    Ok(removed)
+}
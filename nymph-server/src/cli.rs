@@ -1,15 +1,21 @@
 //! Nymph server command-line interface.
 
 use std::path::PathBuf;
+use std::time::Instant;
 
 use chrono::Utc;
 use clap::{Parser, Subcommand};
 
+use tokio::fs;
+
 use anyhow::Error;
 
 use crate::{
     app::AppState,
     auth::api_key::{generate_key, hash_key},
+    backup,
+    import::ImportFormat,
+    migrate, routes, template,
 };
 
 /// The command line arguments.
@@ -28,6 +34,12 @@ pub struct Args {
 #[derive(Subcommand, Debug)]
 pub enum Command {
     CreateApiKey(CreateApiKey),
+    Backup(Backup),
+    CreateTemplate(CreateTemplate),
+    Migrate(Migrate),
+    MigrateContent(MigrateContent),
+    NormalizeNames(NormalizeNames),
+    BenchContent(BenchContent),
 }
 
 /// Creates an API key.
@@ -40,11 +52,226 @@ pub struct CreateApiKey {
     pub name: String,
 }
 
+/// Backs up the database.
+#[derive(clap::Args, Debug)]
+pub struct Backup {
+    /// The directory the backup is written to.
+    ///
+    /// By default, this is the `backup.directory` option.
+    #[arg(short, long)]
+    pub directory: Option<PathBuf>,
+}
+
+/// Creates or replaces a card template from a file.
+#[derive(clap::Args, Debug)]
+pub struct CreateTemplate {
+    /// The template's name.
+    #[arg(short, long)]
+    pub name: String,
+    /// What the template is about.
+    #[arg(short, long)]
+    pub description: Option<String>,
+    /// Replaces the cards of an existing template with the same name.
+    #[arg(short, long)]
+    pub replace: bool,
+    /// The file to read cards from.
+    ///
+    /// The format is picked from the extension, which is either `.csv` or
+    /// `.json`.
+    pub file: PathBuf,
+}
+
+/// Applies pending schema migrations.
+#[derive(clap::Args, Debug)]
+pub struct Migrate {
+    /// Only lists the pending migrations, without applying them.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Rewrites the content of every card under the current `storage` config.
+///
+/// Run after changing the backend, threshold or compression of the `storage`
+/// config to apply it to existing cards.
+#[derive(clap::Args, Debug)]
+pub struct MigrateContent {}
+
+/// Renames cards stored before names were normalized by Unicode's rules.
+///
+/// Run once after upgrading, so older cards can be found by name again.
+/// Cards whose new name is already taken are listed and left alone.
+#[derive(clap::Args, Debug)]
+pub struct NormalizeNames {}
+
+/// Measures how long reading moved card content takes.
+///
+/// Run it before and after changing `storage.compression` to see what
+/// compression costs reads.
+#[derive(clap::Args, Debug)]
+pub struct BenchContent {
+    /// How many times all moved content is read.
+    #[arg(short, long, default_value_t = 10)]
+    pub rounds: u32,
+}
+
 /// Runs a command.
 pub async fn run_command(command: &Command, state: &AppState) -> Result<(), Error> {
     match command {
         Command::CreateApiKey(command) => create_api_key(command, state).await,
+        Command::Backup(command) => backup(command, state).await,
+        Command::CreateTemplate(command) => create_template(command, state).await,
+        Command::Migrate(command) => migrate(command, state).await,
+        Command::MigrateContent(_) => migrate_content(state).await,
+        Command::NormalizeNames(_) => normalize_names(state).await,
+        Command::BenchContent(command) => bench_content(command, state).await,
+    }
+}
+
+async fn migrate(command: &Migrate, state: &AppState) -> Result<(), Error> {
+    let pending = migrate::pending(&state.db).await?;
+
+    for migration in pending.iter() {
+        println!("{} {}", migration.version, migration.description);
+    }
+
+    if command.dry_run {
+        println!("{} migration(s) pending", pending.len());
+        return Ok(());
+    }
+
+    let count = migrate::run(&state.db).await?;
+
+    println!("applied {} migration(s)", count);
+
+    Ok(())
+}
+
+async fn migrate_content(state: &AppState) -> Result<(), Error> {
+    let before = stored_size(state).await?;
+    let count = state.content.rewrite_all(&state.db).await?;
+    let after = stored_size(state).await?;
+
+    println!("rewrote {} cards", count);
+    println!("card_content: {} bytes -> {} bytes", before, after);
+
+    Ok(())
+}
+
+async fn normalize_names(state: &AppState) -> Result<(), Error> {
+    let normalized = routes::card::normalize_names(&state.db).await?;
+
+    for (id, name) in normalized.conflicts.iter() {
+        println!(
+            "card {} cannot be renamed to {:?}, which is taken",
+            id, name
+        );
     }
+
+    println!("renamed {} cards", normalized.renamed);
+
+    Ok(())
+}
+
+async fn bench_content(command: &BenchContent, state: &AppState) -> Result<(), Error> {
+    let ids = sqlx::query_as::<_, (i32,)>(
+        r#"
+        SELECT id
+        FROM card
+        WHERE content_location != 'inline'
+        "#,
+    )
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|(id,)| id)
+    .collect::<Vec<_>>();
+
+    if ids.is_empty() {
+        return Err(Error::msg("no cards have moved content"));
+    }
+
+    let mut bytes = 0;
+    let start = Instant::now();
+
+    for _ in 0..command.rounds {
+        for chunk in ids.chunks(100) {
+            let contents = state.content.read(&state.db, chunk).await?;
+            bytes += contents.values().map(String::len).sum::<usize>();
+        }
+    }
+
+    let elapsed = start.elapsed();
+    let reads = ids.len() as u32 * command.rounds.max(1);
+
+    println!(
+        "read {} cards {} times ({} bytes) in {:?}",
+        ids.len(),
+        command.rounds,
+        bytes,
+        elapsed
+    );
+    println!("{:?} per card", elapsed / reads);
+
+    Ok(())
+}
+
+/// How many bytes moved content takes up in the database.
+async fn stored_size(state: &AppState) -> Result<i64, Error> {
+    let (size,) = sqlx::query_as::<_, (i64,)>(
+        r#"
+        SELECT
+            COALESCE(SUM(LENGTH(CAST(content AS BLOB)) + COALESCE(LENGTH(compressed), 0)), 0)
+        FROM card_content
+        "#,
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok(size)
+}
+
+async fn create_template(command: &CreateTemplate, state: &AppState) -> Result<(), Error> {
+    let format = match command.file.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => ImportFormat::Csv,
+        Some("json") => ImportFormat::Json,
+        _ => return Err(Error::msg("template files must be `.csv` or `.json`")),
+    };
+
+    let body = fs::read_to_string(&command.file).await?;
+    let rows = format.parse(&body)?;
+
+    let template = template::save(
+        &state.db,
+        &command.name,
+        command.description.as_deref(),
+        rows,
+        command.replace,
+    )
+    .await?;
+
+    // export id
+    println!("{}", template.id);
+
+    Ok(())
+}
+
+async fn backup(command: &Backup, state: &AppState) -> Result<(), Error> {
+    let Some(directory) = command
+        .directory
+        .as_ref()
+        .or(state.backup.directory.as_ref())
+    else {
+        return Err(Error::msg(
+            "no backup directory; pass `--directory` or set `backup.directory`",
+        ));
+    };
+
+    let backup = backup::run(&state.db, directory, &state.backup).await?;
+
+    // export path
+    println!("{}", backup.path.display());
+
+    Ok(())
 }
 
 async fn create_api_key(command: &CreateApiKey, state: &AppState) -> Result<(), Error> {
@@ -1,9 +1,13 @@
 //! Server configuration options.
 
-use std::path::Path;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
 
 use anyhow::Error;
 
+use ipnet::IpNet;
+
 use figment::{
     Figment,
     providers::{Env, Format as _, Serialized, Toml},
@@ -11,13 +15,63 @@ use figment::{
 };
 use serde::{Deserialize, Serialize};
 
+/// The default address the server is hosted on, every interface.
+pub const DEFAULT_HOST: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+
 /// The default port the server is hosted on.
 pub const DEFAULT_PORT: u16 = 4000;
 
+/// How long in-flight requests are waited on at shutdown by default, in
+/// seconds.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 30;
+
 /// Server configuration.
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
 pub struct Config {
     pub server: ServerConfig,
+    /// Background job configuration.
+    #[serde(default)]
+    pub worker: WorkerConfig,
+    /// Database backup configuration.
+    #[serde(default)]
+    pub backup: BackupConfig,
+    /// Card content storage configuration.
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// HTTP caching configuration.
+    #[serde(default)]
+    pub cache: CacheConfig,
+    /// Fault injection configuration.
+    #[serde(default)]
+    pub faults: FaultConfig,
+    /// Request recording configuration.
+    #[serde(default)]
+    pub record: RecordConfig,
+    /// Tracing filter directives, like `info,sqlx=debug`.
+    ///
+    /// Overrides `RUST_LOG` when set. Re-read when the server receives
+    /// `SIGUSR1`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log: Option<String>,
+    /// How log lines are written.
+    ///
+    /// Unlike `log`, this is only read at startup.
+    #[serde(default)]
+    pub log_format: LogFormat,
+}
+
+/// How log lines are written.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Pretty,
+    /// A JSON object per line, for log aggregators.
+    ///
+    /// The fields of the spans an event happened in, like a request's method
+    /// and ID, are flattened into the object.
+    Json,
 }
 
 impl Config {
@@ -29,7 +83,7 @@ impl Config {
             .merge(Env::prefixed("NYMPH_"))
             .merge(
                 Env::raw()
-                    .only(&["DATABASE_URL", "PORT"])
+                    .only(&["DATABASE_URL", "HOST", "PORT"])
                     .map(|k| Uncased::from(format!("SERVER.{}", k))),
             )
             .extract()
@@ -40,22 +94,481 @@ impl Config {
 /// Server config.
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub struct ServerConfig {
+    /// The address the server is binded to, like `127.0.0.1` to only serve
+    /// the local machine.
+    pub host: IpAddr,
     /// The port the server is binded to.
     pub port: u16,
     /// The database url the server will connect to.
+    ///
+    /// Only SQLite is supported, so this must be a `sqlite:` url. The
+    /// database is created if it does not exist yet.
     #[serde(default)]
     pub database_url: Option<String>,
+    /// How connections to the database are pooled and set up.
+    #[serde(default)]
+    pub pool: PoolConfig,
     /// The signing key used to sign JWTs.
     #[serde(default)]
     pub signing_key: Option<String>,
+    /// The secret proxy assertions are signed with.
+    ///
+    /// Proxy assertions are refused if this is not set.
+    #[serde(default)]
+    pub proxy_secret: Option<String>,
+    /// How many requests each client may make.
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// How long a request may take before it is answered with an error, in
+    /// seconds.
+    ///
+    /// The request still finishes in the background. Requests are never timed
+    /// out if this is not set, and backups and imports never are.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// How long requests still in flight at shutdown are waited on, in
+    /// seconds, before their connections are closed anyways.
+    ///
+    /// New connections are refused as soon as shutdown starts.
+    pub shutdown_timeout: u64,
+    /// Starts the server in maintenance, refusing requests that could change
+    /// data until it is ended through the API.
+    #[serde(default)]
+    pub maintenance: bool,
+    /// Applies pending schema migrations on startup.
+    ///
+    /// Otherwise, the server refuses to start until they are applied with
+    /// `nymph-server migrate`.
+    #[serde(default)]
+    pub migrate: bool,
+    /// The PEM certificate chain HTTPS is served with.
+    ///
+    /// Requires `tls_key`. Plain HTTP is served if neither is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_cert: Option<PathBuf>,
+    /// The PEM private key of `tls_cert`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_key: Option<PathBuf>,
+    /// The PEM bundle of CAs client certificates are verified against.
+    ///
+    /// If set, managed clients may authenticate with a certificate instead of
+    /// an API key; the certificate's common name picks the user. Requires
+    /// `tls_cert`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_client_ca: Option<PathBuf>,
+    /// Routes that refuse requests without a verified client certificate,
+    /// like `/telemetry/guilds`.
+    ///
+    /// Requires a listener with a `tls_client_ca`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tls_client_routes: Vec<String>,
+    /// Reverse proxies whose `Forwarded` and `X-Forwarded-For` headers are
+    /// believed, like `10.0.0.0/8` or `127.0.0.1/32`.
+    ///
+    /// Requests from anywhere else are identified by the address they come
+    /// from.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_proxies: Vec<IpNet>,
+    /// Networks privileged requests may come from, like `10.0.0.0/8`.
+    ///
+    /// If set, requests with an API key and requests to routes only managed
+    /// users may call are refused from any other address. Clients behind a
+    /// reverse proxy also need it listed in `trusted_proxies`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub privileged_networks: Vec<IpNet>,
+    /// Every address the server listens on, for serving more than one at
+    /// once, like plain HTTP on loopback and HTTPS on a public port.
+    ///
+    /// If set, `host`, `port` and the `tls_*` options above, other than
+    /// `tls_client_routes`, are ignored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub listeners: Vec<ListenerConfig>,
+}
+
+impl ServerConfig {
+    /// The addresses the server listens on.
+    pub fn listeners(&self) -> Vec<ListenerConfig> {
+        if !self.listeners.is_empty() {
+            return self.listeners.clone();
+        }
+
+        vec![ListenerConfig {
+            host: self.host,
+            port: self.port,
+            tls_cert: self.tls_cert.clone(),
+            tls_key: self.tls_key.clone(),
+            tls_client_ca: self.tls_client_ca.clone(),
+        }]
+    }
 }
 
 impl Default for ServerConfig {
     fn default() -> Self {
         ServerConfig {
+            host: DEFAULT_HOST,
             port: DEFAULT_PORT,
             database_url: None,
+            pool: PoolConfig::default(),
             signing_key: None,
+            proxy_secret: None,
+            rate_limit: RateLimitConfig::default(),
+            timeout: None,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            maintenance: false,
+            migrate: false,
+            tls_cert: None,
+            tls_key: None,
+            tls_client_ca: None,
+            tls_client_routes: Vec::new(),
+            trusted_proxies: Vec::new(),
+            privileged_networks: Vec::new(),
+            listeners: Vec::new(),
+        }
+    }
+}
+
+/// Listener config.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct ListenerConfig {
+    /// The address the listener is binded to.
+    pub host: IpAddr,
+    /// The port the listener is binded to.
+    pub port: u16,
+    /// The PEM certificate chain HTTPS is served with.
+    ///
+    /// Requires `tls_key`. Plain HTTP is served if neither is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_cert: Option<PathBuf>,
+    /// The PEM private key of `tls_cert`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_key: Option<PathBuf>,
+    /// The PEM bundle of CAs client certificates are verified against.
+    ///
+    /// Requires `tls_cert`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_client_ca: Option<PathBuf>,
+}
+
+impl Default for ListenerConfig {
+    fn default() -> Self {
+        ListenerConfig {
+            host: DEFAULT_HOST,
+            port: DEFAULT_PORT,
+            tls_cert: None,
+            tls_key: None,
+            tls_client_ca: None,
+        }
+    }
+}
+
+/// Database pool config.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct PoolConfig {
+    /// How many connections may be open at once.
+    pub max_connections: u32,
+    /// How many connections are kept open, even when idle.
+    pub min_connections: u32,
+    /// How long a request waits for a free connection before failing, in
+    /// seconds.
+    pub acquire_timeout: u64,
+    /// The journal mode of the database.
+    ///
+    /// `wal` lets readers go on while a write is in progress.
+    pub journal_mode: JournalMode,
+    /// How long a connection waits on another connection's write lock before
+    /// failing, in milliseconds.
+    pub busy_timeout: u64,
+    /// How often SQLite syncs writes to disk.
+    ///
+    /// `normal` is safe with `wal`, and much faster than `full`.
+    pub synchronous: Synchronous,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        PoolConfig {
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout: 30,
+            journal_mode: JournalMode::default(),
+            busy_timeout: 5000,
+            synchronous: Synchronous::default(),
         }
     }
 }
+
+/// The SQLite `journal_mode` pragma.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalMode {
+    /// A rollback journal, deleted after each transaction.
+    Delete,
+    /// A rollback journal, truncated after each transaction.
+    Truncate,
+    /// A rollback journal, with its header zeroed after each transaction.
+    Persist,
+    /// A rollback journal kept in memory.
+    Memory,
+    /// A write-ahead log.
+    #[default]
+    Wal,
+    /// No journal; transactions cannot be rolled back safely.
+    Off,
+}
+
+/// The SQLite `synchronous` pragma.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Synchronous {
+    /// Never sync; a power loss may corrupt the database.
+    Off,
+    /// Sync at critical moments only.
+    Normal,
+    /// Sync after every transaction.
+    #[default]
+    Full,
+    /// Like `full`, and also sync the directory of a deleted journal.
+    Extra,
+}
+
+/// Rate limit config.
+///
+/// Each API key and token subject is limited separately.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// How many requests a client may make each minute, on average.
+    ///
+    /// If `0`, requests are not limited.
+    pub per_minute: u32,
+    /// How many requests a client may make at once after being idle.
+    pub burst: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            per_minute: 600,
+            burst: 60,
+        }
+    }
+}
+
+/// Background job config.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct WorkerConfig {
+    /// How often card rarity scores are recomputed, in seconds.
+    pub rarity_interval: u64,
+    /// How often ended events are checked for, in seconds.
+    pub event_interval: u64,
+    /// How often reward rules are checked for users that completed them, in
+    /// seconds.
+    pub rule_interval: u64,
+    /// How often pending webhook deliveries are sent, in seconds.
+    pub webhook_interval: u64,
+    /// How many times a webhook delivery is attempted before it is dropped.
+    pub webhook_max_attempts: u32,
+    /// How often old records are cleaned up, in seconds.
+    pub cleanup_interval: u64,
+    /// How many days finished webhook deliveries are kept for.
+    ///
+    /// A delivery is finished once it succeeds or runs out of attempts. If
+    /// `0`, deliveries are kept forever.
+    pub webhook_delivery_retention: u32,
+    /// How many days dispatched events are kept for replaying.
+    ///
+    /// If `0`, events are kept forever.
+    pub event_log_retention: u32,
+    /// How many days audit log entries, like grants and transfers, are kept
+    /// for.
+    ///
+    /// If `0`, entries are kept forever.
+    pub audit_log_retention: u32,
+    /// How often counted card views are written, in seconds.
+    pub view_interval: u64,
+    /// How many days of daily card view counts are kept.
+    ///
+    /// Total view counts are kept forever. If `0`, daily counts are too.
+    pub view_retention: u32,
+    /// How many days the data of guilds the bot departed is kept for.
+    ///
+    /// Once the grace period is over, every card, inventory and setting of
+    /// the guild is purged. A guild that is reported again before then keeps
+    /// its data. If `0`, data is kept forever.
+    pub departed_guild_retention: u32,
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        WorkerConfig {
+            rarity_interval: 60 * 60,
+            event_interval: 60,
+            rule_interval: 60,
+            webhook_interval: 10,
+            webhook_max_attempts: 8,
+            cleanup_interval: 60 * 60,
+            webhook_delivery_retention: 30,
+            event_log_retention: 7,
+            audit_log_retention: 0,
+            view_interval: 30,
+            view_retention: 90,
+            departed_guild_retention: 30,
+        }
+    }
+}
+
+/// Database backup config.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// The directory backups are written to.
+    ///
+    /// The backup endpoint is disabled if this is not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<PathBuf>,
+    /// How many backups are kept before the oldest are removed.
+    ///
+    /// If `0`, no backups are ever removed.
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        BackupConfig {
+            directory: None,
+            keep: 7,
+        }
+    }
+}
+
+/// HTTP caching config.
+///
+/// Each option is how long, in seconds, clients and shared caches may reuse
+/// the responses of a group of routes. Only responses that are the same for
+/// every caller are ever cached; everything else stays `no-store`. Routes are
+/// not cached if their option is not set.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Public cards read by non-managed callers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cards: Option<u32>,
+    /// A guild's season list.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seasons: Option<u32>,
+    /// A guild's trade-in rules.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trade_in: Option<u32>,
+}
+
+/// Fault injection config.
+///
+/// Injects faults into requests to exercise clients' retries, timeouts and
+/// fallbacks; see [`crate::fault`]. Never enable this in production.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct FaultConfig {
+    /// Whether faults are injected at all.
+    pub enabled: bool,
+    /// The faults of routes not listed in `routes`.
+    pub default: FaultRates,
+    /// The faults of single routes, by path without a version prefix, like
+    /// `/guilds/{guild_id}/cards/{id}`.
+    ///
+    /// These replace `default` entirely for the route.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub routes: HashMap<String, FaultRates>,
+}
+
+/// How often faults are injected into a route.
+///
+/// Rates are chances from `0` to `1`, checked in order: a request that is
+/// reset or fails is never delayed too.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct FaultRates {
+    /// The chance the connection is reset mid-response.
+    pub reset: f64,
+    /// The chance the request fails with a `500 Internal Server Error`.
+    pub error: f64,
+    /// The chance the request is delayed by `latency_ms`.
+    pub latency: f64,
+    /// How long delayed requests are delayed, in milliseconds.
+    pub latency_ms: u64,
+}
+
+/// Request recording config.
+///
+/// The latest requests are kept in memory with their responses, so operators
+/// can see what a client sent; see [`crate::request::record`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct RecordConfig {
+    /// How many requests are kept.
+    ///
+    /// If `0`, requests are not recorded.
+    pub capacity: usize,
+    /// How long a body may be to be recorded, in bytes.
+    pub max_body: usize,
+}
+
+impl Default for RecordConfig {
+    fn default() -> Self {
+        RecordConfig {
+            capacity: 0,
+            max_body: 16 * 1024,
+        }
+    }
+}
+
+/// Card content storage config.
+///
+/// See [`crate::storage`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Where content longer than `threshold` is kept.
+    pub backend: StorageBackend,
+    /// How long content may be before it is kept in the backend, in bytes.
+    pub threshold: usize,
+    /// The directory content is kept in by the `filesystem` backend.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directory: Option<PathBuf>,
+    /// The zstd level content kept by the `blob` backend is compressed with.
+    ///
+    /// Content is stored uncompressed if this is not set, and content kept
+    /// inline or by the `filesystem` backend is never compressed. Run the
+    /// `migrate-content` command after changing it to rewrite existing
+    /// content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compression: Option<i32>,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            backend: StorageBackend::Inline,
+            threshold: 4096,
+            directory: None,
+            compression: None,
+        }
+    }
+}
+
+/// Where long card content is kept.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// In the card table, like all other content.
+    #[default]
+    Inline,
+    /// In a table of its own.
+    Blob,
+    /// In a file per card, under [`StorageConfig::directory`].
+    ///
+    /// Database backups do not include these files.
+    Filesystem,
+}
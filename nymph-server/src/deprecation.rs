@@ -0,0 +1,135 @@
+//! Deprecation notices.
+//!
+//! Handlers and middleware call [`warn`] when a request uses a deprecated
+//! part of the API. [`notify`] then marks the response with a `Deprecation`
+//! header, and a `Sunset` header if a removal date is known, and lists the
+//! warnings in the `warnings` array of JSON object bodies, so clients learn
+//! about breaking changes before they land.
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    body::Body,
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse as _, Response},
+};
+
+use chrono::NaiveDate;
+
+use http::{HeaderName, HeaderValue, StatusCode, header};
+
+use nymph_model::response::{DEPRECATION_HEADER, SUNSET_HEADER, Warning};
+
+use crate::app::HTTP_DATE;
+
+pub const DEPRECATION: HeaderName = HeaderName::from_static(DEPRECATION_HEADER);
+pub const SUNSET: HeaderName = HeaderName::from_static(SUNSET_HEADER);
+
+tokio::task_local! {
+    static WARNINGS: Arc<Mutex<Vec<Warning>>>;
+}
+
+/// Warns the caller of the current request that it used a deprecated part of
+/// the API.
+///
+/// Does nothing outside of a request.
+pub fn warn(message: impl Into<String>) {
+    push(Warning {
+        message: message.into(),
+        sunset: None,
+    });
+}
+
+/// Like [`warn`], but the deprecated part of the API stops working on
+/// `sunset`.
+pub fn warn_until(message: impl Into<String>, sunset: NaiveDate) {
+    push(Warning {
+        message: message.into(),
+        sunset: Some(sunset),
+    });
+}
+
+fn push(warning: Warning) {
+    let _ = WARNINGS.try_with(|warnings| {
+        warnings
+            .lock()
+            .expect("warnings lock poisoned")
+            .push(warning)
+    });
+}
+
+/// Middleware that tells callers about the deprecated parts of the API their
+/// request used.
+pub async fn notify(request: Request, next: Next) -> Response {
+    let warnings = Arc::<Mutex<Vec<Warning>>>::default();
+
+    let res = WARNINGS.scope(warnings.clone(), next.run(request)).await;
+
+    let warnings = std::mem::take(&mut *warnings.lock().expect("warnings lock poisoned"));
+
+    if warnings.is_empty() {
+        return res;
+    }
+
+    let (mut parts, body) = res.into_parts();
+
+    parts
+        .headers
+        .insert(DEPRECATION, HeaderValue::from_static("true"));
+
+    // clients only need to know when the first thing breaks
+    let sunset = warnings
+        .iter()
+        .filter_map(|warning| warning.sunset)
+        .min()
+        .and_then(|sunset| {
+            HeaderValue::try_from(
+                sunset
+                    .and_time(Default::default())
+                    .format(HTTP_DATE)
+                    .to_string(),
+            )
+            .ok()
+        });
+
+    if let Some(sunset) = sunset {
+        parts.headers.insert(SUNSET, sunset);
+    }
+
+    let is_json = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|mime| mime.as_bytes().starts_with(b"application/json"));
+
+    if !is_json {
+        return Response::from_parts(parts, body);
+    }
+
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(err) => {
+            tracing::error!(?err, "failed to read response body");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    // only objects have room for warnings; everything else keeps the headers
+    let value = match serde_json::from_slice::<serde_json::Value>(&body) {
+        Ok(serde_json::Value::Object(mut object)) => {
+            object.insert(
+                "warnings".into(),
+                serde_json::to_value(&warnings).expect("valid json"),
+            );
+            serde_json::Value::Object(object)
+        }
+        _ => return Response::from_parts(parts, Body::from(body)),
+    };
+
+    parts.headers.remove(header::CONTENT_LENGTH);
+
+    Response::from_parts(
+        parts,
+        Body::from(serde_json::to_vec(&value).expect("valid json")),
+    )
+}
@@ -0,0 +1,94 @@
+//! Event dispatch.
+//!
+//! Every dispatched event is written to the event log, so webhook consumers
+//! that were down can replay what they missed, and to the audit log, which is
+//! kept for longer. Webhook deliveries are queued along with them, so an event
+//! is either logged and delivered, or neither.
+
+use chrono::Utc;
+
+use nymph_model::dispatch::{Envelope, Event, VERSION};
+
+use sqlx::{SqlitePool, types::Json};
+
+use crate::{app::AppState, routes::webhook};
+
+/// Dispatches an event to the guild's webhooks and to gateway clients.
+///
+/// `actor_id` is the user that caused the event, which is kept in the audit
+/// log.
+pub async fn emit(state: &AppState, actor_id: i32, event: Event) -> Result<(), sqlx::Error> {
+    dispatch(state, Some(actor_id), event).await
+}
+
+/// Dispatches an event nobody caused, like an event ending on schedule.
+pub async fn emit_unattributed(state: &AppState, event: Event) -> Result<(), sqlx::Error> {
+    dispatch(state, None, event).await
+}
+
+async fn dispatch(
+    state: &AppState,
+    actor_id: Option<i32>,
+    event: Event,
+) -> Result<(), sqlx::Error> {
+    let mut envelope = Envelope {
+        version: VERSION,
+        guild_id: event.guild_id(),
+        timestamp: Utc::now().naive_utc(),
+        seq: None,
+        event,
+    };
+
+    record(&state.db, actor_id, &mut envelope).await?;
+    state.gateway.publish(envelope);
+
+    Ok(())
+}
+
+/// Writes an event to the event log and the audit log, and queues it for
+/// delivery to the guild's webhooks, all at once.
+///
+/// Fills in the event's sequence number.
+async fn record(
+    db: &SqlitePool,
+    actor_id: Option<i32>,
+    envelope: &mut Envelope,
+) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+    let mut tx = db.begin().await?;
+
+    let (seq,) = sqlx::query_as::<_, (i64,)>(
+        r#"
+        INSERT INTO event_log (guild_id, event, payload, actor_id, card_id, inserted_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING seq
+        "#,
+    )
+    .bind(envelope.guild_id.get() as i64)
+    .bind(envelope.event.kind().to_str())
+    .bind(Json(&*envelope))
+    .bind(actor_id)
+    .bind(envelope.event.card().map(|card| card.id))
+    .bind(now)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO audit_log (seq, guild_id, event, payload, actor_id, card_id, inserted_at)
+        SELECT seq, guild_id, event, payload, actor_id, card_id, inserted_at
+        FROM event_log
+        WHERE seq = $1
+        "#,
+    )
+    .bind(seq)
+    .execute(&mut *tx)
+    .await?;
+
+    envelope.seq = Some(seq as u64);
+    webhook::enqueue(&mut *tx, envelope).await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
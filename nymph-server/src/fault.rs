@@ -0,0 +1,147 @@
+//! Fault injection.
+//!
+//! When enabled, [`inject`] makes requests fail the ways a flaky network or
+//! an overloaded server would: by resetting the connection, failing with a
+//! `500 Internal Server Error`, or answering late. This lets the bot's
+//! retries, timeouts and fallbacks be exercised in staging. See
+//! [`FaultConfig`] for how often each fault happens.
+
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Error;
+
+use axum::{
+    body::Body,
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::{IntoResponse as _, Response},
+};
+
+use futures_util::stream;
+
+use http::StatusCode;
+
+use crate::{
+    app::{ApiVersion, AppError, AppErrorKind, AppState},
+    config::{FaultConfig, FaultRates},
+};
+
+/// Decides which requests faults are injected into.
+///
+/// Cheaply cloneable.
+#[derive(Clone, Debug, Default)]
+pub struct FaultInjector {
+    config: Arc<FaultConfig>,
+}
+
+/// A fault injected into a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// The connection is reset mid-response.
+    Reset,
+    /// The request fails with a `500 Internal Server Error`.
+    Error,
+    /// The request is answered late.
+    Latency(Duration),
+}
+
+impl FaultInjector {
+    /// Creates a new `FaultInjector`.
+    ///
+    /// Fails if a rate is not a chance from `0` to `1`.
+    pub fn new(config: FaultConfig) -> Result<FaultInjector, Error> {
+        let rates = std::iter::once(("default", &config.default)).chain(
+            config
+                .routes
+                .iter()
+                .map(|(path, rates)| (path.as_str(), rates)),
+        );
+
+        for (route, rates) in rates {
+            for rate in [rates.reset, rates.error, rates.latency] {
+                if !(0.0..=1.0).contains(&rate) {
+                    return Err(Error::msg(format!(
+                        "fault rate {} of `{}` is not between 0 and 1",
+                        rate, route
+                    )));
+                }
+            }
+        }
+
+        Ok(FaultInjector {
+            config: Arc::new(config),
+        })
+    }
+
+    /// `true` if faults are injected at all.
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Rolls the fault injected into a request to a route, if any.
+    ///
+    /// `path` may have a version prefix.
+    pub fn roll(&self, path: &str) -> Option<Fault> {
+        let rates = self.rates(path);
+
+        if rates.reset > 0. && rand::random_bool(rates.reset) {
+            Some(Fault::Reset)
+        } else if rates.error > 0. && rand::random_bool(rates.error) {
+            Some(Fault::Error)
+        } else if rates.latency > 0. && rand::random_bool(rates.latency) {
+            Some(Fault::Latency(Duration::from_millis(rates.latency_ms)))
+        } else {
+            None
+        }
+    }
+
+    fn rates(&self, path: &str) -> &FaultRates {
+        let (_, path) = ApiVersion::split(path);
+
+        // nested index routes are matched with a trailing slash
+        let path = match path.strip_suffix('/') {
+            Some(path) if !path.is_empty() => path,
+            _ => path,
+        };
+
+        self.config.routes.get(path).unwrap_or(&self.config.default)
+    }
+}
+
+/// Middleware that injects faults into requests.
+pub async fn inject(
+    State(state): State<AppState>,
+    path: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Response {
+    // requests that match no route are left alone
+    let Some(path) = path.filter(|_| state.faults.enabled()) else {
+        return next.run(request).await;
+    };
+
+    let fault = state.faults.roll(path.as_str());
+
+    if let Some(fault) = fault {
+        tracing::info!(?fault, path = path.as_str(), "injecting fault");
+    }
+
+    match fault {
+        Some(Fault::Reset) => {
+            // hyper drops the connection when a body fails to stream
+            let body = stream::once(async {
+                Err::<&'static [u8], _>(io::Error::from(io::ErrorKind::ConnectionReset))
+            });
+
+            (StatusCode::OK, Body::from_stream(body)).into_response()
+        }
+        Some(Fault::Error) => AppError::from(AppErrorKind::InjectedFault).into_response(),
+        Some(Fault::Latency(latency)) => {
+            tokio::time::sleep(latency).await;
+            next.run(request).await
+        }
+        None => next.run(request).await,
+    }
+}
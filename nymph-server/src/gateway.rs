@@ -0,0 +1,145 @@
+//! Gateway event bus.
+//!
+//! Events are published to every connected gateway client, and the most
+//! recent are kept in a backlog so a client that lost its connection can
+//! resume without missing any.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use nymph_model::{card::Visibility, dispatch::Envelope};
+
+use tokio::sync::broadcast;
+
+use crate::{auth::Authentication, routes::card::strip_privileged};
+
+/// How many dispatches are kept for resuming.
+pub const BACKLOG: usize = 1024;
+
+/// A published event.
+#[derive(Debug)]
+pub struct Dispatch {
+    /// The sequence number of the dispatch.
+    ///
+    /// These start at `1` and increase by one with each dispatch.
+    pub seq: u64,
+    pub envelope: Envelope,
+}
+
+impl Dispatch {
+    /// Gets the event as a user may see it.
+    ///
+    /// Users that are not managed only see events about public cards, without
+    /// the fields only privileged callers may see, and never see reports.
+    pub fn visible_to(&self, auth: &Authentication) -> Option<Envelope> {
+        if auth.managed {
+            return Some(self.envelope.clone());
+        }
+
+        if self.envelope.event.privileged()
+            || self
+                .envelope
+                .event
+                .card()
+                .is_some_and(|card| card.visibility != Visibility::Public)
+        {
+            return None;
+        }
+
+        let mut envelope = self.envelope.clone();
+        if let Some(card) = envelope.event.card_mut() {
+            strip_privileged(card);
+        }
+
+        Some(envelope)
+    }
+}
+
+/// The gateway event bus.
+///
+/// Cheaply cloneable.
+#[derive(Clone, Debug)]
+pub struct Gateway(Arc<Inner>);
+
+#[derive(Debug)]
+struct Inner {
+    sender: broadcast::Sender<Arc<Dispatch>>,
+    backlog: Mutex<Backlog>,
+}
+
+#[derive(Debug, Default)]
+struct Backlog {
+    seq: u64,
+    dispatches: VecDeque<Arc<Dispatch>>,
+}
+
+impl Gateway {
+    /// Creates a new `Gateway`.
+    pub fn new() -> Gateway {
+        let (sender, _) = broadcast::channel(BACKLOG);
+
+        Gateway(Arc::new(Inner {
+            sender,
+            backlog: Mutex::default(),
+        }))
+    }
+
+    /// Publishes an event to every connected client.
+    pub fn publish(&self, envelope: Envelope) {
+        let mut backlog = self.0.backlog.lock().expect("backlog poisoned");
+
+        backlog.seq += 1;
+
+        let dispatch = Arc::new(Dispatch {
+            seq: backlog.seq,
+            envelope,
+        });
+
+        if backlog.dispatches.len() >= BACKLOG {
+            backlog.dispatches.pop_front();
+        }
+        backlog.dispatches.push_back(dispatch.clone());
+
+        // sending under the lock keeps dispatches in order; it is fine for
+        // nobody to be listening
+        let _ = self.0.sender.send(dispatch);
+    }
+
+    /// Subscribes to every dispatch published from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<Dispatch>> {
+        self.0.sender.subscribe()
+    }
+
+    /// The sequence number of the latest dispatch.
+    pub fn seq(&self) -> u64 {
+        self.0.backlog.lock().expect("backlog poisoned").seq
+    }
+
+    /// Gets every dispatch after `seq`.
+    ///
+    /// Returns `None` if some of them have already left the backlog.
+    pub fn replay(&self, seq: u64) -> Option<Vec<Arc<Dispatch>>> {
+        let backlog = self.0.backlog.lock().expect("backlog poisoned");
+
+        let oldest = backlog.seq - backlog.dispatches.len() as u64;
+
+        if seq < oldest || seq > backlog.seq {
+            return None;
+        }
+
+        Some(
+            backlog
+                .dispatches
+                .iter()
+                .filter(|dispatch| dispatch.seq > seq)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+impl Default for Gateway {
+    fn default() -> Self {
+        Gateway::new()
+    }
+}
@@ -0,0 +1,68 @@
+//! Generic CSV importer.
+//!
+//! The first record must be a header. Columns are matched by name, ignoring
+//! case: `name` (or `title`) is required, while `description` (or `content`),
+//! `image` (or `image_url`), `category` and `previous` (the name of the card
+//! this card upgrades) are optional. Any other columns are ignored.
+
+use ::csv::{ReaderBuilder, StringRecord, Trim};
+
+use crate::app::{AppError, AppErrorKind};
+
+use super::{ImportRow, ImportedCard, RowError};
+
+/// Parses a CSV file into rows.
+pub fn parse(body: &str) -> Result<Vec<ImportRow>, AppError> {
+    let mut reader = ReaderBuilder::new()
+        .flexible(true)
+        .trim(Trim::All)
+        .from_reader(body.as_bytes());
+
+    let headers = reader.headers().map_err(invalid_file)?.clone();
+
+    let column = |names: &[&str]| {
+        headers
+            .iter()
+            .position(|header| names.iter().any(|name| header.eq_ignore_ascii_case(name)))
+    };
+
+    let Some(name) = column(&["name", "title"]) else {
+        return Err(AppError::from(AppErrorKind::MissingField("name".into()))
+            .with_message("CSV header is missing a `name` column."));
+    };
+    let description = column(&["description", "content"]);
+    let image = column(&["image", "image_url"]);
+    let category = column(&["category"]);
+    let previous = column(&["previous", "upgrade_of"]);
+
+    Ok(reader
+        .records()
+        .enumerate()
+        .map(|(i, record)| {
+            let card = match record {
+                Ok(record) => ImportedCard::new(
+                    record.get(name),
+                    get(&record, description),
+                    get(&record, image),
+                    get(&record, category),
+                )
+                .map(|card| card.upgrade_of(get(&record, previous))),
+                Err(err) => Err(RowError::new(None, format!("Malformed row: {}", err))),
+            };
+
+            ImportRow {
+                row: i as u32 + 1,
+                card,
+            }
+        })
+        .collect())
+}
+
+fn get(record: &StringRecord, index: Option<usize>) -> Option<&str> {
+    index.and_then(|i| record.get(i))
+}
+
+fn invalid_file(err: ::csv::Error) -> AppError {
+    AppError::from(AppErrorKind::InvalidFile)
+        .with_message(format!("Failed to read CSV file: {}", err))
+}
@@ -0,0 +1,69 @@
+//! JSON card bot export importer.
+//!
+//! Accepts either a top-level array of cards, or an object with a `cards`
+//! array, which is what most card bots export. Each card is an object with a
+//! `name`, and optionally a `description`, `image`, `series` and `previous`;
+//! common aliases of these fields are also accepted.
+
+use serde::Deserialize;
+
+use serde_json::Value;
+
+use crate::app::{AppError, AppErrorKind};
+
+use super::{ImportRow, ImportedCard, RowError};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Export {
+    Cards(Vec<Value>),
+    Wrapped { cards: Vec<Value> },
+}
+
+#[derive(Deserialize)]
+struct ExportedCard {
+    #[serde(alias = "title")]
+    name: Option<String>,
+    #[serde(alias = "content", alias = "text")]
+    description: Option<String>,
+    #[serde(alias = "image_url", alias = "imageUrl")]
+    image: Option<String>,
+    #[serde(alias = "category", alias = "set")]
+    series: Option<String>,
+    #[serde(alias = "upgrade_of", alias = "evolves_from")]
+    previous: Option<String>,
+}
+
+/// Parses a JSON export into rows.
+pub fn parse(body: &str) -> Result<Vec<ImportRow>, AppError> {
+    let export = serde_json::from_str::<Export>(body).map_err(|err| {
+        AppError::from(AppErrorKind::InvalidFile).with_message(format!(
+            "Expected an array of cards or an object with a `cards` array: {}",
+            err
+        ))
+    })?;
+
+    let (Export::Cards(cards) | Export::Wrapped { cards }) = export;
+
+    Ok(cards
+        .into_iter()
+        .enumerate()
+        .map(|(i, card)| {
+            let card = match serde_json::from_value::<ExportedCard>(card) {
+                Ok(card) => ImportedCard::new(
+                    card.name.as_deref(),
+                    card.description.as_deref(),
+                    card.image.as_deref(),
+                    card.series.as_deref(),
+                )
+                .map(|imported| imported.upgrade_of(card.previous.as_deref())),
+                Err(err) => Err(RowError::new(None, format!("Malformed card: {}", err))),
+            };
+
+            ImportRow {
+                row: i as u32 + 1,
+                card,
+            }
+        })
+        .collect())
+}
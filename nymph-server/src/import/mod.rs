@@ -0,0 +1,154 @@
+//! Card importers.
+//!
+//! Each adapter maps a community file format into [`ImportRow`]s, which the
+//! import endpoint then validates and inserts.
+
+pub mod csv;
+pub mod json;
+
+use nymph_model::card::normalize_name;
+
+use crate::app::{AppError, AppErrorKind};
+
+/// The maximum length of a card name.
+pub const MAX_NAME_LEN: usize = 255;
+
+/// A supported import format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// Generic CSV with `name`, `description` and `image` columns.
+    ///
+    /// See [`csv`].
+    Csv,
+    /// JSON exports of similar card bots.
+    ///
+    /// See [`json`].
+    Json,
+}
+
+impl ImportFormat {
+    /// Picks an import format from a MIME type.
+    pub fn from_mime(mime: &str) -> Result<ImportFormat, AppError> {
+        // ignore any parameters, like charset
+        let essence = mime.split(';').next().unwrap_or_default().trim();
+
+        match essence {
+            "text/csv" => Ok(ImportFormat::Csv),
+            "application/json" => Ok(ImportFormat::Json),
+            mime => Err(AppErrorKind::UnsupportedContentType(mime.to_owned()).into()),
+        }
+    }
+
+    /// Parses a file into rows.
+    pub fn parse(self, body: &str) -> Result<Vec<ImportRow>, AppError> {
+        match self {
+            ImportFormat::Csv => csv::parse(body),
+            ImportFormat::Json => json::parse(body),
+        }
+    }
+}
+
+/// A single row of an import file.
+#[derive(Clone, Debug)]
+pub struct ImportRow {
+    /// The row of the file, starting at `1` for the first card.
+    pub row: u32,
+    /// The card of the row, or why it could not be read.
+    pub card: Result<ImportedCard, RowError>,
+}
+
+/// A card read from an import file.
+#[derive(Clone, Debug)]
+pub struct ImportedCard {
+    /// The card's name.
+    pub name: String,
+    /// The card's category.
+    pub category_name: Option<String>,
+    /// The card's content in Markdown.
+    pub content: String,
+    /// The name of the card this card is an upgrade of.
+    pub previous: Option<String>,
+}
+
+impl ImportedCard {
+    /// Maps the common fields of community formats into a card.
+    ///
+    /// Names are normalized to match how the bot searches for cards, and
+    /// images are appended to the end of the content.
+    pub fn new(
+        name: Option<&str>,
+        description: Option<&str>,
+        image: Option<&str>,
+        category_name: Option<&str>,
+    ) -> Result<ImportedCard, RowError> {
+        let name = normalize_name(name.unwrap_or_default());
+
+        if name.is_empty() {
+            return Err(RowError::new(None, "Missing card name."));
+        }
+
+        if name.chars().any(char::is_control) {
+            return Err(RowError::new(
+                Some(name),
+                "Card names cannot contain line breaks or control characters.",
+            ));
+        }
+
+        if name.len() > MAX_NAME_LEN {
+            return Err(RowError::new(
+                Some(name),
+                format!("Card names cannot be longer than {} bytes.", MAX_NAME_LEN),
+            ));
+        }
+
+        let mut content = description.map(str::trim).unwrap_or_default().to_owned();
+
+        if let Some(image) = image.map(str::trim).filter(|image| !image.is_empty()) {
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            content.push_str(image);
+        }
+
+        let category_name = category_name
+            .map(str::trim)
+            .filter(|category| !category.is_empty())
+            .map(str::to_owned);
+
+        Ok(ImportedCard {
+            name,
+            category_name,
+            content,
+            previous: None,
+        })
+    }
+
+    /// Marks the card as an upgrade of another card, by name.
+    pub fn upgrade_of(self, previous: Option<&str>) -> ImportedCard {
+        let previous = previous
+            .map(str::trim)
+            .filter(|previous| !previous.is_empty())
+            .map(normalize_name);
+
+        ImportedCard { previous, ..self }
+    }
+}
+
+/// A row that could not be mapped into a card.
+#[derive(Clone, Debug)]
+pub struct RowError {
+    /// The name of the card, if one could be read.
+    pub name: Option<String>,
+    /// A user-friendly message of the error.
+    pub message: String,
+}
+
+impl RowError {
+    /// Creates a new `RowError`.
+    pub fn new(name: Option<String>, message: impl Into<String>) -> RowError {
+        RowError {
+            name,
+            message: message.into(),
+        }
+    }
+}
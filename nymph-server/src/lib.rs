@@ -2,7 +2,28 @@
 
 pub mod app;
 pub mod auth;
+pub mod backup;
 pub mod cli;
 pub mod config;
+pub mod deprecation;
+pub mod dispatch;
+pub mod fault;
+pub mod gateway;
+pub mod import;
+pub mod lint;
+pub mod log;
+pub mod maintenance;
+pub mod migrate;
+pub mod ratelimit;
 pub mod request;
+pub mod roll;
+pub mod router;
 pub mod routes;
+pub mod selftest;
+pub mod storage;
+pub mod store;
+pub mod template;
+pub mod test;
+pub mod tls;
+pub mod views;
+pub mod worker;
@@ -0,0 +1,89 @@
+//! Card content linting.
+
+use nymph_model::lint::{LintRules, LintViolation};
+
+use sqlx::{Executor, FromRow, Sqlite, types::Json};
+
+/// Fetches the lint rules of a guild.
+///
+/// Guilds without any rules get the default, empty set of rules.
+pub async fn get_rules<'c, E>(db: E, guild_id: i64) -> Result<LintRules, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    #[derive(FromRow)]
+    struct RulesResult {
+        max_length: Option<i64>,
+        banned_words: Json<Vec<String>>,
+        required_sections: Json<Vec<String>>,
+    }
+
+    let rules = sqlx::query_as::<_, RulesResult>(
+        r#"
+        SELECT max_length, banned_words, required_sections
+        FROM guild_lint_rules
+        WHERE guild_id = $1
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(rules
+        .map(|rules| LintRules {
+            max_length: rules.max_length.map(|max_length| max_length as u32),
+            banned_words: rules.banned_words.0,
+            required_sections: rules.required_sections.0,
+        })
+        .unwrap_or_default())
+}
+
+/// Checks a card's content against a set of lint rules.
+///
+/// Returns every rule the content broke.
+pub fn check(rules: &LintRules, content: &str) -> Vec<LintViolation> {
+    let mut violations = Vec::new();
+
+    if let Some(max_length) = rules.max_length {
+        let length = content.chars().count() as u32;
+
+        if length > max_length {
+            violations.push(LintViolation::MaxLength { max_length, length });
+        }
+    }
+
+    let words = normalize(content);
+
+    for word in rules.banned_words.iter() {
+        let banned = normalize(word);
+
+        if !banned.is_empty() && words.windows(banned.len()).any(|window| window == banned) {
+            violations.push(LintViolation::BannedWord { word: word.clone() });
+        }
+    }
+
+    let headings = content
+        .lines()
+        .filter_map(|line| line.trim_start().strip_prefix('#'))
+        .map(|heading| heading.trim_start_matches('#').trim().to_lowercase())
+        .collect::<Vec<_>>();
+
+    for section in rules.required_sections.iter() {
+        if !headings.contains(&section.trim().to_lowercase()) {
+            violations.push(LintViolation::RequiredSection {
+                section: section.clone(),
+            });
+        }
+    }
+
+    violations
+}
+
+/// Splits text into lowercase words, so phrases can be matched on word
+/// boundaries.
+fn normalize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
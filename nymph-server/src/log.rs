@@ -0,0 +1,181 @@
+//! Runtime-adjustable logging.
+//!
+//! The server's tracing filter can be swapped out while it is running, so
+//! noisy targets like `sqlx=debug` can be turned on to diagnose an issue
+//! without a restart, which would invalidate every token signed with a
+//! development key.
+
+use std::fmt::{self as std_fmt, Debug};
+use std::io;
+
+use chrono::Utc;
+
+use serde_json::{Map, Value};
+
+use tracing::{
+    Event, Subscriber,
+    field::{Field, Visit},
+};
+
+use tracing_subscriber::{
+    EnvFilter, Registry,
+    fmt::{
+        self, FmtContext, FormattedFields,
+        format::{FormatEvent, FormatFields, JsonFields, Writer},
+    },
+    layer::SubscriberExt as _,
+    registry::LookupSpan,
+    reload,
+    util::SubscriberInitExt as _,
+};
+
+use crate::config::LogFormat;
+
+/// The filter used when `RUST_LOG` is not set.
+pub const DEFAULT_FILTER: &str = "info";
+
+/// A handle to the server's tracing filter.
+///
+/// Cheaply cloneable. A default `LogFilter` is detached from any subscriber,
+/// and cannot be changed.
+#[derive(Clone, Debug, Default)]
+pub struct LogFilter {
+    handle: Option<reload::Handle<EnvFilter, Registry>>,
+}
+
+impl LogFilter {
+    /// Installs the global tracing subscriber, returning a handle to its
+    /// filter.
+    ///
+    /// The initial filter is read from `RUST_LOG`, falling back to
+    /// [`DEFAULT_FILTER`].
+    pub fn init(format: LogFormat) -> LogFilter {
+        let filter =
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+        let (filter, handle) = reload::Layer::new(filter);
+
+        let (pretty, json) = match format {
+            LogFormat::Pretty => (Some(fmt::layer().with_writer(io::stderr)), None),
+            LogFormat::Json => (
+                None,
+                Some(
+                    fmt::layer()
+                        .fmt_fields(JsonFields::new())
+                        .event_format(FlatJson)
+                        .with_writer(io::stderr),
+                ),
+            ),
+        };
+
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(pretty)
+            .with(json)
+            .init();
+
+        LogFilter {
+            handle: Some(handle),
+        }
+    }
+
+    /// The directives of the current filter.
+    pub fn current(&self) -> Option<String> {
+        self.handle
+            .as_ref()
+            .and_then(|handle| handle.with_current(|filter| filter.to_string()).ok())
+    }
+
+    /// Replaces the current filter.
+    pub fn reload(&self, filter: EnvFilter) -> Result<(), reload::Error> {
+        let Some(handle) = self.handle.as_ref() else {
+            return Ok(());
+        };
+
+        let directives = filter.to_string();
+        handle.reload(filter)?;
+
+        tracing::info!(filter = directives, "reloaded log filter");
+
+        Ok(())
+    }
+}
+
+/// Formats events as a JSON object per line.
+///
+/// Unlike [`fmt::format::Json`], the fields of every span an event happened
+/// in are flattened into the object alongside the event's own, so fields like
+/// a request's `request_id` can be queried directly. Inner spans win over
+/// outer ones, and the event wins over all of them.
+struct FlatJson;
+
+impl<S, N> FormatEvent<S, N> for FlatJson
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> std_fmt::Result {
+        let metadata = event.metadata();
+
+        let mut object = Map::new();
+        object.insert("timestamp".into(), Utc::now().to_rfc3339().into());
+        object.insert("level".into(), metadata.level().as_str().into());
+        object.insert("target".into(), metadata.target().into());
+
+        if let Some(scope) = ctx.event_scope() {
+            let mut spans = Vec::new();
+
+            for span in scope.from_root() {
+                spans.push(Value::from(span.name()));
+
+                // span fields are already formatted as a JSON object
+                let extensions = span.extensions();
+                if let Some(fields) = extensions.get::<FormattedFields<N>>()
+                    && let Ok(Value::Object(fields)) = serde_json::from_str(fields)
+                {
+                    object.extend(fields);
+                }
+            }
+
+            object.insert("spans".into(), spans.into());
+        }
+
+        event.record(&mut JsonVisitor(&mut object));
+
+        writeln!(writer, "{}", Value::Object(object))
+    }
+}
+
+/// Records fields into a JSON object.
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .insert(field.name().into(), format!("{:?}", value).into());
+    }
+}
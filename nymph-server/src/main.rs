@@ -1,43 +1,44 @@
-use std::{io, net::SocketAddr, path::PathBuf, sync::Arc};
+use std::{collections::HashSet, net::SocketAddr, path::PathBuf, time::Duration};
 
 use anyhow::Error;
 
-use axum::{
-    Router,
-    extract::{MatchedPath, Request},
-    middleware::{Next, from_fn},
-    response::Response,
-    routing::{delete, get, post},
-};
+use axum::Router;
 
-use axum_server::Handle;
+use axum_server::{Handle, tls_rustls::RustlsConfig};
 use clap::Parser as _;
 
+use futures_util::future::try_join_all;
+
 use nymph_server::{
-    app::{AppError, AppState, random_signing_key},
+    app::{AppState, random_signing_key},
     cli::{Args, run_command},
-    config::Config,
-    routes,
+    config::{Config, ServerConfig},
+    log::{DEFAULT_FILTER, LogFilter},
+    migrate, router, selftest,
+    tls::{self, ClientCertAcceptor},
+    worker,
 };
 
 use tokio::{main, select, signal};
 
-use tower_http::{compression::CompressionLayer, trace::TraceLayer};
+use tracing_subscriber::EnvFilter;
 
 #[main]
 async fn main() -> Result<(), Error> {
     sqlx::any::install_default_drivers();
     dotenv::dotenv().ok();
 
-    tracing_subscriber::fmt::fmt()
-        .with_writer(io::stderr)
-        .init();
-
     let args = Args::parse();
 
     // load config
     let config_path = args.config.unwrap_or_else(|| PathBuf::from("./nymph.toml"));
-    let mut config = Config::load(config_path)?;
+    let mut config = Config::load(&config_path)?;
+
+    let log_filter = LogFilter::init(config.log_format);
+
+    if let Some(directives) = config.log.as_ref() {
+        log_filter.reload(EnvFilter::try_new(directives)?)?;
+    }
 
     // check for development defaults
     if config.server.signing_key.is_none() {
@@ -50,7 +51,19 @@ async fn main() -> Result<(), Error> {
         config.server.signing_key = Some(signing_key);
     }
 
-    let state = AppState::new(config.server).await?;
+    // load certificates before anything else is set up
+    let listeners = load_listeners(&config.server)?;
+    let shutdown_timeout = Duration::from_secs(config.server.shutdown_timeout);
+    let run_migrations = config.server.migrate;
+
+    let state = AppState::new(config.server)
+        .await?
+        .with_log_filter(log_filter.clone())
+        .with_backup(config.backup)
+        .with_storage(config.storage)?
+        .with_cache(config.cache)
+        .with_recorder(config.record)
+        .with_faults(config.faults)?;
     let db = state.db.clone();
 
     // Execute command if it exists
@@ -58,66 +71,57 @@ async fn main() -> Result<(), Error> {
         return run_command(&command, &state).await;
     }
 
-    let addr: SocketAddr = ([0, 0, 0, 0], state.port).into();
-
-    // Build router
-    let router = Router::<AppState>::new()
-        .nest(
-            "/guilds/{guild_id}/cards",
-            Router::<AppState>::new()
-                .route("/", get(routes::card::list))
-                .route("/{id}", get(routes::card::show)),
-        )
-        .nest(
-            "/users",
-            Router::<AppState>::new()
-                .route("/discord", post(routes::user::discord))
-                .nest(
-                    "/{user_id}",
-                    Router::<AppState>::new()
-                        .route("/cards", get(routes::card::inventory::list))
-                        .route("/cards", post(routes::card::inventory::grant))
-                        .route("/cards/{card_id}", delete(routes::card::inventory::revoke)),
-                ),
-        )
-        .layer(from_fn(nymph_server::app::app_rest_headers))
-        .layer(
-            TraceLayer::new_for_http()
-                .make_span_with(|req: &Request| {
-                    let method = req.method();
-                    let uri = req.uri();
-
-                    // axum automatically adds this extension.
-                    let matched_path = req
-                        .extensions()
-                        .get::<MatchedPath>()
-                        .map(|matched_path| matched_path.as_str());
-
-                    tracing::debug_span!("request", %method, %uri, matched_path)
-                })
-                // By default `TraceLayer` will log 5xx responses but we're doing our specific
-                // logging of errors so disable that
-                .on_failure(()),
-        )
-        .layer(from_fn(log_app_errors))
-        .layer(CompressionLayer::new())
-        .with_state(state);
+    if run_migrations {
+        let count = migrate::run(&db).await?;
+
+        if count > 0 {
+            tracing::info!(count, "applied pending migrations");
+        }
+    }
+
+    // Refuse to serve if anything requests depend on is broken
+    if let Err(report) = selftest::run(&state).await {
+        tracing::error!("{}", report);
+        return Err(report.into());
+    }
+
+    if state.maintenance.enabled() {
+        tracing::warn!("Starting in maintenance; requests that change data are refused");
+    }
+
+    if state.faults.enabled() {
+        tracing::warn!("Injecting faults into requests; never do this in production!");
+    }
+
+    // Start background jobs
+    worker::spawn(state.clone(), config.worker);
+
+    #[cfg(unix)]
+    tokio::spawn(reload_log_filter_signal(config_path, log_filter));
+
+    let views = state.views.clone();
+    let router = router::build(state);
 
     // Setup cancellation task for server
     let handle = Handle::new();
 
     // Start cancellation task
-    tokio::spawn(shutdown_signal(handle.clone()));
+    tokio::spawn(shutdown_signal(handle.clone(), shutdown_timeout));
 
-    // Serve HTTP
-    tracing::info!("listening on {} (http)", addr);
+    // Serve HTTP(S) on every listener until all of them shut down
+    try_join_all(
+        listeners
+            .into_iter()
+            .map(|listener| listener.serve(router.clone(), handle.clone())),
+    )
+    .await?;
 
-    axum_server::bind(addr)
-        .handle(handle)
-        .serve(router.into_make_service())
-        .await?;
+    // Write views counted since the last flush, so they are not lost
+    if let Err(err) = views.flush(&db).await {
+        tracing::error!(?err, "failed to write card views");
+    }
 
-    // Close Sql connection
+    // Close Sql connection, only once every request is done with it
     db.close().await;
 
     tracing::info!("graceful shutdown complete!");
@@ -125,20 +129,105 @@ async fn main() -> Result<(), Error> {
     Ok(())
 }
 
-// Stolen from: https://github.com/tokio-rs/axum/blob/main/examples/error-handling/src/main.rs
-// Our middleware is responsible for logging error details internally
-async fn log_app_errors(request: Request, next: Next) -> Response {
-    let response = next.run(request).await;
-    // If the response contains an AppError Extension, log it.
-    if let Some(err) = response.extensions().get::<Arc<AppError>>() {
-        tracing::error!(?err, "an unexpected error occurred inside a handler");
+/// An address the server listens on.
+struct Listener {
+    addr: SocketAddr,
+    tls: Option<RustlsConfig>,
+}
+
+impl Listener {
+    /// Serves HTTP(S) until the server shuts down.
+    async fn serve(self, router: Router, handle: Handle) -> Result<(), Error> {
+        match self.tls {
+            Some(tls) => {
+                tracing::info!("listening on {} (https)", self.addr);
+
+                axum_server::bind(self.addr)
+                    .acceptor(ClientCertAcceptor::new(tls))
+                    .handle(handle)
+                    .serve(router.into_make_service_with_connect_info::<SocketAddr>())
+                    .await?;
+            }
+            None => {
+                tracing::info!("listening on {} (http)", self.addr);
+
+                axum_server::bind(self.addr)
+                    .handle(handle)
+                    .serve(router.into_make_service_with_connect_info::<SocketAddr>())
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Loads every listener of the server, with its certificates.
+fn load_listeners(config: &ServerConfig) -> Result<Vec<Listener>, Error> {
+    let listeners = config.listeners();
+    let mut addrs = HashSet::new();
+
+    if !config.tls_client_routes.is_empty()
+        && !listeners
+            .iter()
+            .any(|listener| listener.tls_client_ca.is_some())
+    {
+        return Err(Error::msg("`tls_client_routes` requires `tls_client_ca`"));
+    }
+
+    listeners
+        .iter()
+        .map(|listener| {
+            let addr = SocketAddr::new(listener.host, listener.port);
+
+            if !addrs.insert(addr) {
+                return Err(Error::msg(format!(
+                    "{} is listened on more than once",
+                    addr
+                )));
+            }
+
+            let tls = tls::load(listener)
+                .map_err(|err| Error::msg(format!("listener {}: {}", addr, err)))?;
+
+            Ok(Listener { addr, tls })
+        })
+        .collect()
+}
+
+/// Re-reads the log filter from the config file whenever the server receives
+/// `SIGUSR1`.
+///
+/// If the config file does not set a filter, `RUST_LOG` is used instead.
+#[cfg(unix)]
+async fn reload_log_filter_signal(config_path: PathBuf, log_filter: LogFilter) {
+    let mut signal = signal::unix::signal(signal::unix::SignalKind::user_defined1())
+        .expect("failed to install signal handler");
+
+    while signal.recv().await.is_some() {
+        let filter = Config::load(&config_path).and_then(|config| match config.log {
+            Some(directives) => EnvFilter::try_new(directives).map_err(Error::from),
+            None => Ok(EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER))),
+        });
+
+        match filter {
+            Ok(filter) => {
+                if let Err(err) = log_filter.reload(filter) {
+                    tracing::error!(?err, "failed to reload log filter");
+                }
+            }
+            Err(err) => tracing::error!(?err, "failed to read log filter"),
+        }
     }
-    response
 }
 
 // Stolen from: https://github.com/maxcountryman/tower-sessions-stores/tree/main/sqlx-store
 // Lol
-async fn shutdown_signal(handle: Handle) {
+//
+// In-flight requests are given `timeout` to finish, so a deploy never cuts a
+// grant off halfway; new connections are refused meanwhile.
+async fn shutdown_signal(handle: Handle, timeout: Duration) {
     let ctrl_c = async {
         signal::ctrl_c()
             .await
@@ -157,7 +246,14 @@ async fn shutdown_signal(handle: Handle) {
     let terminate = std::future::pending::<()>();
 
     select! {
-        _ = ctrl_c => { handle.shutdown() }
-        _ = terminate => { handle.shutdown() }
+        _ = ctrl_c => (),
+        _ = terminate => (),
     }
+
+    tracing::info!(
+        connections = handle.connection_count(),
+        ?timeout,
+        "shutting down; waiting on in-flight requests"
+    );
+    handle.graceful_shutdown(Some(timeout));
 }
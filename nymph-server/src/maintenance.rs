@@ -0,0 +1,75 @@
+//! Maintenance mode.
+//!
+//! While the server is in maintenance, [`refuse_writes`] refuses every
+//! request that could change data, so the database can be migrated or backed
+//! up without anything changing under it. Reads keep working. Maintenance is
+//! entered at startup with the `maintenance` option, or at any time through
+//! `PUT /admin/maintenance`.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+
+use http::Method;
+
+use crate::app::{ApiVersion, AppError, AppErrorKind, AppState};
+
+/// Routes served even during maintenance, like the route that ends it.
+const EXEMPT_ROUTES: &[&str] = &["/admin/maintenance", "/admin/backup"];
+
+/// Whether the server is in maintenance.
+///
+/// Cheaply cloneable.
+#[derive(Clone, Debug, Default)]
+pub struct Maintenance(Arc<AtomicBool>);
+
+impl Maintenance {
+    /// Creates a new `Maintenance`.
+    pub fn new(enabled: bool) -> Maintenance {
+        Maintenance(Arc::new(AtomicBool::new(enabled)))
+    }
+
+    /// `true` if the server is in maintenance.
+    pub fn enabled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Enters or leaves maintenance.
+    pub fn set(&self, enabled: bool) {
+        self.0.store(enabled, Ordering::Relaxed);
+    }
+}
+
+/// Middleware that refuses requests that could change data during
+/// maintenance.
+pub async fn refuse_writes(
+    State(state): State<AppState>,
+    path: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if !state.maintenance.enabled()
+        || matches!(
+            *request.method(),
+            Method::GET | Method::HEAD | Method::OPTIONS
+        )
+    {
+        return Ok(next.run(request).await);
+    }
+
+    let exempt = path.is_some_and(|path| {
+        let (_, path) = ApiVersion::split(path.as_str());
+        EXEMPT_ROUTES.contains(&path)
+    });
+
+    if !exempt {
+        return Err(AppErrorKind::Maintenance.into());
+    }
+
+    Ok(next.run(request).await)
+}
@@ -0,0 +1,61 @@
+//! Schema migrations.
+//!
+//! Migrations are built into the binary, so a fresh database only needs
+//! `nymph-server migrate`, or the `migrate` option to have the server run
+//! them on startup.
+
+use anyhow::Error;
+
+use sqlx::{
+    SqlitePool,
+    migrate::{Migration, Migrator},
+};
+
+/// The migrations the server was built against.
+pub static MIGRATOR: Migrator = sqlx::migrate!("../migrations");
+
+/// Lists the migrations that have not been applied yet, oldest first.
+pub async fn pending(db: &SqlitePool) -> Result<Vec<&'static Migration>, sqlx::Error> {
+    let (exists,) = sqlx::query_as::<_, (bool,)>(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM sqlite_master
+            WHERE type = 'table' AND name = '_sqlx_migrations'
+        )
+        "#,
+    )
+    .fetch_one(db)
+    .await?;
+
+    // a database that was never migrated has every migration pending
+    let applied = if exists {
+        sqlx::query_as::<_, (i64,)>(
+            r#"
+            SELECT version
+            FROM _sqlx_migrations
+            WHERE success = TRUE
+            "#,
+        )
+        .fetch_all(db)
+        .await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .filter(|migration| !applied.iter().any(|(v,)| *v == migration.version))
+        .collect())
+}
+
+/// Applies every pending migration.
+///
+/// Returns how many migrations were applied.
+pub async fn run(db: &SqlitePool) -> Result<usize, Error> {
+    let count = pending(db).await?.len();
+
+    MIGRATOR.run(db).await?;
+
+    Ok(count)
+}
@@ -0,0 +1,209 @@
+//! Per-client rate limiting.
+//!
+//! Every API key and token subject gets its own budget of requests, which
+//! refills steadily over time. Requests without credentials share a budget
+//! with every other request from the same [client address](ClientAddr).
+//!
+//! API keys only get their own budget once they are known to belong to a
+//! client, so made-up keys cannot be used to get a fresh budget with every
+//! request.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    RequestPartsExt as _,
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+
+use std::net::IpAddr;
+
+use http::{HeaderMap, header};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppState, SigningKeys},
+    auth::{
+        Claims,
+        api_key::{ApiKeyAuthentication, X_API_KEY, hash_key},
+    },
+    config::RateLimitConfig,
+    request::forwarded::ClientAddr,
+};
+
+/// How many budgets are tracked before idle ones are dropped.
+const PRUNE_AT: usize = 1024;
+
+/// Tracks the request budgets of every client.
+///
+/// Cheaply cloneable.
+#[derive(Clone, Debug)]
+pub struct RateLimiter(Arc<Inner>);
+
+#[derive(Debug)]
+struct Inner {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<String, Bucket>>,
+    /// The hashes of API keys that were found to belong to a client.
+    keys: Mutex<HashSet<String>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// How many requests are left.
+    remaining: f64,
+    updated_at: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a new `RateLimiter`.
+    pub fn new(config: RateLimitConfig) -> RateLimiter {
+        RateLimiter(Arc::new(Inner {
+            config,
+            buckets: Mutex::default(),
+            keys: Mutex::default(),
+        }))
+    }
+
+    /// `true` if requests are limited at all.
+    pub fn enabled(&self) -> bool {
+        self.0.config.per_minute > 0
+    }
+
+    /// `true` if an API key is known to belong to a client.
+    fn knows(&self, key: &str) -> bool {
+        self.0.keys.lock().expect("keys poisoned").contains(key)
+    }
+
+    /// Remembers that an API key belongs to a client.
+    fn remember(&self, key: String) {
+        let mut keys = self.0.keys.lock().expect("keys poisoned");
+
+        // keys are only forgotten to keep memory in check; a forgotten key
+        // is remembered again on its next request
+        if keys.len() >= PRUNE_AT {
+            keys.clear();
+        }
+
+        keys.insert(key);
+    }
+
+    /// Takes a request out of a client's budget.
+    ///
+    /// Returns how long the client must wait if its budget is spent.
+    pub fn check(&self, client: &str) -> Result<(), Duration> {
+        let burst = self.0.config.burst.max(1) as f64;
+        let per_second = self.0.config.per_minute as f64 / 60.;
+        let now = Instant::now();
+
+        let mut buckets = self.0.buckets.lock().expect("buckets poisoned");
+
+        if buckets.len() >= PRUNE_AT && !buckets.contains_key(client) {
+            // budgets that have refilled are no different from new ones
+            buckets.retain(|_, bucket| {
+                bucket.remaining + now.duration_since(bucket.updated_at).as_secs_f64() * per_second
+                    < burst
+            });
+        }
+
+        let bucket = buckets.entry(client.to_owned()).or_insert(Bucket {
+            remaining: burst,
+            updated_at: now,
+        });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.remaining = (bucket.remaining + elapsed * per_second).min(burst);
+        bucket.updated_at = now;
+
+        if bucket.remaining >= 1. {
+            bucket.remaining -= 1.;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1. - bucket.remaining) / per_second,
+            ))
+        }
+    }
+}
+
+/// Middleware that refuses requests from clients that spent their budget.
+pub async fn limit(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if !state.rate_limiter.enabled() {
+        return Ok(next.run(request).await);
+    }
+
+    let key = request
+        .headers()
+        .get(X_API_KEY)
+        .and_then(|s| s.to_str().ok())
+        .map(|key| hash_key(key.trim()));
+    let known = key
+        .as_ref()
+        .is_some_and(|key| state.rate_limiter.knows(key));
+
+    if let Some(client) = client(
+        key.as_deref().filter(|_| known),
+        request.headers(),
+        request.extensions().get::<ClientAddr>().map(|addr| **addr),
+        &state.keys,
+    ) && let Err(retry_after) = state.rate_limiter.check(&client)
+    {
+        tracing::debug!(client, ?retry_after, "rate limited");
+        return Err(AppErrorKind::RateLimited(retry_after).into());
+    }
+
+    // the lookup is cached, so the key is not looked up again to
+    // authenticate the request
+    let request = match key {
+        Some(key) if !known => {
+            let (mut parts, body) = request.into_parts();
+
+            if parts
+                .extract_with_state::<ApiKeyAuthentication, _>(&state)
+                .await
+                .is_ok()
+            {
+                state.rate_limiter.remember(key);
+            }
+
+            Request::from_parts(parts, body)
+        }
+        _ => request,
+    };
+
+    Ok(next.run(request).await)
+}
+
+/// Identifies the client making a request by its credentials, or else by its
+/// address.
+///
+/// Known API keys, given by their hash, are preferred, so a client proxying
+/// for users shares one budget across all of them.
+fn client(
+    key: Option<&str>,
+    headers: &HeaderMap,
+    addr: Option<IpAddr>,
+    keys: &SigningKeys,
+) -> Option<String> {
+    if let Some(key) = key {
+        return Some(format!("key:{}", key));
+    }
+
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|s| s.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer"))
+        .map(|s| s.trim());
+
+    // invalid tokens are refused later anyways
+    token
+        .and_then(|token| Claims::decode(token, keys).ok())
+        .map(|claims| format!("user:{}", claims.sub()))
+        .or_else(|| addr.map(|addr| format!("addr:{}", addr)))
+}
@@ -0,0 +1,119 @@
+//! Client addresses.
+//!
+//! Behind a reverse proxy, every request comes from the proxy's address. If
+//! the proxy is one of the configured
+//! [`trusted_proxies`](crate::config::ServerConfig::trusted_proxies), the
+//! `Forwarded` or `X-Forwarded-For` header it sent is believed instead, up to
+//! the first address that is not a trusted proxy itself. [`resolve`] adds the
+//! address to the extensions of every request as a [`ClientAddr`].
+
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::Response,
+};
+
+use derive_more::{Deref, Display};
+
+use http::{HeaderMap, HeaderName, header};
+
+use ipnet::IpNet;
+
+use crate::app::AppState;
+
+pub const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+
+/// The address of the client that made a request.
+#[derive(Clone, Copy, Debug, Deref, Display, PartialEq, Eq)]
+pub struct ClientAddr(IpAddr);
+
+/// Middleware that finds the address of the client that made a request.
+///
+/// Does nothing if the server was not served with connection info.
+pub async fn resolve(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| peer.ip().to_canonical());
+
+    if let Some(peer) = peer {
+        let addr = client_addr(peer, request.headers(), &state.trusted_proxies);
+        request.extensions_mut().insert(ClientAddr(addr));
+    }
+
+    next.run(request).await
+}
+
+/// Finds the address of the client behind any trusted proxies.
+fn client_addr(peer: IpAddr, headers: &HeaderMap, trusted_proxies: &[IpNet]) -> IpAddr {
+    let trusted = |addr: &IpAddr| trusted_proxies.iter().any(|net| net.contains(addr));
+
+    if !trusted(&peer) {
+        return peer;
+    }
+
+    // each proxy appends the address it was connected from, so the chain is
+    // walked back from the nearest proxy
+    let mut addr = peer;
+
+    for hop in forwarded_for(headers).into_iter().rev() {
+        // nothing before a hop that cannot be read can be believed
+        let Some(hop) = hop else {
+            break;
+        };
+
+        addr = hop;
+
+        if !trusted(&hop) {
+            break;
+        }
+    }
+
+    addr
+}
+
+/// Reads the addresses a request was forwarded for, client first.
+///
+/// `Forwarded` is preferred over `X-Forwarded-For`. Obfuscated and unknown
+/// addresses are `None`.
+fn forwarded_for(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    if headers.contains_key(header::FORWARDED) {
+        headers
+            .get_all(header::FORWARDED)
+            .iter()
+            .flat_map(|value| value.to_str().unwrap_or_default().split(','))
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.split_once('='))
+                    .find(|(key, _)| key.trim().eq_ignore_ascii_case("for"))
+                    .and_then(|(_, node)| parse_node(node))
+            })
+            .collect()
+    } else {
+        headers
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .flat_map(|value| value.to_str().unwrap_or_default().split(','))
+            .map(parse_node)
+            .collect()
+    }
+}
+
+/// Reads a forwarded node, like `192.0.2.43`, `"192.0.2.43:4711"` or
+/// `"[2001:db8::17]:4711"`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim().trim_matches('"');
+
+    if let Some(node) = node.strip_prefix('[') {
+        let (addr, _) = node.split_once(']')?;
+        return addr.parse().ok();
+    }
+
+    node.parse::<IpAddr>()
+        .or_else(|_| node.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .ok()
+        .map(|addr| addr.to_canonical())
+}
@@ -0,0 +1,83 @@
+//! Request IDs.
+//!
+//! Every request is given an ID, or keeps the one it was sent with, so a
+//! failed request can be found in the server's logs. The ID is returned in
+//! the [`REQUEST_ID_HEADER`] and in the body of error responses.
+
+use axum::{extract::Request, middleware::Next, response::Response};
+
+use base16::encode_lower;
+
+use derive_more::{Deref, Display};
+
+use http::{HeaderName, HeaderValue};
+
+use nymph_model::error::REQUEST_ID_HEADER;
+
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static(REQUEST_ID_HEADER);
+
+/// The longest inbound request ID that is kept.
+pub const MAX_REQUEST_ID_LEN: usize = 64;
+
+tokio::task_local! {
+    static REQUEST_ID: RequestId;
+}
+
+/// The ID of a request.
+///
+/// Added to the extensions of every request.
+#[derive(Clone, Debug, Deref, Display)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// Generates a new random `RequestId`.
+    pub fn new() -> RequestId {
+        RequestId(encode_lower(&rand::random::<[u8; 8]>()))
+    }
+
+    /// Reads the `RequestId` a request was sent with.
+    ///
+    /// IDs end up in logs, so anything but short, printable IDs are ignored.
+    pub fn from_header(header: &HeaderValue) -> Option<RequestId> {
+        let id = header.to_str().ok()?;
+
+        let valid = !id.is_empty()
+            && id.len() <= MAX_REQUEST_ID_LEN
+            && id
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'));
+
+        valid.then(|| RequestId(id.to_owned()))
+    }
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        RequestId::new()
+    }
+}
+
+/// The ID of the request being handled, if any.
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.0.clone()).ok()
+}
+
+/// Middleware that gives every request an ID.
+pub async fn propagate(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(X_REQUEST_ID)
+        .and_then(RequestId::from_header)
+        .unwrap_or_default();
+
+    request.extensions_mut().insert(id.clone());
+
+    let mut res = REQUEST_ID.scope(id.clone(), next.run(request)).await;
+
+    res.headers_mut().insert(
+        X_REQUEST_ID,
+        HeaderValue::try_from(id.0).expect("valid request id"),
+    );
+
+    res
+}
@@ -1,3 +1,7 @@
 //! Request helpers and utilities.
 
+pub mod forwarded;
+pub mod id;
+pub mod record;
+pub mod timeout;
 pub mod validate;
@@ -0,0 +1,213 @@
+//! Request recording.
+//!
+//! When enabled, [`record`] keeps the latest requests and their responses in
+//! a ring buffer, which operators can read at `GET /admin/requests` to see
+//! exactly what a client sent. Credentials are redacted before anything is
+//! kept, and only small JSON bodies are recorded; see [`RecordConfig`].
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use axum::{
+    body::{Body, HttpBody as _},
+    extract::Request,
+    extract::State,
+    middleware::Next,
+    response::{IntoResponse as _, Response},
+};
+
+use chrono::Utc;
+
+use http::{HeaderMap, StatusCode, header};
+
+use nymph_model::response::admin::RecordedRequest;
+
+use serde_json::Value;
+
+use crate::{
+    app::{ApiVersion, AppState},
+    config::RecordConfig,
+    request,
+};
+
+/// What redacted values are replaced with.
+const REDACTED: &str = "[redacted]";
+
+/// The path recorded requests are read from, which is never recorded itself.
+const RECORDS_PATH: &str = "/admin/requests";
+
+/// Keeps the latest requests.
+///
+/// Cheaply cloneable.
+#[derive(Clone, Debug, Default)]
+pub struct Recorder(Arc<Inner>);
+
+#[derive(Debug, Default)]
+struct Inner {
+    config: RecordConfig,
+    requests: Mutex<VecDeque<RecordedRequest>>,
+}
+
+impl Recorder {
+    /// Creates a new `Recorder`.
+    pub fn new(config: RecordConfig) -> Recorder {
+        Recorder(Arc::new(Inner {
+            requests: Mutex::new(VecDeque::with_capacity(config.capacity)),
+            config,
+        }))
+    }
+
+    /// `true` if requests are recorded at all.
+    pub fn enabled(&self) -> bool {
+        self.0.config.capacity > 0
+    }
+
+    /// Records a request, dropping the oldest one if the buffer is full.
+    pub fn push(&self, request: RecordedRequest) {
+        let mut requests = self.0.requests.lock().expect("requests poisoned");
+
+        if requests.len() >= self.0.config.capacity {
+            requests.pop_front();
+        }
+
+        requests.push_back(request);
+    }
+
+    /// Lists up to `count` recorded requests, newest first.
+    ///
+    /// If `request_id` is given, only that request is listed.
+    pub fn list(&self, request_id: Option<&str>, count: usize) -> Vec<RecordedRequest> {
+        let requests = self.0.requests.lock().expect("requests poisoned");
+
+        requests
+            .iter()
+            .rev()
+            .filter(|request| request_id.is_none() || request.request_id.as_deref() == request_id)
+            .take(count)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Middleware that records requests and their responses.
+pub async fn record(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let recorder = &state.recorder;
+
+    if !recorder.enabled() || ApiVersion::split(request.uri().path()).1 == RECORDS_PATH {
+        return next.run(request).await;
+    }
+
+    let received_at = Utc::now().naive_utc();
+    let start = Instant::now();
+    let max_body = recorder.0.config.max_body;
+
+    let method = request.method().to_string();
+    let path = match request.uri().query() {
+        Some(query) => format!("{}?{}", request.uri().path(), redact_query(query)),
+        None => request.uri().path().to_owned(),
+    };
+
+    // only bodies that are known to be small are read ahead of the handler
+    let (parts, body) = request.into_parts();
+    let length = parts
+        .headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse::<usize>().ok());
+
+    let (request_body, body) = match length {
+        Some(length) if length <= max_body && is_json(&parts.headers) => {
+            match axum::body::to_bytes(body, max_body).await {
+                Ok(bytes) => (read_body(&bytes), Body::from(bytes)),
+                Err(err) => {
+                    tracing::debug!(?err, "failed to read request body");
+                    return StatusCode::BAD_REQUEST.into_response();
+                }
+            }
+        }
+        _ => (None, body),
+    };
+
+    let res = next.run(Request::from_parts(parts, body)).await;
+
+    let (parts, body) = res.into_parts();
+    let length = body.size_hint().exact();
+
+    let (response_body, body) = match length {
+        Some(length) if length as usize <= max_body && is_json(&parts.headers) => {
+            match axum::body::to_bytes(body, max_body).await {
+                Ok(bytes) => (read_body(&bytes), Body::from(bytes)),
+                Err(err) => {
+                    tracing::error!(?err, "failed to read response body");
+                    return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                }
+            }
+        }
+        _ => (None, body),
+    };
+
+    recorder.push(RecordedRequest {
+        request_id: request::id::current(),
+        received_at,
+        method,
+        path,
+        request_body,
+        status: parts.status.as_u16(),
+        response_body,
+        duration_ms: start.elapsed().as_millis() as u64,
+    });
+
+    Response::from_parts(parts, body)
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|mime| mime.as_bytes().starts_with(b"application/json"))
+}
+
+fn read_body(bytes: &[u8]) -> Option<Value> {
+    let mut value = serde_json::from_slice::<Value>(bytes).ok()?;
+    redact(&mut value);
+    Some(value)
+}
+
+/// Checks if a field or query parameter may hold a credential.
+fn is_secret(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+
+    ["token", "key", "secret", "password", "signature"]
+        .iter()
+        .any(|secret| key.ends_with(secret))
+}
+
+/// Redacts every credential in a JSON document.
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                // flags like `generate_token` hold no secrets
+                if is_secret(key) && !value.is_boolean() && !value.is_null() {
+                    *value = Value::String(REDACTED.into());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => (),
+    }
+}
+
+/// Redacts every credential in a query string.
+fn redact_query(query: &str) -> String {
+    query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if is_secret(key) => format!("{}={}", key, REDACTED),
+            _ => pair.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
@@ -0,0 +1,71 @@
+//! Request timeouts.
+//!
+//! Bots must answer interactions within a few seconds, so a request stuck
+//! behind a slow database write is worse than one that fails. [`abort_slow`]
+//! answers requests that take longer than [`ServerConfig::timeout`] with an
+//! error.
+//!
+//! The request itself is not cancelled: it keeps running in the background
+//! until it is done, so it is never cut off between committing its changes
+//! and dispatching their events. A client that timed out may not assume its
+//! request failed.
+//!
+//! [`ServerConfig::timeout`]: crate::config::ServerConfig::timeout
+
+use std::panic;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::app::{ApiVersion, AppError, AppErrorKind, AppState};
+
+/// Routes that are expected to take long, and are never timed out.
+const EXEMPT: &[&str] = &[
+    "/admin/backup",
+    "/guilds/{guild_id}/cards/import",
+    "/guilds/{guild_id}/templates/{id}/instantiate",
+];
+
+/// Middleware that answers requests that take too long with an error.
+///
+/// Only the time until the response starts counts, so streamed bodies are
+/// never cut off.
+pub async fn abort_slow(
+    State(state): State<AppState>,
+    path: Option<MatchedPath>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let Some(timeout) = state.timeout else {
+        return Ok(next.run(request).await);
+    };
+
+    let exempt = path.is_some_and(|path| {
+        let (_, path) = ApiVersion::split(path.as_str());
+        EXEMPT.contains(&path)
+    });
+
+    if exempt {
+        return Ok(next.run(request).await);
+    }
+
+    let path = request.uri().path().to_owned();
+    // dropping the handle detaches the request, rather than cancelling it
+    let handle = tokio::spawn(next.run(request));
+
+    match tokio::time::timeout(timeout, handle).await {
+        Ok(Ok(res)) => Ok(res),
+        Ok(Err(err)) => match err.try_into_panic() {
+            Ok(payload) => panic::resume_unwind(payload),
+            // the runtime is shutting down
+            Err(_) => Err(AppErrorKind::Maintenance.into()),
+        },
+        Err(_) => {
+            tracing::warn!(path, ?timeout, "request timed out");
+            Err(AppErrorKind::Timeout(timeout).into())
+        }
+    }
+}
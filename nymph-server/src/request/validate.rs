@@ -21,6 +21,12 @@ where
 {
     /// Checks if a value is in range.
     fn in_range<R>(self, range: R) -> RangeValidator<Self, R>;
+
+    /// Checks if a value is one of a set of values.
+    fn one_of<O>(self, options: O) -> OneOfValidator<Self, O>;
+
+    /// Checks if every character of a string passes a test.
+    fn chars<F>(self, test: F) -> CharsValidator<Self, F>;
 }
 
 impl<T, V> ValidatorExt<V> for T
@@ -30,6 +36,14 @@ where
     fn in_range<R>(self, range: R) -> RangeValidator<Self, R> {
         RangeValidator::new(self, range)
     }
+
+    fn one_of<O>(self, options: O) -> OneOfValidator<Self, O> {
+        OneOfValidator::new(self, options)
+    }
+
+    fn chars<F>(self, test: F) -> CharsValidator<Self, F> {
+        CharsValidator::new(self, test)
+    }
 }
 
 /// Represents a value with no constraints.
@@ -105,6 +119,93 @@ where
     }
 }
 
+/// Set validator.
+#[derive(Debug)]
+pub struct OneOfValidator<I, O> {
+    inner: I,
+    options: O,
+}
+
+impl<I, O> OneOfValidator<I, O> {
+    /// Creates a new `OneOfValidator`.
+    pub fn new(inner: I, options: O) -> OneOfValidator<I, O> {
+        OneOfValidator { inner, options }
+    }
+}
+
+impl<T, I, O> Validator<T> for OneOfValidator<I, O>
+where
+    O: AsRef<[T]> + Debug,
+    I: Validator<T>,
+    T: PartialEq,
+{
+    /// Checks if a value is one of the options.
+    ///
+    /// Returns `Err` with a descriptive error if it is not.
+    fn validate(self) -> Result<T, AppError> {
+        let name = self.inner.name();
+        let value = self.inner.validate()?;
+
+        if self.options.as_ref().contains(&value) {
+            Ok(value)
+        } else {
+            Err(
+                AppError::from(AppErrorKind::FieldOutOfRange(name.to_owned())).with_message(
+                    format!(
+                        "Field `{}` is out of range; possible values: {:?}",
+                        name, self.options
+                    ),
+                ),
+            )
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
+/// Character validator.
+#[derive(Debug)]
+pub struct CharsValidator<I, F> {
+    inner: I,
+    test: F,
+}
+
+impl<I, F> CharsValidator<I, F> {
+    /// Creates a new `CharsValidator`.
+    pub fn new(inner: I, test: F) -> CharsValidator<I, F> {
+        CharsValidator { inner, test }
+    }
+}
+
+impl<T, I, F> Validator<T> for CharsValidator<I, F>
+where
+    I: Validator<T>,
+    F: Fn(char) -> bool,
+    T: AsRef<str>,
+{
+    /// Checks if every character passes the test.
+    ///
+    /// Returns `Err` naming the first character that does not.
+    fn validate(self) -> Result<T, AppError> {
+        let name = self.inner.name();
+        let value = self.inner.validate()?;
+
+        match value.as_ref().chars().find(|c| !(self.test)(*c)) {
+            None => Ok(value),
+            Some(c) => Err(
+                AppError::from(AppErrorKind::FieldOutOfRange(name.to_owned()))
+                    .with_message(format!("Field `{}` cannot contain `{}`", name, c)),
+            ),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+}
+
 /// Shorthand for [`Value::new`].
 pub fn value<T>(name: &'static str, value: T) -> Value<T> {
     Value::new(name, value)
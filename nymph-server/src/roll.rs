@@ -0,0 +1,100 @@
+//! Seeded card rolls.
+//!
+//! Rolls draw from a ChaCha8 stream seeded with a fresh random seed, so the
+//! same seed and pool always pick the same cards. The pool is logged once per
+//! roll under its seed, and the seed with every card the roll grants (see
+//! [`Roll`](nymph_model::dispatch::Roll)), which lets an operator replay a
+//! disputed roll from the audit log and show it landed where it says.
+//!
+//! Picks only depend on the raw output of the stream, never on how a version
+//! of `rand` samples ranges, so old rolls keep replaying the same.
+
+use base16::{decode, encode_lower};
+
+use rand_chacha::{
+    ChaCha8Rng,
+    rand_core::{RngCore as _, SeedableRng as _},
+};
+
+/// The seed of a roll.
+pub type Seed = [u8; 32];
+
+/// Generates a fresh seed.
+pub fn seed() -> Seed {
+    rand::random()
+}
+
+/// Encodes a seed in hex, like it is logged.
+pub fn encode_seed(seed: &Seed) -> String {
+    encode_lower(seed)
+}
+
+/// Decodes a seed logged in hex.
+pub fn decode_seed(seed: &str) -> Option<Seed> {
+    decode(seed).ok()?.try_into().ok()
+}
+
+/// Picks `count` cards from a pool.
+///
+/// Cards may be picked more than once. Returns nothing if the pool is empty.
+pub fn pick(seed: &Seed, pool: &[i32], count: u32) -> Vec<i32> {
+    if pool.is_empty() {
+        return Vec::new();
+    }
+
+    let mut rng = ChaCha8Rng::from_seed(*seed);
+
+    (0..count)
+        .map(|_| {
+            // scaling keeps the bias of a pick below `pool.len() / 2^64`
+            let index = (rng.next_u64() as u128 * pool.len() as u128) >> 64;
+            pool[index as usize]
+        })
+        .collect()
+}
+
+/// Picks `count` entries of a weighted table, returning their indices.
+///
+/// Each entry is given as its weight and how many times it may be picked at
+/// most, if it is capped. An entry that reached its cap is no longer picked,
+/// and picks stop early once every entry did.
+pub fn pick_weighted(seed: &Seed, entries: &[(u32, Option<u32>)], count: u32) -> Vec<usize> {
+    let mut rng = ChaCha8Rng::from_seed(*seed);
+    let mut picked = vec![0u32; entries.len()];
+    let mut picks = Vec::with_capacity(count as usize);
+
+    // running totals of the weights still in play, rebuilt when one caps
+    let cumulative = |picked: &[u32]| {
+        entries
+            .iter()
+            .zip(picked)
+            .scan(0u64, |total, (&(weight, cap), &picked)| {
+                if cap.is_none_or(|cap| picked < cap) {
+                    *total += u64::from(weight);
+                }
+                Some(*total)
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut totals = cumulative(&picked);
+
+    for _ in 0..count {
+        let total = totals.last().copied().unwrap_or(0);
+
+        if total == 0 {
+            break;
+        }
+
+        let target = ((rng.next_u64() as u128 * total as u128) >> 64) as u64;
+        let index = totals.partition_point(|&sum| sum <= target);
+
+        picked[index] += 1;
+        picks.push(index);
+
+        if entries[index].1.is_some_and(|cap| picked[index] >= cap) {
+            totals = cumulative(&picked);
+        }
+    }
+
+    picks
+}
@@ -0,0 +1,304 @@
+//! The server's router.
+
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::{MatchedPath, Request},
+    middleware::{Next, from_fn, from_fn_with_state},
+    response::Response,
+    routing::{delete, get, patch, post, put},
+};
+
+use tower_http::{compression::CompressionLayer, trace::TraceLayer};
+
+use crate::{
+    app::{ApiVersion, AppError, AppState},
+    request::{self, forwarded::ClientAddr, id::RequestId},
+    routes,
+};
+
+/// Builds the router of every route the server serves, with its middleware.
+pub fn build(state: AppState) -> Router {
+    let api = Router::<AppState>::new()
+        .nest(
+            "/guilds/{guild_id}/cards",
+            Router::<AppState>::new()
+                .route("/", get(routes::card::list))
+                .route("/", post(routes::card::create))
+                .route("/archive", post(routes::card::archive))
+                .route("/import", post(routes::card::import::import))
+                .route("/popular", get(routes::card::views::popular))
+                .route("/lookup", get(routes::card::lookup))
+                .route("/{id}", get(routes::card::show))
+                .route("/{id}", patch(routes::card::update))
+                .route("/{id}/owners", get(routes::card::inventory::owners))
+                .route("/{id}/grant-policy", get(routes::card::policy::show))
+                .route("/{id}/grant-policy", put(routes::card::policy::update))
+                .route(
+                    "/{id}/prerequisites",
+                    get(routes::card::prerequisites::show),
+                )
+                .route(
+                    "/{id}/prerequisites",
+                    put(routes::card::prerequisites::update),
+                )
+                .route("/{id}/reports", post(routes::report::create)),
+        )
+        .nest(
+            "/guilds/{guild_id}/events",
+            Router::<AppState>::new()
+                .route("/", get(routes::event::list))
+                .route("/", post(routes::event::create))
+                .route("/replay", get(routes::webhook::replay))
+                .route("/{id}", get(routes::event::show)),
+        )
+        .nest(
+            "/guilds/{guild_id}/webhooks",
+            Router::<AppState>::new()
+                .route("/", get(routes::webhook::list))
+                .route("/", post(routes::webhook::create))
+                .route("/{id}", delete(routes::webhook::delete)),
+        )
+        .nest(
+            "/guilds/{guild_id}/rules",
+            Router::<AppState>::new()
+                .route("/", get(routes::rule::list))
+                .route("/", post(routes::rule::create))
+                .route("/{id}", get(routes::rule::show))
+                .route("/{id}", patch(routes::rule::update))
+                .route("/{id}", delete(routes::rule::delete)),
+        )
+        .nest(
+            "/guilds/{guild_id}/syndications",
+            Router::<AppState>::new()
+                .route("/", get(routes::syndication::list))
+                .route("/", post(routes::syndication::create))
+                .route("/{id}", delete(routes::syndication::delete)),
+        )
+        .nest(
+            "/guilds/{guild_id}/drop-table",
+            Router::<AppState>::new()
+                .route("/", get(routes::drop_table::show))
+                .route("/preview", get(routes::drop_table::preview))
+                .route("/{card_id}", put(routes::drop_table::update))
+                .route("/{card_id}", delete(routes::drop_table::delete)),
+        )
+        .route(
+            "/guilds/{guild_id}/seasons",
+            get(routes::card::season::list),
+        )
+        .route(
+            "/guilds/{guild_id}/seasons",
+            post(routes::card::season::create),
+        )
+        .route("/guilds/{guild_id}/reports", get(routes::report::list))
+        .route(
+            "/guilds/{guild_id}/reports/{id}/resolve",
+            post(routes::report::resolve),
+        )
+        .route("/guilds/{guild_id}/audit", get(routes::audit::list))
+        .route("/guilds/{guild_id}/lint", get(routes::guild::lint_rules))
+        .route("/guilds/{guild_id}/lint", put(routes::guild::update_lint_rules))
+        .route(
+            "/guilds/{guild_id}/name-rules",
+            get(routes::guild::name_rules),
+        )
+        .route(
+            "/guilds/{guild_id}/name-rules",
+            put(routes::guild::update_name_rules),
+        )
+        .route(
+            "/guilds/{guild_id}/trade-in",
+            get(routes::guild::trade_in_rules),
+        )
+        .route(
+            "/guilds/{guild_id}/trade-in",
+            put(routes::guild::update_trade_in_rules),
+        )
+        .route(
+            "/guilds/{guild_id}/ownership/export",
+            get(routes::guild::export_ownership),
+        )
+        .route(
+            "/guilds/{guild_id}/announcements",
+            get(routes::guild::announcements),
+        )
+        .route(
+            "/guilds/{guild_id}/announcements",
+            put(routes::guild::update_announcements),
+        )
+        .route(
+            "/guilds/{guild_id}/templates/{id}/instantiate",
+            post(routes::admin::template::instantiate),
+        )
+        .nest(
+            "/users",
+            Router::<AppState>::new()
+                .route("/discord", post(routes::user::discord))
+                .route("/discord/batch", post(routes::user::discord_batch))
+                .route("/discord/stale", get(routes::user::discord_stale))
+                .nest(
+                    "/{user_id}",
+                    Router::<AppState>::new()
+                        .route("/cards", get(routes::card::inventory::list))
+                        .route("/cards", post(routes::card::inventory::grant))
+                        .route("/cards/export", get(routes::card::inventory::export))
+                        .route("/cards/export.csv", get(routes::card::inventory::export))
+                        .route("/cards/{card_id}", delete(routes::card::inventory::revoke))
+                        .route(
+                            "/cards/{card_id}/transfer",
+                            post(routes::card::inventory::transfer),
+                        )
+                        .route(
+                            "/cards/{card_id}/trade-in",
+                            post(routes::card::inventory::trade_in),
+                        )
+                        .route(
+                            "/cards/{card_id}/reaction",
+                            put(routes::card::inventory::react),
+                        )
+                        .route(
+                            "/cards/{card_id}/reaction",
+                            delete(routes::card::inventory::unreact),
+                        )
+                        .route(
+                            "/favorites/{card_id}",
+                            put(routes::card::inventory::favorite),
+                        )
+                        .route(
+                            "/favorites/{card_id}",
+                            delete(routes::card::inventory::unfavorite),
+                        )
+                        .route("/progress", get(routes::card::inventory::progress))
+                        .route(
+                            "/seasons/{season_id}/cards",
+                            get(routes::card::season::cards),
+                        ),
+                ),
+        )
+        .route("/gateway", get(routes::gateway::connect))
+        .route("/admin/log-filter", get(routes::admin::log_filter))
+        .route("/admin/log-filter", put(routes::admin::update_log_filter))
+        .route("/admin/backup", post(routes::admin::backup))
+        .route("/admin/maintenance", get(routes::admin::maintenance))
+        .route("/admin/maintenance", put(routes::admin::update_maintenance))
+        .route("/admin/policies", get(routes::admin::policies))
+        .route("/admin/requests", get(routes::admin::requests))
+        .route("/admin/impersonations", get(routes::admin::impersonations))
+        .route("/admin/rolls/{seq}", get(routes::admin::replay_roll))
+        .route("/admin/guilds", get(routes::telemetry::guilds))
+        .route("/telemetry/guilds", post(routes::telemetry::report_guilds))
+        .route(
+            "/telemetry/guilds/{guild_id}",
+            delete(routes::telemetry::depart_guild),
+        )
+        .route("/admin/templates", get(routes::admin::template::list))
+        .route("/admin/templates", post(routes::admin::template::create))
+        .route(
+            "/admin/templates/{id}",
+            delete(routes::admin::template::delete),
+        )
+        .nest(
+            "/trades",
+            Router::<AppState>::new()
+                .route("/", post(routes::trade::open))
+                .route("/{id}", get(routes::trade::show))
+                .route("/{id}/accept", post(routes::trade::accept))
+                .route("/{id}/cancel", post(routes::trade::cancel)),
+        )
+        .nest(
+            "/views",
+            Router::<AppState>::new()
+                .route("/", post(routes::view::create))
+                .route("/{id}", get(routes::view::show)),
+        )
+        .route_layer(from_fn_with_state(
+            state.clone(),
+            crate::auth::policy::enforce,
+        ));
+
+    // Unversioned paths are deprecated aliases of `/v1`
+    Router::<AppState>::new()
+        .nest(ApiVersion::V1.prefix(), api.clone())
+        .merge(api)
+        .layer(from_fn(crate::app::deprecate_unversioned))
+        .layer(from_fn(crate::deprecation::notify))
+        .layer(from_fn_with_state(
+            state.clone(),
+            request::timeout::abort_slow,
+        ))
+        .layer(from_fn_with_state(
+            state.clone(),
+            crate::auth::mtls::require,
+        ))
+        .layer(from_fn_with_state(
+            state.clone(),
+            crate::auth::allowlist::restrict,
+        ))
+        .layer(from_fn_with_state(
+            state.clone(),
+            crate::maintenance::refuse_writes,
+        ))
+        .layer(from_fn_with_state(state.clone(), crate::fault::inject))
+        .layer(from_fn_with_state(state.clone(), crate::ratelimit::limit))
+        .layer(from_fn(crate::app::app_rest_headers))
+        .layer(from_fn_with_state(state.clone(), request::record::record))
+        .layer(from_fn(crate::app::msgpack_responses))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(|req: &Request| {
+                    let method = req.method();
+                    let uri = req.uri();
+
+                    // axum automatically adds this extension.
+                    let matched_path = req
+                        .extensions()
+                        .get::<MatchedPath>()
+                        .map(|matched_path| matched_path.as_str());
+
+                    let request_id = req.extensions().get::<RequestId>().map(|id| id.as_str());
+
+                    let client = req
+                        .extensions()
+                        .get::<ClientAddr>()
+                        .map(tracing::field::display);
+
+                    tracing::debug_span!(
+                        "request",
+                        %method,
+                        %uri,
+                        matched_path,
+                        request_id,
+                        client
+                    )
+                })
+                // By default `TraceLayer` will log 5xx responses but we're doing our specific
+                // logging of errors so disable that
+                .on_failure(()),
+        )
+        .layer(from_fn(log_app_errors))
+        .layer(from_fn_with_state(
+            state.clone(),
+            request::forwarded::resolve,
+        ))
+        .layer(from_fn(request::id::propagate))
+        .layer(CompressionLayer::new())
+        .with_state(state)
+}
+
+// Stolen from: https://github.com/tokio-rs/axum/blob/main/examples/error-handling/src/main.rs
+// Our middleware is responsible for logging error details internally
+async fn log_app_errors(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    // If the response contains an AppError Extension, log it.
+    if let Some(err) = response.extensions().get::<Arc<AppError>>() {
+        let request_id = request::id::current();
+        tracing::error!(
+            ?err,
+            request_id,
+            "an unexpected error occurred inside a handler"
+        );
+    }
+    response
+}
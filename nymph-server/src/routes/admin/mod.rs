@@ -0,0 +1,343 @@
+//! Operator endpoints.
+
+pub mod template;
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use chrono::NaiveDateTime;
+
+use nymph_model::{
+    dispatch::{Envelope, Event},
+    impersonation::Impersonation,
+    policy::RoutePolicy,
+    request::admin::{
+        ImpersonationsQuery, RecordedRequestsQuery, UpdateLogFilterRequest,
+        UpdateMaintenanceRequest,
+    },
+    response::admin::{
+        BackupResponse, LogFilterResponse, MaintenanceResponse, RecordedRequest, RollReplayResponse,
+    },
+    user::User,
+};
+
+use sqlx::{FromRow, types::Json};
+
+use tracing_subscriber::EnvFilter;
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppQuery, AppState, Payload},
+    auth::{Authentication, policy::POLICIES},
+    backup,
+    request::validate::{Validator as _, ValidatorExt as _, value},
+    roll,
+};
+
+/// How many recorded requests are listed by default.
+const DEFAULT_RECORDED_COUNT: u32 = 50;
+
+/// The most requests made as other users a single page lists.
+pub const MAX_IMPERSONATION_COUNT: u32 = 100;
+
+/// Gets the server's current tracing filter.
+#[debug_handler]
+pub async fn log_filter(
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<AppJson<LogFilterResponse>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    Ok(AppJson(LogFilterResponse {
+        filter: state.log_filter.current().unwrap_or_default(),
+    }))
+}
+
+/// Replaces the server's tracing filter.
+///
+/// The filter lasts until the server restarts or receives `SIGUSR1`.
+#[debug_handler]
+pub async fn update_log_filter(
+    State(state): State<AppState>,
+    auth: Authentication,
+    Payload(request): Payload<UpdateLogFilterRequest>,
+) -> Result<AppJson<LogFilterResponse>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let filter = EnvFilter::try_new(&request.filter).map_err(|err| {
+        AppError::from(AppErrorKind::FieldOutOfRange("filter".into()))
+            .with_message(format!("Invalid log filter: {}", err))
+    })?;
+
+    state.log_filter.reload(filter)?;
+
+    Ok(AppJson(LogFilterResponse {
+        filter: state.log_filter.current().unwrap_or_default(),
+    }))
+}
+
+/// Backs up the database to the configured backup directory.
+///
+/// Meant to be called periodically, for example from cron.
+#[debug_handler]
+pub async fn backup(
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<AppJson<BackupResponse>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let Some(directory) = state.backup.directory.as_ref() else {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message("Backups are not configured on this server."));
+    };
+
+    let backup = backup::run(&state.db, directory, &state.backup).await?;
+
+    Ok(AppJson(BackupResponse {
+        path: backup.path.display().to_string(),
+        size: backup.size,
+        removed: backup
+            .removed
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect(),
+    }))
+}
+
+/// Gets whether the server is in maintenance.
+#[debug_handler]
+pub async fn maintenance(
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<AppJson<MaintenanceResponse>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    Ok(AppJson(MaintenanceResponse {
+        enabled: state.maintenance.enabled(),
+    }))
+}
+
+/// Enters or leaves maintenance.
+///
+/// Maintenance lasts until it is left or the server restarts.
+#[debug_handler]
+pub async fn update_maintenance(
+    State(state): State<AppState>,
+    auth: Authentication,
+    Payload(request): Payload<UpdateMaintenanceRequest>,
+) -> Result<AppJson<MaintenanceResponse>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    state.maintenance.set(request.enabled);
+
+    tracing::warn!(
+        enabled = request.enabled,
+        user_id = auth.id,
+        "updated maintenance"
+    );
+
+    Ok(AppJson(MaintenanceResponse {
+        enabled: request.enabled,
+    }))
+}
+
+/// Lists the latest recorded requests, newest first.
+#[debug_handler]
+pub async fn requests(
+    State(state): State<AppState>,
+    auth: Authentication,
+    AppQuery(query): AppQuery<RecordedRequestsQuery>,
+) -> Result<AppJson<Vec<RecordedRequest>>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    if !state.recorder.enabled() {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message("Requests are not recorded on this server."));
+    }
+
+    let count = query.count.unwrap_or(DEFAULT_RECORDED_COUNT) as usize;
+
+    Ok(AppJson(
+        state.recorder.list(query.request_id.as_deref(), count),
+    ))
+}
+
+/// Lists the requests operators made as other users, newest first.
+#[debug_handler]
+pub async fn impersonations(
+    State(state): State<AppState>,
+    auth: Authentication,
+    AppQuery(query): AppQuery<ImpersonationsQuery>,
+) -> Result<AppJson<Vec<Impersonation>>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    #[derive(FromRow)]
+    struct ImpersonationResult {
+        id: i32,
+        actor_id: i32,
+        actor_display_name: String,
+        user_id: i32,
+        user_display_name: String,
+        reason: String,
+        method: String,
+        path: String,
+        request_id: Option<String>,
+        inserted_at: NaiveDateTime,
+    }
+
+    let count = value("count", query.count.unwrap_or(25))
+        .in_range(1..=MAX_IMPERSONATION_COUNT)
+        .validate()?;
+
+    let impersonations = sqlx::query_as::<_, ImpersonationResult>(
+        r#"
+        SELECT
+            il.id, il.reason, il.method, il.path, il.request_id, il.inserted_at,
+            a.id AS actor_id, a.display_name AS actor_display_name,
+            u.id AS user_id, u.display_name AS user_display_name
+        FROM
+            impersonation_log il, user a, user u
+        WHERE
+            il.actor_id = a.id
+            AND il.user_id = u.id
+            AND ($1 IS NULL OR il.actor_id = $1)
+            AND ($2 IS NULL OR il.user_id = $2)
+            AND ($3 IS NULL OR il.id < $3)
+        ORDER BY il.id DESC
+        LIMIT $4
+        "#,
+    )
+    .bind(query.actor_id)
+    .bind(query.user_id)
+    .bind(query.before)
+    .bind(count)
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|result| Impersonation {
+        id: result.id,
+        actor: User {
+            id: result.actor_id,
+            display_name: result.actor_display_name,
+        },
+        user: User {
+            id: result.user_id,
+            display_name: result.user_display_name,
+        },
+        reason: result.reason,
+        method: result.method,
+        path: result.path,
+        request_id: result.request_id,
+        inserted_at: result.inserted_at,
+    })
+    .collect();
+
+    Ok(AppJson(impersonations))
+}
+
+/// Replays the roll that granted a card, checking it picks the card the
+/// audit log says it did.
+///
+/// Only works for as long as the audit log keeps the grant.
+#[debug_handler]
+pub async fn replay_roll(
+    State(state): State<AppState>,
+    Path((seq,)): Path<(i64,)>,
+    auth: Authentication,
+) -> Result<AppJson<RollReplayResponse>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let payload = sqlx::query_as::<_, (Json<Envelope>,)>(
+        r#"
+        SELECT payload
+        FROM audit_log
+        WHERE seq = $1
+        "#,
+    )
+    .bind(seq)
+    .fetch_optional(&state.db)
+    .await?;
+
+    let Some((Json(envelope),)) = payload else {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The event of sequence {} does not exist.", seq)));
+    };
+
+    let Event::CardGranted(ownership) = envelope.event else {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The event of sequence {} is not a grant.", seq)));
+    };
+
+    let Some(logged) = ownership.roll else {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The card of event {} was not rolled.", seq)));
+    };
+
+    let pool = sqlx::query_as::<_, (Json<Vec<i32>>,)>(
+        r#"
+        SELECT pool
+        FROM roll
+        WHERE seed = $1
+        "#,
+    )
+    .bind(&logged.seed)
+    .fetch_optional(&state.db)
+    .await?
+    .map(|(Json(pool),)| pool)
+    .unwrap_or_default();
+
+    // the roll is replayed from the start, up to the logged pick
+    let replayed_card_id = roll::decode_seed(&logged.seed)
+        .and_then(|seed| roll::pick(&seed, &pool, logged.index + 1).last().copied())
+        .ok_or_else(|| {
+            AppError::from(AppErrorKind::NotFound)
+                .with_message(format!("The roll of event {} cannot be replayed.", seq))
+        })?;
+
+    Ok(AppJson(RollReplayResponse {
+        seq: seq as u64,
+        user_id: ownership.user_id,
+        card_id: ownership.card.id,
+        replayed_card_id,
+        verified: replayed_card_id == ownership.card.id,
+        roll: logged,
+        pool,
+    }))
+}
+
+/// Lists who may call each route the server serves.
+#[debug_handler(state = AppState)]
+pub async fn policies(auth: Authentication) -> Result<AppJson<Vec<RoutePolicy>>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    Ok(AppJson(
+        POLICIES
+            .iter()
+            .map(|policy| RoutePolicy {
+                method: policy.method.to_owned(),
+                path: policy.path.to_owned(),
+                access: policy.access,
+                note: policy.note.map(String::from),
+            })
+            .collect(),
+    ))
+}
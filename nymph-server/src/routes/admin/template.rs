@@ -0,0 +1,122 @@
+//! Card templates.
+//!
+//! See [`crate::template`].
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use http::{HeaderMap, header};
+
+use nymph_model::{
+    request::{admin::CreateTemplateQuery, card::ImportCardsQuery},
+    response::card::ImportCardsResponse,
+    template::Template,
+};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppQuery, AppState},
+    auth::Authentication,
+    import::ImportFormat,
+    routes::card::import::import_rows,
+    template,
+};
+
+/// Lists all card templates.
+#[debug_handler]
+pub async fn list(
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<AppJson<Vec<Template>>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    Ok(AppJson(template::list(&state.db).await?))
+}
+
+/// Creates a card template from a file.
+///
+/// The file is read like a bulk import, with its format picked from the
+/// request's content type, but any row that cannot be read fails the whole
+/// request.
+#[debug_handler]
+pub async fn create(
+    State(state): State<AppState>,
+    AppQuery(query): AppQuery<CreateTemplateQuery>,
+    auth: Authentication,
+    headers: HeaderMap,
+    body: String,
+) -> Result<AppJson<Template>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let format = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(AppErrorKind::MissingContentType)
+        .map_err(AppError::from)
+        .and_then(ImportFormat::from_mime)?;
+
+    let rows = format.parse(&body)?;
+
+    let template = template::save(
+        &state.db,
+        &query.name,
+        query.description.as_deref(),
+        rows,
+        false,
+    )
+    .await?;
+
+    Ok(AppJson(template))
+}
+
+/// Deletes a card template.
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    Path((id,)): Path<(i32,)>,
+    auth: Authentication,
+) -> Result<AppJson<Template>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    Ok(AppJson(template::delete(&state.db, id).await?))
+}
+
+/// Copies a template's cards into a guild.
+///
+/// Cards are imported like a bulk import, so cards the guild already has are
+/// skipped and reported, and `?dry_run=true` only returns the report.
+#[debug_handler]
+pub async fn instantiate(
+    State(state): State<AppState>,
+    Path((guild_id, id)): Path<(i64, i32)>,
+    AppQuery(query): AppQuery<ImportCardsQuery>,
+    auth: Authentication,
+) -> Result<AppJson<ImportCardsResponse>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let template = template::get_template(&state.db, id).await?;
+    let rows = template::get_rows(&state.db, id).await?;
+
+    let response = import_rows(&state, guild_id, auth.id, rows, query.dry_run).await?;
+
+    if !response.dry_run {
+        tracing::info!(
+            guild_id,
+            id,
+            name = template.name,
+            imported = response.imported,
+            "copied card template"
+        );
+    }
+
+    Ok(AppJson(response))
+}
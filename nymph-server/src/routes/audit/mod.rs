@@ -0,0 +1,134 @@
+//! Guild audit logs.
+//!
+//! Every dispatched event is copied to the audit log, which is kept apart
+//! from the event log so entries outlive its replay window. See
+//! [`WorkerConfig::audit_log_retention`](crate::config::WorkerConfig::audit_log_retention).
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use nymph_model::{
+    Id,
+    dispatch::Envelope,
+    request::audit::AuditLogQuery,
+    response::audit::{AuditEntry, AuditLogResponse, AuditUser},
+    user::User,
+};
+
+use sqlx::{FromRow, types::Json};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppQuery, AppState},
+    auth::Authentication,
+    request::validate::{Validator as _, ValidatorExt as _, value},
+};
+
+/// The most entries a single page returns.
+pub const MAX_COUNT: u32 = 100;
+
+/// Lists a guild's audit log, newest first.
+#[debug_handler]
+pub async fn list(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    AppQuery(query): AppQuery<AuditLogQuery>,
+    auth: Authentication,
+) -> Result<AppJson<AuditLogResponse>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    #[derive(FromRow)]
+    struct EntryResult {
+        seq: i64,
+        actor_id: Option<i32>,
+        payload: Json<Envelope>,
+    }
+
+    #[derive(FromRow)]
+    struct UserResult {
+        id: i32,
+        display_name: String,
+        discord_id: Option<i64>,
+    }
+
+    let count = value("count", query.count.unwrap_or(25))
+        .in_range(1..=MAX_COUNT)
+        .validate()?;
+
+    let entries = sqlx::query_as::<_, EntryResult>(
+        r#"
+        SELECT seq, actor_id, payload
+        FROM audit_log
+        WHERE
+            guild_id = $1
+            AND ($2 IS NULL OR card_id = $2)
+            AND (
+                $3 IS NULL
+                OR actor_id = $3
+                OR json_extract(payload, '$.data.user_id') = $3
+                OR json_extract(payload, '$.data.from_id') = $3
+                OR json_extract(payload, '$.data.to_id') = $3
+                OR json_extract(payload, '$.data.report.reporter.id') = $3
+            )
+            AND ($4 IS NULL OR seq < $4)
+        ORDER BY seq DESC
+        LIMIT $5
+        "#,
+    )
+    .bind(guild_id)
+    .bind(query.card_id)
+    .bind(query.user_id)
+    .bind(query.before.map(|before| before as i64))
+    .bind(count)
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|entry| {
+        let Json(envelope) = entry.payload;
+
+        AuditEntry {
+            seq: entry.seq as u64,
+            timestamp: envelope.timestamp,
+            actor_id: entry.actor_id,
+            event: envelope.event,
+        }
+    })
+    .collect::<Vec<_>>();
+
+    let mut user_ids = entries
+        .iter()
+        .flat_map(|entry| entry.actor_id.into_iter().chain(entry.event.user_ids()))
+        .collect::<Vec<_>>();
+    user_ids.sort_unstable();
+    user_ids.dedup();
+
+    let users = sqlx::query_as::<_, UserResult>(
+        r#"
+        SELECT u.id, u.display_name, da.discord_id
+        FROM
+            user u
+        LEFT OUTER JOIN
+            discord_auth AS da
+            ON da.user_id = u.id
+        WHERE u.id IN (SELECT value FROM json_each($1))
+        ORDER BY u.id
+        "#,
+    )
+    .bind(Json(&user_ids))
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|user| AuditUser {
+        user: User {
+            id: user.id,
+            display_name: user.display_name,
+        },
+        discord_id: user.discord_id.and_then(|id| Id::new(id as u64)),
+    })
+    .collect();
+
+    Ok(AppJson(AuditLogResponse { entries, users }))
+}
@@ -0,0 +1,325 @@
+//! Bulk card imports.
+
+use std::collections::{HashMap, HashSet};
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use chrono::Utc;
+
+use http::{HeaderMap, header};
+
+use nymph_model::{
+    request::card::ImportCardsQuery,
+    response::card::{ImportCardsResponse, ImportRowReport, ImportStatus},
+};
+
+use sqlx::SqliteConnection;
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppQuery, AppState},
+    auth::Authentication,
+    import::{ImportFormat, ImportRow, ImportedCard},
+    lint,
+    routes::guild::get_name_rules,
+};
+
+/// Imports cards in bulk from a file.
+///
+/// The format of the file is picked from the request's content type. Rows
+/// that cannot be read, or name a card that already exists, are reported and
+/// skipped; the rest are imported in a single transaction.
+///
+/// With `?dry_run=true`, the file is fully validated and the report returned
+/// without importing anything.
+#[debug_handler]
+pub async fn import(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    AppQuery(query): AppQuery<ImportCardsQuery>,
+    auth: Authentication,
+    headers: HeaderMap,
+    body: String,
+) -> Result<AppJson<ImportCardsResponse>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let format = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .ok_or(AppErrorKind::MissingContentType)
+        .map_err(AppError::from)
+        .and_then(ImportFormat::from_mime)?;
+
+    let rows = format.parse(&body)?;
+
+    let response = import_rows(&state, guild_id, auth.id, rows, query.dry_run).await?;
+
+    if !response.dry_run {
+        tracing::info!(
+            guild_id,
+            imported = response.imported,
+            ?format,
+            "imported cards in bulk"
+        );
+    }
+
+    Ok(AppJson(response))
+}
+
+/// Validates rows against a guild, then imports the valid ones in a single
+/// transaction, attributing them to `user_id`.
+///
+/// If `dry_run` is set, the report is returned without importing anything.
+pub async fn import_rows(
+    state: &AppState,
+    guild_id: i64,
+    user_id: i32,
+    rows: Vec<ImportRow>,
+    dry_run: bool,
+) -> Result<ImportCardsResponse, AppError> {
+    let mut tx = state.db.begin().await?;
+
+    let (cards, reports) = validate(&mut tx, guild_id, rows).await?;
+    let imported = cards.len() as u32;
+
+    if dry_run {
+        // nothing has been written, but be explicit about it
+        tx.rollback().await?;
+
+        return Ok(ImportCardsResponse {
+            imported,
+            dry_run: true,
+            rows: reports,
+        });
+    }
+
+    let now = Utc::now();
+
+    for card in cards.iter() {
+        let (id,) = sqlx::query_as::<_, (i32,)>(
+            r#"
+            INSERT INTO card (
+                guild_id, name, category_name, content,
+                created_by, last_edited_by, inserted_at, updated_at
+            )
+            VALUES ($1, $2, $3, '', $4, $4, $5, $5)
+            RETURNING id
+            "#,
+        )
+        .bind(guild_id)
+        .bind(&card.name)
+        .bind(card.category_name.as_ref())
+        .bind(user_id)
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        state.content.write(&mut tx, id, &card.content).await?;
+    }
+
+    // link upgrades once every card in the file exists
+    for card in cards.iter() {
+        let Some(previous) = card.previous.as_ref() else {
+            continue;
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE card
+            SET previous_id = (
+                SELECT id FROM card WHERE guild_id = $1 AND name = $2
+            )
+            WHERE guild_id = $1 AND name = $3
+            "#,
+        )
+        .bind(guild_id)
+        .bind(previous)
+        .bind(&card.name)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(ImportCardsResponse {
+        imported,
+        dry_run: false,
+        rows: reports,
+    })
+}
+
+/// Validates the rows of an import file against a guild.
+///
+/// Returns the cards that can be imported, along with a report for every row.
+async fn validate(
+    db: &mut SqliteConnection,
+    guild_id: i64,
+    rows: Vec<ImportRow>,
+) -> Result<(Vec<ImportedCard>, Vec<ImportRowReport>), AppError> {
+    let rules = lint::get_rules(&mut *db, guild_id).await?;
+    let name_rules = get_name_rules(&mut *db, guild_id).await?;
+
+    let existing = sqlx::query_as::<_, (String, Option<String>)>(
+        r#"
+        SELECT name, category_name
+        FROM card
+        WHERE guild_id = $1
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(&mut *db)
+    .await?;
+
+    let categories = existing
+        .iter()
+        .filter_map(|(_, category)| category.clone())
+        .collect::<HashSet<_>>();
+    let existing = existing
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect::<HashSet<_>>();
+
+    let mut reports = Vec::with_capacity(rows.len());
+    let mut accepted = HashMap::<String, (usize, ImportedCard)>::new();
+
+    for row in rows {
+        let card = match row.card {
+            Ok(card) => card,
+            Err(err) => {
+                reports.push(ImportRowReport {
+                    row: row.row,
+                    name: err.name,
+                    status: ImportStatus::Failed,
+                    message: Some(err.message),
+                    warnings: Vec::new(),
+                    violations: Vec::new(),
+                });
+                continue;
+            }
+        };
+
+        if existing.contains(&card.name) || accepted.contains_key(&card.name) {
+            reports.push(ImportRowReport {
+                row: row.row,
+                message: Some(format!("A card named `{}` already exists.", card.name)),
+                name: Some(card.name),
+                status: ImportStatus::Skipped,
+                warnings: Vec::new(),
+                violations: Vec::new(),
+            });
+            continue;
+        }
+
+        if let Err(err) = super::card_name(&card.name, &name_rules) {
+            reports.push(ImportRowReport {
+                row: row.row,
+                name: Some(card.name),
+                status: ImportStatus::Failed,
+                message: Some(err.to_string()),
+                warnings: Vec::new(),
+                violations: Vec::new(),
+            });
+            continue;
+        }
+
+        let violations = lint::check(&rules, &card.content);
+
+        if !violations.is_empty() {
+            reports.push(ImportRowReport {
+                row: row.row,
+                name: Some(card.name),
+                status: ImportStatus::Failed,
+                message: Some(String::from("Card content breaks the guild's lint rules.")),
+                warnings: Vec::new(),
+                violations,
+            });
+            continue;
+        }
+
+        let mut warnings = Vec::new();
+
+        if let Some(category) = card.category_name.as_ref()
+            && !categories.contains(category)
+        {
+            warnings.push(format!(
+                "Category `{}` is not used by any existing card.",
+                category
+            ));
+        }
+
+        accepted.insert(card.name.clone(), (reports.len(), card.clone()));
+        reports.push(ImportRowReport {
+            row: row.row,
+            name: Some(card.name),
+            status: ImportStatus::Imported,
+            message: None,
+            warnings,
+            violations: Vec::new(),
+        });
+    }
+
+    // reject upgrades of cards that will not exist, repeating until no more
+    // cards are rejected, since rejecting a card orphans its upgrades
+    loop {
+        let rejected = accepted
+            .values()
+            .filter_map(|(index, card)| {
+                let previous = card.previous.as_ref()?;
+
+                if previous == &card.name || upgrade_cycle(&accepted, &card.name) {
+                    Some((*index, card.name.clone(), "Card upgrades form a cycle.".into()))
+                } else if !existing.contains(previous) && !accepted.contains_key(previous) {
+                    Some((
+                        *index,
+                        card.name.clone(),
+                        format!("Card `{}` does not exist or was not imported.", previous),
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if rejected.is_empty() {
+            break;
+        }
+
+        for (index, name, message) in rejected {
+            accepted.remove(&name);
+
+            let report = &mut reports[index];
+            report.status = ImportStatus::Failed;
+            report.message = Some(message);
+        }
+    }
+
+    // keep file order
+    let mut cards = accepted.into_values().collect::<Vec<_>>();
+    cards.sort_by_key(|(index, _)| *index);
+
+    Ok((cards.into_iter().map(|(_, card)| card).collect(), reports))
+}
+
+/// Checks if following a card's upgrade chain through the accepted cards
+/// leads back to the card.
+fn upgrade_cycle(accepted: &HashMap<String, (usize, ImportedCard)>, name: &str) -> bool {
+    let mut current = name;
+
+    // a chain longer than the amount of cards must have looped
+    for _ in 0..accepted.len() {
+        match accepted
+            .get(current)
+            .and_then(|(_, card)| card.previous.as_deref())
+        {
+            Some(previous) if previous == name => return true,
+            Some(previous) => current = previous,
+            None => return false,
+        }
+    }
+
+    false
+}
@@ -1,23 +1,54 @@
 //! Endpoints to manage what cards a user owns.
 
 use axum::{
+    body::Body,
     debug_handler,
     extract::{Path, State},
+    response::{IntoResponse as _, Response},
 };
 
+use futures_util::{StreamExt as _, stream};
+
+use http::header;
+
 use nymph_model::{
     card::Card,
-    request::card::inventory::{GrantRequest, ListInventoryQuery},
+    dispatch::{CardOwnership, CardTransfer, Event, Roll},
+    request::{
+        card::inventory::{
+            ExportFormat, ExportInventoryQuery, GrantRequest, ListInventoryQuery, ListOwnersQuery,
+            ReactionRequest, TradeInRequest, TransferRequest,
+        },
+        user::ProgressQuery,
+    },
+    response::{
+        Paginated,
+        card::{CardOwner, TradeInResponse, TransferResponse},
+        user::{CategoryProgress, ProgressResponse},
+    },
+    trade_in::TradeInReward,
 };
 
-use sqlx::{Executor, Sqlite, sqlite::SqliteQueryResult};
+use chrono::{NaiveDateTime, Utc};
+
+use sqlx::{Executor, FromRow, Sqlite, types::Json};
 
-use super::CardResult;
+use tokio::sync::mpsc;
 
 use crate::{
     app::{AppError, AppErrorKind, AppJson, AppQuery, AppState, Payload},
     auth::Authentication,
-    routes::{Pagination, card::get_card},
+    dispatch,
+    request::validate::{Validator as _, ValidatorExt as _, value},
+    roll,
+    routes::{
+        Pagination,
+        card::{get_card, prerequisites::check_prerequisites, redact_card},
+        csv_cell,
+        event::upcoming_event,
+        guild::get_trade_in_rules,
+    },
+    store::InventoryFilter,
 };
 
 /// Lists all cards belonging to a user.
@@ -27,61 +58,394 @@ pub async fn list(
     AppQuery(query): AppQuery<ListInventoryQuery>,
     State(state): State<AppState>,
     auth: Authentication,
-) -> Result<AppJson<Vec<Card>>, AppError> {
-    // TODO: finer grained permissions
-    // if this is the authorized user, they can always list their own cards
-    if auth.id == user_id {
+) -> Result<AppJson<Paginated<Card>>, AppError> {
+    // users may only list their own cards
+    if auth.id != user_id && !auth.managed {
         return Err(AppErrorKind::InsufficientPermissions.into());
     }
 
-    let results = if let Some(guild_id) = query.guild_id {
-        sqlx::query_as::<_, CardResult>(
-            r#"
-            SELECT
-                c.id, c.guild_id, c.name, c.category_name, c.content,
-                c.visibility, c.inserted_at, c.updated_at
-            FROM
-                card c, ownership o
-            WHERE
-                o.card_id = c.id
-                AND o.owner_id = $1
-                AND c.guild_id = $2
-            "#,
-        )
-        .bind(auth.id)
-        .bind(guild_id.get() as i64)
-        .fetch_all(&state.db)
+    let filter = InventoryFilter {
+        owner_id: user_id,
+        guild_id: query.guild_id.map(|id| id.get() as i64),
+        favorites: query.favorites.unwrap_or(false),
+    };
+
+    let total = state.ownership.count_inventory(&filter).await?;
+
+    let page = Pagination::default().limit(25).paginate(
+        total as i64,
+        query.page.unwrap_or(1),
+        query.count.unwrap_or(25),
+    )?;
+
+    let mut results = state
+        .ownership
+        .inventory(&filter, page.limit as u64, page.offset as u64)
         .await?
-    } else {
-        sqlx::query_as::<_, CardResult>(
-            r#"
-            SELECT
-                c.id, c.guild_id, c.name, c.category_name, c.content,
-                c.visibility, c.inserted_at, c.updated_at
-            FROM
-                card c, ownership o
-            WHERE
-                o.card_id = c.id
-                AND o.owner_id = $1
-            "#,
+        .into_iter()
+        .map(|card| redact_card(card, &auth))
+        .collect::<Vec<_>>();
+
+    state.content.load(&state.db, &mut results).await?;
+
+    Ok(AppJson(page.wrap(results)))
+}
+
+/// Exports the cards a user owns.
+///
+/// Each row has a card's name, category, rarity, how many copies the user
+/// owns and when they were first granted the card, if known. Rows are written
+/// as they come from the database, so large collections are never buffered
+/// whole. An error midway cuts the export short.
+#[debug_handler]
+pub async fn export(
+    Path((user_id,)): Path<(i32,)>,
+    AppQuery(query): AppQuery<ExportInventoryQuery>,
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<Response, AppError> {
+    // users may only export their own cards
+    if auth.id != user_id && !auth.managed {
+        return Err(AppErrorKind::InsufficientPermissions.into());
+    }
+
+    let guild_id = query.guild_id.map(|id| id.get() as i64);
+
+    // csv is the only format so far
+    let ExportFormat::Csv = query.format.unwrap_or_default();
+
+    let (tx, rx) = mpsc::channel::<Result<Vec<u8>, anyhow::Error>>(32);
+
+    tokio::spawn(async move {
+        let mut rows =
+            sqlx::query_as::<_, (String, Option<String>, String, i64, Option<NaiveDateTime>)>(
+                r#"
+                SELECT c.name, c.category_name, c.rarity, o.quantity, o.granted_at
+                FROM
+                    card c, ownership o
+                WHERE
+                    o.card_id = c.id
+                    AND o.owner_id = $1
+                    AND o.quantity > 0
+                    AND ($2 IS NULL OR c.guild_id = $2)
+                ORDER BY c.name
+                "#,
+            )
+            .bind(user_id)
+            .bind(guild_id)
+            .fetch(&state.db);
+
+        let header = csv_record(["name", "category", "rarity", "quantity", "granted_at"]);
+
+        if tx.send(Ok(header)).await.is_err() {
+            return;
+        }
+
+        while let Some(row) = rows.next().await {
+            let record = row.map_err(anyhow::Error::from).map(
+                |(name, category, rarity, quantity, granted_at)| {
+                    csv_record([
+                        name,
+                        category.unwrap_or_default(),
+                        rarity,
+                        quantity.to_string(),
+                        granted_at
+                            .map(|granted_at| granted_at.format("%Y-%m-%d %H:%M:%S").to_string())
+                            .unwrap_or_default(),
+                    ])
+                },
+            );
+
+            if let Err(err) = &record {
+                tracing::error!(?err, "failed to export inventory");
+            }
+
+            let failed = record.is_err();
+
+            // stop early if the client went away
+            if tx.send(record).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    // compression polls the body again after it ends, which `unfold` panics on
+    let body = Body::from_stream(
+        stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|record| (record, rx))
+        })
+        .fuse(),
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"inventory.csv\"",
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Writes a single CSV record, with its line ending.
+///
+/// Cells that look like formulas are escaped.
+fn csv_record<I>(record: I) -> Vec<u8>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut writer = ::csv::Writer::from_writer(Vec::new());
+
+    // writing to memory never fails
+    writer
+        .write_record(
+            record
+                .into_iter()
+                .map(|cell| csv_cell(cell.as_ref()).into_owned()),
         )
-        .bind(auth.id)
-        .fetch_all(&state.db)
+        .expect("csv written to memory");
+    writer.into_inner().expect("csv written to memory")
+}
+
+/// Adds a card to a user's favorites.
+#[debug_handler]
+pub async fn favorite(
+    Path((user_id, card_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<AppJson<Card>, AppError> {
+    // users may only pick their own favorites
+    if auth.id != user_id && !auth.managed {
+        return Err(AppErrorKind::InsufficientPermissions.into());
+    }
+
+    // only cards the user can see may be favorited
+    let card = get_card(&state, card_id, &auth).await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO favorite (user_id, card_id, inserted_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_id, card_id) DO NOTHING
+        "#,
+    )
+    .bind(user_id)
+    .bind(card.id)
+    .bind(Utc::now())
+    .execute(&state.db)
+    .await?;
+
+    Ok(AppJson(card))
+}
+
+/// Removes a card from a user's favorites.
+#[debug_handler]
+pub async fn unfavorite(
+    Path((user_id, card_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<AppJson<Card>, AppError> {
+    if auth.id != user_id && !auth.managed {
+        return Err(AppErrorKind::InsufficientPermissions.into());
+    }
+
+    let card = get_card(&state, card_id, &auth).await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM favorite
+        WHERE user_id = $1 AND card_id = $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(card.id)
+    .execute(&state.db)
+    .await?;
+
+    Ok(AppJson(card))
+}
+
+/// Rates a card the user owns.
+#[debug_handler]
+pub async fn react(
+    Path((user_id, card_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    auth: Authentication,
+    Payload(request): Payload<ReactionRequest>,
+) -> Result<AppJson<Card>, AppError> {
+    // users may only rate cards themselves
+    if auth.id != user_id && !auth.managed {
+        return Err(AppErrorKind::InsufficientPermissions.into());
+    }
+
+    let rating = value("rating", request.rating).in_range(1..=5).validate()?;
+
+    let card = get_card(&state, card_id, &auth).await?;
+
+    // only owners may rate a card
+    let res = sqlx::query(
+        r#"
+        INSERT INTO card_reaction (user_id, card_id, rating, inserted_at, updated_at)
+        SELECT $1, $2, $3, $4, $4
+        FROM ownership o
+        WHERE o.owner_id = $1 AND o.card_id = $2 AND o.quantity > 0
+        ON CONFLICT (user_id, card_id) DO UPDATE
+        SET rating = excluded.rating, updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(card.id)
+    .bind(rating)
+    .bind(Utc::now())
+    .execute(&state.db)
+    .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(
+            AppError::from(AppErrorKind::Forbidden).with_message(format!(
+                "Card `{}` can only be rated by its owners.",
+                card.name
+            )),
+        );
+    }
+
+    // the card's ratings changed
+    Ok(AppJson(get_card(&state, card_id, &auth).await?))
+}
+
+/// Removes a user's rating of a card.
+#[debug_handler]
+pub async fn unreact(
+    Path((user_id, card_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<AppJson<Card>, AppError> {
+    if auth.id != user_id && !auth.managed {
+        return Err(AppErrorKind::InsufficientPermissions.into());
+    }
+
+    let card = get_card(&state, card_id, &auth).await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM card_reaction
+        WHERE user_id = $1 AND card_id = $2
+        "#,
+    )
+    .bind(user_id)
+    .bind(card.id)
+    .execute(&state.db)
+    .await?;
+
+    Ok(AppJson(get_card(&state, card_id, &auth).await?))
+}
+
+/// Counts how many cards of a guild a user has collected.
+#[debug_handler]
+pub async fn progress(
+    Path((user_id,)): Path<(i32,)>,
+    AppQuery(query): AppQuery<ProgressQuery>,
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<AppJson<ProgressResponse>, AppError> {
+    // users may only see their own progress
+    if auth.id != user_id && !auth.managed {
+        return Err(AppErrorKind::InsufficientPermissions.into());
+    }
+
+    #[derive(FromRow)]
+    struct CategoryResult {
+        category_name: Option<String>,
+        owned: i64,
+        total: i64,
+    }
+
+    // private cards and archived cards only count once they are owned
+    let categories = sqlx::query_as::<_, CategoryResult>(
+        r#"
+        SELECT
+            c.category_name,
+            SUM(COALESCE(o.quantity, 0) > 0) AS owned,
+            COUNT(*) AS total
+        FROM
+            card c
+        LEFT OUTER JOIN
+            ownership AS o
+            ON o.card_id = c.id AND o.owner_id = $1
+        WHERE
+            c.guild_id = $2
+            AND (
+                COALESCE(o.quantity, 0) > 0
+                OR (c.archived_at IS NULL AND c.visibility != 'private')
+            )
+        GROUP BY c.category_name
+        ORDER BY c.category_name
+        "#,
+    )
+    .bind(user_id)
+    .bind(query.guild_id.get() as i64)
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|category| CategoryProgress {
+        category_name: category.category_name,
+        owned: category.owned as u32,
+        total: category.total as u32,
+    })
+    .collect::<Vec<_>>();
+
+    Ok(AppJson(ProgressResponse {
+        owned: categories.iter().map(|category| category.owned).sum(),
+        total: categories.iter().map(|category| category.total).sum(),
+        categories,
+    }))
+}
+
+/// Lists all users that own a card.
+#[debug_handler]
+pub async fn owners(
+    Path((guild_id, id)): Path<(i64, i32)>,
+    AppQuery(query): AppQuery<ListOwnersQuery>,
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<AppJson<Paginated<CardOwner>>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    // owners are only listed for the guild's own cards
+    let card = state
+        .cards
+        .get(auth.id, id)
         .await?
-    };
+        .filter(|card| card.guild_id.get() as i64 == guild_id);
+
+    if card.is_none() {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The card of id {} does not exist.", id)));
+    }
+
+    let total = state.ownership.count_owners(id).await?;
+
+    let page = Pagination::default().limit(25).paginate(
+        total as i64,
+        query.page.unwrap_or(1),
+        query.count.unwrap_or(25),
+    )?;
 
-    let results: Vec<_> = results.into_iter().map(|card| Card::from(card)).collect();
+    let results = state
+        .ownership
+        .owners(id, page.limit as u64, page.offset as u64)
+        .await?;
 
-    // Paginate cards
-    Ok(AppJson(
-        Pagination::new(results)
-            .limit(25)
-            .paginate(query.page.unwrap_or(1), query.count.unwrap_or(25))?
-            .to_owned(),
-    ))
+    Ok(AppJson(page.wrap(results)))
 }
 
-/// Adds a card to a user's inventory.
+/// Adds a copy of a card to a user's inventory.
 #[debug_handler]
 pub async fn grant(
     Path((user_id,)): Path<(i32,)>,
@@ -89,72 +453,445 @@ pub async fn grant(
     auth: Authentication,
     Payload(request): Payload<GrantRequest>,
 ) -> Result<AppJson<Card>, AppError> {
-    // TODO: finer grained permissions
+    // grant policies are checked by the managed client, which knows the
+    // granting member's roles
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
 
-    let res = update_ownership(&state.db, user_id, request.card_id, true).await?;
     let card = get_card(&state, request.card_id, &auth).await?;
 
-    if res.rows_affected() > 0 {
-        Ok(AppJson(card))
-    } else {
-        Err(
+    // archived cards are out of circulation
+    if card.archived_at.is_some() {
+        return Err(
             AppError::from(AppErrorKind::InvalidTransfer(card.name.to_owned())).with_message(
                 format!(
-                    "Card `{}` cannot be granted because user already owns that card.",
+                    "Card `{}` cannot be granted because it has been archived.",
                     &card.name
                 ),
             ),
-        )
+        );
     }
+
+    // event cards are out of circulation until their event starts
+    if let Some(event) = upcoming_event(&state.db, card.id).await? {
+        return Err(
+            AppError::from(AppErrorKind::InvalidTransfer(card.name.to_owned())).with_message(
+                format!(
+                    "Card `{}` cannot be granted until the event `{}` starts.",
+                    &card.name, event
+                ),
+            ),
+        );
+    }
+
+    // the user must have collected what the card builds on
+    check_prerequisites(&state, user_id, &card).await?;
+
+    let quantity = state.ownership.add(user_id, card.id).await?;
+
+    let card = Card {
+        quantity: Some(quantity),
+        ..card
+    };
+
+    dispatch::emit(
+        &state,
+        auth.id,
+        Event::CardGranted(CardOwnership {
+            user_id,
+            card: card.clone(),
+            roll: None,
+        }),
+    )
+    .await?;
+
+    Ok(AppJson(card))
 }
 
-/// Removes a card from a user's inventory.
+/// Removes a copy of a card from a user's inventory.
 #[debug_handler]
 pub async fn revoke(
     Path((user_id, card_id)): Path<(i32, i32)>,
     State(state): State<AppState>,
     auth: Authentication,
 ) -> Result<AppJson<Card>, AppError> {
-    // TODO: finer grained permissions
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
 
-    let res = update_ownership(&state.db, user_id, card_id, false).await?;
     let card = get_card(&state, card_id, &auth).await?;
 
-    if res.rows_affected() > 0 {
-        Ok(AppJson(card))
-    } else {
-        Err(
+    let Some(quantity) = state.ownership.remove(user_id, card.id).await? else {
+        return Err(
             AppError::from(AppErrorKind::InvalidTransfer(card.name.to_owned())).with_message(
                 format!(
                     "Card `{}` cannot be revoked because user does not own that card.",
                     &card.name
                 ),
             ),
+        );
+    };
+
+    let card = Card {
+        quantity: Some(quantity),
+        ..card
+    };
+
+    dispatch::emit(
+        &state,
+        auth.id,
+        Event::CardRevoked(CardOwnership {
+            user_id,
+            card: card.clone(),
+            roll: None,
+        }),
+    )
+    .await?;
+
+    Ok(AppJson(card))
+}
+
+/// Moves a copy of a card from one user's inventory to another's.
+#[debug_handler]
+pub async fn transfer(
+    Path((from_id, card_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    auth: Authentication,
+    Payload(request): Payload<TransferRequest>,
+) -> Result<AppJson<TransferResponse>, AppError> {
+    // users may only give away their own cards
+    if auth.id != from_id && !auth.managed {
+        return Err(AppErrorKind::InsufficientPermissions.into());
+    }
+
+    let card = get_card(&state, card_id, &auth).await?;
+
+    if request.to_id == from_id {
+        return Err(
+            AppError::from(AppErrorKind::InvalidTransfer(card.name.to_owned())).with_message(
+                format!(
+                    "Card `{}` cannot be transferred to the user that owns it.",
+                    &card.name
+                ),
+            ),
+        );
+    }
+
+    // archived cards are out of circulation
+    if card.archived_at.is_some() {
+        return Err(
+            AppError::from(AppErrorKind::InvalidTransfer(card.name.to_owned())).with_message(
+                format!(
+                    "Card `{}` cannot be transferred because it has been archived.",
+                    &card.name
+                ),
+            ),
+        );
+    }
+
+    if !state.users.exists(request.to_id).await? {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The user of id {} does not exist.", request.to_id)));
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    let Some(from_quantity) = remove_card(&mut *tx, from_id, card.id).await? else {
+        return Err(
+            AppError::from(AppErrorKind::InvalidTransfer(card.name.to_owned())).with_message(
+                format!(
+                    "Card `{}` cannot be transferred because user does not own that card.",
+                    &card.name
+                ),
+            ),
+        );
+    };
+    let to_quantity = add_card(&mut *tx, request.to_id, card.id).await?;
+
+    tx.commit().await?;
+
+    tracing::info!(from_id, to_id = request.to_id, card_id, "transferred card");
+
+    dispatch::emit(
+        &state,
+        auth.id,
+        Event::CardTransferred(CardTransfer {
+            from_id,
+            to_id: request.to_id,
+            card: card.clone(),
+            from_quantity,
+            to_quantity,
+        }),
+    )
+    .await?;
+
+    Ok(AppJson(TransferResponse {
+        card,
+        from_quantity,
+        to_quantity,
+    }))
+}
+
+/// Trades in duplicates of a card for the guild's trade-in reward.
+///
+/// The user always keeps at least one copy of the card.
+#[debug_handler]
+pub async fn trade_in(
+    Path((user_id, card_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    auth: Authentication,
+    Payload(request): Payload<TradeInRequest>,
+) -> Result<AppJson<TradeInResponse>, AppError> {
+    // users may only trade in their own cards
+    if auth.id != user_id && !auth.managed {
+        return Err(AppErrorKind::InsufficientPermissions.into());
+    }
+
+    let card = get_card(&state, card_id, &auth).await?;
+    let guild_id = card.guild_id.get() as i64;
+
+    let Some(reward) = get_trade_in_rules(&state.db, guild_id).await?.reward else {
+        return Err(AppError::from(AppErrorKind::Forbidden)
+            .with_message("This guild does not take trade-ins."));
+    };
+
+    let count = value("count", request.count).in_range(1..).validate()?;
+
+    let rolls = match reward {
+        TradeInReward::Roll { copies } if count % copies != 0 => {
+            return Err(
+                AppError::from(AppErrorKind::FieldOutOfRange("count".into()))
+                    .with_message(format!("Duplicates are traded in {} at a time.", copies)),
+            );
+        }
+        TradeInReward::Roll { copies } => count / copies,
+        TradeInReward::Currency { .. } => 0,
+    };
+
+    let mut tx = state.db.begin().await?;
+
+    let quantity = sqlx::query_as::<_, (i64,)>(
+        r#"
+        UPDATE ownership
+        SET quantity = quantity - $3
+        WHERE
+            owner_id = $1
+            AND card_id = $2
+            AND quantity > $3
+        RETURNING quantity
+        "#,
+    )
+    .bind(user_id)
+    .bind(card.id)
+    .bind(count)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((quantity,)) = quantity else {
+        return Err(
+            AppError::from(AppErrorKind::InvalidTransfer(card.name.to_owned())).with_message(
+                format!(
+                    "Card `{}` cannot be traded in because user does not own {} duplicate(s) of it.",
+                    &card.name, count
+                ),
+            ),
+        );
+    };
+
+    let balance = match reward {
+        TradeInReward::Currency { per_copy } => {
+            let (balance,) = sqlx::query_as::<_, (i64,)>(
+                r#"
+                INSERT INTO wallet (user_id, guild_id, balance)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (user_id, guild_id) DO UPDATE
+                SET balance = balance + excluded.balance
+                RETURNING balance
+                "#,
+            )
+            .bind(user_id)
+            .bind(guild_id)
+            .bind(count as i64 * per_copy as i64)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            // balances only ever grow, so they are never negative
+            Some(balance as u64)
+        }
+        TradeInReward::Roll { .. } => None,
+    };
+
+    let seed = roll::seed();
+    let mut rolled = Vec::with_capacity(rolls as usize);
+
+    if rolls > 0 {
+        // rolls only land on cards anyone could be granted; the pool is
+        // ordered, so the roll can be replayed from its seed
+        let pool = sqlx::query_as::<_, (i32,)>(
+            r#"
+            SELECT c.id
+            FROM card c
+            WHERE
+                c.guild_id = $1
+                AND c.archived_at IS NULL
+                AND c.visibility = 'public'
+                AND NOT EXISTS (
+                    SELECT 1 FROM card_prerequisite p
+                    WHERE p.card_id = c.id
+                )
+                AND NOT EXISTS (
+                    SELECT 1 FROM event e, event_card ec
+                    WHERE
+                        ec.event_id = e.id
+                        AND ec.card_id = c.id
+                        AND datetime(e.starts_at) > datetime($2)
+                )
+            ORDER BY c.id
+            "#,
         )
+        .bind(guild_id)
+        .bind(Utc::now())
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|(id,)| id)
+        .collect::<Vec<_>>();
+
+        // dropping the transaction gives the duplicates back
+        if pool.is_empty() {
+            return Err(AppError::from(AppErrorKind::NotFound)
+                .with_message("There are no cards to roll in this guild."));
+        }
+
+        for rolled_id in roll::pick(&seed, &pool, rolls) {
+            let quantity = add_card(&mut *tx, user_id, rolled_id).await?;
+
+            rolled.push((rolled_id, quantity));
+        }
+
+        // the pool is logged once, every grant only refers to it by seed
+        sqlx::query(
+            r#"
+            INSERT INTO roll (seed, guild_id, pool, inserted_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(roll::encode_seed(&seed))
+        .bind(guild_id)
+        .bind(Json(&pool))
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(user_id, card_id, count, "traded in duplicates");
+
+    let card = Card {
+        quantity: Some(quantity as u32),
+        ..card
+    };
+
+    dispatch::emit(
+        &state,
+        auth.id,
+        Event::CardRevoked(CardOwnership {
+            user_id,
+            card: card.clone(),
+            roll: None,
+        }),
+    )
+    .await?;
+
+    let seed = (!rolled.is_empty()).then(|| roll::encode_seed(&seed));
+    let mut rolled_cards = Vec::with_capacity(rolled.len());
+
+    for (index, (rolled_id, quantity)) in rolled.into_iter().enumerate() {
+        let rolled_card = Card {
+            quantity: Some(quantity),
+            ..get_card(&state, rolled_id, &auth).await?
+        };
+
+        dispatch::emit(
+            &state,
+            auth.id,
+            Event::CardGranted(CardOwnership {
+                user_id,
+                card: rolled_card.clone(),
+                roll: seed.clone().map(|seed| Roll {
+                    seed,
+                    index: index as u32,
+                }),
+            }),
+        )
+        .await?;
+
+        rolled_cards.push(rolled_card);
     }
+
+    Ok(AppJson(TradeInResponse {
+        card,
+        balance,
+        rolled: rolled_cards,
+        seed,
+    }))
+}
+
+/// Adds a copy of a card to a user's inventory.
+///
+/// Returns how many copies of the card the user owns afterwards.
+pub async fn add_card<'c, E>(db: E, owner_id: i32, card_id: i32) -> Result<u32, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    sqlx::query_as::<_, (i64,)>(
+        r#"
+        INSERT INTO ownership (owner_id, card_id, quantity, granted_at)
+        VALUES ($1, $2, 1, $3)
+        ON CONFLICT (owner_id, card_id) DO UPDATE
+        SET
+            quantity = quantity + 1,
+            -- a card the user lost every copy of is granted anew
+            granted_at = CASE WHEN quantity = 0 THEN excluded.granted_at ELSE granted_at END
+        RETURNING quantity
+        "#,
+    )
+    .bind(owner_id)
+    .bind(card_id)
+    .bind(Utc::now())
+    .fetch_one(db)
+    .await
+    .map(|(quantity,)| quantity as u32)
 }
 
-async fn update_ownership<'c, E>(
+/// Removes a copy of a card from a user's inventory.
+///
+/// Returns how many copies of the card the user owns afterwards, or `None` if
+/// the user did not own the card to begin with.
+pub async fn remove_card<'c, E>(
     db: E,
     owner_id: i32,
     card_id: i32,
-    owned: bool,
-) -> Result<SqliteQueryResult, sqlx::Error>
+) -> Result<Option<u32>, sqlx::Error>
 where
     E: Executor<'c, Database = Sqlite>,
 {
-    sqlx::query(
+    sqlx::query_as::<_, (i64,)>(
         r#"
-        INSERT INTO ownership (owner_id, card_id, owned)
-        VALUES ($1, $2, $3)
-        ON CONFLICT (owner_id, card_id) DO UPDATE
-        SET owned = $3
-        WHERE NOT owned = $3
+        UPDATE ownership
+        SET quantity = quantity - 1
+        WHERE
+            owner_id = $1
+            AND card_id = $2
+            AND quantity > 0
+        RETURNING quantity
         "#,
     )
     .bind(owner_id)
     .bind(card_id)
-    .bind(owned)
-    .execute(db)
+    .fetch_optional(db)
     .await
+    .map(|row| row.map(|(quantity,)| quantity as u32))
 }
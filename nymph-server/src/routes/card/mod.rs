@@ -1,42 +1,91 @@
 //! Card routes.
 
+pub mod import;
 pub mod inventory;
+pub mod policy;
+pub mod prerequisites;
+pub mod search;
+pub mod season;
+pub mod views;
 
-use std::iter;
+use std::sync::Arc;
 
 use axum::{
+    body::Body,
     debug_handler,
     extract::{Path, State},
+    response::{IntoResponse as _, Response},
 };
 
-use sqlx::FromRow;
+use futures_util::{StreamExt as _, stream};
 
-use chrono::NaiveDateTime;
+use http::{HeaderMap, HeaderValue, StatusCode, header};
+
+use sqlx::{Executor, FromRow, Sqlite, SqlitePool};
+
+use tokio::sync::mpsc;
+
+use chrono::{NaiveDateTime, Utc};
 
 use nymph_model::{
     Id,
-    card::{Card, Visibility},
-    request::card::ListCardsQuery,
+    card::{Author, Card, NameRules, Rarity, Ratings, Visibility, normalize_name},
+    dispatch::Event,
+    request::card::{
+        ArchiveCardsRequest, CreateCardRequest, Expand, FieldSet, ListCardsQuery, LookupCardQuery,
+        ShowCardQuery, SortOrder, UpdateCardRequest,
+    },
+    response::card::ArchiveCardsResponse,
+    user::User,
 };
 
-use textdistance::{Algorithm as _, Levenshtein};
-
 use crate::{
-    app::{AppError, AppErrorKind, AppJson, AppQuery, AppState},
+    app::{
+        AppError, AppErrorKind, AppJson, AppQuery, AppState, Cached, Conditional, Payload, Sparse,
+        revision_matches, revision_of,
+    },
     auth::Authentication,
-    routes::Pagination,
+    dispatch,
+    import::MAX_NAME_LEN,
+    lint,
+    request::validate::{Validator as _, ValidatorExt as _, value},
+    routes::{Pagination, guild::get_name_rules},
+    store::CardFilter,
 };
 
+use search::Search;
+
+/// The media type of newline-delimited JSON.
+const NDJSON: &str = "application/x-ndjson";
+
+/// How many cards a streamed listing reads at a time.
+const STREAM_PAGE: u64 = 100;
+
+/// The longest a phrase searched for in card content may be.
+pub const MAX_CONTENT_SEARCH_LEN: usize = 200;
+
+/// The longest a Unicode card emoji may be, in bytes.
+///
+/// Emojis joined with zero-width joiners can be surprisingly long.
+pub const MAX_EMOJI_LEN: usize = 64;
+
 #[derive(FromRow)]
-struct CardResult {
+pub(crate) struct CardResult {
     id: i32,
     guild_id: i64,
     name: String,
+    emoji: Option<String>,
     category_name: Option<String>,
     #[sqlx(try_from = "String")]
     visibility: Visibility,
+    #[sqlx(try_from = "String")]
+    rarity: Rarity,
     content: String,
-    owned: bool,
+    pub(crate) owned: bool,
+    #[sqlx(default)]
+    pub(crate) quantity: i64,
+    rarity_score: Option<f64>,
+    archived_at: Option<NaiveDateTime>,
     inserted_at: NaiveDateTime,
     updated_at: NaiveDateTime,
 }
@@ -48,12 +97,20 @@ impl From<CardResult> for Card {
             // TODO: maybe not panic when getting arbitrary data?
             guild_id: Id::new(value.guild_id as u64).expect("valid id"),
             name: value.name,
+            emoji: value.emoji,
             category_name: value.category_name,
             content: value.content,
             hidden: Some(!value.owned && value.visibility != Visibility::Public),
             visibility: value.visibility,
+            rarity: value.rarity,
             upgrades: None,
             downgrade: None,
+            quantity: None,
+            rarity_score: value.rarity_score,
+            archived_at: value.archived_at,
+            created_by: None,
+            last_edited_by: None,
+            ratings: None,
             created_at: value.inserted_at,
             updated_at: value.updated_at,
         }
@@ -61,73 +118,206 @@ impl From<CardResult> for Card {
 }
 
 /// Lists all cards in a guilds with optional query params.
+///
+/// Public cards syndicated from other guilds are listed too; see
+/// [`crate::routes::syndication`].
+///
+/// Clients that accept `application/x-ndjson` get every matching card
+/// instead, one per line, with no pagination.
 #[debug_handler]
 pub async fn list(
     AppQuery(query): AppQuery<ListCardsQuery>,
     State(state): State<AppState>,
     Path((guild_id,)): Path<(i64,)>,
     auth: Authentication,
-) -> Result<AppJson<Vec<Card>>, AppError> {
-    let results = if let Some(search) = query.query.as_ref() {
-        sqlx::query_as::<_, CardResult>(
-            r#"
-            SELECT
-                c.id, c.guild_id, c.name, c.category_name, c.content,
-                c.visibility, c.inserted_at, c.updated_at,
-                COALESCE(o.owned, FALSE) AS owned
-            FROM
-                card c
-            LEFT OUTER JOIN
-                ownership AS o
-                ON o.card_id = c.id AND o.owner_id = $1
-            WHERE
-                c.guild_id = $2
-                AND c.name LIKE CONCAT('%', $3, '%')
-            "#,
-        )
-        .bind(auth.id)
-        .bind(guild_id)
-        .bind(&search)
-        .fetch_all(&state.db)
-        .await?
-    } else {
-        sqlx::query_as::<_, CardResult>(
-            r#"
-            SELECT
-                c.id, c.guild_id, c.name, c.category_name, c.content,
-                c.visibility, c.inserted_at, c.updated_at,
-                COALESCE(o.owned, FALSE) AS owned
-            FROM
-                card c
-            LEFT OUTER JOIN
-                ownership AS o
-                ON o.card_id = c.id AND o.owner_id = $1
-            WHERE
-                c.guild_id = $2
-            "#,
-        )
-        .bind(auth.id)
-        .bind(guild_id)
-        .fetch_all(&state.db)
-        .await?
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let fields = card_fields(query.fields.as_ref())?;
+    let expand = query
+        .expand
+        .as_ref()
+        .map(|expand| expand.0.clone())
+        .unwrap_or_default();
+    // filters given as params take precedence over those in the search
+    let parsed = match query.query.as_deref() {
+        Some(search) => Search::parse(search)?,
+        None => Search::default(),
     };
+    // searches are normalized like names, so they match however they were typed
+    let search = parsed.text.as_deref().map(normalize_name);
+    let rarity = query.rarity.or(parsed.rarity);
+    let category = query.category.as_ref().or(parsed.category.as_ref());
+    let owned = query.owned.or(parsed.owned);
+    let visibility = query.visibility.as_ref().or(parsed.visibility.as_ref());
 
-    let results = results.into_iter().map(Card::from);
+    if let Some(filter) = visibility
+        && !filter.is_public()
+        && !auth.managed
+    {
+        return Err(AppError::from(AppErrorKind::Forbidden)
+            .with_message("Only privileged users may filter for cards that are not public."));
+    }
+    let sort = query.sort.unwrap_or_default();
 
-    // TODO: skip hidden results if the user doesn't have permissions
+    // relevance and ratings have a direction of their own
+    if query.order.is_some() {
+        value("sort", sort.to_str())
+            .one_of(["name", "created_at", "updated_at"])
+            .validate()?;
+    }
 
-    let results: Vec<_> = if let Some(search) = query.query.as_ref() {
-        sort_query_results(results, search).collect()
-    } else {
-        results.collect()
+    let content_search = match query
+        .q_content
+        .as_deref()
+        .or(parsed.content.as_deref())
+        .map(str::trim)
+    {
+        Some(search) => {
+            value("q_content", search.len())
+                .in_range(1..=MAX_CONTENT_SEARCH_LEN)
+                .validate()?;
+
+            Some(search.to_owned())
+        }
+        None => None,
+    };
+
+    let filter = CardFilter {
+        viewer_id: auth.id,
+        guild_id,
+        search,
+        rarity,
+        category: category.cloned(),
+        owned,
+        visibility: visibility.map(|filter| filter.0.clone()),
+        content_search,
+        sort,
+        descending: query.order == Some(SortOrder::Desc),
     };
 
-    Ok(AppJson(
-        Pagination::new(results)
-            .limit(25)
-            .paginate(query.page.unwrap_or(1), query.count.unwrap_or(25))?
-            .to_owned(),
-    ))
+    // whole listings may be streamed instead, without pagination
+    if accepts_ndjson(&headers) {
+        return Ok(stream_cards(state, auth, filter, fields, expand));
+    }
+
+    let total = state.cards.count(&filter).await?;
+
+    let page = Pagination::default().limit(25).paginate(
+        total as i64,
+        query.page.unwrap_or(1),
+        query.count.unwrap_or(25),
+    )?;
+
+    let results = state
+        .cards
+        .list(&filter, page.limit as u64, page.offset as u64)
+        .await?;
+
+    let mut cards = Vec::with_capacity(results.len());
+
+    for card in results {
+        let card = redact_card(card, &auth);
+
+        cards.push(expand_card(&state, &auth, card, &expand).await?);
+    }
+
+    if wants_content(fields.as_deref()) {
+        state.content.load(&state.db, &mut cards).await?;
+    }
+
+    let cards = cards
+        .into_iter()
+        .map(|card| Sparse::new(card, fields.clone()))
+        .collect::<Vec<_>>();
+
+    // TODO: skip hidden results if the user doesn't have permissions
+
+    Ok(AppJson(page.wrap(cards)).into_response())
+}
+
+/// Checks if a request asks for newline-delimited JSON.
+fn accepts_ndjson(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| {
+            accept
+                .split(',')
+                .any(|media| media.trim().starts_with(NDJSON))
+        })
+}
+
+/// Streams every card of a listing as newline-delimited JSON.
+///
+/// Cards are read and written a page at a time, so large listings are never
+/// buffered whole. An error midway cuts the response short.
+fn stream_cards(
+    state: AppState,
+    auth: Authentication,
+    filter: CardFilter,
+    fields: Option<Arc<[String]>>,
+    expand: Vec<Expand>,
+) -> Response {
+    let (tx, rx) = mpsc::channel::<Result<Vec<u8>, anyhow::Error>>(32);
+
+    tokio::spawn(async move {
+        let mut offset = 0;
+
+        loop {
+            let cards = match state.cards.list(&filter, STREAM_PAGE, offset).await {
+                Ok(cards) => cards,
+                Err(err) => {
+                    tracing::error!(?err, "failed to stream cards");
+                    let _ = tx.send(Err(err.into())).await;
+                    break;
+                }
+            };
+            let last = (cards.len() as u64) < STREAM_PAGE;
+            offset += STREAM_PAGE;
+
+            for card in cards {
+                let line = async {
+                    let card = redact_card(card, &auth);
+                    let mut card = expand_card(&state, &auth, card, &expand).await?;
+
+                    if wants_content(fields.as_deref()) {
+                        state.content.load_one(&state.db, &mut card).await?;
+                    }
+                    let mut line = serde_json::to_vec(&Sparse::new(card, fields.clone()))?;
+
+                    line.push(b'\n');
+
+                    Ok::<_, anyhow::Error>(line)
+                }
+                .await;
+
+                if let Err(err) = &line {
+                    tracing::error!(?err, "failed to stream cards");
+                }
+
+                let failed = line.is_err();
+
+                // stop early if the client went away
+                if tx.send(line).await.is_err() || failed {
+                    return;
+                }
+            }
+
+            if last {
+                break;
+            }
+        }
+    });
+
+    // compression polls the body again after it ends, which `unfold` panics on
+    let body = Body::from_stream(
+        stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|line| (line, rx))
+        })
+        .fuse(),
+    );
+
+    ([(header::CONTENT_TYPE, NDJSON)], body).into_response()
 }
 
 /// Gets a card by its ID.
@@ -135,42 +325,68 @@ pub async fn list(
 pub async fn show(
     State(state): State<AppState>,
     Path((guild_id, id)): Path<(i64, i32)>,
+    AppQuery(query): AppQuery<ShowCardQuery>,
     auth: Authentication,
-) -> Result<AppJson<Card>, AppError> {
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let fields = card_fields(query.fields.as_ref())?;
+    let expand = query.expand.map(|expand| expand.0).unwrap_or_default();
+
     // fetch main card
-    let card = sqlx::query_as::<_, CardResult>(
-        r#"
-        SELECT
-            c.id, c.guild_id, c.name, c.category_name, c.content, c.visibility,
-            c.inserted_at, c.updated_at, COALESCE(o.owned, FALSE) AS owned
-        FROM
-            card c
-        LEFT OUTER JOIN
-            ownership AS o
-            ON o.card_id = c.id AND o.owner_id = $1
-        WHERE
-            c.id = $3
-            AND c.guild_id = $2
-        "#,
-    )
-    .bind(auth.id)
-    .bind(guild_id)
-    .bind(id)
-    .fetch_optional(&state.db)
-    .await?
-    .map(Card::from);
+    let card = state
+        .cards
+        .find(auth.id, guild_id, id)
+        .await?
+        // archived cards only remain visible to their owners
+        .filter(|found| found.card.archived_at.is_none() || found.owned || auth.managed)
+        .map(|found| found.card);
 
     if let Some(card) = card {
-        // TODO: reveal hidden cards if user has perms
-        let hidden = card.hidden.unwrap_or_default();
+        // privileged callers may always view cards
+        let hidden = card.hidden.unwrap_or_default() && !auth.managed;
 
         match card.visibility.into() {
             Visibility::Hidden if hidden => Err(AppErrorKind::Hidden(card.name).into()),
             Visibility::Private if hidden => Err(AppErrorKind::Forbidden.into()),
             // Public cards are always viewable
-            _ => Ok(AppJson(
-                preload_card(&state, &auth, Card::from(card)).await?,
-            )),
+            _ => {
+                let mut card =
+                    preload_card(&state, &auth, redact_card(card, &auth), &expand).await?;
+
+                if wants_content(fields.as_deref()) {
+                    state.content.load_one(&state.db, &mut card).await?;
+                }
+
+                // managed callers see more of a card than anyone else
+                let max_age = state
+                    .cache
+                    .cards
+                    .filter(|_| !auth.managed && is_shared(&card));
+                let id = card.id;
+                let updated_at = card.updated_at;
+                let revision = revision_of(card.id, updated_at);
+
+                let mut res = Cached::new(
+                    &headers,
+                    Conditional::new(&headers, Sparse::new(card, fields)).revision(revision),
+                    max_age,
+                );
+
+                // expanded cards change without the card's own date moving,
+                // so only the ETag can tell those responses apart
+                if expand.is_empty() {
+                    res = res.last_modified(updated_at);
+                }
+
+                let res = res.into_response();
+
+                // revalidating a cached card is not another view of it
+                if res.status() == StatusCode::OK {
+                    state.views.record(id);
+                }
+
+                Ok(res)
+            }
         }
     } else {
         Err(AppError::from(AppErrorKind::NotFound)
@@ -178,143 +394,716 @@ pub async fn show(
     }
 }
 
+/// Gets a card by its emoji.
+///
+/// Emojis are looked up exactly as they were set, and only among the guild's
+/// own cards. The card is then shown like [`show`] would.
+#[debug_handler]
+pub async fn lookup(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    AppQuery(query): AppQuery<LookupCardQuery>,
+    auth: Authentication,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let emoji = query.emoji.trim();
+
+    let id = sqlx::query_scalar::<_, i32>(
+        r#"
+        SELECT id
+        FROM card
+        WHERE guild_id = $1 AND emoji = $2
+        "#,
+    )
+    .bind(guild_id)
+    .bind(emoji)
+    .fetch_optional(&state.db)
+    .await?;
+
+    let Some(id) = id else {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("No card uses emoji {}.", emoji)));
+    };
+
+    show(
+        State(state),
+        Path((guild_id, id)),
+        AppQuery(ShowCardQuery::default()),
+        auth,
+        headers,
+    )
+    .await
+}
+
+/// Creates a new card.
+#[debug_handler]
+pub async fn create(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+    Payload(request): Payload<CreateCardRequest>,
+) -> Result<AppJson<Card>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let name_rules = get_name_rules(&state.db, guild_id).await?;
+    let name = card_name(&request.name, &name_rules)?;
+    let emoji = request.emoji.as_deref().map(card_emoji).transpose()?;
+
+    if let Some(emoji) = emoji.as_ref() {
+        check_emoji(&state, guild_id, emoji, None).await?;
+    }
+
+    let rules = lint::get_rules(&state.db, guild_id).await?;
+    let violations = lint::check(&rules, &request.content);
+
+    if !violations.is_empty() {
+        return Err(AppErrorKind::Lint(violations).into());
+    }
+
+    let now = Utc::now();
+
+    let mut tx = state.db.begin().await?;
+
+    let id = sqlx::query_as::<_, (i32,)>(
+        r#"
+        INSERT INTO card (
+            guild_id, name, category_name, content, visibility, rarity,
+            created_by, last_edited_by, inserted_at, updated_at, emoji
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $7, $8, $8, $9)
+        ON CONFLICT (guild_id, name) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(guild_id)
+    .bind(&name)
+    .bind(request.category_name.as_ref())
+    // the content is written to where it belongs below
+    .bind("")
+    .bind(request.visibility.unwrap_or(Visibility::Private).to_str())
+    .bind(request.rarity.unwrap_or_default().to_str())
+    .bind(auth.id)
+    .bind(now)
+    .bind(emoji.as_ref())
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((id,)) = id else {
+        return Err(AppErrorKind::AlreadyExists(name).into());
+    };
+
+    state.content.write(&mut tx, id, &request.content).await?;
+
+    tx.commit().await?;
+
+    tracing::info!(guild_id, id, name, "created card");
+
+    let card = get_card(&state, id, &auth).await?;
+
+    dispatch::emit(&state, auth.id, Event::CardCreated(card.clone())).await?;
+
+    Ok(AppJson(card))
+}
+
+/// Updates a card.
+#[debug_handler]
+pub async fn update(
+    State(state): State<AppState>,
+    Path((guild_id, id)): Path<(i64, i32)>,
+    auth: Authentication,
+    headers: HeaderMap,
+    Payload(request): Payload<UpdateCardRequest>,
+) -> Result<AppJson<Card>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    // the revision the client expects to replace, if it sent `If-Match`
+    let revision = match headers.get(header::IF_MATCH) {
+        Some(tags) => Some(check_if_match(&state, guild_id, id, tags).await?),
+        None => None,
+    };
+
+    let name = match request.name.as_deref() {
+        Some(name) => {
+            let name_rules = get_name_rules(&state.db, guild_id).await?;
+            Some(card_name(name, &name_rules)?)
+        }
+        None => None,
+    };
+    let emoji = match request.emoji.as_ref() {
+        Some(Some(emoji)) => {
+            let emoji = card_emoji(emoji)?;
+            check_emoji(&state, guild_id, &emoji, Some(id)).await?;
+
+            Some(Some(emoji))
+        }
+        Some(None) => Some(None),
+        None => None,
+    };
+
+    if let Some(content) = request.content.as_ref() {
+        let rules = lint::get_rules(&state.db, guild_id).await?;
+        let violations = lint::check(&rules, content);
+
+        if !violations.is_empty() {
+            return Err(AppErrorKind::Lint(violations).into());
+        }
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    let res = sqlx::query(
+        r#"
+        UPDATE card
+        SET
+            name = COALESCE($3, name),
+            category_name = CASE WHEN $4 THEN $5 ELSE category_name END,
+            visibility = COALESCE($6, visibility),
+            rarity = COALESCE($7, rarity),
+            last_edited_by = $8,
+            updated_at = $9,
+            emoji = CASE WHEN $11 THEN $12 ELSE emoji END
+        WHERE
+            id = $1
+            AND guild_id = $2
+            AND ($10 IS NULL OR updated_at = $10)
+        "#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .bind(name.as_ref())
+    .bind(request.category_name.is_some())
+    .bind(request.category_name.flatten())
+    .bind(request.visibility.map(|visibility| visibility.to_str()))
+    .bind(request.rarity.map(|rarity| rarity.to_str()))
+    .bind(auth.id)
+    .bind(Utc::now())
+    .bind(revision.as_ref())
+    .bind(emoji.is_some())
+    .bind(emoji.flatten())
+    .execute(&mut *tx)
+    .await;
+
+    match res {
+        // the card was edited between the check and the update
+        Ok(res) if res.rows_affected() == 0 && revision.is_some() => {
+            return Err(AppErrorKind::PreconditionFailed.into());
+        }
+        Ok(res) if res.rows_affected() == 0 => {
+            return Err(AppError::from(AppErrorKind::NotFound)
+                .with_message(format!("The card of id {} does not exist.", id)));
+        }
+        Ok(_) => (),
+        Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+            return Err(AppErrorKind::AlreadyExists(name.unwrap_or_default()).into());
+        }
+        Err(err) => return Err(err.into()),
+    }
+
+    if let Some(content) = request.content.as_ref() {
+        state.content.write(&mut tx, id, content).await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(guild_id, id, "updated card");
+
+    let card = get_card(&state, id, &auth).await?;
+
+    dispatch::emit(&state, auth.id, Event::CardUpdated(card.clone())).await?;
+
+    Ok(AppJson(card))
+}
+
+/// Checks an `If-Match` header against the card's current revision.
+///
+/// Only the revision is compared, so ETags of sparse or expanded cards match
+/// too. Returns the card's current `updated_at`, so the update can make sure
+/// nothing changed in between.
+async fn check_if_match(
+    state: &AppState,
+    guild_id: i64,
+    id: i32,
+    tags: &HeaderValue,
+) -> Result<String, AppError> {
+    let (raw, updated_at) = sqlx::query_as::<_, (String, NaiveDateTime)>(
+        r#"
+        SELECT updated_at, updated_at FROM card WHERE id = $1 AND guild_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| {
+        AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The card of id {} does not exist.", id))
+    })?;
+
+    if revision_matches(tags, &revision_of(id, updated_at)) {
+        Ok(raw)
+    } else {
+        Err(AppErrorKind::PreconditionFailed.into())
+    }
+}
+
+/// Archives all cards in a guild matching a filter.
+#[debug_handler]
+pub async fn archive(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+    Payload(request): Payload<ArchiveCardsRequest>,
+) -> Result<AppJson<ArchiveCardsResponse>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    // refuse to archive an entire guild by accident
+    if request.category_name.is_none() && request.created_before.is_none() {
+        return Err(
+            AppError::from(AppErrorKind::MissingField("category_name".into())).with_message(
+                "At least one of `category_name` or `created_before` must be given.",
+            ),
+        );
+    }
+
+    let res = sqlx::query(
+        r#"
+        UPDATE card
+        SET archived_at = $2, updated_at = $2
+        WHERE
+            guild_id = $1
+            AND archived_at IS NULL
+            AND ($3 IS NULL OR category_name = $3)
+            AND ($4 IS NULL OR datetime(inserted_at) < datetime($4))
+        "#,
+    )
+    .bind(guild_id)
+    .bind(Utc::now())
+    .bind(request.category_name.as_ref())
+    .bind(request.created_before)
+    .execute(&state.db)
+    .await?;
+
+    tracing::info!(
+        guild_id,
+        archived = res.rows_affected(),
+        "archived cards in bulk"
+    );
+
+    Ok(AppJson(ArchiveCardsResponse {
+        archived: res.rows_affected() as u32,
+    }))
+}
+
 /// Preloads card information from an already fetched card.
+///
+/// Related cards are only loaded if named in `expand`.
 pub async fn preload_card(
+    state: &AppState,
+    auth: &Authentication,
+    card: Card,
+    expand: &[Expand],
+) -> Result<Card, AppError> {
+    let mut card = expand_card(state, auth, card, expand).await?;
+
+    // privileged callers can see who to ask about a card, and what owners
+    // think of it
+    if auth.managed {
+        load_authors(state, &mut card).await?;
+        card.ratings = get_ratings(&state.db, card.id).await?;
+    }
+
+    Ok(card)
+}
+
+/// Loads the cards related to a card that are named in `expand`.
+pub async fn expand_card(
     state: &AppState,
     auth: &Authentication,
     mut card: Card,
+    expand: &[Expand],
 ) -> Result<Card, AppError> {
-    // Fetch all the upgrades for the card
-    let upgrades = sqlx::query_as::<_, CardResult>(
+    if expand.contains(&Expand::Upgrades) {
+        let upgrades = sqlx::query_as::<_, CardResult>(
+            r#"
+            SELECT
+                c.id, c.guild_id, c.name, c.emoji, c.category_name, c.content,
+                c.visibility, c.rarity, c.rarity_score, c.archived_at,
+                c.inserted_at, c.updated_at,
+                COALESCE(o.quantity, 0) > 0 AS owned
+            FROM
+                card c
+            LEFT OUTER JOIN
+                ownership AS o
+                ON o.card_id = c.id AND o.owner_id = $1
+            WHERE
+                c.previous_id = $2
+            "#,
+        )
+        .bind(auth.id)
+        .bind(card.id)
+        .fetch_all(&state.db)
+        .await?
+        .into_iter()
+        .filter(|card| card.owned || matches!(card.visibility.into(), Visibility::Public))
+        .filter(|card| card.owned || card.archived_at.is_none())
+        .map(|card| redact_card(Card::from(card), auth))
+        .collect::<Vec<_>>();
+
+        if upgrades.len() > 0 {
+            card.upgrades = Some(upgrades);
+        }
+    }
+
+    if expand.contains(&Expand::Downgrade) {
+        let downgrade = sqlx::query_as::<_, CardResult>(
+            r#"
+            SELECT
+                down.id,
+                down.guild_id,
+                down.name,
+                down.emoji,
+                down.category_name,
+                down.content,
+                down.visibility,
+                down.rarity,
+                down.rarity_score,
+                down.archived_at,
+                down.inserted_at,
+                down.updated_at,
+                COALESCE(o.quantity, 0) > 0 AS owned
+            FROM
+                card down, card up
+            LEFT OUTER JOIN
+                ownership AS o
+                ON o.card_id = down.id AND o.owner_id = $1
+            WHERE
+                down.id = up.previous_id
+                AND up.id = $2
+        "#,
+        )
+        .bind(auth.id)
+        .bind(card.id)
+        .fetch_optional(&state.db)
+        .await?;
+
+        if let Some(downgrade) = downgrade {
+            if downgrade.owned
+                || (downgrade.archived_at.is_none()
+                    && matches!(downgrade.visibility.into(), Visibility::Public))
+            {
+                card.downgrade = Some(Box::new(redact_card(Card::from(downgrade), auth)));
+            }
+        }
+    }
+
+    Ok(card)
+}
+
+/// Gets how owners rated a card, if any did.
+///
+/// Ratings of users that no longer own the card are left out, but kept in
+/// case they get the card back.
+pub async fn get_ratings<'c, E>(db: E, card_id: i32) -> Result<Option<Ratings>, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let (count, average) = sqlx::query_as::<_, (i64, Option<f64>)>(
         r#"
-        SELECT
-            c.id, c.guild_id, c.name, c.category_name, c.content,
-            c.visibility, c.inserted_at, c.updated_at,
-            COALESCE(o.owned, FALSE) AS owned
-        FROM
-            card c
-        LEFT OUTER JOIN
-            ownership AS o
-            ON o.card_id = c.id AND o.owner_id = $1
+        SELECT COUNT(*), AVG(r.rating)
+        FROM card_reaction r, ownership o
         WHERE
-            c.previous_id = $2
+            r.card_id = $1
+            AND o.card_id = r.card_id
+            AND o.owner_id = r.user_id
+            AND o.quantity > 0
         "#,
     )
-    .bind(auth.id)
-    .bind(card.id)
-    .fetch_all(&state.db)
-    .await?
-    .into_iter()
-    .filter(|card| card.owned || matches!(card.visibility.into(), Visibility::Public))
-    .map(|card| Card::from(card))
-    .collect::<Vec<_>>();
+    .bind(card_id)
+    .fetch_one(db)
+    .await?;
 
-    // Fetch the downgrade for the card
-    let downgrade = sqlx::query_as::<_, CardResult>(
+    Ok(average.map(|average| Ratings {
+        count: count as u32,
+        average,
+    }))
+}
+
+/// Loads the authors of a card.
+async fn load_authors(state: &AppState, card: &mut Card) -> Result<(), AppError> {
+    #[derive(FromRow)]
+    struct AuthorResult {
+        id: i32,
+        display_name: String,
+        discord_id: Option<i64>,
+        created: bool,
+        edited: bool,
+    }
+
+    let authors = sqlx::query_as::<_, AuthorResult>(
         r#"
         SELECT
-            down.id,
-            down.guild_id,
-            down.name,
-            down.category_name,
-            down.content,
-            down.visibility,
-            down.inserted_at,
-            down.updated_at,
-            COALESCE(o.owned, FALSE) AS owned
+            u.id, u.display_name, da.discord_id,
+            COALESCE(c.created_by = u.id, FALSE) AS created,
+            COALESCE(c.last_edited_by = u.id, FALSE) AS edited
         FROM
-            card down, card up
+            card c, user u
         LEFT OUTER JOIN
-            ownership AS o
-            ON o.card_id = down.id AND o.owner_id = $1
+            discord_auth AS da
+            ON da.user_id = u.id
         WHERE
-            down.id = up.previous_id
-            AND up.id = $2
-    "#,
+            c.id = $1
+            AND u.id IN (c.created_by, c.last_edited_by)
+        "#,
     )
-    .bind(auth.id)
     .bind(card.id)
-    .fetch_optional(&state.db)
+    .fetch_all(&state.db)
     .await?;
 
-    // Apply to card
-    if upgrades.len() > 0 {
-        card.upgrades = Some(upgrades);
-    }
+    for author in authors {
+        let (created, edited) = (author.created, author.edited);
+        let author = Author {
+            user: User {
+                id: author.id,
+                display_name: author.display_name,
+            },
+            discord_id: author.discord_id.and_then(|id| Id::new(id as u64)),
+        };
+
+        if created {
+            card.created_by = Some(author.clone());
+        }
 
-    if let Some(downgrade) = downgrade {
-        if downgrade.owned || matches!(downgrade.visibility.into(), Visibility::Public) {
-            card.downgrade = Some(Box::new(Card::from(downgrade)));
+        if edited {
+            card.last_edited_by = Some(author);
         }
     }
 
-    Ok(card)
+    Ok(())
 }
 
 /// Lower-level request handler given simply a card id.
 pub async fn get_card(state: &AppState, id: i32, auth: &Authentication) -> Result<Card, AppError> {
     // fetch main card
-    let card = sqlx::query_as::<_, CardResult>(
-        r#"
-        SELECT
-            c.id, c.guild_id, c.name, c.category_name, c.content, c.visibility,
-            c.inserted_at, c.updated_at, COALESCE(o.owned, FALSE) AS owned
-        FROM
-            card c
-        LEFT OUTER JOIN
-            ownership AS o
-            ON o.card_id = c.id AND o.owner_id = $1
-        WHERE
-            c.id = $2
-        "#,
-    )
-    .bind(auth.id)
-    .bind(id)
-    .fetch_optional(&state.db)
-    .await?;
+    let card = state.cards.get(auth.id, id).await?;
 
     match card {
-        Some(card) => Ok(preload_card(state, auth, Card::from(card)).await?),
+        Some(card) => {
+            let mut card = preload_card(state, auth, redact_card(card, auth), &[]).await?;
+            state.content.load_one(&state.db, &mut card).await?;
+
+            Ok(card)
+        }
         None => Err(AppError::from(AppErrorKind::NotFound)
             .with_message(format!("The card of id {} does not exist.", id))),
     }
 }
 
-fn sort_query_results(
-    cards: impl IntoIterator<Item = Card>,
-    query: impl AsRef<str>,
-) -> impl Iterator<Item = Card> {
-    let query = query.as_ref();
-
-    // results that start with the query are prioritized
-    let mut exact_match = None;
-
-    let mut top = Vec::new();
-    let mut bottom = Vec::new();
-
-    for card in cards {
-        if card.name == query {
-            exact_match = Some(card);
-        } else if card.name.starts_with(query) {
-            top.push(card);
-        } else {
-            bottom.push(card);
+/// Validates the fields asked for in a sparse response.
+fn card_fields(fields: Option<&FieldSet>) -> Result<Option<Arc<[String]>>, AppError> {
+    let Some(fields) = fields else {
+        return Ok(None);
+    };
+
+    for field in fields.0.iter() {
+        value("fields", field.as_str())
+            .one_of(Card::FIELDS)
+            .validate()?;
+    }
+
+    Ok(Some(fields.0.iter().cloned().collect()))
+}
+
+/// Checks if a sparse response includes card content, which may need to be
+/// loaded separately.
+fn wants_content(fields: Option<&[String]>) -> bool {
+    fields.is_none_or(|fields| fields.iter().any(|field| field == "content"))
+}
+
+/// Normalizes and validates a card name against a guild's name rules.
+///
+/// Names are normalized to match how the bot searches for cards.
+fn card_name(name: &str, rules: &NameRules) -> Result<String, AppError> {
+    let name = normalize_name(name);
+
+    value("name", name.len())
+        .in_range(1..=MAX_NAME_LEN)
+        .validate()?;
+
+    if let Some(max_length) = rules.max_length {
+        value("name", name.chars().count())
+            .in_range(1..=max_length as usize)
+            .validate()?;
+    }
+
+    value("name", name).chars(|c| rules.allows(c)).validate()
+}
+
+/// The outcome of [`normalize_names`].
+#[derive(Clone, Debug, Default)]
+pub struct NormalizedNames {
+    /// How many cards were renamed.
+    pub renamed: u64,
+    /// Cards left alone because another card of their guild already has
+    /// their normalized name, with that name.
+    pub conflicts: Vec<(i32, String)>,
+}
+
+/// Renames every card whose stored name is not normalized.
+///
+/// Names stored before normalization followed Unicode's rules were only
+/// uppercased for ASCII, so they never match what the bot searches for.
+/// SQLite cannot uppercase the rest, so this runs from the server instead of
+/// a migration. A card is left alone if its new name is already taken.
+pub async fn normalize_names(db: &SqlitePool) -> Result<NormalizedNames, sqlx::Error> {
+    let cards = sqlx::query_as::<_, (i32, String)>("SELECT id, name FROM card ORDER BY id")
+        .fetch_all(db)
+        .await?;
+
+    let mut normalized = NormalizedNames::default();
+    let mut tx = db.begin().await?;
+
+    for (id, name) in cards {
+        let new_name = normalize_name(&name);
+
+        if new_name == name {
+            continue;
+        }
+
+        let res = sqlx::query(
+            r#"
+            UPDATE card
+            SET name = $2, updated_at = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(&new_name)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await;
+
+        match res {
+            Ok(_) => normalized.renamed += 1,
+            Err(sqlx::Error::Database(err)) if err.is_unique_violation() => {
+                normalized.conflicts.push((id, new_name));
+            }
+            Err(err) => return Err(err),
         }
     }
 
-    // sort by lexicographic score
-    let textdistance = Levenshtein::default();
-    let sorter = |a: &Card, b: &Card| {
-        let a = textdistance.for_str(&a.name, query).val();
-        let b = textdistance.for_str(&b.name, query).val();
-        a.cmp(&b)
+    tx.commit().await?;
+
+    Ok(normalized)
+}
+
+/// Normalizes and validates a card emoji.
+///
+/// Emojis are either Discord custom emoji markup, like `<:name:id>` or
+/// `<a:name:id>` when animated, or a short run of Unicode emoji, which
+/// cannot hold ASCII letters so they are never mistaken for card names.
+fn card_emoji(emoji: &str) -> Result<String, AppError> {
+    let emoji = emoji.trim();
+
+    let valid = match emoji.strip_prefix('<').and_then(|e| e.strip_suffix('>')) {
+        Some(custom) => {
+            let custom = custom.strip_prefix('a').unwrap_or(custom);
+
+            match custom.strip_prefix(':').and_then(|c| c.split_once(':')) {
+                Some((name, id)) => {
+                    (2..=32).contains(&name.len())
+                        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                        && !id.is_empty()
+                        && id.chars().all(|c| c.is_ascii_digit())
+                }
+                None => false,
+            }
+        }
+        None => {
+            emoji.len() <= MAX_EMOJI_LEN
+                && !emoji.is_ascii()
+                && !emoji
+                    .chars()
+                    .any(|c| c.is_ascii_alphabetic() || c.is_whitespace() || c.is_control())
+        }
     };
 
-    top.sort_unstable_by(&sorter);
-    bottom.sort_unstable_by(&sorter);
+    if !valid {
+        return Err(
+            AppError::from(AppErrorKind::FieldOutOfRange("emoji".into())).with_message(
+                "A card emoji must be a custom emoji, like `<:name:id>`, or a Unicode emoji.",
+            ),
+        );
+    }
+
+    Ok(emoji.to_owned())
+}
+
+/// Checks that no other card in a guild uses an emoji.
+async fn check_emoji(
+    state: &AppState,
+    guild_id: i64,
+    emoji: &str,
+    except_id: Option<i32>,
+) -> Result<(), AppError> {
+    let taken = sqlx::query_as::<_, (String,)>(
+        r#"
+        SELECT name
+        FROM card
+        WHERE guild_id = $1 AND emoji = $2 AND ($3 IS NULL OR id != $3)
+        "#,
+    )
+    .bind(guild_id)
+    .bind(emoji)
+    .bind(except_id)
+    .fetch_optional(&state.db)
+    .await?;
+
+    match taken {
+        Some((name,)) => Err(AppError::from(AppErrorKind::AlreadyExists(name.clone()))
+            .with_message(format!("Card `{}` already uses emoji {}.", name, emoji))),
+        None => Ok(()),
+    }
+}
+
+/// Checks if a card reads the same for every non-managed caller.
+///
+/// That is, the card and everything related to it is public and in
+/// circulation.
+fn is_shared(card: &Card) -> bool {
+    card.visibility == Visibility::Public
+        && card.archived_at.is_none()
+        && card.upgrades.iter().flatten().all(is_shared)
+        && card.downgrade.as_deref().is_none_or(is_shared)
+}
+
+/// Strips fields only privileged callers may see from a card.
+pub fn redact_card(mut card: Card, auth: &Authentication) -> Card {
+    if !auth.managed {
+        strip_privileged(&mut card);
+    }
 
-    iter::once(exact_match)
-        .filter_map(std::convert::identity)
-        .chain(top)
-        .chain(bottom)
+    card
+}
+
+/// Strips fields only privileged callers may see from a card and the cards
+/// related to it, whoever the card was loaded for.
+pub fn strip_privileged(card: &mut Card) {
+    card.rarity_score = None;
+    card.created_by = None;
+    card.last_edited_by = None;
+    card.ratings = None;
+
+    for upgrade in card.upgrades.iter_mut().flatten() {
+        strip_privileged(upgrade);
+    }
+
+    if let Some(downgrade) = card.downgrade.as_deref_mut() {
+        strip_privileged(downgrade);
+    }
 }
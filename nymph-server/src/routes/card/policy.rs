@@ -0,0 +1,140 @@
+//! Per-card grant policies.
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use nymph_model::{Id, card::GrantPolicy};
+
+use sqlx::{Executor, Sqlite};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppState, Payload},
+    auth::Authentication,
+};
+
+/// Gets the grant policy of a card.
+#[debug_handler]
+pub async fn show(
+    State(state): State<AppState>,
+    Path((guild_id, id)): Path<(i64, i32)>,
+    auth: Authentication,
+) -> Result<AppJson<GrantPolicy>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    check_card(&state, guild_id, id).await?;
+
+    Ok(AppJson(get_policy(&state.db, id).await?))
+}
+
+/// Replaces the grant policy of a card.
+#[debug_handler]
+pub async fn update(
+    State(state): State<AppState>,
+    Path((guild_id, id)): Path<(i64, i32)>,
+    auth: Authentication,
+    Payload(policy): Payload<GrantPolicy>,
+) -> Result<AppJson<GrantPolicy>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    check_card(&state, guild_id, id).await?;
+
+    let mut tx = state.db.begin().await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM card_grant_policy
+        WHERE card_id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    let subjects = policy
+        .users
+        .iter()
+        .map(|user| ("user", user))
+        .chain(policy.roles.iter().map(|role| ("role", role)));
+
+    for (kind, subject_id) in subjects {
+        sqlx::query(
+            r#"
+            INSERT INTO card_grant_policy (card_id, kind, subject_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(id)
+        .bind(kind)
+        .bind(subject_id.get() as i64)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(guild_id, id, ?policy, "updated card grant policy");
+
+    Ok(AppJson(get_policy(&state.db, id).await?))
+}
+
+/// Fetches the grant policy of a card.
+pub async fn get_policy<'c, E>(db: E, card_id: i32) -> Result<GrantPolicy, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let subjects = sqlx::query_as::<_, (String, i64)>(
+        r#"
+        SELECT kind, subject_id
+        FROM card_grant_policy
+        WHERE card_id = $1
+        ORDER BY kind, subject_id
+        "#,
+    )
+    .bind(card_id)
+    .fetch_all(db)
+    .await?;
+
+    let mut policy = GrantPolicy::default();
+
+    for (kind, subject_id) in subjects {
+        let Some(subject_id) = Id::new(subject_id as u64) else {
+            continue;
+        };
+
+        match kind.as_str() {
+            "user" => policy.users.push(subject_id),
+            "role" => policy.roles.push(subject_id),
+            _ => tracing::warn!(card_id, kind, "unknown grant policy kind"),
+        }
+    }
+
+    Ok(policy)
+}
+
+/// Checks that a card exists in a guild.
+pub async fn check_card(state: &AppState, guild_id: i64, id: i32) -> Result<(), AppError> {
+    let card = sqlx::query_as::<_, (i32,)>(
+        r#"
+        SELECT id
+        FROM card
+        WHERE id = $1 AND guild_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .fetch_optional(&state.db)
+    .await?;
+
+    match card {
+        Some(_) => Ok(()),
+        None => Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The card of id {} does not exist.", id))),
+    }
+}
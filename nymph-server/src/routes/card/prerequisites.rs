@@ -0,0 +1,260 @@
+//! Per-card grant prerequisites.
+//!
+//! Prerequisites let a guild build progression systems: a card may ask that
+//! the user owns other cards, or has collected whole categories, before it
+//! can be granted to them.
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use nymph_model::card::{Card, Prerequisites};
+
+use sqlx::{Executor, Sqlite};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppState, Payload},
+    auth::Authentication,
+    routes::card::policy::check_card,
+};
+
+/// Gets the prerequisites of a card.
+#[debug_handler]
+pub async fn show(
+    State(state): State<AppState>,
+    Path((guild_id, id)): Path<(i64, i32)>,
+    auth: Authentication,
+) -> Result<AppJson<Prerequisites>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    check_card(&state, guild_id, id).await?;
+
+    Ok(AppJson(get_prerequisites(&state.db, id).await?))
+}
+
+/// Replaces the prerequisites of a card.
+#[debug_handler]
+pub async fn update(
+    State(state): State<AppState>,
+    Path((guild_id, id)): Path<(i64, i32)>,
+    auth: Authentication,
+    Payload(mut prerequisites): Payload<Prerequisites>,
+) -> Result<AppJson<Prerequisites>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    check_card(&state, guild_id, id).await?;
+
+    prerequisites.cards.sort();
+    prerequisites.cards.dedup();
+    prerequisites.categories.sort();
+    prerequisites.categories.dedup();
+
+    for &card_id in prerequisites.cards.iter() {
+        if card_id == id {
+            return Err(
+                AppError::from(AppErrorKind::FieldOutOfRange("cards".into()))
+                    .with_message("A card cannot be a prerequisite of itself."),
+            );
+        }
+
+        check_card(&state, guild_id, card_id).await?;
+    }
+
+    for category in prerequisites.categories.iter() {
+        let exists = sqlx::query_as::<_, (i32,)>(
+            r#"
+            SELECT id
+            FROM card
+            WHERE guild_id = $1 AND category_name = $2
+            LIMIT 1
+            "#,
+        )
+        .bind(guild_id)
+        .bind(category)
+        .fetch_optional(&state.db)
+        .await?;
+
+        if exists.is_none() {
+            return Err(AppError::from(AppErrorKind::NotFound)
+                .with_message(format!("The category `{}` does not exist.", category)));
+        }
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM card_prerequisite
+        WHERE card_id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    for card_id in prerequisites.cards.iter() {
+        sqlx::query(
+            r#"
+            INSERT INTO card_prerequisite (card_id, required_card_id)
+            VALUES ($1, $2)
+            "#,
+        )
+        .bind(id)
+        .bind(card_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    for category in prerequisites.categories.iter() {
+        sqlx::query(
+            r#"
+            INSERT INTO card_prerequisite (card_id, required_category)
+            VALUES ($1, $2)
+            "#,
+        )
+        .bind(id)
+        .bind(category)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(guild_id, id, ?prerequisites, "updated card prerequisites");
+
+    Ok(AppJson(get_prerequisites(&state.db, id).await?))
+}
+
+/// Fetches the prerequisites of a card.
+pub async fn get_prerequisites<'c, E>(db: E, card_id: i32) -> Result<Prerequisites, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let rows = sqlx::query_as::<_, (Option<i32>, Option<String>)>(
+        r#"
+        SELECT required_card_id, required_category
+        FROM card_prerequisite
+        WHERE card_id = $1
+        ORDER BY required_card_id, required_category
+        "#,
+    )
+    .bind(card_id)
+    .fetch_all(db)
+    .await?;
+
+    let mut prerequisites = Prerequisites::default();
+
+    for row in rows {
+        match row {
+            (Some(card_id), _) => prerequisites.cards.push(card_id),
+            (None, Some(category)) => prerequisites.categories.push(category),
+            (None, None) => (),
+        }
+    }
+
+    Ok(prerequisites)
+}
+
+/// Checks that a user has collected everything a card asks for before it
+/// can be granted to them.
+///
+/// Like progress, private and archived cards of a category only count once
+/// the user owns them.
+pub async fn check_prerequisites(
+    state: &AppState,
+    user_id: i32,
+    card: &Card,
+) -> Result<(), AppError> {
+    let missing_cards = sqlx::query_as::<_, (String,)>(
+        r#"
+        SELECT c.name
+        FROM
+            card_prerequisite p
+        INNER JOIN
+            card AS c
+            ON c.id = p.required_card_id
+        LEFT OUTER JOIN
+            ownership AS o
+            ON o.card_id = c.id AND o.owner_id = $2
+        WHERE
+            p.card_id = $1
+            AND COALESCE(o.quantity, 0) = 0
+        ORDER BY c.name
+        "#,
+    )
+    .bind(card.id)
+    .bind(user_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    let missing_categories = sqlx::query_as::<_, (String,)>(
+        r#"
+        SELECT p.required_category
+        FROM card_prerequisite p
+        WHERE
+            p.card_id = $1
+            AND p.required_category IS NOT NULL
+            AND EXISTS (
+                SELECT 1
+                FROM
+                    card c
+                LEFT OUTER JOIN
+                    ownership AS o
+                    ON o.card_id = c.id AND o.owner_id = $2
+                WHERE
+                    c.guild_id = $3
+                    AND c.category_name = p.required_category
+                    AND c.id != p.card_id
+                    AND c.archived_at IS NULL
+                    AND c.visibility != 'private'
+                    AND COALESCE(o.quantity, 0) = 0
+            )
+        ORDER BY p.required_category
+        "#,
+    )
+    .bind(card.id)
+    .bind(user_id)
+    .bind(card.guild_id.get() as i64)
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut requirements = Vec::with_capacity(2);
+
+    if !missing_cards.is_empty() {
+        requirements.push(format!("owns {}", quote_all(missing_cards)));
+    }
+
+    if !missing_categories.is_empty() {
+        requirements.push(format!(
+            "has collected every card of {}",
+            quote_all(missing_categories)
+        ));
+    }
+
+    if requirements.is_empty() {
+        return Ok(());
+    }
+
+    Err(
+        AppError::from(AppErrorKind::UnmetPrerequisites(card.name.to_owned())).with_message(
+            format!(
+                "Card `{}` cannot be granted until the user {}.",
+                &card.name,
+                requirements.join(" and ")
+            ),
+        ),
+    )
+}
+
+fn quote_all(names: Vec<(String,)>) -> String {
+    names
+        .into_iter()
+        .map(|(name,)| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
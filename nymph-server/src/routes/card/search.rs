@@ -0,0 +1,124 @@
+//! Card search query syntax.
+//!
+//! A search like `category:forest owned:false dragon` is split into field
+//! filters and the words left over, which search card names as usual. This
+//! lets the bot forward complex searches through a single string option.
+
+use nymph_model::{card::Rarity, request::card::VisibilityFilter};
+
+use crate::app::{AppError, AppErrorKind};
+
+/// A search split into its filters.
+#[derive(Debug, Default)]
+pub struct Search {
+    /// The words that are not filters, joined by spaces.
+    pub text: Option<String>,
+    /// The `category:` filter.
+    pub category: Option<String>,
+    /// The `owned:` filter.
+    pub owned: Option<bool>,
+    /// The `rarity:` filter.
+    pub rarity: Option<Rarity>,
+    /// The `visibility:` filter.
+    pub visibility: Option<VisibilityFilter>,
+    /// The `content:` filter.
+    pub content: Option<String>,
+}
+
+impl Search {
+    /// Parses a search.
+    ///
+    /// Filters are written `field:value`, and values with spaces may be put
+    /// in double quotes. Field names and values are not case sensitive, so
+    /// searches can be uppercased like card names are. A word with an
+    /// unknown field name is searched for as-is, since card names may have
+    /// colons of their own.
+    pub fn parse(search: &str) -> Result<Search, AppError> {
+        let mut parsed = Search::default();
+        let mut text = Vec::new();
+
+        for term in terms(search) {
+            let Some((field, value)) = term.split_once(':') else {
+                text.push(unquote(term));
+                continue;
+            };
+
+            let value = unquote(value);
+
+            match field.to_ascii_lowercase().as_str() {
+                "category" => parsed.category = Some(value.to_owned()),
+                "owned" => {
+                    parsed.owned = Some(match value.to_ascii_lowercase().as_str() {
+                        "true" | "yes" => true,
+                        "false" | "no" => false,
+                        _ => return Err(invalid("owned", "`true` or `false`")),
+                    })
+                }
+                "rarity" => {
+                    parsed.rarity = Some(
+                        value
+                            .to_ascii_lowercase()
+                            .parse()
+                            .map_err(|_| invalid("rarity", "a rarity"))?,
+                    )
+                }
+                "visibility" => {
+                    parsed.visibility = Some(
+                        VisibilityFilter::try_from(value.to_ascii_lowercase())
+                            .map_err(|_| invalid("visibility", "a list of visibilities"))?,
+                    )
+                }
+                "content" => parsed.content = Some(value.to_owned()),
+                _ => text.push(term),
+            }
+        }
+
+        if !text.is_empty() {
+            parsed.text = Some(text.join(" "));
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Splits a search on whitespace, keeping quoted runs together.
+fn terms(search: &str) -> Vec<&str> {
+    let mut terms = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+
+    for (i, c) in search.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if let Some(start) = start.take() {
+                    terms.push(&search[start..i]);
+                }
+                continue;
+            }
+            _ => (),
+        }
+
+        start.get_or_insert(i);
+    }
+
+    if let Some(start) = start {
+        terms.push(&search[start..]);
+    }
+
+    terms
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .map(|value| value.strip_suffix('"').unwrap_or(value))
+        .unwrap_or(value)
+}
+
+fn invalid(field: &str, expected: &str) -> AppError {
+    AppError::from(AppErrorKind::FieldOutOfRange("query".into())).with_message(format!(
+        "The `{}:` filter of the query must be {}.",
+        field, expected
+    ))
+}
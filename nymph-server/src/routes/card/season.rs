@@ -0,0 +1,313 @@
+//! Seasons.
+//!
+//! A season snapshots everything owned in a guild under a name, and may reset
+//! ownership afterwards, for communities that start their collections over
+//! periodically. Past seasons stay browsable per user.
+//!
+//! Resetting ownership also forgets which users reward rules have rewarded, so
+//! every reward can be earned again in the new season.
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use http::HeaderMap;
+
+use chrono::{NaiveDateTime, Utc};
+
+use nymph_model::{
+    Id,
+    card::Card,
+    dispatch::Event,
+    request::season::{CreateSeasonRequest, ListSeasonCardsQuery},
+    response::Paginated,
+    season::Season,
+};
+
+use sqlx::{Executor, FromRow, Sqlite};
+
+use super::CardResult;
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppQuery, AppState, Cached, Payload},
+    auth::Authentication,
+    dispatch,
+    request::validate::{Validator as _, ValidatorExt as _, value},
+    routes::{Pagination, card::redact_card},
+};
+
+/// The maximum length of a season name.
+pub const MAX_NAME_LEN: usize = 255;
+
+#[derive(FromRow)]
+struct SeasonResult {
+    id: i32,
+    guild_id: i64,
+    name: String,
+    reset: bool,
+    owners: i64,
+    copies: i64,
+    inserted_at: NaiveDateTime,
+}
+
+impl From<SeasonResult> for Season {
+    fn from(season: SeasonResult) -> Season {
+        Season {
+            id: season.id,
+            // TODO: maybe not panic when getting arbitrary data?
+            guild_id: Id::new(season.guild_id as u64).expect("valid id"),
+            name: season.name,
+            reset: season.reset,
+            owners: season.owners as u32,
+            copies: season.copies as u32,
+            created_at: season.inserted_at,
+        }
+    }
+}
+
+/// Lists all seasons of a guild, newest first.
+#[debug_handler]
+pub async fn list(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    _auth: Authentication,
+    headers: HeaderMap,
+) -> Result<Cached<AppJson<Vec<Season>>>, AppError> {
+    let seasons = sqlx::query_as::<_, SeasonResult>(
+        r#"
+        SELECT
+            s.id, s.guild_id, s.name, s.reset, s.inserted_at,
+            COUNT(DISTINCT so.owner_id) AS owners,
+            COALESCE(SUM(so.quantity), 0) AS copies
+        FROM
+            season s
+        LEFT OUTER JOIN
+            season_ownership AS so
+            ON so.season_id = s.id
+        WHERE s.guild_id = $1
+        GROUP BY s.id
+        ORDER BY s.id DESC
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(Season::from)
+    .collect();
+
+    // owner counts change all the time, so there is no good modification date
+    Ok(Cached::new(&headers, AppJson(seasons), state.cache.seasons))
+}
+
+/// Records a season, optionally resetting every user's cards in the guild.
+#[debug_handler]
+pub async fn create(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+    Payload(request): Payload<CreateSeasonRequest>,
+) -> Result<AppJson<Season>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let name = request.name.trim();
+
+    value("name", name.len())
+        .in_range(1..=MAX_NAME_LEN)
+        .validate()?;
+
+    let mut tx = state.db.begin().await?;
+
+    let id = sqlx::query_as::<_, (i32,)>(
+        r#"
+        INSERT INTO season (guild_id, name, reset, inserted_at)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (guild_id, name) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(guild_id)
+    .bind(name)
+    .bind(request.reset)
+    .bind(Utc::now())
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((id,)) = id else {
+        return Err(AppError::from(AppErrorKind::AlreadyExists(name.to_owned()))
+            .with_message(format!("A season named `{}` already exists.", name)));
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO season_ownership (season_id, card_id, owner_id, quantity)
+        SELECT $1, o.card_id, o.owner_id, o.quantity
+        FROM
+            ownership o, card c
+        WHERE
+            o.card_id = c.id
+            AND c.guild_id = $2
+            AND o.quantity > 0
+        "#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .execute(&mut *tx)
+    .await?;
+
+    if request.reset {
+        // ownership is reset in bulk, so no revocations are dispatched; the
+        // reset is dispatched once instead
+        let reset = sqlx::query(
+            r#"
+            UPDATE ownership
+            SET quantity = 0
+            WHERE
+                quantity > 0
+                AND card_id IN (SELECT id FROM card WHERE guild_id = $1)
+            "#,
+        )
+        .bind(guild_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tracing::info!(
+            guild_id,
+            id,
+            count = reset.rows_affected(),
+            "reset ownership"
+        );
+
+        // users start their collections over, so they may earn every reward
+        // again
+        sqlx::query(
+            r#"
+            DELETE FROM card_rule_reward
+            WHERE rule_id IN (SELECT id FROM card_rule WHERE guild_id = $1)
+            "#,
+        )
+        .bind(guild_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(guild_id, id, name, "recorded season");
+
+    let season = get_season(&state.db, id).await?;
+
+    if season.reset {
+        dispatch::emit(&state, auth.id, Event::SeasonReset(season.clone())).await?;
+    }
+
+    Ok(AppJson(season))
+}
+
+/// Lists all cards a user owned when a season was recorded.
+#[debug_handler]
+pub async fn cards(
+    State(state): State<AppState>,
+    Path((user_id, season_id)): Path<(i32, i32)>,
+    AppQuery(query): AppQuery<ListSeasonCardsQuery>,
+    auth: Authentication,
+) -> Result<AppJson<Paginated<Card>>, AppError> {
+    // users may only list their own cards
+    if auth.id != user_id && !auth.managed {
+        return Err(AppErrorKind::InsufficientPermissions.into());
+    }
+
+    // fails early if the season does not exist
+    get_season(&state.db, season_id).await?;
+
+    let (total,) = sqlx::query_as::<_, (i64,)>(
+        r#"
+        SELECT COUNT(*)
+        FROM season_ownership
+        WHERE season_id = $1 AND owner_id = $2
+        "#,
+    )
+    .bind(season_id)
+    .bind(user_id)
+    .fetch_one(&state.db)
+    .await?;
+
+    let page = Pagination::default().limit(25).paginate(
+        total,
+        query.page.unwrap_or(1),
+        query.count.unwrap_or(25),
+    )?;
+
+    let mut results = sqlx::query_as::<_, CardResult>(
+        r#"
+        SELECT
+            c.id, c.guild_id, c.name, c.emoji, c.category_name, c.content,
+            c.visibility, c.rarity, c.rarity_score, c.archived_at,
+            c.inserted_at, c.updated_at,
+            TRUE AS owned, so.quantity
+        FROM
+            card c, season_ownership so
+        WHERE
+            so.card_id = c.id
+            AND so.season_id = $1
+            AND so.owner_id = $2
+        ORDER BY c.id
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(season_id)
+    .bind(user_id)
+    .bind(page.limit)
+    .bind(page.offset)
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|result| {
+        let quantity = result.quantity as u32;
+        let card = redact_card(Card::from(result), &auth);
+
+        Card {
+            quantity: Some(quantity),
+            ..card
+        }
+    })
+    .collect::<Vec<_>>();
+
+    state.content.load(&state.db, &mut results).await?;
+
+    Ok(AppJson(page.wrap(results)))
+}
+
+/// Fetches a season.
+pub async fn get_season<'c, E>(db: E, id: i32) -> Result<Season, AppError>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let season = sqlx::query_as::<_, SeasonResult>(
+        r#"
+        SELECT
+            s.id, s.guild_id, s.name, s.reset, s.inserted_at,
+            COUNT(DISTINCT so.owner_id) AS owners,
+            COALESCE(SUM(so.quantity), 0) AS copies
+        FROM
+            season s
+        LEFT OUTER JOIN
+            season_ownership AS so
+            ON so.season_id = s.id
+        WHERE s.id = $1
+        GROUP BY s.id
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(db)
+    .await?;
+
+    match season {
+        Some(season) => Ok(season.into()),
+        None => Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The season of id {} does not exist.", id))),
+    }
+}
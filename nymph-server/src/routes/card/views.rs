@@ -0,0 +1,119 @@
+//! Card view statistics.
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use chrono::{TimeDelta, Utc};
+
+use nymph_model::{card::Card, request::card::PopularCardsQuery, response::card::PopularCard};
+
+use sqlx::FromRow;
+
+use super::{CardResult, redact_card};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppQuery, AppState},
+    auth::Authentication,
+    request::validate::{Validator as _, ValidatorExt as _, value},
+};
+
+/// The most days views can be counted over.
+///
+/// Daily view counts may be removed earlier than this; see
+/// [`WorkerConfig::view_retention`](crate::config::WorkerConfig::view_retention).
+pub const MAX_DAYS: u32 = 90;
+
+/// The most cards that can be listed at once.
+pub const MAX_COUNT: u32 = 25;
+
+/// Lists the most viewed cards of a guild.
+#[debug_handler]
+pub async fn popular(
+    AppQuery(query): AppQuery<PopularCardsQuery>,
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+) -> Result<AppJson<Vec<PopularCard>>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    #[derive(FromRow)]
+    struct PopularResult {
+        #[sqlx(flatten)]
+        card: CardResult,
+        views: i64,
+        view_count: i64,
+    }
+
+    let days = value("days", query.days.unwrap_or(7))
+        .in_range(1..=MAX_DAYS)
+        .validate()?;
+    let count = value("count", query.count.unwrap_or(10))
+        .in_range(1..=MAX_COUNT)
+        .validate()?;
+
+    // today counts as one of the days
+    let since = Utc::now().date_naive() - TimeDelta::days(i64::from(days) - 1);
+
+    let results = sqlx::query_as::<_, PopularResult>(
+        r#"
+        SELECT
+            c.id, c.guild_id, c.name, c.emoji, c.category_name, c.content,
+            c.visibility, c.rarity, c.rarity_score, c.archived_at,
+            c.inserted_at, c.updated_at,
+            COALESCE(o.quantity, 0) > 0 AS owned,
+            v.views, c.view_count
+        FROM
+            card c
+        INNER JOIN
+            (
+                SELECT card_id, SUM(views) AS views
+                FROM card_view
+                WHERE day >= $3
+                GROUP BY card_id
+            ) AS v
+            ON v.card_id = c.id
+        LEFT OUTER JOIN
+            ownership AS o
+            ON o.card_id = c.id AND o.owner_id = $1
+        WHERE
+            c.guild_id = $2
+            AND c.archived_at IS NULL
+        ORDER BY v.views DESC, c.id
+        LIMIT $4
+        "#,
+    )
+    .bind(auth.id)
+    .bind(guild_id)
+    .bind(since)
+    .bind(count)
+    .fetch_all(&state.db)
+    .await?;
+
+    let (mut cards, views): (Vec<_>, Vec<_>) = results
+        .into_iter()
+        .map(|result| {
+            (
+                redact_card(Card::from(result.card), &auth),
+                (result.views, result.view_count),
+            )
+        })
+        .unzip();
+
+    state.content.load(&state.db, &mut cards).await?;
+
+    let results = cards
+        .into_iter()
+        .zip(views)
+        .map(|(card, (views, view_count))| PopularCard {
+            card,
+            views: views as u64,
+            total_views: view_count as u64,
+        })
+        .collect();
+
+    Ok(AppJson(results))
+}
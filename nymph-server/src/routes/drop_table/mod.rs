@@ -0,0 +1,282 @@
+//! Drop tables.
+//!
+//! See [`nymph_model::drop_table`].
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use chrono::{NaiveDateTime, Utc};
+
+use nymph_model::{
+    drop_table::{
+        DEFAULT_PREVIEW_PULLS, DropTable, DropTableEntry, MAX_ENTRIES, MAX_PREVIEW_PULLS,
+        MAX_WEIGHT,
+    },
+    request::drop_table::{DropTablePreviewQuery, UpdateDropTableEntryRequest},
+    response::drop_table::{DropTablePreview, DropTablePreviewEntry},
+};
+
+use sqlx::{Executor, FromRow, Sqlite};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppQuery, AppState, Payload},
+    auth::Authentication,
+    request::validate::{Validator as _, ValidatorExt as _, value},
+    roll,
+};
+
+#[derive(FromRow)]
+struct EntryResult {
+    card_id: i32,
+    weight: i64,
+    max_copies: Option<i64>,
+    updated_at: NaiveDateTime,
+}
+
+impl From<EntryResult> for DropTableEntry {
+    fn from(entry: EntryResult) -> DropTableEntry {
+        DropTableEntry {
+            card_id: entry.card_id,
+            weight: entry.weight as u32,
+            max_copies: entry.max_copies.map(|max_copies| max_copies as u32),
+            updated_at: entry.updated_at,
+        }
+    }
+}
+
+/// Gets the drop table of a guild.
+#[debug_handler]
+pub async fn show(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+) -> Result<AppJson<DropTable>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    Ok(AppJson(get_drop_table(&state.db, guild_id).await?))
+}
+
+/// Adds a card to the drop table of a guild, or changes its entry.
+#[debug_handler]
+pub async fn update(
+    State(state): State<AppState>,
+    Path((guild_id, card_id)): Path<(i64, i32)>,
+    auth: Authentication,
+    Payload(request): Payload<UpdateDropTableEntryRequest>,
+) -> Result<AppJson<DropTable>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    // a card that should never be pulled is removed instead
+    value("weight", request.weight)
+        .in_range(1..=MAX_WEIGHT)
+        .validate()?;
+
+    if let Some(max_copies) = request.max_copies {
+        value("max_copies", max_copies).in_range(1..).validate()?;
+    }
+
+    let archived = sqlx::query_as::<_, (bool,)>(
+        r#"
+        SELECT archived_at IS NOT NULL
+        FROM card
+        WHERE id = $1 AND guild_id = $2
+        "#,
+    )
+    .bind(card_id)
+    .bind(guild_id)
+    .fetch_optional(&state.db)
+    .await?;
+
+    match archived {
+        Some((false,)) => (),
+        Some((true,)) => {
+            return Err(
+                AppError::from(AppErrorKind::FieldOutOfRange("card_id".into())).with_message(
+                    format!(
+                        "Card {} has been archived, so it cannot be pulled.",
+                        card_id
+                    ),
+                ),
+            );
+        }
+        None => {
+            return Err(AppError::from(AppErrorKind::NotFound)
+                .with_message(format!("The card of id {} does not exist.", card_id)));
+        }
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO drop_table_entry (guild_id, card_id, weight, max_copies, updated_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (guild_id, card_id) DO UPDATE
+        SET
+            weight = excluded.weight,
+            max_copies = excluded.max_copies,
+            updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(guild_id)
+    .bind(card_id)
+    .bind(request.weight)
+    .bind(request.max_copies)
+    .bind(Utc::now())
+    .execute(&mut *tx)
+    .await?;
+
+    let table = get_drop_table(&mut *tx, guild_id).await?;
+
+    if table.entries.len() > MAX_ENTRIES {
+        return Err(
+            AppError::from(AppErrorKind::FieldOutOfRange("card_id".into())).with_message(format!(
+                "A drop table cannot list more than {} cards.",
+                MAX_ENTRIES
+            )),
+        );
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(
+        guild_id,
+        card_id,
+        weight = request.weight,
+        "updated drop table"
+    );
+
+    Ok(AppJson(table))
+}
+
+/// Removes a card from the drop table of a guild.
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    Path((guild_id, card_id)): Path<(i64, i32)>,
+    auth: Authentication,
+) -> Result<AppJson<DropTable>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let removed = sqlx::query(
+        r#"
+        DELETE FROM drop_table_entry
+        WHERE guild_id = $1 AND card_id = $2
+        "#,
+    )
+    .bind(guild_id)
+    .bind(card_id)
+    .execute(&state.db)
+    .await?;
+
+    if removed.rows_affected() == 0 {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("Card {} is not in the drop table.", card_id)));
+    }
+
+    tracing::info!(guild_id, card_id, "removed card from drop table");
+
+    Ok(AppJson(get_drop_table(&state.db, guild_id).await?))
+}
+
+/// Simulates pulls from the drop table of a guild.
+///
+/// Nothing is granted; the preview only shows how pulls would be spread, to
+/// check weights before a pack goes out.
+#[debug_handler]
+pub async fn preview(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+    AppQuery(query): AppQuery<DropTablePreviewQuery>,
+) -> Result<AppJson<DropTablePreview>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let pulls = value("pulls", query.pulls.unwrap_or(DEFAULT_PREVIEW_PULLS))
+        .in_range(1..=MAX_PREVIEW_PULLS)
+        .validate()?;
+
+    let seed = match query.seed.as_deref() {
+        Some(seed) => roll::decode_seed(seed).ok_or_else(|| {
+            AppError::from(AppErrorKind::FieldOutOfRange("seed".into()))
+                .with_message("A seed must be 64 hex digits.")
+        })?,
+        None => roll::seed(),
+    };
+
+    let table = get_drop_table(&state.db, guild_id).await?;
+
+    if table.entries.is_empty() {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message("The drop table does not list any cards."));
+    }
+
+    let weights = table
+        .entries
+        .iter()
+        .map(|entry| (entry.weight, entry.max_copies))
+        .collect::<Vec<_>>();
+
+    let mut counts = vec![0u32; table.entries.len()];
+    let picks = roll::pick_weighted(&seed, &weights, pulls);
+
+    for &index in picks.iter() {
+        counts[index] += 1;
+    }
+
+    let entries = table
+        .entries
+        .iter()
+        .zip(counts)
+        .map(|(entry, count)| DropTablePreviewEntry {
+            card_id: entry.card_id,
+            chance: entry.weight as f64 / table.total_weight as f64,
+            count,
+            share: count as f64 / pulls as f64,
+        })
+        .collect();
+
+    Ok(AppJson(DropTablePreview {
+        pulls,
+        exhausted: pulls - picks.len() as u32,
+        seed: roll::encode_seed(&seed),
+        total_weight: table.total_weight,
+        entries,
+    }))
+}
+
+/// Gets the drop table of a guild, ordered by card.
+pub async fn get_drop_table<'c, E>(db: E, guild_id: i64) -> Result<DropTable, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let entries = sqlx::query_as::<_, EntryResult>(
+        r#"
+        SELECT card_id, weight, max_copies, updated_at
+        FROM drop_table_entry
+        WHERE guild_id = $1
+        ORDER BY card_id
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(DropTableEntry::from)
+    .collect::<Vec<_>>();
+
+    Ok(DropTable {
+        total_weight: entries.iter().map(|entry| u64::from(entry.weight)).sum(),
+        entries,
+    })
+}
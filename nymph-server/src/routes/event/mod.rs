@@ -0,0 +1,254 @@
+//! Time-limited events.
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use chrono::{NaiveDateTime, Utc};
+
+use nymph_model::{
+    Id,
+    event::{Event, EventSummary},
+    request::event::CreateEventRequest,
+};
+
+use sqlx::{Executor, FromRow, Sqlite};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppState, Payload},
+    auth::Authentication,
+    request::validate::{Validator as _, ValidatorExt as _, value},
+};
+
+/// The maximum length of an event name.
+pub const MAX_NAME_LEN: usize = 255;
+
+#[derive(FromRow)]
+struct EventResult {
+    id: i32,
+    guild_id: i64,
+    name: String,
+    starts_at: NaiveDateTime,
+    ends_at: NaiveDateTime,
+    ended_at: Option<NaiveDateTime>,
+    participants: Option<i64>,
+    cards_granted: Option<i64>,
+}
+
+/// Lists all events in a guild, most recent first.
+#[debug_handler]
+pub async fn list(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    _auth: Authentication,
+) -> Result<AppJson<Vec<Event>>, AppError> {
+    let results = sqlx::query_as::<_, EventResult>(
+        r#"
+        SELECT
+            id, guild_id, name, starts_at, ends_at, ended_at, participants,
+            cards_granted
+        FROM event
+        WHERE guild_id = $1
+        ORDER BY starts_at DESC
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut events = Vec::with_capacity(results.len());
+
+    for result in results {
+        events.push(load_event(&state.db, result).await?);
+    }
+
+    Ok(AppJson(events))
+}
+
+/// Gets a single event.
+#[debug_handler]
+pub async fn show(
+    State(state): State<AppState>,
+    Path((guild_id, id)): Path<(i64, i32)>,
+    _auth: Authentication,
+) -> Result<AppJson<Event>, AppError> {
+    Ok(AppJson(get_event(&state.db, guild_id, id).await?))
+}
+
+/// Creates an event.
+#[debug_handler]
+pub async fn create(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+    Payload(request): Payload<CreateEventRequest>,
+) -> Result<AppJson<Event>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let name = request.name.trim();
+
+    value("name", name.len())
+        .in_range(1..=MAX_NAME_LEN)
+        .validate()?;
+
+    if request.ends_at <= request.starts_at {
+        return Err(
+            AppError::from(AppErrorKind::FieldOutOfRange("ends_at".into()))
+                .with_message("An event must end after it starts."),
+        );
+    }
+
+    let now = Utc::now();
+
+    let mut tx = state.db.begin().await?;
+
+    let id = sqlx::query_as::<_, (i32,)>(
+        r#"
+        INSERT INTO event (guild_id, name, starts_at, ends_at, inserted_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $5)
+        ON CONFLICT (guild_id, name) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(guild_id)
+    .bind(name)
+    .bind(request.starts_at)
+    .bind(request.ends_at)
+    .bind(now)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((id,)) = id else {
+        return Err(AppError::from(AppErrorKind::AlreadyExists(name.to_owned()))
+            .with_message(format!("An event named `{}` already exists.", name)));
+    };
+
+    for &card_id in request.cards.iter() {
+        let card = sqlx::query_as::<_, (i32,)>(
+            r#"
+            SELECT id
+            FROM card
+            WHERE id = $1 AND guild_id = $2
+            "#,
+        )
+        .bind(card_id)
+        .bind(guild_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if card.is_none() {
+            return Err(AppError::from(AppErrorKind::NotFound)
+                .with_message(format!("The card of id {} does not exist.", card_id)));
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO event_card (event_id, card_id)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(id)
+        .bind(card_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(guild_id, id, name, "created event");
+
+    Ok(AppJson(get_event(&state.db, guild_id, id).await?))
+}
+
+/// Finds an event that a card belongs to which has not started yet.
+///
+/// Returns the name of the event.
+pub async fn upcoming_event<'c, E>(db: E, card_id: i32) -> Result<Option<String>, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    sqlx::query_as::<_, (String,)>(
+        r#"
+        SELECT e.name
+        FROM event e, event_card ec
+        WHERE
+            ec.event_id = e.id
+            AND ec.card_id = $1
+            AND datetime(e.starts_at) > datetime($2)
+        ORDER BY e.starts_at
+        LIMIT 1
+        "#,
+    )
+    .bind(card_id)
+    .bind(Utc::now())
+    .fetch_optional(db)
+    .await
+    .map(|row| row.map(|(name,)| name))
+}
+
+/// Fetches an event of a guild.
+pub async fn get_event<'c, E>(db: E, guild_id: i64, id: i32) -> Result<Event, AppError>
+where
+    E: Executor<'c, Database = Sqlite> + Copy,
+{
+    let result = sqlx::query_as::<_, EventResult>(
+        r#"
+        SELECT
+            id, guild_id, name, starts_at, ends_at, ended_at, participants,
+            cards_granted
+        FROM event
+        WHERE id = $1 AND guild_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .fetch_optional(db)
+    .await?;
+
+    match result {
+        Some(result) => Ok(load_event(db, result).await?),
+        None => Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The event of id {} does not exist.", id))),
+    }
+}
+
+async fn load_event<'c, E>(db: E, result: EventResult) -> Result<Event, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let cards = sqlx::query_as::<_, (i32,)>(
+        r#"
+        SELECT card_id
+        FROM event_card
+        WHERE event_id = $1
+        ORDER BY card_id
+        "#,
+    )
+    .bind(result.id)
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|(card_id,)| card_id)
+    .collect();
+
+    let summary = result.ended_at.map(|ended_at| EventSummary {
+        ended_at,
+        participants: result.participants.unwrap_or(0) as u32,
+        cards_granted: result.cards_granted.unwrap_or(0) as u32,
+    });
+
+    Ok(Event {
+        id: result.id,
+        // TODO: maybe not panic when getting arbitrary data?
+        guild_id: Id::new(result.guild_id as u64).expect("valid id"),
+        name: result.name,
+        starts_at: result.starts_at,
+        ends_at: result.ends_at,
+        cards,
+        summary,
+    })
+}
@@ -0,0 +1,202 @@
+//! Gateway WebSocket.
+
+use std::time::Duration;
+
+use axum::{
+    debug_handler,
+    extract::{
+        State, WebSocketUpgrade,
+        ws::{CloseFrame, Message, WebSocket},
+    },
+    response::Response,
+};
+
+use nymph_model::{
+    Id,
+    dispatch::{Envelope, EventKind},
+    gateway::{GatewayCommand, GatewayMessage},
+};
+
+use tokio::{
+    select,
+    sync::broadcast::error::RecvError,
+    time::{Instant, sleep_until},
+};
+
+use crate::{app::AppState, auth::Authentication, gateway::Dispatch};
+
+/// How often clients must send heartbeats.
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The close code sent when a client stops sending heartbeats.
+const CLOSE_TIMED_OUT: u16 = 4000;
+
+/// Upgrades a connection to the gateway.
+#[debug_handler]
+pub async fn connect(
+    State(state): State<AppState>,
+    auth: Authentication,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(move |socket| async move {
+        let user_id = auth.id;
+
+        match session(state, auth, socket).await {
+            Ok(()) => tracing::debug!(user_id, "gateway session closed"),
+            Err(err) => tracing::debug!(user_id, %err, "gateway session dropped"),
+        }
+    })
+}
+
+/// What a client is subscribed to.
+struct Subscription {
+    guilds: Vec<Id>,
+    events: Vec<EventKind>,
+}
+
+impl Subscription {
+    fn allows(&self, envelope: &Envelope) -> bool {
+        (self.guilds.is_empty() || self.guilds.contains(&envelope.guild_id))
+            && (self.events.is_empty() || self.events.contains(&envelope.event.kind()))
+    }
+}
+
+async fn session(
+    state: AppState,
+    auth: Authentication,
+    mut socket: WebSocket,
+) -> Result<(), axum::Error> {
+    send(
+        &mut socket,
+        &GatewayMessage::Hello {
+            heartbeat_interval: HEARTBEAT_INTERVAL.as_millis() as u64,
+        },
+    )
+    .await?;
+
+    let mut receiver = state.gateway.subscribe();
+    let mut subscription: Option<Subscription> = None;
+    // the last dispatch the client has seen, or skipped
+    let mut seq = state.gateway.seq();
+    // clients get some leeway for latency
+    let mut deadline = Instant::now() + HEARTBEAT_INTERVAL * 3 / 2;
+
+    loop {
+        select! {
+            message = socket.recv() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => return Ok(()),
+                    Some(Ok(_)) => continue,
+                    Some(Err(err)) => return Err(err),
+                };
+
+                let command = match serde_json::from_str::<GatewayCommand>(text.as_str()) {
+                    Ok(command) => command,
+                    Err(err) => {
+                        tracing::debug!(user_id = auth.id, %err, "invalid gateway command");
+                        continue;
+                    }
+                };
+
+                match command {
+                    GatewayCommand::Heartbeat => {
+                        deadline = Instant::now() + HEARTBEAT_INTERVAL * 3 / 2;
+                        send(&mut socket, &GatewayMessage::HeartbeatAck).await?;
+                    }
+                    GatewayCommand::Subscribe { guilds, events, seq: resume } => {
+                        let new = Subscription { guilds, events };
+
+                        if let Some(resume) = resume {
+                            // dispatches already waiting in the receiver are
+                            // skipped once replayed
+                            let Some(missed) = state.gateway.replay(resume) else {
+                                subscription = None;
+                                send(&mut socket, &GatewayMessage::InvalidSession)
+                                    .await?;
+                                continue;
+                            };
+
+                            let mut replayed = 0;
+
+                            for dispatch in missed.iter() {
+                                if dispatch.seq > seq {
+                                    seq = dispatch.seq;
+                                }
+
+                                if new.allows(&dispatch.envelope)
+                                    && dispatch_to(&mut socket, &auth, dispatch).await?
+                                {
+                                    replayed += 1;
+                                }
+                            }
+
+                            send(&mut socket, &GatewayMessage::Resumed { replayed })
+                                .await?;
+                        }
+
+                        subscription = Some(new);
+                    }
+                }
+            }
+            dispatch = receiver.recv() => {
+                let dispatch = match dispatch {
+                    Ok(dispatch) => dispatch,
+                    // the client fell too far behind to catch up
+                    Err(RecvError::Lagged(_)) => {
+                        send(&mut socket, &GatewayMessage::InvalidSession).await?;
+                        return Ok(());
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                };
+
+                if dispatch.seq <= seq {
+                    continue;
+                }
+
+                seq = dispatch.seq;
+
+                if let Some(subscription) = subscription.as_ref()
+                    && subscription.allows(&dispatch.envelope)
+                {
+                    dispatch_to(&mut socket, &auth, &dispatch).await?;
+                }
+            }
+            _ = sleep_until(deadline) => {
+                socket
+                    .send(Message::Close(Some(CloseFrame {
+                        code: CLOSE_TIMED_OUT,
+                        reason: "heartbeat timed out".into(),
+                    })))
+                    .await?;
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Sends a dispatch to a client, if they are allowed to see it.
+///
+/// Returns `true` if the dispatch was sent.
+async fn dispatch_to(
+    socket: &mut WebSocket,
+    auth: &Authentication,
+    dispatch: &Dispatch,
+) -> Result<bool, axum::Error> {
+    let Some(envelope) = dispatch.visible_to(auth) else {
+        return Ok(false);
+    };
+
+    let message = GatewayMessage::Dispatch {
+        seq: dispatch.seq,
+        envelope: Box::new(envelope),
+    };
+
+    send(socket, &message).await.map(|_| true)
+}
+
+async fn send(socket: &mut WebSocket, message: &GatewayMessage) -> Result<(), axum::Error> {
+    let text = serde_json::to_string(message).expect("valid json");
+
+    socket.send(Message::Text(text.into())).await
+}
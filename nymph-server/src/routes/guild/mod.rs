@@ -0,0 +1,527 @@
+//! Guild settings.
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+    response::{IntoResponse as _, Response},
+};
+
+use chrono::{NaiveDateTime, Utc};
+
+use http::{HeaderMap, header};
+
+use nymph_model::{
+    Id,
+    announcement::AnnouncementSettings,
+    card::NameRules,
+    lint::LintRules,
+    request::card::inventory::{ExportOwnershipQuery, MatrixFormat},
+    response::card::{
+        OwnershipMatrix, OwnershipMatrixCard, OwnershipMatrixEntry, OwnershipMatrixUser,
+    },
+    trade_in::{TradeInReward, TradeInRules},
+    user::User,
+};
+
+use sqlx::{Executor, Sqlite, types::Json};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppQuery, AppState, Cached, Payload},
+    auth::Authentication,
+    import::MAX_NAME_LEN,
+    lint,
+    request::validate::{Validator as _, ValidatorExt as _, value},
+    routes::csv_cell,
+};
+
+/// The most cells an ownership matrix exported as CSV may have.
+pub const MAX_EXPORT_CELLS: usize = 1_000_000;
+
+/// Gets the card content lint rules of a guild.
+#[debug_handler]
+pub async fn lint_rules(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+) -> Result<AppJson<LintRules>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    Ok(AppJson(lint::get_rules(&state.db, guild_id).await?))
+}
+
+/// Replaces the card content lint rules of a guild.
+///
+/// Existing cards are not checked against the new rules.
+#[debug_handler]
+pub async fn update_lint_rules(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+    Payload(rules): Payload<LintRules>,
+) -> Result<AppJson<LintRules>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO guild_lint_rules (guild_id, max_length, banned_words, required_sections, updated_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (guild_id) DO UPDATE
+        SET
+            max_length = excluded.max_length,
+            banned_words = excluded.banned_words,
+            required_sections = excluded.required_sections,
+            updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(guild_id)
+    .bind(rules.max_length)
+    .bind(Json(&rules.banned_words))
+    .bind(Json(&rules.required_sections))
+    .bind(Utc::now())
+    .execute(&state.db)
+    .await?;
+
+    tracing::info!(guild_id, ?rules, "updated lint rules");
+
+    Ok(AppJson(rules))
+}
+
+/// The most symbols a guild's name rules may allow.
+const MAX_SYMBOLS_LEN: usize = 64;
+
+/// Gets the card name rules of a guild.
+#[debug_handler]
+pub async fn name_rules(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+) -> Result<AppJson<NameRules>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    Ok(AppJson(get_name_rules(&state.db, guild_id).await?))
+}
+
+/// Replaces the card name rules of a guild.
+///
+/// Existing cards are not checked against the new rules.
+#[debug_handler]
+pub async fn update_name_rules(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+    Payload(rules): Payload<NameRules>,
+) -> Result<AppJson<NameRules>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    if let Some(max_length) = rules.max_length {
+        value("max_length", max_length as usize)
+            .in_range(1..=MAX_NAME_LEN)
+            .validate()?;
+    }
+
+    if let Some(symbols) = rules.symbols.as_deref() {
+        value("symbols", symbols.chars().count())
+            .in_range(..=MAX_SYMBOLS_LEN)
+            .validate()?;
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO guild_name_rules (guild_id, max_length, ascii_only, symbols, updated_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (guild_id) DO UPDATE
+        SET
+            max_length = excluded.max_length,
+            ascii_only = excluded.ascii_only,
+            symbols = excluded.symbols,
+            updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(guild_id)
+    .bind(rules.max_length)
+    .bind(rules.ascii_only)
+    .bind(rules.symbols.as_deref())
+    .bind(Utc::now())
+    .execute(&state.db)
+    .await?;
+
+    tracing::info!(guild_id, ?rules, "updated name rules");
+
+    Ok(AppJson(rules))
+}
+
+/// Fetches the card name rules of a guild.
+pub async fn get_name_rules<'c, E>(db: E, guild_id: i64) -> Result<NameRules, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let rules = sqlx::query_as::<_, (Option<i64>, bool, Option<String>)>(
+        r#"
+        SELECT max_length, ascii_only, symbols
+        FROM guild_name_rules
+        WHERE guild_id = $1
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(rules
+        .map(|(max_length, ascii_only, symbols)| NameRules {
+            max_length: max_length.map(|max_length| max_length as u32),
+            ascii_only,
+            symbols,
+        })
+        .unwrap_or_default())
+}
+
+/// Gets the duplicate trade-in rules of a guild.
+#[debug_handler]
+pub async fn trade_in_rules(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    _auth: Authentication,
+    headers: HeaderMap,
+) -> Result<Cached<AppJson<TradeInRules>>, AppError> {
+    let rules = get_trade_in_rules(&state.db, guild_id).await?;
+
+    let updated_at = sqlx::query_scalar::<_, NaiveDateTime>(
+        r#"
+        SELECT updated_at
+        FROM guild_trade_in_rules
+        WHERE guild_id = $1
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_optional(&state.db)
+    .await?;
+
+    let res = Cached::new(&headers, AppJson(rules), state.cache.trade_in);
+
+    Ok(match updated_at {
+        Some(updated_at) => res.last_modified(updated_at),
+        None => res,
+    })
+}
+
+/// Replaces the duplicate trade-in rules of a guild.
+#[debug_handler]
+pub async fn update_trade_in_rules(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+    Payload(rules): Payload<TradeInRules>,
+) -> Result<AppJson<TradeInRules>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    match rules.reward {
+        Some(TradeInReward::Currency { per_copy }) => {
+            value("per_copy", per_copy).in_range(1..).validate()?;
+        }
+        Some(TradeInReward::Roll { copies }) => {
+            value("copies", copies).in_range(1..).validate()?;
+        }
+        None => (),
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO guild_trade_in_rules (guild_id, reward, updated_at)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (guild_id) DO UPDATE
+        SET
+            reward = excluded.reward,
+            updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(guild_id)
+    .bind(rules.reward.map(Json))
+    .bind(Utc::now())
+    .execute(&state.db)
+    .await?;
+
+    tracing::info!(guild_id, ?rules, "updated trade-in rules");
+
+    Ok(AppJson(rules))
+}
+
+/// Fetches the duplicate trade-in rules of a guild.
+pub async fn get_trade_in_rules<'c, E>(db: E, guild_id: i64) -> Result<TradeInRules, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let rules = sqlx::query_as::<_, (Option<Json<TradeInReward>>,)>(
+        r#"
+        SELECT reward
+        FROM guild_trade_in_rules
+        WHERE guild_id = $1
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(TradeInRules {
+        reward: rules.and_then(|(reward,)| reward).map(|reward| reward.0),
+    })
+}
+
+/// Gets what a guild announces in public.
+#[debug_handler]
+pub async fn announcements(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+) -> Result<AppJson<AnnouncementSettings>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    Ok(AppJson(get_announcements(&state.db, guild_id).await?))
+}
+
+/// Replaces what a guild announces in public.
+#[debug_handler]
+pub async fn update_announcements(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+    Payload(settings): Payload<AnnouncementSettings>,
+) -> Result<AppJson<AnnouncementSettings>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO guild_announcements (
+            guild_id, completions_channel_id, events_channel_id, updated_at
+        )
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (guild_id) DO UPDATE
+        SET
+            completions_channel_id = excluded.completions_channel_id,
+            events_channel_id = excluded.events_channel_id,
+            updated_at = excluded.updated_at
+        "#,
+    )
+    .bind(guild_id)
+    .bind(
+        settings
+            .completions_channel_id
+            .map(|channel_id| channel_id.get() as i64),
+    )
+    .bind(
+        settings
+            .events_channel_id
+            .map(|channel_id| channel_id.get() as i64),
+    )
+    .bind(Utc::now())
+    .execute(&state.db)
+    .await?;
+
+    tracing::info!(guild_id, ?settings, "updated announcements");
+
+    Ok(AppJson(settings))
+}
+
+/// Fetches what a guild announces in public.
+pub async fn get_announcements<'c, E>(
+    db: E,
+    guild_id: i64,
+) -> Result<AnnouncementSettings, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let settings = sqlx::query_as::<_, (Option<i64>, Option<i64>)>(
+        r#"
+        SELECT completions_channel_id, events_channel_id
+        FROM guild_announcements
+        WHERE guild_id = $1
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_optional(db)
+    .await?;
+
+    let (completions_channel_id, events_channel_id) = settings.unwrap_or_default();
+
+    Ok(AnnouncementSettings {
+        completions_channel_id: completions_channel_id
+            .and_then(|channel_id| Id::new(channel_id as u64)),
+        events_channel_id: events_channel_id.and_then(|channel_id| Id::new(channel_id as u64)),
+    })
+}
+
+/// Exports how many copies of every card of a guild every user owns.
+///
+/// Meant for looking into how cards are spread among users in other tools.
+/// Everything is read in a single transaction, so the cards, users and
+/// quantities agree with each other.
+#[debug_handler]
+pub async fn export_ownership(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    AppQuery(query): AppQuery<ExportOwnershipQuery>,
+    auth: Authentication,
+) -> Result<Response, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    let cards = sqlx::query_as::<_, (i32, String)>(
+        r#"
+        SELECT id, name
+        FROM card
+        WHERE guild_id = $1
+        ORDER BY id
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let users = sqlx::query_as::<_, (i32, String, Option<i64>)>(
+        r#"
+        SELECT u.id, u.display_name, da.discord_id
+        FROM
+            user u
+        LEFT OUTER JOIN
+            discord_auth AS da
+            ON da.user_id = u.id
+        WHERE
+            u.id IN (
+                SELECT o.owner_id
+                FROM ownership o, card c
+                WHERE
+                    o.card_id = c.id
+                    AND c.guild_id = $1
+                    AND o.quantity > 0
+            )
+        ORDER BY u.id
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let format = query.format.unwrap_or_default();
+
+    if format == MatrixFormat::Csv && cards.len() * users.len() > MAX_EXPORT_CELLS {
+        return Err(
+            AppError::from(AppErrorKind::FieldOutOfRange("format".into())).with_message(format!(
+                "The guild has too many cards and users to export as CSV ({} cards, {} users); export it as JSON instead.",
+                cards.len(),
+                users.len()
+            )),
+        );
+    }
+
+    let ownership = sqlx::query_as::<_, (i32, i32, i64)>(
+        r#"
+        SELECT o.card_id, o.owner_id, o.quantity
+        FROM ownership o, card c
+        WHERE
+            o.card_id = c.id
+            AND c.guild_id = $1
+            AND o.quantity > 0
+        ORDER BY o.card_id, o.owner_id
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let matrix = OwnershipMatrix {
+        cards: cards
+            .into_iter()
+            .map(|(id, name)| OwnershipMatrixCard { id, name })
+            .collect(),
+        users: users
+            .into_iter()
+            .map(|(id, display_name, discord_id)| OwnershipMatrixUser {
+                user: User { id, display_name },
+                discord_id: discord_id.and_then(|id| Id::new(id as u64)),
+            })
+            .collect(),
+        ownership: ownership
+            .into_iter()
+            .map(|(card_id, user_id, quantity)| OwnershipMatrixEntry {
+                card_id,
+                user_id,
+                quantity: quantity as u32,
+            })
+            .collect(),
+    };
+
+    match format {
+        MatrixFormat::Json => Ok(AppJson(matrix).into_response()),
+        MatrixFormat::Csv => {
+            // both are ordered by id, so cells are found by binary search
+            let mut quantities = vec![vec![0u32; matrix.users.len()]; matrix.cards.len()];
+
+            for entry in matrix.ownership.iter() {
+                let row = matrix
+                    .cards
+                    .binary_search_by_key(&entry.card_id, |card| card.id);
+                let column = matrix
+                    .users
+                    .binary_search_by_key(&entry.user_id, |user| user.user.id);
+
+                if let (Ok(row), Ok(column)) = (row, column) {
+                    quantities[row][column] = entry.quantity;
+                }
+            }
+
+            let mut writer = ::csv::Writer::from_writer(Vec::new());
+
+            // writing to memory never fails
+            writer
+                .write_record(["card_id".to_owned(), "name".to_owned()].into_iter().chain(
+                    matrix.users.iter().map(|user| {
+                        csv_cell(&format!("{} ({})", user.user.display_name, user.user.id))
+                            .into_owned()
+                    }),
+                ))
+                .expect("csv written to memory");
+
+            for (card, row) in matrix.cards.iter().zip(quantities.iter()) {
+                writer
+                    .write_record(
+                        [card.id.to_string(), csv_cell(&card.name).into_owned()]
+                            .into_iter()
+                            .chain(row.iter().map(|quantity| quantity.to_string())),
+                    )
+                    .expect("csv written to memory");
+            }
+
+            let body = writer.into_inner().expect("csv written to memory");
+
+            Ok((
+                [
+                    (header::CONTENT_TYPE, "text/csv"),
+                    (
+                        header::CONTENT_DISPOSITION,
+                        "attachment; filename=\"ownership.csv\"",
+                    ),
+                ],
+                body,
+            )
+                .into_response())
+        }
+    }
+}
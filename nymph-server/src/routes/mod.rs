@@ -1,54 +1,108 @@
 //! API routes.
 
-use std::cmp::{max, min};
+use std::borrow::Cow;
+use std::cmp::max;
+
+use nymph_model::response::Paginated;
 
 use crate::app::AppError;
 use crate::request::validate::{Validator as _, ValidatorExt as _, value};
 
+pub mod admin;
+pub mod audit;
 pub mod card;
+pub mod drop_table;
+pub mod event;
+pub mod gateway;
+pub mod guild;
+pub mod report;
+pub mod rule;
+pub mod syndication;
+pub mod telemetry;
+pub mod trade;
 pub mod user;
+pub mod view;
+pub mod webhook;
 
 /// Pagination helper.
-pub struct Pagination<T> {
-    results: Vec<T>,
+///
+/// Validates a requested page against how many results there are, so the
+/// page can be applied in the query itself with `LIMIT` and `OFFSET`.
+pub struct Pagination {
     limit: u32,
 }
 
-impl<T> Pagination<T> {
-    /// Creates a new pagination with a count limit.
-    pub fn new(results: impl Into<Vec<T>>) -> Pagination<T> {
-        Pagination {
-            results: results.into(),
-            limit: 25,
-        }
+impl Default for Pagination {
+    fn default() -> Pagination {
+        Pagination { limit: 25 }
     }
+}
 
+impl Pagination {
     /// Changes the limit of the pagination.
     ///
     /// By default, it is `25`.
-    pub fn limit(self, limit: u32) -> Pagination<T> {
-        Pagination { limit, ..self }
+    pub fn limit(self, limit: u32) -> Pagination {
+        Pagination { limit }
     }
 
-    /// Paginates the results.
-    pub fn paginate(&self, page: u32, count: u32) -> Result<&[T], AppError> {
+    /// Paginates `total` results.
+    pub fn paginate(&self, total: i64, page: u32, count: u32) -> Result<Page, AppError> {
         // limit results
         let count = value("count", count as usize)
             .in_range(1..=(self.limit as usize))
             .validate()?;
 
-        let max_page = self.results.len() / count;
+        let max_page = (total as usize).div_ceil(count);
         let page = value("page", page as usize)
             .in_range(1..=max(max_page, 1))
             .validate()?;
 
-        if self.results.len() > 0 {
-            let start = (page - 1) * count;
-            let end = min(self.results.len(), start + count);
+        Ok(Page {
+            limit: count as i64,
+            offset: ((page - 1) * count) as i64,
+            page: page as u32,
+            total,
+        })
+    }
+}
 
-            Ok(&self.results[start..end])
-        } else {
-            Ok(&[])
+/// A page of results.
+#[derive(Clone, Copy, Debug)]
+pub struct Page {
+    /// How many results are on the page.
+    pub limit: i64,
+    /// How many results come before the page.
+    pub offset: i64,
+    page: u32,
+    total: i64,
+}
+
+impl Page {
+    /// Wraps the results on the page with pagination metadata.
+    pub fn wrap<T>(self, items: Vec<T>) -> Paginated<T> {
+        let total_pages = (self.total as u64).div_ceil(self.limit as u64).max(1) as u32;
+
+        Paginated {
+            items,
+            page: self.page,
+            total_items: self.total as u64,
+            total_pages,
+            next_cursor: (self.page < total_pages).then_some(self.page + 1),
+            // filled in by `deprecation::notify`
+            warnings: Vec::new(),
         }
     }
 }
+
+/// Escapes a CSV cell that a spreadsheet would read as a formula.
+///
+/// Cells are user-provided names more often than not, so any cell starting
+/// like a formula is prefixed with a `'`, which spreadsheets hide.
+pub fn csv_cell(cell: &str) -> Cow<'_, str> {
+    if cell.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        Cow::Owned(format!("'{}", cell))
+    } else {
+        Cow::Borrowed(cell)
+    }
+}
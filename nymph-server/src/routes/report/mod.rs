@@ -0,0 +1,362 @@
+//! Content reports.
+//!
+//! Any user that can see a card may report it. Reports wait in the guild's
+//! queue until a privileged user resolves or dismisses them; both outcomes
+//! are dispatched as events, so they show up in the audit log.
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use chrono::{NaiveDateTime, Utc};
+
+use nymph_model::{
+    Id,
+    card::Visibility,
+    dispatch::{CardReport, Event},
+    report::{Report, ReportStatus},
+    request::report::{CreateReportRequest, ListReportsQuery, ResolveReportRequest},
+    response::Paginated,
+    user::User,
+};
+
+use sqlx::{Executor, FromRow, Sqlite};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppQuery, AppState, Payload},
+    auth::Authentication,
+    dispatch,
+    request::validate::{Validator as _, ValidatorExt as _, value},
+    routes::{Pagination, card::get_card},
+};
+
+/// The longest a report's reason may be.
+pub const MAX_REASON_LEN: usize = 1000;
+
+#[derive(FromRow)]
+struct ReportResult {
+    id: i32,
+    guild_id: i64,
+    card_id: i32,
+    card_name: String,
+    reporter_id: i32,
+    reporter_name: String,
+    reason: String,
+    #[sqlx(try_from = "String")]
+    status: ReportStatus,
+    resolver_id: Option<i32>,
+    resolver_name: Option<String>,
+    resolved_at: Option<NaiveDateTime>,
+    inserted_at: NaiveDateTime,
+}
+
+impl From<ReportResult> for Report {
+    fn from(report: ReportResult) -> Report {
+        Report {
+            id: report.id,
+            // TODO: maybe not panic when getting arbitrary data?
+            guild_id: Id::new(report.guild_id as u64).expect("valid id"),
+            card_id: report.card_id,
+            card_name: report.card_name,
+            reporter: User {
+                id: report.reporter_id,
+                display_name: report.reporter_name,
+            },
+            reason: report.reason,
+            status: report.status,
+            resolved_by: report
+                .resolver_id
+                .zip(report.resolver_name)
+                .map(|(id, display_name)| User { id, display_name }),
+            resolved_at: report.resolved_at,
+            created_at: report.inserted_at,
+        }
+    }
+}
+
+/// Reports a card.
+///
+/// Reporting a card again while the last report is still open replaces the
+/// reason of the open report.
+#[debug_handler]
+pub async fn create(
+    State(state): State<AppState>,
+    Path((guild_id, id)): Path<(i64, i32)>,
+    auth: Authentication,
+    Payload(request): Payload<CreateReportRequest>,
+) -> Result<AppJson<Report>, AppError> {
+    let reason = request.reason.trim();
+
+    value("reason", reason.len())
+        .in_range(1..=MAX_REASON_LEN)
+        .validate()?;
+
+    let card = get_card(&state, id, &auth).await?;
+
+    if card.guild_id.get() as i64 != guild_id {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The card of id {} does not exist.", id)));
+    }
+
+    // users may only report what they can see
+    let hidden = card.hidden.unwrap_or_default() && !auth.managed;
+
+    match card.visibility {
+        Visibility::Hidden if hidden => return Err(AppErrorKind::Hidden(card.name).into()),
+        Visibility::Private if hidden => return Err(AppErrorKind::Forbidden.into()),
+        _ => (),
+    }
+
+    if let Some(report_id) = replace_reason(&state.db, id, auth.id, reason).await? {
+        // the report is already in the queue, so nothing is dispatched
+        return Ok(AppJson(get_report(&state.db, guild_id, report_id).await?));
+    }
+
+    let inserted = sqlx::query_as::<_, (i32,)>(
+        r#"
+        INSERT INTO card_report (guild_id, card_id, reporter_id, reason, status, inserted_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (card_id, reporter_id) WHERE status = 'open' DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(guild_id)
+    .bind(id)
+    .bind(auth.id)
+    .bind(reason)
+    .bind(ReportStatus::Open.to_str())
+    .bind(Utc::now())
+    .fetch_optional(&state.db)
+    .await?;
+
+    let Some((report_id,)) = inserted else {
+        // the same report was opened since the check
+        let Some(report_id) = replace_reason(&state.db, id, auth.id, reason).await? else {
+            return Err(AppError::from(AppErrorKind::PreconditionFailed)
+                .with_message("The open report was resolved meanwhile; report the card again."));
+        };
+
+        return Ok(AppJson(get_report(&state.db, guild_id, report_id).await?));
+    };
+
+    tracing::info!(
+        id = report_id,
+        card_id = id,
+        reporter_id = auth.id,
+        "reported card"
+    );
+
+    let report = get_report(&state.db, guild_id, report_id).await?;
+
+    dispatch::emit(
+        &state,
+        auth.id,
+        Event::CardReported(CardReport {
+            report: report.clone(),
+            card,
+        }),
+    )
+    .await?;
+
+    Ok(AppJson(report))
+}
+
+/// Lists a guild's reports, oldest first.
+#[debug_handler]
+pub async fn list(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    AppQuery(query): AppQuery<ListReportsQuery>,
+    auth: Authentication,
+) -> Result<AppJson<Paginated<Report>>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let status = query.status.unwrap_or(ReportStatus::Open);
+
+    let (total,) = sqlx::query_as::<_, (i64,)>(
+        r#"
+        SELECT COUNT(*)
+        FROM card_report
+        WHERE guild_id = $1 AND status = $2
+        "#,
+    )
+    .bind(guild_id)
+    .bind(status.to_str())
+    .fetch_one(&state.db)
+    .await?;
+
+    let page = Pagination::default().limit(25).paginate(
+        total,
+        query.page.unwrap_or(1),
+        query.count.unwrap_or(25),
+    )?;
+
+    let reports = sqlx::query_as::<_, ReportResult>(
+        r#"
+        SELECT
+            r.id, r.guild_id, r.card_id, c.name AS card_name, r.reason, r.status,
+            r.resolved_at, r.inserted_at,
+            u.id AS reporter_id, u.display_name AS reporter_name,
+            m.id AS resolver_id, m.display_name AS resolver_name
+        FROM
+            card_report r
+        INNER JOIN
+            card AS c
+            ON c.id = r.card_id
+        INNER JOIN
+            user AS u
+            ON u.id = r.reporter_id
+        LEFT OUTER JOIN
+            user AS m
+            ON m.id = r.resolved_by
+        WHERE
+            r.guild_id = $1
+            AND r.status = $2
+        ORDER BY r.id
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(guild_id)
+    .bind(status.to_str())
+    .bind(page.limit)
+    .bind(page.offset)
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(Report::from)
+    .collect();
+
+    Ok(AppJson(page.wrap(reports)))
+}
+
+/// Resolves or dismisses a report.
+#[debug_handler]
+pub async fn resolve(
+    State(state): State<AppState>,
+    Path((guild_id, id)): Path<(i64, i32)>,
+    auth: Authentication,
+    Payload(request): Payload<ResolveReportRequest>,
+) -> Result<AppJson<Report>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    if request.status == ReportStatus::Open {
+        return Err(
+            AppError::from(AppErrorKind::FieldOutOfRange("status".into()))
+                .with_message("A report can only be resolved or dismissed."),
+        );
+    }
+
+    // fails early if the report does not exist
+    get_report(&state.db, guild_id, id).await?;
+
+    let closed = sqlx::query(
+        r#"
+        UPDATE card_report
+        SET status = $3, resolved_by = $4, resolved_at = $5
+        WHERE id = $1 AND guild_id = $2 AND status = $6
+        "#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .bind(request.status.to_str())
+    .bind(auth.id)
+    .bind(Utc::now())
+    .bind(ReportStatus::Open.to_str())
+    .execute(&state.db)
+    .await?;
+
+    if closed.rows_affected() == 0 {
+        return Err(AppErrorKind::ReportClosed(id).into());
+    }
+
+    tracing::info!(id, status = request.status.to_str(), "closed report");
+
+    let report = get_report(&state.db, guild_id, id).await?;
+    let card = get_card(&state, report.card_id, &auth).await?;
+
+    dispatch::emit(
+        &state,
+        auth.id,
+        Event::ReportResolved(CardReport {
+            report: report.clone(),
+            card,
+        }),
+    )
+    .await?;
+
+    Ok(AppJson(report))
+}
+
+/// Replaces the reason of a user's open report on a card, if they have one.
+///
+/// Returns the ID of the report.
+async fn replace_reason<'c, E>(
+    db: E,
+    card_id: i32,
+    reporter_id: i32,
+    reason: &str,
+) -> Result<Option<i32>, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    sqlx::query_as::<_, (i32,)>(
+        r#"
+        UPDATE card_report
+        SET reason = $3
+        WHERE card_id = $1 AND reporter_id = $2 AND status = $4
+        RETURNING id
+        "#,
+    )
+    .bind(card_id)
+    .bind(reporter_id)
+    .bind(reason)
+    .bind(ReportStatus::Open.to_str())
+    .fetch_optional(db)
+    .await
+    .map(|row| row.map(|(id,)| id))
+}
+
+/// Fetches a report in a guild.
+pub async fn get_report<'c, E>(db: E, guild_id: i64, id: i32) -> Result<Report, AppError>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let report = sqlx::query_as::<_, ReportResult>(
+        r#"
+        SELECT
+            r.id, r.guild_id, r.card_id, c.name AS card_name, r.reason, r.status,
+            r.resolved_at, r.inserted_at,
+            u.id AS reporter_id, u.display_name AS reporter_name,
+            m.id AS resolver_id, m.display_name AS resolver_name
+        FROM
+            card_report r
+        INNER JOIN
+            card AS c
+            ON c.id = r.card_id
+        INNER JOIN
+            user AS u
+            ON u.id = r.reporter_id
+        LEFT OUTER JOIN
+            user AS m
+            ON m.id = r.resolved_by
+        WHERE
+            r.id = $1
+            AND r.guild_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .fetch_optional(db)
+    .await?;
+
+    match report {
+        Some(report) => Ok(report.into()),
+        None => Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The report of id {} does not exist.", id))),
+    }
+}
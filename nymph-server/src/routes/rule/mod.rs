@@ -0,0 +1,392 @@
+//! Reward rules.
+//!
+//! A rule names a set of cards and a reward card. The worker periodically
+//! grants the reward to every user that collected the whole set; see
+//! [`crate::worker`].
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use chrono::{NaiveDateTime, Utc};
+
+use nymph_model::{
+    Id,
+    request::rule::{CreateRuleRequest, UpdateRuleRequest},
+    rule::Rule,
+};
+
+use sqlx::{Executor, FromRow, Sqlite, SqliteConnection};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppState, Payload},
+    auth::Authentication,
+    request::validate::{Validator as _, ValidatorExt as _, value},
+};
+
+/// The maximum length of a rule name.
+pub const MAX_NAME_LEN: usize = 255;
+
+#[derive(FromRow)]
+struct RuleResult {
+    id: i32,
+    guild_id: i64,
+    name: String,
+    reward_id: i32,
+    inserted_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+/// Lists all reward rules in a guild.
+#[debug_handler]
+pub async fn list(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+) -> Result<AppJson<Vec<Rule>>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let results = sqlx::query_as::<_, RuleResult>(
+        r#"
+        SELECT id, guild_id, name, reward_id, inserted_at, updated_at
+        FROM card_rule
+        WHERE guild_id = $1
+        ORDER BY name
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut rules = Vec::with_capacity(results.len());
+
+    for result in results {
+        rules.push(load_rule(&state.db, result).await?);
+    }
+
+    Ok(AppJson(rules))
+}
+
+/// Gets a single reward rule.
+#[debug_handler]
+pub async fn show(
+    State(state): State<AppState>,
+    Path((guild_id, id)): Path<(i64, i32)>,
+    auth: Authentication,
+) -> Result<AppJson<Rule>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    Ok(AppJson(get_rule(&state.db, guild_id, id).await?))
+}
+
+/// Creates a reward rule.
+#[debug_handler]
+pub async fn create(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+    Payload(request): Payload<CreateRuleRequest>,
+) -> Result<AppJson<Rule>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let name = request.name.trim();
+
+    value("name", name.len())
+        .in_range(1..=MAX_NAME_LEN)
+        .validate()?;
+
+    let now = Utc::now();
+
+    let mut tx = state.db.begin().await?;
+
+    check_cards(&mut tx, guild_id, &request.cards, request.reward_id).await?;
+
+    let id = sqlx::query_as::<_, (i32,)>(
+        r#"
+        INSERT INTO card_rule (guild_id, name, reward_id, inserted_at, updated_at)
+        VALUES ($1, $2, $3, $4, $4)
+        ON CONFLICT (guild_id, name) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(guild_id)
+    .bind(name)
+    .bind(request.reward_id)
+    .bind(now)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some((id,)) = id else {
+        return Err(AppError::from(AppErrorKind::AlreadyExists(name.to_owned()))
+            .with_message(format!("A rule named `{}` already exists.", name)));
+    };
+
+    set_cards(&mut tx, id, &request.cards).await?;
+
+    tx.commit().await?;
+
+    tracing::info!(guild_id, id, name, "created reward rule");
+
+    Ok(AppJson(get_rule(&state.db, guild_id, id).await?))
+}
+
+/// Updates a reward rule.
+///
+/// Users that were already rewarded by the rule are not rewarded again, even
+/// if the rule's set changes.
+#[debug_handler]
+pub async fn update(
+    State(state): State<AppState>,
+    Path((guild_id, id)): Path<(i64, i32)>,
+    auth: Authentication,
+    Payload(request): Payload<UpdateRuleRequest>,
+) -> Result<AppJson<Rule>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let rule = get_rule(&state.db, guild_id, id).await?;
+
+    let name = match request.name.as_deref().map(str::trim) {
+        Some(name) => {
+            value("name", name.len())
+                .in_range(1..=MAX_NAME_LEN)
+                .validate()?;
+
+            name
+        }
+        None => rule.name.as_str(),
+    };
+    let cards = request.cards.as_ref().unwrap_or(&rule.cards);
+    let reward_id = request.reward_id.unwrap_or(rule.reward_id);
+
+    let mut tx = state.db.begin().await?;
+
+    check_cards(&mut tx, guild_id, cards, reward_id).await?;
+
+    let taken = sqlx::query_as::<_, (i32,)>(
+        r#"
+        SELECT id
+        FROM card_rule
+        WHERE guild_id = $1 AND name = $2 AND id != $3
+        "#,
+    )
+    .bind(guild_id)
+    .bind(name)
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if taken.is_some() {
+        return Err(AppError::from(AppErrorKind::AlreadyExists(name.to_owned()))
+            .with_message(format!("A rule named `{}` already exists.", name)));
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE card_rule
+        SET name = $2, reward_id = $3, updated_at = $4
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(name)
+    .bind(reward_id)
+    .bind(Utc::now())
+    .execute(&mut *tx)
+    .await?;
+
+    if request.cards.is_some() {
+        set_cards(&mut tx, id, cards).await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(guild_id, id, name, "updated reward rule");
+
+    Ok(AppJson(get_rule(&state.db, guild_id, id).await?))
+}
+
+/// Deletes a reward rule.
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    Path((guild_id, id)): Path<(i64, i32)>,
+    auth: Authentication,
+) -> Result<AppJson<Rule>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let rule = get_rule(&state.db, guild_id, id).await?;
+
+    let mut tx = state.db.begin().await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM card_rule_card
+        WHERE rule_id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM card_rule_reward
+        WHERE rule_id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM card_rule
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    tracing::info!(guild_id, id, "deleted reward rule");
+
+    Ok(AppJson(rule))
+}
+
+/// Fetches a reward rule in a guild.
+pub async fn get_rule<'c, E>(db: E, guild_id: i64, id: i32) -> Result<Rule, AppError>
+where
+    E: Executor<'c, Database = Sqlite> + Copy,
+{
+    let result = sqlx::query_as::<_, RuleResult>(
+        r#"
+        SELECT id, guild_id, name, reward_id, inserted_at, updated_at
+        FROM card_rule
+        WHERE id = $1 AND guild_id = $2
+        "#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .fetch_optional(db)
+    .await?;
+
+    match result {
+        Some(result) => Ok(load_rule(db, result).await?),
+        None => Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The rule of id {} does not exist.", id))),
+    }
+}
+
+async fn load_rule<'c, E>(db: E, result: RuleResult) -> Result<Rule, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let cards = sqlx::query_as::<_, (i32,)>(
+        r#"
+        SELECT card_id
+        FROM card_rule_card
+        WHERE rule_id = $1
+        ORDER BY card_id
+        "#,
+    )
+    .bind(result.id)
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|(card_id,)| card_id)
+    .collect();
+
+    Ok(Rule {
+        id: result.id,
+        // TODO: maybe not panic when getting arbitrary data?
+        guild_id: Id::new(result.guild_id as u64).expect("valid id"),
+        name: result.name,
+        cards,
+        reward_id: result.reward_id,
+        created_at: result.inserted_at,
+        updated_at: result.updated_at,
+    })
+}
+
+/// Checks that a rule's cards and reward all belong to the guild.
+async fn check_cards(
+    db: &mut SqliteConnection,
+    guild_id: i64,
+    cards: &[i32],
+    reward_id: i32,
+) -> Result<(), AppError> {
+    if cards.is_empty() {
+        return Err(AppError::from(AppErrorKind::MissingField("cards".into()))
+            .with_message("A rule must ask for at least one card."));
+    }
+
+    if cards.contains(&reward_id) {
+        return Err(
+            AppError::from(AppErrorKind::FieldOutOfRange("reward_id".into()))
+                .with_message("A rule cannot reward a card of its own set."),
+        );
+    }
+
+    for &card_id in cards.iter().chain([&reward_id]) {
+        let card = sqlx::query_as::<_, (i32,)>(
+            r#"
+            SELECT id
+            FROM card
+            WHERE id = $1 AND guild_id = $2
+            "#,
+        )
+        .bind(card_id)
+        .bind(guild_id)
+        .fetch_optional(&mut *db)
+        .await?;
+
+        if card.is_none() {
+            return Err(AppError::from(AppErrorKind::NotFound)
+                .with_message(format!("The card of id {} does not exist.", card_id)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Replaces the set of cards a rule asks for.
+async fn set_cards(db: &mut SqliteConnection, id: i32, cards: &[i32]) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        DELETE FROM card_rule_card
+        WHERE rule_id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *db)
+    .await?;
+
+    for &card_id in cards.iter() {
+        sqlx::query(
+            r#"
+            INSERT INTO card_rule_card (rule_id, card_id)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(id)
+        .bind(card_id)
+        .execute(&mut *db)
+        .await?;
+    }
+
+    Ok(())
+}
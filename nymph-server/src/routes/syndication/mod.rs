@@ -0,0 +1,226 @@
+//! Card syndication.
+//!
+//! A guild may subscribe to a category of another guild's cards. Public cards
+//! of the category are then listed and shown in the subscribing guild, see
+//! [`crate::routes::card::list`], but every other route still only finds
+//! them in their source guild, so they cannot be edited from elsewhere.
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use chrono::{NaiveDateTime, Utc};
+
+use nymph_model::{Id, request::syndication::CreateSyndicationRequest, syndication::Syndication};
+
+use sqlx::{Executor, FromRow, Sqlite};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppState, Payload},
+    auth::Authentication,
+};
+
+#[derive(FromRow)]
+struct SyndicationResult {
+    id: i32,
+    guild_id: i64,
+    source_guild_id: i64,
+    category_name: String,
+    cards: i64,
+    inserted_at: NaiveDateTime,
+}
+
+impl From<SyndicationResult> for Syndication {
+    fn from(syndication: SyndicationResult) -> Syndication {
+        Syndication {
+            id: syndication.id,
+            // TODO: maybe not panic when getting arbitrary data?
+            guild_id: Id::new(syndication.guild_id as u64).expect("valid id"),
+            source_guild_id: Id::new(syndication.source_guild_id as u64).expect("valid id"),
+            category_name: syndication.category_name,
+            cards: syndication.cards as u32,
+            created_at: syndication.inserted_at,
+        }
+    }
+}
+
+/// Lists the categories a guild subscribes to.
+#[debug_handler]
+pub async fn list(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+) -> Result<AppJson<Vec<Syndication>>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let syndications = sqlx::query_as::<_, SyndicationResult>(
+        r#"
+        SELECT
+            gs.id, gs.guild_id, gs.source_guild_id, gs.category_name,
+            gs.inserted_at,
+            COUNT(c.id) AS cards
+        FROM
+            guild_syndication gs
+        LEFT OUTER JOIN
+            card AS c
+            ON c.guild_id = gs.source_guild_id
+                AND c.category_name = gs.category_name
+                AND c.visibility = 'public'
+                AND c.archived_at IS NULL
+        WHERE gs.guild_id = $1
+        GROUP BY gs.id
+        ORDER BY gs.source_guild_id, gs.category_name
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(Syndication::from)
+    .collect();
+
+    Ok(AppJson(syndications))
+}
+
+/// Subscribes a guild to a category of another guild's cards.
+#[debug_handler]
+pub async fn create(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+    Payload(request): Payload<CreateSyndicationRequest>,
+) -> Result<AppJson<Syndication>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let source_guild_id = request.source_guild_id.get() as i64;
+    let category = request.category_name.trim();
+
+    if source_guild_id == guild_id {
+        return Err(
+            AppError::from(AppErrorKind::FieldOutOfRange("source_guild_id".into()))
+                .with_message("A guild cannot subscribe to its own cards."),
+        );
+    }
+
+    let exists = sqlx::query_as::<_, (i32,)>(
+        r#"
+        SELECT id
+        FROM card
+        WHERE guild_id = $1 AND category_name = $2
+        LIMIT 1
+        "#,
+    )
+    .bind(source_guild_id)
+    .bind(category)
+    .fetch_optional(&state.db)
+    .await?;
+
+    if exists.is_none() {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The category `{}` does not exist.", category)));
+    }
+
+    let id = sqlx::query_as::<_, (i32,)>(
+        r#"
+        INSERT INTO guild_syndication (guild_id, source_guild_id, category_name, inserted_at)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (guild_id, source_guild_id, category_name) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(guild_id)
+    .bind(source_guild_id)
+    .bind(category)
+    .bind(Utc::now())
+    .fetch_optional(&state.db)
+    .await?;
+
+    let Some((id,)) = id else {
+        return Err(
+            AppError::from(AppErrorKind::AlreadyExists(category.to_owned())).with_message(format!(
+                "The guild already subscribes to category `{}`.",
+                category
+            )),
+        );
+    };
+
+    tracing::info!(
+        guild_id,
+        id,
+        source_guild_id,
+        category,
+        "subscribed to category"
+    );
+
+    Ok(AppJson(get_syndication(&state.db, guild_id, id).await?))
+}
+
+/// Unsubscribes a guild from a category.
+///
+/// Users keep any syndicated cards they were granted.
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    Path((guild_id, id)): Path<(i64, i32)>,
+    auth: Authentication,
+) -> Result<AppJson<Syndication>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let syndication = get_syndication(&state.db, guild_id, id).await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM guild_syndication
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&state.db)
+    .await?;
+
+    tracing::info!(guild_id, id, "unsubscribed from category");
+
+    Ok(AppJson(syndication))
+}
+
+/// Fetches a guild's subscription.
+pub async fn get_syndication<'c, E>(db: E, guild_id: i64, id: i32) -> Result<Syndication, AppError>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let syndication = sqlx::query_as::<_, SyndicationResult>(
+        r#"
+        SELECT
+            gs.id, gs.guild_id, gs.source_guild_id, gs.category_name,
+            gs.inserted_at,
+            COUNT(c.id) AS cards
+        FROM
+            guild_syndication gs
+        LEFT OUTER JOIN
+            card AS c
+            ON c.guild_id = gs.source_guild_id
+                AND c.category_name = gs.category_name
+                AND c.visibility = 'public'
+                AND c.archived_at IS NULL
+        WHERE gs.id = $1 AND gs.guild_id = $2
+        GROUP BY gs.id
+        "#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .fetch_optional(db)
+    .await?;
+
+    match syndication {
+        Some(syndication) => Ok(syndication.into()),
+        None => Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The syndication of id {} does not exist.", id))),
+    }
+}
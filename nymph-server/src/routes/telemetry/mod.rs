@@ -0,0 +1,229 @@
+//! Bot telemetry.
+//!
+//! Bots that opt in periodically report every guild they serve. Guilds that
+//! stop being reported, or that the bot reports leaving, are marked as
+//! departed. Their data is purged by the worker once
+//! [`WorkerConfig::departed_guild_retention`] is over, unless they are
+//! reported again before then. Operators can also see how many guilds and
+//! members are served.
+//!
+//! [`WorkerConfig::departed_guild_retention`]: crate::config::WorkerConfig::departed_guild_retention
+
+use std::collections::HashSet;
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use chrono::{NaiveDateTime, Utc};
+
+use nymph_model::{
+    Id,
+    request::telemetry::ReportGuildsRequest,
+    response::telemetry::{GuildPresence, GuildPresenceResponse, ReportGuildsResponse},
+};
+
+use sqlx::{FromRow, types::Json};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppState, Payload},
+    auth::Authentication,
+    request::validate::{Validator as _, ValidatorExt as _, value},
+};
+
+/// How many guilds may be reported at once.
+pub const MAX_REPORTED_GUILDS: usize = 10_000;
+
+#[derive(FromRow)]
+struct PresenceResult {
+    guild_id: i64,
+    name: String,
+    member_count: Option<i64>,
+    first_seen_at: NaiveDateTime,
+    last_seen_at: NaiveDateTime,
+    departed_at: Option<NaiveDateTime>,
+}
+
+impl PresenceResult {
+    fn into_presence(self) -> Option<GuildPresence> {
+        Some(GuildPresence {
+            id: Id::new(self.guild_id as u64)?,
+            name: self.name,
+            member_count: self.member_count.map(|count| count as u64),
+            first_seen_at: self.first_seen_at,
+            last_seen_at: self.last_seen_at,
+            departed_at: self.departed_at,
+        })
+    }
+}
+
+/// Records every guild a bot serves.
+///
+/// Guilds missing from the report are marked as departed. An empty report is
+/// refused, as it is far more likely a bot that has not connected yet than
+/// one that left every guild.
+#[debug_handler]
+pub async fn report_guilds(
+    State(state): State<AppState>,
+    auth: Authentication,
+    Payload(request): Payload<ReportGuildsRequest>,
+) -> Result<AppJson<ReportGuildsResponse>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    value("guilds", request.guilds.len())
+        .in_range(1..=MAX_REPORTED_GUILDS)
+        .validate()?;
+
+    let now = Utc::now();
+    let mut tx = state.db.begin().await?;
+
+    let served = sqlx::query_as::<_, (i64,)>(
+        r#"
+        SELECT guild_id
+        FROM guild_presence
+        WHERE departed_at IS NULL
+        "#,
+    )
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .map(|(guild_id,)| guild_id)
+    .collect::<HashSet<_>>();
+
+    let mut joined = Vec::new();
+
+    for guild in request.guilds.iter() {
+        sqlx::query(
+            r#"
+            INSERT INTO guild_presence (guild_id, name, member_count, first_seen_at, last_seen_at)
+            VALUES ($1, $2, $3, $4, $4)
+            ON CONFLICT (guild_id) DO UPDATE
+            SET
+                name = excluded.name,
+                member_count = excluded.member_count,
+                last_seen_at = excluded.last_seen_at,
+                departed_at = NULL
+            "#,
+        )
+        .bind(guild.id.get() as i64)
+        .bind(&guild.name)
+        .bind(guild.member_count.map(|count| count as i64))
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        // guilds that were never seen, or had departed, are joined again
+        if !served.contains(&(guild.id.get() as i64)) {
+            joined.push(guild.id);
+        }
+    }
+
+    let ids = request
+        .guilds
+        .iter()
+        .map(|guild| guild.id.get() as i64)
+        .collect::<Vec<_>>();
+
+    let departed = sqlx::query_as::<_, (i64,)>(
+        r#"
+        UPDATE guild_presence
+        SET departed_at = $2
+        WHERE
+            departed_at IS NULL
+            AND guild_id NOT IN (SELECT value FROM json_each($1))
+        RETURNING guild_id
+        "#,
+    )
+    .bind(Json(&ids))
+    .bind(now)
+    .fetch_all(&mut *tx)
+    .await?
+    .into_iter()
+    .filter_map(|(guild_id,)| Id::new(guild_id as u64))
+    .collect::<Vec<_>>();
+
+    tx.commit().await?;
+
+    if !joined.is_empty() || !departed.is_empty() {
+        tracing::info!(
+            joined = joined.len(),
+            departed = departed.len(),
+            "guild presence changed"
+        );
+    }
+
+    Ok(AppJson(ReportGuildsResponse { joined, departed }))
+}
+
+/// Marks a guild the bot left as departed.
+///
+/// Guilds that already departed keep when they first did, so leaving again
+/// does not delay their purge.
+#[debug_handler]
+pub async fn depart_guild(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+) -> Result<AppJson<GuildPresence>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let guild = sqlx::query_as::<_, PresenceResult>(
+        r#"
+        UPDATE guild_presence
+        SET departed_at = COALESCE(departed_at, $2)
+        WHERE guild_id = $1
+        RETURNING guild_id, name, member_count, first_seen_at, last_seen_at, departed_at
+        "#,
+    )
+    .bind(guild_id)
+    .bind(Utc::now())
+    .fetch_optional(&state.db)
+    .await?
+    .and_then(PresenceResult::into_presence);
+
+    let Some(guild) = guild else {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The guild of id {} was never reported.", guild_id)));
+    };
+
+    tracing::info!(guild_id, "guild departed");
+
+    Ok(AppJson(guild))
+}
+
+/// Lists every guild a bot has reported serving.
+#[debug_handler]
+pub async fn guilds(
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<AppJson<GuildPresenceResponse>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let guilds = sqlx::query_as::<_, PresenceResult>(
+        r#"
+        SELECT guild_id, name, member_count, first_seen_at, last_seen_at, departed_at
+        FROM guild_presence
+        ORDER BY departed_at IS NOT NULL, member_count DESC, guild_id
+        "#,
+    )
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .filter_map(PresenceResult::into_presence)
+    .collect::<Vec<_>>();
+
+    let served = guilds.iter().filter(|guild| guild.departed_at.is_none());
+
+    Ok(AppJson(GuildPresenceResponse {
+        served: served.clone().count() as u32,
+        members: served.filter_map(|guild| guild.member_count).sum(),
+        guilds,
+    }))
+}
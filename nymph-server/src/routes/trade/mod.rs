@@ -0,0 +1,427 @@
+//! Trades between users.
+//!
+//! Trades happen in two phases. The initiator first opens a trade, offering
+//! some of their cards and requesting some of the recipient's. Nothing
+//! changes hands until the recipient accepts the trade, at which point both
+//! sides must still own what they give up.
+
+use std::collections::BTreeMap;
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use chrono::{NaiveDateTime, Utc};
+
+use nymph_model::{
+    Id,
+    request::trade::{OpenTradeRequest, TradeItemRequest},
+    trade::{Trade, TradeItem, TradeStatus},
+    user::User,
+};
+
+use sqlx::{Executor, FromRow, Sqlite};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppState, Payload},
+    auth::Authentication,
+    routes::card::{
+        get_card,
+        inventory::{add_card, remove_card},
+    },
+};
+
+/// Opens a trade with another user.
+#[debug_handler]
+pub async fn open(
+    State(state): State<AppState>,
+    auth: Authentication,
+    Payload(request): Payload<OpenTradeRequest>,
+) -> Result<AppJson<Trade>, AppError> {
+    if request.recipient_id == auth.id {
+        return Err(
+            AppError::from(AppErrorKind::FieldOutOfRange("recipient_id".into()))
+                .with_message("A trade cannot be opened with yourself."),
+        );
+    }
+
+    if request.offered.is_empty() && request.requested.is_empty() {
+        return Err(AppError::from(AppErrorKind::MissingField("offered".into()))
+            .with_message("A trade must offer or request at least one card."));
+    }
+
+    if !state.users.exists(request.recipient_id).await? {
+        return Err(AppError::from(AppErrorKind::NotFound).with_message(format!(
+            "The user of id {} does not exist.",
+            request.recipient_id
+        )));
+    }
+
+    let guild_id = request.guild_id.get() as i64;
+    let offered = merge_items("offered", &request.offered)?;
+    let requested = merge_items("requested", &request.requested)?;
+
+    // every card must be tradeable in the guild
+    for &card_id in offered.keys().chain(requested.keys()) {
+        let card = get_card(&state, card_id, &auth).await?;
+
+        if card.guild_id.get() as i64 != guild_id {
+            return Err(AppError::from(AppErrorKind::NotFound)
+                .with_message(format!("The card of id {} does not exist.", card_id)));
+        }
+
+        if card.archived_at.is_some() {
+            return Err(
+                AppError::from(AppErrorKind::InvalidTransfer(card.name.to_owned())).with_message(
+                    format!(
+                        "Card `{}` cannot be traded because it has been archived.",
+                        &card.name
+                    ),
+                ),
+            );
+        }
+    }
+
+    // the initiator must own what they offer; the recipient's side is only
+    // checked once they accept
+    for (&card_id, &quantity) in offered.iter() {
+        check_owned(&state.db, auth.id, card_id, quantity).await?;
+    }
+
+    let now = Utc::now();
+
+    let mut tx = state.db.begin().await?;
+
+    let (id,) = sqlx::query_as::<_, (i32,)>(
+        r#"
+        INSERT INTO trade (guild_id, initiator_id, recipient_id, status, inserted_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $5)
+        RETURNING id
+        "#,
+    )
+    .bind(guild_id)
+    .bind(auth.id)
+    .bind(request.recipient_id)
+    .bind(TradeStatus::Open.to_str())
+    .bind(now)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let items = offered
+        .iter()
+        .map(|item| (auth.id, item))
+        .chain(requested.iter().map(|item| (request.recipient_id, item)));
+
+    for (owner_id, (card_id, quantity)) in items {
+        sqlx::query(
+            r#"
+            INSERT INTO trade_item (trade_id, owner_id, card_id, quantity)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(id)
+        .bind(owner_id)
+        .bind(card_id)
+        .bind(quantity)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(
+        id,
+        initiator_id = auth.id,
+        recipient_id = request.recipient_id,
+        "opened trade"
+    );
+
+    Ok(AppJson(get_trade(&state.db, id).await?))
+}
+
+/// Gets a single trade.
+#[debug_handler]
+pub async fn show(
+    Path((id,)): Path<(i32,)>,
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<AppJson<Trade>, AppError> {
+    let trade = get_trade(&state.db, id).await?;
+
+    // only the two sides of a trade may see it
+    if !is_party(&trade, &auth) && !auth.managed {
+        return Err(AppErrorKind::InsufficientPermissions.into());
+    }
+
+    Ok(AppJson(trade))
+}
+
+/// Accepts a trade, exchanging the cards of both sides.
+#[debug_handler]
+pub async fn accept(
+    Path((id,)): Path<(i32,)>,
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<AppJson<Trade>, AppError> {
+    let trade = get_trade(&state.db, id).await?;
+
+    // only the recipient may accept a trade
+    if auth.id != trade.recipient.id && !auth.managed {
+        return Err(AppErrorKind::InsufficientPermissions.into());
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    // closing the trade first keeps a concurrent accept or cancel from
+    // seeing it as open
+    let closed = sqlx::query(
+        r#"
+        UPDATE trade
+        SET status = $2, updated_at = $3
+        WHERE id = $1 AND status = $4
+        "#,
+    )
+    .bind(id)
+    .bind(TradeStatus::Accepted.to_str())
+    .bind(Utc::now())
+    .bind(TradeStatus::Open.to_str())
+    .execute(&mut *tx)
+    .await?;
+
+    if closed.rows_affected() == 0 {
+        return Err(AppErrorKind::TradeClosed(id).into());
+    }
+
+    let sides = [
+        (&trade.offered, trade.initiator.id, trade.recipient.id),
+        (&trade.requested, trade.recipient.id, trade.initiator.id),
+    ];
+
+    for (items, from_id, to_id) in sides {
+        for item in items {
+            for _ in 0..item.quantity {
+                if remove_card(&mut *tx, from_id, item.card_id)
+                    .await?
+                    .is_none()
+                {
+                    // dropping the transaction rolls back the exchange
+                    return Err(
+                        AppError::from(AppErrorKind::InvalidTransfer(item.name.to_owned()))
+                            .with_message(format!(
+                                "Card `{}` cannot be traded because user does not own enough copies of that card.",
+                                &item.name
+                            )),
+                    );
+                }
+
+                add_card(&mut *tx, to_id, item.card_id).await?;
+            }
+        }
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(id, "accepted trade");
+
+    Ok(AppJson(get_trade(&state.db, id).await?))
+}
+
+/// Cancels a trade.
+#[debug_handler]
+pub async fn cancel(
+    Path((id,)): Path<(i32,)>,
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<AppJson<Trade>, AppError> {
+    let trade = get_trade(&state.db, id).await?;
+
+    // either side may back out of a trade
+    if !is_party(&trade, &auth) && !auth.managed {
+        return Err(AppErrorKind::InsufficientPermissions.into());
+    }
+
+    let closed = sqlx::query(
+        r#"
+        UPDATE trade
+        SET status = $2, updated_at = $3
+        WHERE id = $1 AND status = $4
+        "#,
+    )
+    .bind(id)
+    .bind(TradeStatus::Cancelled.to_str())
+    .bind(Utc::now())
+    .bind(TradeStatus::Open.to_str())
+    .execute(&state.db)
+    .await?;
+
+    if closed.rows_affected() == 0 {
+        return Err(AppErrorKind::TradeClosed(id).into());
+    }
+
+    tracing::info!(id, "cancelled trade");
+
+    Ok(AppJson(get_trade(&state.db, id).await?))
+}
+
+/// Fetches a trade and the cards on both of its sides.
+pub async fn get_trade<'c, E>(db: E, id: i32) -> Result<Trade, AppError>
+where
+    E: Executor<'c, Database = Sqlite> + Copy,
+{
+    #[derive(FromRow)]
+    struct TradeResult {
+        id: i32,
+        guild_id: i64,
+        initiator_id: i32,
+        initiator_name: String,
+        recipient_id: i32,
+        recipient_name: String,
+        #[sqlx(try_from = "String")]
+        status: TradeStatus,
+        inserted_at: NaiveDateTime,
+        updated_at: NaiveDateTime,
+    }
+
+    #[derive(FromRow)]
+    struct ItemResult {
+        owner_id: i32,
+        card_id: i32,
+        name: String,
+        quantity: i64,
+    }
+
+    let trade = sqlx::query_as::<_, TradeResult>(
+        r#"
+        SELECT
+            t.id, t.guild_id, t.status, t.inserted_at, t.updated_at,
+            i.id AS initiator_id, i.display_name AS initiator_name,
+            r.id AS recipient_id, r.display_name AS recipient_name
+        FROM
+            trade t
+        INNER JOIN
+            user AS i
+            ON i.id = t.initiator_id
+        INNER JOIN
+            user AS r
+            ON r.id = t.recipient_id
+        WHERE
+            t.id = $1
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(db)
+    .await?;
+
+    let Some(trade) = trade else {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The trade of id {} does not exist.", id)));
+    };
+
+    let items = sqlx::query_as::<_, ItemResult>(
+        r#"
+        SELECT ti.owner_id, ti.card_id, c.name, ti.quantity
+        FROM
+            trade_item ti
+        INNER JOIN
+            card AS c
+            ON c.id = ti.card_id
+        WHERE
+            ti.trade_id = $1
+        ORDER BY c.name
+        "#,
+    )
+    .bind(id)
+    .fetch_all(db)
+    .await?;
+
+    let (offered, requested): (Vec<_>, Vec<_>) = items
+        .into_iter()
+        .partition(|item| item.owner_id == trade.initiator_id);
+
+    let into_item = |item: ItemResult| TradeItem {
+        card_id: item.card_id,
+        name: item.name,
+        quantity: item.quantity as u32,
+    };
+
+    Ok(Trade {
+        id: trade.id,
+        // TODO: maybe not panic when getting arbitrary data?
+        guild_id: Id::new(trade.guild_id as u64).expect("valid id"),
+        initiator: User {
+            id: trade.initiator_id,
+            display_name: trade.initiator_name,
+        },
+        recipient: User {
+            id: trade.recipient_id,
+            display_name: trade.recipient_name,
+        },
+        status: trade.status,
+        offered: offered.into_iter().map(into_item).collect(),
+        requested: requested.into_iter().map(into_item).collect(),
+        created_at: trade.inserted_at,
+        updated_at: trade.updated_at,
+    })
+}
+
+/// Checks if the authenticated user is either side of a trade.
+fn is_party(trade: &Trade, auth: &Authentication) -> bool {
+    auth.id == trade.initiator.id || auth.id == trade.recipient.id
+}
+
+/// Merges the items of one side of a trade by card, so the same card listed
+/// twice is given up twice.
+fn merge_items(field: &str, items: &[TradeItemRequest]) -> Result<BTreeMap<i32, u32>, AppError> {
+    let mut merged = BTreeMap::new();
+
+    for item in items {
+        let quantity = item.quantity.unwrap_or(1);
+
+        if quantity == 0 {
+            return Err(AppErrorKind::FieldOutOfRange(format!("{}.quantity", field)).into());
+        }
+
+        *merged.entry(item.card_id).or_insert(0) += quantity;
+    }
+
+    Ok(merged)
+}
+
+/// Checks that a user owns at least `quantity` copies of a card.
+async fn check_owned<'c, E>(
+    db: E,
+    owner_id: i32,
+    card_id: i32,
+    quantity: u32,
+) -> Result<(), AppError>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let owned = sqlx::query_as::<_, (String, i64)>(
+        r#"
+        SELECT c.name, COALESCE(o.quantity, 0)
+        FROM
+            card c
+        LEFT OUTER JOIN
+            ownership AS o
+            ON o.card_id = c.id AND o.owner_id = $1
+        WHERE
+            c.id = $2
+        "#,
+    )
+    .bind(owner_id)
+    .bind(card_id)
+    .fetch_one(db)
+    .await?;
+
+    match owned {
+        (_, owned) if owned >= quantity as i64 => Ok(()),
+        (name, _) => Err(
+            AppError::from(AppErrorKind::InvalidTransfer(name.to_owned())).with_message(format!(
+                "Card `{}` cannot be traded because user does not own enough copies of that card.",
+                &name
+            )),
+        ),
+    }
+}
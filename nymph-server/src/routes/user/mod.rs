@@ -1,31 +1,205 @@
 //! User editing and authorization.
 
 use crate::{
-    app::{AppError, AppErrorKind, AppJson, AppState},
+    app::{AppError, AppErrorKind, AppJson, AppQuery, AppState, Payload},
     auth::{Authentication, Claims},
+    request::validate::{Validator as _, ValidatorExt as _, value},
 };
 
 use axum::{debug_handler, extract::State};
 
-use chrono::{DateTime, TimeDelta, Utc};
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
 
-use sqlx::{Acquire as _, FromRow};
+use sqlx::{Acquire as _, FromRow, SqliteConnection};
 
 use nymph_model::{
-    request::user::UpdateDiscordUserRequest, response::user::UpdateDiscordUserResponse, user::User,
+    Id,
+    request::user::{
+        BatchUpdateDiscordUsersRequest, StaleDiscordUsersQuery, UpdateDiscordUserRequest,
+    },
+    response::user::{StaleDiscordUser, UpdateDiscordUserResponse},
+    user::User,
 };
 
+/// The most users that may be updated in one batch.
+pub const MAX_BATCH_LEN: usize = 100;
+
 /// Updates user information from discord.
 #[debug_handler]
 pub async fn discord(
     State(state): State<AppState>,
     auth: Authentication,
-    AppJson(request): AppJson<UpdateDiscordUserRequest>,
+    Payload(request): Payload<UpdateDiscordUserRequest>,
 ) -> Result<AppJson<UpdateDiscordUserResponse>, AppError> {
     if !auth.managed {
         return Err(AppErrorKind::Forbidden.into());
     }
 
+    let mut conn = state.db.acquire().await?;
+
+    let user = upsert_discord_user(
+        &mut conn,
+        request.discord_id,
+        &request.display_name,
+        Utc::now(),
+    )
+    .await?;
+
+    Ok(AppJson(discord_response(
+        &state,
+        user,
+        request.discord_id,
+        request.generate_token,
+    )?))
+}
+
+/// Updates the information of many users from discord at once.
+///
+/// Users are updated in a single transaction, and returned in the order they
+/// were given. Users that could not be looked up are only marked as such.
+#[debug_handler]
+pub async fn discord_batch(
+    State(state): State<AppState>,
+    auth: Authentication,
+    Payload(request): Payload<BatchUpdateDiscordUsersRequest>,
+) -> Result<AppJson<Vec<UpdateDiscordUserResponse>>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    value("users", request.users.len() + request.failed.len())
+        .in_range(1..=MAX_BATCH_LEN)
+        .validate()?;
+
+    let now = Utc::now();
+
+    let mut tx = state.db.begin().await?;
+    let mut users = Vec::with_capacity(request.users.len());
+
+    for discord_user in request.users.iter() {
+        let user = upsert_discord_user(
+            &mut tx,
+            discord_user.discord_id,
+            &discord_user.display_name,
+            now,
+        )
+        .await?;
+
+        users.push(discord_response(
+            &state,
+            user,
+            discord_user.discord_id,
+            request.generate_tokens,
+        )?);
+    }
+
+    for discord_id in request.failed.iter() {
+        sqlx::query(
+            r#"
+            UPDATE discord_auth
+            SET refresh_failed_at = $2
+            WHERE discord_id = $1
+            "#,
+        )
+        .bind(discord_id.get() as i64)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(AppJson(users))
+}
+
+/// Lists recently active users whose display names may be out of date.
+///
+/// A user is active if they caused an event or were granted a card in the
+/// last `active_within` days, and stale if their display name was not
+/// refreshed, nor failed to be, in the last `max_age` hours. The least
+/// recently refreshed users come first, so a bot can refresh them from Discord
+/// in passes.
+#[debug_handler]
+pub async fn discord_stale(
+    State(state): State<AppState>,
+    auth: Authentication,
+    AppQuery(query): AppQuery<StaleDiscordUsersQuery>,
+) -> Result<AppJson<Vec<StaleDiscordUser>>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let count = query.count.unwrap_or(MAX_BATCH_LEN as u32);
+
+    value("count", count as usize)
+        .in_range(1..=MAX_BATCH_LEN)
+        .validate()?;
+
+    let now = Utc::now();
+    let refreshed_before = now - TimeDelta::hours(query.max_age.unwrap_or(24).into());
+    let active_after = now - TimeDelta::days(query.active_within.unwrap_or(7).into());
+
+    #[derive(FromRow)]
+    struct StaleQuery {
+        id: i32,
+        display_name: String,
+        discord_id: i64,
+        refreshed_at: Option<NaiveDateTime>,
+    }
+
+    let users = sqlx::query_as::<_, StaleQuery>(
+        r#"
+        SELECT u.id, u.display_name, da.discord_id, da.refreshed_at
+        FROM user u, discord_auth da
+        WHERE
+            u.id = da.user_id
+            AND (da.refreshed_at IS NULL OR da.refreshed_at < $1)
+            AND (da.refresh_failed_at IS NULL OR da.refresh_failed_at < $1)
+            AND (
+                EXISTS (
+                    SELECT 1 FROM event_log e
+                    WHERE e.actor_id = u.id AND e.inserted_at >= $2
+                )
+                OR EXISTS (
+                    SELECT 1 FROM ownership o
+                    WHERE o.owner_id = u.id AND o.granted_at >= $2
+                )
+            )
+        ORDER BY
+            COALESCE(da.refresh_failed_at, da.refreshed_at) IS NOT NULL,
+            COALESCE(da.refresh_failed_at, da.refreshed_at)
+        LIMIT $3
+        "#,
+    )
+    .bind(refreshed_before)
+    .bind(active_after)
+    .bind(count)
+    .fetch_all(&state.db)
+    .await?
+    .into_iter()
+    .map(|user| StaleDiscordUser {
+        user: User {
+            id: user.id,
+            display_name: user.display_name,
+        },
+        // TODO: maybe not panic when getting arbitrary data?
+        discord_id: Id::new(user.discord_id as u64).expect("valid id"),
+        refreshed_at: user.refreshed_at,
+    })
+    .collect();
+
+    Ok(AppJson(users))
+}
+
+/// Finds the user of a discord id, creating them if they do not exist yet.
+///
+/// Stale display names are updated along the way.
+async fn upsert_discord_user(
+    conn: &mut SqliteConnection,
+    discord_id: Id,
+    display_name: &str,
+    now: DateTime<Utc>,
+) -> Result<User, AppError> {
     #[derive(Debug, FromRow)]
     #[allow(dead_code)]
     struct UserQuery {
@@ -36,10 +210,6 @@ pub async fn discord(
         updated_at: DateTime<Utc>,
     }
 
-    let mut conn = state.db.acquire().await?;
-
-    let now = Utc::now();
-
     let user = sqlx::query_as::<_, UserQuery>(
         r#"
         SELECT u.id, u.display_name, u.managed, u.inserted_at, u.updated_at
@@ -49,16 +219,16 @@ pub async fn discord(
             AND da.discord_id = $1
         "#,
     )
-    .bind(request.discord_id.get() as i64)
+    .bind(discord_id.get() as i64)
     .fetch_optional(&mut *conn)
     .await?;
 
     let user = match user {
         // check if we need to update the display name
-        Some(user) if user.display_name != request.display_name => {
+        Some(user) if user.display_name != display_name => {
             tracing::info!(
                 ?user,
-                new = { &request.display_name },
+                new = display_name,
                 "proxy: updating stale display name",
             );
 
@@ -70,7 +240,7 @@ pub async fn discord(
                 "#,
             )
             .bind(user.id)
-            .bind(&request.display_name)
+            .bind(display_name)
             .bind(now)
             .execute(&mut *conn)
             .await?;
@@ -89,7 +259,7 @@ pub async fn discord(
                 RETURNING id, display_name, managed, inserted_at, updated_at
                 "#,
             )
-            .bind(&request.display_name)
+            .bind(display_name)
             .bind(now)
             .fetch_one(&mut *tx)
             .await?;
@@ -99,12 +269,12 @@ pub async fn discord(
             // create discord auth
             sqlx::query(
                 r#"
-                INSERT INTO discord_auth (user_id, discord_id, inserted_at)
-                VALUES ($1, $2, $3)
+                INSERT INTO discord_auth (user_id, discord_id, inserted_at, refreshed_at)
+                VALUES ($1, $2, $3, $3)
                 "#,
             )
             .bind(user.id)
-            .bind(request.discord_id.get() as i64)
+            .bind(discord_id.get() as i64)
             .bind(now)
             .execute(&mut *tx)
             .await?;
@@ -115,22 +285,43 @@ pub async fn discord(
         }
     };
 
-    let user = User {
+    // the name was just confirmed with discord
+    sqlx::query(
+        r#"
+        UPDATE discord_auth
+        SET refreshed_at = $2, refresh_failed_at = NULL
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(user.id)
+    .bind(now)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(User {
         id: user.id,
-        display_name: user.display_name.clone(),
-    };
+        display_name: user.display_name,
+    })
+}
 
+/// Builds the response for an updated user, signing a token if asked to.
+fn discord_response(
+    state: &AppState,
+    user: User,
+    discord_id: Id,
+    generate_token: bool,
+) -> Result<UpdateDiscordUserResponse, AppError> {
     // create claims
-    let access_token = if request.generate_token {
+    let access_token = if generate_token {
         let claims = Claims::builder(user.id).exp(TimeDelta::minutes(15)).build();
         Some(claims.encode(&state.keys)?)
     } else {
         None
     };
 
-    Ok(AppJson(UpdateDiscordUserResponse {
+    Ok(UpdateDiscordUserResponse {
         user,
-        discord_id: request.discord_id,
+        discord_id,
         access_token,
-    }))
+    })
 }
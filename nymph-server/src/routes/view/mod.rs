@@ -0,0 +1,136 @@
+//! Shared view state.
+//!
+//! See [`nymph_model::view`].
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+
+use nymph_model::{
+    request::view::CreateViewRequest,
+    view::{DEFAULT_VIEW_TTL, MAX_VIEW_LEN, MAX_VIEW_TTL, View},
+};
+
+use serde_json::Value;
+
+use sqlx::{Executor, FromRow, Sqlite, types::Json};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppState, Payload},
+    auth::Authentication,
+    request::validate::{Validator as _, ValidatorExt as _, value},
+};
+
+/// Stores a view.
+#[debug_handler]
+pub async fn create(
+    State(state): State<AppState>,
+    auth: Authentication,
+    Payload(request): Payload<CreateViewRequest>,
+) -> Result<AppJson<View>, AppError> {
+    let ttl = value("ttl", request.ttl.unwrap_or(DEFAULT_VIEW_TTL))
+        .in_range(1..=MAX_VIEW_TTL)
+        .validate()?;
+
+    let len = serde_json::to_string(&request.data)
+        .expect("valid json")
+        .len();
+
+    if len > MAX_VIEW_LEN {
+        return Err(
+            AppError::from(AppErrorKind::FieldOutOfRange("data".into())).with_message(format!(
+                "A view cannot hold more than {} bytes of state.",
+                MAX_VIEW_LEN
+            )),
+        );
+    }
+
+    let now = Utc::now();
+    let expires_at = now + TimeDelta::seconds(ttl.into());
+
+    let (id,) = sqlx::query_as::<_, (i32,)>(
+        r#"
+        INSERT INTO view_state (user_id, data, expires_at, created_at)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+    )
+    .bind(auth.id)
+    .bind(Json(&request.data))
+    .bind(expires_at)
+    .bind(now)
+    .fetch_one(&state.db)
+    .await?;
+
+    tracing::debug!(id, user_id = auth.id, ttl, "stored view");
+
+    Ok(AppJson(View {
+        id,
+        data: request.data,
+        expires_at: expires_at.naive_utc(),
+        created_at: now.naive_utc(),
+    }))
+}
+
+/// Gets a view.
+#[debug_handler]
+pub async fn show(
+    Path((id,)): Path<(i32,)>,
+    State(state): State<AppState>,
+    auth: Authentication,
+) -> Result<AppJson<View>, AppError> {
+    let (user_id, view) = get_view(&state.db, id).await?;
+
+    // only the user that stored a view may read it
+    if user_id != auth.id && !auth.managed {
+        return Err(AppErrorKind::InsufficientPermissions.into());
+    }
+
+    Ok(AppJson(view))
+}
+
+/// Fetches a view that has not expired, along with the ID of the user that
+/// stored it.
+pub async fn get_view<'c, E>(db: E, id: i32) -> Result<(i32, View), AppError>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    #[derive(FromRow)]
+    struct ViewResult {
+        id: i32,
+        user_id: i32,
+        data: Json<Value>,
+        expires_at: NaiveDateTime,
+        created_at: NaiveDateTime,
+    }
+
+    let view = sqlx::query_as::<_, ViewResult>(
+        r#"
+        SELECT id, user_id, data, expires_at, created_at
+        FROM view_state
+        WHERE id = $1 AND datetime(expires_at) > datetime($2)
+        "#,
+    )
+    .bind(id)
+    .bind(Utc::now())
+    .fetch_optional(db)
+    .await?;
+
+    let Some(view) = view else {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The view of id {} does not exist.", id)));
+    };
+
+    Ok((
+        view.user_id,
+        View {
+            id: view.id,
+            data: view.data.0,
+            expires_at: view.expires_at,
+            created_at: view.created_at,
+        },
+    ))
+}
@@ -0,0 +1,266 @@
+//! Outbound webhooks.
+//!
+//! Events are not delivered while handling the request that caused them.
+//! Instead, a delivery is written to an outbox for every webhook in the guild,
+//! and the worker posts them in the background, retrying failed deliveries.
+
+use axum::{
+    debug_handler,
+    extract::{Path, State},
+};
+
+use base16::encode_lower;
+
+use chrono::{NaiveDateTime, Utc};
+
+use http::Uri;
+
+use nymph_model::{
+    Id,
+    dispatch::Envelope,
+    request::webhook::{CreateWebhookRequest, ReplayQuery},
+    response::webhook::ReplayResponse,
+    webhook::Webhook,
+};
+
+use rand::{Rng as _, SeedableRng as _, rngs::StdRng};
+
+use sqlx::{Executor, FromRow, Sqlite, types::Json};
+
+use crate::{
+    app::{AppError, AppErrorKind, AppJson, AppQuery, AppState, Payload},
+    auth::Authentication,
+    request::validate::{Validator as _, ValidatorExt as _, value},
+};
+
+/// The most events a single replay returns.
+pub const MAX_REPLAY_COUNT: u32 = 1000;
+
+#[derive(FromRow)]
+struct WebhookResult {
+    id: i32,
+    guild_id: i64,
+    url: String,
+    inserted_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+impl From<WebhookResult> for Webhook {
+    fn from(value: WebhookResult) -> Self {
+        Webhook {
+            id: value.id,
+            // TODO: maybe not panic when getting arbitrary data?
+            guild_id: Id::new(value.guild_id as u64).expect("valid id"),
+            url: value.url,
+            secret: None,
+            created_at: value.inserted_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+/// Lists all webhooks in a guild.
+#[debug_handler]
+pub async fn list(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+) -> Result<AppJson<Vec<Webhook>>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let webhooks = sqlx::query_as::<_, WebhookResult>(
+        r#"
+        SELECT id, guild_id, url, inserted_at, updated_at
+        FROM webhook
+        WHERE guild_id = $1
+        ORDER BY id
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(AppJson(webhooks.into_iter().map(Webhook::from).collect()))
+}
+
+/// Registers a webhook.
+///
+/// The webhook's secret is only ever returned here.
+#[debug_handler]
+pub async fn create(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    auth: Authentication,
+    Payload(request): Payload<CreateWebhookRequest>,
+) -> Result<AppJson<Webhook>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let url = request.url.trim();
+
+    let valid = url.parse::<Uri>().is_ok_and(|uri| {
+        matches!(uri.scheme_str(), Some("http" | "https")) && uri.host().is_some()
+    });
+
+    if !valid {
+        return Err(AppError::from(AppErrorKind::FieldOutOfRange("url".into()))
+            .with_message("A webhook url must be an absolute http or https url."));
+    }
+
+    let secret = random_secret();
+    let now = Utc::now();
+
+    let webhook = sqlx::query_as::<_, WebhookResult>(
+        r#"
+        INSERT INTO webhook (guild_id, url, secret, inserted_at, updated_at)
+        VALUES ($1, $2, $3, $4, $4)
+        ON CONFLICT (guild_id, url) DO NOTHING
+        RETURNING id, guild_id, url, inserted_at, updated_at
+        "#,
+    )
+    .bind(guild_id)
+    .bind(url)
+    .bind(&secret)
+    .bind(now)
+    .fetch_optional(&state.db)
+    .await?;
+
+    let Some(webhook) = webhook else {
+        return Err(AppError::from(AppErrorKind::AlreadyExists(url.to_owned()))
+            .with_message(format!("A webhook for `{}` already exists.", url)));
+    };
+
+    tracing::info!(guild_id, id = webhook.id, url, "created webhook");
+
+    Ok(AppJson(Webhook {
+        secret: Some(secret),
+        ..webhook.into()
+    }))
+}
+
+/// Deletes a webhook, dropping any deliveries still waiting to be sent.
+#[debug_handler]
+pub async fn delete(
+    State(state): State<AppState>,
+    Path((guild_id, id)): Path<(i64, i32)>,
+    auth: Authentication,
+) -> Result<AppJson<Webhook>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let webhook = sqlx::query_as::<_, WebhookResult>(
+        r#"
+        DELETE FROM webhook
+        WHERE id = $1 AND guild_id = $2
+        RETURNING id, guild_id, url, inserted_at, updated_at
+        "#,
+    )
+    .bind(id)
+    .bind(guild_id)
+    .fetch_optional(&state.db)
+    .await?;
+
+    let Some(webhook) = webhook else {
+        return Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The webhook of id {} does not exist.", id)));
+    };
+
+    tracing::info!(guild_id, id, "deleted webhook");
+
+    Ok(AppJson(webhook.into()))
+}
+
+/// Replays a guild's logged events after a sequence number.
+///
+/// Meant for webhook consumers catching up after downtime. Events are only
+/// kept for as long as the event log's retention allows.
+#[debug_handler]
+pub async fn replay(
+    State(state): State<AppState>,
+    Path((guild_id,)): Path<(i64,)>,
+    AppQuery(query): AppQuery<ReplayQuery>,
+    auth: Authentication,
+) -> Result<AppJson<ReplayResponse>, AppError> {
+    if !auth.managed {
+        return Err(AppErrorKind::Forbidden.into());
+    }
+
+    let after = query.after.unwrap_or(0);
+    let count = value("count", query.count.unwrap_or(100))
+        .in_range(1..=MAX_REPLAY_COUNT)
+        .validate()?;
+
+    let results = sqlx::query_as::<_, (i64, Json<Envelope>)>(
+        r#"
+        SELECT seq, payload
+        FROM event_log
+        WHERE guild_id = $1 AND seq > $2
+        ORDER BY seq
+        LIMIT $3
+        "#,
+    )
+    .bind(guild_id)
+    .bind(after as i64)
+    .bind(count)
+    .fetch_all(&state.db)
+    .await?;
+
+    // sequence numbers are never reused, so events were removed if one after
+    // `after` ever was
+    let pruned = sqlx::query_as::<_, (i64,)>(
+        r#"
+        SELECT seq
+        FROM event_log_pruned
+        WHERE guild_id = $1
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_optional(&state.db)
+    .await?;
+    let truncated = pruned.is_some_and(|(seq,)| seq as u64 > after);
+
+    let events = results
+        .into_iter()
+        .map(|(seq, Json(envelope))| Envelope {
+            seq: Some(seq as u64),
+            ..envelope
+        })
+        .collect();
+
+    Ok(AppJson(ReplayResponse { events, truncated }))
+}
+
+/// Queues an event for delivery to every webhook in its guild.
+pub async fn enqueue<'c, E>(db: E, envelope: &Envelope) -> Result<(), sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO webhook_delivery (webhook_id, event, payload, next_attempt_at, inserted_at)
+        SELECT id, $2, $3, $4, $4
+        FROM webhook
+        WHERE guild_id = $1
+        "#,
+    )
+    .bind(envelope.guild_id.get() as i64)
+    .bind(envelope.event.kind().to_str())
+    .bind(Json(envelope))
+    .bind(Utc::now())
+    .execute(db)
+    .await
+    .map(|_| ())
+}
+
+/// Generates a random webhook secret.
+fn random_secret() -> String {
+    let mut rng = StdRng::from_os_rng();
+    let mut bytes = [0u8; 32];
+    rng.fill(&mut bytes);
+
+    encode_lower(&bytes)
+}
@@ -0,0 +1,202 @@
+//! Startup self-test.
+//!
+//! Before the server binds its port, it checks that everything a request
+//! depends on actually works, so a misconfigured deployment fails loudly on
+//! boot instead of on its first request.
+
+use std::fmt::{self, Display, Formatter};
+
+use anyhow::Error;
+
+use base16::encode_lower;
+
+use rand::{Rng as _, SeedableRng as _, rngs::StdRng};
+
+use tokio::fs;
+
+use crate::{app::AppState, auth::Claims, migrate::MIGRATOR};
+
+/// A report of every failed check.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// The name of each failed check, and why it failed.
+    pub failures: Vec<(&'static str, Error)>,
+}
+
+impl Report {
+    /// Checks if every check passed.
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "self-test failed {} check(s):", self.failures.len())?;
+
+        for (check, err) in self.failures.iter() {
+            write!(f, "\n  - {}: {:#}", check, err)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for Report {}
+
+/// Runs every check.
+///
+/// Checks run even if an earlier one failed, so the report shows every
+/// problem at once.
+pub async fn run(state: &AppState) -> Result<(), Report> {
+    let mut report = Report::default();
+
+    let checks = [
+        ("database connectivity", check_database(state).await),
+        ("migrations", check_migrations(state).await),
+        ("signing keys", check_signing_keys(state)),
+        ("database round-trip", check_round_trip(state).await),
+        ("content storage", check_content_storage(state).await),
+    ];
+
+    for (check, result) in checks {
+        match result {
+            Ok(()) => tracing::debug!("self-test: {} ok", check),
+            Err(err) => report.failures.push((check, err)),
+        }
+    }
+
+    if report.is_ok() { Ok(()) } else { Err(report) }
+}
+
+async fn check_database(state: &AppState) -> Result<(), Error> {
+    sqlx::query("SELECT 1").execute(&state.db).await?;
+
+    Ok(())
+}
+
+async fn check_migrations(state: &AppState) -> Result<(), Error> {
+    let applied = sqlx::query_as::<_, (i64, bool, Vec<u8>)>(
+        r#"
+        SELECT version, success, checksum
+        FROM _sqlx_migrations
+        "#,
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|err| Error::new(err).context("migrations have never been run"))?;
+
+    let mut pending = Vec::new();
+
+    for migration in MIGRATOR.iter() {
+        if migration.migration_type.is_down_migration() {
+            continue;
+        }
+
+        match applied
+            .iter()
+            .find(|(version, _, _)| *version == migration.version)
+        {
+            Some((_, false, _)) => {
+                return Err(Error::msg(format!(
+                    "migration {} ({}) failed to apply",
+                    migration.version, migration.description
+                )));
+            }
+            Some((_, true, checksum)) if checksum[..] != migration.checksum[..] => {
+                return Err(Error::msg(format!(
+                    "migration {} ({}) was changed after it was applied",
+                    migration.version, migration.description
+                )));
+            }
+            Some(_) => (),
+            None => pending.push(migration.version.to_string()),
+        }
+    }
+
+    if pending.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::msg(format!(
+            "{} migration(s) are pending: {}",
+            pending.len(),
+            pending.join(", ")
+        )))
+    }
+}
+
+fn check_signing_keys(state: &AppState) -> Result<(), Error> {
+    let claims = Claims::builder(0).build();
+    let token = claims.encode(&state.keys)?;
+    let decoded = Claims::decode(&token, &state.keys)?;
+
+    if decoded.sub().get() != claims.sub().get() {
+        return Err(Error::msg("decoded token does not match the signed token"));
+    }
+
+    Ok(())
+}
+
+async fn check_round_trip(state: &AppState) -> Result<(), Error> {
+    let mut rng = StdRng::from_os_rng();
+    let mut bytes = [0u8; 16];
+    rng.fill(&mut bytes);
+    let token = encode_lower(&bytes);
+
+    let (id,) = sqlx::query_as::<_, (i64,)>(
+        r#"
+        INSERT INTO self_test (token)
+        VALUES ($1)
+        RETURNING id
+        "#,
+    )
+    .bind(&token)
+    .fetch_one(&state.db)
+    .await?;
+
+    let read = sqlx::query_as::<_, (String,)>(
+        r#"
+        SELECT token
+        FROM self_test
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM self_test
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&state.db)
+    .await?;
+
+    match read {
+        Some((read,)) if read == token => Ok(()),
+        _ => Err(Error::msg("a written row could not be read back")),
+    }
+}
+
+async fn check_content_storage(state: &AppState) -> Result<(), Error> {
+    // only the filesystem needs checking; other content is in the database
+    let Some(directory) = state.content.directory() else {
+        return Ok(());
+    };
+
+    fs::create_dir_all(directory).await?;
+
+    let path = directory.join(".self-test");
+    fs::write(&path, b"nymph").await?;
+    let read = fs::read(&path).await?;
+    fs::remove_file(&path).await?;
+
+    if read == b"nymph" {
+        Ok(())
+    } else {
+        Err(Error::msg("a written file could not be read back"))
+    }
+}
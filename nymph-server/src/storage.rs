@@ -0,0 +1,426 @@
+//! Card content storage.
+//!
+//! Content is kept inline in the card table by default, so listings stay a
+//! single query. Content longer than [`StorageConfig::threshold`] is moved to
+//! the configured [`StorageBackend`] instead, leaving an empty column behind,
+//! so very long cards do not bloat every query that reads the card table.
+//! Moved content is only read back when a response includes it; see
+//! [`ContentStore::load`].
+//!
+//! Content kept by the `blob` backend may be compressed with zstd; see
+//! [`StorageConfig::compression`]. Compression is transparent to readers.
+//!
+//! Every write of card content must go through [`ContentStore::write`], which
+//! also keeps the search index up to date.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Error;
+
+use derive_more::{Display, Error, From};
+
+use nymph_model::card::Card;
+
+use sqlx::{FromRow, SqliteConnection, SqlitePool, types::Json};
+
+use tokio::fs;
+
+use crate::config::{StorageBackend, StorageConfig};
+
+/// How many cards [`ContentStore::rewrite_all`] rewrites per transaction.
+const REWRITE_BATCH_LEN: i64 = 100;
+
+/// An error reading or writing card content.
+#[derive(Debug, Display, Error, From)]
+pub enum StorageError {
+    /// A content file could not be read or written.
+    #[display("{_0}")]
+    Io(io::Error),
+    /// The database could not be read or written.
+    #[display("{_0}")]
+    Database(sqlx::Error),
+    /// Content is kept on the filesystem, but no directory is configured.
+    #[display("card {_0} has its content on the filesystem, but no directory is set")]
+    #[from(ignore)]
+    MissingDirectory(#[error(not(source))] i32),
+}
+
+/// Reads and writes card content.
+///
+/// Cheaply cloneable.
+#[derive(Clone, Debug, Default)]
+pub struct ContentStore {
+    config: Arc<StorageConfig>,
+}
+
+impl ContentStore {
+    /// Creates a new `ContentStore`.
+    ///
+    /// Fails if the `filesystem` backend is picked without a directory.
+    pub fn new(config: StorageConfig) -> Result<ContentStore, Error> {
+        if config.backend == StorageBackend::Filesystem && config.directory.is_none() {
+            return Err(Error::msg(
+                "the `filesystem` storage backend needs a `directory`",
+            ));
+        }
+
+        if let Some(level) = config.compression
+            && !zstd::compression_level_range().contains(&level)
+        {
+            return Err(Error::msg(format!(
+                "storage compression level {} is out of range",
+                level
+            )));
+        }
+
+        Ok(ContentStore {
+            config: Arc::new(config),
+        })
+    }
+
+    /// The directory content files are kept in, if one is set.
+    pub fn directory(&self) -> Option<&PathBuf> {
+        self.config.directory.as_ref()
+    }
+
+    /// Writes a card's content, moving it to wherever it belongs now.
+    ///
+    /// The card must already exist. Files are written before the surrounding
+    /// transaction commits, so a rolled back write may leave a stray file
+    /// behind; it is replaced the next time the card's content is written.
+    pub async fn write(
+        &self,
+        conn: &mut SqliteConnection,
+        card_id: i32,
+        content: &str,
+    ) -> Result<(), StorageError> {
+        let backend = if content.len() > self.config.threshold {
+            self.config.backend
+        } else {
+            StorageBackend::Inline
+        };
+
+        match backend {
+            StorageBackend::Inline => (),
+            StorageBackend::Blob => {
+                let compressed = match self.config.compression {
+                    Some(level) => Some(zstd::bulk::compress(content.as_bytes(), level)?),
+                    None => None,
+                };
+
+                sqlx::query(
+                    r#"
+                    INSERT INTO card_content (card_id, content, compressed)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT (card_id) DO UPDATE
+                    SET content = excluded.content, compressed = excluded.compressed
+                    "#,
+                )
+                .bind(card_id)
+                .bind(if compressed.is_some() { "" } else { content })
+                .bind(compressed)
+                .execute(&mut *conn)
+                .await?;
+            }
+            StorageBackend::Filesystem => {
+                let path = self.path(card_id)?;
+                let temp = path.with_extension("md.tmp");
+
+                fs::create_dir_all(path.parent().expect("file in a directory")).await?;
+                fs::write(&temp, content).await?;
+                fs::rename(&temp, &path).await?;
+            }
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE card
+            SET content = $2, content_location = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(card_id)
+        .bind(if backend == StorageBackend::Inline {
+            content
+        } else {
+            ""
+        })
+        .bind(location(backend))
+        .execute(&mut *conn)
+        .await?;
+
+        // clean up wherever the content was kept before
+        if backend != StorageBackend::Blob {
+            sqlx::query(
+                r#"
+                DELETE FROM card_content
+                WHERE card_id = $1
+                "#,
+            )
+            .bind(card_id)
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        if backend != StorageBackend::Filesystem
+            && let Ok(path) = self.path(card_id)
+        {
+            match fs::remove_file(path).await {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+                _ => (),
+            }
+        }
+
+        sqlx::query(
+            r#"
+            DELETE FROM card_search
+            WHERE rowid = $1
+            "#,
+        )
+        .bind(card_id)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO card_search (rowid, content)
+            VALUES ($1, $2)
+            "#,
+        )
+        .bind(card_id)
+        .bind(content)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Fills in the content of cards whose content was moved out of the card
+    /// table, including their upgrades and downgrades.
+    pub async fn load(&self, db: &SqlitePool, cards: &mut [Card]) -> Result<(), StorageError> {
+        let mut ids = Vec::new();
+
+        for card in cards.iter() {
+            collect_ids(card, &mut ids);
+        }
+
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let contents = self.read(db, &ids).await?;
+
+        if contents.is_empty() {
+            return Ok(());
+        }
+
+        for card in cards.iter_mut() {
+            fill_content(card, &contents);
+        }
+
+        Ok(())
+    }
+
+    /// Loads the content of a single card; see [`ContentStore::load`].
+    pub async fn load_one(&self, db: &SqlitePool, card: &mut Card) -> Result<(), StorageError> {
+        self.load(db, std::slice::from_mut(card)).await
+    }
+
+    /// Reads the content of every card in `ids` whose content was moved out
+    /// of the card table.
+    ///
+    /// Cards with inline content are left out.
+    pub async fn read(
+        &self,
+        db: &SqlitePool,
+        ids: &[i32],
+    ) -> Result<HashMap<i32, String>, StorageError> {
+        let moved = sqlx::query_as::<_, ContentResult>(
+            r#"
+            SELECT c.id, c.content_location, cc.content, cc.compressed
+            FROM
+                card c
+            LEFT OUTER JOIN
+                card_content AS cc
+                ON cc.card_id = c.id
+            WHERE
+                c.id IN (SELECT value FROM json_each($1))
+                AND c.content_location != 'inline'
+            "#,
+        )
+        .bind(Json(ids))
+        .fetch_all(db)
+        .await?;
+
+        let mut contents = HashMap::with_capacity(moved.len());
+
+        for result in moved {
+            let id = result.id;
+            contents.insert(id, self.resolve(result).await?);
+        }
+
+        Ok(contents)
+    }
+
+    /// Removes the content file of a deleted card, if it had one.
+    ///
+    /// Content kept in the database goes with the card, so this only needs
+    /// to be called once the deletion commits.
+    pub async fn remove(&self, card_id: i32) -> Result<(), StorageError> {
+        let Ok(path) = self.path(card_id) else {
+            return Ok(());
+        };
+
+        match fs::remove_file(path).await {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Rewrites the content of every card, so it is kept wherever, and
+    /// compressed however, the current config says.
+    ///
+    /// Returns how many cards were rewritten.
+    pub async fn rewrite_all(&self, db: &SqlitePool) -> Result<usize, StorageError> {
+        #[derive(FromRow)]
+        struct RewriteResult {
+            card_content: String,
+            #[sqlx(flatten)]
+            moved: ContentResult,
+        }
+
+        let mut last_id = 0;
+        let mut count = 0;
+
+        loop {
+            let rows = sqlx::query_as::<_, RewriteResult>(
+                r#"
+                SELECT
+                    c.id,
+                    c.content AS card_content,
+                    c.content_location,
+                    cc.content,
+                    cc.compressed
+                FROM
+                    card c
+                LEFT OUTER JOIN
+                    card_content AS cc
+                    ON cc.card_id = c.id
+                WHERE c.id > $1
+                ORDER BY c.id
+                LIMIT $2
+                "#,
+            )
+            .bind(last_id)
+            .bind(REWRITE_BATCH_LEN)
+            .fetch_all(db)
+            .await?;
+
+            let Some(last) = rows.last() else {
+                break;
+            };
+            last_id = last.moved.id;
+
+            let mut tx = db.begin().await?;
+
+            for row in rows {
+                let id = row.moved.id;
+                let content = if row.moved.content_location == location(StorageBackend::Inline) {
+                    row.card_content
+                } else {
+                    self.resolve(row.moved).await?
+                };
+
+                self.write(&mut tx, id, &content).await?;
+                count += 1;
+            }
+
+            tx.commit().await?;
+        }
+
+        Ok(count)
+    }
+
+    /// Reads moved content from wherever it was kept.
+    async fn resolve(&self, result: ContentResult) -> Result<String, StorageError> {
+        match result {
+            ContentResult {
+                compressed: Some(compressed),
+                ..
+            } => {
+                let content = zstd::stream::decode_all(compressed.as_slice())?;
+                String::from_utf8(content)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err).into())
+            }
+            ContentResult {
+                content: Some(content),
+                ..
+            } => Ok(content),
+            ContentResult {
+                id,
+                content_location,
+                ..
+            } if content_location == location(StorageBackend::Filesystem) => {
+                Ok(fs::read_to_string(self.path(id)?).await?)
+            }
+            ContentResult { id, .. } => {
+                tracing::warn!(card_id = id, "card content is missing");
+                Ok(String::new())
+            }
+        }
+    }
+
+    fn path(&self, card_id: i32) -> Result<PathBuf, StorageError> {
+        match self.config.directory.as_ref() {
+            Some(directory) => Ok(directory.join(format!("{}.md", card_id))),
+            None => Err(StorageError::MissingDirectory(card_id)),
+        }
+    }
+}
+
+/// Moved content, as it is kept in the database.
+#[derive(FromRow)]
+struct ContentResult {
+    id: i32,
+    content_location: String,
+    content: Option<String>,
+    compressed: Option<Vec<u8>>,
+}
+
+/// The name a backend is recorded as in `card.content_location`.
+fn location(backend: StorageBackend) -> &'static str {
+    match backend {
+        StorageBackend::Inline => "inline",
+        StorageBackend::Blob => "blob",
+        StorageBackend::Filesystem => "file",
+    }
+}
+
+fn collect_ids(card: &Card, ids: &mut Vec<i32>) {
+    ids.push(card.id);
+
+    for upgrade in card.upgrades.iter().flatten() {
+        collect_ids(upgrade, ids);
+    }
+
+    if let Some(downgrade) = card.downgrade.as_ref() {
+        collect_ids(downgrade, ids);
+    }
+}
+
+fn fill_content(card: &mut Card, contents: &HashMap<i32, String>) {
+    if let Some(content) = contents.get(&card.id) {
+        card.content = content.clone();
+    }
+
+    for upgrade in card.upgrades.iter_mut().flatten() {
+        fill_content(upgrade, contents);
+    }
+
+    if let Some(downgrade) = card.downgrade.as_mut() {
+        fill_content(downgrade, contents);
+    }
+}
@@ -0,0 +1,711 @@
+//! Data access behind traits.
+//!
+//! Handlers reach cards, users and inventories through the stores on
+//! [`AppState`](crate::app::AppState) instead of querying the database
+//! themselves, so route logic can be exercised against in-memory fakes (see
+//! [`AppState::with_stores`](crate::app::AppState::with_stores)). [`SqlStore`]
+//! is the implementation the server runs with. Stores fail with a
+//! [`StoreError`], so handlers do not depend on any one backend.
+//!
+//! Flows that must happen in one transaction, like transfers and trades,
+//! still go through [`add_card`] and [`remove_card`] directly, since a store
+//! cannot take part in a caller's transaction.
+
+use std::fmt::{self, Display, Formatter};
+
+use futures_util::future::BoxFuture;
+
+use nymph_model::{
+    Id,
+    card::{Card, Rarity, Visibility},
+    request::card::CardSort,
+    response::card::CardOwner,
+    user::User,
+};
+
+use sqlx::{FromRow, SqlitePool, types::Json};
+
+use crate::routes::card::{
+    CardResult,
+    inventory::{add_card, remove_card},
+};
+
+/// An error a store fails with.
+///
+/// Whatever the backend failed with is kept as the error's source.
+#[derive(Debug)]
+pub struct StoreError(Box<dyn std::error::Error + Send + Sync>);
+
+impl StoreError {
+    /// Creates a new `StoreError` from a backend's error.
+    pub fn new(err: impl std::error::Error + Send + Sync + 'static) -> StoreError {
+        StoreError(Box::new(err))
+    }
+}
+
+impl Display for StoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.0)
+    }
+}
+
+impl From<sqlx::Error> for StoreError {
+    fn from(err: sqlx::Error) -> Self {
+        StoreError::new(err)
+    }
+}
+
+/// A card found for a user.
+#[derive(Clone, Debug)]
+pub struct FoundCard {
+    /// The card, not redacted.
+    pub card: Card,
+    /// If the user owns a copy of the card.
+    pub owned: bool,
+}
+
+/// The filters and ordering of a card listing.
+#[derive(Clone, Debug)]
+pub struct CardFilter {
+    /// The user listing cards, whose copies `owned` filters by.
+    pub viewer_id: i32,
+    /// The guild listed, along with the public cards it syndicates.
+    pub guild_id: i64,
+    /// Text names must contain, already normalized.
+    ///
+    /// Any `*` stands in for a run of characters, and makes the search match
+    /// whole names instead.
+    pub search: Option<String>,
+    /// Only list cards of this rarity.
+    pub rarity: Option<Rarity>,
+    /// Only list cards of this category, whatever its case.
+    pub category: Option<String>,
+    /// Only list cards the viewer owns, or does not own.
+    pub owned: Option<bool>,
+    /// Only list cards of these visibilities.
+    pub visibility: Option<Vec<Visibility>>,
+    /// A phrase card content must contain.
+    pub content_search: Option<String>,
+    /// How cards are ordered.
+    pub sort: CardSort,
+    /// If cards sorted by a column are ordered last to first.
+    pub descending: bool,
+}
+
+/// The filters of an inventory listing.
+#[derive(Clone, Debug)]
+pub struct InventoryFilter {
+    /// The user whose inventory is listed.
+    pub owner_id: i32,
+    /// Only list cards of this guild.
+    pub guild_id: Option<i64>,
+    /// Only list cards the user has favorited.
+    pub favorites: bool,
+}
+
+/// Reads cards.
+pub trait CardStore: Send + Sync {
+    /// Gets a card as a user sees it.
+    ///
+    /// Returns `None` if the card does not exist. The card is not redacted.
+    fn get(&self, viewer_id: i32, id: i32) -> BoxFuture<'_, Result<Option<Card>, StoreError>>;
+
+    /// Gets a card of a guild, or one it syndicates, as a user sees it.
+    ///
+    /// Returns `None` if the guild has no such card. Archived cards are
+    /// found too.
+    fn find(
+        &self,
+        viewer_id: i32,
+        guild_id: i64,
+        id: i32,
+    ) -> BoxFuture<'_, Result<Option<FoundCard>, StoreError>>;
+
+    /// Counts the cards a listing would return. Archived cards are never
+    /// listed.
+    fn count<'a>(&'a self, filter: &'a CardFilter) -> BoxFuture<'a, Result<u64, StoreError>>;
+
+    /// Lists a page of cards, as the viewer sees them.
+    ///
+    /// The cards are not redacted.
+    fn list<'a>(
+        &'a self,
+        filter: &'a CardFilter,
+        limit: u64,
+        offset: u64,
+    ) -> BoxFuture<'a, Result<Vec<Card>, StoreError>>;
+}
+
+/// Reads users.
+pub trait UserStore: Send + Sync {
+    /// Checks if a user exists.
+    fn exists(&self, id: i32) -> BoxFuture<'_, Result<bool, StoreError>>;
+
+    /// Gets the Discord account linked to a user, if any.
+    fn discord_id(&self, id: i32) -> BoxFuture<'_, Result<Option<Id>, StoreError>>;
+}
+
+/// Reads and changes what users own.
+pub trait OwnershipStore: Send + Sync {
+    /// Adds a copy of a card to a user's inventory.
+    ///
+    /// Returns how many copies of the card the user owns afterwards.
+    fn add(&self, owner_id: i32, card_id: i32) -> BoxFuture<'_, Result<u32, StoreError>>;
+
+    /// Removes a copy of a card from a user's inventory.
+    ///
+    /// Returns how many copies of the card the user owns afterwards, or
+    /// `None` if the user did not own the card to begin with.
+    fn remove(&self, owner_id: i32, card_id: i32)
+    -> BoxFuture<'_, Result<Option<u32>, StoreError>>;
+
+    /// Counts the cards an inventory listing would return.
+    fn count_inventory<'a>(
+        &'a self,
+        filter: &'a InventoryFilter,
+    ) -> BoxFuture<'a, Result<u64, StoreError>>;
+
+    /// Lists a page of a user's inventory, by card.
+    ///
+    /// Each card has its quantity set, and is not redacted.
+    fn inventory<'a>(
+        &'a self,
+        filter: &'a InventoryFilter,
+        limit: u64,
+        offset: u64,
+    ) -> BoxFuture<'a, Result<Vec<Card>, StoreError>>;
+
+    /// Counts the users that own a card.
+    fn count_owners(&self, card_id: i32) -> BoxFuture<'_, Result<u64, StoreError>>;
+
+    /// Lists a page of the users that own a card, most copies first.
+    fn owners(
+        &self,
+        card_id: i32,
+        limit: u64,
+        offset: u64,
+    ) -> BoxFuture<'_, Result<Vec<CardOwner>, StoreError>>;
+}
+
+/// The stores backed by the server's database.
+///
+/// Cheaply cloneable.
+#[derive(Clone, Debug)]
+pub struct SqlStore {
+    db: SqlitePool,
+}
+
+impl SqlStore {
+    /// Creates a new `SqlStore`.
+    pub fn new(db: SqlitePool) -> SqlStore {
+        SqlStore { db }
+    }
+}
+
+impl CardStore for SqlStore {
+    fn get(&self, viewer_id: i32, id: i32) -> BoxFuture<'_, Result<Option<Card>, StoreError>> {
+        Box::pin(async move {
+            let card = sqlx::query_as::<_, CardResult>(
+                r#"
+                SELECT
+                    c.id, c.guild_id, c.name, c.emoji, c.category_name, c.content,
+                    c.visibility, c.rarity, c.rarity_score, c.archived_at,
+                    c.inserted_at, c.updated_at,
+                    COALESCE(o.quantity, 0) > 0 AS owned
+                FROM
+                    card c
+                LEFT OUTER JOIN
+                    ownership AS o
+                    ON o.card_id = c.id AND o.owner_id = $1
+                WHERE
+                    c.id = $2
+                "#,
+            )
+            .bind(viewer_id)
+            .bind(id)
+            .fetch_optional(&self.db)
+            .await?;
+
+            Ok(card.map(Card::from))
+        })
+    }
+
+    fn find(
+        &self,
+        viewer_id: i32,
+        guild_id: i64,
+        id: i32,
+    ) -> BoxFuture<'_, Result<Option<FoundCard>, StoreError>> {
+        Box::pin(async move {
+            let card = sqlx::query_as::<_, CardResult>(
+                r#"
+                SELECT
+                    c.id, c.guild_id, c.name, c.emoji, c.category_name, c.content,
+                    c.visibility, c.rarity, c.rarity_score, c.archived_at,
+                    c.inserted_at, c.updated_at,
+                    COALESCE(o.quantity, 0) > 0 AS owned
+                FROM
+                    card c
+                LEFT OUTER JOIN
+                    ownership AS o
+                    ON o.card_id = c.id AND o.owner_id = $1
+                WHERE
+                    c.id = $3
+                    AND (
+                        c.guild_id = $2
+                        OR (c.visibility = 'public' AND EXISTS (
+                            SELECT 1 FROM guild_syndication gs
+                            WHERE
+                                gs.guild_id = $2
+                                AND gs.source_guild_id = c.guild_id
+                                AND gs.category_name = c.category_name
+                        ))
+                    )
+                "#,
+            )
+            .bind(viewer_id)
+            .bind(guild_id)
+            .bind(id)
+            .fetch_optional(&self.db)
+            .await?;
+
+            Ok(card.map(|card| FoundCard {
+                owned: card.owned,
+                card: Card::from(card),
+            }))
+        })
+    }
+
+    fn count<'a>(&'a self, filter: &'a CardFilter) -> BoxFuture<'a, Result<u64, StoreError>> {
+        Box::pin(async move {
+            let filter = SqlCardFilter::new(filter);
+
+            let (total,) = sqlx::query_as::<_, (i64,)>(
+                r#"
+                SELECT COUNT(*)
+                FROM
+                    card c
+                LEFT OUTER JOIN
+                    ownership AS o
+                    ON o.card_id = c.id AND o.owner_id = $6
+                WHERE
+                    (
+                        c.guild_id = $1
+                        OR (c.visibility = 'public' AND EXISTS (
+                            SELECT 1 FROM guild_syndication gs
+                            WHERE
+                                gs.guild_id = $1
+                                AND gs.source_guild_id = c.guild_id
+                                AND gs.category_name = c.category_name
+                        ))
+                    )
+                    AND c.archived_at IS NULL
+                    AND ($2 IS NULL OR c.name LIKE $2 ESCAPE '\')
+                    AND ($3 IS NULL OR c.rarity = $3)
+                    AND ($4 IS NULL OR c.visibility IN (SELECT value FROM json_each($4)))
+                    AND ($5 IS NULL OR c.category_name = $5 COLLATE NOCASE)
+                    AND ($7 IS NULL OR (COALESCE(o.quantity, 0) > 0) = $7)
+                    AND ($8 IS NULL OR c.id IN (
+                        SELECT rowid FROM card_search
+                        WHERE card_search MATCH COALESCE($8, '""')
+                    ))
+                "#,
+            )
+            .bind(filter.inner.guild_id)
+            .bind(filter.pattern.as_ref())
+            .bind(filter.rarity)
+            .bind(&filter.visibility)
+            .bind(filter.inner.category.as_ref())
+            .bind(filter.inner.viewer_id)
+            .bind(filter.inner.owned)
+            .bind(filter.content_search.as_ref())
+            .fetch_one(&self.db)
+            .await?;
+
+            Ok(total as u64)
+        })
+    }
+
+    fn list<'a>(
+        &'a self,
+        filter: &'a CardFilter,
+        limit: u64,
+        offset: u64,
+    ) -> BoxFuture<'a, Result<Vec<Card>, StoreError>> {
+        Box::pin(async move {
+            let filter = SqlCardFilter::new(filter);
+
+            // results are ranked entirely in the database: exact matches
+            // first, then names where the search appears closest to the
+            // start, so prefixes come before other matches. a name containing
+            // the search is exactly as far from it as it is longer, so
+            // shorter names break ties. like `LIKE`, none of this minds case.
+            // content matches are ranked by bm25 before any of that. sorting
+            // by a column skips relevance entirely
+            let cards = sqlx::query_as::<_, CardResult>(
+                r#"
+                SELECT
+                    c.id, c.guild_id, c.name, c.emoji, c.category_name, c.content,
+                    c.visibility, c.rarity, c.rarity_score, c.archived_at,
+                    c.inserted_at, c.updated_at,
+                    COALESCE(o.quantity, 0) > 0 AS owned,
+                    CASE $8
+                        WHEN 'name' THEN c.name
+                        WHEN 'created_at' THEN c.inserted_at
+                        WHEN 'updated_at' THEN c.updated_at
+                    END AS sort_key
+                FROM
+                    card c
+                LEFT OUTER JOIN
+                    ownership AS o
+                    ON o.card_id = c.id AND o.owner_id = $1
+                LEFT OUTER JOIN
+                    (
+                        -- only owners' ratings count
+                        SELECT r.card_id, AVG(r.rating) AS rating, COUNT(*) AS ratings
+                        FROM card_reaction r, ownership o
+                        WHERE
+                            o.card_id = r.card_id
+                            AND o.owner_id = r.user_id
+                            AND o.quantity > 0
+                        GROUP BY r.card_id
+                    ) AS r
+                    ON $7 AND r.card_id = c.id
+                LEFT OUTER JOIN
+                    (
+                        -- an empty phrase matches nothing
+                        SELECT rowid AS card_id, bm25(card_search) AS rank
+                        FROM card_search
+                        WHERE card_search MATCH COALESCE($13, '""')
+                    ) AS s
+                    ON s.card_id = c.id
+                WHERE
+                    -- public cards of categories the guild subscribes to are
+                    -- listed alongside its own
+                    (
+                        c.guild_id = $2
+                        OR (c.visibility = 'public' AND EXISTS (
+                            SELECT 1 FROM guild_syndication gs
+                            WHERE
+                                gs.guild_id = $2
+                                AND gs.source_guild_id = c.guild_id
+                                AND gs.category_name = c.category_name
+                        ))
+                    )
+                    AND c.archived_at IS NULL
+                    AND ($14 IS NULL OR c.name LIKE $14 ESCAPE '\')
+                    AND ($4 IS NULL OR c.rarity = $4)
+                    AND ($10 IS NULL OR c.visibility IN (SELECT value FROM json_each($10)))
+                    AND ($11 IS NULL OR c.category_name = $11 COLLATE NOCASE)
+                    AND ($12 IS NULL OR (COALESCE(o.quantity, 0) > 0) = $12)
+                    AND ($13 IS NULL OR s.card_id IS NOT NULL)
+                ORDER BY
+                    -- unrated cards come last
+                    r.rating DESC,
+                    r.ratings DESC,
+                    CASE WHEN $9 THEN NULL ELSE sort_key END,
+                    CASE WHEN $9 THEN sort_key END DESC,
+                    s.rank,
+                    c.name = $3 COLLATE NOCASE DESC,
+                    instr(upper(c.name), upper($3)),
+                    CASE WHEN $3 IS NULL THEN 0 ELSE length(c.name) END,
+                    c.id
+                LIMIT $5 OFFSET $6
+                "#,
+            )
+            .bind(filter.inner.viewer_id)
+            .bind(filter.inner.guild_id)
+            .bind(filter.inner.search.as_ref())
+            .bind(filter.rarity)
+            .bind(limit as i64)
+            .bind(offset as i64)
+            .bind(filter.inner.sort == CardSort::TopRated)
+            .bind(filter.inner.sort.to_str())
+            .bind(filter.inner.descending)
+            .bind(&filter.visibility)
+            .bind(filter.inner.category.as_ref())
+            .bind(filter.inner.owned)
+            .bind(filter.content_search.as_ref())
+            .bind(filter.pattern.as_ref())
+            .fetch_all(&self.db)
+            .await?
+            .into_iter()
+            .map(Card::from)
+            .collect();
+
+            Ok(cards)
+        })
+    }
+}
+
+impl UserStore for SqlStore {
+    fn exists(&self, id: i32) -> BoxFuture<'_, Result<bool, StoreError>> {
+        Box::pin(async move {
+            let user = sqlx::query_as::<_, (i32,)>(
+                r#"
+                SELECT id
+                FROM user
+                WHERE id = $1
+                "#,
+            )
+            .bind(id)
+            .fetch_optional(&self.db)
+            .await?;
+
+            Ok(user.is_some())
+        })
+    }
+
+    fn discord_id(&self, id: i32) -> BoxFuture<'_, Result<Option<Id>, StoreError>> {
+        Box::pin(async move {
+            let discord_id = sqlx::query_as::<_, (i64,)>(
+                r#"
+                SELECT discord_id
+                FROM discord_auth
+                WHERE user_id = $1
+                "#,
+            )
+            .bind(id)
+            .fetch_optional(&self.db)
+            .await?;
+
+            Ok(discord_id.and_then(|(discord_id,)| Id::new(discord_id as u64)))
+        })
+    }
+}
+
+impl OwnershipStore for SqlStore {
+    fn add(&self, owner_id: i32, card_id: i32) -> BoxFuture<'_, Result<u32, StoreError>> {
+        Box::pin(async move { Ok(add_card(&self.db, owner_id, card_id).await?) })
+    }
+
+    fn remove(
+        &self,
+        owner_id: i32,
+        card_id: i32,
+    ) -> BoxFuture<'_, Result<Option<u32>, StoreError>> {
+        Box::pin(async move { Ok(remove_card(&self.db, owner_id, card_id).await?) })
+    }
+
+    fn count_inventory<'a>(
+        &'a self,
+        filter: &'a InventoryFilter,
+    ) -> BoxFuture<'a, Result<u64, StoreError>> {
+        Box::pin(async move {
+            let (total,) = sqlx::query_as::<_, (i64,)>(
+                r#"
+                SELECT COUNT(*)
+                FROM
+                    card c, ownership o
+                WHERE
+                    o.card_id = c.id
+                    AND o.owner_id = $1
+                    AND o.quantity > 0
+                    AND ($2 IS NULL OR c.guild_id = $2)
+                    AND (NOT $3 OR EXISTS (
+                        SELECT 1 FROM favorite f
+                        WHERE f.user_id = o.owner_id AND f.card_id = c.id
+                    ))
+                "#,
+            )
+            .bind(filter.owner_id)
+            .bind(filter.guild_id)
+            .bind(filter.favorites)
+            .fetch_one(&self.db)
+            .await?;
+
+            Ok(total as u64)
+        })
+    }
+
+    fn inventory<'a>(
+        &'a self,
+        filter: &'a InventoryFilter,
+        limit: u64,
+        offset: u64,
+    ) -> BoxFuture<'a, Result<Vec<Card>, StoreError>> {
+        Box::pin(async move {
+            let cards = sqlx::query_as::<_, CardResult>(
+                r#"
+                SELECT
+                    c.id, c.guild_id, c.name, c.emoji, c.category_name, c.content,
+                    c.visibility, c.rarity, c.rarity_score, c.archived_at,
+                    c.inserted_at, c.updated_at,
+                    o.quantity > 0 AS owned, o.quantity
+                FROM
+                    card c, ownership o
+                WHERE
+                    o.card_id = c.id
+                    AND o.owner_id = $1
+                    AND o.quantity > 0
+                    AND ($2 IS NULL OR c.guild_id = $2)
+                    AND (NOT $3 OR EXISTS (
+                        SELECT 1 FROM favorite f
+                        WHERE f.user_id = o.owner_id AND f.card_id = c.id
+                    ))
+                ORDER BY c.id
+                LIMIT $4 OFFSET $5
+                "#,
+            )
+            .bind(filter.owner_id)
+            .bind(filter.guild_id)
+            .bind(filter.favorites)
+            .bind(limit as i64)
+            .bind(offset as i64)
+            .fetch_all(&self.db)
+            .await?
+            .into_iter()
+            .map(|result| {
+                let quantity = result.quantity as u32;
+
+                Card {
+                    quantity: Some(quantity),
+                    ..Card::from(result)
+                }
+            })
+            .collect();
+
+            Ok(cards)
+        })
+    }
+
+    fn count_owners(&self, card_id: i32) -> BoxFuture<'_, Result<u64, StoreError>> {
+        Box::pin(async move {
+            let (total,) = sqlx::query_as::<_, (i64,)>(
+                r#"
+                SELECT COUNT(*)
+                FROM ownership o
+                WHERE o.card_id = $1 AND o.quantity > 0
+                "#,
+            )
+            .bind(card_id)
+            .fetch_one(&self.db)
+            .await?;
+
+            Ok(total as u64)
+        })
+    }
+
+    fn owners(
+        &self,
+        card_id: i32,
+        limit: u64,
+        offset: u64,
+    ) -> BoxFuture<'_, Result<Vec<CardOwner>, StoreError>> {
+        #[derive(FromRow)]
+        struct OwnerResult {
+            id: i32,
+            display_name: String,
+            discord_id: Option<i64>,
+            quantity: i64,
+        }
+
+        Box::pin(async move {
+            let owners = sqlx::query_as::<_, OwnerResult>(
+                r#"
+                SELECT u.id, u.display_name, da.discord_id, o.quantity
+                FROM
+                    ownership o
+                INNER JOIN
+                    user AS u
+                    ON u.id = o.owner_id
+                LEFT OUTER JOIN
+                    discord_auth AS da
+                    ON da.user_id = u.id
+                WHERE
+                    o.card_id = $1
+                    AND o.quantity > 0
+                ORDER BY o.quantity DESC, u.id
+                LIMIT $2 OFFSET $3
+                "#,
+            )
+            .bind(card_id)
+            .bind(limit as i64)
+            .bind(offset as i64)
+            .fetch_all(&self.db)
+            .await?
+            .into_iter()
+            .map(|owner| CardOwner {
+                user: User {
+                    id: owner.id,
+                    display_name: owner.display_name,
+                },
+                discord_id: owner.discord_id.and_then(|id| Id::new(id as u64)),
+                quantity: owner.quantity as u32,
+            })
+            .collect();
+
+            Ok(owners)
+        })
+    }
+}
+
+/// A [`CardFilter`] with its values as the database takes them.
+struct SqlCardFilter<'a> {
+    inner: &'a CardFilter,
+    pattern: Option<String>,
+    rarity: Option<&'static str>,
+    visibility: Option<Json<Vec<&'static str>>>,
+    content_search: Option<String>,
+}
+
+impl<'a> SqlCardFilter<'a> {
+    fn new(filter: &'a CardFilter) -> SqlCardFilter<'a> {
+        SqlCardFilter {
+            inner: filter,
+            pattern: filter.search.as_deref().map(search_pattern),
+            rarity: filter.rarity.map(|rarity| rarity.to_str()),
+            visibility: filter.visibility.as_ref().map(|visibility| {
+                Json(
+                    visibility
+                        .iter()
+                        .map(|visibility| visibility.to_str())
+                        .collect(),
+                )
+            }),
+            // content is searched as one phrase, so users need not know
+            // fts5's syntax
+            content_search: filter
+                .content_search
+                .as_deref()
+                .map(|search| format!("\"{}\"", search.replace('"', "\"\""))),
+        }
+    }
+}
+
+/// Turns a search into a `LIKE` pattern, escaped with `\`.
+///
+/// A search without `*` matches names that contain it anywhere. Otherwise,
+/// the search must match the whole name, with each `*` standing in for any
+/// run of characters.
+fn search_pattern(search: &str) -> String {
+    let mut pattern = String::with_capacity(search.len() + 2);
+    let glob = search.contains('*');
+
+    if !glob {
+        pattern.push('%');
+    }
+
+    for c in search.chars() {
+        match c {
+            '*' => pattern.push('%'),
+            '%' | '_' | '\\' => {
+                pattern.push('\\');
+                pattern.push(c);
+            }
+            c => pattern.push(c),
+        }
+    }
+
+    if !glob {
+        pattern.push('%');
+    }
+
+    pattern
+}
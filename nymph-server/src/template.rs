@@ -0,0 +1,323 @@
+//! Card templates.
+//!
+//! Templates are starter decks kept by the server's operators, which new
+//! guilds can copy into themselves. A template's cards are kept as they were
+//! read from an import file, so copying one goes through the same validation
+//! as a bulk import; see [`crate::routes::card::import`].
+
+use std::collections::HashSet;
+
+use chrono::{NaiveDateTime, Utc};
+
+use nymph_model::template::Template;
+
+use sqlx::{Executor, FromRow, Sqlite, SqlitePool};
+
+use crate::{
+    app::{AppError, AppErrorKind},
+    import::{ImportRow, ImportedCard},
+    request::validate::{Validator as _, ValidatorExt as _, value},
+};
+
+/// The maximum length of a template name.
+pub const MAX_NAME_LEN: usize = 255;
+
+#[derive(FromRow)]
+struct TemplateResult {
+    id: i32,
+    name: String,
+    description: Option<String>,
+    cards: i64,
+    inserted_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+impl From<TemplateResult> for Template {
+    fn from(template: TemplateResult) -> Template {
+        Template {
+            id: template.id,
+            name: template.name,
+            description: template.description,
+            cards: template.cards as u32,
+            created_at: template.inserted_at,
+            updated_at: template.updated_at,
+        }
+    }
+}
+
+#[derive(FromRow)]
+struct TemplateCardResult {
+    position: i64,
+    name: String,
+    category_name: Option<String>,
+    content: String,
+    previous: Option<String>,
+}
+
+/// Saves a template from the rows of an import file.
+///
+/// Unlike a bulk import, a template is all or nothing: every row must be
+/// readable and name a different card. If `replace` is set, the cards of an
+/// existing template with the same name are replaced.
+pub async fn save(
+    db: &SqlitePool,
+    name: &str,
+    description: Option<&str>,
+    rows: Vec<ImportRow>,
+    replace: bool,
+) -> Result<Template, AppError> {
+    let name = name.trim();
+    let description = description
+        .map(str::trim)
+        .filter(|description| !description.is_empty());
+
+    value("name", name.len())
+        .in_range(1..=MAX_NAME_LEN)
+        .validate()?;
+
+    let cards = read_rows(rows)?;
+
+    let now = Utc::now();
+
+    let mut tx = db.begin().await?;
+
+    let existing = sqlx::query_as::<_, (i32,)>(
+        r#"
+        SELECT id
+        FROM card_template
+        WHERE name = $1
+        "#,
+    )
+    .bind(name)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let id = match existing {
+        Some(_) if !replace => {
+            return Err(AppError::from(AppErrorKind::AlreadyExists(name.to_owned()))
+                .with_message(format!("A template named `{}` already exists.", name)));
+        }
+        Some((id,)) => {
+            sqlx::query(
+                r#"
+                UPDATE card_template
+                SET description = $2, updated_at = $3
+                WHERE id = $1
+                "#,
+            )
+            .bind(id)
+            .bind(description)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                r#"
+                DELETE FROM card_template_card
+                WHERE template_id = $1
+                "#,
+            )
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+            id
+        }
+        None => {
+            let (id,) = sqlx::query_as::<_, (i32,)>(
+                r#"
+                INSERT INTO card_template (name, description, inserted_at, updated_at)
+                VALUES ($1, $2, $3, $3)
+                RETURNING id
+                "#,
+            )
+            .bind(name)
+            .bind(description)
+            .bind(now)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            id
+        }
+    };
+
+    for (position, card) in cards.iter().enumerate() {
+        sqlx::query(
+            r#"
+            INSERT INTO card_template_card (
+                template_id, position, name, category_name, content, previous
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(id)
+        .bind(position as i64)
+        .bind(&card.name)
+        .bind(card.category_name.as_ref())
+        .bind(&card.content)
+        .bind(card.previous.as_ref())
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(id, name, cards = cards.len(), "saved card template");
+
+    get_template(db, id).await
+}
+
+/// Lists all templates by name.
+pub async fn list<'c, E>(db: E) -> Result<Vec<Template>, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let templates = sqlx::query_as::<_, TemplateResult>(
+        r#"
+        SELECT
+            t.id, t.name, t.description, t.inserted_at, t.updated_at,
+            COUNT(tc.name) AS cards
+        FROM
+            card_template t
+        LEFT OUTER JOIN
+            card_template_card AS tc
+            ON tc.template_id = t.id
+        GROUP BY t.id
+        ORDER BY t.name
+        "#,
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(Template::from)
+    .collect();
+
+    Ok(templates)
+}
+
+/// Fetches a template.
+pub async fn get_template<'c, E>(db: E, id: i32) -> Result<Template, AppError>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let template = sqlx::query_as::<_, TemplateResult>(
+        r#"
+        SELECT
+            t.id, t.name, t.description, t.inserted_at, t.updated_at,
+            COUNT(tc.name) AS cards
+        FROM
+            card_template t
+        LEFT OUTER JOIN
+            card_template_card AS tc
+            ON tc.template_id = t.id
+        WHERE t.id = $1
+        GROUP BY t.id
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(db)
+    .await?;
+
+    match template {
+        Some(template) => Ok(template.into()),
+        None => Err(AppError::from(AppErrorKind::NotFound)
+            .with_message(format!("The template of id {} does not exist.", id))),
+    }
+}
+
+/// Fetches the cards of a template as import rows, in file order.
+pub async fn get_rows<'c, E>(db: E, id: i32) -> Result<Vec<ImportRow>, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let rows = sqlx::query_as::<_, TemplateCardResult>(
+        r#"
+        SELECT position, name, category_name, content, previous
+        FROM card_template_card
+        WHERE template_id = $1
+        ORDER BY position
+        "#,
+    )
+    .bind(id)
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|card| ImportRow {
+        row: card.position as u32 + 1,
+        card: Ok(ImportedCard {
+            name: card.name,
+            category_name: card.category_name,
+            content: card.content,
+            previous: card.previous,
+        }),
+    })
+    .collect();
+
+    Ok(rows)
+}
+
+/// Deletes a template.
+///
+/// Guilds that copied the template keep their cards.
+pub async fn delete(db: &SqlitePool, id: i32) -> Result<Template, AppError> {
+    let template = get_template(db, id).await?;
+
+    let mut tx = db.begin().await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM card_template_card
+        WHERE template_id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        DELETE FROM card_template
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    tracing::info!(id, name = template.name, "deleted card template");
+
+    Ok(template)
+}
+
+/// Reads every row of a template file, failing on the first bad row.
+fn read_rows(rows: Vec<ImportRow>) -> Result<Vec<ImportedCard>, AppError> {
+    if rows.is_empty() {
+        return Err(AppError::from(AppErrorKind::InvalidFile)
+            .with_message("A template must have at least one card."));
+    }
+
+    let mut names = HashSet::with_capacity(rows.len());
+    let mut cards = Vec::with_capacity(rows.len());
+
+    for row in rows {
+        let card = row.card.map_err(|err| {
+            AppError::from(AppErrorKind::InvalidFile)
+                .with_message(format!("Row {}: {}", row.row, err.message))
+        })?;
+
+        if !names.insert(card.name.clone()) {
+            return Err(
+                AppError::from(AppErrorKind::InvalidFile).with_message(format!(
+                    "Row {}: card `{}` is already in the template.",
+                    row.row, card.name
+                )),
+            );
+        }
+
+        cards.push(card);
+    }
+
+    Ok(cards)
+}
@@ -0,0 +1,481 @@
+//! In-process test harness.
+//!
+//! [`TestApp`] builds the server's router on a fresh in-memory database and
+//! seeds it with a few fixtures, so a route can be tested by sending it
+//! requests directly, without binding a port or running a daemon:
+//!
+//! ```no_run
+//! # async fn test() -> anyhow::Result<()> {
+//! use http::StatusCode;
+//! use nymph_model::card::Card;
+//! use nymph_server::test::TestApp;
+//!
+//! let app = TestApp::new().await?;
+//!
+//! let card = app
+//!     .get(format!("/v1/guilds/1/cards/{}", app.cards.public.id))
+//!     .as_user(app.user_id)
+//!     .send()
+//!     .await
+//!     .assert_status(StatusCode::OK)
+//!     .json::<Card>();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Requests are sent as the managed client unless told otherwise.
+
+use std::sync::Arc;
+
+use anyhow::Error;
+
+use axum::{Router, body::Body};
+
+use chrono::Utc;
+
+use http::{HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode, header};
+
+use nymph_model::{
+    Id,
+    card::{Card, Rarity, Visibility},
+    request::{
+        card::{CreateCardRequest, inventory::GrantRequest},
+        user::UpdateDiscordUserRequest,
+    },
+    response::user::UpdateDiscordUserResponse,
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use tower::ServiceExt as _;
+
+use crate::{
+    app::{AppState, random_signing_key},
+    auth::{
+        api_key::{generate_key, hash_key},
+        token::Claims,
+    },
+    config::{RateLimitConfig, ServerConfig},
+    migrate, router,
+    store::{CardStore, OwnershipStore, UserStore},
+};
+
+/// The guild the fixtures are in.
+pub const GUILD_ID: i64 = 1;
+
+/// The Discord account of the fixture user.
+pub const DISCORD_ID: u64 = 1000;
+
+/// The header API keys are passed in.
+const X_API_KEY: &str = "x-api-key";
+
+/// A server running in-process on a fresh database.
+#[derive(Clone, Debug)]
+pub struct TestApp {
+    /// The server's state, for setting up data or checking on it directly.
+    pub state: AppState,
+    /// The managed user requests are sent as by default.
+    pub client_id: i32,
+    /// A regular user, linked to [`DISCORD_ID`].
+    pub user_id: i32,
+    /// Cards in [`GUILD_ID`], none owned by anyone.
+    pub cards: Fixtures,
+    router: Router,
+    api_key: String,
+}
+
+/// The cards a [`TestApp`] starts with, one of each visibility.
+#[derive(Clone, Debug)]
+pub struct Fixtures {
+    /// A card anyone can see.
+    pub public: Card,
+    /// A card only owners can see the details of.
+    pub hidden: Card,
+    /// A card only owners know of.
+    pub private: Card,
+}
+
+impl TestApp {
+    /// Starts a new app, seeded with fixtures.
+    pub async fn new() -> Result<TestApp, Error> {
+        let config = ServerConfig {
+            port: 0,
+            database_url: Some("sqlite::memory:".into()),
+            signing_key: Some(random_signing_key()),
+            rate_limit: RateLimitConfig {
+                per_minute: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let state = AppState::new(config).await?;
+        migrate::run(&state.db).await?;
+
+        let (client_id, api_key) = create_client(&state).await?;
+        let router = router::build(state.clone());
+
+        let request = |method, uri: &str| TestRequest::new(&router, &state, &api_key, method, uri);
+
+        let user_id = create_user(request(Method::POST, "/v1/users/discord"), DISCORD_ID).await?;
+
+        let cards_uri = format!("/v1/guilds/{}/cards", GUILD_ID);
+        let cards = Fixtures {
+            public: create_card(
+                request(Method::POST, &cards_uri),
+                "Public",
+                Visibility::Public,
+            )
+            .await?,
+            hidden: create_card(
+                request(Method::POST, &cards_uri),
+                "Hidden",
+                Visibility::Hidden,
+            )
+            .await?,
+            private: create_card(
+                request(Method::POST, &cards_uri),
+                "Private",
+                Visibility::Private,
+            )
+            .await?,
+        };
+
+        Ok(TestApp {
+            state,
+            client_id,
+            user_id,
+            cards,
+            router,
+            api_key,
+        })
+    }
+
+    /// Swaps the stores the app's routes use, like for in-memory fakes.
+    ///
+    /// Fixtures stay in the database, so fakes may not know of them.
+    pub fn with_stores(
+        self,
+        cards: Arc<dyn CardStore>,
+        users: Arc<dyn UserStore>,
+        ownership: Arc<dyn OwnershipStore>,
+    ) -> TestApp {
+        let state = self.state.with_stores(cards, users, ownership);
+
+        TestApp {
+            router: router::build(state.clone()),
+            state,
+            ..self
+        }
+    }
+
+    /// Starts building a `GET` request.
+    pub fn get(&self, uri: impl Into<String>) -> TestRequest<'_> {
+        self.request(Method::GET, uri)
+    }
+
+    /// Starts building a `POST` request.
+    pub fn post(&self, uri: impl Into<String>) -> TestRequest<'_> {
+        self.request(Method::POST, uri)
+    }
+
+    /// Starts building a `PUT` request.
+    pub fn put(&self, uri: impl Into<String>) -> TestRequest<'_> {
+        self.request(Method::PUT, uri)
+    }
+
+    /// Starts building a `PATCH` request.
+    pub fn patch(&self, uri: impl Into<String>) -> TestRequest<'_> {
+        self.request(Method::PATCH, uri)
+    }
+
+    /// Starts building a `DELETE` request.
+    pub fn delete(&self, uri: impl Into<String>) -> TestRequest<'_> {
+        self.request(Method::DELETE, uri)
+    }
+
+    /// Starts building a request.
+    pub fn request(&self, method: Method, uri: impl Into<String>) -> TestRequest<'_> {
+        TestRequest::new(&self.router, &self.state, &self.api_key, method, uri)
+    }
+
+    /// Creates a card in [`GUILD_ID`].
+    pub async fn create_card(&self, name: &str, visibility: Visibility) -> Result<Card, Error> {
+        create_card(
+            self.post(format!("/v1/guilds/{}/cards", GUILD_ID)),
+            name,
+            visibility,
+        )
+        .await
+    }
+
+    /// Creates a regular user, linked to a Discord account.
+    pub async fn create_user(&self, discord_id: u64) -> Result<i32, Error> {
+        create_user(self.post("/v1/users/discord"), discord_id).await
+    }
+
+    /// Gives a user a copy of a card.
+    pub async fn grant(&self, user_id: i32, card_id: i32) -> Result<(), Error> {
+        self.post(format!("/v1/users/{}/cards", user_id))
+            .json(&GrantRequest { card_id })
+            .send()
+            .await
+            .ok()?;
+
+        Ok(())
+    }
+}
+
+/// Who a request is sent as.
+#[derive(Clone, Copy, Debug)]
+enum Caller {
+    /// The managed client, with its API key.
+    Client,
+    /// A user, with an access token.
+    User(i32),
+    /// Nobody.
+    Anonymous,
+}
+
+/// A request being built for a [`TestApp`].
+#[derive(Debug)]
+pub struct TestRequest<'a> {
+    router: &'a Router,
+    state: &'a AppState,
+    api_key: &'a str,
+    method: Method,
+    uri: String,
+    caller: Caller,
+    headers: HeaderMap,
+    body: Option<Vec<u8>>,
+}
+
+impl<'a> TestRequest<'a> {
+    fn new(
+        router: &'a Router,
+        state: &'a AppState,
+        api_key: &'a str,
+        method: Method,
+        uri: impl Into<String>,
+    ) -> TestRequest<'a> {
+        TestRequest {
+            router,
+            state,
+            api_key,
+            method,
+            uri: uri.into(),
+            caller: Caller::Client,
+            headers: HeaderMap::new(),
+            body: None,
+        }
+    }
+
+    /// Sends the request as a user, instead of the managed client.
+    pub fn as_user(self, user_id: i32) -> Self {
+        TestRequest {
+            caller: Caller::User(user_id),
+            ..self
+        }
+    }
+
+    /// Sends the request without any credentials.
+    pub fn anonymous(self) -> Self {
+        TestRequest {
+            caller: Caller::Anonymous,
+            ..self
+        }
+    }
+
+    /// Sends an extra header.
+    pub fn header(mut self, name: HeaderName, value: &str) -> Self {
+        self.headers
+            .insert(name, HeaderValue::from_str(value).expect("valid header"));
+        self
+    }
+
+    /// Sends a JSON body.
+    pub fn json(self, body: &impl Serialize) -> Self {
+        TestRequest {
+            body: Some(serde_json::to_vec(body).expect("serializable body")),
+            ..self
+        }
+    }
+
+    /// Sends the request.
+    pub async fn send(self) -> TestResponse {
+        let mut request = Request::builder().method(self.method).uri(&self.uri);
+
+        match self.caller {
+            Caller::Client => request = request.header(X_API_KEY, self.api_key),
+            Caller::User(user_id) => {
+                let token = Claims::builder(user_id)
+                    .build()
+                    .encode(&self.state.keys)
+                    .expect("valid claims");
+
+                request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+            }
+            Caller::Anonymous => (),
+        }
+
+        if let Some(headers) = request.headers_mut() {
+            headers.extend(self.headers);
+        }
+
+        let body = match self.body {
+            Some(body) => {
+                request = request.header(header::CONTENT_TYPE, "application/json");
+                Body::from(body)
+            }
+            None => Body::empty(),
+        };
+
+        let response = self
+            .router
+            .clone()
+            .oneshot(request.body(body).expect("valid request"))
+            .await
+            .expect("infallible router");
+
+        let (parts, body) = response.into_parts();
+        let body = axum::body::to_bytes(body, usize::MAX)
+            .await
+            .expect("readable body");
+
+        TestResponse {
+            status: parts.status,
+            headers: parts.headers,
+            body: body.to_vec(),
+        }
+    }
+}
+
+/// A response of a [`TestApp`].
+#[derive(Clone, Debug)]
+pub struct TestResponse {
+    /// The status of the response.
+    pub status: StatusCode,
+    /// The headers of the response.
+    pub headers: HeaderMap,
+    /// The body of the response.
+    pub body: Vec<u8>,
+}
+
+impl TestResponse {
+    /// Panics if the response does not have a status.
+    #[track_caller]
+    pub fn assert_status(self, status: StatusCode) -> Self {
+        assert_eq!(
+            self.status,
+            status,
+            "unexpected status; body: {}",
+            String::from_utf8_lossy(&self.body)
+        );
+
+        self
+    }
+
+    /// Fails if the response is not successful.
+    pub fn ok(self) -> Result<Self, Error> {
+        if self.status.is_success() {
+            Ok(self)
+        } else {
+            Err(Error::msg(format!(
+                "request failed with {}: {}",
+                self.status,
+                String::from_utf8_lossy(&self.body)
+            )))
+        }
+    }
+
+    /// Decodes the body as JSON.
+    ///
+    /// Panics if the body is not the JSON expected.
+    #[track_caller]
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        match serde_json::from_slice(&self.body) {
+            Ok(body) => body,
+            Err(err) => panic!(
+                "unexpected body ({}): {}",
+                err,
+                String::from_utf8_lossy(&self.body)
+            ),
+        }
+    }
+}
+
+/// Creates a user with a `POST` to the Discord users.
+async fn create_user(request: TestRequest<'_>, discord_id: u64) -> Result<i32, Error> {
+    let user_id = request
+        .json(&UpdateDiscordUserRequest {
+            discord_id: Id::new(discord_id).expect("nonzero id"),
+            display_name: format!("User {}", discord_id),
+            generate_token: false,
+        })
+        .send()
+        .await
+        .ok()?
+        .json::<UpdateDiscordUserResponse>()
+        .user
+        .id;
+
+    Ok(user_id)
+}
+
+/// Creates a card in [`GUILD_ID`] with a `POST` to its cards.
+async fn create_card(
+    request: TestRequest<'_>,
+    name: &str,
+    visibility: Visibility,
+) -> Result<Card, Error> {
+    let card = request
+        .json(&CreateCardRequest {
+            name: name.into(),
+            emoji: None,
+            category_name: None,
+            content: format!("All about {}.", name),
+            visibility: Some(visibility),
+            rarity: Some(Rarity::Common),
+        })
+        .send()
+        .await
+        .ok()?
+        .json();
+
+    Ok(card)
+}
+
+/// Creates the managed user requests are sent as, with an API key.
+async fn create_client(state: &AppState) -> Result<(i32, String), Error> {
+    let now = Utc::now();
+    let mut tx = state.db.begin().await?;
+
+    let (id,) = sqlx::query_as::<_, (i32,)>(
+        r#"
+        INSERT INTO user (display_name, managed, inserted_at, updated_at)
+        VALUES ('test', TRUE, $1, $1)
+        RETURNING id
+        "#,
+    )
+    .bind(now)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let api_key = generate_key();
+
+    sqlx::query(
+        r#"
+        INSERT INTO api_auth (user_id, hash, inserted_at)
+        VALUES ($1, $2, $3)
+        "#,
+    )
+    .bind(id)
+    .bind(hash_key(&api_key))
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((id, api_key))
+}
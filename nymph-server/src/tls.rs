@@ -0,0 +1,190 @@
+//! TLS termination.
+//!
+//! Small deployments may serve HTTPS directly instead of behind a reverse
+//! proxy; see [`ListenerConfig::tls_cert`]. If a client CA is configured,
+//! clients may also present a certificate, which [`ClientCertAcceptor`]
+//! hands to every request of the connection as a [`ClientCertificate`].
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use anyhow::Error;
+
+use axum_server::{
+    accept::Accept,
+    tls_rustls::{RustlsAcceptor, RustlsConfig},
+};
+
+use futures_util::future::BoxFuture;
+
+use http::Request;
+
+use rustls::{
+    RootCertStore,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject as _},
+    server::{ServerConfig as RustlsServerConfig, WebPkiClientVerifier},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use tower::Service;
+
+use crate::config::ListenerConfig;
+
+/// Loads the TLS config of a listener.
+///
+/// Returns `None` if the listener serves plain HTTP.
+pub fn load(config: &ListenerConfig) -> Result<Option<RustlsConfig>, Error> {
+    let (cert, key) = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => (cert, key),
+        (None, None) if config.tls_client_ca.is_some() => {
+            return Err(Error::msg("`tls_client_ca` requires `tls_cert`"));
+        }
+        (None, None) => return Ok(None),
+        _ => return Err(Error::msg("`tls_cert` and `tls_key` must be set together")),
+    };
+
+    let certs = read_certs(cert)?;
+    let key = PrivateKeyDer::from_pem_file(key)
+        .map_err(|err| Error::msg(format!("failed to read {}: {}", key.display(), err)))?;
+
+    let builder = RustlsServerConfig::builder();
+
+    let builder = match config.tls_client_ca.as_ref() {
+        Some(ca) => {
+            let mut roots = RootCertStore::empty();
+
+            for cert in read_certs(ca)? {
+                roots.add(cert)?;
+            }
+
+            // routes decide for themselves whether a certificate is required
+            let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+                .allow_unauthenticated()
+                .build()?;
+
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut tls = builder.with_single_cert(certs, key)?;
+    tls.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(Some(RustlsConfig::from_config(Arc::new(tls))))
+}
+
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, Error> {
+    let pem = fs::read(path)?;
+    let certs = CertificateDer::pem_slice_iter(&pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| Error::msg(format!("failed to read {}: {}", path.display(), err)))?;
+
+    if certs.is_empty() {
+        return Err(Error::msg(format!(
+            "{} has no certificates",
+            path.display()
+        )));
+    }
+
+    Ok(certs)
+}
+
+/// A verified certificate a client presented.
+#[derive(Clone, Debug)]
+pub struct ClientCertificate {
+    /// The common name of the certificate's subject, if it has one.
+    pub common_name: Option<String>,
+}
+
+impl ClientCertificate {
+    /// Reads a DER encoded certificate.
+    pub fn from_der(der: &[u8]) -> ClientCertificate {
+        let common_name = x509_parser::parse_x509_certificate(der)
+            .ok()
+            .and_then(|(_, cert)| {
+                cert.subject()
+                    .iter_common_name()
+                    .next()
+                    .and_then(|name| name.as_str().ok())
+                    .map(|name| name.to_owned())
+            });
+
+        ClientCertificate { common_name }
+    }
+}
+
+/// Terminates TLS, and passes client certificates on to requests.
+#[derive(Clone, Debug)]
+pub struct ClientCertAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl ClientCertAcceptor {
+    /// Creates a new `ClientCertAcceptor`.
+    pub fn new(config: RustlsConfig) -> ClientCertAcceptor {
+        ClientCertAcceptor {
+            inner: RustlsAcceptor::new(config),
+        }
+    }
+}
+
+impl<I, S> Accept<I, S> for ClientCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = <RustlsAcceptor as Accept<I, S>>::Stream;
+    type Service = CertifiedService<S>;
+    type Future = BoxFuture<'static, io::Result<(Self::Stream, Self::Service)>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let accept = self.inner.accept(stream, service);
+
+        Box::pin(async move {
+            let (stream, inner) = accept.await?;
+
+            // the verifier already checked the chain; only the leaf matters
+            let certificate = stream
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .map(|cert| ClientCertificate::from_der(cert));
+
+            Ok((stream, CertifiedService { inner, certificate }))
+        })
+    }
+}
+
+/// A service that adds the connection's [`ClientCertificate`] to every
+/// request, if the client presented one.
+#[derive(Clone, Debug)]
+pub struct CertifiedService<S> {
+    inner: S,
+    certificate: Option<ClientCertificate>,
+}
+
+impl<S, B> Service<Request<B>> for CertifiedService<S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        if let Some(certificate) = self.certificate.as_ref() {
+            request.extensions_mut().insert(certificate.clone());
+        }
+
+        self.inner.call(request)
+    }
+}
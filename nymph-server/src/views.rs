@@ -0,0 +1,104 @@
+//! Card view counting.
+//!
+//! Views are tallied in memory and written to the database in batches by a
+//! background job, so showing a card never waits on a write.
+
+use std::collections::HashMap;
+use std::mem;
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+
+use sqlx::SqlitePool;
+
+/// Tallies card views until they are flushed.
+///
+/// Cheaply cloneable.
+#[derive(Clone, Debug, Default)]
+pub struct ViewCounter(Arc<Mutex<HashMap<i32, u64>>>);
+
+impl ViewCounter {
+    /// Creates a new, empty `ViewCounter`.
+    pub fn new() -> ViewCounter {
+        ViewCounter::default()
+    }
+
+    /// Counts a view of a card.
+    pub fn record(&self, card_id: i32) {
+        let mut views = self.0.lock().expect("views poisoned");
+
+        *views.entry(card_id).or_default() += 1;
+    }
+
+    /// Takes all views counted since the last call.
+    fn take(&self) -> HashMap<i32, u64> {
+        mem::take(&mut *self.0.lock().expect("views poisoned"))
+    }
+
+    /// Counts views that were taken, but could not be written, again.
+    fn restore(&self, taken: HashMap<i32, u64>) {
+        let mut views = self.0.lock().expect("views poisoned");
+
+        for (card_id, count) in taken {
+            *views.entry(card_id).or_default() += count;
+        }
+    }
+
+    /// Writes all counted views to the database.
+    ///
+    /// Returns how many cards had views written. If the views could not be
+    /// written, they are kept for the next flush.
+    pub async fn flush(&self, db: &SqlitePool) -> Result<usize, sqlx::Error> {
+        let views = self.take();
+
+        if views.is_empty() {
+            return Ok(0);
+        }
+
+        match write(db, &views).await {
+            Ok(()) => Ok(views.len()),
+            Err(err) => {
+                self.restore(views);
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Writes view counts to the database in a single transaction.
+async fn write(db: &SqlitePool, views: &HashMap<i32, u64>) -> Result<(), sqlx::Error> {
+    let day = Utc::now().date_naive();
+    let mut tx = db.begin().await?;
+
+    for (&card_id, &count) in views.iter() {
+        sqlx::query(
+            r#"
+            UPDATE card
+            SET view_count = view_count + $2
+            WHERE id = $1
+            "#,
+        )
+        .bind(card_id)
+        .bind(count as i64)
+        .execute(&mut *tx)
+        .await?;
+
+        // cards deleted since the view are skipped
+        sqlx::query(
+            r#"
+            INSERT INTO card_view (card_id, day, views)
+            SELECT id, $2, $3 FROM card WHERE id = $1
+            ON CONFLICT (card_id, day) DO UPDATE SET views = views + excluded.views
+            "#,
+        )
+        .bind(card_id)
+        .bind(day)
+        .bind(count as i64)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,984 @@
+//! Background jobs.
+
+use std::time::Duration;
+
+use chrono::{TimeDelta, Utc};
+
+use nymph_model::{
+    Id,
+    card::Card,
+    dispatch::{Event, EventEnd, RuleReward, SetCompletion},
+    webhook::{self, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER},
+};
+
+use sqlx::{Executor, FromRow, Sqlite, SqliteConnection, SqlitePool};
+
+use tokio::time::{MissedTickBehavior, interval};
+
+use crate::{
+    app::{AppError, AppState},
+    auth::{AuthenticatedUser, Authentication},
+    config::WorkerConfig,
+    dispatch,
+    routes::{
+        card::{get_card, inventory::add_card},
+        event::get_event,
+        guild::get_announcements,
+        rule::get_rule,
+    },
+};
+
+/// Spawns all background jobs onto the runtime.
+pub fn spawn(state: AppState, config: WorkerConfig) {
+    tokio::spawn(rarity_scores(
+        state.clone(),
+        Duration::from_secs(config.rarity_interval),
+    ));
+    tokio::spawn(events(
+        state.clone(),
+        Duration::from_secs(config.event_interval),
+    ));
+    tokio::spawn(rules(
+        state.clone(),
+        Duration::from_secs(config.rule_interval),
+    ));
+    tokio::spawn(webhooks(
+        state.clone(),
+        Duration::from_secs(config.webhook_interval),
+        config.webhook_max_attempts,
+    ));
+    tokio::spawn(views(
+        state.clone(),
+        Duration::from_secs(config.view_interval),
+    ));
+    tokio::spawn(cleanup(
+        state,
+        Duration::from_secs(config.cleanup_interval),
+        config,
+    ));
+}
+
+/// Periodically recomputes the rarity scores of every card.
+async fn rarity_scores(state: AppState, period: Duration) {
+    let mut interval = interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        match update_rarity_scores(&state.db).await {
+            Ok(()) => tracing::debug!("worker: updated rarity scores"),
+            Err(err) => tracing::error!(?err, "worker: failed to update rarity scores"),
+        }
+    }
+}
+
+/// Periodically ends events that are over.
+async fn events(state: AppState, period: Duration) {
+    let mut interval = interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = end_events(&state).await {
+            tracing::error!(?err, "worker: failed to end events");
+        }
+    }
+}
+
+/// Periodically grants the rewards of completed reward rules.
+async fn rules(state: AppState, period: Duration) {
+    let mut interval = interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        match grant_rewards(&state).await {
+            Ok(0) => (),
+            Ok(granted) => tracing::debug!(granted, "worker: granted rule rewards"),
+            Err(err) => tracing::error!(?err, "worker: failed to grant rule rewards"),
+        }
+    }
+}
+
+/// Periodically sends pending webhook deliveries.
+async fn webhooks(state: AppState, period: Duration, max_attempts: u32) {
+    let client = reqwest::Client::builder()
+        .user_agent(concat!("nymph/", env!("CARGO_PKG_VERSION")))
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()
+        .expect("valid http client");
+
+    let mut interval = interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = deliver_webhooks(&state.db, &client, max_attempts).await {
+            tracing::error!(?err, "worker: failed to deliver webhooks");
+        }
+    }
+}
+
+/// Periodically writes counted card views.
+async fn views(state: AppState, period: Duration) {
+    let mut interval = interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        match state.views.flush(&state.db).await {
+            Ok(0) => (),
+            Ok(cards) => tracing::debug!(cards, "worker: wrote card views"),
+            Err(err) => tracing::error!(?err, "worker: failed to write card views"),
+        }
+    }
+}
+
+/// Periodically removes records that are past their retention period.
+async fn cleanup(state: AppState, period: Duration, config: WorkerConfig) {
+    let mut interval = interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        if config.webhook_delivery_retention > 0 {
+            let res = remove_webhook_deliveries(
+                &state.db,
+                config.webhook_delivery_retention,
+                config.webhook_max_attempts,
+            )
+            .await;
+
+            match res {
+                Ok(0) => (),
+                Ok(removed) => tracing::info!(removed, "worker: removed old webhook deliveries"),
+                Err(err) => tracing::error!(?err, "worker: failed to remove webhook deliveries"),
+            }
+        }
+
+        if config.event_log_retention > 0 {
+            match remove_event_log(&state.db, config.event_log_retention).await {
+                Ok(0) => (),
+                Ok(removed) => tracing::info!(removed, "worker: removed old logged events"),
+                Err(err) => tracing::error!(?err, "worker: failed to remove logged events"),
+            }
+        }
+
+        if config.audit_log_retention > 0 {
+            match remove_audit_log(&state.db, config.audit_log_retention).await {
+                Ok(0) => (),
+                Ok(removed) => tracing::info!(removed, "worker: removed old audit log entries"),
+                Err(err) => tracing::error!(?err, "worker: failed to remove audit log entries"),
+            }
+        }
+
+        if config.view_retention > 0 {
+            match remove_card_views(&state.db, config.view_retention).await {
+                Ok(0) => (),
+                Ok(removed) => tracing::info!(removed, "worker: removed old card views"),
+                Err(err) => tracing::error!(?err, "worker: failed to remove card views"),
+            }
+        }
+
+        if config.departed_guild_retention > 0 {
+            match purge_departed_guilds(&state, config.departed_guild_retention).await {
+                Ok(0) => (),
+                Ok(purged) => tracing::info!(purged, "worker: purged departed guilds"),
+                Err(err) => tracing::error!(?err, "worker: failed to purge departed guilds"),
+            }
+        }
+
+        match remove_expired_views(&state.db).await {
+            Ok(0) => (),
+            Ok(removed) => tracing::debug!(removed, "worker: removed expired views"),
+            Err(err) => tracing::error!(?err, "worker: failed to remove expired views"),
+        }
+    }
+}
+
+/// Removes views that have expired.
+///
+/// Returns how many views were removed.
+pub async fn remove_expired_views<'c, E>(db: E) -> Result<u64, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    sqlx::query(
+        r#"
+        DELETE FROM view_state
+        WHERE datetime(expires_at) <= datetime($1)
+        "#,
+    )
+    .bind(Utc::now())
+    .execute(db)
+    .await
+    .map(|res| res.rows_affected())
+}
+
+/// Removes daily card view counts older than `retention` days.
+///
+/// Returns how many counts were removed.
+pub async fn remove_card_views<'c, E>(db: E, retention: u32) -> Result<u64, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let cutoff = Utc::now().date_naive() - TimeDelta::days(retention.into());
+
+    sqlx::query(
+        r#"
+        DELETE FROM card_view
+        WHERE day < $1
+        "#,
+    )
+    .bind(cutoff)
+    .execute(db)
+    .await
+    .map(|res| res.rows_affected())
+}
+
+/// Removes logged events older than `retention` days.
+///
+/// The newest removed event of each guild is remembered, so replays can tell
+/// whether events were removed. Returns how many events were removed.
+pub async fn remove_event_log(db: &SqlitePool, retention: u32) -> Result<u64, sqlx::Error> {
+    let cutoff = Utc::now() - TimeDelta::days(retention.into());
+    let mut tx = db.begin().await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO event_log_pruned (guild_id, seq)
+        SELECT guild_id, MAX(seq)
+        FROM event_log
+        WHERE datetime(inserted_at) < datetime($1)
+        GROUP BY guild_id
+        ON CONFLICT (guild_id) DO UPDATE SET seq = MAX(seq, excluded.seq)
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?;
+
+    let removed = sqlx::query(
+        r#"
+        DELETE FROM event_log
+        WHERE datetime(inserted_at) < datetime($1)
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    tx.commit().await?;
+
+    Ok(removed)
+}
+
+/// Removes audit log entries older than `retention` days.
+///
+/// The pools of rolls that old go with them, since no logged grant can refer
+/// to them anymore. Returns how many entries were removed.
+pub async fn remove_audit_log(db: &SqlitePool, retention: u32) -> Result<u64, sqlx::Error> {
+    let cutoff = Utc::now() - TimeDelta::days(retention.into());
+    let mut tx = db.begin().await?;
+
+    let removed = sqlx::query(
+        r#"
+        DELETE FROM audit_log
+        WHERE datetime(inserted_at) < datetime($1)
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected();
+
+    sqlx::query(
+        r#"
+        DELETE FROM roll
+        WHERE datetime(inserted_at) < datetime($1)
+        "#,
+    )
+    .bind(cutoff)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(removed)
+}
+
+/// Removes finished webhook deliveries older than `retention` days.
+///
+/// Deliveries that are still being retried are never removed.
+pub async fn remove_webhook_deliveries<'c, E>(
+    db: E,
+    retention: u32,
+    max_attempts: u32,
+) -> Result<u64, sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    let cutoff = Utc::now() - TimeDelta::days(retention.into());
+
+    sqlx::query(
+        r#"
+        DELETE FROM webhook_delivery
+        WHERE
+            datetime(inserted_at) < datetime($1)
+            AND (delivered_at IS NOT NULL OR attempts >= $2)
+        "#,
+    )
+    .bind(cutoff)
+    .bind(max_attempts)
+    .execute(db)
+    .await
+    .map(|res| res.rows_affected())
+}
+
+/// Purges every guild that departed more than `retention` days ago.
+///
+/// Returns how many guilds were purged.
+pub async fn purge_departed_guilds(state: &AppState, retention: u32) -> Result<u64, AppError> {
+    let cutoff = Utc::now() - TimeDelta::days(retention.into());
+
+    let guild_ids = sqlx::query_as::<_, (i64,)>(
+        r#"
+        SELECT guild_id
+        FROM guild_presence
+        WHERE
+            departed_at IS NOT NULL
+            AND datetime(departed_at) < datetime($1)
+        "#,
+    )
+    .bind(cutoff)
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut purged = 0;
+
+    for (guild_id,) in guild_ids {
+        let mut tx = state.db.begin().await?;
+
+        // the guild may have come back since it was listed; claiming its
+        // presence row first keeps it from coming back mid-purge
+        let departed = sqlx::query_as::<_, (i64,)>(
+            r#"
+            DELETE FROM guild_presence
+            WHERE
+                guild_id = $1
+                AND departed_at IS NOT NULL
+                AND datetime(departed_at) < datetime($2)
+            RETURNING guild_id
+            "#,
+        )
+        .bind(guild_id)
+        .bind(cutoff)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        if departed.is_none() {
+            tracing::info!(guild_id, "guild came back before it was purged");
+            continue;
+        }
+
+        let card_ids = purge_guild(&mut tx, guild_id).await?;
+        tx.commit().await?;
+
+        for card_id in card_ids.iter() {
+            state.content.remove(*card_id).await?;
+        }
+
+        tracing::info!(guild_id, cards = card_ids.len(), "purged departed guild");
+        purged += 1;
+    }
+
+    Ok(purged)
+}
+
+/// Statements that delete everything of the guild `$1`, in an order that
+/// keeps every foreign key intact.
+const PURGE_GUILD: &[&str] = &[
+    r#"
+    DELETE FROM trade_item
+    WHERE
+        trade_id IN (SELECT id FROM trade WHERE guild_id = $1)
+        OR card_id IN (SELECT id FROM card WHERE guild_id = $1)
+    "#,
+    "DELETE FROM trade WHERE guild_id = $1",
+    r#"
+    DELETE FROM event_card
+    WHERE
+        event_id IN (SELECT id FROM event WHERE guild_id = $1)
+        OR card_id IN (SELECT id FROM card WHERE guild_id = $1)
+    "#,
+    "DELETE FROM event WHERE guild_id = $1",
+    r#"
+    DELETE FROM card_rule_card
+    WHERE
+        rule_id IN (
+            SELECT id FROM card_rule
+            WHERE guild_id = $1 OR reward_id IN (SELECT id FROM card WHERE guild_id = $1)
+        )
+        OR card_id IN (SELECT id FROM card WHERE guild_id = $1)
+    "#,
+    r#"
+    DELETE FROM card_rule_reward
+    WHERE rule_id IN (
+        SELECT id FROM card_rule
+        WHERE guild_id = $1 OR reward_id IN (SELECT id FROM card WHERE guild_id = $1)
+    )
+    "#,
+    r#"
+    DELETE FROM card_rule
+    WHERE guild_id = $1 OR reward_id IN (SELECT id FROM card WHERE guild_id = $1)
+    "#,
+    r#"
+    DELETE FROM season_ownership
+    WHERE
+        season_id IN (SELECT id FROM season WHERE guild_id = $1)
+        OR card_id IN (SELECT id FROM card WHERE guild_id = $1)
+    "#,
+    "DELETE FROM season WHERE guild_id = $1",
+    r#"
+    DELETE FROM webhook_delivery
+    WHERE webhook_id IN (SELECT id FROM webhook WHERE guild_id = $1)
+    "#,
+    "DELETE FROM webhook WHERE guild_id = $1",
+    "DELETE FROM event_log WHERE guild_id = $1",
+    "DELETE FROM event_log_pruned WHERE guild_id = $1",
+    "DELETE FROM audit_log WHERE guild_id = $1",
+    "DELETE FROM roll WHERE guild_id = $1",
+    r#"
+    DELETE FROM card_report
+    WHERE guild_id = $1 OR card_id IN (SELECT id FROM card WHERE guild_id = $1)
+    "#,
+    r#"
+    DELETE FROM card_prerequisite
+    WHERE
+        card_id IN (SELECT id FROM card WHERE guild_id = $1)
+        OR required_card_id IN (SELECT id FROM card WHERE guild_id = $1)
+    "#,
+    "DELETE FROM ownership WHERE card_id IN (SELECT id FROM card WHERE guild_id = $1)",
+    "DELETE FROM card_grant_policy WHERE card_id IN (SELECT id FROM card WHERE guild_id = $1)",
+    "DELETE FROM favorite WHERE card_id IN (SELECT id FROM card WHERE guild_id = $1)",
+    "DELETE FROM card_reaction WHERE card_id IN (SELECT id FROM card WHERE guild_id = $1)",
+    "DELETE FROM card_view WHERE card_id IN (SELECT id FROM card WHERE guild_id = $1)",
+    "DELETE FROM card_content WHERE card_id IN (SELECT id FROM card WHERE guild_id = $1)",
+    "DELETE FROM drop_table_entry WHERE guild_id = $1",
+    r#"
+    UPDATE card
+    SET previous_id = NULL
+    WHERE previous_id IN (SELECT id FROM card WHERE guild_id = $1)
+    "#,
+    // the search index is cleaned up by a trigger
+    "DELETE FROM card WHERE guild_id = $1",
+    "DELETE FROM guild_lint_rules WHERE guild_id = $1",
+    "DELETE FROM guild_name_rules WHERE guild_id = $1",
+    "DELETE FROM guild_trade_in_rules WHERE guild_id = $1",
+    "DELETE FROM guild_announcements WHERE guild_id = $1",
+    "DELETE FROM wallet WHERE guild_id = $1",
+    "DELETE FROM guild_syndication WHERE guild_id = $1 OR source_guild_id = $1",
+    "DELETE FROM guild_presence WHERE guild_id = $1",
+];
+
+/// Deletes every card, inventory and setting of a guild.
+///
+/// Returns the IDs of the deleted cards, whose content files must be removed
+/// once the transaction commits.
+pub async fn purge_guild(
+    conn: &mut SqliteConnection,
+    guild_id: i64,
+) -> Result<Vec<i32>, sqlx::Error> {
+    let card_ids = sqlx::query_as::<_, (i32,)>(
+        r#"
+        SELECT id
+        FROM card
+        WHERE guild_id = $1
+        "#,
+    )
+    .bind(guild_id)
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|(id,)| id)
+    .collect::<Vec<_>>();
+
+    for statement in PURGE_GUILD {
+        sqlx::query(statement)
+            .bind(guild_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(card_ids)
+}
+
+/// How long a webhook has to respond to a delivery.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many deliveries are sent each time the outbox is checked.
+const WEBHOOK_BATCH: i64 = 100;
+
+/// Sends every webhook delivery that is due.
+///
+/// A delivery succeeds when the webhook responds with a success status.
+/// Failed deliveries are retried with exponential backoff, starting at 30
+/// seconds, until they have been attempted `max_attempts` times.
+pub async fn deliver_webhooks(
+    db: &SqlitePool,
+    client: &reqwest::Client,
+    max_attempts: u32,
+) -> Result<(), sqlx::Error> {
+    #[derive(FromRow)]
+    struct DeliveryResult {
+        id: i32,
+        webhook_id: i32,
+        event: String,
+        payload: String,
+        attempts: i64,
+        url: String,
+        secret: String,
+    }
+
+    let deliveries = sqlx::query_as::<_, DeliveryResult>(
+        r#"
+        SELECT d.id, d.webhook_id, d.event, d.payload, d.attempts, w.url, w.secret
+        FROM
+            webhook_delivery d
+        INNER JOIN
+            webhook AS w
+            ON w.id = d.webhook_id
+        WHERE
+            d.delivered_at IS NULL
+            AND d.attempts < $2
+            AND datetime(d.next_attempt_at) <= datetime($1)
+        ORDER BY d.id
+        LIMIT $3
+        "#,
+    )
+    .bind(Utc::now())
+    .bind(max_attempts)
+    .bind(WEBHOOK_BATCH)
+    .fetch_all(db)
+    .await?;
+
+    for delivery in deliveries {
+        let timestamp = Utc::now().timestamp();
+        let signature = webhook::sign(&delivery.secret, timestamp, delivery.payload.as_bytes());
+
+        let res = client
+            .post(&delivery.url)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(EVENT_HEADER, &delivery.event)
+            .header(DELIVERY_HEADER, delivery.id.to_string())
+            .body(delivery.payload)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status());
+
+        let now = Utc::now();
+        let attempts = delivery.attempts + 1;
+
+        match res {
+            Ok(_) => {
+                sqlx::query(
+                    r#"
+                    UPDATE webhook_delivery
+                    SET delivered_at = $2, attempts = $3, last_error = NULL
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(delivery.id)
+                .bind(now)
+                .bind(attempts)
+                .execute(db)
+                .await?;
+
+                tracing::debug!(
+                    id = delivery.id,
+                    webhook_id = delivery.webhook_id,
+                    event = delivery.event,
+                    "worker: delivered webhook"
+                );
+            }
+            Err(err) => {
+                // 30s, 1m, 2m, 4m, ... capped at a day
+                let backoff =
+                    TimeDelta::seconds(30 << delivery.attempts.min(12)).min(TimeDelta::days(1));
+
+                sqlx::query(
+                    r#"
+                    UPDATE webhook_delivery
+                    SET attempts = $2, next_attempt_at = $3, last_error = $4
+                    WHERE id = $1
+                    "#,
+                )
+                .bind(delivery.id)
+                .bind(attempts)
+                .bind(now + backoff)
+                .bind(err.to_string())
+                .execute(db)
+                .await?;
+
+                if attempts >= max_attempts as i64 {
+                    tracing::warn!(
+                        id = delivery.id,
+                        webhook_id = delivery.webhook_id,
+                        event = delivery.event,
+                        %err,
+                        "worker: giving up on webhook delivery"
+                    );
+                } else {
+                    tracing::debug!(
+                        id = delivery.id,
+                        webhook_id = delivery.webhook_id,
+                        %err,
+                        "worker: webhook delivery failed"
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Ends every event that is over but has not been ended yet.
+///
+/// An event's cards are archived when it ends, and a summary of how many
+/// players took part and how many copies of its cards were granted is written
+/// to the event. Grants are counted from the audit log, so ones older than
+/// `audit_log_retention` are left out.
+///
+/// Every archived card is dispatched as updated, and the summary is dispatched
+/// as the event ending, to be posted in the guild's events channel.
+pub async fn end_events(state: &AppState) -> Result<(), AppError> {
+    let db = &state.db;
+    let now = Utc::now();
+
+    let events = sqlx::query_as::<_, (i32, i64, String)>(
+        r#"
+        SELECT id, guild_id, name
+        FROM event
+        WHERE
+            ended_at IS NULL
+            AND datetime(ends_at) <= datetime($1)
+        "#,
+    )
+    .bind(now)
+    .fetch_all(db)
+    .await?;
+
+    for (id, guild_id, name) in events {
+        let mut tx = db.begin().await?;
+
+        let (participants,) = sqlx::query_as::<_, (i64,)>(
+            r#"
+            SELECT COUNT(DISTINCT o.owner_id)
+            FROM ownership o, event_card ec
+            WHERE
+                ec.card_id = o.card_id
+                AND ec.event_id = $1
+                AND o.quantity > 0
+            "#,
+        )
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // every grant is of a single copy; copies since revoked still count
+        let (cards_granted,) = sqlx::query_as::<_, (i64,)>(
+            r#"
+            SELECT COUNT(*)
+            FROM audit_log a, event e
+            WHERE
+                e.id = $1
+                AND a.guild_id = e.guild_id
+                AND a.event IN ('card.granted', 'reward.granted')
+                AND a.card_id IN (SELECT card_id FROM event_card WHERE event_id = $1)
+                AND datetime(a.inserted_at) >= datetime(e.starts_at)
+                AND datetime(a.inserted_at) <= datetime(e.ends_at)
+            "#,
+        )
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let archived = sqlx::query_as::<_, (i32,)>(
+            r#"
+            UPDATE card
+            SET archived_at = $2, updated_at = $2
+            WHERE
+                archived_at IS NULL
+                AND id IN (SELECT card_id FROM event_card WHERE event_id = $1)
+            RETURNING id
+            "#,
+        )
+        .bind(id)
+        .bind(now)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE event
+            SET
+                ended_at = $2,
+                participants = $3,
+                cards_granted = $4,
+                updated_at = $2
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(now)
+        .bind(participants)
+        .bind(cards_granted)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        tracing::info!(
+            guild_id,
+            event = name,
+            participants,
+            cards_granted,
+            archived = archived.len(),
+            "worker: event ended"
+        );
+
+        // nobody archived the cards, so they are shown as a managed client
+        // that owns none of them sees them
+        let auth = Authentication::from(AuthenticatedUser {
+            id: 0,
+            display_name: String::from("worker"),
+            managed: true,
+        });
+
+        for (card_id,) in archived {
+            let card = get_card(state, card_id, &auth).await?;
+            dispatch::emit_unattributed(state, Event::CardUpdated(card)).await?;
+        }
+
+        let announcements = get_announcements(db, guild_id).await?;
+        let event = get_event(db, guild_id, id).await?;
+
+        dispatch::emit_unattributed(
+            state,
+            Event::EventEnded(EventEnd {
+                event,
+                channel_id: announcements.events_channel_id,
+            }),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Grants the reward of every reward rule to each user that owns its whole
+/// set and was not rewarded by it yet.
+///
+/// Rules whose reward is archived wait until it is back in circulation.
+/// Returns how many rewards were granted.
+pub async fn grant_rewards(state: &AppState) -> Result<u32, AppError> {
+    let completions = sqlx::query_as::<_, (i32, i64, i32)>(
+        r#"
+        SELECT r.id, r.guild_id, o.owner_id
+        FROM
+            card_rule r
+        INNER JOIN
+            card AS reward
+            ON reward.id = r.reward_id
+        INNER JOIN
+            card_rule_card AS rc
+            ON rc.rule_id = r.id
+        INNER JOIN
+            ownership AS o
+            ON o.card_id = rc.card_id AND o.quantity > 0
+        WHERE
+            reward.archived_at IS NULL
+            AND NOT EXISTS (
+                SELECT 1 FROM card_rule_reward w
+                WHERE w.rule_id = r.id AND w.user_id = o.owner_id
+            )
+        GROUP BY r.id, o.owner_id
+        HAVING COUNT(*) = (
+            SELECT COUNT(*) FROM card_rule_card
+            WHERE rule_id = r.id
+        )
+        "#,
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut granted = 0;
+
+    for (rule_id, guild_id, user_id) in completions {
+        let rule = get_rule(&state.db, guild_id, rule_id).await?;
+
+        let mut tx = state.db.begin().await?;
+
+        let rewarded = sqlx::query(
+            r#"
+            INSERT INTO card_rule_reward (rule_id, user_id, inserted_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(rule_id)
+        .bind(user_id)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
+
+        if rewarded.rows_affected() == 0 {
+            continue;
+        }
+
+        let quantity = add_card(&mut *tx, user_id, rule.reward_id).await?;
+
+        tx.commit().await?;
+
+        granted += 1;
+
+        tracing::info!(
+            rule_id,
+            user_id,
+            card_id = rule.reward_id,
+            "worker: granted rule reward"
+        );
+
+        // the event shows the card as the rewarded user sees it
+        let user = sqlx::query_as::<_, AuthenticatedUser>(
+            r#"
+            SELECT id, display_name, managed
+            FROM user
+            WHERE id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&state.db)
+        .await?;
+        let auth = Authentication::from(user);
+
+        let discord_id = sqlx::query_as::<_, (i64,)>(
+            r#"
+            SELECT discord_id
+            FROM discord_auth
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await?
+        .and_then(|(discord_id,)| Id::new(discord_id as u64));
+
+        let card = Card {
+            quantity: Some(quantity),
+            ..get_card(state, rule.reward_id, &auth).await?
+        };
+
+        let announcements = get_announcements(&state.db, guild_id).await?;
+
+        // the user collected the set themselves
+        dispatch::emit(
+            state,
+            user_id,
+            Event::RewardGranted(RuleReward {
+                rule: rule.clone(),
+                user_id,
+                discord_id,
+                card: card.clone(),
+            }),
+        )
+        .await?;
+
+        let Some(channel_id) = announcements.completions_channel_id else {
+            continue;
+        };
+
+        // a set of cards that all share a category completes the category
+        let categories = sqlx::query_as::<_, (Option<String>,)>(
+            r#"
+            SELECT DISTINCT c.category_name
+            FROM card c, card_rule_card rc
+            WHERE
+                c.id = rc.card_id
+                AND rc.rule_id = $1
+            "#,
+        )
+        .bind(rule_id)
+        .fetch_all(&state.db)
+        .await?;
+
+        let category_name = match categories.as_slice() {
+            [(category_name,)] => category_name.clone(),
+            _ => None,
+        };
+
+        dispatch::emit(
+            state,
+            user_id,
+            Event::SetCompleted(SetCompletion {
+                rule,
+                category_name,
+                user_id,
+                discord_id,
+                channel_id,
+                card,
+            }),
+        )
+        .await?;
+    }
+
+    Ok(granted)
+}
+
+/// Recomputes the rarity score of every card.
+///
+/// A card's rarity score is the inverse of the ratio of players in a guild
+/// that own the card; a card owned by every player has a score of `1.0`, and
+/// the score grows as fewer players own the card. Cards without any owners
+/// have no score.
+pub async fn update_rarity_scores<'c, E>(db: E) -> Result<(), sqlx::Error>
+where
+    E: Executor<'c, Database = Sqlite>,
+{
+    sqlx::query(
+        r#"
+        WITH
+            players AS (
+                SELECT c.guild_id, COUNT(DISTINCT o.owner_id) AS n
+                FROM card c, ownership o
+                WHERE o.card_id = c.id AND o.quantity > 0
+                GROUP BY c.guild_id
+            ),
+            owners AS (
+                SELECT o.card_id, COUNT(*) AS n
+                FROM ownership o
+                WHERE o.quantity > 0
+                GROUP BY o.card_id
+            )
+        UPDATE card
+        SET rarity_score = (
+            SELECT CAST(p.n AS REAL) / ow.n
+            FROM players p, owners ow
+            WHERE p.guild_id = card.guild_id AND ow.card_id = card.id
+        )
+        "#,
+    )
+    .execute(db)
+    .await
+    .map(|_| ())
+}
@@ -0,0 +1,76 @@
+use http::{StatusCode, header};
+
+use nymph_model::request::card::UpdateCardRequest;
+
+use nymph_server::test::{GUILD_ID, TestApp};
+
+fn update(name: &str) -> UpdateCardRequest {
+    UpdateCardRequest {
+        name: Some(name.into()),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn sparse_and_expanded_etags_match_updates() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let uri = format!("/v1/guilds/{}/cards/{}", GUILD_ID, app.cards.public.id);
+
+    for (query, name) in [
+        ("?fields=name", "Sparse"),
+        ("?expand=upgrades,downgrade", "Expanded"),
+    ] {
+        let etag = app
+            .get(format!("{}{}", uri, query))
+            .send()
+            .await
+            .assert_status(StatusCode::OK)
+            .headers[header::ETAG]
+            .to_str()?
+            .to_owned();
+
+        app.patch(&uri)
+            .header(header::IF_MATCH, &etag)
+            .json(&update(name))
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+
+        // the card has changed since
+        app.patch(&uri)
+            .header(header::IF_MATCH, &etag)
+            .json(&update("Stale"))
+            .send()
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn etags_match_updates_after_ownership_changes() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let uri = format!("/v1/guilds/{}/cards/{}", GUILD_ID, app.cards.public.id);
+
+    let etag = app
+        .get(&uri)
+        .as_user(app.user_id)
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .headers[header::ETAG]
+        .to_str()?
+        .to_owned();
+
+    app.grant(app.user_id, app.cards.public.id).await?;
+
+    app.patch(&uri)
+        .header(header::IF_MATCH, &etag)
+        .json(&update("Renamed"))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use chrono::{TimeDelta, Utc};
+
+use http::StatusCode;
+
+use nymph_model::{event::Event, request::event::CreateEventRequest};
+
+use nymph_server::{
+    test::{GUILD_ID, TestApp},
+    worker::end_events,
+};
+
+#[tokio::test]
+async fn summaries_count_revoked_grants() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let card_id = app.cards.public.id;
+    let now = Utc::now().naive_utc();
+
+    let event = app
+        .post(format!("/v1/guilds/{}/events", GUILD_ID))
+        .json(&CreateEventRequest {
+            name: "Solstice".into(),
+            starts_at: now - TimeDelta::hours(1),
+            ends_at: now + TimeDelta::hours(1),
+            cards: vec![card_id],
+        })
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .json::<Event>();
+
+    app.grant(app.user_id, card_id).await?;
+    app.grant(app.user_id, card_id).await?;
+
+    app.delete(format!("/v1/users/{}/cards/{}", app.user_id, card_id))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+
+    sqlx::query("UPDATE event SET ends_at = $1 WHERE id = $2")
+        .bind(Utc::now())
+        .bind(event.id)
+        .execute(&app.state.db)
+        .await?;
+
+    end_events(&app.state).await?;
+
+    let event = app
+        .get(format!("/v1/guilds/{}/events/{}", GUILD_ID, event.id))
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .json::<Event>();
+
+    let summary = event.summary.expect("ended event");
+    assert_eq!(summary.participants, 1);
+    assert_eq!(summary.cards_granted, 2);
+
+    // the archived card and the summary are dispatched
+    let events = sqlx::query_as::<_, (String, Option<i32>)>(
+        "SELECT event, card_id FROM audit_log ORDER BY seq DESC LIMIT 2",
+    )
+    .fetch_all(&app.state.db)
+    .await?;
+    assert_eq!(
+        events,
+        [
+            (String::from("event.ended"), None),
+            (String::from("card.updated"), Some(card_id)),
+        ]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use nymph_model::{card::Visibility, response::card::OwnershipMatrix};
+
+use nymph_server::test::{GUILD_ID, TestApp};
+
+#[tokio::test]
+async fn ownership_exports_are_sparse_and_escaped() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let card = app.create_card("=1+1", Visibility::Public).await?;
+
+    app.grant(app.user_id, card.id).await?;
+
+    let matrix = app
+        .get(format!(
+            "/v1/guilds/{}/ownership/export?format=json",
+            GUILD_ID
+        ))
+        .send()
+        .await
+        .ok()?
+        .json::<OwnershipMatrix>();
+    assert_eq!(matrix.cards.len(), 4);
+    assert_eq!(matrix.users.len(), 1);
+    assert_eq!(matrix.ownership.len(), 1);
+    assert_eq!(matrix.ownership[0].card_id, card.id);
+    assert_eq!(matrix.ownership[0].user_id, app.user_id);
+    assert_eq!(matrix.ownership[0].quantity, 1);
+
+    let csv = app
+        .get(format!("/v1/guilds/{}/ownership/export", GUILD_ID))
+        .send()
+        .await
+        .ok()?
+        .body;
+    let csv = String::from_utf8(csv)?;
+    assert!(csv.contains(&format!("{},'=1+1,1", card.id)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn inventory_exports_are_escaped() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let card = app.create_card("@SUM(A1)", Visibility::Public).await?;
+
+    app.grant(app.user_id, card.id).await?;
+
+    let csv = app
+        .get(format!("/v1/users/{}/cards/export", app.user_id))
+        .send()
+        .await
+        .ok()?
+        .body;
+    let csv = String::from_utf8(csv)?;
+    assert!(csv.lines().any(|line| line.starts_with("'@SUM(A1),")));
+
+    Ok(())
+}
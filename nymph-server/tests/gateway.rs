@@ -0,0 +1,63 @@
+use http::StatusCode;
+
+use nymph_model::{
+    dispatch::Event,
+    request::card::{UpdateCardRequest, inventory::ReactionRequest},
+};
+
+use nymph_server::{
+    auth::{AuthenticatedUser, Authentication},
+    test::{GUILD_ID, TestApp},
+};
+
+#[tokio::test]
+async fn subscribers_never_see_privileged_fields() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let card_id = app.cards.public.id;
+
+    // give the card ratings, so every privileged field is filled in
+    app.grant(app.user_id, card_id).await?;
+    app.put(format!(
+        "/v1/users/{}/cards/{}/reaction",
+        app.user_id, card_id
+    ))
+    .as_user(app.user_id)
+    .json(&ReactionRequest { rating: 5 })
+    .send()
+    .await
+    .assert_status(StatusCode::OK);
+
+    let mut dispatches = app.state.gateway.subscribe();
+
+    app.patch(format!("/v1/guilds/{}/cards/{}", GUILD_ID, card_id))
+        .json(&UpdateCardRequest {
+            name: Some("Renamed".into()),
+            ..Default::default()
+        })
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+
+    let dispatch = dispatches.recv().await?;
+    assert!(matches!(dispatch.envelope.event, Event::CardUpdated(_)));
+
+    let managed = dispatch.envelope.event.card().expect("card event");
+    assert!(managed.created_by.is_some());
+    assert!(managed.last_edited_by.is_some());
+    assert!(managed.ratings.is_some());
+
+    let user = Authentication::from(AuthenticatedUser {
+        id: app.user_id,
+        display_name: String::from("user"),
+        managed: false,
+    });
+
+    let envelope = dispatch.visible_to(&user).expect("public card");
+    let card = envelope.event.card().expect("card event");
+    assert_eq!(card.rarity_score, None);
+    assert!(card.created_by.is_none());
+    assert!(card.last_edited_by.is_none());
+    assert!(card.ratings.is_none());
+
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use http::StatusCode;
+
+use nymph_model::{ApiError, ErrorCode, card::Card};
+
+use nymph_server::test::{GUILD_ID, TestApp};
+
+#[tokio::test]
+async fn hidden_card_is_shown_once_owned() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let uri = format!("/v1/guilds/{}/cards/{}", GUILD_ID, app.cards.hidden.id);
+
+    let error = app
+        .get(&uri)
+        .as_user(app.user_id)
+        .send()
+        .await
+        .assert_status(StatusCode::FORBIDDEN)
+        .json::<ApiError>();
+    assert_eq!(error.code, ErrorCode::Hidden);
+
+    app.grant(app.user_id, app.cards.hidden.id).await?;
+
+    let card = app
+        .get(&uri)
+        .as_user(app.user_id)
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .json::<Card>();
+    assert_eq!(card.hidden, Some(false));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn anonymous_requests_are_refused() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+
+    let error = app
+        .get(format!("/v1/guilds/{}/cards", GUILD_ID))
+        .anonymous()
+        .send()
+        .await
+        .assert_status(StatusCode::UNAUTHORIZED)
+        .json::<ApiError>();
+    assert_eq!(error.code, ErrorCode::Unauthenticated);
+
+    Ok(())
+}
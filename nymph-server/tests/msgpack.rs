@@ -0,0 +1,35 @@
+use http::{StatusCode, header};
+
+use nymph_server::{
+    app::MSGPACK,
+    test::{GUILD_ID, TestApp},
+};
+
+#[tokio::test]
+async fn msgpack_is_negotiated_by_quality() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let uri = format!("/v1/guilds/{}/cards/{}", GUILD_ID, app.cards.public.id);
+
+    for (accept, expected) in [
+        ("application/msgpack", MSGPACK),
+        ("application/msgpack, */*", MSGPACK),
+        ("application/msgpack;q=0", "application/json"),
+        (
+            "application/json, application/msgpack;q=0.5",
+            "application/json",
+        ),
+        ("*/*", "application/json"),
+    ] {
+        let res = app
+            .get(&uri)
+            .header(header::ACCEPT, accept)
+            .send()
+            .await
+            .assert_status(StatusCode::OK);
+        let content_type = res.headers[header::CONTENT_TYPE].to_str()?;
+
+        assert!(content_type.starts_with(expected), "{}", accept);
+    }
+
+    Ok(())
+}
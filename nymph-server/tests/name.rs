@@ -0,0 +1,78 @@
+use nymph_model::{
+    card::{Card, Visibility},
+    response::Paginated,
+};
+
+use nymph_server::{
+    routes::card::normalize_names,
+    test::{GUILD_ID, TestApp},
+};
+
+#[tokio::test]
+async fn names_are_normalized_by_unicode_rules() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+
+    let card = app
+        .create_card("  émile \t zola ", Visibility::Public)
+        .await?;
+    assert_eq!(card.name, "ÉMILE ZOLA");
+
+    // the same name typed differently is taken
+    let res = app.create_card("ÉMILE   zola", Visibility::Public).await;
+    assert!(res.is_err());
+
+    let cards = app
+        .get(format!(
+            "/v1/guilds/{}/cards?query=%C3%A9mile%20%20zola",
+            GUILD_ID
+        ))
+        .send()
+        .await
+        .ok()?
+        .json::<Paginated<Card>>();
+    assert_eq!(cards.items.len(), 1);
+    assert_eq!(cards.items[0].id, card.id);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn stored_names_are_renormalized() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let taken = app.create_card("Émile", Visibility::Public).await?;
+
+    // names stored before only had their ASCII uppercased
+    let rename = |id: i32, name: &'static str| {
+        sqlx::query("UPDATE card SET name = $2 WHERE id = $1")
+            .bind(id)
+            .bind(name)
+            .execute(&app.state.db)
+    };
+    rename(app.cards.public.id, "STRAßE  AVENUE").await?;
+    rename(app.cards.hidden.id, "éMILE").await?;
+
+    let normalized = normalize_names(&app.state.db).await?;
+    assert_eq!(normalized.renamed, 1);
+    assert_eq!(
+        normalized.conflicts,
+        vec![(app.cards.hidden.id, "ÉMILE".to_owned())]
+    );
+
+    let names = sqlx::query_as::<_, (i32, String)>("SELECT id, name FROM card")
+        .fetch_all(&app.state.db)
+        .await?;
+    for (id, name) in names {
+        let expected = match id {
+            id if id == app.cards.public.id => "STRASSE AVENUE",
+            id if id == app.cards.hidden.id => "éMILE",
+            id if id == taken.id => "ÉMILE",
+            _ => continue,
+        };
+        assert_eq!(name, expected);
+    }
+
+    // a second pass has nothing left to do
+    assert_eq!(normalize_names(&app.state.db).await?.renamed, 0);
+
+    Ok(())
+}
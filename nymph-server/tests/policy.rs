@@ -0,0 +1,157 @@
+use http::{Method, StatusCode};
+
+use nymph_model::{
+    ApiError, ErrorCode,
+    policy::Access,
+    request::card::inventory::GrantRequest,
+    response::{Paginated, card::CardOwner},
+};
+
+use nymph_server::{
+    auth::policy::{POLICIES, Policy},
+    test::{GUILD_ID, TestApp},
+};
+
+/// Builds a request uri for a policy's path, naming `user_id` as the user.
+///
+/// Cards are the public fixture; any other parameter is `1`.
+fn uri(app: &TestApp, policy: &Policy, user_id: i32) -> String {
+    let path = policy
+        .path
+        .split('/')
+        .map(|segment| match segment {
+            "{guild_id}" => GUILD_ID.to_string(),
+            "{user_id}" => user_id.to_string(),
+            "{card_id}" | "{id}" => app.cards.public.id.to_string(),
+            segment if segment.starts_with('{') => "1".to_owned(),
+            segment => segment.to_owned(),
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+
+    format!("/v1{}", path)
+}
+
+fn method(policy: &Policy) -> Method {
+    policy.method.parse().expect("valid method")
+}
+
+#[tokio::test]
+async fn every_route_refuses_anonymous_callers() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+
+    for policy in POLICIES {
+        let response = app
+            .request(method(policy), uri(&app, policy, app.user_id))
+            .anonymous()
+            .send()
+            .await;
+
+        assert_eq!(
+            response.status,
+            StatusCode::UNAUTHORIZED,
+            "{} {} let an anonymous caller through",
+            policy.method,
+            policy.path
+        );
+        assert_eq!(response.json::<ApiError>().code, ErrorCode::Unauthenticated);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn every_route_refuses_under_scoped_callers() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let other_id = app.create_user(1001).await?;
+
+    for policy in POLICIES {
+        // owner routes are called on someone else's behalf
+        let user_id = match policy.access {
+            Access::Owner => other_id,
+            _ => app.user_id,
+        };
+
+        let response = app
+            .request(method(policy), uri(&app, policy, user_id))
+            .as_user(app.user_id)
+            .send()
+            .await;
+
+        let (caller, code) = match policy.access {
+            Access::Authenticated => {
+                assert_ne!(
+                    response.status,
+                    StatusCode::UNAUTHORIZED,
+                    "{} {} refused an authenticated caller",
+                    policy.method,
+                    policy.path
+                );
+                continue;
+            }
+            Access::Owner => ("wrong-user", ErrorCode::InsufficientPermissions),
+            Access::Managed => ("non-managed", ErrorCode::Forbidden),
+        };
+
+        assert_eq!(
+            response.status,
+            StatusCode::FORBIDDEN,
+            "{} {} let a {} caller through",
+            policy.method,
+            policy.path,
+            caller
+        );
+        assert_eq!(response.json::<ApiError>().code, code);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn users_cannot_revoke_cards() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let other_id = app.create_user(1001).await?;
+    app.grant(other_id, app.cards.public.id).await?;
+
+    let uri = format!("/v1/users/{}/cards/{}", other_id, app.cards.public.id);
+
+    for user_id in [app.user_id, other_id] {
+        app.delete(&uri)
+            .as_user(user_id)
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+
+    app.delete(&uri).send().await.assert_status(StatusCode::OK);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn users_cannot_grant_themselves_cards() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+
+    // the public fixture has no grant policy
+    app.post(format!("/v1/users/{}/cards", app.user_id))
+        .as_user(app.user_id)
+        .json(&GrantRequest {
+            card_id: app.cards.public.id,
+        })
+        .send()
+        .await
+        .assert_status(StatusCode::FORBIDDEN);
+
+    let owners = app
+        .get(format!(
+            "/v1/guilds/{}/cards/{}/owners",
+            GUILD_ID, app.cards.public.id
+        ))
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .json::<Paginated<CardOwner>>();
+    assert!(owners.items.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use chrono::{TimeDelta, Utc};
+
+use nymph_model::{card::NameRules, request::drop_table::UpdateDropTableEntryRequest};
+
+use nymph_server::{
+    test::{GUILD_ID, TestApp},
+    worker::purge_departed_guilds,
+};
+
+#[tokio::test]
+async fn purged_guilds_leave_nothing_behind() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+
+    app.grant(app.user_id, app.cards.public.id).await?;
+
+    app.put(format!("/v1/guilds/{}/name-rules", GUILD_ID))
+        .json(&NameRules {
+            max_length: Some(32),
+            ..Default::default()
+        })
+        .send()
+        .await
+        .ok()?;
+
+    app.put(format!(
+        "/v1/guilds/{}/drop-table/{}",
+        GUILD_ID, app.cards.public.id
+    ))
+    .json(&UpdateDropTableEntryRequest {
+        weight: 1,
+        max_copies: None,
+    })
+    .send()
+    .await
+    .ok()?;
+
+    let departed_at = Utc::now() - TimeDelta::days(2);
+    sqlx::query(
+        r#"
+        INSERT INTO guild_presence (guild_id, name, first_seen_at, last_seen_at, departed_at)
+        VALUES ($1, 'Departed', $2, $2, $2)
+        "#,
+    )
+    .bind(GUILD_ID)
+    .bind(departed_at)
+    .execute(&app.state.db)
+    .await?;
+
+    assert_eq!(purge_departed_guilds(&app.state, 1).await?, 1);
+
+    // every table keyed by guild must have been covered by the purge
+    let tables = sqlx::query_as::<_, (String,)>(
+        r#"
+        SELECT m.name
+        FROM sqlite_master m, pragma_table_info(m.name) c
+        WHERE m.type = 'table' AND c.name = 'guild_id'
+        "#,
+    )
+    .fetch_all(&app.state.db)
+    .await?;
+    assert!(!tables.is_empty());
+
+    for (table,) in tables {
+        let (count,) = sqlx::query_as::<_, (i64,)>(&format!(
+            "SELECT COUNT(*) FROM {} WHERE guild_id = $1",
+            table
+        ))
+        .bind(GUILD_ID)
+        .fetch_one(&app.state.db)
+        .await?;
+
+        assert_eq!(count, 0, "{} kept rows of a purged guild", table);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use http::StatusCode;
+
+use nymph_model::{card::Card, request::card::inventory::ReactionRequest};
+
+use nymph_server::test::{GUILD_ID, TestApp};
+
+#[tokio::test]
+async fn only_owners_ratings_count() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let card_id = app.cards.public.id;
+
+    app.grant(app.user_id, card_id).await?;
+
+    // managed callers see ratings, and the rating is counted right away
+    let card = app
+        .put(format!(
+            "/v1/users/{}/cards/{}/reaction",
+            app.user_id, card_id
+        ))
+        .json(&ReactionRequest { rating: 4 })
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .json::<Card>();
+    let ratings = card.ratings.expect("rated card");
+    assert_eq!(ratings.count, 1);
+    assert_eq!(ratings.average, 4.);
+
+    app.delete(format!("/v1/users/{}/cards/{}", app.user_id, card_id))
+        .send()
+        .await
+        .assert_status(StatusCode::OK);
+
+    let card = app
+        .get(format!("/v1/guilds/{}/cards/{}", GUILD_ID, card_id))
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .json::<Card>();
+    assert!(card.ratings.is_none());
+
+    Ok(())
+}
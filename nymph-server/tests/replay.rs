@@ -0,0 +1,45 @@
+use chrono::{TimeDelta, Utc};
+
+use nymph_model::response::webhook::ReplayResponse;
+
+use nymph_server::{
+    test::{GUILD_ID, TestApp},
+    worker::remove_event_log,
+};
+
+#[tokio::test]
+async fn replays_know_when_events_were_removed() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+
+    app.grant(app.user_id, app.cards.public.id).await?;
+    app.grant(app.user_id, app.cards.public.id).await?;
+
+    let (first,) = sqlx::query_as::<_, (i64,)>("SELECT MAX(seq) FROM event_log")
+        .fetch_one(&app.state.db)
+        .await?;
+
+    // removing every event leaves nothing to tell the gap by
+    sqlx::query("UPDATE event_log SET inserted_at = $1")
+        .bind(Utc::now() - TimeDelta::days(30))
+        .execute(&app.state.db)
+        .await?;
+    assert_eq!(remove_event_log(&app.state.db, 7).await?, first as u64);
+
+    let replay = |after: i64| {
+        app.get(format!(
+            "/v1/guilds/{}/events/replay?after={}",
+            GUILD_ID, after
+        ))
+        .send()
+    };
+
+    let res = replay(0).await.ok()?.json::<ReplayResponse>();
+    assert!(res.events.is_empty());
+    assert!(res.truncated);
+
+    let res = replay(first).await.ok()?.json::<ReplayResponse>();
+    assert!(res.events.is_empty());
+    assert!(!res.truncated);
+
+    Ok(())
+}
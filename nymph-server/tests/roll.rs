@@ -0,0 +1,63 @@
+use nymph_model::{
+    request::card::inventory::TradeInRequest,
+    response::{admin::RollReplayResponse, card::TradeInResponse},
+    trade_in::{TradeInReward, TradeInRules},
+};
+
+use nymph_server::test::{GUILD_ID, TestApp};
+
+#[tokio::test]
+async fn roll_pools_are_logged_once() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let card_id = app.cards.public.id;
+
+    app.put(format!("/v1/guilds/{}/trade-in", GUILD_ID))
+        .json(&TradeInRules {
+            reward: Some(TradeInReward::Roll { copies: 1 }),
+        })
+        .send()
+        .await
+        .ok()?;
+
+    for _ in 0..3 {
+        app.grant(app.user_id, card_id).await?;
+    }
+
+    let res = app
+        .post(format!(
+            "/v1/users/{}/cards/{}/trade-in",
+            app.user_id, card_id
+        ))
+        .json(&TradeInRequest { count: 2 })
+        .send()
+        .await
+        .ok()?
+        .json::<TradeInResponse>();
+    assert_eq!(res.rolled.len(), 2);
+
+    let (pools,) = sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM roll")
+        .fetch_one(&app.state.db)
+        .await?;
+    assert_eq!(pools, 1);
+
+    let grants = sqlx::query_as::<_, (i64, String)>(
+        "SELECT seq, payload FROM audit_log WHERE event = 'card.granted' ORDER BY seq DESC LIMIT 2",
+    )
+    .fetch_all(&app.state.db)
+    .await?;
+    for (seq, payload) in grants {
+        assert!(!payload.contains("pool"), "{}", payload);
+
+        let replay = app
+            .get(format!("/v1/admin/rolls/{}", seq))
+            .send()
+            .await
+            .ok()?
+            .json::<RollReplayResponse>();
+        assert!(replay.verified);
+        assert!(!replay.pool.is_empty());
+        assert_eq!(Some(replay.roll.seed), res.seed);
+    }
+
+    Ok(())
+}
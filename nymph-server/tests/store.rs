@@ -0,0 +1,201 @@
+use std::sync::{Arc, Mutex};
+
+use futures_util::future::BoxFuture;
+
+use http::StatusCode;
+
+use nymph_model::{
+    ApiError, ErrorCode,
+    card::Card,
+    response::{Paginated, card::CardOwner},
+    user::User,
+};
+
+use nymph_server::{
+    store::{InventoryFilter, OwnershipStore, StoreError},
+    test::{GUILD_ID, TestApp},
+};
+
+/// Ownership kept in memory, as `(owner_id, card_id, quantity)`.
+#[derive(Default)]
+struct FakeOwnership {
+    copies: Mutex<Vec<(i32, i32, u32)>>,
+    broken: bool,
+}
+
+impl FakeOwnership {
+    fn check(&self) -> Result<(), StoreError> {
+        if self.broken {
+            Err(StoreError::new(std::io::Error::other("store is down")))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl OwnershipStore for FakeOwnership {
+    fn add(&self, owner_id: i32, card_id: i32) -> BoxFuture<'_, Result<u32, StoreError>> {
+        Box::pin(async move {
+            self.check()?;
+
+            let mut copies = self.copies.lock().unwrap();
+
+            match copies
+                .iter_mut()
+                .find(|(owner, card, _)| *owner == owner_id && *card == card_id)
+            {
+                Some((_, _, quantity)) => {
+                    *quantity += 1;
+                    Ok(*quantity)
+                }
+                None => {
+                    copies.push((owner_id, card_id, 1));
+                    Ok(1)
+                }
+            }
+        })
+    }
+
+    fn remove(
+        &self,
+        owner_id: i32,
+        card_id: i32,
+    ) -> BoxFuture<'_, Result<Option<u32>, StoreError>> {
+        Box::pin(async move {
+            self.check()?;
+
+            let mut copies = self.copies.lock().unwrap();
+
+            Ok(copies
+                .iter_mut()
+                .find(|(owner, card, quantity)| {
+                    *owner == owner_id && *card == card_id && *quantity > 0
+                })
+                .map(|(_, _, quantity)| {
+                    *quantity -= 1;
+                    *quantity
+                }))
+        })
+    }
+
+    fn count_inventory<'a>(
+        &'a self,
+        _filter: &'a InventoryFilter,
+    ) -> BoxFuture<'a, Result<u64, StoreError>> {
+        Box::pin(async move {
+            self.check()?;
+            Ok(0)
+        })
+    }
+
+    fn inventory<'a>(
+        &'a self,
+        _filter: &'a InventoryFilter,
+        _limit: u64,
+        _offset: u64,
+    ) -> BoxFuture<'a, Result<Vec<Card>, StoreError>> {
+        Box::pin(async move {
+            self.check()?;
+            Ok(Vec::new())
+        })
+    }
+
+    fn count_owners(&self, card_id: i32) -> BoxFuture<'_, Result<u64, StoreError>> {
+        Box::pin(async move {
+            self.check()?;
+
+            let copies = self.copies.lock().unwrap();
+
+            Ok(copies
+                .iter()
+                .filter(|(_, card, quantity)| *card == card_id && *quantity > 0)
+                .count() as u64)
+        })
+    }
+
+    fn owners(
+        &self,
+        card_id: i32,
+        limit: u64,
+        offset: u64,
+    ) -> BoxFuture<'_, Result<Vec<CardOwner>, StoreError>> {
+        Box::pin(async move {
+            self.check()?;
+
+            let copies = self.copies.lock().unwrap();
+
+            Ok(copies
+                .iter()
+                .filter(|(_, card, quantity)| *card == card_id && *quantity > 0)
+                .skip(offset as usize)
+                .take(limit as usize)
+                .map(|(owner_id, _, quantity)| CardOwner {
+                    user: User {
+                        id: *owner_id,
+                        display_name: format!("Fake {}", owner_id),
+                    },
+                    discord_id: None,
+                    quantity: *quantity,
+                })
+                .collect())
+        })
+    }
+}
+
+#[tokio::test]
+async fn routes_use_the_ownership_store() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let app = app.clone().with_stores(
+        app.state.cards.clone(),
+        app.state.users.clone(),
+        Arc::new(FakeOwnership::default()),
+    );
+    let card_id = app.cards.public.id;
+
+    app.grant(app.user_id, card_id).await?;
+    app.grant(app.user_id, card_id).await?;
+
+    let owners = app
+        .get(format!("/v1/guilds/{}/cards/{}/owners", GUILD_ID, card_id))
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .json::<Paginated<CardOwner>>();
+    assert_eq!(owners.total_items, 1);
+    assert_eq!(owners.items[0].user.id, app.user_id);
+    assert_eq!(owners.items[0].quantity, 2);
+
+    // nothing reached the database
+    let (owned,) = sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM ownership")
+        .fetch_one(&app.state.db)
+        .await?;
+    assert_eq!(owned, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn store_failures_are_internal_errors() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let app = app.clone().with_stores(
+        app.state.cards.clone(),
+        app.state.users.clone(),
+        Arc::new(FakeOwnership {
+            broken: true,
+            ..Default::default()
+        }),
+    );
+
+    let error = app
+        .get(format!(
+            "/v1/guilds/{}/cards/{}/owners",
+            GUILD_ID, app.cards.public.id
+        ))
+        .send()
+        .await
+        .assert_status(StatusCode::INTERNAL_SERVER_ERROR)
+        .json::<ApiError>();
+    assert_eq!(error.code, ErrorCode::InternalServerError);
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use chrono::{TimeDelta, Utc};
+
+use nymph_model::{
+    Id,
+    request::user::BatchUpdateDiscordUsersRequest,
+    response::user::{StaleDiscordUser, UpdateDiscordUserResponse},
+};
+
+use nymph_server::test::{DISCORD_ID, TestApp};
+
+#[tokio::test]
+async fn failed_lookups_leave_the_stale_queue() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+
+    app.grant(app.user_id, app.cards.public.id).await?;
+
+    sqlx::query("UPDATE discord_auth SET refreshed_at = $1 WHERE user_id = $2")
+        .bind(Utc::now() - TimeDelta::days(2))
+        .bind(app.user_id)
+        .execute(&app.state.db)
+        .await?;
+
+    let stale = app
+        .get("/v1/users/discord/stale")
+        .send()
+        .await
+        .ok()?
+        .json::<Vec<StaleDiscordUser>>();
+    assert_eq!(stale.len(), 1);
+    assert_eq!(stale[0].user.id, app.user_id);
+
+    let updated = app
+        .post("/v1/users/discord/batch")
+        .json(&BatchUpdateDiscordUsersRequest {
+            users: Vec::new(),
+            failed: vec![Id::new(DISCORD_ID).expect("valid id")],
+            generate_tokens: false,
+        })
+        .send()
+        .await
+        .ok()?
+        .json::<Vec<UpdateDiscordUserResponse>>();
+    assert!(updated.is_empty());
+
+    let stale = app
+        .get("/v1/users/discord/stale")
+        .send()
+        .await
+        .ok()?
+        .json::<Vec<StaleDiscordUser>>();
+    assert!(stale.is_empty());
+
+    Ok(())
+}
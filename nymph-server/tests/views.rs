@@ -0,0 +1,42 @@
+use http::{StatusCode, header};
+
+use nymph_server::test::{GUILD_ID, TestApp};
+
+#[tokio::test]
+async fn only_shown_cards_count_as_viewed() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let public = format!("/v1/guilds/{}/cards/{}", GUILD_ID, app.cards.public.id);
+    let hidden = format!("/v1/guilds/{}/cards/{}", GUILD_ID, app.cards.hidden.id);
+
+    let etag = app
+        .get(&public)
+        .as_user(app.user_id)
+        .send()
+        .await
+        .assert_status(StatusCode::OK)
+        .headers[header::ETAG]
+        .to_str()?
+        .to_owned();
+
+    app.get(&public)
+        .as_user(app.user_id)
+        .header(header::IF_NONE_MATCH, &etag)
+        .send()
+        .await
+        .assert_status(StatusCode::NOT_MODIFIED);
+
+    let res = app.get(&hidden).as_user(app.user_id).send().await;
+    assert!(res.status.is_client_error());
+
+    assert_eq!(app.state.views.flush(&app.state.db).await?, 1);
+
+    let views = sqlx::query_as::<_, (i32, i64)>("SELECT id, view_count FROM card ORDER BY id")
+        .fetch_all(&app.state.db)
+        .await?;
+    for (id, view_count) in views {
+        let expected = if id == app.cards.public.id { 1 } else { 0 };
+        assert_eq!(view_count, expected, "views of card {}", id);
+    }
+
+    Ok(())
+}
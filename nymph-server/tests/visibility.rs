@@ -0,0 +1,50 @@
+use http::StatusCode;
+
+use nymph_model::{card::Card, response::Paginated};
+
+use nymph_server::test::{GUILD_ID, TestApp};
+
+#[tokio::test]
+async fn cards_are_listed_by_visibility() -> anyhow::Result<()> {
+    let app = TestApp::new().await?;
+    let cards = &app.cards;
+
+    let list = |visibility: &str| {
+        app.get(format!(
+            "/v1/guilds/{}/cards?visibility={}",
+            GUILD_ID, visibility
+        ))
+    };
+
+    for (visibility, expected) in [
+        ("public", vec![cards.public.id]),
+        ("hidden", vec![cards.hidden.id]),
+        ("private", vec![cards.private.id]),
+        ("hidden,private", vec![cards.hidden.id, cards.private.id]),
+    ] {
+        let mut ids = list(visibility)
+            .send()
+            .await
+            .ok()?
+            .json::<Paginated<Card>>()
+            .items
+            .into_iter()
+            .map(|card| card.id)
+            .collect::<Vec<_>>();
+        ids.sort();
+
+        assert_eq!(ids, expected, "{}", visibility);
+    }
+
+    // users may only ask for public cards
+    list("public").as_user(app.user_id).send().await.ok()?;
+    for visibility in ["hidden", "private"] {
+        list(visibility)
+            .as_user(app.user_id)
+            .send()
+            .await
+            .assert_status(StatusCode::FORBIDDEN);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! Contract tests between the bot's HTTP client and the server.
+//!
+//! Each test boots the server in-process on an in-memory database, then
+//! drives the bot's request builders against it, so a builder and the route
+//! it calls cannot drift apart unnoticed.
+
+use std::net::SocketAddr;
+
+use anyhow::Error;
+
+use chrono::Utc;
+
+use nymph_bot::{config::ApiConfig, http::Client};
+
+use nymph_model::{
+    card::{Card, Rarity, Visibility},
+    request::card::CreateCardRequest,
+};
+
+use nymph_server::{
+    app::{AppState, random_signing_key},
+    auth::api_key::{generate_key, hash_key},
+    config::{RateLimitConfig, ServerConfig},
+    migrate, router,
+};
+
+use serde_json::json;
+
+use tokio::net::TcpListener;
+
+use twilight_model::{
+    id::{Id, marker::GuildMarker},
+    user::User,
+};
+
+/// The guild every test works in.
+pub const GUILD_ID: Id<GuildMarker> = Id::new(1);
+
+/// The secret the bot signs proxy assertions with.
+const PROXY_SECRET: &str = "contract-test-proxy-secret";
+
+/// A server running in the background of a test.
+///
+/// The server stops when the test's runtime shuts down.
+#[derive(Clone, Debug)]
+pub struct TestServer {
+    /// The server's state, for setting up data the bot cannot.
+    pub state: AppState,
+    addr: SocketAddr,
+    api_key: String,
+}
+
+impl TestServer {
+    /// Starts a new server on a fresh database.
+    pub async fn start() -> Result<TestServer, Error> {
+        sqlx::any::install_default_drivers();
+
+        let config = ServerConfig {
+            port: 0,
+            database_url: Some("sqlite::memory:".into()),
+            signing_key: Some(random_signing_key()),
+            proxy_secret: Some(PROXY_SECRET.into()),
+            rate_limit: RateLimitConfig {
+                per_minute: 0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let state = AppState::new(config).await?;
+        migrate::MIGRATOR.run(&state.db).await?;
+
+        let api_key = create_api_key(&state, "nymph-bot").await?;
+
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+
+        tokio::spawn(axum::serve(listener, router::build(state.clone())).into_future());
+
+        Ok(TestServer {
+            state,
+            addr,
+            api_key,
+        })
+    }
+
+    /// The base URL of the server.
+    pub fn endpoint(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Creates a bot client that proxies with signed assertions.
+    pub fn client(&self) -> Client {
+        self.client_with(Some(PROXY_SECRET.into()))
+    }
+
+    /// Creates a bot client that proxies with access tokens.
+    pub fn token_client(&self) -> Client {
+        self.client_with(None)
+    }
+
+    fn client_with(&self, proxy_secret: Option<String>) -> Client {
+        Client::new(&ApiConfig {
+            endpoint: self.endpoint(),
+            key: self.api_key.clone(),
+            token_refresh_retries: 5,
+            proxy_secret,
+            identity: None,
+            error_rate: Default::default(),
+        })
+        .expect("valid client")
+    }
+
+    /// Creates a card in [`GUILD_ID`].
+    ///
+    /// The bot never creates cards, so this goes around its client.
+    pub async fn create_card(
+        &self,
+        name: &str,
+        emoji: Option<&str>,
+        category_name: Option<&str>,
+        visibility: Visibility,
+    ) -> Result<Card, Error> {
+        let body = CreateCardRequest {
+            name: name.into(),
+            emoji: emoji.map(From::from),
+            category_name: category_name.map(From::from),
+            content: format!("All about {}.", name),
+            visibility: Some(visibility),
+            rarity: Some(Rarity::Common),
+        };
+
+        let card = reqwest::Client::new()
+            .post(format!("{}/v1/guilds/{}/cards", self.endpoint(), GUILD_ID))
+            .header("x-api-key", &self.api_key)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(card)
+    }
+
+    /// Makes each duplicate in [`GUILD_ID`] worth `per_copy` currency.
+    pub async fn set_trade_in_currency(&self, per_copy: u32) -> Result<(), Error> {
+        reqwest::Client::new()
+            .put(format!(
+                "{}/v1/guilds/{}/trade-in",
+                self.endpoint(),
+                GUILD_ID
+            ))
+            .header("x-api-key", &self.api_key)
+            .json(&json!({ "reward": { "kind": "currency", "per_copy": per_copy } }))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// Creates a Discord user the bot can proxy for.
+pub fn discord_user(id: u64, name: &str) -> User {
+    serde_json::from_value(json!({
+        "id": id.to_string(),
+        "username": name,
+        "discriminator": "0000",
+        "avatar": null,
+    }))
+    .expect("valid user")
+}
+
+/// Creates a managed user with an API key, like `nymph-server create-api-key`.
+async fn create_api_key(state: &AppState, name: &str) -> Result<String, Error> {
+    let now = Utc::now();
+    let mut tx = state.db.begin().await?;
+
+    let (id,) = sqlx::query_as::<_, (i32,)>(
+        r#"
+        INSERT INTO user (display_name, managed, inserted_at, updated_at)
+        VALUES ($1, TRUE, $2, $2)
+        RETURNING id
+        "#,
+    )
+    .bind(name)
+    .bind(now)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let api_key = generate_key();
+
+    sqlx::query(
+        r#"
+        INSERT INTO api_auth (user_id, hash, inserted_at)
+        VALUES ($1, $2, $3)
+        "#,
+    )
+    .bind(id)
+    .bind(hash_key(&api_key))
+    .bind(now)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(api_key)
+}
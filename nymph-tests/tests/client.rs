@@ -0,0 +1,471 @@
+//! Drives every request builder of the bot's client against the server.
+
+use anyhow::Error;
+
+use chrono::{TimeDelta, Utc};
+
+use nymph_model::{
+    ApiError, ErrorCode,
+    card::{Prerequisites, Visibility},
+    report::ReportStatus,
+    request::telemetry::ReportedGuild,
+};
+
+use nymph_server::{import::ImportFormat, template};
+
+use nymph_tests::{GUILD_ID, TestServer, discord_user};
+
+use twilight_model::id::Id;
+
+/// The error code the server answered a failed request with.
+fn code(err: Error) -> ErrorCode {
+    match err.downcast::<ApiError>() {
+        Ok(err) => err.code,
+        Err(err) => panic!("request did not fail with an api error: {:?}", err),
+    }
+}
+
+#[tokio::test]
+async fn cards() -> Result<(), Error> {
+    let server = TestServer::start().await?;
+    let client = server.client();
+
+    let alpha = server
+        .create_card("Alpha", Some("🅰️"), Some("Letters"), Visibility::Public)
+        .await?;
+    let beta = server
+        .create_card("Beta", None, Some("Letters"), Visibility::Hidden)
+        .await?;
+
+    let card = client.get_card(GUILD_ID, alpha.id).execute().await?;
+    assert_eq!(card.name, "ALPHA");
+
+    let card = client.lookup_card(GUILD_ID, "🅰️").execute().await?;
+    assert_eq!(card.id, alpha.id);
+
+    let cards = client
+        .list_cards(GUILD_ID)
+        .category("Letters")
+        .visibility([Visibility::Public, Visibility::Hidden])
+        .page(1)
+        .count(10)
+        .execute()
+        .await?;
+    assert_eq!(cards.items.len(), 2);
+
+    let cards = client.list_cards(GUILD_ID).find("Beta").execute().await?;
+    assert_eq!(cards.items.len(), 1);
+    assert_eq!(cards.items[0].id, beta.id);
+
+    client
+        .popular_cards(GUILD_ID)
+        .days(7)
+        .count(5)
+        .execute()
+        .await?;
+
+    let err = client.get_card(GUILD_ID, 1000).execute().await.unwrap_err();
+    assert_eq!(code(err), ErrorCode::NotFound);
+
+    let err = client
+        .lookup_card(GUILD_ID, "🅱️")
+        .execute()
+        .await
+        .unwrap_err();
+    assert_eq!(code(err), ErrorCode::NotFound);
+
+    let archived = client
+        .archive_cards(GUILD_ID)
+        .category("Letters")
+        .execute()
+        .await?;
+    assert_eq!(archived.archived, 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn policies() -> Result<(), Error> {
+    let server = TestServer::start().await?;
+    let client = server.client();
+
+    let alpha = server
+        .create_card("Alpha", None, None, Visibility::Public)
+        .await?;
+    let beta = server
+        .create_card("Beta", None, None, Visibility::Public)
+        .await?;
+
+    let policy = client
+        .get_grant_policy(GUILD_ID, alpha.id)
+        .execute()
+        .await?;
+    assert!(policy.users.is_empty());
+    assert!(policy.roles.is_empty());
+
+    let prerequisites = client
+        .get_prerequisites(GUILD_ID, beta.id)
+        .execute()
+        .await?;
+    assert!(prerequisites.is_empty());
+
+    let prerequisites = Prerequisites {
+        cards: vec![alpha.id],
+        categories: Vec::new(),
+    };
+    let prerequisites = client
+        .update_prerequisites(GUILD_ID, beta.id, prerequisites)
+        .execute()
+        .await?;
+    assert_eq!(prerequisites.cards, [alpha.id]);
+
+    let alice = client.get_discord_user(&discord_user(10, "alice")).await?;
+    let err = client
+        .grant_card_to_user(alice.id, beta.id)
+        .execute()
+        .await
+        .unwrap_err();
+    assert_eq!(code(err), ErrorCode::UnmetPrerequisites);
+
+    let err = client
+        .get_grant_policy(GUILD_ID, 1000)
+        .execute()
+        .await
+        .unwrap_err();
+    assert_eq!(code(err), ErrorCode::NotFound);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn inventory() -> Result<(), Error> {
+    let server = TestServer::start().await?;
+    let client = server.client();
+
+    server.set_trade_in_currency(2).await?;
+
+    let alpha = server
+        .create_card("Alpha", None, Some("Letters"), Visibility::Public)
+        .await?;
+
+    let alice = client.get_discord_user(&discord_user(10, "alice")).await?;
+    let bob = client.get_discord_user(&discord_user(11, "bob")).await?;
+
+    for _ in 0..3 {
+        client
+            .grant_card_to_user(alice.id, alpha.id)
+            .execute()
+            .await?;
+    }
+
+    let owners = client
+        .list_card_owners(GUILD_ID, alpha.id)
+        .page(1)
+        .count(10)
+        .execute()
+        .await?;
+    assert_eq!(owners.items.len(), 1);
+
+    let transfer = client
+        .transfer_card(alice.id, alpha.id, bob.id)
+        .execute()
+        .await?;
+    assert_eq!(transfer.from_quantity, 2);
+    assert_eq!(transfer.to_quantity, 1);
+
+    let rules = client.get_trade_in_rules(GUILD_ID).execute().await?;
+    assert!(rules.reward.is_some());
+
+    let trade_in = client.trade_in(alice.id, alpha.id, 1).execute().await?;
+    assert_eq!(trade_in.balance, Some(2));
+
+    let err = client
+        .trade_in(alice.id, alpha.id, 1000)
+        .execute()
+        .await
+        .unwrap_err();
+    assert_eq!(code(err), ErrorCode::InvalidTransfer);
+
+    client
+        .revoke_card_from_user(bob.id, alpha.id)
+        .execute()
+        .await?;
+
+    let inventory = client
+        .list_inventory(alice.id)
+        .guild(GUILD_ID)
+        .page(1)
+        .count(10)
+        .execute()
+        .await?;
+    assert_eq!(inventory.items.len(), 1);
+
+    let progress = client.get_progress(alice.id, GUILD_ID).execute().await?;
+    assert_eq!(progress.owned, 1);
+    assert_eq!(progress.total, 1);
+
+    let export = client
+        .export_inventory(alice.id)
+        .guild(GUILD_ID)
+        .execute()
+        .await?;
+    let export = String::from_utf8(export)?;
+    assert!(export.starts_with("name,"));
+    assert!(export.contains("ALPHA,Letters,"));
+
+    let inventory = client.list_inventory(bob.id).execute().await?;
+    assert!(inventory.items.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn events() -> Result<(), Error> {
+    let server = TestServer::start().await?;
+    let client = server.client();
+
+    let alpha = server
+        .create_card("Alpha", None, None, Visibility::Public)
+        .await?;
+    let alice = client.get_discord_user(&discord_user(10, "alice")).await?;
+
+    client
+        .grant_card_to_user(alice.id, alpha.id)
+        .execute()
+        .await?;
+
+    let log = client
+        .get_audit_log(GUILD_ID)
+        .user(alice.id)
+        .card(alpha.id)
+        .count(10)
+        .execute()
+        .await?;
+    assert!(!log.entries.is_empty());
+
+    let replay = client
+        .replay_events(GUILD_ID)
+        .after(0)
+        .count(10)
+        .execute()
+        .await?;
+    assert!(!replay.events.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn reports() -> Result<(), Error> {
+    let server = TestServer::start().await?;
+    let client = server.client();
+
+    let alpha = server
+        .create_card("Alpha", None, None, Visibility::Public)
+        .await?;
+    let alice = discord_user(10, "alice");
+
+    let report = client
+        .proxy_for(&alice)
+        .create_report(GUILD_ID, alpha.id, "Spelled wrong")
+        .execute()
+        .await?;
+    assert_eq!(report.status, ReportStatus::Open);
+
+    let reports = client
+        .list_reports(GUILD_ID)
+        .status(ReportStatus::Open)
+        .page(1)
+        .count(10)
+        .execute()
+        .await?;
+    assert_eq!(reports.items.len(), 1);
+
+    let report = client
+        .resolve_report(GUILD_ID, report.id, ReportStatus::Resolved)
+        .execute()
+        .await?;
+    assert_eq!(report.status, ReportStatus::Resolved);
+
+    let err = client
+        .resolve_report(GUILD_ID, report.id, ReportStatus::Dismissed)
+        .execute()
+        .await
+        .unwrap_err();
+    assert_eq!(code(err), ErrorCode::ReportClosed);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn templates() -> Result<(), Error> {
+    let server = TestServer::start().await?;
+    let client = server.client();
+
+    let rows = ImportFormat::Csv.parse("name,description\nAlpha,The first\nBeta,The second\n")?;
+    let saved = template::save(&server.state.db, "Greek", None, rows, false).await?;
+
+    let templates = client.list_templates().execute().await?;
+    assert_eq!(templates.len(), 1);
+    assert_eq!(templates[0].id, saved.id);
+
+    let imported = client
+        .instantiate_template(GUILD_ID, saved.id)
+        .dry_run(true)
+        .execute()
+        .await?;
+    assert!(imported.dry_run);
+    assert_eq!(imported.imported, 2);
+
+    let imported = client
+        .instantiate_template(GUILD_ID, saved.id)
+        .execute()
+        .await?;
+    assert_eq!(imported.imported, 2);
+
+    let cards = client.list_cards(GUILD_ID).execute().await?;
+    assert_eq!(cards.items.len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn users() -> Result<(), Error> {
+    let server = TestServer::start().await?;
+    let client = server.client();
+
+    let res = client
+        .update_discord_user(Id::new(10), "alice")
+        .generate_token(true)
+        .execute()
+        .await?;
+    assert_eq!(res.user.display_name, "alice");
+    assert!(res.access_token.is_some());
+
+    let res = client
+        .update_discord_users([(Id::new(10), "alicia".into()), (Id::new(11), "bob".into())])
+        .execute()
+        .await?;
+    assert_eq!(res.len(), 2);
+
+    let users = client
+        .get_discord_users(&[discord_user(11, "bob"), discord_user(12, "carol")])
+        .await?;
+    assert_eq!(users[0].display_name, "bob");
+    assert_eq!(users[1].display_name, "carol");
+
+    client
+        .stale_discord_users()
+        .count(10)
+        .max_age(0)
+        .execute()
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn views() -> Result<(), Error> {
+    let server = TestServer::start().await?;
+    let client = server.client();
+
+    let data = serde_json::json!({ "page": 2, "query": "alpha" });
+
+    let view = client.create_view(data.clone()).ttl(60).execute().await?;
+    assert_eq!(view.data, data);
+
+    let fetched = client.get_view(view.id).execute().await?;
+    assert_eq!(fetched.id, view.id);
+    assert_eq!(fetched.data, data);
+
+    let err = client.get_view(view.id + 1).execute().await.unwrap_err();
+    assert_eq!(code(err), ErrorCode::NotFound);
+
+    sqlx::query("UPDATE view_state SET expires_at = $1 WHERE id = $2")
+        .bind(Utc::now() - TimeDelta::seconds(1))
+        .bind(view.id)
+        .execute(&server.state.db)
+        .await?;
+
+    let err = client.get_view(view.id).execute().await.unwrap_err();
+    assert_eq!(code(err), ErrorCode::NotFound);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn proxying() -> Result<(), Error> {
+    let server = TestServer::start().await?;
+
+    let alpha = server
+        .create_card("Alpha", None, None, Visibility::Public)
+        .await?;
+    let gamma = server
+        .create_card("Gamma", None, None, Visibility::Private)
+        .await?;
+
+    let alice = discord_user(10, "alice");
+    let bob = discord_user(11, "bob");
+
+    // both ways of proxying must be accepted
+    for client in [server.client(), server.token_client()] {
+        let card = client
+            .proxy_for(&alice)
+            .get_card(GUILD_ID, alpha.id)
+            .execute()
+            .await?;
+        assert_eq!(card.id, alpha.id);
+
+        let err = client
+            .proxy_for(&alice)
+            .get_card(GUILD_ID, gamma.id)
+            .execute()
+            .await
+            .unwrap_err();
+        assert_eq!(code(err), ErrorCode::Forbidden);
+
+        let bob = client.get_discord_user(&bob).await?;
+        let err = client
+            .proxy_for(&alice)
+            .list_inventory(bob.id)
+            .execute()
+            .await
+            .unwrap_err();
+        assert_eq!(code(err), ErrorCode::InsufficientPermissions);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn telemetry() -> Result<(), Error> {
+    let server = TestServer::start().await?;
+    let client = server.client();
+
+    let guild = |id: u64, name: &str| ReportedGuild {
+        id: nymph_model::Id::new(id).expect("valid id"),
+        name: name.into(),
+        member_count: Some(10),
+    };
+
+    let res = client
+        .report_guilds([guild(1, "One"), guild(2, "Two")])
+        .execute()
+        .await?;
+    assert_eq!(res.joined.len(), 2);
+    assert!(res.departed.is_empty());
+
+    let res = client.report_guilds([guild(2, "Two")]).execute().await?;
+    assert!(res.joined.is_empty());
+    assert_eq!(res.departed, [nymph_model::Id::new(1).expect("valid id")]);
+
+    let err = client.report_guilds([]).execute().await.unwrap_err();
+    assert_eq!(code(err), ErrorCode::InvalidData);
+
+    let guild = client.depart_guild(Id::new(2)).execute().await?;
+    assert!(guild.departed_at.is_some());
+
+    let err = client.depart_guild(Id::new(3)).execute().await.unwrap_err();
+    assert_eq!(code(err), ErrorCode::NotFound);
+
+    Ok(())
+}